@@ -0,0 +1,226 @@
+//! Projection of match coordinates through a pairwise alignment of the reference and the query.
+
+use std::ops::Range;
+
+use crate::{MatchTable, MatchTableError, Quadrant, StorageIndex};
+
+/// The columns of a pairwise alignment of the reference and the query, given as a CIGAR string.
+///
+/// The alignment is global, i.e. it starts at the first characters of both sequences.
+/// Positions after the end of the alignment have no column.
+/// The operations `M`, `=` and `X` align a reference character to a query character,
+/// `I` inserts query characters, and `D` and `N` delete reference characters.
+/// Each operation occupies one alignment column per character.
+///
+/// # Example
+///
+/// ```rust
+/// use template_switch_error_free_inners::AlignmentProjection;
+///
+/// let alignment = AlignmentProjection::from_cigar("3M2I2D1M").unwrap();
+/// assert_eq!(alignment.column_count(), 8);
+/// assert_eq!(alignment.reference_to_column(3), Some(5));
+/// assert_eq!(alignment.query_to_column(3), Some(3));
+/// assert_eq!(alignment.column_to_reference(3), None);
+/// assert_eq!(alignment.column_to_query(7), Some(5));
+/// assert_eq!(alignment.reference_range_to_columns(2..6), Some(2..8));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlignmentProjection {
+    /// The column of each reference position, in increasing order.
+    reference_columns: Vec<usize>,
+    /// The column of each query position, in increasing order.
+    query_columns: Vec<usize>,
+    column_count: usize,
+}
+
+/// The alignment columns of a match, see [`MatchTable::project_match`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProjectedMatch {
+    /// The columns spanned by the primary kmer.
+    pub primary_columns: Range<usize>,
+    /// The columns spanned by the secondary kmer on its forward strand.
+    ///
+    /// The inner copies these columns from right to left.
+    pub secondary_columns: Range<usize>,
+}
+
+impl AlignmentProjection {
+    /// Parse the alignment from a CIGAR string.
+    ///
+    /// Returns [`MatchTableError::InvalidCigar`] if the string contains an operation other than `M`, `=`, `X`, `I`, `D` and `N`,
+    /// an operation without a length, or a length without an operation.
+    pub fn from_cigar(cigar: &str) -> Result<Self, MatchTableError> {
+        let mut reference_columns = Vec::new();
+        let mut query_columns = Vec::new();
+        let mut column_count = 0;
+        let mut length: Option<usize> = None;
+
+        for (position, character) in cigar.char_indices() {
+            if let Some(digit) = character.to_digit(10) {
+                length = length
+                    .unwrap_or(0)
+                    .checked_mul(10)
+                    .and_then(|length| length.checked_add(digit as usize));
+                if length.is_none() {
+                    return Err(MatchTableError::InvalidCigar { position });
+                }
+                continue;
+            }
+
+            let (consumes_reference, consumes_query) = match character {
+                'M' | '=' | 'X' => (true, true),
+                'I' => (false, true),
+                'D' | 'N' => (true, false),
+                _ => return Err(MatchTableError::InvalidCigar { position }),
+            };
+            let Some(length) = length.take() else {
+                return Err(MatchTableError::InvalidCigar { position });
+            };
+            let columns = column_count..column_count + length;
+            if consumes_reference {
+                reference_columns.extend(columns.clone());
+            }
+            if consumes_query {
+                query_columns.extend(columns);
+            }
+            column_count += length;
+        }
+
+        if length.is_some() {
+            return Err(MatchTableError::InvalidCigar {
+                position: cigar.len(),
+            });
+        }
+        Ok(Self {
+            reference_columns,
+            query_columns,
+            column_count,
+        })
+    }
+
+    /// Returns the number of columns of the alignment.
+    pub fn column_count(&self) -> usize {
+        self.column_count
+    }
+
+    /// Returns the number of reference characters covered by the alignment.
+    pub fn reference_length(&self) -> usize {
+        self.reference_columns.len()
+    }
+
+    /// Returns the number of query characters covered by the alignment.
+    pub fn query_length(&self) -> usize {
+        self.query_columns.len()
+    }
+
+    /// Returns the column of the reference character at `position`, or `None` if it is not covered by the alignment.
+    pub fn reference_to_column(&self, position: usize) -> Option<usize> {
+        self.reference_columns.get(position).copied()
+    }
+
+    /// Returns the column of the query character at `position`, or `None` if it is not covered by the alignment.
+    pub fn query_to_column(&self, position: usize) -> Option<usize> {
+        self.query_columns.get(position).copied()
+    }
+
+    /// Returns the position of the reference character in `column`, or `None` if the column is a gap in the reference.
+    pub fn column_to_reference(&self, column: usize) -> Option<usize> {
+        self.reference_columns.binary_search(&column).ok()
+    }
+
+    /// Returns the position of the query character in `column`, or `None` if the column is a gap in the query.
+    pub fn column_to_query(&self, column: usize) -> Option<usize> {
+        self.query_columns.binary_search(&column).ok()
+    }
+
+    /// Returns the columns from the column of the first to the column of the last character of the reference range,
+    /// including any gaps in between.
+    ///
+    /// Returns `None` if the range is empty or not covered by the alignment.
+    pub fn reference_range_to_columns(&self, range: Range<usize>) -> Option<Range<usize>> {
+        Self::range_to_columns(&self.reference_columns, range)
+    }
+
+    /// Returns the columns from the column of the first to the column of the last character of the query range,
+    /// including any gaps in between.
+    ///
+    /// Returns `None` if the range is empty or not covered by the alignment.
+    pub fn query_range_to_columns(&self, range: Range<usize>) -> Option<Range<usize>> {
+        Self::range_to_columns(&self.query_columns, range)
+    }
+
+    /// Returns the columns of the given range of the reference or the query.
+    fn genome_range_to_columns(
+        &self,
+        is_reference: bool,
+        range: Range<usize>,
+    ) -> Option<Range<usize>> {
+        if is_reference {
+            self.reference_range_to_columns(range)
+        } else {
+            self.query_range_to_columns(range)
+        }
+    }
+
+    fn range_to_columns(columns: &[usize], range: Range<usize>) -> Option<Range<usize>> {
+        if range.is_empty() {
+            return None;
+        }
+        let first = *columns.get(range.start)?;
+        let last = *columns.get(range.end - 1)?;
+        Some(first..last + 1)
+    }
+}
+
+impl<Index: StorageIndex> MatchTable<Index> {
+    /// Project the given match of the given quadrant to the columns of a pairwise alignment of the reference and the query.
+    ///
+    /// The kmers are projected to the columns from their first to their last character, including any gaps in between,
+    /// and the secondary kmer is projected on its forward strand, see [`secondary_forward_end`](Self::secondary_forward_end).
+    /// Returns `None` if a kmer is not covered by the alignment.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{AlignmentProjection, MatchTable, ProjectedMatch, Quadrant};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AGGGGCCCCAA").unwrap();
+    /// let matches = MatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    /// let alignment = AlignmentProjection::from_cigar("5M2D6M").unwrap();
+    ///
+    /// // `GGGG` at 1..5 in the query is the reverse complement of `CCCC` at 7..11 in the reference.
+    /// assert!(matches.has_query_reference_match(1, 2));
+    /// assert_eq!(
+    ///     matches.project_match(&alignment, Quadrant::QueryReference, 1, 2),
+    ///     Some(ProjectedMatch { primary_columns: 1..5, secondary_columns: 7..11 }),
+    /// );
+    /// ```
+    pub fn project_match(
+        &self,
+        alignment: &AlignmentProjection,
+        quadrant: Quadrant,
+        primary_index: usize,
+        secondary_rc_index: usize,
+    ) -> Option<ProjectedMatch> {
+        let secondary_end = self.secondary_forward_end(quadrant, secondary_rc_index);
+        Some(ProjectedMatch {
+            primary_columns: alignment.genome_range_to_columns(
+                quadrant.primary_is_reference(),
+                primary_index..primary_index + self.minimum_length,
+            )?,
+            secondary_columns: alignment.genome_range_to_columns(
+                quadrant.secondary_is_reference(),
+                secondary_end - self.minimum_length..secondary_end,
+            )?,
+        })
+    }
+}
@@ -0,0 +1,149 @@
+//! Matches between every ordered pair of a set of sequences.
+
+use compact_genome::interface::{alphabet::Alphabet, sequence::GenomeSequence};
+
+use crate::{MatchTable, MatchTableBuilder, Quadrant, StorageIndex};
+
+/// A table of the error-free template switch inner entry points between every ordered pair of a set of sequences.
+///
+/// The sequences are concatenated as the contigs of a single reference that is compared against itself,
+/// so only one index is built, and kmers spanning two sequences never match.
+/// A match `(sequence_a, primary_index, sequence_b, secondary_rc_index)` means that the kmer of `sequence_a` at `primary_index`
+/// matches the kmer of the reverse complement of `sequence_b` at `secondary_rc_index`.
+/// Indices are relative to the individual sequences.
+///
+/// The kmer indices of the underlying table are stored as `Index`, see [`StorageIndex`].
+pub struct AllVsAllMatchTable<Index: StorageIndex = u32> {
+    table: MatchTable<Index>,
+}
+
+impl AllVsAllMatchTable {
+    /// Compute the error-free template switch inner entry points of the given minimum length between every ordered pair of sequences.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`MatchTableBuilder::try_build_all_vs_all`] returns an error.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::AllVsAllMatchTable;
+    ///
+    /// let sequences = [
+    ///     VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGA").unwrap(),
+    ///     VectorGenome::from_slice_u8(b"AAAAAA").unwrap(),
+    ///     VectorGenome::from_slice_u8(b"ACCCCAA").unwrap(),
+    /// ];
+    /// let matches = AllVsAllMatchTable::new(
+    ///     &sequences.each_ref().map(|sequence| sequence.as_genome_subsequence()),
+    ///     4,
+    /// );
+    ///
+    /// // The reverse complement of the third sequence is `TTGGGGT`.
+    /// assert!(matches.has_match(0, 1, 2, 2));
+    /// assert_eq!(matches.matches(2, 0).collect::<Vec<_>>(), vec![(1, 1)]);
+    /// assert_eq!(matches.matches(0, 1).count(), 0);
+    /// ```
+    pub fn new<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        sequences: &[&GenomeSubsequence],
+        minimum_length: usize,
+    ) -> Self {
+        MatchTableBuilder::new(minimum_length).build_all_vs_all(sequences)
+    }
+}
+
+impl<Index: StorageIndex> AllVsAllMatchTable<Index> {
+    pub(crate) fn from_table(table: MatchTable<Index>) -> Self {
+        Self { table }
+    }
+
+    /// Returns the number of sequences.
+    pub fn sequence_count(&self) -> usize {
+        self.table.reference_contigs().contig_count()
+    }
+
+    /// Returns the number of kmers in the given sequence, which is zero if it is shorter than the minimum length.
+    pub fn kmer_count(&self, sequence: usize) -> usize {
+        (self.table.reference_contigs().contig_length(sequence) + 1)
+            .saturating_sub(self.table.minimum_length())
+    }
+
+    /// Returns the minimum length of the inners, i.e. the length of the kmers.
+    pub fn minimum_length(&self) -> usize {
+        self.table.minimum_length()
+    }
+
+    /// Returns the underlying table of the concatenation of all sequences against itself.
+    ///
+    /// Its reference consists of the sequences as contigs.
+    pub fn table(&self) -> &MatchTable<Index> {
+        &self.table
+    }
+
+    /// Returns the index of the kmer of `sequence` at `primary_index` in the concatenation of all sequences.
+    fn concatenated_primary_index(&self, sequence: usize, primary_index: usize) -> usize {
+        debug_assert!(primary_index < self.kmer_count(sequence));
+        self.table.reference_contigs().contig_start(sequence) + primary_index
+    }
+
+    /// Returns the index of the kmer of the reverse complement of `sequence` at `secondary_rc_index`
+    /// in the reverse complement of the concatenation of all sequences.
+    fn concatenated_secondary_rc_index(&self, sequence: usize, secondary_rc_index: usize) -> usize {
+        debug_assert!(secondary_rc_index < self.kmer_count(sequence));
+        let contigs = self.table.reference_contigs();
+        let sequence_end = contigs.contig_start(sequence) + contigs.contig_length(sequence);
+        contigs.len() - sequence_end + secondary_rc_index
+    }
+
+    /// Returns `true` if the kmer of `sequence_a` at `primary_index`
+    /// matches the kmer of the reverse complement of `sequence_b` at `secondary_rc_index`.
+    pub fn has_match(
+        &self,
+        sequence_a: usize,
+        primary_index: usize,
+        sequence_b: usize,
+        secondary_rc_index: usize,
+    ) -> bool {
+        self.table.has_reference_reference_match(
+            self.concatenated_primary_index(sequence_a, primary_index),
+            self.concatenated_secondary_rc_index(sequence_b, secondary_rc_index),
+        )
+    }
+
+    /// Returns an iterator over all matches between kmers of `sequence_a` and kmers of the reverse complement of `sequence_b`
+    /// as `(primary_index, secondary_rc_index)` pairs.
+    ///
+    /// The matches are returned in the same order as by [`MatchTable::matches`].
+    pub fn matches(
+        &self,
+        sequence_a: usize,
+        sequence_b: usize,
+    ) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let primary_kmer_count = self.kmer_count(sequence_a);
+        let secondary_kmer_count = self.kmer_count(sequence_b);
+        let primary_offset = self.table.reference_contigs().contig_start(sequence_a);
+        let secondary_offset = if secondary_kmer_count > 0 {
+            self.concatenated_secondary_rc_index(sequence_b, 0)
+        } else {
+            0
+        };
+
+        (0..primary_kmer_count).flat_map(move |primary_index| {
+            self.table
+                .row_matches(Quadrant::ReferenceReference, primary_offset + primary_index)
+                .skip_while(move |&secondary_rc_index| secondary_rc_index < secondary_offset)
+                .take_while(move |&secondary_rc_index| {
+                    secondary_rc_index < secondary_offset + secondary_kmer_count
+                })
+                .map(move |secondary_rc_index| {
+                    (primary_index, secondary_rc_index - secondary_offset)
+                })
+        })
+    }
+}
@@ -0,0 +1,71 @@
+//! Restriction of the match table to a band around the main diagonal.
+
+use std::ops::Range;
+
+/// The band of index pairs for which matches are computed.
+///
+/// A pair `(primary_index, secondary_rc_index)` lies within the band
+/// if `secondary_rc_index - primary_index` lies within `min_offset..=max_offset`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Band {
+    pub min_offset: isize,
+    pub max_offset: isize,
+}
+
+impl Band {
+    /// Returns the band relative to primary indices that start at `primary_offset`.
+    pub fn shifted(&self, primary_offset: usize) -> Self {
+        Self {
+            min_offset: self.min_offset + primary_offset as isize,
+            max_offset: self.max_offset + primary_offset as isize,
+        }
+    }
+
+    /// Returns the number of offsets within the band.
+    pub fn width(&self) -> usize {
+        self.max_offset.abs_diff(self.min_offset) + 1
+    }
+
+    fn offset(primary_index: usize, secondary_rc_index: usize) -> isize {
+        secondary_rc_index as isize - primary_index as isize
+    }
+
+    /// Returns `true` if the given pair lies within the band.
+    pub fn contains(&self, primary_index: usize, secondary_rc_index: usize) -> bool {
+        (self.min_offset..=self.max_offset)
+            .contains(&Self::offset(primary_index, secondary_rc_index))
+    }
+
+    /// Returns the offset of the given pair from the lower end of the band.
+    ///
+    /// The pair must lie within the band.
+    pub fn offset_in_band(&self, primary_index: usize, secondary_rc_index: usize) -> usize {
+        debug_assert!(self.contains(primary_index, secondary_rc_index));
+        (Self::offset(primary_index, secondary_rc_index) - self.min_offset) as usize
+    }
+
+    /// Returns the primary indices below `primary_kmer_count` that lie within the band together with `secondary_rc_index`.
+    pub fn primary_range(
+        &self,
+        secondary_rc_index: usize,
+        primary_kmer_count: usize,
+    ) -> Range<usize> {
+        let secondary_rc_index = secondary_rc_index as isize;
+        let start = (secondary_rc_index - self.max_offset).clamp(0, primary_kmer_count as isize);
+        let end =
+            (secondary_rc_index - self.min_offset + 1).clamp(start, primary_kmer_count as isize);
+        start as usize..end as usize
+    }
+
+    /// Returns the secondary rc indices below `secondary_kmer_count` that lie within the band together with `primary_index`.
+    pub fn secondary_range(
+        &self,
+        primary_index: usize,
+        secondary_kmer_count: usize,
+    ) -> Range<usize> {
+        let primary_index = primary_index as isize;
+        let start = (primary_index + self.min_offset).clamp(0, secondary_kmer_count as isize);
+        let end = (primary_index + self.max_offset + 1).clamp(start, secondary_kmer_count as isize);
+        start as usize..end as usize
+    }
+}
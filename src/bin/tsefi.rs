@@ -0,0 +1,172 @@
+//! Compute the error-free template switch inners of two fasta files and write them to stdout.
+
+use std::{
+    io::{BufWriter, Write},
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use clap::{Parser, ValueEnum};
+use compact_genome::{
+    implementation::alphabets::dna_alphabet::DnaAlphabet, interface::sequence::GenomeSequence,
+};
+use log::{LevelFilter, info};
+use simplelog::{ColorChoice, TermLogger, TerminalMode};
+use template_switch_error_free_inners::{
+    LowComplexityFilter, MatchTableBuilder, ProgressEvent, Quadrant, StorageBackend,
+    io::{
+        export::{write_bedpe, write_json, write_tsv},
+        fasta::read_fasta_sequence,
+    },
+};
+
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// The fasta file containing the reference.
+    reference: PathBuf,
+
+    /// The fasta file containing the query.
+    query: PathBuf,
+
+    /// The minimum length of the inners.
+    #[arg(short = 'k', long)]
+    minimum_length: usize,
+
+    /// Store the matches sparsely, which uses less memory if there are few matches.
+    #[arg(long)]
+    sparse: bool,
+
+    /// Keep only the matches where at least one kmer is a minimizer of a window of this many kmers.
+    ///
+    /// All inners of length at least the minimum length plus the window size minus one are still detected.
+    #[arg(long)]
+    minimizer_window: Option<usize>,
+
+    /// Exclude kmers whose DUST score exceeds this threshold, e.g. homopolymers and simple repeats.
+    #[arg(long)]
+    dust: Option<f64>,
+
+    /// Exclude kmers that occur more than this many times on both strands of the reference or of the query.
+    #[arg(long)]
+    max_kmer_occurrences: Option<usize>,
+
+    /// Only output matches between kmers that occur exactly once in their sequences.
+    #[arg(long)]
+    unique_matches_only: bool,
+
+    /// Skip the index lookups of kmers not in a Bloom filter with this false positive rate.
+    ///
+    /// This speeds up the comparison of diverged sequences without changing the output.
+    #[arg(long)]
+    bloom_filter: Option<f64>,
+
+    /// The format of the output.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Indices)]
+    format: OutputFormat,
+
+    /// The log level.
+    #[arg(long, default_value = "info")]
+    log_level: LevelFilter,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    /// TSV of the raw primary and secondary rc indices of each quadrant.
+    Indices,
+    /// TSV of forward-strand coordinates.
+    Tsv,
+    /// BEDPE records of forward-strand coordinates.
+    Bedpe,
+    /// A JSON report of the parameters, the counts and the matches in forward-strand coordinates.
+    Json,
+}
+
+/// Log the progress of the construction in steps of ten percent per genome.
+fn log_progress() -> impl Fn(ProgressEvent) + Send + Sync {
+    let last_decile = AtomicUsize::new(0);
+    move |event| match event {
+        ProgressEvent::IndexesBuilt => {
+            last_decile.store(0, Ordering::Relaxed);
+            info!("Built kmer indexes");
+        }
+        ProgressEvent::KmersProcessed {
+            genome,
+            processed,
+            total,
+        } => {
+            let decile = processed * 10 / total;
+            if last_decile.fetch_max(decile, Ordering::Relaxed) < decile {
+                info!("Processed {processed}/{total} reverse-complemented {genome} kmers");
+            }
+            if processed == total {
+                last_decile.store(0, Ordering::Relaxed);
+            }
+        }
+        ProgressEvent::Finished => {}
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+    TermLogger::init(
+        cli.log_level,
+        Default::default(),
+        TerminalMode::Stderr,
+        ColorChoice::Auto,
+    )?;
+
+    info!("Loading sequences");
+    let reference = read_fasta_sequence::<DnaAlphabet>(&cli.reference)?;
+    let query = read_fasta_sequence::<DnaAlphabet>(&cli.query)?;
+
+    info!("Computing match table");
+    let storage = if cli.sparse {
+        StorageBackend::Sparse
+    } else {
+        StorageBackend::Dense
+    };
+    let mut builder = MatchTableBuilder::new(cli.minimum_length)
+        .storage(storage)
+        .progress(log_progress());
+    if let Some(window_size) = cli.minimizer_window {
+        builder = builder.minimizer_window(window_size);
+    }
+    if let Some(threshold) = cli.dust {
+        builder = builder.low_complexity_filter(LowComplexityFilter::Dust { threshold });
+    }
+    if let Some(max_occurrences) = cli.max_kmer_occurrences {
+        builder = builder.max_kmer_occurrences(max_occurrences);
+    }
+    if cli.unique_matches_only {
+        builder = builder.unique_matches_only(true);
+    }
+    if let Some(false_positive_rate) = cli.bloom_filter {
+        builder = builder.bloom_filter(false_positive_rate);
+    }
+    let matches = builder.try_build(
+        reference.sequence.as_genome_subsequence(),
+        query.sequence.as_genome_subsequence(),
+    )?;
+
+    info!("Writing matches");
+    let mut output = BufWriter::new(std::io::stdout().lock());
+    let reference_names = [&reference.id];
+    let query_names = [&query.id];
+    match cli.format {
+        OutputFormat::Indices => {
+            writeln!(output, "quadrant\tprimary_index\tsecondary_rc_index")?;
+            for quadrant in Quadrant::ALL {
+                for (primary_index, secondary_rc_index) in matches.matches(quadrant) {
+                    writeln!(output, "{quadrant}\t{primary_index}\t{secondary_rc_index}")?;
+                }
+            }
+        }
+        OutputFormat::Tsv => write_tsv(&matches, &reference_names, &query_names, &mut output)?,
+        OutputFormat::Bedpe => write_bedpe(&matches, &reference_names, &query_names, &mut output)?,
+        OutputFormat::Json => write_json(&matches, &reference_names, &query_names, &mut output)?,
+    }
+    output.flush()?;
+
+    Ok(())
+}
@@ -0,0 +1,603 @@
+//! A versioned binary format for caching match tables.
+//!
+//! The format starts with a header of [`MAGIC`] and [`VERSION`], followed by the parameters of the table,
+//! the layouts of the contigs, the excluded intervals, the fingerprint of the inputs, the summary of the frequent kmers
+//! and the four quadrants in storage order.
+//! All integers are little-endian, and bitvectors are stored as their raw bytes in least-significant-bit-first order.
+
+use std::{
+    io::{Read, Write},
+    ops::Range,
+};
+
+use bitvec::{order::Lsb0, slice::BitSlice, vec::BitVec};
+
+use crate::{
+    ContigLayout, MatchTable, MatchTableError, Quadrant, Quadrants, StorageIndex,
+    band::Band,
+    fingerprint::Fingerprint,
+    storage::{QuadrantStorage, QuadrantStorageBuilder, SparseRows},
+};
+
+/// The first bytes of a binary match table.
+const MAGIC: &[u8; 8] = b"TSEFI\x00MT";
+
+/// The version of the binary format, incremented on every incompatible change.
+///
+/// Version 2 added the computed quadrants, which are all quadrants in version 1.
+/// Version 3 added the excluded intervals, which are empty in earlier versions.
+/// Version 4 added the fingerprint of the inputs, which is missing in earlier versions.
+/// Version 5 added the maximum number of kmer occurrences and the numbers of frequent kmers, which are unset and zero in earlier versions.
+/// Version 6 added whether only unique matches were recorded, which is false in earlier versions.
+/// Version 7 added the maximum length of the inners, which is unset in earlier versions.
+const VERSION: u32 = 7;
+
+const DENSE_TAG: u8 = 0;
+const SPARSE_TAG: u8 = 1;
+const BANDED_TAG: u8 = 2;
+const TRIANGULAR_TAG: u8 = 3;
+const WIDE_SPARSE_TAG: u8 = 4;
+
+impl<Index: StorageIndex> MatchTable<Index> {
+    /// Write this table in a versioned binary format that can be loaded with [`read_binary`](Self::read_binary).
+    ///
+    /// Each quadrant is written in its storage backend, with memory-mapped quadrants written as dense quadrants.
+    /// The writer should be buffered.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTable, Quadrant};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+    /// let matches = MatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    ///
+    /// let mut binary = Vec::new();
+    /// matches.write_binary(&mut binary).unwrap();
+    /// let loaded: MatchTable = MatchTable::read_binary(binary.as_slice()).unwrap();
+    /// assert_eq!(
+    ///     loaded.matches(Quadrant::ReferenceReference).collect::<Vec<_>>(),
+    ///     vec![(1, 2), (7, 8)],
+    /// );
+    /// ```
+    pub fn write_binary(&self, mut writer: impl Write) -> Result<(), MatchTableError> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        for value in [
+            self.minimum_length,
+            self.max_mismatches,
+            self.reference_kmer_count,
+            self.query_kmer_count,
+            self.skipped_reference_kmer_count,
+            self.skipped_query_kmer_count,
+        ] {
+            write_usize(&mut writer, value)?;
+        }
+        match self.band {
+            Some(band) => {
+                writer.write_all(&[1])?;
+                writer.write_all(&(band.min_offset as i64).to_le_bytes())?;
+                writer.write_all(&(band.max_offset as i64).to_le_bytes())?;
+            }
+            None => writer.write_all(&[0])?,
+        }
+        writer.write_all(&[self.quadrants.bits()])?;
+        for contigs in [&self.reference_contigs, &self.query_contigs] {
+            write_usize(&mut writer, contigs.contig_count())?;
+            for contig_id in 0..contigs.contig_count() {
+                write_usize(&mut writer, contigs.contig_length(contig_id))?;
+            }
+        }
+        for intervals in [
+            &self.reference_excluded_intervals,
+            &self.query_excluded_intervals,
+        ] {
+            write_usize(&mut writer, intervals.len())?;
+            for interval in intervals {
+                write_usize(&mut writer, interval.start)?;
+                write_usize(&mut writer, interval.end)?;
+            }
+        }
+        match self.fingerprint {
+            Some(fingerprint) => {
+                writer.write_all(&[1])?;
+                for hash in [
+                    fingerprint.reference,
+                    fingerprint.query,
+                    fingerprint.parameters,
+                ] {
+                    writer.write_all(&hash.to_le_bytes())?;
+                }
+            }
+            None => writer.write_all(&[0])?,
+        }
+        match self.max_kmer_occurrences {
+            Some(max_kmer_occurrences) => {
+                writer.write_all(&[1])?;
+                write_usize(&mut writer, max_kmer_occurrences)?;
+            }
+            None => writer.write_all(&[0])?,
+        }
+        write_usize(&mut writer, self.frequent_reference_kmer_count)?;
+        write_usize(&mut writer, self.frequent_query_kmer_count)?;
+        writer.write_all(&[u8::from(self.unique_matches_only)])?;
+        match self.maximum_length {
+            Some(maximum_length) => {
+                writer.write_all(&[1])?;
+                write_usize(&mut writer, maximum_length)?;
+            }
+            None => writer.write_all(&[0])?,
+        }
+        for quadrant in Quadrant::ALL {
+            write_storage(
+                &mut writer,
+                self.quadrant(quadrant),
+                quadrant.dimensions(self.reference_kmer_count, self.query_kmer_count),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Read a table written by [`write_binary`](Self::write_binary).
+    ///
+    /// Returns [`MatchTableError::UnsupportedBinaryVersion`] if the table was written in a newer version of the format,
+    /// and [`MatchTableError::InvalidBinaryFormat`] if the input is not a valid table.
+    /// The table may be loaded with a different [index type](StorageIndex) than it was written with,
+    /// as long as its sparse quadrants fit into it.
+    /// The reader should be buffered.
+    pub fn read_binary(mut reader: impl Read) -> Result<Self, MatchTableError> {
+        let mut magic = [0; MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(MatchTableError::InvalidBinaryFormat(
+                "the input does not start with the magic bytes of a match table",
+            ));
+        }
+        let mut version = [0; 4];
+        reader.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        if !(1..=VERSION).contains(&version) {
+            return Err(MatchTableError::UnsupportedBinaryVersion {
+                version,
+                supported_version: VERSION,
+            });
+        }
+
+        let minimum_length = read_usize(&mut reader)?;
+        let max_mismatches = read_usize(&mut reader)?;
+        let reference_kmer_count = read_usize(&mut reader)?;
+        let query_kmer_count = read_usize(&mut reader)?;
+        let skipped_reference_kmer_count = read_usize(&mut reader)?;
+        let skipped_query_kmer_count = read_usize(&mut reader)?;
+        let band = match read_u8(&mut reader)? {
+            0 => None,
+            1 => {
+                let min_offset = read_isize(&mut reader)?;
+                let max_offset = read_isize(&mut reader)?;
+                if min_offset > max_offset {
+                    return Err(MatchTableError::InvalidBinaryFormat("the band is empty"));
+                }
+                Some(Band {
+                    min_offset,
+                    max_offset,
+                })
+            }
+            _ => return Err(MatchTableError::InvalidBinaryFormat("invalid band flag")),
+        };
+        let quadrants = if version >= 2 {
+            Quadrants::from_bits(read_u8(&mut reader)?).ok_or(
+                MatchTableError::InvalidBinaryFormat("invalid set of computed quadrants"),
+            )?
+        } else {
+            Quadrants::ALL
+        };
+        let reference_contigs = read_contigs(&mut reader)?;
+        let query_contigs = read_contigs(&mut reader)?;
+        let (reference_excluded_intervals, query_excluded_intervals) = if version >= 3 {
+            (
+                read_intervals(&mut reader, reference_contigs.len())?,
+                read_intervals(&mut reader, query_contigs.len())?,
+            )
+        } else {
+            (Vec::new(), Vec::new())
+        };
+        let fingerprint = if version >= 4 {
+            match read_u8(&mut reader)? {
+                0 => None,
+                1 => Some(Fingerprint {
+                    reference: read_u64(&mut reader)?,
+                    query: read_u64(&mut reader)?,
+                    parameters: read_u64(&mut reader)?,
+                }),
+                _ => {
+                    return Err(MatchTableError::InvalidBinaryFormat(
+                        "invalid fingerprint flag",
+                    ));
+                }
+            }
+        } else {
+            None
+        };
+        let (max_kmer_occurrences, frequent_reference_kmer_count, frequent_query_kmer_count) =
+            if version >= 5 {
+                let max_kmer_occurrences = match read_u8(&mut reader)? {
+                    0 => None,
+                    1 => Some(read_usize(&mut reader)?),
+                    _ => {
+                        return Err(MatchTableError::InvalidBinaryFormat(
+                            "invalid maximum kmer occurrences flag",
+                        ));
+                    }
+                };
+                (
+                    max_kmer_occurrences,
+                    read_usize(&mut reader)?,
+                    read_usize(&mut reader)?,
+                )
+            } else {
+                (None, 0, 0)
+            };
+        let unique_matches_only = if version >= 6 {
+            match read_u8(&mut reader)? {
+                0 => false,
+                1 => true,
+                _ => {
+                    return Err(MatchTableError::InvalidBinaryFormat(
+                        "invalid unique matches flag",
+                    ));
+                }
+            }
+        } else {
+            false
+        };
+        let maximum_length = if version >= 7 {
+            match read_u8(&mut reader)? {
+                0 => None,
+                1 => Some(read_usize(&mut reader)?),
+                _ => {
+                    return Err(MatchTableError::InvalidBinaryFormat(
+                        "invalid maximum length flag",
+                    ));
+                }
+            }
+        } else {
+            None
+        };
+        if maximum_length.is_some_and(|maximum_length| maximum_length < minimum_length) {
+            return Err(MatchTableError::InvalidBinaryFormat(
+                "the maximum length is less than the minimum length",
+            ));
+        }
+
+        let table = Self {
+            reference_reference: read_storage(&mut reader, band)?,
+            reference_query: read_storage(&mut reader, band)?,
+            query_reference: read_storage(&mut reader, band)?,
+            query_query: read_storage(&mut reader, band)?,
+            reference_kmer_count,
+            query_kmer_count,
+            minimum_length,
+            max_mismatches,
+            skipped_reference_kmer_count,
+            skipped_query_kmer_count,
+            max_kmer_occurrences,
+            unique_matches_only,
+            maximum_length,
+            frequent_reference_kmer_count,
+            frequent_query_kmer_count,
+            reference_contigs,
+            query_contigs,
+            band,
+            quadrants,
+            reference_excluded_intervals,
+            query_excluded_intervals,
+            fingerprint,
+        };
+        table.validate_dimensions()?;
+        Ok(table)
+    }
+
+    /// Check that the dimensions of the quadrants match the kmer counts.
+    fn validate_dimensions(&self) -> Result<(), MatchTableError> {
+        let has_kmer_count = |contigs: &ContigLayout, kmer_count: usize| {
+            kmer_count > 0 && contigs.len().checked_sub(self.minimum_length) == Some(kmer_count - 1)
+        };
+        if self.minimum_length == 0
+            || !has_kmer_count(&self.reference_contigs, self.reference_kmer_count)
+            // The query is empty in tables of a sequence against itself.
+            || !(has_kmer_count(&self.query_contigs, self.query_kmer_count)
+                || (self.query_contigs.is_empty() && self.query_kmer_count == 0))
+        {
+            return Err(MatchTableError::InvalidBinaryFormat(
+                "the kmer counts do not match the contigs",
+            ));
+        }
+        for quadrant in Quadrant::ALL {
+            let (primary_kmer_count, secondary_kmer_count) =
+                quadrant.dimensions(self.reference_kmer_count, self.query_kmer_count);
+            if !self
+                .quadrant(quadrant)
+                .has_dimensions(primary_kmer_count, secondary_kmer_count)
+            {
+                return Err(MatchTableError::InvalidBinaryFormat(
+                    "the dimensions of a quadrant do not match the kmer counts",
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_storage<Index: StorageIndex>(
+    writer: &mut impl Write,
+    storage: &QuadrantStorage<Index>,
+    (primary_kmer_count, secondary_kmer_count): (usize, usize),
+) -> std::io::Result<()> {
+    debug_assert!(storage.has_dimensions(primary_kmer_count, secondary_kmer_count));
+    match storage {
+        QuadrantStorage::Dense {
+            bits,
+            secondary_kmer_count,
+            ..
+        } => {
+            writer.write_all(&[DENSE_TAG])?;
+            write_usize(writer, *secondary_kmer_count)?;
+            write_bits(writer, bits)
+        }
+        QuadrantStorage::Sparse(rows) if Index::BITS <= 32 => {
+            writer.write_all(&[SPARSE_TAG])?;
+            write_sparse_rows(writer, rows, |writer, index| {
+                writer.write_all(&(index.into_usize() as u32).to_le_bytes())
+            })
+        }
+        QuadrantStorage::Sparse(rows) => {
+            writer.write_all(&[WIDE_SPARSE_TAG])?;
+            write_sparse_rows(writer, rows, |writer, index| {
+                write_usize(writer, index.into_usize())
+            })
+        }
+        QuadrantStorage::Banded { bits, .. } => {
+            // The band is stored once for the whole table.
+            writer.write_all(&[BANDED_TAG])?;
+            write_bits(writer, bits)
+        }
+        QuadrantStorage::Triangular {
+            bits, kmer_count, ..
+        } => {
+            writer.write_all(&[TRIANGULAR_TAG])?;
+            write_usize(writer, *kmer_count)?;
+            write_bits(writer, bits)
+        }
+        #[cfg(feature = "mmap")]
+        QuadrantStorage::Mapped { map, .. } => {
+            writer.write_all(&[DENSE_TAG])?;
+            write_usize(writer, secondary_kmer_count)?;
+            // The mapped region is padded to whole bytes.
+            write_bits(
+                writer,
+                &BitSlice::<u8, Lsb0>::from_slice(map)[..primary_kmer_count * secondary_kmer_count],
+            )
+        }
+        #[cfg(feature = "roaring")]
+        QuadrantStorage::Roaring { .. } => {
+            // Written like sparse storage, so it can be read without the `roaring` feature.
+            let is_narrow = u32::try_from(primary_kmer_count.max(secondary_kmer_count)).is_ok();
+            writer.write_all(&[if is_narrow {
+                SPARSE_TAG
+            } else {
+                WIDE_SPARSE_TAG
+            }])?;
+            write_usize(writer, primary_kmer_count + 1)?;
+            let mut offset = 0;
+            write_usize(writer, offset)?;
+            for primary_index in 0..primary_kmer_count {
+                offset += storage.row_match_count(primary_index);
+                write_usize(writer, offset)?;
+            }
+            write_usize(writer, offset)?;
+            for (_, secondary_rc_index) in storage.iter() {
+                if is_narrow {
+                    writer.write_all(&(secondary_rc_index as u32).to_le_bytes())?;
+                } else {
+                    write_usize(writer, secondary_rc_index)?;
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+fn read_storage<Index: StorageIndex>(
+    reader: &mut impl Read,
+    band: Option<Band>,
+) -> Result<QuadrantStorage<Index>, MatchTableError> {
+    Ok(match read_u8(reader)? {
+        DENSE_TAG => QuadrantStorageBuilder::Dense {
+            secondary_kmer_count: read_usize(reader)?,
+            bits: read_bits(reader)?,
+        }
+        .build(),
+        // Both widths are read into the index type of the table, so tables can be loaded with either index type.
+        SPARSE_TAG => QuadrantStorage::Sparse(read_sparse_rows(reader, |reader| {
+            let mut bytes = [0; 4];
+            reader.read_exact(&mut bytes)?;
+            read_index(u32::from_le_bytes(bytes) as usize)
+        })?),
+        WIDE_SPARSE_TAG => QuadrantStorage::Sparse(read_sparse_rows(reader, |reader| {
+            read_index(read_usize(reader)?)
+        })?),
+        BANDED_TAG => QuadrantStorageBuilder::Banded {
+            band: band.ok_or(MatchTableError::InvalidBinaryFormat(
+                "a banded quadrant requires a band",
+            ))?,
+            bits: read_bits(reader)?,
+        }
+        .build(),
+        TRIANGULAR_TAG => {
+            let kmer_count = read_usize(reader)?;
+            let bits = read_bits(reader)?;
+            // Building the triangular storage reads the bits of each row.
+            if kmer_count
+                .checked_add(1)
+                .and_then(|n| n.checked_mul(kmer_count))
+                .is_none_or(|n| bits.len() != n / 2)
+            {
+                return Err(MatchTableError::InvalidBinaryFormat(
+                    "the length of a triangular quadrant does not match its kmer count",
+                ));
+            }
+            QuadrantStorageBuilder::Triangular { bits, kmer_count }.build()
+        }
+        _ => {
+            return Err(MatchTableError::InvalidBinaryFormat(
+                "unknown storage backend",
+            ));
+        }
+    })
+}
+
+fn write_sparse_rows<W: Write, Index: StorageIndex>(
+    writer: &mut W,
+    rows: &SparseRows<Index>,
+    write_index: impl Fn(&mut W, Index) -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    write_usize(writer, rows.row_offsets().len())?;
+    for &offset in rows.row_offsets() {
+        write_usize(writer, offset)?;
+    }
+    write_usize(writer, rows.secondary_indices().len())?;
+    for &index in rows.secondary_indices() {
+        write_index(writer, index)?;
+    }
+    Ok(())
+}
+
+fn read_sparse_rows<R: Read, Index: StorageIndex>(
+    reader: &mut R,
+    read_index: impl Fn(&mut R) -> Result<Index, MatchTableError>,
+) -> Result<SparseRows<Index>, MatchTableError> {
+    let row_offsets = (0..read_usize(reader)?)
+        .map(|_| read_usize(reader))
+        .collect::<Result<Vec<_>, _>>()?;
+    let secondary_indices = (0..read_usize(reader)?)
+        .map(|_| read_index(reader))
+        .collect::<Result<Vec<_>, _>>()?;
+    SparseRows::from_parts(row_offsets, secondary_indices).ok_or(
+        MatchTableError::InvalidBinaryFormat("the rows of a sparse quadrant are inconsistent"),
+    )
+}
+
+fn read_index<Index: StorageIndex>(index: usize) -> Result<Index, MatchTableError> {
+    Index::try_from_usize(index).ok_or(MatchTableError::InvalidBinaryFormat(
+        "a sparse quadrant has an index that does not fit into the index type of the table",
+    ))
+}
+
+fn write_bits(
+    writer: &mut impl Write,
+    bits: &BitSlice<impl bitvec::store::BitStore, Lsb0>,
+) -> std::io::Result<()> {
+    write_usize(writer, bits.len())?;
+    let mut bytes = vec![0u8; bits.len().div_ceil(8)];
+    BitSlice::<u8, Lsb0>::from_slice_mut(&mut bytes)[..bits.len()].clone_from_bitslice(bits);
+    writer.write_all(&bytes)
+}
+
+fn read_bits(reader: &mut impl Read) -> Result<BitVec, MatchTableError> {
+    let bit_count = read_usize(reader)?;
+    if bit_count > BitSlice::<usize, Lsb0>::MAX_BITS {
+        return Err(MatchTableError::InvalidBinaryFormat(
+            "a quadrant has more bits than can be addressed on this platform",
+        ));
+    }
+    let mut bytes = Vec::new();
+    reader
+        .take(bit_count.div_ceil(8) as u64)
+        .read_to_end(&mut bytes)?;
+    if bytes.len() != bit_count.div_ceil(8) {
+        return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+    }
+
+    let mut bits = BitVec::repeat(false, bit_count);
+    bits.clone_from_bitslice(&BitSlice::<u8, Lsb0>::from_slice(&bytes)[..bit_count]);
+    Ok(bits)
+}
+
+fn read_contigs(reader: &mut impl Read) -> Result<ContigLayout, MatchTableError> {
+    let contig_count = read_usize(reader)?;
+    let lengths = (0..contig_count)
+        .map(|_| read_usize(reader))
+        .collect::<Result<Vec<_>, _>>()?;
+    if lengths
+        .iter()
+        .try_fold(0usize, |total, &length| total.checked_add(length))
+        .is_none()
+    {
+        return Err(MatchTableError::InvalidBinaryFormat(
+            "the total length of the contigs does not fit into the address space",
+        ));
+    }
+    Ok(ContigLayout::new(lengths))
+}
+
+/// Read sorted, disjoint and nonempty intervals within a sequence of the given length.
+fn read_intervals(
+    reader: &mut impl Read,
+    sequence_length: usize,
+) -> Result<Vec<Range<usize>>, MatchTableError> {
+    let interval_count = read_usize(reader)?;
+    let mut intervals: Vec<Range<usize>> = Vec::new();
+    for _ in 0..interval_count {
+        let interval = read_usize(reader)?..read_usize(reader)?;
+        if interval.is_empty()
+            || interval.end > sequence_length
+            || intervals
+                .last()
+                .is_some_and(|last| last.end >= interval.start)
+        {
+            return Err(MatchTableError::InvalidBinaryFormat(
+                "the excluded intervals are not sorted, disjoint and within the sequence",
+            ));
+        }
+        intervals.push(interval);
+    }
+    Ok(intervals)
+}
+
+fn write_usize(writer: &mut impl Write, value: usize) -> std::io::Result<()> {
+    writer.write_all(&(value as u64).to_le_bytes())
+}
+
+fn read_u8(reader: &mut impl Read) -> Result<u8, MatchTableError> {
+    let mut bytes = [0; 1];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes[0])
+}
+
+fn read_u64(reader: &mut impl Read) -> Result<u64, MatchTableError> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_usize(reader: &mut impl Read) -> Result<usize, MatchTableError> {
+    usize::try_from(read_u64(reader)?).map_err(|_| {
+        MatchTableError::InvalidBinaryFormat("an integer does not fit into the address space")
+    })
+}
+
+fn read_isize(reader: &mut impl Read) -> Result<isize, MatchTableError> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    isize::try_from(i64::from_le_bytes(bytes)).map_err(|_| {
+        MatchTableError::InvalidBinaryFormat("an integer does not fit into the address space")
+    })
+}
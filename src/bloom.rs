@@ -0,0 +1,69 @@
+//! A Bloom filter of kmers to skip index lookups of kmers that cannot match.
+
+use bitvec::vec::BitVec;
+
+use crate::minimizer::kmer_hash;
+
+/// A Bloom filter of the kmers of a text.
+///
+/// The bit positions of a kmer are derived from a single hash by double hashing.
+pub(crate) struct BloomFilter {
+    bits: BitVec,
+    hash_count: u32,
+}
+
+impl BloomFilter {
+    /// Create a filter of the non-excluded kmers of `text`,
+    /// sized such that the probability of a false positive is at most `false_positive_rate`.
+    pub fn new(
+        text: &[u8],
+        excluded: &BitVec,
+        kmer_length: usize,
+        false_positive_rate: f64,
+    ) -> Self {
+        let item_count = excluded.count_zeros();
+        let (bit_count, hash_count) = Self::dimensions(item_count, false_positive_rate);
+        let mut filter = Self {
+            bits: BitVec::repeat(false, bit_count),
+            hash_count,
+        };
+        for kmer_index in excluded.iter_zeros() {
+            filter.insert(&text[kmer_index..kmer_index + kmer_length]);
+        }
+        filter
+    }
+
+    /// Returns the number of bits and the number of hash functions of a filter of `item_count` kmers.
+    fn dimensions(item_count: usize, false_positive_rate: f64) -> (usize, u32) {
+        let item_count = item_count.max(1) as f64;
+        let bits_per_item = -false_positive_rate.ln() / std::f64::consts::LN_2.powi(2);
+        let bit_count = (item_count * bits_per_item).ceil().max(64.0) as usize;
+        let hash_count = (bits_per_item * std::f64::consts::LN_2).round().max(1.0) as u32;
+        (bit_count, hash_count)
+    }
+
+    /// Returns the number of bytes of a filter of `item_count` kmers.
+    pub fn estimated_bytes(item_count: usize, false_positive_rate: f64) -> u64 {
+        let (bit_count, _) = Self::dimensions(item_count, false_positive_rate);
+        (bit_count as u64).div_ceil(8)
+    }
+
+    fn positions(&self, kmer: &[u8]) -> impl Iterator<Item = usize> + use<> {
+        let hash = kmer_hash(kmer);
+        let step = hash.rotate_left(32) | 1;
+        let bit_count = self.bits.len() as u64;
+        (0..u64::from(self.hash_count))
+            .map(move |index| (hash.wrapping_add(index.wrapping_mul(step)) % bit_count) as usize)
+    }
+
+    fn insert(&mut self, kmer: &[u8]) {
+        for position in self.positions(kmer) {
+            self.bits.set(position, true);
+        }
+    }
+
+    /// Returns `false` if the kmer is certainly not in the filter.
+    pub fn contains(&self, kmer: &[u8]) -> bool {
+        self.positions(kmer).all(|position| self.bits[position])
+    }
+}
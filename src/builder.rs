@@ -0,0 +1,1518 @@
+//! A builder for match tables with configurable options.
+
+use std::{
+    marker::PhantomData,
+    ops::Range,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use bitvec::vec::BitVec;
+use compact_genome::{
+    implementation::vec_sequence::VectorGenome,
+    interface::{alphabet::Alphabet, sequence::GenomeSequence},
+};
+use log::debug;
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::stream::MatchStream;
+use crate::{
+    AllVsAllMatchTable, AmbiguityPolicy, ConstructionStrategy, ContigLayout, IndexBackend,
+    LazyMatchTable, LowComplexityFilter, MatchOrientation, MatchTable, MatchTableError,
+    MultiKMatchTable, ProgressEvent, ProgressReporter, Quadrant, Quadrants, StorageBackend,
+    StorageIndex, band::Band, construction::Texts, progress::ProgressHandle,
+    storage::QuadrantStorageBuilder,
+};
+
+/// Configures and constructs a [`MatchTable`].
+///
+/// The builder constructs tables with kmer indices stored as `Index`, which is `u32` by default,
+/// see [`index_type`](Self::index_type).
+///
+/// # Example
+///
+/// ```rust
+/// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+/// use compact_genome::implementation::vec_sequence::VectorGenome;
+/// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+/// use template_switch_error_free_inners::{IndexBackend, MatchTableBuilder, StorageBackend};
+///
+/// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+/// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+/// let matches = MatchTableBuilder::new(4)
+///     .storage(StorageBackend::Sparse)
+///     .index_backend(IndexBackend::FmIndex)
+///     .build(reference.as_genome_subsequence(), query.as_genome_subsequence());
+///
+/// assert!(matches.has_reference_reference_match(1, 2));
+/// assert!(matches.has_reference_reference_match(7, 8));
+/// ```
+#[derive(Debug, Clone)]
+pub struct MatchTableBuilder<Index: StorageIndex = u32> {
+    pub(crate) minimum_length: usize,
+    pub(crate) max_mismatches: usize,
+    pub(crate) storage: StorageBackend,
+    pub(crate) index_backend: IndexBackend,
+    pub(crate) strategy: ConstructionStrategy,
+    pub(crate) bloom_false_positive_rate: Option<f64>,
+    pub(crate) parallel: bool,
+    pub(crate) ambiguity_policy: AmbiguityPolicy,
+    pub(crate) skip_n: bool,
+    pub(crate) low_complexity_filter: Option<LowComplexityFilter>,
+    pub(crate) max_kmer_occurrences: Option<usize>,
+    pub(crate) unique_matches_only: bool,
+    pub(crate) maximum_length: Option<usize>,
+    pub(crate) reference_mask: Option<BitVec>,
+    pub(crate) query_mask: Option<BitVec>,
+    pub(crate) reference_excluded_intervals: Vec<Range<usize>>,
+    pub(crate) query_excluded_intervals: Vec<Range<usize>>,
+    pub(crate) band: Option<Band>,
+    pub(crate) minimizer_window: Option<usize>,
+    pub(crate) orientation: MatchOrientation,
+    pub(crate) quadrants: Quadrants,
+    pub(crate) progress: Option<ProgressHandle>,
+    pub(crate) cancellation: Option<Arc<AtomicBool>>,
+    pub(crate) index_type: PhantomData<Index>,
+}
+
+impl MatchTableBuilder {
+    /// Create a builder for match tables of inners with the given minimum length.
+    ///
+    /// All other options are set to their defaults:
+    /// no mismatches, [`StorageBackend::Dense`], [`IndexBackend::SuffixTable`], [`ConstructionStrategy::Automatic`],
+    /// no Bloom filter, parallel construction if the `parallel` feature is enabled, [`AmbiguityPolicy::Literal`],
+    /// no skipping of kmers containing `N`, no low-complexity filter, no maximum number of kmer occurrences, not only unique matches, no maximum length, no masks, no band, no minimizer sparsification, [`MatchOrientation::ReverseComplement`],
+    /// all four quadrants, no progress reporter, and no cancellation flag.
+    pub fn new(minimum_length: usize) -> Self {
+        Self {
+            minimum_length,
+            max_mismatches: 0,
+            storage: StorageBackend::default(),
+            index_backend: IndexBackend::default(),
+            strategy: ConstructionStrategy::default(),
+            bloom_false_positive_rate: None,
+            parallel: cfg!(feature = "parallel"),
+            ambiguity_policy: AmbiguityPolicy::default(),
+            skip_n: false,
+            low_complexity_filter: None,
+            max_kmer_occurrences: None,
+            unique_matches_only: false,
+            maximum_length: None,
+            reference_mask: None,
+            query_mask: None,
+            reference_excluded_intervals: Vec::new(),
+            query_excluded_intervals: Vec::new(),
+            band: None,
+            minimizer_window: None,
+            orientation: MatchOrientation::default(),
+            quadrants: Quadrants::ALL,
+            progress: None,
+            cancellation: None,
+            index_type: PhantomData,
+        }
+    }
+}
+
+impl<Index: StorageIndex> MatchTableBuilder<Index> {
+    /// Set the minimum length of the inners, i.e. the length of the kmers.
+    pub fn minimum_length(mut self, minimum_length: usize) -> Self {
+        self.minimum_length = minimum_length;
+        self
+    }
+
+    /// Set the maximum number of mismatches between two matching kmers.
+    ///
+    /// See [`MatchTable::new_with_mismatches`] for details.
+    pub fn max_mismatches(mut self, max_mismatches: usize) -> Self {
+        self.max_mismatches = max_mismatches;
+        self
+    }
+
+    /// Set the storage backend of the quadrants.
+    pub fn storage(mut self, storage: StorageBackend) -> Self {
+        self.storage = storage;
+        self
+    }
+
+    /// Set the index used to find the occurrences of kmers if the strategy is [`ConstructionStrategy::IndexLookup`].
+    pub fn index_backend(mut self, index_backend: IndexBackend) -> Self {
+        self.index_backend = index_backend;
+        self
+    }
+
+    /// Set the strategy used to find the matching kmers.
+    pub fn strategy(mut self, strategy: ConstructionStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Consult a Bloom filter of the kmers of the primary sequence before looking up a kmer in its index.
+    ///
+    /// Kmers that do not occur in the primary sequence are skipped without querying the index,
+    /// which saves most index lookups when comparing diverged sequences.
+    /// The filter is sized such that at most a fraction `false_positive_rate` of these kmers is looked up anyway,
+    /// taking about `1.44 * log2(1 / false_positive_rate)` bits per kmer.
+    /// The table is the same with and without the filter.
+    ///
+    /// The filter is only used for exact matching, i.e. it is ignored if mismatches are allowed,
+    /// and if the kmers within the band are compared directly.
+    /// The false positive rate must lie strictly between zero and one.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::MatchTableBuilder;
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+    /// let matches = MatchTableBuilder::new(4)
+    ///     .bloom_filter(0.01)
+    ///     .build(reference.as_genome_subsequence(), query.as_genome_subsequence());
+    ///
+    /// assert!(matches.has_reference_reference_match(1, 2));
+    /// assert!(!matches.has_reference_query_match(1, 2));
+    /// ```
+    pub fn bloom_filter(mut self, false_positive_rate: f64) -> Self {
+        self.bloom_false_positive_rate = Some(false_positive_rate);
+        self
+    }
+
+    /// Set if the construction runs in parallel.
+    ///
+    /// This has no effect unless the `parallel` feature is enabled.
+    ///
+    /// The table does not depend on the parallelism or on the number of threads of the `rayon` thread pool,
+    /// because the matches found by the threads are merged in a fixed order.
+    /// So the iteration order of the matches and the [binary](MatchTable::write_binary) output are reproducible between runs.
+    /// Only the order of the [progress events](Self::progress) varies.
+    pub fn parallel(mut self, parallel: bool) -> Self {
+        self.parallel = parallel;
+        self
+    }
+
+    /// Set how IUPAC ambiguity codes are handled when matching kmers.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_iupac_nucleic_acid_alphabet::DnaIupacNucleicAcidAlphabet;
+    /// use template_switch_error_free_inners::{AmbiguityPolicy, MatchTableBuilder};
+    ///
+    /// // The reverse complement of the query is `TTACGGATT`.
+    /// let reference =
+    ///     VectorGenome::<DnaIupacNucleicAcidAlphabet>::from_slice_u8(b"TTACRGATT").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AATCCGTAA").unwrap();
+    ///
+    /// let never_match = MatchTableBuilder::new(4)
+    ///     .ambiguity_policy(AmbiguityPolicy::NeverMatch)
+    ///     .build(reference.as_genome_subsequence(), query.as_genome_subsequence());
+    /// assert!(!never_match.has_reference_query_match(2, 2));
+    ///
+    /// let compatible = MatchTableBuilder::new(4)
+    ///     .ambiguity_policy(AmbiguityPolicy::Compatible)
+    ///     .build(reference.as_genome_subsequence(), query.as_genome_subsequence());
+    /// assert!(compatible.has_reference_query_match(2, 2));
+    /// ```
+    pub fn ambiguity_policy(mut self, ambiguity_policy: AmbiguityPolicy) -> Self {
+        self.ambiguity_policy = ambiguity_policy;
+        self
+    }
+
+    /// Exclude all kmers overlapping an `N` or `n` from indexing and matching.
+    ///
+    /// This takes precedence over the [`AmbiguityPolicy`].
+    /// The number of skipped kmers is reported by [`MatchTable::skipped_reference_kmer_count`]
+    /// and [`MatchTable::skipped_query_kmer_count`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_iupac_nucleic_acid_alphabet::DnaIupacNucleicAcidAlphabet;
+    /// use template_switch_error_free_inners::MatchTableBuilder;
+    ///
+    /// let reference =
+    ///     VectorGenome::<DnaIupacNucleicAcidAlphabet>::from_slice_u8(b"NNNNNNNNACGT").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"ACGT").unwrap();
+    /// let matches = MatchTableBuilder::new(4)
+    ///     .skip_n(true)
+    ///     .build(reference.as_genome_subsequence(), query.as_genome_subsequence());
+    ///
+    /// assert_eq!(matches.skipped_reference_kmer_count(), 8);
+    /// assert_eq!(matches.skipped_query_kmer_count(), 0);
+    /// assert!(!matches.has_reference_reference_match(0, 8));
+    /// assert!(matches.has_reference_query_match(8, 0));
+    /// ```
+    pub fn skip_n(mut self, skip_n: bool) -> Self {
+        self.skip_n = skip_n;
+        self
+    }
+
+    /// Exclude low-complexity kmers like homopolymers and simple repeats from indexing and matching.
+    ///
+    /// Without this filter, poly-A tracts and other simple repeats generate dense blocks of matches
+    /// that carry no information about template switches.
+    /// The number of excluded kmers is included in [`MatchTable::skipped_reference_kmer_count`]
+    /// and [`MatchTable::skipped_query_kmer_count`].
+    /// The threshold of the filter must be finite and not negative.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{LowComplexityFilter, MatchTableBuilder};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AAAAAAAAGATTACA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"TTTTTTTTTGTAATC").unwrap();
+    ///
+    /// let matches = MatchTableBuilder::new(6)
+    ///     .build(reference.as_genome_subsequence(), query.as_genome_subsequence());
+    /// assert!(matches.has_reference_query_match(0, 9));
+    ///
+    /// let matches = MatchTableBuilder::new(6)
+    ///     .low_complexity_filter(LowComplexityFilter::Entropy { min_entropy: 1.0 })
+    ///     .build(reference.as_genome_subsequence(), query.as_genome_subsequence());
+    /// assert!(!matches.has_reference_query_match(0, 9));
+    /// // `GATTAC` has entropy 1.92 and is kept.
+    /// assert!(matches.has_reference_query_match(8, 0));
+    /// ```
+    pub fn low_complexity_filter(mut self, filter: LowComplexityFilter) -> Self {
+        self.low_complexity_filter = Some(filter);
+        self
+    }
+
+    /// Exclude all kmers from indexing and matching that occur more than `max_occurrences` times in the reference or in the query.
+    ///
+    /// The occurrences of a kmer are counted on both strands, i.e. they include the occurrences of its reverse complement,
+    /// so a kmer and its reverse complement are either both excluded or both kept.
+    /// All kmers are counted, including those excluded for other reasons.
+    /// This is the standard mitigation of repeats in seed-based aligners:
+    /// a kmer that occurs `n` times in a sequence has up to `n` matches in each quadrant of that sequence,
+    /// so repeats generate most of the matches without adding information about template switches.
+    ///
+    /// The maximum is recorded in the table, see [`MatchTable::max_kmer_occurrences`],
+    /// and the numbers of excluded kmers are reported by [`MatchTable::frequent_reference_kmer_count`]
+    /// and [`MatchTable::frequent_query_kmer_count`], as well as included in [`MatchTable::skipped_reference_kmer_count`]
+    /// and [`MatchTable::skipped_query_kmer_count`].
+    /// The maximum must be positive.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::MatchTableBuilder;
+    ///
+    /// // `GGGG` occurs three times in the reference, and `CCCC` once.
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"GGGGAGGGGACCCCAGGGG").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"TACGT").unwrap();
+    ///
+    /// let matches = MatchTableBuilder::new(4)
+    ///     .build(reference.as_genome_subsequence(), query.as_genome_subsequence());
+    /// assert!(matches.has_reference_reference_match(0, 5));
+    ///
+    /// let matches = MatchTableBuilder::new(4)
+    ///     .max_kmer_occurrences(3)
+    ///     .build(reference.as_genome_subsequence(), query.as_genome_subsequence());
+    /// assert!(!matches.has_reference_reference_match(0, 5));
+    /// assert_eq!(matches.max_kmer_occurrences(), Some(3));
+    /// assert_eq!(matches.frequent_reference_kmer_count(), 4);
+    /// // `ACGT` is its own reverse complement, so it occurs twice on both strands of the query.
+    /// assert!(matches.has_query_query_match(1, 0));
+    /// ```
+    pub fn max_kmer_occurrences(mut self, max_occurrences: usize) -> Self {
+        self.max_kmer_occurrences = Some(max_occurrences);
+        self
+    }
+
+    /// If set, record only the matches where both the primary kmer and the secondary kmer occur exactly once in their sequences.
+    ///
+    /// The occurrences are counted on the forward strand of each sequence, and all kmers are counted,
+    /// including those excluded for other reasons.
+    /// Unlike [`max_kmer_occurrences`](Self::max_kmer_occurrences)`(1)`, this keeps the matches between a unique kmer
+    /// and the unique occurrence of its reverse complement in the same sequence,
+    /// so the reference–reference and query–query quadrants are not emptied.
+    /// The matches between unique kmers are high-confidence anchors for conservative template switch calling.
+    ///
+    /// The option is recorded in the table, see [`MatchTable::unique_matches_only`],
+    /// and the repeated kmers are counted like the kmers excluded by [`max_kmer_occurrences`](Self::max_kmer_occurrences).
+    /// Both options can be combined.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::MatchTableBuilder;
+    ///
+    /// // `GGGG` occurs twice in the reference, and `CCCC` once.
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"GGGGATGGGGTCCCCA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"TTGGGGTT").unwrap();
+    ///
+    /// let matches = MatchTableBuilder::new(4)
+    ///     .build(reference.as_genome_subsequence(), query.as_genome_subsequence());
+    /// assert!(matches.has_reference_reference_match(0, 1));
+    ///
+    /// let matches = MatchTableBuilder::new(4)
+    ///     .unique_matches_only(true)
+    ///     .build(reference.as_genome_subsequence(), query.as_genome_subsequence());
+    /// assert!(matches.unique_matches_only());
+    /// assert_eq!(matches.frequent_reference_kmer_count(), 2);
+    /// assert!(!matches.has_reference_reference_match(0, 1));
+    /// // `GGGG` occurs once in the query, and its reverse complement `CCCC` once in the reference.
+    /// assert!(matches.has_query_reference_match(2, 1));
+    /// ```
+    pub fn unique_matches_only(mut self, unique_matches_only: bool) -> Self {
+        self.unique_matches_only = unique_matches_only;
+        self
+    }
+
+    /// Stop the extension of matches into inners at the given maximum length.
+    ///
+    /// Very long inners are almost always segmental duplications rather than template switches,
+    /// and each of them yields many candidates downstream.
+    /// With a maximum length, [`longest_match_length`](MatchTable::longest_match_length), [`matches_with_length`](MatchTable::matches_with_length)
+    /// and [`maximal_matches`](MatchTable::maximal_matches) report no inners longer than it,
+    /// and a longer run of matches is reported only with its first `maximum_length` characters.
+    /// The matches themselves are not affected.
+    ///
+    /// The maximum length must be at least the minimum length.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTableBuilder, Quadrant};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"TTACGGATTT").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"GGTCCGTGG").unwrap();
+    ///
+    /// let matches = MatchTableBuilder::new(4)
+    ///     .maximum_length(4)
+    ///     .build(reference.as_genome_subsequence(), query.as_genome_subsequence());
+    /// assert_eq!(matches.maximum_length(), Some(4));
+    /// assert_eq!(matches.match_count(Quadrant::ReferenceQuery, 3), 1);
+    /// // The inner `ACGGA` is cut after its first kmer.
+    /// assert_eq!(
+    ///     matches.maximal_matches(Quadrant::ReferenceQuery).collect::<Vec<_>>(),
+    ///     vec![(2, 2, 4)],
+    /// );
+    /// ```
+    pub fn maximum_length(mut self, maximum_length: usize) -> Self {
+        self.maximum_length = Some(maximum_length);
+        self
+    }
+
+    /// Exclude all kmers of the reference overlapping a character marked in `mask` from matching.
+    ///
+    /// The mask must have the same length as the reference.
+    /// Coordinates are not affected by the mask.
+    /// See [`soft_masked_characters`](crate::soft_masked_characters) for masking soft-masked regions.
+    pub fn reference_mask(mut self, mask: BitVec) -> Self {
+        self.reference_mask = Some(mask);
+        self
+    }
+
+    /// Exclude all kmers of the query overlapping a character marked in `mask` from matching.
+    ///
+    /// The mask must have the same length as the query.
+    /// Coordinates are not affected by the mask.
+    /// See [`soft_masked_characters`](crate::soft_masked_characters) for masking soft-masked regions.
+    pub fn query_mask(mut self, mask: BitVec) -> Self {
+        self.query_mask = Some(mask);
+        self
+    }
+
+    /// Exclude all kmers of the reference overlapping one of the given intervals from indexing and matching.
+    ///
+    /// The intervals are BED-style, i.e. zero-based and half-open, and must lie within the reference.
+    /// This masks regions like centromeres or rDNA arrays that would otherwise dominate the matches.
+    /// Coordinates are not affected by the intervals,
+    /// and the intervals are recorded in the table, see [`MatchTable::reference_excluded_intervals`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::MatchTableBuilder;
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+    /// let matches = MatchTableBuilder::new(4)
+    ///     .reference_excluded_intervals([8..9, 6..8])
+    ///     .build(reference.as_genome_subsequence(), query.as_genome_subsequence());
+    ///
+    /// // The kmer `GGGG` at 1 matches `CCCC` at 7, which overlaps the excluded interval.
+    /// assert!(!matches.has_reference_reference_match(1, 2));
+    /// assert_eq!(matches.skipped_reference_kmer_count(), 6);
+    /// assert_eq!(matches.reference_excluded_intervals(), &[6..9]);
+    /// ```
+    pub fn reference_excluded_intervals(
+        mut self,
+        intervals: impl IntoIterator<Item = Range<usize>>,
+    ) -> Self {
+        self.reference_excluded_intervals = intervals.into_iter().collect();
+        self
+    }
+
+    /// Exclude all kmers of the query overlapping one of the given intervals from indexing and matching.
+    ///
+    /// See [`reference_excluded_intervals`](Self::reference_excluded_intervals) for details.
+    pub fn query_excluded_intervals(
+        mut self,
+        intervals: impl IntoIterator<Item = Range<usize>>,
+    ) -> Self {
+        self.query_excluded_intervals = intervals.into_iter().collect();
+        self
+    }
+
+    /// Compute and store only the matches `(primary_index, secondary_rc_index)`
+    /// where `secondary_rc_index - primary_index` lies within `min_offset..=max_offset`.
+    ///
+    /// With [`StorageBackend::Dense`], only the band is stored, so memory scales with the width of the band.
+    /// If the band is narrow, then [`ConstructionStrategy::Automatic`] compares the kmers within the band directly,
+    /// so time scales with the width of the band as well.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::MatchTableBuilder;
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+    /// let matches = MatchTableBuilder::new(4)
+    ///     .band(0, 0)
+    ///     .build(reference.as_genome_subsequence(), query.as_genome_subsequence());
+    ///
+    /// assert!(!matches.has_reference_reference_match(1, 2));
+    /// assert!(!matches.has_reference_reference_match(7, 8));
+    ///
+    /// let matches = MatchTableBuilder::new(4)
+    ///     .band(-1, 1)
+    ///     .build(reference.as_genome_subsequence(), query.as_genome_subsequence());
+    ///
+    /// assert!(matches.has_reference_reference_match(1, 2));
+    /// assert!(matches.has_reference_reference_match(7, 8));
+    /// ```
+    pub fn band(mut self, min_offset: isize, max_offset: isize) -> Self {
+        self.band = Some(Band {
+            min_offset,
+            max_offset,
+        });
+        self
+    }
+
+    /// Keep only the matches where at least one of the two kmers is a minimizer of a window of `window_size` consecutive kmers of its genome.
+    ///
+    /// This trades completeness for a reduction of the number of stored matches,
+    /// by a factor of roughly `(window_size + 1) / 4` for large windows on genomes without long repeats.
+    /// An inner of length at least `minimum_length + window_size - 1` consists of `window_size` consecutive matching kmers,
+    /// of which at least one is a minimizer, so each such inner is still detected by at least one match,
+    /// unless one of its kmers is excluded from matching.
+    /// Shorter inners may be missed.
+    ///
+    /// Minimizers are selected by a fixed hash of the kmers, so the same options always yield the same table.
+    /// The sparsified table is still symmetric under [`Quadrant::transposed`].
+    /// A window size of one keeps all matches.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTableBuilder, Quadrant};
+    ///
+    /// // The reverse complement of the query contains the inner `GATTACAGATTACA` of the reference.
+    /// let reference =
+    ///     VectorGenome::<DnaAlphabet>::from_slice_u8(b"CCGATTACAGATTACACC").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"TGTAATCTGTAATC").unwrap();
+    /// let matches = MatchTableBuilder::new(4)
+    ///     .minimizer_window(8)
+    ///     .build(reference.as_genome_subsequence(), query.as_genome_subsequence());
+    ///
+    /// // The inner has length 14 >= 4 + 8 - 1, so at least one of its matches is kept.
+    /// assert!((0..11).any(|offset| matches.has_reference_query_match(2 + offset, offset)));
+    /// ```
+    pub fn minimizer_window(mut self, window_size: usize) -> Self {
+        self.minimizer_window = Some(window_size);
+        self
+    }
+
+    /// Set if kmers are matched canonically, i.e. regardless of the orientation of the secondary kmer.
+    ///
+    /// By default, a primary kmer matches a secondary kmer if it equals its reverse complement,
+    /// which is the orientation of a template switch.
+    /// Canonical matching additionally reports the pairs where the primary kmer equals the secondary kmer itself,
+    /// such that a pair matches if the canonical kmers, i.e. the minimum of a kmer and its reverse complement, are equal.
+    /// Indices are unaffected, so both orientations are reported at the same `(primary_index, secondary_rc_index)`.
+    ///
+    /// In the self-comparison quadrants, each kmer matches itself in forward orientation,
+    /// so all pairs `(primary_index, kmer_count - 1 - primary_index)` of non-excluded kmers match.
+    /// The table remains symmetric under [`Quadrant::transposed`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::MatchTableBuilder;
+    ///
+    /// // The query contains the reference kmer `GATT` in forward orientation.
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"CGATTC").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"GATTGG").unwrap();
+    ///
+    /// let matches = MatchTableBuilder::new(4)
+    ///     .build(reference.as_genome_subsequence(), query.as_genome_subsequence());
+    /// assert!(!matches.has_reference_query_match(1, 2));
+    ///
+    /// let matches = MatchTableBuilder::new(4)
+    ///     .canonical(true)
+    ///     .build(reference.as_genome_subsequence(), query.as_genome_subsequence());
+    /// assert!(matches.has_reference_query_match(1, 2));
+    /// ```
+    pub fn canonical(mut self, canonical: bool) -> Self {
+        self.orientation = if canonical {
+            MatchOrientation::Both
+        } else {
+            MatchOrientation::ReverseComplement
+        };
+        self
+    }
+
+    /// Set the orientations in which kmers are matched.
+    ///
+    /// With [`MatchOrientation::Forward`], the table contains the direct repeats between the sequences instead of the inverted ones,
+    /// which distinguishes template switches from tandem duplications.
+    /// [`MatchOrientation::Both`] is the same as [`canonical`](Self::canonical) matching.
+    /// Indices are unaffected, so a forward match of the secondary kmer at forward index `i` is reported at the secondary rc index `kmer_count - 1 - i`.
+    /// Hence consecutive forward matches lie on the anti-diagonals of a quadrant rather than on its diagonals,
+    /// and are not joined by [`MatchTable::maximal_matches`] or [`MatchTable::candidates`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchOrientation, MatchTableBuilder};
+    ///
+    /// // The query contains the reference kmer `GATT` in forward orientation,
+    /// // and the reverse complement `AATC` of the reference kmer `GATT` at its end.
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"CGATTC").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"GATTGAATC").unwrap();
+    ///
+    /// let matches = MatchTableBuilder::new(4)
+    ///     .orientation(MatchOrientation::Forward)
+    ///     .build(reference.as_genome_subsequence(), query.as_genome_subsequence());
+    /// assert!(matches.has_reference_query_match(1, 5));
+    /// assert!(!matches.has_reference_query_match(1, 0));
+    /// ```
+    pub fn orientation(mut self, orientation: MatchOrientation) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    /// Set the quadrants that are computed.
+    ///
+    /// The other quadrants are left empty, which saves their memory and construction time.
+    /// For example, cross-genome analyses only need [`Quadrants::REFERENCE_QUERY`] and [`Quadrants::QUERY_REFERENCE`].
+    /// The index of a genome is only built if it is the primary genome of a selected quadrant.
+    /// See [`MatchTable::quadrants`] for the quadrants of a table.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTableBuilder, Quadrants};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"TTGGGGTT").unwrap();
+    /// let matches = MatchTableBuilder::new(4)
+    ///     .quadrants(Quadrants::REFERENCE_QUERY | Quadrants::QUERY_REFERENCE)
+    ///     .build(reference.as_genome_subsequence(), query.as_genome_subsequence());
+    ///
+    /// assert!(matches.has_reference_query_match(7, 2));
+    /// // The self quadrants are not computed.
+    /// assert!(!matches.has_reference_reference_match(1, 2));
+    /// ```
+    pub fn quadrants(mut self, quadrants: Quadrants) -> Self {
+        self.quadrants = quadrants;
+        self
+    }
+
+    /// Set a reporter that receives [`ProgressEvent`]s during construction.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::sync::Mutex;
+    ///
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTableBuilder, ProgressEvent};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+    /// let events = std::sync::Arc::new(Mutex::new(Vec::new()));
+    /// let reporter_events = events.clone();
+    /// MatchTableBuilder::new(4)
+    ///     .progress(move |event| reporter_events.lock().unwrap().push(event))
+    ///     .build(reference.as_genome_subsequence(), query.as_genome_subsequence());
+    ///
+    /// let events = events.lock().unwrap();
+    /// assert_eq!(events.first(), Some(&ProgressEvent::IndexesBuilt));
+    /// assert!(events.contains(&ProgressEvent::KmersProcessed {
+    ///     genome: "query",
+    ///     processed: 5,
+    ///     total: 5,
+    /// }));
+    /// assert_eq!(events.last(), Some(&ProgressEvent::Finished));
+    /// ```
+    pub fn progress(mut self, reporter: impl ProgressReporter + 'static) -> Self {
+        self.progress = Some(ProgressHandle::new(reporter));
+        self
+    }
+
+    /// Set a flag that cancels the construction when it is set.
+    ///
+    /// The flag is checked regularly while searching for matches, so setting it from another thread,
+    /// e.g. when the user of a GUI or the client of a server aborts, stops the construction soon.
+    /// The building of indexes is not interrupted.
+    /// A cancelled construction returns [`MatchTableError::Cancelled`] from the `try_` methods, and the `build` methods panic.
+    /// A cancelled stream ends early.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+    ///
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTableBuilder, MatchTableError};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+    /// let cancelled = Arc::new(AtomicBool::new(false));
+    /// let options = MatchTableBuilder::new(4).cancellation(cancelled.clone());
+    /// assert!(options.try_build(reference.as_genome_subsequence(), query.as_genome_subsequence()).is_ok());
+    ///
+    /// cancelled.store(true, Ordering::Relaxed);
+    /// assert!(matches!(
+    ///     options.try_build(reference.as_genome_subsequence(), query.as_genome_subsequence()),
+    ///     Err(MatchTableError::Cancelled),
+    /// ));
+    /// ```
+    pub fn cancellation(mut self, cancelled: Arc<AtomicBool>) -> Self {
+        self.cancellation = Some(cancelled);
+        self
+    }
+
+    /// Set the integer type that stores the kmer indices of the table, either `u32`, the default, or `u64`.
+    ///
+    /// [Sparse storage](StorageBackend::Sparse) stores an index per match, and the hash indexes of
+    /// [`ConstructionStrategy::HashJoin`] and [`ConstructionStrategy::SortMerge`] store an index per kmer,
+    /// so `u32` indices take half the memory of `u64` indices.
+    /// However, with `u32` indices, sparse storage returns [`MatchTableError::IndexTypeTooNarrow`] for sequences of 2^32 or more kmers,
+    /// and the hash and sort-merge joins fall back to [`ConstructionStrategy::IndexLookup`] for sequences of 2^32 or more characters.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTable, MatchTableBuilder, StorageBackend};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+    /// let matches: MatchTable<u64> = MatchTableBuilder::new(4)
+    ///     .storage(StorageBackend::Sparse)
+    ///     .index_type::<u64>()
+    ///     .build(reference.as_genome_subsequence(), query.as_genome_subsequence());
+    ///
+    /// assert!(matches.has_reference_reference_match(1, 2));
+    /// assert!(matches.has_reference_reference_match(7, 8));
+    /// ```
+    pub fn index_type<Other: StorageIndex>(self) -> MatchTableBuilder<Other> {
+        let Self {
+            minimum_length,
+            max_mismatches,
+            storage,
+            index_backend,
+            strategy,
+            bloom_false_positive_rate,
+            parallel,
+            ambiguity_policy,
+            skip_n,
+            low_complexity_filter,
+            max_kmer_occurrences,
+            unique_matches_only,
+            maximum_length,
+            reference_mask,
+            query_mask,
+            reference_excluded_intervals,
+            query_excluded_intervals,
+            band,
+            minimizer_window,
+            orientation,
+            quadrants,
+            progress,
+            cancellation,
+            index_type: _,
+        } = self;
+        MatchTableBuilder {
+            minimum_length,
+            max_mismatches,
+            storage,
+            index_backend,
+            strategy,
+            bloom_false_positive_rate,
+            parallel,
+            ambiguity_policy,
+            skip_n,
+            low_complexity_filter,
+            max_kmer_occurrences,
+            unique_matches_only,
+            maximum_length,
+            reference_mask,
+            query_mask,
+            reference_excluded_intervals,
+            query_excluded_intervals,
+            band,
+            minimizer_window,
+            orientation,
+            quadrants,
+            progress,
+            cancellation,
+            index_type: PhantomData,
+        }
+    }
+
+    /// Returns true if the cancellation flag is set.
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(|cancelled| cancelled.load(Ordering::Relaxed))
+    }
+
+    /// Returns [`MatchTableError::Cancelled`] if the cancellation flag is set.
+    pub(crate) fn check_cancelled(&self) -> Result<(), MatchTableError> {
+        if self.is_cancelled() {
+            Err(MatchTableError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Report the given event to the progress reporter, if any.
+    pub(crate) fn report(&self, event: ProgressEvent) {
+        if let Some(progress) = &self.progress {
+            progress.report(event);
+        }
+    }
+
+    /// Compute the match table of the given reference and query.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`try_build`](Self::try_build) returns an error.
+    pub fn build<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        &self,
+        reference: &GenomeSubsequence,
+        query: &GenomeSubsequence,
+    ) -> MatchTable<Index> {
+        self.try_build(reference, query)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Compute the match table of the given reference and query.
+    ///
+    /// The sequences may be of any [`GenomeSequence`] type of `compact_genome`, including the bit-packed
+    /// [`BitVectorGenome`](compact_genome::implementation::bit_vec_sequence::BitVectorGenome) and the sequences of a
+    /// [`BitVectorSequenceStore`](compact_genome::implementation::bit_vec_sequence_store::BitVectorSequenceStore),
+    /// so they do not need to be converted to strings or byte vectors first.
+    /// The kmer indexes are built over one byte per character, so only the sequences themselves stay packed.
+    ///
+    /// Returns an error if the options are invalid, a sequence is shorter than the minimum length,
+    /// a mask does not match the length of its sequence, or the storage cannot be allocated.
+    pub fn try_build<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        &self,
+        reference: &GenomeSubsequence,
+        query: &GenomeSubsequence,
+    ) -> Result<MatchTable<Index>, MatchTableError> {
+        let kmer_counts = self.kmer_counts(reference.len(), query.len())?;
+        let contigs = [
+            ContigLayout::new([reference.len()]),
+            ContigLayout::new([query.len()]),
+        ];
+        self.try_build_with_contigs(reference, query, contigs, kmer_counts)
+    }
+
+    /// Compute the match table of a single sequence against itself.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`try_build_self`](Self::try_build_self) returns an error.
+    pub fn build_self<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        &self,
+        sequence: &GenomeSubsequence,
+    ) -> MatchTable<Index> {
+        self.try_build_self(sequence)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Compute the match table of a single sequence against itself.
+    ///
+    /// The sequence is the reference, and the query is empty,
+    /// so only [`Quadrant::ReferenceReference`] contains kmers, and the other quadrants are empty.
+    /// Neither the query nor the cross quadrants are indexed or stored.
+    /// The query mask is ignored.
+    ///
+    /// Returns an error under the same conditions as [`try_build`](Self::try_build).
+    pub fn try_build_self<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        &self,
+        sequence: &GenomeSubsequence,
+    ) -> Result<MatchTable<Index>, MatchTableError> {
+        self.try_build_self_with_contigs(sequence, ContigLayout::new([sequence.len()]))
+    }
+
+    /// Compute the matches between every ordered pair of the given sequences.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`try_build_all_vs_all`](Self::try_build_all_vs_all) returns an error.
+    pub fn build_all_vs_all<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        &self,
+        sequences: &[&GenomeSubsequence],
+    ) -> AllVsAllMatchTable<Index> {
+        self.try_build_all_vs_all(sequences)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Compute the matches between every ordered pair of the given sequences.
+    ///
+    /// The sequences are concatenated as the contigs of the reference, which is compared against itself
+    /// as by [`try_build_self`](Self::try_build_self).
+    /// The reference mask and the band apply to the concatenation, and the query mask is ignored.
+    /// See [`AllVsAllMatchTable`] for details.
+    ///
+    /// Returns an error under the same conditions as [`try_build`](Self::try_build),
+    /// where the length of the reference is the total length of the sequences.
+    /// Individual sequences may be shorter than the minimum length.
+    pub fn try_build_all_vs_all<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        &self,
+        sequences: &[&GenomeSubsequence],
+    ) -> Result<AllVsAllMatchTable<Index>, MatchTableError> {
+        let contigs = ContigLayout::new(sequences.iter().map(|sequence| sequence.len()));
+        let concatenation = VectorGenome::<AlphabetType>::from_iter(
+            sequences
+                .iter()
+                .flat_map(|sequence| sequence.iter().cloned()),
+        );
+        self.try_build_self_with_contigs(concatenation.as_genome_subsequence(), contigs)
+            .map(AllVsAllMatchTable::from_table)
+    }
+
+    /// Compute the match tables of the given reference and query for each of the given minimum lengths.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`try_build_multi_k`](Self::try_build_multi_k) returns an error.
+    pub fn build_multi_k<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        &self,
+        reference: &GenomeSubsequence,
+        query: &GenomeSubsequence,
+        minimum_lengths: &[usize],
+    ) -> MultiKMatchTable<Index> {
+        self.try_build_multi_k(reference, query, minimum_lengths)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Compute the match tables of the given reference and query for each of the given minimum lengths.
+    ///
+    /// The minimum length configured in this builder is ignored, and all other options apply to each table.
+    /// Without mismatches and in reverse-complement orientation, the index is searched only once for the shortest minimum length,
+    /// and the other tables are derived from its matches, see [`MultiKMatchTable`].
+    /// Otherwise, longer matches do not consist of shorter ones, and each table is built separately.
+    ///
+    /// Returns an error under the same conditions as [`try_build`](Self::try_build) for any of the minimum lengths.
+    pub fn try_build_multi_k<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        &self,
+        reference: &GenomeSubsequence,
+        query: &GenomeSubsequence,
+        minimum_lengths: &[usize],
+    ) -> Result<MultiKMatchTable<Index>, MatchTableError> {
+        let mut minimum_lengths = minimum_lengths.to_vec();
+        minimum_lengths.sort_unstable();
+        minimum_lengths.dedup();
+        let options = minimum_lengths
+            .into_iter()
+            .map(|minimum_length| {
+                let options = self.clone().minimum_length(minimum_length);
+                let kmer_counts = options.kmer_counts(reference.len(), query.len())?;
+                Ok((options, kmer_counts))
+            })
+            .collect::<Result<Vec<_>, MatchTableError>>()?;
+
+        if self.max_mismatches > 0 || self.orientation != MatchOrientation::ReverseComplement {
+            let contigs = [
+                ContigLayout::new([reference.len()]),
+                ContigLayout::new([query.len()]),
+            ];
+            return options
+                .into_iter()
+                .map(|(options, kmer_counts)| {
+                    options.try_build_with_contigs(reference, query, contigs.clone(), kmer_counts)
+                })
+                .collect::<Result<_, _>>()
+                .map(MultiKMatchTable::from_tables);
+        }
+
+        MultiKMatchTable::construct(reference, query, &options)
+    }
+    fn try_build_self_with_contigs<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        &self,
+        sequence: &GenomeSubsequence,
+        contigs: ContigLayout,
+    ) -> Result<MatchTable<Index>, MatchTableError> {
+        self.validate()?;
+        Self::validate_mask("reference", self.reference_mask.as_ref(), sequence.len())?;
+        Self::validate_intervals(
+            "reference",
+            &self.reference_excluded_intervals,
+            sequence.len(),
+        )?;
+        let kmer_count = self.kmer_count("reference", sequence.len())?;
+        let options = Self {
+            query_mask: None,
+            query_excluded_intervals: Vec::new(),
+            quadrants: self.quadrants & Quadrants::REFERENCE_REFERENCE,
+            ..self.clone()
+        };
+        options.try_build_with_contigs(
+            sequence,
+            &sequence[0..0],
+            [contigs, ContigLayout::new([0])],
+            (kmer_count, 0),
+        )
+    }
+
+    /// Compute the match table of the given reference and query contigs.
+    ///
+    /// The contigs of each genome are concatenated, and kmers spanning the boundary between two contigs never match.
+    /// Masks apply to the concatenation of the contigs.
+    /// Indices into the table refer to the concatenation,
+    /// and [`MatchTable::contig_matches`] reports the matches relative to the individual contigs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`try_build_contigs`](Self::try_build_contigs) returns an error.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{ContigPosition, MatchTableBuilder, Quadrant};
+    ///
+    /// let reference = [
+    ///     VectorGenome::<DnaAlphabet>::from_slice_u8(b"AAAGG").unwrap(),
+    ///     VectorGenome::from_slice_u8(b"GGTT").unwrap(),
+    /// ];
+    /// let query = [
+    ///     VectorGenome::from_slice_u8(b"ACCCT").unwrap(),
+    ///     VectorGenome::from_slice_u8(b"ACCTT").unwrap(),
+    /// ];
+    /// let matches = MatchTableBuilder::new(4).build_contigs(
+    ///     &reference.each_ref().map(|contig| contig.as_genome_subsequence()),
+    ///     &query.each_ref().map(|contig| contig.as_genome_subsequence()),
+    /// );
+    ///
+    /// // The reference kmers `AGGG` and `GGGT` span the boundary between the reference contigs,
+    /// // so they do not match the reverse complement `AGGGT` of the first query contig.
+    /// assert_eq!(
+    ///     matches.contig_matches(Quadrant::ReferenceQuery).collect::<Vec<_>>(),
+    ///     vec![(
+    ///         ContigPosition { contig_id: 0, offset: 1 },
+    ///         ContigPosition { contig_id: 1, offset: 0 },
+    ///     )],
+    /// );
+    /// ```
+    pub fn build_contigs<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        &self,
+        reference: &[&GenomeSubsequence],
+        query: &[&GenomeSubsequence],
+    ) -> MatchTable<Index> {
+        self.try_build_contigs(reference, query)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Compute the match table of the given reference and query contigs.
+    ///
+    /// Returns an error under the same conditions as [`try_build`](Self::try_build),
+    /// where the length of a genome is the total length of its contigs.
+    /// See [`build_contigs`](Self::build_contigs) for details.
+    pub fn try_build_contigs<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        &self,
+        reference: &[&GenomeSubsequence],
+        query: &[&GenomeSubsequence],
+    ) -> Result<MatchTable<Index>, MatchTableError> {
+        let concatenate = |contigs: &[&GenomeSubsequence]| {
+            let layout = ContigLayout::new(contigs.iter().map(|contig| contig.len()));
+            let concatenation = VectorGenome::<AlphabetType>::from_iter(
+                contigs.iter().flat_map(|contig| contig.iter().cloned()),
+            );
+            (concatenation, layout)
+        };
+        let (reference, reference_contigs) = concatenate(reference);
+        let (query, query_contigs) = concatenate(query);
+        let kmer_counts = self.kmer_counts(reference_contigs.len(), query_contigs.len())?;
+
+        self.try_build_with_contigs(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+            [reference_contigs, query_contigs],
+            kmer_counts,
+        )
+    }
+    /// Enumerate the matches of the given reference and query without storing them in a [`MatchTable`].
+    ///
+    /// The matches are searched by a background thread and yielded in no particular order as they are found,
+    /// so memory does not grow with the number of matches.
+    /// The search itself is sequential, and the configured storage backend is ignored.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`try_stream`](Self::try_stream) returns an error.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn stream<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        &self,
+        reference: &GenomeSubsequence,
+        query: &GenomeSubsequence,
+    ) -> MatchStream {
+        self.try_stream(reference, query)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Enumerate the matches of the given reference and query without storing them in a [`MatchTable`].
+    ///
+    /// Returns an error under the same conditions as [`try_build`](Self::try_build),
+    /// except that no storage is allocated.
+    /// See [`stream`](Self::stream) for details.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn try_stream<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        &self,
+        reference: &GenomeSubsequence,
+        query: &GenomeSubsequence,
+    ) -> Result<MatchStream, MatchTableError> {
+        self.kmer_counts(reference.len(), query.len())?;
+        let contigs = [
+            ContigLayout::new([reference.len()]),
+            ContigLayout::new([query.len()]),
+        ];
+        Ok(MatchStream::spawn(
+            Texts::new(reference, query, self),
+            contigs,
+            self.clone().parallel(false),
+        ))
+    }
+
+    /// Prepare on-demand queries of the matches of the given reference and query, without precomputing them.
+    ///
+    /// The configured storage backend, index backend, strategy and parallelism are ignored.
+    /// See [`LazyMatchTable`] for details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`try_build_lazy`](Self::try_build_lazy) returns an error.
+    pub fn build_lazy<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        &self,
+        reference: &GenomeSubsequence,
+        query: &GenomeSubsequence,
+    ) -> LazyMatchTable {
+        self.try_build_lazy(reference, query)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Prepare on-demand queries of the matches of the given reference and query, without precomputing them.
+    ///
+    /// Returns an error under the same conditions as [`try_build`](Self::try_build),
+    /// except that no storage is allocated.
+    pub fn try_build_lazy<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        &self,
+        reference: &GenomeSubsequence,
+        query: &GenomeSubsequence,
+    ) -> Result<LazyMatchTable, MatchTableError> {
+        self.kmer_counts(reference.len(), query.len())?;
+        let contigs = [
+            ContigLayout::new([reference.len()]),
+            ContigLayout::new([query.len()]),
+        ];
+        Ok(LazyMatchTable::from_texts(
+            Texts::new(reference, query, self),
+            contigs,
+            self,
+        ))
+    }
+    fn try_build_with_contigs<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        &self,
+        reference: &GenomeSubsequence,
+        query: &GenomeSubsequence,
+        contigs: [ContigLayout; 2],
+        kmer_counts: (usize, usize),
+    ) -> Result<MatchTable<Index>, MatchTableError> {
+        let builders = self.quadrant_storage_builders(kmer_counts)?;
+        let table = MatchTable::construct(reference, query, contigs, self, builders);
+        self.check_cancelled()?;
+        Ok(table)
+    }
+
+    /// Initialise the storage of all quadrants, leaving the quadrants that are not computed empty.
+    pub(crate) fn quadrant_storage_builders(
+        &self,
+        (reference_kmer_count, query_kmer_count): (usize, usize),
+    ) -> Result<[QuadrantStorageBuilder<Index>; 4], MatchTableError> {
+        debug!("Initialising storage");
+        enter_span!("allocate_storage", storage = ?self.storage);
+        let mut builders = Vec::with_capacity(4);
+        for quadrant in Quadrant::ALL {
+            let (primary_kmer_count, secondary_kmer_count) =
+                quadrant.dimensions(reference_kmer_count, query_kmer_count);
+            builders.push(if self.quadrants.contains(quadrant) {
+                QuadrantStorageBuilder::new(
+                    self.storage,
+                    primary_kmer_count,
+                    secondary_kmer_count,
+                    self.band,
+                    quadrant.is_self_comparison(),
+                )?
+            } else {
+                QuadrantStorageBuilder::empty(primary_kmer_count)
+            });
+        }
+        let Ok(builders) = builders.try_into() else {
+            unreachable!()
+        };
+        Ok(builders)
+    }
+
+    /// Compute the match table of the given reference and query, storing the matches in a memory-mapped file at the given path.
+    ///
+    /// The configured storage backend is ignored.
+    /// See [`MatchTable::new_mmap`] for details.
+    #[cfg(feature = "mmap")]
+    pub fn build_mmap<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        &self,
+        path: impl AsRef<std::path::Path>,
+        reference: &GenomeSubsequence,
+        query: &GenomeSubsequence,
+    ) -> Result<MatchTable<Index>, MatchTableError> {
+        let (reference_kmer_count, query_kmer_count) =
+            self.kmer_counts(reference.len(), query.len())?;
+
+        debug!("Initialising memory-mapped storage");
+        // Quadrants that are not computed are kept in memory, since they are empty.
+        let region_lengths = Quadrant::ALL.map(|quadrant| {
+            let (primary_kmer_count, secondary_kmer_count) =
+                quadrant.dimensions(reference_kmer_count, query_kmer_count);
+            if self.quadrants.contains(quadrant) {
+                QuadrantStorageBuilder::<Index>::mapped_region_length(
+                    primary_kmer_count,
+                    secondary_kmer_count,
+                )
+            } else {
+                Ok(0)
+            }
+        });
+        let [
+            reference_reference,
+            reference_query,
+            query_reference,
+            query_query,
+        ] = region_lengths;
+        let region_lengths = [
+            reference_reference?,
+            reference_query?,
+            query_reference?,
+            query_query?,
+        ];
+
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        file.set_len(region_lengths.iter().sum())?;
+
+        let mut builders = Vec::with_capacity(4);
+        let mut offset = 0;
+        for (quadrant, region_length) in Quadrant::ALL.into_iter().zip(region_lengths) {
+            let (primary_kmer_count, secondary_kmer_count) =
+                quadrant.dimensions(reference_kmer_count, query_kmer_count);
+            builders.push(if self.quadrants.contains(quadrant) {
+                QuadrantStorageBuilder::new_mapped(
+                    &file,
+                    offset,
+                    primary_kmer_count,
+                    secondary_kmer_count,
+                )?
+            } else {
+                QuadrantStorageBuilder::empty(primary_kmer_count)
+            });
+            offset += region_length;
+        }
+        let Ok(builders) = builders.try_into() else {
+            unreachable!()
+        };
+
+        let contigs = [
+            ContigLayout::new([reference.len()]),
+            ContigLayout::new([query.len()]),
+        ];
+        let result = MatchTable::construct(reference, query, contigs, self, builders);
+        self.check_cancelled()?;
+        for quadrant in Quadrant::ALL {
+            result.quadrant(quadrant).flush()?;
+        }
+        Ok(result)
+    }
+
+    /// Compute the match table of the given reference and query, marking the candidate matches on the GPU.
+    ///
+    /// This is experimental.
+    /// The kmers are hashed on the GPU, and the hashes of all pairs of kmers within the band are compared on the GPU,
+    /// so this pays off only for large banded comparisons on a workstation GPU.
+    /// The candidates are verified on the CPU, so the table is the same as computed by [`try_build`](Self::try_build).
+    /// The configured construction strategy and index backend are ignored.
+    /// If mismatches, canonical matching or [`AmbiguityPolicy::Compatible`] are configured, then the table is computed on the CPU.
+    ///
+    /// Returns an error under the same conditions as [`try_build`](Self::try_build),
+    /// or if no GPU is available or the sequences do not fit into its buffers.
+    #[cfg(feature = "gpu")]
+    pub fn build_gpu<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        &self,
+        reference: &GenomeSubsequence,
+        query: &GenomeSubsequence,
+    ) -> Result<MatchTable<Index>, MatchTableError> {
+        let kmer_counts = self.kmer_counts(reference.len(), query.len())?;
+        let contigs = [
+            ContigLayout::new([reference.len()]),
+            ContigLayout::new([query.len()]),
+        ];
+        let builders = self.quadrant_storage_builders(kmer_counts)?;
+        let table = MatchTable::construct_with(
+            reference,
+            query,
+            contigs,
+            self,
+            builders,
+            crate::gpu::find_matches_gpu,
+        )?;
+        self.check_cancelled()?;
+        Ok(table)
+    }
+
+    /// Validate the options that do not depend on the sequences.
+    fn validate(&self) -> Result<(), MatchTableError> {
+        if self.minimum_length == 0 || self.minimum_length <= self.max_mismatches {
+            return Err(MatchTableError::InvalidMinimumLength {
+                minimum_length: self.minimum_length,
+                max_mismatches: self.max_mismatches,
+            });
+        }
+
+        if let Some(band) = self.band {
+            if band.min_offset > band.max_offset {
+                return Err(MatchTableError::InvalidBand {
+                    min_offset: band.min_offset,
+                    max_offset: band.max_offset,
+                });
+            }
+        }
+
+        if self.minimizer_window == Some(0) {
+            return Err(MatchTableError::InvalidMinimizerWindow);
+        }
+
+        if self.max_kmer_occurrences == Some(0) {
+            return Err(MatchTableError::InvalidMaxKmerOccurrences);
+        }
+
+        if let Some(maximum_length) = self.maximum_length {
+            if maximum_length < self.minimum_length {
+                return Err(MatchTableError::InvalidMaximumLength {
+                    maximum_length,
+                    minimum_length: self.minimum_length,
+                });
+            }
+        }
+
+        if let Some(filter) = self.low_complexity_filter {
+            let threshold = filter.threshold();
+            if !(threshold.is_finite() && threshold >= 0.0) {
+                return Err(MatchTableError::InvalidLowComplexityThreshold { threshold });
+            }
+        }
+
+        if let Some(false_positive_rate) = self.bloom_false_positive_rate {
+            if !(false_positive_rate > 0.0 && false_positive_rate < 1.0) {
+                return Err(MatchTableError::InvalidFalsePositiveRate {
+                    false_positive_rate,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Validate the options and return the number of kmers in the reference and the query.
+    pub(crate) fn kmer_counts(
+        &self,
+        reference_length: usize,
+        query_length: usize,
+    ) -> Result<(usize, usize), MatchTableError> {
+        self.validate()?;
+        Self::validate_mask("reference", self.reference_mask.as_ref(), reference_length)?;
+        Self::validate_mask("query", self.query_mask.as_ref(), query_length)?;
+        Self::validate_intervals(
+            "reference",
+            &self.reference_excluded_intervals,
+            reference_length,
+        )?;
+        Self::validate_intervals("query", &self.query_excluded_intervals, query_length)?;
+
+        Ok((
+            self.kmer_count("reference", reference_length)?,
+            self.kmer_count("query", query_length)?,
+        ))
+    }
+
+    fn validate_mask(
+        sequence: &'static str,
+        mask: Option<&BitVec>,
+        sequence_length: usize,
+    ) -> Result<(), MatchTableError> {
+        match mask {
+            Some(mask) if mask.len() != sequence_length => {
+                Err(MatchTableError::MaskLengthMismatch {
+                    sequence,
+                    mask_length: mask.len(),
+                    sequence_length,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn validate_intervals(
+        sequence: &'static str,
+        intervals: &[Range<usize>],
+        sequence_length: usize,
+    ) -> Result<(), MatchTableError> {
+        match intervals
+            .iter()
+            .find(|interval| interval.start > interval.end || interval.end > sequence_length)
+        {
+            Some(interval) => Err(MatchTableError::InvalidInterval {
+                sequence,
+                start: interval.start,
+                end: interval.end,
+                sequence_length,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the number of kmers in a sequence of the given length.
+    fn kmer_count(&self, sequence: &'static str, length: usize) -> Result<usize, MatchTableError> {
+        length
+            .checked_sub(self.minimum_length - 1)
+            .filter(|&kmer_count| kmer_count > 0)
+            .ok_or(MatchTableError::SequenceTooShort {
+                sequence,
+                length,
+                minimum_length: self.minimum_length,
+            })
+    }
+}
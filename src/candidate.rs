@@ -0,0 +1,284 @@
+//! Pairing of inner entry points into template switch candidates.
+
+use std::ops::RangeInclusive;
+
+use crate::{MatchTable, Quadrant, StorageIndex};
+
+/// A template switch candidate in the classic four-point model.
+///
+/// The primary genome contains an inner that is the reverse complement of a region of the secondary genome.
+/// The template switch leaves the outer alignment at point 1 of the primary,
+/// jumps to point 2 of the secondary, copies the secondary backwards to point 3,
+/// and returns to the outer alignment at point 4 of the primary.
+///
+/// Points 1 and 4 are positions in the primary genome, and points 2 and 3 are positions on the forward strand of the secondary genome.
+/// The inner occupies `point1..point4` in the primary,
+/// and is the reverse complement of `point3..point2` in the secondary,
+/// so both intervals have the same length.
+///
+/// The outer alignment maps each primary position `p` to the secondary position `p + outer_offset`,
+/// which relates the positions of the primary to those of the secondary for measuring the geometry of the template switch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TemplateSwitchCandidate {
+    /// The quadrant of the primary and secondary genome.
+    pub quadrant: Quadrant,
+    /// The first position of the inner in the primary genome, where the template switch starts.
+    pub point1: usize,
+    /// The exclusive end of the template of the inner on the forward strand of the secondary genome.
+    pub point2: usize,
+    /// The first position of the template of the inner on the forward strand of the secondary genome.
+    pub point3: usize,
+    /// The exclusive end of the inner in the primary genome, where the template switch returns.
+    pub point4: usize,
+}
+
+impl TemplateSwitchCandidate {
+    /// Returns the length of the inner.
+    pub fn inner_length(&self) -> usize {
+        self.point4 - self.point1
+    }
+
+    /// Returns the distance between point 1 and point 2 in secondary coordinates under the given outer alignment.
+    pub fn switch_in_distance(&self, outer_offset: isize) -> usize {
+        (self.point1 as isize + outer_offset).abs_diff(self.point2 as isize)
+    }
+
+    /// Returns the distance between point 3 and point 4 in secondary coordinates under the given outer alignment.
+    pub fn switch_out_distance(&self, outer_offset: isize) -> usize {
+        (self.point4 as isize + outer_offset).abs_diff(self.point3 as isize)
+    }
+
+    /// Returns the offset of the template of the inner from the region of the secondary that the inner replaces under the given outer alignment.
+    ///
+    /// This is `point3 - (point1 + outer_offset)`, which is negative if the template lies upstream.
+    pub fn template_offset(&self, outer_offset: isize) -> isize {
+        self.point3 as isize - (self.point1 as isize + outer_offset)
+    }
+}
+
+/// Constraints on the template switch candidates enumerated by [`MatchTable::candidates`].
+///
+/// # Example
+///
+/// ```rust
+/// use template_switch_error_free_inners::CandidateConstraints;
+///
+/// let constraints = CandidateConstraints::new()
+///     .min_inner_length(10)
+///     .max_inner_length(50)
+///     .switch_distance(0, 200)
+///     .template_offset(-100, 100);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CandidateConstraints {
+    pub(crate) min_inner_length: usize,
+    pub(crate) max_inner_length: Option<usize>,
+    pub(crate) outer_offset: isize,
+    pub(crate) switch_distance: Option<RangeInclusive<usize>>,
+    pub(crate) template_offset: Option<RangeInclusive<isize>>,
+}
+
+impl CandidateConstraints {
+    /// Create constraints that accept all candidates.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the minimum length of the inner.
+    ///
+    /// Inners are never shorter than the minimum length of the match table.
+    pub fn min_inner_length(mut self, min_inner_length: usize) -> Self {
+        self.min_inner_length = min_inner_length;
+        self
+    }
+
+    /// Set the maximum length of the inner.
+    ///
+    /// Without a maximum, a run of `n` consecutive matching kmers yields `n * (n + 1) / 2` candidates.
+    pub fn max_inner_length(mut self, max_inner_length: usize) -> Self {
+        self.max_inner_length = Some(max_inner_length);
+        self
+    }
+
+    /// Set the offset of the outer alignment, which maps each primary position `p` to the secondary position `p + outer_offset`.
+    ///
+    /// The geometric constraints are measured relative to the outer alignment.
+    /// The default of zero suits self-comparisons and globally aligned genomes.
+    pub fn outer_offset(mut self, outer_offset: isize) -> Self {
+        self.outer_offset = outer_offset;
+        self
+    }
+
+    /// Constrain both the distance between point 1 and point 2 and the distance between point 3 and point 4
+    /// to `min_distance..=max_distance`.
+    ///
+    /// See [`TemplateSwitchCandidate::switch_in_distance`] and [`TemplateSwitchCandidate::switch_out_distance`].
+    /// The maximum bounds the length of the loop that the template switch jumps over.
+    pub fn switch_distance(mut self, min_distance: usize, max_distance: usize) -> Self {
+        self.switch_distance = Some(min_distance..=max_distance);
+        self
+    }
+
+    /// Constrain the offset of the template of the inner from the region that the inner replaces
+    /// to `min_offset..=max_offset`.
+    ///
+    /// See [`TemplateSwitchCandidate::template_offset`].
+    pub fn template_offset(mut self, min_offset: isize, max_offset: isize) -> Self {
+        self.template_offset = Some(min_offset..=max_offset);
+        self
+    }
+
+    /// Returns `true` if the candidate satisfies the constraints.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use template_switch_error_free_inners::{
+    ///     CandidateConstraints, Quadrant, TemplateSwitchCandidate,
+    /// };
+    ///
+    /// let candidate = TemplateSwitchCandidate {
+    ///     quadrant: Quadrant::ReferenceReference,
+    ///     point1: 100,
+    ///     point2: 130,
+    ///     point3: 120,
+    ///     point4: 110,
+    /// };
+    /// assert_eq!(candidate.switch_in_distance(0), 30);
+    /// assert_eq!(candidate.switch_out_distance(0), 10);
+    /// assert_eq!(candidate.template_offset(0), 20);
+    ///
+    /// assert!(CandidateConstraints::new().switch_distance(5, 30).accepts(&candidate));
+    /// assert!(!CandidateConstraints::new().switch_distance(5, 20).accepts(&candidate));
+    /// assert!(!CandidateConstraints::new()
+    ///     .outer_offset(25)
+    ///     .template_offset(0, 100)
+    ///     .accepts(&candidate));
+    /// ```
+    pub fn accepts(&self, candidate: &TemplateSwitchCandidate) -> bool {
+        let inner_length = candidate.inner_length();
+        inner_length >= self.min_inner_length
+            && self
+                .max_inner_length
+                .is_none_or(|max_inner_length| inner_length <= max_inner_length)
+            && self.switch_distance.as_ref().is_none_or(|switch_distance| {
+                switch_distance.contains(&candidate.switch_in_distance(self.outer_offset))
+                    && switch_distance.contains(&candidate.switch_out_distance(self.outer_offset))
+            })
+            && self.template_offset.as_ref().is_none_or(|template_offset| {
+                template_offset.contains(&candidate.template_offset(self.outer_offset))
+            })
+    }
+}
+
+impl<Index: StorageIndex> MatchTable<Index> {
+    /// Returns an iterator over the template switch candidates of the given quadrant that satisfy the constraints.
+    ///
+    /// Each match is a switch-in point, and is paired with each switch-out point on the same diagonal,
+    /// such that all kmers in between match as well.
+    /// The switch-in match `(primary_index, secondary_rc_index)` yields points 1 and 2,
+    /// and the switch-out match `(primary_index + offset, secondary_rc_index + offset)` yields points 3 and 4.
+    /// If the table allows no mismatches, then all inners are error-free.
+    ///
+    /// Only the candidates [accepted](CandidateConstraints::accepts) by the constraints are returned.
+    /// Switch-in matches that violate the distance between point 1 and point 2 are skipped without pairing them.
+    ///
+    /// The candidates are returned ordered by their switch-in match as in [`matches`](Self::matches),
+    /// and then by increasing inner length.
+    /// In the self-comparison quadrants, each inverted repeat is reported twice,
+    /// once with each arm as the inner.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{
+    ///     CandidateConstraints, MatchTable, Quadrant, TemplateSwitchCandidate,
+    /// };
+    ///
+    /// // The reverse complement of `GGTCA` is `TGACC`.
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGTCAAAAATGACCA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AAAA").unwrap();
+    /// let matches = MatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    ///
+    /// let candidates: Vec<_> = matches
+    ///     .candidates(
+    ///         Quadrant::ReferenceReference,
+    ///         &CandidateConstraints::new().min_inner_length(5),
+    ///     )
+    ///     .collect();
+    /// assert_eq!(
+    ///     candidates[0],
+    ///     TemplateSwitchCandidate {
+    ///         quadrant: Quadrant::ReferenceReference,
+    ///         point1: 1,
+    ///         point2: 15,
+    ///         point3: 10,
+    ///         point4: 6,
+    ///     },
+    /// );
+    /// assert_eq!(candidates.len(), 2);
+    /// ```
+    pub fn candidates<'table>(
+        &'table self,
+        quadrant: Quadrant,
+        constraints: &CandidateConstraints,
+    ) -> impl Iterator<Item = TemplateSwitchCandidate> + 'table {
+        let primary_kmer_count = self.primary_kmer_count(quadrant);
+        let secondary_kmer_count = self.secondary_kmer_count(quadrant);
+        let minimum_length = self.minimum_length;
+        let min_offset = constraints.min_inner_length.saturating_sub(minimum_length);
+        let max_offset = constraints
+            .max_inner_length
+            .map_or(usize::MAX, |max_inner_length| {
+                max_inner_length.saturating_sub(minimum_length)
+            });
+        let is_satisfiable = constraints
+            .max_inner_length
+            .is_none_or(|max_inner_length| max_inner_length >= minimum_length);
+
+        let constraints = constraints.clone();
+        let switch_in_distance = constraints.switch_distance.clone();
+        let outer_offset = constraints.outer_offset;
+
+        self.matches(quadrant)
+            .filter(move |_| is_satisfiable)
+            .filter(move |&(primary_index, secondary_rc_index)| {
+                switch_in_distance.as_ref().is_none_or(|switch_distance| {
+                    let point2 = self.secondary_forward_end(quadrant, secondary_rc_index);
+                    switch_distance.contains(
+                        &(primary_index as isize + outer_offset).abs_diff(point2 as isize),
+                    )
+                })
+            })
+            .flat_map(move |(primary_index, secondary_rc_index)| {
+                (0..=max_offset)
+                    .take_while(move |&offset| {
+                        offset == 0
+                            || (primary_index + offset < primary_kmer_count
+                                && secondary_rc_index + offset < secondary_kmer_count
+                                && self.has_match(
+                                    quadrant,
+                                    primary_index + offset,
+                                    secondary_rc_index + offset,
+                                ))
+                    })
+                    .skip(min_offset)
+                    .map(move |offset| TemplateSwitchCandidate {
+                        quadrant,
+                        point1: primary_index,
+                        point2: self.secondary_forward_end(quadrant, secondary_rc_index),
+                        point3: self.secondary_forward_end(quadrant, secondary_rc_index + offset)
+                            - minimum_length,
+                        point4: primary_index + offset + minimum_length,
+                    })
+            })
+            .filter(move |candidate| constraints.accepts(candidate))
+    }
+}
@@ -0,0 +1,228 @@
+//! Co-linear chaining of matches into longer inners that may contain mismatches and small indels.
+
+use std::{cmp::Reverse, ops::Range};
+
+use crate::{MatchTable, Quadrant, StorageIndex};
+
+/// Parameters of the chaining of matches by [`MatchTable::chains`].
+///
+/// # Example
+///
+/// ```rust
+/// use template_switch_error_free_inners::ChainingParameters;
+///
+/// let parameters = ChainingParameters::new()
+///     .max_gap(100)
+///     .max_diagonal_shift(4)
+///     .diagonal_shift_cost(2)
+///     .min_score(30);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ChainingParameters {
+    pub(crate) max_gap: usize,
+    pub(crate) max_diagonal_shift: usize,
+    pub(crate) diagonal_shift_cost: usize,
+    pub(crate) max_predecessors: usize,
+    pub(crate) min_score: isize,
+}
+
+impl Default for ChainingParameters {
+    fn default() -> Self {
+        Self {
+            max_gap: 50,
+            max_diagonal_shift: 8,
+            diagonal_shift_cost: 1,
+            max_predecessors: 50,
+            min_score: 0,
+        }
+    }
+}
+
+impl ChainingParameters {
+    /// Create the default parameters, which chain matches up to 50 characters apart and up to 8 diagonals apart,
+    /// at a cost of 1 per diagonal, and report all chains.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum distance between the starts of consecutive matches of a chain, both in the primary and in the secondary.
+    pub fn max_gap(mut self, max_gap: usize) -> Self {
+        self.max_gap = max_gap;
+        self
+    }
+
+    /// Set the maximum difference between the diagonals of consecutive matches of a chain, i.e. the maximum length of an indel.
+    ///
+    /// With zero, only matches on the same diagonal are chained, which bridges mismatches but no indels.
+    pub fn max_diagonal_shift(mut self, max_diagonal_shift: usize) -> Self {
+        self.max_diagonal_shift = max_diagonal_shift;
+        self
+    }
+
+    /// Set the cost per diagonal of the difference between the diagonals of consecutive matches of a chain.
+    pub fn diagonal_shift_cost(mut self, diagonal_shift_cost: usize) -> Self {
+        self.diagonal_shift_cost = diagonal_shift_cost;
+        self
+    }
+
+    /// Set the maximum number of preceding matches that are tried as predecessors of each match.
+    ///
+    /// This bounds the time of the chaining in dense regions of the table, at the risk of missing the best predecessor.
+    pub fn max_predecessors(mut self, max_predecessors: usize) -> Self {
+        self.max_predecessors = max_predecessors;
+        self
+    }
+
+    /// Set the minimum score of the reported chains.
+    pub fn min_score(mut self, min_score: isize) -> Self {
+        self.min_score = min_score;
+        self
+    }
+}
+
+/// A co-linear chain of matches, see [`MatchTable::chains`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MatchChain {
+    /// The quadrant of the matches.
+    pub quadrant: Quadrant,
+    /// The matches of the chain as `(primary_index, secondary_rc_index)` pairs, ordered by increasing indexes.
+    pub anchors: Vec<(usize, usize)>,
+    /// The score of the chain, see [`MatchTable::chains`].
+    pub score: isize,
+    /// The interval of the primary from the start of the first kmer to the end of the last kmer of the chain.
+    pub primary: Range<usize>,
+    /// The interval of the reverse-complemented secondary from the start of the first kmer to the end of the last kmer of the chain.
+    ///
+    /// The forward-strand positions can be obtained with [`MatchTable::secondary_forward_end`].
+    pub secondary_rc: Range<usize>,
+}
+
+impl<Index: StorageIndex> MatchTable<Index> {
+    /// Group the matches of the given quadrant into co-linear chains, and return the chains ordered by decreasing score.
+    ///
+    /// Along an inner, the primary index and the secondary rc index increase together,
+    /// so an inner is an anti-diagonal on the forward strand of the secondary, and a diagonal of the quadrant.
+    /// A match can follow another match in a chain if both of its indexes are larger,
+    /// they are at most [`max_gap`](ChainingParameters::max_gap) larger,
+    /// and the diagonals differ by at most [`max_diagonal_shift`](ChainingParameters::max_diagonal_shift).
+    ///
+    /// The chains are computed with the gap-cost chaining dynamic program:
+    /// a chain scores the number of characters covered by its kmers,
+    /// minus the [diagonal shift cost](ChainingParameters::diagonal_shift_cost) times the difference of the diagonals of each pair of consecutive matches.
+    /// So a run of consecutive matches along a diagonal scores the length of its inner.
+    /// The chains are extracted greedily from the highest-scoring end, and each match is part of at most one chain.
+    /// A chain that would continue into a match of a higher-scoring chain is cut off there,
+    /// and is scored without the shared part.
+    /// Chains with equal scores are ordered by their first match.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{ChainingParameters, MatchTable, Quadrant};
+    ///
+    /// // The query contains the reverse complement of the reference with a mismatch in the middle.
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"ACGTTGCAAGTCCTGA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"TCAGGACTAGCAACGT").unwrap();
+    /// let matches = MatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    /// assert_eq!(matches.maximal_matches(Quadrant::ReferenceQuery).count(), 2);
+    ///
+    /// let chains = matches.chains(Quadrant::ReferenceQuery, &ChainingParameters::new().min_score(10));
+    /// assert_eq!(chains.len(), 1);
+    /// assert_eq!(chains[0].primary, 0..16);
+    /// assert_eq!(chains[0].secondary_rc, 0..16);
+    /// assert_eq!(chains[0].score, 15);
+    /// ```
+    pub fn chains(&self, quadrant: Quadrant, parameters: &ChainingParameters) -> Vec<MatchChain> {
+        let anchors: Vec<_> = self.matches(quadrant).collect();
+        let minimum_length = self.minimum_length;
+
+        // The best score of a chain ending in each anchor, and the predecessor of the anchor in that chain.
+        let mut scores = Vec::with_capacity(anchors.len());
+        let mut predecessors = Vec::with_capacity(anchors.len());
+        for (index, &(primary_index, secondary_rc_index)) in anchors.iter().enumerate() {
+            let mut best = (minimum_length as isize, None);
+            let mut tried = 0;
+            for predecessor in (0..index).rev() {
+                let (predecessor_primary, predecessor_secondary_rc) = anchors[predecessor];
+                if primary_index - predecessor_primary > parameters.max_gap
+                    || tried == parameters.max_predecessors
+                {
+                    break;
+                }
+                if predecessor_primary == primary_index
+                    || predecessor_secondary_rc >= secondary_rc_index
+                    || secondary_rc_index - predecessor_secondary_rc > parameters.max_gap
+                {
+                    continue;
+                }
+                tried += 1;
+
+                let primary_distance = primary_index - predecessor_primary;
+                let secondary_distance = secondary_rc_index - predecessor_secondary_rc;
+                let diagonal_shift = primary_distance.abs_diff(secondary_distance);
+                if diagonal_shift > parameters.max_diagonal_shift {
+                    continue;
+                }
+                let covered = primary_distance.min(secondary_distance).min(minimum_length);
+                let score = scores[predecessor] + covered as isize
+                    - (diagonal_shift * parameters.diagonal_shift_cost) as isize;
+                if score > best.0 {
+                    best = (score, Some(predecessor));
+                }
+            }
+            scores.push(best.0);
+            predecessors.push(best.1);
+        }
+
+        let mut ends: Vec<_> = (0..anchors.len()).collect();
+        ends.sort_by_key(|&index| (Reverse(scores[index]), index));
+        let mut is_used = vec![false; anchors.len()];
+        let mut chains = Vec::new();
+        for end in ends {
+            if is_used[end] {
+                continue;
+            }
+
+            let mut chain = Vec::new();
+            let mut current = Some(end);
+            let mut shared_score = 0;
+            while let Some(index) = current {
+                if is_used[index] {
+                    shared_score = scores[index];
+                    break;
+                }
+                is_used[index] = true;
+                chain.push(anchors[index]);
+                current = predecessors[index];
+            }
+            chain.reverse();
+
+            let score = scores[end] - shared_score;
+            if score >= parameters.min_score {
+                let (first_primary, first_secondary_rc) = chain[0];
+                let (last_primary, last_secondary_rc) = chain[chain.len() - 1];
+                chains.push(MatchChain {
+                    quadrant,
+                    primary: first_primary..last_primary + minimum_length,
+                    secondary_rc: first_secondary_rc..last_secondary_rc + minimum_length,
+                    anchors: chain,
+                    score,
+                });
+            }
+        }
+
+        chains.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then_with(|| a.anchors[0].cmp(&b.anchors[0]))
+        });
+        chains
+    }
+}
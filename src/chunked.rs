@@ -0,0 +1,224 @@
+//! Construction in chunks of primary kmers within a memory budget.
+
+use std::ops::Range;
+
+use compact_genome::interface::{alphabet::Alphabet, sequence::GenomeSequence};
+use log::debug;
+
+use crate::{
+    ContigLayout, Match, MatchTableBuilder, MatchTableError, ProgressEvent, Quadrant, StorageIndex,
+    bloom::BloomFilter,
+    construction::{PrimaryChunk, QuadrantSinks, Texts, find_flagged_matches},
+    index::NoIndex,
+    mask::KmerFlags,
+};
+
+/// The matches of the kmers in a chunk of a primary sequence, see [`MatchTableBuilder::try_for_each_chunk`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchChunk {
+    /// If the primary sequence of the chunk is the reference, otherwise it is the query.
+    pub primary_is_reference: bool,
+    /// The indices of the primary kmers of the chunk.
+    pub primary_kmers: Range<usize>,
+    /// The matches whose primary kmer lies in the chunk,
+    /// ordered by quadrant in the order of [`Quadrant::ALL`], then by primary index and then by secondary rc index.
+    pub matches: Vec<Match>,
+}
+
+impl<Index: StorageIndex> MatchTableBuilder<Index> {
+    /// Compute the matches of the given reference and query in chunks of primary kmers, such that construction stays within `memory_budget` bytes.
+    ///
+    /// The sequences and the flags of their kmers are kept in memory throughout,
+    /// but only the primary kmers of one chunk are indexed at a time.
+    /// The chunks are as large as the budget allows according to the estimates of [`estimate_memory`](Self::estimate_memory).
+    /// The reference chunks come first and contain the matches of the quadrants with the reference as primary sequence,
+    /// followed by the query chunks.
+    /// Each chunk is passed to `on_chunk`, which may e.g. write its matches to disk, before the next chunk is computed.
+    /// The matches of a chunk are held in memory until `on_chunk` returns and are not counted against the budget.
+    ///
+    /// All secondary kmers are compared against each chunk, so construction takes longer the more chunks there are.
+    /// The matches are the same as those of a table built with the same options, and the configured storage backend is ignored.
+    ///
+    /// Returns an error under the same conditions as [`try_build`](Self::try_build),
+    /// if the budget does not suffice for a chunk of a single kmer, or if `on_chunk` returns an error.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTableBuilder, Quadrant};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&b"AGGGGAACCCCAA".repeat(20)).unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+    ///
+    /// let mut chunk_count = 0;
+    /// let mut match_count = 0;
+    /// MatchTableBuilder::new(4)
+    ///     .try_for_each_chunk(
+    ///         reference.as_genome_subsequence(),
+    ///         query.as_genome_subsequence(),
+    ///         1_000,
+    ///         |chunk| {
+    ///             chunk_count += 1;
+    ///             match_count += chunk.matches.len();
+    ///             Ok(())
+    ///         },
+    ///     )
+    ///     .unwrap();
+    ///
+    /// let matches = MatchTableBuilder::new(4)
+    ///     .build(reference.as_genome_subsequence(), query.as_genome_subsequence());
+    /// assert!(chunk_count > 2);
+    /// assert_eq!(
+    ///     match_count,
+    ///     Quadrant::ALL.into_iter().map(|quadrant| matches.matches(quadrant).count()).sum(),
+    /// );
+    /// ```
+    pub fn try_for_each_chunk<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        &self,
+        reference: &GenomeSubsequence,
+        query: &GenomeSubsequence,
+        memory_budget: u64,
+        mut on_chunk: impl FnMut(MatchChunk) -> Result<(), MatchTableError>,
+    ) -> Result<(), MatchTableError> {
+        let (reference_kmer_count, query_kmer_count) =
+            self.kmer_counts(reference.len(), query.len())?;
+        let chunk_kmer_count =
+            self.chunk_kmer_count(reference.len(), query.len(), memory_budget)?;
+        debug!("Computing matches in chunks of {chunk_kmer_count} kmers");
+
+        let texts = Texts::new(reference, query, self);
+        let contigs = [
+            ContigLayout::new([reference.len()]),
+            ContigLayout::new([query.len()]),
+        ];
+        let reference_flags = KmerFlags::new(
+            &texts.reference,
+            texts.frequent_reference_kmers(),
+            self.reference_mask.as_ref(),
+            &self.reference_excluded_intervals,
+            &contigs[0],
+            self,
+        );
+        let query_flags = KmerFlags::new(
+            &texts.query,
+            texts.frequent_query_kmers(),
+            self.query_mask.as_ref(),
+            &self.query_excluded_intervals,
+            &contigs[1],
+            self,
+        );
+
+        for (is_reference, kmer_count) in [(true, reference_kmer_count), (false, query_kmer_count)]
+        {
+            let quadrants = Quadrant::ALL.map(|quadrant| {
+                quadrant.primary_is_reference() == is_reference && self.quadrants.contains(quadrant)
+            });
+            if !quadrants.contains(&true) {
+                continue;
+            }
+
+            for start in (0..kmer_count).step_by(chunk_kmer_count) {
+                let chunk = PrimaryChunk {
+                    is_reference,
+                    kmers: start..(start + chunk_kmer_count).min(kmer_count),
+                };
+                let [
+                    mut reference_reference,
+                    mut reference_query,
+                    mut query_reference,
+                    mut query_query,
+                ] = [(); 4].map(|_| Vec::new());
+                find_flagged_matches(
+                    &texts,
+                    [&reference_flags, &query_flags],
+                    self,
+                    Some(&chunk),
+                    None::<[&NoIndex; 2]>,
+                    QuadrantSinks {
+                        reference_reference: &mut reference_reference,
+                        reference_query: &mut reference_query,
+                        query_reference: &mut query_reference,
+                        query_query: &mut query_query,
+                    },
+                );
+                self.check_cancelled()?;
+
+                let mut matches = Vec::new();
+                for (quadrant, mut quadrant_matches) in Quadrant::ALL.into_iter().zip([
+                    reference_reference,
+                    reference_query,
+                    query_reference,
+                    query_query,
+                ]) {
+                    quadrant_matches.sort_unstable();
+                    matches.extend(quadrant_matches.into_iter().map(
+                        |(primary_index, secondary_rc_index)| Match {
+                            quadrant,
+                            primary_index,
+                            secondary_rc_index,
+                        },
+                    ));
+                }
+                on_chunk(MatchChunk {
+                    primary_is_reference: is_reference,
+                    primary_kmers: chunk.kmers,
+                    matches,
+                })?;
+            }
+        }
+
+        self.report(ProgressEvent::Finished);
+        Ok(())
+    }
+
+    /// Returns the largest number of primary kmers per chunk for which construction stays within the memory budget.
+    fn chunk_kmer_count(
+        &self,
+        reference_length: usize,
+        query_length: usize,
+        memory_budget: u64,
+    ) -> Result<usize, MatchTableError> {
+        let fixed_bytes = self
+            .estimate_memory(reference_length, query_length)
+            .text_bytes;
+        let chunk_bytes = |kmer_count: usize| {
+            let mut bytes = fixed_bytes
+                + self
+                    .index_backend
+                    .estimated_bytes(kmer_count + self.minimum_length - 1);
+            if let Some(false_positive_rate) = self.bloom_false_positive_rate {
+                if self.max_mismatches == 0 {
+                    bytes += BloomFilter::estimated_bytes(kmer_count, false_positive_rate);
+                }
+            }
+            bytes
+        };
+
+        let max_kmer_count = (reference_length.max(query_length) + 1)
+            .saturating_sub(self.minimum_length)
+            .max(1);
+        if chunk_bytes(1) > memory_budget {
+            return Err(MatchTableError::MemoryBudgetTooSmall {
+                memory_budget,
+                required_bytes: chunk_bytes(1),
+            });
+        }
+        // The estimate grows with the chunk size, so the largest chunk within the budget is found by binary search.
+        let (mut low, mut high) = (1, max_kmer_count);
+        while low < high {
+            let middle = low + (high - low).div_ceil(2);
+            if chunk_bytes(middle) <= memory_budget {
+                low = middle;
+            } else {
+                high = middle - 1;
+            }
+        }
+        Ok(low)
+    }
+}
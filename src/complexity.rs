@@ -0,0 +1,159 @@
+//! Detection of low-complexity kmers like homopolymers and simple repeats.
+
+use bitvec::vec::BitVec;
+
+/// A filter that excludes low-complexity kmers from matching, see [`MatchTableBuilder::low_complexity_filter`](crate::MatchTableBuilder::low_complexity_filter).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LowComplexityFilter {
+    /// Exclude kmers whose DUST score exceeds `threshold`.
+    ///
+    /// The DUST score of a kmer is `sum(c * (c - 1) / 2) / (l - 1)`,
+    /// where `l = k - 2` is the number of triplets of the kmer and `c` ranges over the occurrence counts of the distinct triplets.
+    /// It is zero if all triplets are distinct, and `l / 2` for a homopolymer.
+    /// Dinucleotide repeats score about half as much as homopolymers, and random sequence scores well below one.
+    /// Kmers shorter than four characters always have score zero.
+    Dust {
+        /// The maximum DUST score of a kmer that is kept.
+        threshold: f64,
+    },
+    /// Exclude kmers whose Shannon entropy of the character distribution in bits is below `min_entropy`.
+    ///
+    /// The entropy is zero for a homopolymer, one for a dinucleotide repeat, and at most two for DNA.
+    Entropy {
+        /// The minimum entropy of a kmer that is kept.
+        min_entropy: f64,
+    },
+}
+
+impl LowComplexityFilter {
+    /// Returns the threshold of the filter.
+    pub(crate) fn threshold(&self) -> f64 {
+        match *self {
+            Self::Dust { threshold } => threshold,
+            Self::Entropy { min_entropy } => min_entropy,
+        }
+    }
+
+    /// Returns a bitvector that marks each low-complexity kmer of `text`.
+    ///
+    /// The scores are computed in a single pass with a sliding window.
+    pub(crate) fn low_complexity_kmers(&self, text: &[u8], kmer_length: usize) -> BitVec {
+        let kmer_count = (text.len() + 1).saturating_sub(kmer_length);
+        match *self {
+            Self::Dust { threshold } => dust_scores(text, kmer_length, kmer_count)
+                .map(|score| score > threshold)
+                .collect(),
+            Self::Entropy { min_entropy } => entropies(text, kmer_length, kmer_count)
+                .map(|entropy| entropy < min_entropy)
+                .collect(),
+        }
+    }
+}
+
+/// The number of distinct character codes of a triplet, see [`character_code`].
+const CODE_COUNT: usize = 5;
+
+/// Maps `ACGT` case-insensitively to `0..4` and all other characters to `4`.
+fn character_code(character: u8) -> usize {
+    match character.to_ascii_uppercase() {
+        b'A' => 0,
+        b'C' => 1,
+        b'G' => 2,
+        b'T' => 3,
+        _ => 4,
+    }
+}
+
+/// Returns the DUST scores of the first `kmer_count` kmers of `text`.
+fn dust_scores(
+    text: &[u8],
+    kmer_length: usize,
+    kmer_count: usize,
+) -> impl Iterator<Item = f64> + '_ {
+    let triplet_count = kmer_length.saturating_sub(2);
+    let triplet = move |start: usize| {
+        text[start..start + 3]
+            .iter()
+            .fold(0, |triplet, &character| {
+                triplet * CODE_COUNT + character_code(character)
+            })
+    };
+
+    let mut counts = [0usize; CODE_COUNT * CODE_COUNT * CODE_COUNT];
+    // The number of pairs of equal triplets, i.e. `sum(c * (c - 1) / 2)`.
+    let mut pair_count = 0;
+    (0..kmer_count).map(move |kmer_index| {
+        if triplet_count < 2 {
+            return 0.0;
+        }
+        if kmer_index == 0 {
+            for start in 0..triplet_count {
+                pair_count += counts[triplet(start)];
+                counts[triplet(start)] += 1;
+            }
+        } else {
+            let removed = triplet(kmer_index - 1);
+            counts[removed] -= 1;
+            pair_count -= counts[removed];
+            let added = triplet(kmer_index + triplet_count - 1);
+            pair_count += counts[added];
+            counts[added] += 1;
+        }
+        pair_count as f64 / (triplet_count - 1) as f64
+    })
+}
+
+/// Returns the Shannon entropies in bits of the first `kmer_count` kmers of `text`.
+fn entropies(text: &[u8], kmer_length: usize, kmer_count: usize) -> impl Iterator<Item = f64> + '_ {
+    let mut counts = CharacterCounts::default();
+    (0..kmer_count).map(move |kmer_index| {
+        if kmer_index == 0 {
+            for &character in &text[..kmer_length] {
+                counts.update(character, 1);
+            }
+        } else {
+            counts.update(text[kmer_index - 1], -1);
+            counts.update(text[kmer_index + kmer_length - 1], 1);
+        }
+        counts.entropy(kmer_length)
+    })
+}
+
+/// The occurrence counts of the characters of a window, ignoring case.
+struct CharacterCounts {
+    counts: [usize; 256],
+    /// The sum of `c * log2(c)` over the counts `c`.
+    weight_sum: f64,
+}
+
+impl Default for CharacterCounts {
+    fn default() -> Self {
+        Self {
+            counts: [0; 256],
+            weight_sum: 0.0,
+        }
+    }
+}
+
+impl CharacterCounts {
+    fn weight(count: usize) -> f64 {
+        if count == 0 {
+            0.0
+        } else {
+            count as f64 * (count as f64).log2()
+        }
+    }
+
+    fn update(&mut self, character: u8, delta: isize) {
+        let count = &mut self.counts[usize::from(character.to_ascii_uppercase())];
+        self.weight_sum -= Self::weight(*count);
+        *count = count.wrapping_add_signed(delta);
+        self.weight_sum += Self::weight(*count);
+    }
+
+    /// Returns the entropy of a window of the given length.
+    fn entropy(&self, length: usize) -> f64 {
+        // Clamp rounding errors of the incrementally updated sum.
+        ((length as f64).log2() - self.weight_sum / length as f64).max(0.0)
+    }
+}
@@ -0,0 +1,1469 @@
+//! The construction of the match table.
+
+use bitvec::vec::BitVec;
+use compact_genome::interface::{
+    alphabet::{Alphabet, AlphabetCharacter},
+    sequence::GenomeSequence,
+};
+use log::debug;
+use suffix::SuffixTable;
+
+use std::{
+    ops::{ControlFlow, Range},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use crate::{
+    ContigLayout, MatchTable, MatchTableBuilder, Quadrant, StorageIndex,
+    band::Band,
+    bloom::BloomFilter,
+    fingerprint::Fingerprint,
+    index::{
+        ConstructionStrategy, FmIndex, HashKmerIndex, IndexBackend, KmerPacker, NoIndex,
+        SequenceIndex, ascii_str,
+    },
+    mask::{AmbiguityPolicy, KmerFlags, frequent_kmers, is_compatible, merge_intervals},
+    progress::{ProgressEvent, ProgressHandle},
+    storage::QuadrantStorageBuilder,
+};
+
+/// The number of reverse-complemented kmers processed by one parallel task.
+#[cfg(feature = "parallel")]
+const PARALLEL_CHUNK_SIZE: usize = 4096;
+
+/// The number of reverse-complemented kmers processed between two progress reports of sequential construction.
+const PROGRESS_INTERVAL: usize = 4096;
+
+/// The longest kmers that are joined by code specialised to their length, see [`find_joined_matches`].
+const MAXIMUM_JOINED_KMER_LENGTH: usize = 32;
+
+/// The orientations in which a primary kmer is compared with a secondary kmer, see [`MatchTableBuilder::orientation`].
+///
+/// Matches are always reported at `(primary_index, secondary_rc_index)`, regardless of their orientation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MatchOrientation {
+    /// A primary kmer matches a secondary kmer if it equals its reverse complement,
+    /// which is the orientation of the inner of a template switch.
+    #[default]
+    ReverseComplement,
+    /// A primary kmer matches a secondary kmer if it equals the secondary kmer itself,
+    /// which is the orientation of a direct repeat like a tandem duplication.
+    Forward,
+    /// A primary kmer matches a secondary kmer if it equals the secondary kmer or its reverse complement,
+    /// i.e. if their canonical kmers are equal.
+    Both,
+}
+
+impl MatchOrientation {
+    /// Returns `true` if kmers are compared with the reverse complement of the secondary kmer.
+    pub(crate) fn reverse_complement(self) -> bool {
+        matches!(self, Self::ReverseComplement | Self::Both)
+    }
+
+    /// Returns `true` if kmers are compared with the forward secondary kmer.
+    pub(crate) fn forward(self) -> bool {
+        matches!(self, Self::Forward | Self::Both)
+    }
+}
+
+impl<Index: StorageIndex> MatchTable<Index> {
+    pub(crate) fn construct<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        reference: &GenomeSubsequence,
+        query: &GenomeSubsequence,
+        contigs: [ContigLayout; 2],
+        options: &MatchTableBuilder<Index>,
+        builders: [QuadrantStorageBuilder<Index>; 4],
+    ) -> Self {
+        let Ok(table) = Self::construct_with(
+            reference,
+            query,
+            contigs,
+            options,
+            builders,
+            |texts, contigs, options, sinks| {
+                Ok::<_, std::convert::Infallible>(find_matches(texts, contigs, options, sinks))
+            },
+        );
+        table
+    }
+
+    /// Like [`construct`](Self::construct), but finding the matches with the given function,
+    /// which returns the number of excluded reference and query kmers like [`find_matches`].
+    pub(crate) fn construct_with<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+        Error,
+    >(
+        reference: &GenomeSubsequence,
+        query: &GenomeSubsequence,
+        [reference_contigs, query_contigs]: [ContigLayout; 2],
+        options: &MatchTableBuilder<Index>,
+        [
+            mut reference_reference,
+            mut reference_query,
+            mut query_reference,
+            mut query_query,
+        ]: [QuadrantStorageBuilder<Index>; 4],
+        find: impl FnOnce(
+            &Texts,
+            [&ContigLayout; 2],
+            &MatchTableBuilder<Index>,
+            QuadrantSinks<QuadrantStorageBuilder<Index>>,
+        ) -> Result<[usize; 2], Error>,
+    ) -> Result<Self, Error> {
+        enter_span!(
+            "construct_match_table",
+            reference_length = reference.len(),
+            query_length = query.len(),
+            minimum_length = options.minimum_length,
+        );
+        let texts = Texts::new(reference, query, options);
+        let reference_kmer_count = texts.reference.len() - options.minimum_length + 1;
+        // The query is empty when comparing the reference against itself.
+        let query_kmer_count = (texts.query.len() + 1).saturating_sub(options.minimum_length);
+
+        let [skipped_reference_kmer_count, skipped_query_kmer_count] = find(
+            &texts,
+            [&reference_contigs, &query_contigs],
+            options,
+            QuadrantSinks {
+                reference_reference: &mut reference_reference,
+                reference_query: &mut reference_query,
+                query_reference: &mut query_reference,
+                query_query: &mut query_query,
+            },
+        )?;
+
+        let table = Self {
+            reference_reference: reference_reference.build(),
+            reference_query: reference_query.build(),
+            query_reference: query_reference.build(),
+            query_query: query_query.build(),
+            reference_kmer_count,
+            query_kmer_count,
+            minimum_length: options.minimum_length,
+            max_mismatches: options.max_mismatches,
+            skipped_reference_kmer_count,
+            skipped_query_kmer_count,
+            max_kmer_occurrences: options.max_kmer_occurrences,
+            unique_matches_only: options.unique_matches_only,
+            maximum_length: options.maximum_length,
+            frequent_reference_kmer_count: texts
+                .frequent_reference_kmers()
+                .map_or(0, |kmers| kmers.count_ones()),
+            frequent_query_kmer_count: texts
+                .frequent_query_kmers()
+                .map_or(0, |kmers| kmers.count_ones()),
+            reference_contigs,
+            query_contigs,
+            band: options.band,
+            quadrants: options.quadrants,
+            reference_excluded_intervals: merge_intervals(&options.reference_excluded_intervals),
+            query_excluded_intervals: merge_intervals(&options.query_excluded_intervals),
+            fingerprint: Some(Fingerprint::new(&texts.reference, &texts.query, options)),
+        };
+        options.report(ProgressEvent::Finished);
+        Ok(table)
+    }
+}
+
+/// The reference and the query as ASCII texts with one byte per character.
+///
+/// Their reverse complements are not stored, but computed on the fly by [`RcText`].
+pub(crate) struct Texts {
+    pub reference: Vec<u8>,
+    pub query: Vec<u8>,
+    /// Maps each character of the alphabet to its complement.
+    pub complement: [u8; 256],
+    /// The kmers of the reference and the query that are excluded by [`MatchTableBuilder::max_kmer_occurrences`]
+    /// or [`MatchTableBuilder::unique_matches_only`].
+    pub frequent_kmers: Option<[BitVec; 2]>,
+}
+
+impl Texts {
+    pub fn new<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        reference: &GenomeSubsequence,
+        query: &GenomeSubsequence,
+        options: &MatchTableBuilder<impl StorageIndex>,
+    ) -> Self {
+        debug!("Converting genomes to ASCII texts");
+        // The characters of an alphabet are ASCII, so each character is a single byte.
+        // Iterators over bit-packed genomes may not report their exact length, so the capacity is reserved up front.
+        let text = |sequence: &GenomeSubsequence| {
+            let mut text = Vec::with_capacity(sequence.len());
+            text.extend(
+                sequence
+                    .iter()
+                    .map(|character| Into::<u8>::into(character.clone())),
+            );
+            text
+        };
+
+        let mut complement = [0; 256];
+        for index in 0..AlphabetType::SIZE {
+            let Ok(character) = AlphabetType::CharacterType::from_index(index) else {
+                unreachable!("all indices below the alphabet size are valid")
+            };
+            let character_byte: u8 = character.clone().into();
+            complement[usize::from(character_byte)] = character.complement().into();
+        }
+
+        let mut texts = Self {
+            reference: text(reference),
+            query: text(query),
+            complement,
+            frequent_kmers: None,
+        };
+        texts.frequent_kmers = texts.find_frequent_kmers(options);
+        texts
+    }
+
+    /// Returns the kmers of the reference and the query that are excluded by [`MatchTableBuilder::max_kmer_occurrences`]
+    /// or [`MatchTableBuilder::unique_matches_only`] under the given options, or `None` if neither option is set.
+    pub fn find_frequent_kmers(
+        &self,
+        options: &MatchTableBuilder<impl StorageIndex>,
+    ) -> Option<[BitVec; 2]> {
+        if options.max_kmer_occurrences.is_none() && !options.unique_matches_only {
+            return None;
+        }
+        debug!("Counting kmer occurrences");
+        let reference_rc: Vec<_> = self.reference_rc().iter().collect();
+        let query_rc: Vec<_> = self.query_rc().iter().collect();
+        frequent_kmers(
+            [&self.reference, &self.query],
+            [&reference_rc, &query_rc],
+            options,
+        )
+    }
+
+    /// Returns the frequent kmers of the reference, see [`frequent_kmers`](Self::frequent_kmers).
+    pub fn frequent_reference_kmers(&self) -> Option<&BitVec> {
+        self.frequent_kmers.as_ref().map(|[reference, _]| reference)
+    }
+
+    /// Returns the frequent kmers of the query, see [`frequent_kmers`](Self::frequent_kmers).
+    pub fn frequent_query_kmers(&self) -> Option<&BitVec> {
+        self.frequent_kmers.as_ref().map(|[_, query]| query)
+    }
+
+    pub fn reference_rc(&self) -> RcText<'_> {
+        RcText::new(&self.reference, &self.complement)
+    }
+
+    pub fn query_rc(&self) -> RcText<'_> {
+        RcText::new(&self.query, &self.complement)
+    }
+}
+
+/// The reverse complement of a text, computed on the fly from the forward text.
+#[derive(Clone, Copy)]
+pub(crate) struct RcText<'text> {
+    forward: &'text [u8],
+    complement: &'text [u8; 256],
+}
+
+impl<'text> RcText<'text> {
+    pub fn new(forward: &'text [u8], complement: &'text [u8; 256]) -> Self {
+        Self {
+            forward,
+            complement,
+        }
+    }
+
+    /// Returns the characters of the reverse complement.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = u8> + ExactSizeIterator + 'text {
+        self.substring(0, self.forward.len())
+    }
+
+    /// Returns the characters of the reverse complement at `start..start + length`.
+    pub fn substring(
+        &self,
+        start: usize,
+        length: usize,
+    ) -> impl DoubleEndedIterator<Item = u8> + ExactSizeIterator + 'text {
+        let end = self.forward.len() - start;
+        let complement = self.complement;
+        self.forward[end - length..end]
+            .iter()
+            .rev()
+            .map(|&character| complement[usize::from(character)])
+    }
+}
+
+/// Returns `true` if the kmers have at most `max_mismatches` mismatches.
+///
+/// Under [`AmbiguityPolicy::Compatible`], characters mismatch if they are incompatible.
+pub(crate) fn kmers_match(
+    primary_kmer: &[u8],
+    rc_kmer: impl IntoIterator<Item = u8>,
+    max_mismatches: usize,
+    ambiguity_policy: AmbiguityPolicy,
+) -> bool {
+    primary_kmer
+        .iter()
+        .zip(rc_kmer)
+        .filter(|&(&a, b)| match ambiguity_policy {
+            AmbiguityPolicy::Compatible => !is_compatible(a, b),
+            AmbiguityPolicy::Literal | AmbiguityPolicy::NeverMatch => a != b,
+        })
+        .count()
+        <= max_mismatches
+}
+
+/// Receives the matches of a quadrant as `(primary_index, secondary_rc_index)` pairs.
+pub(crate) trait MatchSink {
+    fn insert(&mut self, primary_index: usize, secondary_rc_index: usize);
+
+    /// Returns `true` if the sink accepts no more matches, which stops the search.
+    fn is_closed(&self) -> bool {
+        false
+    }
+}
+
+impl<Index: StorageIndex> MatchSink for QuadrantStorageBuilder<Index> {
+    fn insert(&mut self, primary_index: usize, secondary_rc_index: usize) {
+        QuadrantStorageBuilder::insert(self, primary_index, secondary_rc_index);
+    }
+}
+
+impl MatchSink for Vec<(usize, usize)> {
+    fn insert(&mut self, primary_index: usize, secondary_rc_index: usize) {
+        self.push((primary_index, secondary_rc_index));
+    }
+}
+
+/// Shifts the primary indices of the matches inserted into a sink by a fixed offset.
+struct OffsetSink<'sink, Sink> {
+    sink: &'sink mut Sink,
+    offset: usize,
+}
+
+impl<'sink, Sink> OffsetSink<'sink, Sink> {
+    fn new(sink: &'sink mut Sink, offset: usize) -> Self {
+        Self { sink, offset }
+    }
+}
+
+impl<Sink: MatchSink> MatchSink for OffsetSink<'_, Sink> {
+    fn insert(&mut self, primary_index: usize, secondary_rc_index: usize) {
+        self.sink
+            .insert(primary_index + self.offset, secondary_rc_index);
+    }
+
+    fn is_closed(&self) -> bool {
+        self.sink.is_closed()
+    }
+}
+
+/// Mutable references to the sinks of all four quadrants.
+pub(crate) struct QuadrantSinks<'sinks, Sink> {
+    pub reference_reference: &'sinks mut Sink,
+    pub reference_query: &'sinks mut Sink,
+    pub query_reference: &'sinks mut Sink,
+    pub query_query: &'sinks mut Sink,
+}
+
+/// Find all matches between the texts and insert them into the sinks of their quadrants.
+///
+/// Returns the number of excluded reference and query kmers.
+pub(crate) fn find_matches(
+    texts: &Texts,
+    contigs: [&ContigLayout; 2],
+    options: &MatchTableBuilder<impl StorageIndex>,
+    sinks: QuadrantSinks<impl MatchSink>,
+) -> [usize; 2] {
+    find_indexed_matches(texts, contigs, options, None::<[&NoIndex; 2]>, sinks)
+}
+
+/// Like [`find_matches`], but looking up the kmers in the given indexes of the reference and the query
+/// if the strategy resolves to [`ConstructionStrategy::IndexLookup`].
+pub(crate) fn find_indexed_matches(
+    texts: &Texts,
+    [reference_contigs, query_contigs]: [&ContigLayout; 2],
+    options: &MatchTableBuilder<impl StorageIndex>,
+    indexes: Option<[&impl SequenceIndex; 2]>,
+    sinks: QuadrantSinks<impl MatchSink>,
+) -> [usize; 2] {
+    let Texts {
+        reference, query, ..
+    } = texts;
+
+    let reference_flags = KmerFlags::new(
+        reference,
+        texts.frequent_reference_kmers(),
+        options.reference_mask.as_ref(),
+        &options.reference_excluded_intervals,
+        reference_contigs,
+        options,
+    );
+    let query_flags = KmerFlags::new(
+        query,
+        texts.frequent_query_kmers(),
+        options.query_mask.as_ref(),
+        &options.query_excluded_intervals,
+        query_contigs,
+        options,
+    );
+    debug!(
+        "Excluded {} reference kmers and {} query kmers",
+        reference_flags.excluded_kmer_count(),
+        query_flags.excluded_kmer_count()
+    );
+    find_flagged_matches(
+        texts,
+        [&reference_flags, &query_flags],
+        options,
+        None,
+        indexes,
+        sinks,
+    );
+
+    [
+        reference_flags.excluded_kmer_count(),
+        query_flags.excluded_kmer_count(),
+    ]
+}
+
+/// A range of the kmers of a primary sequence, see [`find_flagged_matches`].
+#[derive(Debug, Clone)]
+pub(crate) struct PrimaryChunk {
+    /// If the primary sequence is the reference, otherwise it is the query.
+    pub is_reference: bool,
+    /// The indices of the primary kmers of the chunk.
+    pub kmers: Range<usize>,
+}
+
+/// Find all matches between the texts with the given kmer flags and insert them into the sinks of their quadrants.
+///
+/// If a chunk is given, then only the matches of its primary kmers are found, and only the chunk is indexed.
+/// Otherwise, prebuilt indexes of the whole texts may be given, which are used instead of building new ones
+/// if the strategy resolves to [`ConstructionStrategy::IndexLookup`].
+pub(crate) fn find_flagged_matches<Index: StorageIndex>(
+    texts: &Texts,
+    [reference_flags, query_flags]: [&KmerFlags; 2],
+    options: &MatchTableBuilder<Index>,
+    chunk: Option<&PrimaryChunk>,
+    indexes: Option<[&impl SequenceIndex; 2]>,
+    sinks: QuadrantSinks<impl MatchSink>,
+) {
+    let MatchTableBuilder {
+        minimum_length,
+        max_mismatches,
+        index_backend,
+        strategy,
+        ..
+    } = *options;
+    let Texts {
+        reference, query, ..
+    } = texts;
+
+    let strategy = strategy.resolve::<Index>(
+        reference,
+        query,
+        minimum_length,
+        max_mismatches,
+        options.band,
+    );
+    let reference_rc = RcKmers::new(
+        reference,
+        texts.reference_rc(),
+        "reference",
+        [Quadrant::ReferenceReference, Quadrant::QueryReference],
+        reference_flags,
+        strategy,
+        options,
+    );
+    let query_rc = RcKmers::new(
+        query,
+        texts.query_rc(),
+        "query",
+        [Quadrant::ReferenceQuery, Quadrant::QueryQuery],
+        query_flags,
+        strategy,
+        options,
+    );
+    // Only build the index of a sequence if it is the primary sequence of a computed quadrant.
+    let [reference_is_primary, query_is_primary] = [
+        [Quadrant::ReferenceReference, Quadrant::ReferenceQuery],
+        [Quadrant::QueryReference, Quadrant::QueryQuery],
+    ]
+    .map(|quadrants| {
+        quadrants
+            .into_iter()
+            .any(|quadrant| options.quadrants.contains(quadrant))
+    });
+    let [reference_is_primary, query_is_primary] = match chunk {
+        Some(chunk) => [
+            reference_is_primary && chunk.is_reference,
+            query_is_primary && !chunk.is_reference,
+        ],
+        None => [reference_is_primary, query_is_primary],
+    };
+
+    // Restrict the primary sequence to the chunk, and shift the indices of its kmers accordingly.
+    let offset = chunk.map_or(0, |chunk| chunk.kmers.start);
+    let mut reference_rc = reference_rc;
+    let mut query_rc = query_rc;
+    for rc in [&mut reference_rc, &mut query_rc] {
+        rc.computed_quadrants[0] &= reference_is_primary;
+        rc.computed_quadrants[1] &= query_is_primary;
+        rc.band = rc.band.map(|band| band.shifted(offset));
+    }
+    let alphabet_texts = [reference.as_slice(), query.as_slice()];
+    let window_flags;
+    let (reference, reference_flags, query, query_flags) = match chunk {
+        Some(chunk) => {
+            let window = chunk.kmers.start..chunk.kmers.end + minimum_length - 1;
+            if chunk.is_reference {
+                window_flags = reference_flags.window(chunk.kmers.clone());
+                (
+                    &reference[window],
+                    &window_flags,
+                    query.as_slice(),
+                    query_flags,
+                )
+            } else {
+                window_flags = query_flags.window(chunk.kmers.clone());
+                (
+                    reference.as_slice(),
+                    reference_flags,
+                    &query[window],
+                    &window_flags,
+                )
+            }
+        }
+        None => (
+            reference.as_slice(),
+            reference_flags,
+            query.as_slice(),
+            query_flags,
+        ),
+    };
+    let mut sinks = QuadrantSinks {
+        reference_reference: &mut OffsetSink::new(sinks.reference_reference, offset),
+        reference_query: &mut OffsetSink::new(sinks.reference_query, offset),
+        query_reference: &mut OffsetSink::new(sinks.query_reference, offset),
+        query_query: &mut OffsetSink::new(sinks.query_query, offset),
+    };
+
+    // Join the packed kmers directly if only error-free matches in reverse-complement orientation
+    // between kmers that are not ambiguous are found.
+    let joins_packed_kmers = strategy == ConstructionStrategy::HashJoin
+        && minimum_length <= MAXIMUM_JOINED_KMER_LENGTH
+        && !options.orientation.forward()
+        && reference_flags.ambiguous_kmers.is_empty()
+        && query_flags.ambiguous_kmers.is_empty();
+
+    let bloom_filter = |is_primary: bool, text: &[u8], flags: &KmerFlags| {
+        let false_positive_rate = options.bloom_false_positive_rate.filter(|_| {
+            is_primary
+                && max_mismatches == 0
+                && strategy != ConstructionStrategy::BandScan
+                && !joins_packed_kmers
+        })?;
+        debug!("Computing Bloom filter");
+        Some(BloomFilter::new(
+            text,
+            &flags.excluded,
+            minimum_length,
+            false_positive_rate,
+        ))
+    };
+    let reference_bloom = bloom_filter(reference_is_primary, reference, reference_flags);
+    let query_bloom = bloom_filter(query_is_primary, query, query_flags);
+
+    debug_assert!(chunk.is_none() || indexes.is_none());
+    match (strategy, index_backend, indexes) {
+        (ConstructionStrategy::IndexLookup, _, Some([reference_index, query_index])) => {
+            debug!("Reusing the prebuilt indexes");
+            let reference = Primary::new(
+                reference,
+                reference_is_primary.then_some(reference_index),
+                reference_flags,
+                reference_bloom,
+            );
+            let query = Primary::new(
+                query,
+                query_is_primary.then_some(query_index),
+                query_flags,
+                query_bloom,
+            );
+            find_all_matches(&reference, &query, &reference_rc, &query_rc, &mut sinks);
+        }
+        (ConstructionStrategy::BandScan, _, _) => {
+            debug!("Scanning the band");
+            let reference = Primary::new(reference, NoIndex, reference_flags, None);
+            let query = Primary::new(query, NoIndex, query_flags, None);
+            find_all_matches(&reference, &query, &reference_rc, &query_rc, &mut sinks);
+        }
+        (ConstructionStrategy::SortMerge, _, _)
+            if !options.orientation.forward()
+                && reference_flags.ambiguous_kmers.is_empty()
+                && query_flags.ambiguous_kmers.is_empty() =>
+        {
+            debug!("Sorting packed kmers");
+            let packer = KmerPacker::new(alphabet_texts, minimum_length);
+            let reference = Primary::new(reference, NoIndex, reference_flags, None);
+            let query = Primary::new(query, NoIndex, query_flags, None);
+            let (reference_kmers, query_kmers) = {
+                enter_span!("build_indexes", backend = "sort_merge");
+                let sorted_kmers = |primary: &Primary<NoIndex>, is_primary: bool| {
+                    if !is_primary {
+                        return Vec::new();
+                    }
+                    reference_rc.sort_kmers::<Index>(
+                        packer.pack_all(primary.text.iter().copied()),
+                        |kmer_index| primary.flags.excluded[kmer_index],
+                    )
+                };
+                (
+                    sorted_kmers(&reference, reference_is_primary),
+                    sorted_kmers(&query, query_is_primary),
+                )
+            };
+
+            debug!("Merging sorted kmers");
+            reference_rc.report(ProgressEvent::IndexesBuilt);
+            let primaries = [
+                (&reference, &reference_kmers[..]),
+                (&query, &query_kmers[..]),
+            ];
+            reference_rc.find_merged_matches(
+                &packer,
+                primaries,
+                sinks.reference_reference,
+                sinks.query_reference,
+            );
+            query_rc.find_merged_matches(
+                &packer,
+                primaries,
+                sinks.reference_query,
+                sinks.query_query,
+            );
+        }
+        (ConstructionStrategy::HashJoin, _, _) if joins_packed_kmers => {
+            debug!("Joining packed kmers");
+            let packer = KmerPacker::new(alphabet_texts, minimum_length);
+            let primaries = [
+                (reference, reference_flags, reference_is_primary),
+                (query, query_flags, query_is_primary),
+            ];
+            macro_rules! find_joined_matches {
+                ($($length:literal)*) => {
+                    match minimum_length {
+                        $($length => find_joined_matches::<$length, Index, _>(
+                            &packer,
+                            primaries,
+                            &reference_rc,
+                            &query_rc,
+                            &mut sinks,
+                        ),)*
+                        _ => unreachable!(),
+                    }
+                };
+            }
+            find_joined_matches!(
+                1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31 32
+            );
+        }
+        (ConstructionStrategy::HashJoin | ConstructionStrategy::SortMerge, _, _) => {
+            debug!("Computing hash indexes");
+            let (reference, query) = {
+                enter_span!("build_indexes", backend = "hash");
+                let packer = KmerPacker::new(alphabet_texts, minimum_length);
+                let reference = Primary::new(
+                    reference,
+                    reference_is_primary.then(|| {
+                        HashKmerIndex::<Index>::new(
+                            reference,
+                            &reference_flags.excluded,
+                            packer.clone(),
+                        )
+                    }),
+                    reference_flags,
+                    reference_bloom,
+                );
+                let query = Primary::new(
+                    query,
+                    query_is_primary
+                        .then(|| HashKmerIndex::<Index>::new(query, &query_flags.excluded, packer)),
+                    query_flags,
+                    query_bloom,
+                );
+                (reference, query)
+            };
+            find_all_matches(&reference, &query, &reference_rc, &query_rc, &mut sinks);
+        }
+        (_, IndexBackend::SuffixTable, _) => {
+            debug!("Computing suffix table indexes");
+            let (reference, query) = {
+                enter_span!("build_indexes", backend = "suffix_table");
+                let reference = Primary::new(
+                    reference,
+                    reference_is_primary.then(|| SuffixTable::new(ascii_str(reference))),
+                    reference_flags,
+                    reference_bloom,
+                );
+                let query = Primary::new(
+                    query,
+                    query_is_primary.then(|| SuffixTable::new(ascii_str(query))),
+                    query_flags,
+                    query_bloom,
+                );
+                (reference, query)
+            };
+            find_all_matches(&reference, &query, &reference_rc, &query_rc, &mut sinks);
+        }
+        (_, IndexBackend::FmIndex, _) => {
+            debug!("Computing FM-indexes");
+            let (reference, query) = {
+                enter_span!("build_indexes", backend = "fm_index");
+                let reference = Primary::new(
+                    reference,
+                    reference_is_primary.then(|| FmIndex::new(reference)),
+                    reference_flags,
+                    reference_bloom,
+                );
+                let query = Primary::new(
+                    query,
+                    query_is_primary.then(|| FmIndex::new(query)),
+                    query_flags,
+                    query_bloom,
+                );
+                (reference, query)
+            };
+            find_all_matches(&reference, &query, &reference_rc, &query_rc, &mut sinks);
+        }
+    }
+}
+
+/// Find all matches by joining the packed reverse-complemented kmers with hash indexes of the packed primary kmers,
+/// see [`ConstructionStrategy::HashJoin`].
+///
+/// This is monomorphised for each kmer length of up to [`MAXIMUM_JOINED_KMER_LENGTH`],
+/// such that the kmers are packed by code specialised to their length.
+fn find_joined_matches<const KMER_LENGTH: usize, Index: StorageIndex, Sink: MatchSink>(
+    packer: &KmerPacker,
+    primaries: [(&[u8], &KmerFlags, bool); 2],
+    reference_rc: &RcKmers,
+    query_rc: &RcKmers,
+    sinks: &mut QuadrantSinks<Sink>,
+) {
+    debug!("Computing hash indexes");
+    let [reference, query] = {
+        enter_span!("build_indexes", backend = "hash");
+        primaries.map(|(text, flags, is_primary)| {
+            let index = is_primary.then(|| {
+                HashKmerIndex::<Index>::from_packed_kmers(
+                    packer.pack_all_fixed::<KMER_LENGTH>(text.iter().copied()),
+                    &flags.excluded,
+                    packer.clone(),
+                )
+            });
+            Primary::new(text, index, flags, None)
+        })
+    };
+
+    debug!("Finding matches");
+    reference_rc.report(ProgressEvent::IndexesBuilt);
+    reference_rc.find_joined_matches::<KMER_LENGTH>(
+        packer,
+        [&reference, &query],
+        sinks.reference_reference,
+        sinks.query_reference,
+    );
+    query_rc.find_joined_matches::<KMER_LENGTH>(
+        packer,
+        [&reference, &query],
+        sinks.reference_query,
+        sinks.query_query,
+    );
+}
+
+fn find_all_matches<Sink: MatchSink>(
+    reference: &Primary<impl SequenceIndex>,
+    query: &Primary<impl SequenceIndex>,
+    reference_rc: &RcKmers,
+    query_rc: &RcKmers,
+    sinks: &mut QuadrantSinks<Sink>,
+) {
+    debug!("Finding matches");
+    reference_rc.report(ProgressEvent::IndexesBuilt);
+    reference_rc.find_matches(
+        reference,
+        query,
+        sinks.reference_reference,
+        sinks.query_reference,
+    );
+    query_rc.find_matches(reference, query, sinks.reference_query, sinks.query_query);
+}
+
+/// A primary sequence together with its index.
+struct Primary<'text, Index> {
+    text: &'text [u8],
+    index: Index,
+    flags: &'text KmerFlags,
+    /// The kmers of the text, which are consulted before looking up a kmer in the index.
+    bloom: Option<BloomFilter>,
+}
+
+impl<'text, Index: SequenceIndex> Primary<'text, Index> {
+    fn new(
+        text: &'text [u8],
+        index: Index,
+        flags: &'text KmerFlags,
+        bloom: Option<BloomFilter>,
+    ) -> Self {
+        Self {
+            text,
+            index,
+            flags,
+            bloom,
+        }
+    }
+
+    fn kmer_count(&self) -> usize {
+        self.flags.excluded.len()
+    }
+}
+
+/// The kmers of a reverse-complemented secondary sequence.
+struct RcKmers<'rc> {
+    rc: RcText<'rc>,
+    /// If kmers are matched in reverse-complement orientation.
+    reverse_complement: bool,
+    /// The forward secondary sequence, if kmers are matched in forward orientation.
+    forward: Option<&'rc [u8]>,
+    /// The name of the secondary genome, either `"reference"` or `"query"`.
+    genome: &'static str,
+    /// The flags of the forward secondary sequence.
+    flags: &'rc KmerFlags,
+    /// If the quadrants of this secondary sequence with the reference and the query as primary sequence are computed.
+    computed_quadrants: [bool; 2],
+    kmer_count: usize,
+    minimum_length: usize,
+    max_mismatches: usize,
+    ambiguity_policy: AmbiguityPolicy,
+    band: Option<Band>,
+    /// Compare the kmers within the band directly instead of using the index.
+    band_scan: bool,
+    #[cfg(feature = "parallel")]
+    parallel: bool,
+    progress: Option<ProgressHandle>,
+    cancellation: Option<Arc<AtomicBool>>,
+}
+
+impl<'rc> RcKmers<'rc> {
+    fn new(
+        forward: &'rc [u8],
+        rc: RcText<'rc>,
+        genome: &'static str,
+        quadrants: [Quadrant; 2],
+        flags: &'rc KmerFlags,
+        strategy: ConstructionStrategy,
+        options: &MatchTableBuilder<impl StorageIndex>,
+    ) -> Self {
+        Self {
+            rc,
+            reverse_complement: options.orientation.reverse_complement(),
+            forward: options.orientation.forward().then_some(forward),
+            genome,
+            flags,
+            computed_quadrants: quadrants.map(|quadrant| options.quadrants.contains(quadrant)),
+            kmer_count: flags.excluded.len(),
+            minimum_length: options.minimum_length,
+            max_mismatches: options.max_mismatches,
+            ambiguity_policy: options.ambiguity_policy,
+            band: options.band,
+            band_scan: strategy == ConstructionStrategy::BandScan,
+            #[cfg(feature = "parallel")]
+            parallel: options.parallel,
+            progress: options.progress.clone(),
+            cancellation: options.cancellation.clone(),
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(|cancelled| cancelled.load(Ordering::Relaxed))
+    }
+
+    fn report(&self, event: ProgressEvent) {
+        if let Some(progress) = &self.progress {
+            progress.report(event);
+        }
+    }
+
+    fn report_processed(&self, processed: usize) {
+        self.report(ProgressEvent::KmersProcessed {
+            genome: self.genome,
+            processed,
+            total: self.kmer_count,
+        });
+    }
+
+    /// Call `f` with each primary kmer index that matches the reverse-complemented kmer at `rc_kmer_index`
+    /// if kmers are matched in reverse-complement orientation.
+    ///
+    /// If kmers are matched in forward orientation, then `f` is additionally called with each primary kmer index
+    /// that matches the forward secondary kmer but not the reverse-complemented one.
+    ///
+    /// Excluded kmers, kmers outside of the band and pairs of kmers that are both not minimizers are never reported.
+    ///
+    /// The reverse-complemented kmer is written into `rc_kmer`, which the caller reuses between calls
+    /// such that no allocation is made per kmer.
+    fn for_each_match(
+        &self,
+        rc_kmer_index: usize,
+        primary: &Primary<impl SequenceIndex>,
+        rc_kmer: &mut Vec<u8>,
+        mut f: impl FnMut(usize),
+    ) {
+        let forward_kmer_index = self.kmer_count - 1 - rc_kmer_index;
+        if self.flags.excluded[forward_kmer_index] {
+            return;
+        }
+        let is_minimizer = self.flags.is_minimizer(forward_kmer_index);
+        let mut f = |primary_kmer_index: usize| {
+            if !primary.flags.excluded[primary_kmer_index]
+                && (is_minimizer || primary.flags.is_minimizer(primary_kmer_index))
+                && self
+                    .band
+                    .is_none_or(|band| band.contains(primary_kmer_index, rc_kmer_index))
+            {
+                f(primary_kmer_index);
+            }
+        };
+
+        rc_kmer.clear();
+        rc_kmer.extend(self.rc.substring(rc_kmer_index, self.minimum_length));
+        let rc_kmer = &*rc_kmer;
+        if self.reverse_complement {
+            self.for_each_oriented_match(rc_kmer, rc_kmer_index, primary, &mut f);
+        }
+
+        if let Some(forward) = self.forward {
+            let forward_kmer =
+                &forward[forward_kmer_index..forward_kmer_index + self.minimum_length];
+            self.for_each_oriented_match(
+                forward_kmer,
+                rc_kmer_index,
+                primary,
+                |primary_kmer_index| {
+                    if !self.reverse_complement
+                        || !self.is_match(rc_kmer, primary, primary_kmer_index)
+                    {
+                        f(primary_kmer_index);
+                    }
+                },
+            );
+        }
+    }
+
+    /// Call `f` with each primary kmer index that matches `kmer`,
+    /// which belongs to the reverse-complemented kmer at `rc_kmer_index`.
+    ///
+    /// If mismatches are allowed, then the kmer is split into `max_mismatches + 1` pieces,
+    /// of which at least one must match exactly by the pigeonhole principle.
+    /// The exact matches of the pieces are then verified to have at most `max_mismatches` mismatches.
+    /// Each primary kmer is reported only once, by the first piece that matches it exactly.
+    ///
+    /// Ambiguous kmers are compared directly against all kmers of the other sequence, since they cannot be found via the index.
+    /// Primary kmers may be reported even if they are excluded or outside of the band.
+    fn for_each_oriented_match(
+        &self,
+        kmer: &[u8],
+        rc_kmer_index: usize,
+        primary: &Primary<impl SequenceIndex>,
+        mut f: impl FnMut(usize),
+    ) {
+        let forward_kmer_index = self.kmer_count - 1 - rc_kmer_index;
+
+        if self.band_scan {
+            let primary_range = match self.band {
+                Some(band) => band.primary_range(rc_kmer_index, primary.kmer_count()),
+                None => 0..primary.kmer_count(),
+            };
+            for primary_kmer_index in primary_range {
+                if self.is_match(kmer, primary, primary_kmer_index) {
+                    f(primary_kmer_index);
+                }
+            }
+            return;
+        }
+
+        if self.flags.ambiguous[forward_kmer_index] {
+            for primary_kmer_index in 0..primary.kmer_count() {
+                if self.is_match(kmer, primary, primary_kmer_index) {
+                    f(primary_kmer_index);
+                }
+            }
+            return;
+        }
+
+        for &primary_kmer_index in &primary.flags.ambiguous_kmers {
+            if self.is_match(kmer, primary, primary_kmer_index) {
+                f(primary_kmer_index);
+            }
+        }
+        let f = |primary_kmer_index: usize| {
+            if !primary.flags.ambiguous[primary_kmer_index] {
+                f(primary_kmer_index);
+            }
+        };
+
+        self.for_each_indexed_match(kmer, primary, f);
+    }
+
+    /// Returns `true` if the primary kmer has at most `max_mismatches` mismatches to `kmer` by direct comparison.
+    fn is_match(
+        &self,
+        kmer: &[u8],
+        primary: &Primary<impl SequenceIndex>,
+        primary_kmer_index: usize,
+    ) -> bool {
+        let primary_kmer =
+            &primary.text[primary_kmer_index..primary_kmer_index + self.minimum_length];
+        kmers_match(
+            primary_kmer,
+            kmer.iter().copied(),
+            self.max_mismatches,
+            self.ambiguity_policy,
+        )
+    }
+
+    fn for_each_indexed_match(
+        &self,
+        kmer: &[u8],
+        primary: &Primary<impl SequenceIndex>,
+        mut f: impl FnMut(usize),
+    ) {
+        if self.max_mismatches == 0 {
+            if primary
+                .bloom
+                .as_ref()
+                .is_none_or(|bloom| bloom.contains(kmer))
+            {
+                primary.index.positions(kmer).for_each(f);
+            }
+            return;
+        }
+
+        let primary_index = &primary.index;
+        let primary = primary.text;
+        let piece_count = self.max_mismatches + 1;
+        let piece_bounds = |piece: usize| {
+            piece * self.minimum_length / piece_count
+                ..(piece + 1) * self.minimum_length / piece_count
+        };
+
+        for piece in 0..piece_count {
+            let bounds = piece_bounds(piece);
+            let pattern = &kmer[bounds.clone()];
+
+            for position in primary_index.positions(pattern) {
+                let Some(primary_kmer_index) = position.checked_sub(bounds.start) else {
+                    continue;
+                };
+                let Some(primary_kmer) =
+                    primary.get(primary_kmer_index..primary_kmer_index + self.minimum_length)
+                else {
+                    continue;
+                };
+
+                let found_by_earlier_piece = (0..piece).any(|earlier_piece| {
+                    let bounds = piece_bounds(earlier_piece);
+                    primary_kmer[bounds.clone()] == kmer[bounds]
+                });
+                let mismatches = primary_kmer
+                    .iter()
+                    .zip(kmer)
+                    .filter(|(a, b)| a != b)
+                    .count();
+
+                if !found_by_earlier_piece && mismatches <= self.max_mismatches {
+                    f(primary_kmer_index);
+                }
+            }
+        }
+    }
+
+    /// Insert the matches of the kmers of this reverse-complemented sequence against the reference and the query.
+    fn find_matches(
+        &self,
+        reference: &Primary<impl SequenceIndex>,
+        query: &Primary<impl SequenceIndex>,
+        reference_primary: &mut impl MatchSink,
+        query_primary: &mut impl MatchSink,
+    ) {
+        enter_span!("find_matches", secondary = self.genome);
+        #[cfg(feature = "parallel")]
+        if self.parallel {
+            self.find_matches_parallel(reference, query, reference_primary, query_primary);
+            return;
+        }
+
+        self.find_matches_sequential(reference, query, reference_primary, query_primary);
+    }
+
+    fn find_matches_sequential(
+        &self,
+        reference: &Primary<impl SequenceIndex>,
+        query: &Primary<impl SequenceIndex>,
+        reference_primary: &mut impl MatchSink,
+        query_primary: &mut impl MatchSink,
+    ) {
+        let [reference_is_primary, query_is_primary] = self.computed_quadrants;
+        let mut rc_kmer = Vec::with_capacity(self.minimum_length);
+        for rc_kmer_index in 0..self.kmer_count {
+            if reference_primary.is_closed() || query_primary.is_closed() || self.is_cancelled() {
+                return;
+            }
+            if reference_is_primary {
+                self.for_each_match(
+                    rc_kmer_index,
+                    reference,
+                    &mut rc_kmer,
+                    |reference_kmer_index| {
+                        reference_primary.insert(reference_kmer_index, rc_kmer_index)
+                    },
+                );
+            }
+            if query_is_primary {
+                self.for_each_match(rc_kmer_index, query, &mut rc_kmer, |query_kmer_index| {
+                    query_primary.insert(query_kmer_index, rc_kmer_index)
+                });
+            }
+
+            let processed = rc_kmer_index + 1;
+            if processed % PROGRESS_INTERVAL == 0 || processed == self.kmer_count {
+                self.report_processed(processed);
+            }
+        }
+    }
+
+    /// Returns the kmers that are not excluded and contain only characters of the alphabet of the packer,
+    /// as pairs of the packed kmer and the kmer index sorted by packed kmer and then by kmer index.
+    fn sort_kmers<Index: StorageIndex>(
+        &self,
+        packed_kmers: impl Iterator<Item = Option<u64>>,
+        is_excluded: impl Fn(usize) -> bool,
+    ) -> Vec<SortedKmer<Index>> {
+        let mut sorted_kmers: Vec<_> = packed_kmers
+            .enumerate()
+            .filter(|&(kmer_index, _)| !is_excluded(kmer_index))
+            .filter_map(|(kmer_index, packed)| Some((packed?, Index::from_usize(kmer_index))))
+            .collect();
+        #[cfg(feature = "parallel")]
+        if self.parallel {
+            use rayon::slice::ParallelSliceMut;
+
+            sorted_kmers.par_sort_unstable();
+            return sorted_kmers;
+        }
+        sorted_kmers.sort_unstable();
+        sorted_kmers
+    }
+
+    /// Insert the matches of the kmers of this reverse-complemented sequence against the reference and the query,
+    /// given with their kmers sorted by [`sort_kmers`](Self::sort_kmers), see [`ConstructionStrategy::SortMerge`].
+    ///
+    /// Only matches in reverse-complement orientation between kmers that are not ambiguous are found.
+    fn find_merged_matches<Index: StorageIndex>(
+        &self,
+        packer: &KmerPacker,
+        [reference, query]: [(&Primary<NoIndex>, &[SortedKmer<Index>]); 2],
+        reference_primary: &mut impl MatchSink,
+        query_primary: &mut impl MatchSink,
+    ) {
+        enter_span!("find_matches", secondary = self.genome);
+        if self.is_cancelled() {
+            return;
+        }
+        let rc_kmers = self.sort_kmers(packer.pack_all(self.rc.iter()), |rc_kmer_index| {
+            self.flags.excluded[self.kmer_count - 1 - rc_kmer_index]
+        });
+
+        let [reference_is_primary, query_is_primary] = self.computed_quadrants;
+        if reference_is_primary {
+            self.insert_merged_matches(reference, &rc_kmers, reference_primary);
+        }
+        if query_is_primary && !self.is_cancelled() {
+            self.insert_merged_matches(query, &rc_kmers, query_primary);
+        }
+        self.report_processed(self.kmer_count);
+    }
+
+    /// Insert the matches of the kmers of this reverse-complemented sequence against the reference and the query,
+    /// given with hash indexes of their packed kmers of length `KMER_LENGTH`, see [`find_joined_matches`].
+    ///
+    /// Only matches in reverse-complement orientation between kmers that are not ambiguous are found.
+    fn find_joined_matches<const KMER_LENGTH: usize>(
+        &self,
+        packer: &KmerPacker,
+        primaries: [&Primary<Option<HashKmerIndex<impl StorageIndex>>>; 2],
+        reference_primary: &mut impl MatchSink,
+        query_primary: &mut impl MatchSink,
+    ) {
+        enter_span!("find_matches", secondary = self.genome);
+        #[cfg(feature = "parallel")]
+        if self.parallel {
+            use std::sync::atomic::AtomicUsize;
+
+            use rayon::prelude::*;
+
+            let processed = AtomicUsize::new(0);
+            let chunks: Vec<_> = (0..self.kmer_count.div_ceil(PARALLEL_CHUNK_SIZE))
+                .into_par_iter()
+                .map(|chunk_index| {
+                    let mut matches = [Vec::new(), Vec::new()];
+                    let chunk_start = chunk_index * PARALLEL_CHUNK_SIZE;
+                    let chunk_end = (chunk_start + PARALLEL_CHUNK_SIZE).min(self.kmer_count);
+                    if self.is_cancelled() {
+                        return matches;
+                    }
+
+                    let _ = self.join_kmers::<KMER_LENGTH>(
+                        packer,
+                        chunk_start..chunk_end,
+                        primaries,
+                        |primary, primary_kmer_index, rc_kmer_index| {
+                            matches[primary].push((primary_kmer_index, rc_kmer_index));
+                            ControlFlow::Continue(())
+                        },
+                    );
+
+                    let chunk_length = chunk_end - chunk_start;
+                    self.report_processed(
+                        processed.fetch_add(chunk_length, Ordering::Relaxed) + chunk_length,
+                    );
+                    matches
+                })
+                .collect();
+
+            for [reference_matches, query_matches] in chunks {
+                for (reference_kmer_index, rc_kmer_index) in reference_matches {
+                    reference_primary.insert(reference_kmer_index, rc_kmer_index);
+                }
+                for (query_kmer_index, rc_kmer_index) in query_matches {
+                    query_primary.insert(query_kmer_index, rc_kmer_index);
+                }
+            }
+            return;
+        }
+
+        for chunk_start in (0..self.kmer_count).step_by(PROGRESS_INTERVAL) {
+            if self.is_cancelled() {
+                return;
+            }
+            let chunk_end = (chunk_start + PROGRESS_INTERVAL).min(self.kmer_count);
+            let flow = self.join_kmers::<KMER_LENGTH>(
+                packer,
+                chunk_start..chunk_end,
+                primaries,
+                |primary, primary_kmer_index, rc_kmer_index| {
+                    if reference_primary.is_closed() || query_primary.is_closed() {
+                        return ControlFlow::Break(());
+                    }
+                    if primary == 0 {
+                        reference_primary.insert(primary_kmer_index, rc_kmer_index);
+                    } else {
+                        query_primary.insert(primary_kmer_index, rc_kmer_index);
+                    }
+                    ControlFlow::Continue(())
+                },
+            );
+            if flow.is_break() {
+                return;
+            }
+            self.report_processed(chunk_end);
+        }
+    }
+
+    /// Call `f` with the primary sequence, `0` for the reference and `1` for the query,
+    /// the primary kmer index and the reverse-complemented kmer index of each match of the reverse-complemented kmers in `rc_kmers`.
+    /// The matches are ordered by reverse-complemented kmer index, then by primary sequence and then by primary kmer index.
+    ///
+    /// The reverse-complemented kmers are packed by updating the packed kmer with each character,
+    /// and each packed kmer is looked up directly in the hash indexes of the primary sequences of the computed quadrants.
+    /// Stops as soon as `f` breaks.
+    fn join_kmers<const KMER_LENGTH: usize>(
+        &self,
+        packer: &KmerPacker,
+        rc_kmers: Range<usize>,
+        primaries: [&Primary<Option<HashKmerIndex<impl StorageIndex>>>; 2],
+        mut f: impl FnMut(usize, usize, usize) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
+        let packed_kmers = packer.pack_all_fixed::<KMER_LENGTH>(
+            self.rc
+                .substring(rc_kmers.start, rc_kmers.len() + KMER_LENGTH - 1),
+        );
+        for (rc_kmer_index, packed) in rc_kmers.zip(packed_kmers) {
+            let forward_kmer_index = self.kmer_count - 1 - rc_kmer_index;
+            let Some(packed) = packed else {
+                continue;
+            };
+            if self.flags.excluded[forward_kmer_index] {
+                continue;
+            }
+            let is_minimizer = self.flags.is_minimizer(forward_kmer_index);
+
+            for (primary_index, primary) in primaries.into_iter().enumerate() {
+                let (true, Some(index)) = (self.computed_quadrants[primary_index], &primary.index)
+                else {
+                    continue;
+                };
+                for &primary_kmer_index in index.get(packed) {
+                    let primary_kmer_index = primary_kmer_index.into_usize();
+                    if (is_minimizer || primary.flags.is_minimizer(primary_kmer_index))
+                        && self
+                            .band
+                            .is_none_or(|band| band.contains(primary_kmer_index, rc_kmer_index))
+                    {
+                        f(primary_index, primary_kmer_index, rc_kmer_index)?;
+                    }
+                }
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// Merge the sorted kmers of the primary sequence with the sorted reverse-complemented kmers,
+    /// and insert the matches ordered by reverse-complemented kmer index and then by primary kmer index.
+    fn insert_merged_matches<Index: StorageIndex>(
+        &self,
+        (primary, primary_kmers): (&Primary<NoIndex>, &[SortedKmer<Index>]),
+        rc_kmers: &[SortedKmer<Index>],
+        sink: &mut impl MatchSink,
+    ) {
+        let mut matches = Vec::new();
+        let (mut primary_start, mut rc_start) = (0, 0);
+        while primary_start < primary_kmers.len() && rc_start < rc_kmers.len() {
+            let kmer = primary_kmers[primary_start].0;
+            match kmer.cmp(&rc_kmers[rc_start].0) {
+                std::cmp::Ordering::Less => primary_start += 1,
+                std::cmp::Ordering::Greater => rc_start += 1,
+                std::cmp::Ordering::Equal => {
+                    let primary_end = equal_kmers_end(primary_kmers, primary_start);
+                    let rc_end = equal_kmers_end(rc_kmers, rc_start);
+                    for &(_, rc_kmer_index) in &rc_kmers[rc_start..rc_end] {
+                        let rc_kmer_index = rc_kmer_index.into_usize();
+                        let is_minimizer =
+                            self.flags.is_minimizer(self.kmer_count - 1 - rc_kmer_index);
+                        for &(_, primary_kmer_index) in &primary_kmers[primary_start..primary_end] {
+                            let primary_kmer_index = primary_kmer_index.into_usize();
+                            if (is_minimizer || primary.flags.is_minimizer(primary_kmer_index))
+                                && self.band.is_none_or(|band| {
+                                    band.contains(primary_kmer_index, rc_kmer_index)
+                                })
+                            {
+                                matches.push((rc_kmer_index, primary_kmer_index));
+                            }
+                        }
+                    }
+                    primary_start = primary_end;
+                    rc_start = rc_end;
+                }
+            }
+        }
+
+        matches.sort_unstable();
+        for (rc_kmer_index, primary_kmer_index) in matches {
+            if sink.is_closed() {
+                return;
+            }
+            sink.insert(primary_kmer_index, rc_kmer_index);
+        }
+    }
+
+    /// Insert the matches of the kmers of this reverse-complemented sequence against the reference and the query.
+    ///
+    /// The kmers are partitioned into chunks that are processed in parallel,
+    /// each collecting its matches in thread-local buffers that are merged into the storage in chunk order.
+    #[cfg(feature = "parallel")]
+    fn find_matches_parallel(
+        &self,
+        reference: &Primary<impl SequenceIndex>,
+        query: &Primary<impl SequenceIndex>,
+        reference_primary: &mut impl MatchSink,
+        query_primary: &mut impl MatchSink,
+    ) {
+        use std::sync::atomic::AtomicUsize;
+
+        use rayon::prelude::*;
+
+        let [reference_is_primary, query_is_primary] = self.computed_quadrants;
+        let processed = AtomicUsize::new(0);
+        let chunks: Vec<_> = (0..self.kmer_count.div_ceil(PARALLEL_CHUNK_SIZE))
+            .into_par_iter()
+            .map(|chunk_index| {
+                let mut reference_matches = Vec::new();
+                let mut query_matches = Vec::new();
+                let mut rc_kmer = Vec::with_capacity(self.minimum_length);
+                let chunk_start = chunk_index * PARALLEL_CHUNK_SIZE;
+                let chunk_end = (chunk_start + PARALLEL_CHUNK_SIZE).min(self.kmer_count);
+                if self.is_cancelled() {
+                    return (reference_matches, query_matches);
+                }
+
+                for rc_kmer_index in chunk_start..chunk_end {
+                    if reference_is_primary {
+                        self.for_each_match(
+                            rc_kmer_index,
+                            reference,
+                            &mut rc_kmer,
+                            |reference_kmer_index| {
+                                reference_matches.push((reference_kmer_index, rc_kmer_index))
+                            },
+                        );
+                    }
+                    if query_is_primary {
+                        self.for_each_match(
+                            rc_kmer_index,
+                            query,
+                            &mut rc_kmer,
+                            |query_kmer_index| {
+                                query_matches.push((query_kmer_index, rc_kmer_index))
+                            },
+                        );
+                    }
+                }
+
+                let chunk_length = chunk_end - chunk_start;
+                self.report_processed(
+                    processed.fetch_add(chunk_length, Ordering::Relaxed) + chunk_length,
+                );
+                (reference_matches, query_matches)
+            })
+            .collect();
+
+        for (reference_matches, query_matches) in chunks {
+            for (reference_kmer_index, rc_kmer_index) in reference_matches {
+                reference_primary.insert(reference_kmer_index, rc_kmer_index);
+            }
+            for (query_kmer_index, rc_kmer_index) in query_matches {
+                query_primary.insert(query_kmer_index, rc_kmer_index);
+            }
+        }
+    }
+}
+
+/// A packed kmer and its kmer index, see [`RcKmers::sort_kmers`].
+type SortedKmer<Index> = (u64, Index);
+
+/// Returns the end of the run of equal packed kmers that starts at `start` in the sorted kmers.
+fn equal_kmers_end<Index>(sorted_kmers: &[SortedKmer<Index>], start: usize) -> usize {
+    let kmer = sorted_kmers[start].0;
+    start + sorted_kmers[start..].partition_point(|&(other, _)| other == kmer)
+}
@@ -0,0 +1,93 @@
+//! Genomes consisting of multiple contigs.
+
+use bitvec::vec::BitVec;
+
+/// The layout of the contigs of a genome that are concatenated for matching.
+///
+/// A genome given as a single sequence consists of a single contig.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContigLayout {
+    /// The start of each contig in the concatenation, followed by the total length.
+    offsets: Vec<usize>,
+}
+
+/// The position of a kmer within a contig.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ContigPosition {
+    /// The index of the contig in the list of contigs of its genome.
+    pub contig_id: usize,
+    /// The offset of the kmer within the contig.
+    pub offset: usize,
+}
+
+impl ContigLayout {
+    /// Create the layout of contigs with the given lengths.
+    pub(crate) fn new(lengths: impl IntoIterator<Item = usize>) -> Self {
+        let mut offsets = vec![0];
+        for length in lengths {
+            offsets.push(offsets.last().unwrap() + length);
+        }
+        Self { offsets }
+    }
+
+    /// Returns the number of contigs.
+    pub fn contig_count(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    /// Returns the start of the given contig in the concatenation of all contigs.
+    pub fn contig_start(&self, contig_id: usize) -> usize {
+        self.offsets[contig_id]
+    }
+
+    /// Returns the length of the given contig.
+    pub fn contig_length(&self, contig_id: usize) -> usize {
+        self.offsets[contig_id + 1] - self.offsets[contig_id]
+    }
+
+    /// Returns the total length of all contigs.
+    pub fn len(&self) -> usize {
+        *self.offsets.last().unwrap()
+    }
+
+    /// Returns `true` if the total length of all contigs is zero.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the contig position of the given index of the concatenation of all contigs.
+    pub fn position(&self, index: usize) -> ContigPosition {
+        debug_assert!(index < self.len());
+        let contig_id = self.offsets.partition_point(|&offset| offset <= index) - 1;
+        ContigPosition {
+            contig_id,
+            offset: index - self.offsets[contig_id],
+        }
+    }
+
+    /// Returns the contig position of the kmer at the given index of the reverse complement of the concatenation of all contigs.
+    ///
+    /// The offset of the returned position is relative to the reverse complement of the contig.
+    pub fn rc_position(&self, rc_index: usize, kmer_length: usize) -> ContigPosition {
+        let ContigPosition { contig_id, offset } =
+            self.position(self.len() - kmer_length - rc_index);
+        ContigPosition {
+            contig_id,
+            offset: self.contig_length(contig_id) - kmer_length - offset,
+        }
+    }
+
+    /// Returns a bitvector that marks each kmer that spans the boundary between two contigs.
+    pub(crate) fn spanning_kmers(&self, kmer_length: usize) -> BitVec {
+        let kmer_count = (self.len() + 1).saturating_sub(kmer_length);
+        let mut result = BitVec::repeat(false, kmer_count);
+        for &boundary in &self.offsets[1..self.offsets.len() - 1] {
+            let first_kmer = boundary.saturating_sub(kmer_length - 1);
+            let limit = boundary.min(kmer_count);
+            if first_kmer < limit {
+                result[first_kmer..limit].fill(true);
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,186 @@
+//! Conversion between reverse-complement and forward-strand coordinates of secondary kmers.
+
+use crate::{MatchTable, Quadrant, StorageIndex};
+
+/// Returns the forward index of the kmer at `rc_index` in the reverse complement of a sequence of length `sequence_length`.
+///
+/// The kmer of length `kmer_length` starting at `rc_index` in the reverse complement
+/// is the reverse complement of the kmer starting at the returned index in the sequence.
+/// The conversion is its own inverse, so it also converts forward indices to rc indices.
+///
+/// # Example
+///
+/// ```rust
+/// use template_switch_error_free_inners::rc_index_to_forward;
+///
+/// // The reverse complement of `AACGT` is `ACGTT`, and its kmer `CG` at index 1 is the kmer `CG` at index 2 of `AACGT`.
+/// assert_eq!(rc_index_to_forward(5, 2, 1), 2);
+/// assert_eq!(rc_index_to_forward(5, 2, 2), 1);
+/// ```
+pub fn rc_index_to_forward(sequence_length: usize, kmer_length: usize, rc_index: usize) -> usize {
+    debug_assert!(rc_index + kmer_length <= sequence_length);
+    sequence_length - kmer_length - rc_index
+}
+
+/// Returns the exclusive end on the forward strand of the kmer at `rc_index` in the reverse complement of a sequence of length `sequence_length`.
+///
+/// A template switch reads the inner from right to left on the forward strand of the secondary,
+/// so this is the forward position just after the first character of the inner.
+/// The kmer occupies the forward interval `end - kmer_length..end`.
+///
+/// # Example
+///
+/// ```rust
+/// use template_switch_error_free_inners::{forward_end_to_rc_index, rc_index_to_forward_end};
+///
+/// assert_eq!(rc_index_to_forward_end(5, 2, 1), 4);
+/// assert_eq!(forward_end_to_rc_index(5, 2, 4), 1);
+/// ```
+pub fn rc_index_to_forward_end(
+    sequence_length: usize,
+    kmer_length: usize,
+    rc_index: usize,
+) -> usize {
+    debug_assert!(rc_index + kmer_length <= sequence_length);
+    sequence_length - rc_index
+}
+
+/// Returns the index in the reverse complement of a sequence of length `sequence_length`
+/// of the kmer that ends at the exclusive forward position `forward_end`.
+///
+/// This is the inverse of [`rc_index_to_forward_end`].
+pub fn forward_end_to_rc_index(
+    sequence_length: usize,
+    kmer_length: usize,
+    forward_end: usize,
+) -> usize {
+    debug_assert!(kmer_length <= forward_end && forward_end <= sequence_length);
+    sequence_length - forward_end
+}
+
+impl<Index: StorageIndex> MatchTable<Index> {
+    /// Returns the length of the secondary genome of the given quadrant.
+    fn secondary_length(&self, quadrant: Quadrant) -> usize {
+        self.secondary_kmer_count(quadrant) + self.minimum_length - 1
+    }
+
+    /// Converts the secondary rc index of the given quadrant to the exclusive end of the secondary kmer on the forward strand.
+    ///
+    /// See [`rc_index_to_forward_end`] for details.
+    pub fn secondary_forward_end(&self, quadrant: Quadrant, secondary_rc_index: usize) -> usize {
+        rc_index_to_forward_end(
+            self.secondary_length(quadrant),
+            self.minimum_length,
+            secondary_rc_index,
+        )
+    }
+
+    /// Converts the exclusive end of a secondary kmer on the forward strand to the secondary rc index of the given quadrant.
+    ///
+    /// See [`forward_end_to_rc_index`] for details.
+    pub fn secondary_rc_index(&self, quadrant: Quadrant, secondary_forward_end: usize) -> usize {
+        forward_end_to_rc_index(
+            self.secondary_length(quadrant),
+            self.minimum_length,
+            secondary_forward_end,
+        )
+    }
+
+    /// Returns `true` if the primary kmer at `primary_index` matches the reverse complement of the secondary kmer
+    /// that ends at the exclusive forward position `secondary_forward_end` in the given quadrant.
+    ///
+    /// The secondary kmer occupies `secondary_forward_end - minimum_length..secondary_forward_end` on the forward strand.
+    pub fn has_match_forward(
+        &self,
+        quadrant: Quadrant,
+        primary_index: usize,
+        secondary_forward_end: usize,
+    ) -> bool {
+        self.has_match(
+            quadrant,
+            primary_index,
+            self.secondary_rc_index(quadrant, secondary_forward_end),
+        )
+    }
+
+    /// Returns `true` if the reference kmer at `primary_index` matches the reverse complement of the reference kmer
+    /// that ends at the exclusive forward position `secondary_forward_end`.
+    ///
+    /// See [`has_match_forward`](Self::has_match_forward) for details.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::MatchTable;
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+    /// let matches = MatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    ///
+    /// // `GGGG` at 1..5 is the reverse complement of `CCCC` at 7..11.
+    /// assert!(matches.has_reference_reference_match(1, 2));
+    /// assert!(matches.has_reference_reference_match_forward(1, 11));
+    /// ```
+    pub fn has_reference_reference_match_forward(
+        &self,
+        primary_index: usize,
+        secondary_forward_end: usize,
+    ) -> bool {
+        self.has_match_forward(
+            Quadrant::ReferenceReference,
+            primary_index,
+            secondary_forward_end,
+        )
+    }
+
+    /// Returns `true` if the reference kmer at `primary_index` matches the reverse complement of the query kmer
+    /// that ends at the exclusive forward position `secondary_forward_end`.
+    ///
+    /// See [`has_match_forward`](Self::has_match_forward) for details.
+    pub fn has_reference_query_match_forward(
+        &self,
+        primary_index: usize,
+        secondary_forward_end: usize,
+    ) -> bool {
+        self.has_match_forward(
+            Quadrant::ReferenceQuery,
+            primary_index,
+            secondary_forward_end,
+        )
+    }
+
+    /// Returns `true` if the query kmer at `primary_index` matches the reverse complement of the reference kmer
+    /// that ends at the exclusive forward position `secondary_forward_end`.
+    ///
+    /// See [`has_match_forward`](Self::has_match_forward) for details.
+    pub fn has_query_reference_match_forward(
+        &self,
+        primary_index: usize,
+        secondary_forward_end: usize,
+    ) -> bool {
+        self.has_match_forward(
+            Quadrant::QueryReference,
+            primary_index,
+            secondary_forward_end,
+        )
+    }
+
+    /// Returns `true` if the query kmer at `primary_index` matches the reverse complement of the query kmer
+    /// that ends at the exclusive forward position `secondary_forward_end`.
+    ///
+    /// See [`has_match_forward`](Self::has_match_forward) for details.
+    pub fn has_query_query_match_forward(
+        &self,
+        primary_index: usize,
+        secondary_forward_end: usize,
+    ) -> bool {
+        self.has_match_forward(Quadrant::QueryQuery, primary_index, secondary_forward_end)
+    }
+}
@@ -0,0 +1,190 @@
+//! Extraction of the matches of a locus of interest into a smaller table.
+
+use std::ops::Range;
+
+use crate::{
+    ContigLayout, MatchTable, MatchTableBuilder, MatchTableError, Quadrant, StorageBackend,
+    StorageIndex,
+};
+
+impl<Index: StorageIndex> MatchTable<Index> {
+    /// Returns a table of the given forward-strand ranges of the reference and the query,
+    /// containing the matches of this table between kmers that lie completely within the ranges.
+    ///
+    /// The indices of the cropped table are relative to the ranges, i.e. the table equals the table computed from the ranges alone,
+    /// except for the kmers that were excluded from matching by properties of the whole sequences, like their number of occurrences.
+    /// A primary index `i` of the cropped table corresponds to the primary index `i + range.start` of this table,
+    /// and a secondary rc index `j` to the secondary rc index `j + sequence_length - range.end`.
+    /// Only the part of each row within the ranges is visited, and the matches are stored [sparsely](StorageBackend::Sparse),
+    /// so cropping a small locus of a large table is cheap.
+    ///
+    /// Like a [merged](Self::merge) table, the cropped table consists of a single contig per sequence and has no band,
+    /// records no [maximum number of kmer occurrences](Self::max_kmer_occurrences), and does not record [unique matches only](Self::unique_matches_only).
+    /// Its [excluded intervals](Self::reference_excluded_intervals) are those of this table clipped to the ranges,
+    /// and it records no [skipped kmers](Self::skipped_reference_kmer_count), since these are not stored by position.
+    /// It keeps the [maximum length](Self::maximum_length) of this table.
+    ///
+    /// Returns an error if a range is shorter than the minimum length.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start of a range is greater than its end, or its end is greater than the length of its sequence.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTable, Quadrant};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"TTACGGATTT").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"GGTCCGTGG").unwrap();
+    /// let matches = MatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    /// assert_eq!(matches.matches(Quadrant::ReferenceQuery).collect::<Vec<_>>(), vec![(2, 2), (3, 3)]);
+    ///
+    /// // Keep `ACGGAT` of the reference and `TCCGT` of the query.
+    /// let cropped = matches.crop(2..8, 2..7).unwrap();
+    /// assert_eq!(cropped.reference_kmer_count(), 3);
+    /// assert_eq!(cropped.query_kmer_count(), 2);
+    /// assert_eq!(cropped.matches(Quadrant::ReferenceQuery).collect::<Vec<_>>(), vec![(0, 0), (1, 1)]);
+    /// ```
+    pub fn crop(
+        &self,
+        reference_range: Range<usize>,
+        query_range: Range<usize>,
+    ) -> Result<Self, MatchTableError> {
+        let minimum_length = self.minimum_length;
+        let reference = CroppedRange::new(
+            "reference",
+            reference_range,
+            self.reference_contigs.len(),
+            minimum_length,
+        )?;
+        let query = CroppedRange::new(
+            "query",
+            query_range,
+            self.query_contigs.len(),
+            minimum_length,
+        )?;
+
+        let mut builders = MatchTableBuilder::new(minimum_length)
+            .index_type::<Index>()
+            .storage(StorageBackend::Sparse)
+            .quadrants(self.quadrants)
+            .quadrant_storage_builders((reference.kmer_count, query.kmer_count))?;
+        for (quadrant, builder) in Quadrant::ALL.into_iter().zip(&mut builders) {
+            if !self.quadrants.contains(quadrant) {
+                continue;
+            }
+            let range = |is_reference| if is_reference { &reference } else { &query };
+            let primary = range(quadrant.primary_is_reference());
+            let secondary = range(quadrant.secondary_is_reference());
+            if secondary.kmer_count == 0 {
+                continue;
+            }
+            for primary_index in 0..primary.kmer_count {
+                let original_primary_index = primary_index + primary.primary_offset;
+                let min_offset =
+                    secondary.secondary_rc_offset as isize - original_primary_index as isize;
+                let max_offset = min_offset + secondary.kmer_count as isize - 1;
+                for secondary_rc_index in self.matches_near_diagonal(
+                    quadrant,
+                    original_primary_index,
+                    min_offset,
+                    max_offset,
+                ) {
+                    builder.insert(
+                        primary_index,
+                        secondary_rc_index - secondary.secondary_rc_offset,
+                    );
+                }
+            }
+        }
+
+        let [
+            reference_reference,
+            reference_query,
+            query_reference,
+            query_query,
+        ] = builders;
+        Ok(MatchTable {
+            reference_reference: reference_reference.build(),
+            reference_query: reference_query.build(),
+            query_reference: query_reference.build(),
+            query_query: query_query.build(),
+            reference_kmer_count: reference.kmer_count,
+            query_kmer_count: query.kmer_count,
+            minimum_length,
+            max_mismatches: self.max_mismatches,
+            skipped_reference_kmer_count: 0,
+            skipped_query_kmer_count: 0,
+            max_kmer_occurrences: None,
+            unique_matches_only: false,
+            maximum_length: self.maximum_length,
+            frequent_reference_kmer_count: 0,
+            frequent_query_kmer_count: 0,
+            reference_contigs: ContigLayout::new([reference.range.len()]),
+            query_contigs: ContigLayout::new([query.range.len()]),
+            band: None,
+            quadrants: self.quadrants,
+            reference_excluded_intervals: reference.clip(&self.reference_excluded_intervals),
+            query_excluded_intervals: query.clip(&self.query_excluded_intervals),
+            // The cropped table was not computed from the ranges, so it has no fingerprint.
+            fingerprint: None,
+        })
+    }
+}
+
+/// A range of a sequence and the offsets of its kmers in the table of the whole sequence.
+struct CroppedRange {
+    range: Range<usize>,
+    kmer_count: usize,
+    primary_offset: usize,
+    secondary_rc_offset: usize,
+}
+
+impl CroppedRange {
+    fn new(
+        sequence: &'static str,
+        range: Range<usize>,
+        sequence_length: usize,
+        minimum_length: usize,
+    ) -> Result<Self, MatchTableError> {
+        assert!(
+            range.start <= range.end && range.end <= sequence_length,
+            "The {sequence} range {}..{} does not lie within the {sequence} of length {sequence_length}",
+            range.start,
+            range.end,
+        );
+        if range.len() < minimum_length {
+            return Err(MatchTableError::SequenceTooShort {
+                sequence,
+                length: range.len(),
+                minimum_length,
+            });
+        }
+        Ok(Self {
+            kmer_count: range.len() - minimum_length + 1,
+            primary_offset: range.start,
+            secondary_rc_offset: sequence_length - range.end,
+            range,
+        })
+    }
+
+    /// Returns the parts of the given intervals within the range, relative to its start.
+    fn clip(&self, intervals: &[Range<usize>]) -> Vec<Range<usize>> {
+        intervals
+            .iter()
+            .map(|interval| {
+                interval.start.max(self.range.start) - self.range.start
+                    ..interval.end.min(self.range.end).max(self.range.start) - self.range.start
+            })
+            .filter(|interval| !interval.is_empty())
+            .collect()
+    }
+}
@@ -0,0 +1,127 @@
+//! Conversion of quadrants and windows of quadrants into dense [`ndarray`] arrays.
+
+use std::ops::Range;
+
+use ndarray::Array2;
+
+use crate::{MatchTable, MatchTableError, Quadrant, StorageIndex};
+
+impl<Index: StorageIndex> MatchTable<Index> {
+    /// The maximum number of elements of an array returned by [`to_dense_array`](Self::to_dense_array) and [`to_dense_window`](Self::to_dense_window),
+    /// i.e. one GiB of memory.
+    pub const MAX_DENSE_ARRAY_ELEMENTS: usize = 1 << 30;
+
+    /// Returns the given quadrant as a dense array with one row per primary kmer and one column per secondary kmer,
+    /// where the element at `(primary_index, secondary_rc_index)` is one for a match and zero otherwise.
+    ///
+    /// The array requires one byte per pair of kmers, so this is intended for small quadrants,
+    /// see [`to_dense_window`](Self::to_dense_window) for regions of large quadrants.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MatchTableError::DenseArrayTooLarge`] if the array would have more than [`MAX_DENSE_ARRAY_ELEMENTS`](Self::MAX_DENSE_ARRAY_ELEMENTS) elements.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTable, Quadrant};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+    /// let matches = MatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    ///
+    /// let array = matches.to_dense_array(Quadrant::ReferenceReference).unwrap();
+    /// assert_eq!(array.dim(), (10, 10));
+    /// assert_eq!(array[(1, 2)], 1);
+    /// assert_eq!(array[(7, 8)], 1);
+    /// assert_eq!(array.sum(), 2);
+    ///
+    /// let window = matches
+    ///     .to_dense_window(Quadrant::ReferenceReference, 6..9, 7..10)
+    ///     .unwrap();
+    /// assert_eq!(window.dim(), (3, 3));
+    /// assert_eq!(window[(1, 1)], 1);
+    /// assert_eq!(window.sum(), 1);
+    /// ```
+    pub fn to_dense_array(&self, quadrant: Quadrant) -> Result<Array2<u8>, MatchTableError> {
+        self.to_dense_window(
+            quadrant,
+            0..self.primary_kmer_count(quadrant),
+            0..self.secondary_kmer_count(quadrant),
+        )
+    }
+
+    /// Returns the matches of the given quadrant whose primary index lies in `primary_indices` and whose secondary rc index lies in `secondary_rc_indices`
+    /// as a dense array with one row per primary index and one column per secondary rc index of the window,
+    /// where the element at `(i, j)` is one if `primary_indices.start + i` matches `secondary_rc_indices.start + j` and zero otherwise.
+    ///
+    /// Only the part of each row within the window is visited, like in [`matches_near_diagonal`](Self::matches_near_diagonal).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MatchTableError::DenseArrayTooLarge`] if the array would have more than [`MAX_DENSE_ARRAY_ELEMENTS`](Self::MAX_DENSE_ARRAY_ELEMENTS) elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start of a range is greater than its end, or its end is greater than the number of kmers of the quadrant.
+    pub fn to_dense_window(
+        &self,
+        quadrant: Quadrant,
+        primary_indices: Range<usize>,
+        secondary_rc_indices: Range<usize>,
+    ) -> Result<Array2<u8>, MatchTableError> {
+        for (indices, kmer_count, name) in [
+            (
+                &primary_indices,
+                self.primary_kmer_count(quadrant),
+                "primary",
+            ),
+            (
+                &secondary_rc_indices,
+                self.secondary_kmer_count(quadrant),
+                "secondary rc",
+            ),
+        ] {
+            assert!(
+                indices.start <= indices.end && indices.end <= kmer_count,
+                "The {name} range {}..{} does not lie within the {kmer_count} {name} kmers of quadrant {quadrant}",
+                indices.start,
+                indices.end,
+            );
+        }
+        let shape = (primary_indices.len(), secondary_rc_indices.len());
+        if shape
+            .0
+            .checked_mul(shape.1)
+            .is_none_or(|element_count| element_count > Self::MAX_DENSE_ARRAY_ELEMENTS)
+        {
+            return Err(MatchTableError::DenseArrayTooLarge {
+                row_count: shape.0,
+                column_count: shape.1,
+                max_element_count: Self::MAX_DENSE_ARRAY_ELEMENTS,
+            });
+        }
+
+        let mut array = Array2::zeros(shape);
+        if shape.1 == 0 {
+            return Ok(array);
+        }
+        for (row, primary_index) in primary_indices.enumerate() {
+            let min_offset = secondary_rc_indices.start as isize - primary_index as isize;
+            let max_offset = (secondary_rc_indices.end - 1) as isize - primary_index as isize;
+            for secondary_rc_index in
+                self.matches_near_diagonal(quadrant, primary_index, min_offset, max_offset)
+            {
+                array[(row, secondary_rc_index - secondary_rc_indices.start)] = 1;
+            }
+        }
+        Ok(array)
+    }
+}
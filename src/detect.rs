@@ -0,0 +1,196 @@
+//! The end-to-end detection of template switches from a pair of sequences.
+
+use compact_genome::interface::{alphabet::Alphabet, sequence::GenomeSequence};
+
+use crate::{
+    CandidateConstraints, ChainingParameters, DefaultScore, Inner, MatchTable, MatchTableBuilder,
+    MatchTableError, TemplateSwitchCandidate,
+};
+
+/// Parameters of [`detect_template_switches`].
+///
+/// # Example
+///
+/// ```rust
+/// use template_switch_error_free_inners::{
+///     CandidateConstraints, ChainingParameters, DefaultScore, DetectionParameters, MatchTableBuilder,
+/// };
+///
+/// let parameters = DetectionParameters::new(MatchTableBuilder::new(8).max_kmer_occurrences(10))
+///     .chaining(ChainingParameters::new().max_diagonal_shift(0).min_score(12))
+///     .constraints(CandidateConstraints::new().min_inner_length(12).switch_distance(0, 500))
+///     .score(DefaultScore::new().gc_balance_weight(0.0))
+///     .min_score(20.0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct DetectionParameters {
+    table: MatchTableBuilder,
+    chaining: Option<ChainingParameters>,
+    constraints: CandidateConstraints,
+    score: DefaultScore,
+    min_score: f64,
+}
+
+impl DetectionParameters {
+    /// Create parameters that compute the match table with the given builder,
+    /// and report all maximal error-free inners ranked by the [`DefaultScore`].
+    pub fn new(table: MatchTableBuilder) -> Self {
+        Self {
+            table,
+            chaining: None,
+            constraints: CandidateConstraints::new(),
+            score: DefaultScore::new(),
+            min_score: f64::NEG_INFINITY,
+        }
+    }
+
+    /// Chain the matches with the given parameters, such that inners may contain mismatches and small indels.
+    ///
+    /// Without chaining, the inners are the maximal error-free inners of the table.
+    pub fn chaining(mut self, chaining: ChainingParameters) -> Self {
+        self.chaining = Some(chaining);
+        self
+    }
+
+    /// Set the constraints on the template switches.
+    pub fn constraints(mut self, constraints: CandidateConstraints) -> Self {
+        self.constraints = constraints;
+        self
+    }
+
+    /// Set the score of the inners of the template switches.
+    pub fn score(mut self, score: DefaultScore) -> Self {
+        self.score = score;
+        self
+    }
+
+    /// Set the minimum score of the reported template switches.
+    pub fn min_score(mut self, min_score: f64) -> Self {
+        self.min_score = min_score;
+        self
+    }
+}
+
+/// A template switch detected by [`detect_template_switches`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateSwitchEvent {
+    /// The four points of the template switch.
+    pub candidate: TemplateSwitchCandidate,
+    /// The inner of the template switch in the coordinates of the match table.
+    pub inner: Inner,
+    /// The score of the inner.
+    pub score: f64,
+}
+
+/// Detect the template switches between the given reference and query, and return them ordered by decreasing score.
+///
+/// This runs the whole workflow of the crate:
+///
+/// 1. The match table is computed with the [builder](DetectionParameters::new) of the parameters.
+/// 2. The matches of each computed quadrant are extended into [maximal inners](MatchTable::maximal_inners),
+///    or [chained](MatchTable::chains) into inners with mismatches and small indels if [chaining](DetectionParameters::chaining) is enabled.
+/// 3. Each inner is paired into a [candidate](TemplateSwitchCandidate) with all four switch points,
+///    and the candidates are filtered by the [constraints](DetectionParameters::constraints).
+///    Unlike [`MatchTable::candidates`], each inner yields only the candidate of its full length.
+/// 4. The inners of the remaining candidates are [scored](MatchTable::rank_inners),
+///    and those with at least the [minimum score](DetectionParameters::min_score) are returned.
+///
+/// Template switches with equal scores are ordered by their quadrant and then by their start in the primary and the secondary.
+/// In the self-comparison quadrants, each inverted repeat is reported twice, once with each arm as the inner.
+/// The inner of a chain may differ in length between the primary and the secondary,
+/// so then the distances of the points 1 and 4 and the points 3 and 2 differ as well.
+///
+/// Returns an error if the match table cannot be computed, see [`MatchTableBuilder::try_build`].
+///
+/// # Example
+///
+/// ```rust
+/// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+/// use compact_genome::implementation::vec_sequence::VectorGenome;
+/// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+/// use template_switch_error_free_inners::{
+///     CandidateConstraints, DetectionParameters, MatchTableBuilder, Quadrant, TemplateSwitchCandidate,
+///     detect_template_switches,
+/// };
+///
+/// // The reverse complement of `GGTCA` is `TGACC`.
+/// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGTCAAAAATGACCA").unwrap();
+/// let query = VectorGenome::from_slice_u8(b"AAAA").unwrap();
+/// let parameters = DetectionParameters::new(MatchTableBuilder::new(4))
+///     .constraints(CandidateConstraints::new().min_inner_length(5));
+///
+/// let events = detect_template_switches(
+///     reference.as_genome_subsequence(),
+///     query.as_genome_subsequence(),
+///     &parameters,
+/// )
+/// .unwrap();
+/// assert_eq!(events.len(), 2);
+/// assert_eq!(
+///     events[0].candidate,
+///     TemplateSwitchCandidate {
+///         quadrant: Quadrant::ReferenceReference,
+///         point1: 1,
+///         point2: 15,
+///         point3: 10,
+///         point4: 6,
+///     },
+/// );
+/// assert_eq!(events[1].candidate.point1, 10);
+/// assert!(events[0].score >= events[1].score);
+/// ```
+pub fn detect_template_switches<
+    AlphabetType: Alphabet,
+    GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+>(
+    reference: &GenomeSubsequence,
+    query: &GenomeSubsequence,
+    parameters: &DetectionParameters,
+) -> Result<Vec<TemplateSwitchEvent>, MatchTableError> {
+    let table = parameters.table.try_build(reference, query)?;
+
+    let mut inners = Vec::new();
+    for quadrant in table.quadrants().iter() {
+        match &parameters.chaining {
+            Some(chaining) => {
+                inners.extend(table.chains(quadrant, chaining).iter().map(Inner::from))
+            }
+            None => inners.extend(table.maximal_inners(quadrant)),
+        }
+    }
+    let inners = inners
+        .into_iter()
+        .filter(|inner| parameters.constraints.accepts(&candidate(&table, inner)));
+
+    let reference = reference.clone_as_vec();
+    let query = query.clone_as_vec();
+    Ok(table
+        .rank_inners(
+            inners,
+            &reference,
+            &query,
+            &parameters.score,
+            parameters.min_score,
+        )
+        .into_iter()
+        .map(|scored| TemplateSwitchEvent {
+            candidate: candidate(&table, &scored.inner),
+            inner: scored.inner,
+            score: scored.score,
+        })
+        .collect())
+}
+
+/// Returns the template switch candidate whose inner is the given inner of the table.
+fn candidate(table: &MatchTable, inner: &Inner) -> TemplateSwitchCandidate {
+    let minimum_length = table.minimum_length();
+    TemplateSwitchCandidate {
+        quadrant: inner.quadrant,
+        point1: inner.primary.start,
+        point2: table.secondary_forward_end(inner.quadrant, inner.secondary_rc.start),
+        point3: table
+            .secondary_forward_end(inner.quadrant, inner.secondary_rc.end - minimum_length)
+            - minimum_length,
+        point4: inner.primary.end,
+    }
+}
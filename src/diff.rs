@@ -0,0 +1,151 @@
+//! Comparison of two tables of the same sequences, e.g. to debug regressions between storage backends or versions of the crate.
+
+use std::cmp::Ordering;
+
+use crate::{MatchTable, MatchTableError, Quadrant, StorageIndex};
+
+/// The matches present in only one of two tables, see [`MatchTable::diff`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchTableDiff {
+    only_in_self: [Vec<(usize, usize)>; 4],
+    only_in_other: [Vec<(usize, usize)>; 4],
+}
+
+impl MatchTableDiff {
+    /// Returns `true` if both tables contain the same matches.
+    pub fn is_empty(&self) -> bool {
+        self.only_in_self
+            .iter()
+            .chain(&self.only_in_other)
+            .all(Vec::is_empty)
+    }
+
+    /// Returns the number of matches present in only one of the tables.
+    pub fn difference_count(&self) -> usize {
+        self.only_in_self
+            .iter()
+            .chain(&self.only_in_other)
+            .map(Vec::len)
+            .sum()
+    }
+
+    /// Returns the matches of the given quadrant present in the table that [`MatchTable::diff`] was called on but not in the other table,
+    /// as `(primary_index, secondary_rc_index)` pairs ordered like those of [`MatchTable::matches`].
+    pub fn only_in_self(&self, quadrant: Quadrant) -> &[(usize, usize)] {
+        &self.only_in_self[quadrant as usize]
+    }
+
+    /// Returns the matches of the given quadrant present in the other table but not in the table that [`MatchTable::diff`] was called on,
+    /// as `(primary_index, secondary_rc_index)` pairs ordered like those of [`MatchTable::matches`].
+    pub fn only_in_other(&self, quadrant: Quadrant) -> &[(usize, usize)] {
+        &self.only_in_other[quadrant as usize]
+    }
+}
+
+impl<Index: StorageIndex> MatchTable<Index> {
+    /// Compare the matches of this table with those of another table of the same sequences.
+    ///
+    /// The rows of both tables are merged in time linear in the size of their storage,
+    /// so tables with different [storage backends](crate::StorageBackend) can be compared.
+    ///
+    /// Returns an error if the tables differ in their minimum length, maximum number of mismatches, number of reference or query kmers,
+    /// or computed quadrants, since their matches are not comparable then.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTable, MatchTableBuilder, Quadrant, StorageBackend};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"TTACGGATTT").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"GGTCCGTGG").unwrap();
+    /// let dense = MatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    /// let sparse = MatchTableBuilder::new(4)
+    ///     .storage(StorageBackend::Sparse)
+    ///     .build(reference.as_genome_subsequence(), query.as_genome_subsequence());
+    /// assert!(dense.diff(&sparse).unwrap().is_empty());
+    ///
+    /// // Only the first kmer of the inner `ACGGA` is a minimizer of its window of two kmers,
+    /// // so the second match is removed from the reference-query and the query-reference quadrant.
+    /// let minimizers = MatchTableBuilder::new(4)
+    ///     .minimizer_window(2)
+    ///     .build(reference.as_genome_subsequence(), query.as_genome_subsequence());
+    /// let diff = dense.diff(&minimizers).unwrap();
+    /// assert_eq!(diff.difference_count(), 2);
+    /// assert_eq!(diff.only_in_self(Quadrant::ReferenceQuery), &[(3, 3)]);
+    /// assert!(diff.only_in_other(Quadrant::ReferenceQuery).is_empty());
+    /// ```
+    pub fn diff(
+        &self,
+        other: &MatchTable<impl StorageIndex>,
+    ) -> Result<MatchTableDiff, MatchTableError> {
+        if self.minimum_length != other.minimum_length {
+            return Err(MatchTableError::InvalidDiff(
+                "the tables have different minimum lengths",
+            ));
+        }
+        if self.max_mismatches != other.max_mismatches {
+            return Err(MatchTableError::InvalidDiff(
+                "the tables have different maximum numbers of mismatches",
+            ));
+        }
+        if self.reference_kmer_count != other.reference_kmer_count
+            || self.query_kmer_count != other.query_kmer_count
+        {
+            return Err(MatchTableError::InvalidDiff(
+                "the tables have different numbers of kmers",
+            ));
+        }
+        if self.quadrants != other.quadrants {
+            return Err(MatchTableError::InvalidDiff(
+                "the tables have different computed quadrants",
+            ));
+        }
+
+        let mut diff = MatchTableDiff {
+            only_in_self: Default::default(),
+            only_in_other: Default::default(),
+        };
+        for quadrant in self.quadrants.iter() {
+            let only_in_self = &mut diff.only_in_self[quadrant as usize];
+            let only_in_other = &mut diff.only_in_other[quadrant as usize];
+            for primary_index in 0..self.primary_kmer_count(quadrant) {
+                let mut own = self.row_matches(quadrant, primary_index).peekable();
+                let mut others = other.row_matches(quadrant, primary_index).peekable();
+                loop {
+                    match (own.peek(), others.peek()) {
+                        (None, None) => break,
+                        (Some(_), None) => only_in_self.extend(
+                            own.by_ref()
+                                .map(|secondary_rc_index| (primary_index, secondary_rc_index)),
+                        ),
+                        (None, Some(_)) => only_in_other.extend(
+                            others
+                                .by_ref()
+                                .map(|secondary_rc_index| (primary_index, secondary_rc_index)),
+                        ),
+                        (Some(own_index), Some(other_index)) => match own_index.cmp(other_index) {
+                            Ordering::Less => {
+                                only_in_self.push((primary_index, own.next().unwrap()))
+                            }
+                            Ordering::Greater => {
+                                only_in_other.push((primary_index, others.next().unwrap()))
+                            }
+                            Ordering::Equal => {
+                                own.next();
+                                others.next();
+                            }
+                        },
+                    }
+                }
+            }
+        }
+        Ok(diff)
+    }
+}
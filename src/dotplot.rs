@@ -0,0 +1,129 @@
+//! ASCII dot plots of match tables, for debugging and for tests.
+
+use std::fmt::{Debug, Display, Formatter};
+
+use crate::{MatchTable, Quadrant, StorageIndex};
+
+/// The maximum width of the dot plots of the [`Debug`] output of a [`MatchTable`].
+const DEBUG_DOTPLOT_WIDTH: usize = 64;
+
+/// An ASCII dot plot of a quadrant, see [`MatchTable::fmt_dotplot`].
+pub struct Dotplot<'table, Index: StorageIndex = u32> {
+    table: &'table MatchTable<Index>,
+    quadrant: Quadrant,
+    max_width: usize,
+}
+
+impl<Index: StorageIndex> MatchTable<Index> {
+    /// Returns a [`Display`]able ASCII dot plot of the given quadrant that is at most `max_width` characters wide and high.
+    ///
+    /// The first line names the quadrant and its number of kmers.
+    /// It is followed by one line per primary kmer, with one character per secondary rc kmer,
+    /// which is `*` if the kmers match and `.` otherwise.
+    /// So an inner is a diagonal from the top left to the bottom right.
+    /// If the quadrant has more than `max_width` primary or secondary kmers,
+    /// then each character covers a square of kmers, which is `*` if it contains any match,
+    /// and the first line reports the number of kmers per character.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTable, Quadrant};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"TTACGGATTT").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"GGTCCGTGG").unwrap();
+    /// let matches = MatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    ///
+    /// assert_eq!(
+    ///     matches.fmt_dotplot(Quadrant::ReferenceQuery, 10).to_string(),
+    ///     "reference_query: 7 primary x 6 secondary rc kmers\n\
+    ///      ......\n\
+    ///      ......\n\
+    ///      ..*...\n\
+    ///      ...*..\n\
+    ///      ......\n\
+    ///      ......\n\
+    ///      ......\n",
+    /// );
+    /// assert_eq!(
+    ///     matches.fmt_dotplot(Quadrant::ReferenceQuery, 3).to_string(),
+    ///     "reference_query: 7 primary x 6 secondary rc kmers, 3 x 3 kmers per character\n\
+    ///      *.\n\
+    ///      .*\n\
+    ///      ..\n",
+    /// );
+    /// ```
+    pub fn fmt_dotplot(&self, quadrant: Quadrant, max_width: usize) -> Dotplot<'_, Index> {
+        Dotplot {
+            table: self,
+            quadrant,
+            max_width: max_width.max(1),
+        }
+    }
+}
+
+impl<Index: StorageIndex> Display for Dotplot<'_, Index> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let primary_kmer_count = self.table.primary_kmer_count(self.quadrant);
+        let secondary_kmer_count = self.table.secondary_kmer_count(self.quadrant);
+        let kmers_per_character = primary_kmer_count
+            .max(secondary_kmer_count)
+            .div_ceil(self.max_width)
+            .max(1);
+        let width = secondary_kmer_count.div_ceil(kmers_per_character);
+        let height = primary_kmer_count.div_ceil(kmers_per_character);
+
+        write!(
+            f,
+            "{}: {primary_kmer_count} primary x {secondary_kmer_count} secondary rc kmers",
+            self.quadrant
+        )?;
+        if kmers_per_character > 1 {
+            write!(
+                f,
+                ", {kmers_per_character} x {kmers_per_character} kmers per character"
+            )?;
+        }
+        writeln!(f)?;
+
+        let mut cells = vec![b'.'; width * height];
+        for (primary_index, secondary_rc_index) in self.table.matches(self.quadrant) {
+            cells[primary_index / kmers_per_character * width
+                + secondary_rc_index / kmers_per_character] = b'*';
+        }
+        for row in cells.chunks(width.max(1)).take(height) {
+            writeln!(
+                f,
+                "{}",
+                std::str::from_utf8(row).unwrap_or_else(|_| unreachable!())
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Shows the parameters and the kmer counts of the table, followed by a [dot plot](MatchTable::fmt_dotplot) of each computed quadrant
+/// that is at most 64 characters wide.
+impl<Index: StorageIndex> Debug for MatchTable<Index> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "MatchTable {{ minimum_length: {}, max_mismatches: {}, reference_kmer_count: {}, query_kmer_count: {} }}",
+            self.minimum_length(),
+            self.max_mismatches(),
+            self.reference_kmer_count(),
+            self.query_kmer_count(),
+        )?;
+        for quadrant in self.quadrants().iter() {
+            write!(f, "{}", self.fmt_dotplot(quadrant, DEBUG_DOTPLOT_WIDTH))?;
+        }
+        Ok(())
+    }
+}
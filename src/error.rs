@@ -0,0 +1,252 @@
+//! The error type of match table construction.
+
+/// An error when constructing or loading a [`MatchTable`](crate::MatchTable).
+#[derive(Debug, thiserror::Error)]
+pub enum MatchTableError {
+    /// The minimum length is zero, or not greater than the maximum number of mismatches.
+    #[error(
+        "Invalid minimum length {minimum_length}: it must be positive and greater than the maximum number of mismatches {max_mismatches}"
+    )]
+    InvalidMinimumLength {
+        /// The minimum length of the inners.
+        minimum_length: usize,
+        /// The maximum number of mismatches.
+        max_mismatches: usize,
+    },
+
+    /// The band is empty.
+    #[error(
+        "Invalid band: the minimum offset {min_offset} is greater than the maximum offset {max_offset}"
+    )]
+    InvalidBand {
+        /// The minimum offset of the band.
+        min_offset: isize,
+        /// The maximum offset of the band.
+        max_offset: isize,
+    },
+
+    /// The window size of the minimizers is zero.
+    #[error("Invalid minimizer window: the window size must be positive")]
+    InvalidMinimizerWindow,
+
+    /// The maximum number of kmer occurrences is zero.
+    #[error("Invalid maximum number of kmer occurrences: it must be positive")]
+    InvalidMaxKmerOccurrences,
+
+    /// The maximum length of the inners is less than their minimum length.
+    #[error(
+        "Invalid maximum length {maximum_length}: it must be at least the minimum length {minimum_length}"
+    )]
+    InvalidMaximumLength {
+        /// The maximum length of the inners.
+        maximum_length: usize,
+        /// The minimum length of the inners.
+        minimum_length: usize,
+    },
+
+    /// The threshold of the low-complexity filter is negative or not finite.
+    #[error("Invalid low-complexity threshold {threshold}: it must be finite and not negative")]
+    InvalidLowComplexityThreshold {
+        /// The threshold of the low-complexity filter.
+        threshold: f64,
+    },
+
+    /// The false positive rate of the Bloom filter does not lie strictly between zero and one.
+    #[error(
+        "Invalid false positive rate {false_positive_rate}: it must lie strictly between zero and one"
+    )]
+    InvalidFalsePositiveRate {
+        /// The false positive rate of the Bloom filter.
+        false_positive_rate: f64,
+    },
+
+    /// A sequence is shorter than the minimum length, so it contains no kmers.
+    #[error(
+        "The {sequence} has length {length}, which is shorter than the minimum length {minimum_length}"
+    )]
+    SequenceTooShort {
+        /// The name of the sequence, either `"reference"` or `"query"`.
+        sequence: &'static str,
+        /// The length of the sequence.
+        length: usize,
+        /// The minimum length of the inners.
+        minimum_length: usize,
+    },
+
+    /// A sequence given to [`MatchTable::from_ascii`](crate::MatchTable::from_ascii) contains a character that is not `A`, `C`, `G` or `T`.
+    #[error(
+        "The {sequence} contains the invalid character '{}' at position {position}, expected one of 'ACGT'",
+        char::from(*character).escape_default()
+    )]
+    InvalidCharacter {
+        /// The name of the sequence, either `"reference"` or `"query"`.
+        sequence: &'static str,
+        /// The position of the first invalid character in the sequence.
+        position: usize,
+        /// The invalid character as ASCII byte.
+        character: u8,
+    },
+
+    /// A mask does not have the same length as its sequence.
+    #[error(
+        "The mask of the {sequence} has length {mask_length}, but the {sequence} has length {sequence_length}"
+    )]
+    MaskLengthMismatch {
+        /// The name of the sequence, either `"reference"` or `"query"`.
+        sequence: &'static str,
+        /// The length of the mask.
+        mask_length: usize,
+        /// The length of the sequence.
+        sequence_length: usize,
+    },
+
+    /// An excluded interval is reversed or does not lie within its sequence.
+    #[error(
+        "The excluded interval {start}..{end} of the {sequence} does not lie within the {sequence} of length {sequence_length}"
+    )]
+    InvalidInterval {
+        /// The name of the sequence, either `"reference"` or `"query"`.
+        sequence: &'static str,
+        /// The start of the interval.
+        start: usize,
+        /// The end of the interval.
+        end: usize,
+        /// The length of the sequence.
+        sequence_length: usize,
+    },
+
+    /// A quadrant has more pairs of kmers than bits can be addressed on this platform.
+    ///
+    /// On 64-bit platforms, this happens only for quadrants far beyond any available memory,
+    /// while on 32-bit platforms, quadrants are limited to 2^29 bits.
+    #[error(
+        "A quadrant of {primary_kmer_count} x {secondary_kmer_count} kmers has more pairs than the at most {max_bit_count} bits that can be addressed on this platform"
+    )]
+    QuadrantTooLarge {
+        /// The number of primary kmers of the quadrant.
+        primary_kmer_count: usize,
+        /// The number of secondary kmers of the quadrant.
+        secondary_kmer_count: usize,
+        /// The maximum number of bits of a quadrant on this platform.
+        max_bit_count: usize,
+    },
+
+    /// A quadrant in [sparse storage](crate::StorageBackend::Sparse) has more kmers than its indices can represent,
+    /// see [`MatchTableBuilder::index_type`](crate::MatchTableBuilder::index_type).
+    #[error(
+        "A quadrant of {primary_kmer_count} x {secondary_kmer_count} kmers has more kmers than {index_bits}-bit indices can represent"
+    )]
+    IndexTypeTooNarrow {
+        /// The number of primary kmers of the quadrant.
+        primary_kmer_count: usize,
+        /// The number of secondary kmers of the quadrant.
+        secondary_kmer_count: usize,
+        /// The number of bits of the index type of the table.
+        index_bits: u32,
+    },
+
+    /// The storage of a quadrant cannot be allocated.
+    #[error(
+        "Cannot allocate storage for a quadrant of {primary_kmer_count} x {secondary_kmer_count} kmers"
+    )]
+    AllocationTooLarge {
+        /// The number of primary kmers of the quadrant.
+        primary_kmer_count: usize,
+        /// The number of secondary kmers of the quadrant.
+        secondary_kmer_count: usize,
+    },
+
+    /// A binary match table was written in an unsupported version of the format.
+    #[error(
+        "The binary match table has format version {version}, but only versions up to {supported_version} are supported"
+    )]
+    UnsupportedBinaryVersion {
+        /// The version of the format of the binary match table.
+        version: u32,
+        /// The newest version of the format supported by this version of the crate.
+        supported_version: u32,
+    },
+
+    /// The input of [`MatchTable::read_binary`](crate::MatchTable::read_binary) is not a valid binary match table.
+    #[error("Invalid binary match table: {0}")]
+    InvalidBinaryFormat(&'static str),
+
+    /// The window of a [`WindowScanner`](crate::WindowScanner) is shorter than the minimum length, or its stride is zero.
+    #[error(
+        "Invalid scanning window of size {window_size} with stride {stride}: the size must be at least the minimum length {minimum_length} and the stride must be positive"
+    )]
+    InvalidWindow {
+        /// The size of the window.
+        window_size: usize,
+        /// The distance between the starts of consecutive windows.
+        stride: usize,
+        /// The minimum length of the inners.
+        minimum_length: usize,
+    },
+
+    /// The memory budget of [`MatchTableBuilder::try_for_each_chunk`](crate::MatchTableBuilder::try_for_each_chunk)
+    /// does not suffice for a chunk of a single kmer.
+    #[error(
+        "The memory budget of {memory_budget} bytes is too small, at least {required_bytes} bytes are required"
+    )]
+    MemoryBudgetTooSmall {
+        /// The configured memory budget in bytes.
+        memory_budget: u64,
+        /// The estimated memory required for a chunk of a single kmer.
+        required_bytes: u64,
+    },
+
+    /// An input differs from the one the table was computed from, see [`MatchTable::validate_against`](crate::MatchTable::validate_against).
+    #[error("The {input} differs from the one the table was computed from")]
+    FingerprintMismatch {
+        /// The input that differs, either `"reference"`, `"query"` or `"parameters"`.
+        input: &'static str,
+    },
+
+    /// The table has no fingerprint of its inputs to validate against, see [`MatchTable::validate_against`](crate::MatchTable::validate_against).
+    #[error("The table has no fingerprint of its inputs")]
+    MissingFingerprint,
+
+    /// The construction was cancelled by setting the flag given to [`MatchTableBuilder::cancellation`](crate::MatchTableBuilder::cancellation).
+    #[error("The construction was cancelled")]
+    Cancelled,
+
+    /// A CIGAR string given to [`AlignmentProjection::from_cigar`](crate::AlignmentProjection::from_cigar) is malformed.
+    #[error("Invalid CIGAR string at byte {position}")]
+    InvalidCigar {
+        /// The byte position of the first invalid character, or the length of the string if it ends with a length.
+        position: usize,
+    },
+
+    /// The tables given to [`MatchTable::merge`](crate::MatchTable::merge) cannot be merged.
+    #[error("Cannot merge tables: {0}")]
+    InvalidMerge(&'static str),
+
+    /// The tables given to [`MatchTable::diff`](crate::MatchTable::diff) cannot be compared.
+    #[error("Cannot compare tables: {0}")]
+    InvalidDiff(&'static str),
+
+    /// No GPU is available for [`MatchTableBuilder::build_gpu`](crate::MatchTableBuilder::build_gpu), or the sequences do not fit into its buffers.
+    #[cfg(feature = "gpu")]
+    #[error("GPU error: {0}")]
+    Gpu(String),
+
+    /// A dense array of the matches would have more elements than allowed,
+    /// see [`MatchTable::to_dense_window`](crate::MatchTable::to_dense_window).
+    #[cfg(feature = "ndarray")]
+    #[error(
+        "A dense array of {row_count} x {column_count} elements has more than the at most {max_element_count} elements"
+    )]
+    DenseArrayTooLarge {
+        /// The number of rows of the array, i.e. of primary kmers.
+        row_count: usize,
+        /// The number of columns of the array, i.e. of secondary kmers.
+        column_count: usize,
+        /// The maximum number of elements of a dense array.
+        max_element_count: usize,
+    },
+
+    /// An IO error occurred while creating a file-backed table or reading or writing a binary table.
+    #[error("IO error: {0}")]
+    IO(#[from] std::io::Error),
+}
@@ -0,0 +1,264 @@
+//! Extension of matches along their diagonals into longer inners.
+
+use std::collections::HashMap;
+
+use crate::{Inner, MatchTable, Quadrant, StorageIndex};
+
+/// Whether a match cannot be extended along its diagonal, see [`MatchTable::matches_with_maximality`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Maximality {
+    /// The match is the first match of its run, i.e. the preceding pair of kmers on its diagonal does not match.
+    pub left: bool,
+    /// The match is the last match of its run, i.e. the succeeding pair of kmers on its diagonal does not match.
+    pub right: bool,
+}
+
+impl Maximality {
+    /// Returns `true` if the match is both left- and right-maximal, i.e. it is a maximal inner of exactly the minimum length.
+    pub fn is_maximal(&self) -> bool {
+        self.left && self.right
+    }
+}
+
+impl<Index: StorageIndex> MatchTable<Index> {
+    /// Returns the length of the longest inner that starts with the match `(primary_index, secondary_rc_index)` in the given quadrant,
+    /// or `None` if the kmers do not match.
+    ///
+    /// The inner is extended along the diagonal for as long as the consecutive kmers match,
+    /// so a run of `n` matches yields an inner of length `minimum_length + n - 1`.
+    /// If the table allows no mismatches and is not sparsified, then this is the length of the longest error-free inner
+    /// that starts at both positions, ending at the end of a sequence or contig, or before an excluded kmer.
+    /// The extension stops at the [maximum length](Self::maximum_length) of the table.
+    /// Takes time linear in the length of the run.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTable, Quadrant};
+    ///
+    /// // The reverse complement of `GGGGA` is `TCCCC`.
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAATCCCCAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+    /// let matches = MatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    ///
+    /// assert_eq!(matches.longest_match_length(Quadrant::ReferenceReference, 1, 2), Some(5));
+    /// assert_eq!(matches.longest_match_length(Quadrant::ReferenceReference, 2, 3), Some(4));
+    /// assert_eq!(matches.longest_match_length(Quadrant::ReferenceReference, 2, 2), None);
+    /// ```
+    pub fn longest_match_length(
+        &self,
+        quadrant: Quadrant,
+        primary_index: usize,
+        secondary_rc_index: usize,
+    ) -> Option<usize> {
+        self.has_match(quadrant, primary_index, secondary_rc_index)
+            .then(|| {
+                self.minimum_length
+                    + self.diagonal_run_length(
+                        quadrant,
+                        primary_index + 1,
+                        secondary_rc_index + 1,
+                        self.max_run_length() - 1,
+                    )
+            })
+    }
+
+    /// Returns an iterator over all matches of the given quadrant together with the length of the longest inner starting with them
+    /// as `(primary_index, secondary_rc_index, length)` triples.
+    ///
+    /// The matches are returned in the same order as by [`matches`](Self::matches),
+    /// and the lengths are the same as returned by [`longest_match_length`](Self::longest_match_length).
+    /// Each run of consecutive matches along a diagonal is extended only once,
+    /// so the iteration takes time linear in the number of matches, plus the time for iterating the matches.
+    /// Like the lengths of `longest_match_length`, the lengths are at most the [maximum length](Self::maximum_length) of the table.
+    pub fn matches_with_length(
+        &self,
+        quadrant: Quadrant,
+    ) -> impl Iterator<Item = (usize, usize, usize)> + '_ {
+        // The primary index and uncapped length of the last match of each diagonal, keyed by `secondary_rc_index - primary_index`.
+        let mut previous_matches = HashMap::<isize, (usize, usize)>::new();
+        let maximum_length = self.maximum_length.unwrap_or(usize::MAX);
+
+        self.matches(quadrant)
+            .map(move |(primary_index, secondary_rc_index)| {
+                let diagonal = secondary_rc_index as isize - primary_index as isize;
+                let length = match previous_matches.get(&diagonal) {
+                    Some(&(previous_primary_index, previous_length))
+                        if previous_primary_index + 1 == primary_index =>
+                    {
+                        previous_length - 1
+                    }
+                    _ => {
+                        self.minimum_length
+                            + self.diagonal_run_length(
+                                quadrant,
+                                primary_index + 1,
+                                secondary_rc_index + 1,
+                                usize::MAX,
+                            )
+                    }
+                };
+                previous_matches.insert(diagonal, (primary_index, length));
+                (
+                    primary_index,
+                    secondary_rc_index,
+                    length.min(maximum_length),
+                )
+            })
+    }
+
+    /// Returns an iterator over all matches of the given quadrant together with their [maximality](Maximality)
+    /// as `(primary_index, secondary_rc_index, maximality)` triples.
+    ///
+    /// A match is left-maximal if the inner of its kmers cannot be extended to the left,
+    /// and right-maximal if it cannot be extended to the right, i.e. if there is no match before or after it on its diagonal.
+    /// So keeping only the left-maximal matches keeps one representative of each [run](Self::runs).
+    /// The matches are returned in the same order as by [`matches`](Self::matches).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTable, Maximality, Quadrant};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"TTACGGATTT").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"GGTCCGTGG").unwrap();
+    /// let matches = MatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    ///
+    /// // The inner `ACGGA` consists of the matches `(2, 2)` and `(3, 3)`.
+    /// assert_eq!(
+    ///     matches.matches_with_maximality(Quadrant::ReferenceQuery).collect::<Vec<_>>(),
+    ///     vec![
+    ///         (2, 2, Maximality { left: true, right: false }),
+    ///         (3, 3, Maximality { left: false, right: true }),
+    ///     ],
+    /// );
+    /// ```
+    pub fn matches_with_maximality(
+        &self,
+        quadrant: Quadrant,
+    ) -> impl Iterator<Item = (usize, usize, Maximality)> + '_ {
+        let primary_kmer_count = self.primary_kmer_count(quadrant);
+        let secondary_kmer_count = self.secondary_kmer_count(quadrant);
+        self.matches(quadrant)
+            .map(move |(primary_index, secondary_rc_index)| {
+                let left = primary_index == 0
+                    || secondary_rc_index == 0
+                    || !self.has_match(quadrant, primary_index - 1, secondary_rc_index - 1);
+                let right = primary_index + 1 == primary_kmer_count
+                    || secondary_rc_index + 1 == secondary_kmer_count
+                    || !self.has_match(quadrant, primary_index + 1, secondary_rc_index + 1);
+                (
+                    primary_index,
+                    secondary_rc_index,
+                    Maximality { left, right },
+                )
+            })
+    }
+
+    /// Returns the longest inner starting at each primary kmer of the given quadrant, indexed by primary index,
+    /// or `None` for primary kmers without a match.
+    ///
+    /// Unlike [`matches`](Self::matches), which reports the entry points of kmers, this reports for each primary position
+    /// the maximal interval over which an error-free inner exists, i.e. the matching statistics of the primary
+    /// against the reverse complement of the secondary, restricted to lengths of at least the minimum length.
+    /// Of several longest inners, the one with the smallest secondary rc start is reported.
+    /// Like [`matches_with_length`](Self::matches_with_length), whose lengths these are,
+    /// the inners stop at the [maximum length](Self::maximum_length) of the table,
+    /// and if the table was constructed with mismatches, then they are maximal runs of matching kmers.
+    /// Takes time linear in the number of matches, plus the time for iterating the matches.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTable, Quadrant};
+    ///
+    /// // The reverse complement of `GGGGA` is `TCCCC`.
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAATCCCCAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+    /// let matches = MatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    ///
+    /// let longest = matches.longest_inners(Quadrant::ReferenceReference);
+    /// assert_eq!(longest.len(), matches.reference_kmer_count());
+    /// let inner = longest[1].as_ref().unwrap();
+    /// assert_eq!(inner.primary, 1..6);
+    /// assert_eq!(inner.secondary_rc, 2..7);
+    /// assert_eq!(
+    ///     matches.matching_statistics(Quadrant::ReferenceReference),
+    ///     vec![0, 5, 4, 0, 0, 0, 0, 5, 4, 0, 0],
+    /// );
+    /// ```
+    pub fn longest_inners(&self, quadrant: Quadrant) -> Vec<Option<Inner>> {
+        let mut longest_inners: Vec<Option<Inner>> = vec![None; self.primary_kmer_count(quadrant)];
+        for (primary_index, secondary_rc_index, length) in self.matches_with_length(quadrant) {
+            let longest_inner = &mut longest_inners[primary_index];
+            if longest_inner
+                .as_ref()
+                .is_none_or(|inner| inner.primary.len() < length)
+            {
+                *longest_inner = Some(Inner {
+                    quadrant,
+                    primary: primary_index..primary_index + length,
+                    secondary_rc: secondary_rc_index..secondary_rc_index + length,
+                });
+            }
+        }
+        longest_inners
+    }
+
+    /// Returns the length of the longest inner starting at each primary kmer of the given quadrant, indexed by primary index,
+    /// or zero for primary kmers without a match.
+    ///
+    /// These are the lengths of the [`longest_inners`](Self::longest_inners).
+    pub fn matching_statistics(&self, quadrant: Quadrant) -> Vec<usize> {
+        self.longest_inners(quadrant)
+            .into_iter()
+            .map(|inner| inner.map_or(0, |inner| inner.primary.len()))
+            .collect()
+    }
+
+    /// Returns the number of consecutive matches along the diagonal starting at `(primary_index, secondary_rc_index)`,
+    /// but at most `limit`.
+    fn diagonal_run_length(
+        &self,
+        quadrant: Quadrant,
+        primary_index: usize,
+        secondary_rc_index: usize,
+        limit: usize,
+    ) -> usize {
+        let primary_kmer_count = self.primary_kmer_count(quadrant);
+        let secondary_kmer_count = self.secondary_kmer_count(quadrant);
+        (0..)
+            .take_while(|&offset| {
+                primary_index + offset < primary_kmer_count
+                    && secondary_rc_index + offset < secondary_kmer_count
+                    && self.has_match(
+                        quadrant,
+                        primary_index + offset,
+                        secondary_rc_index + offset,
+                    )
+            })
+            .take(limit)
+            .count()
+    }
+}
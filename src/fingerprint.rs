@@ -0,0 +1,202 @@
+//! Fingerprints of the inputs of a match table, for detecting cached tables that are stale.
+
+use compact_genome::interface::{alphabet::Alphabet, sequence::GenomeSequence};
+
+use crate::{MatchTable, MatchTableBuilder, MatchTableError, StorageIndex};
+
+/// Hashes of the sequences and the parameters a table was computed from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Fingerprint {
+    pub reference: u64,
+    pub query: u64,
+    pub parameters: u64,
+}
+
+impl Fingerprint {
+    pub fn new(
+        reference: &[u8],
+        query: &[u8],
+        options: &MatchTableBuilder<impl StorageIndex>,
+    ) -> Self {
+        Self {
+            reference: sequence_hash(reference.iter().copied()),
+            query: sequence_hash(query.iter().copied()),
+            parameters: parameters_hash(options),
+        }
+    }
+}
+
+/// A 64-bit FNV-1a hasher, whose hashes do not depend on the platform or the version of Rust.
+struct FnvHasher(u64);
+
+impl FnvHasher {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    fn new() -> Self {
+        Self(Self::OFFSET_BASIS)
+    }
+
+    fn write_u8(&mut self, byte: u8) {
+        self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(Self::PRIME);
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        self.write_usize(bytes.len());
+        for &byte in bytes {
+            self.write_u8(byte);
+        }
+    }
+
+    fn write_usize(&mut self, value: usize) {
+        for byte in (value as u64).to_le_bytes() {
+            self.write_u8(byte);
+        }
+    }
+}
+
+/// Returns the hash of a sequence given as ASCII characters.
+fn sequence_hash(characters: impl IntoIterator<Item = u8>) -> u64 {
+    let mut hasher = FnvHasher::new();
+    let mut length = 0;
+    for character in characters {
+        hasher.write_u8(character);
+        length += 1;
+    }
+    hasher.write_usize(length);
+    hasher.0
+}
+
+/// Returns the hash of the options that affect the matches of a table.
+///
+/// The storage, the index backend, the strategy, the Bloom filter, the parallelism, the maximum length, the progress reporter and the cancellation flag do not affect the matches,
+/// so tables built with different choices of these have the same fingerprint.
+fn parameters_hash(options: &MatchTableBuilder<impl StorageIndex>) -> u64 {
+    let mut hasher = FnvHasher::new();
+    hasher.write_usize(options.minimum_length);
+    hasher.write_usize(options.max_mismatches);
+    // The debug representations of these options are unambiguous and do not change between runs.
+    for debug in [
+        format!("{:?}", options.ambiguity_policy),
+        format!("{:?}", options.skip_n),
+        format!("{:?}", options.low_complexity_filter),
+        format!("{:?}", options.band),
+        format!("{:?}", options.minimizer_window),
+        format!("{:?}", options.orientation),
+        format!("{:?}", options.quadrants.bits()),
+    ] {
+        hasher.write_bytes(debug.as_bytes());
+    }
+    // Hashed only if set, so the fingerprints of tables built without this option do not change.
+    if let Some(max_kmer_occurrences) = options.max_kmer_occurrences {
+        hasher.write_usize(max_kmer_occurrences);
+    }
+    if options.unique_matches_only {
+        hasher.write_u8(1);
+    }
+    for mask in [&options.reference_mask, &options.query_mask] {
+        match mask {
+            Some(mask) => {
+                hasher.write_u8(1);
+                hasher.write_usize(mask.len());
+                hasher.write_usize(mask.count_ones());
+                for position in mask.iter_ones() {
+                    hasher.write_usize(position);
+                }
+            }
+            None => hasher.write_u8(0),
+        }
+    }
+    for intervals in [
+        &options.reference_excluded_intervals,
+        &options.query_excluded_intervals,
+    ] {
+        hasher.write_usize(intervals.len());
+        for interval in intervals {
+            hasher.write_usize(interval.start);
+            hasher.write_usize(interval.end);
+        }
+    }
+    hasher.0
+}
+
+impl<Index: StorageIndex> MatchTable<Index> {
+    /// Check that this table was computed from the given reference and query.
+    ///
+    /// Tables store hashes of their sequences, which are kept by [`write_binary`](Self::write_binary),
+    /// so a cached table can be checked against the sequences it is about to be used with.
+    /// For tables of [contigs](MatchTableBuilder::build_contigs), the sequences are the concatenations of the contigs,
+    /// and for tables of a [sequence against itself](MatchTableBuilder::build_self), the query is empty.
+    ///
+    /// Returns [`MatchTableError::FingerprintMismatch`] if a sequence differs from the one the table was computed from,
+    /// and [`MatchTableError::MissingFingerprint`] if the table has no fingerprint,
+    /// because it was [merged](Self::merge) or loaded from a version of the binary format before fingerprints.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTable, MatchTableBuilder};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+    /// let options = MatchTableBuilder::new(4);
+    /// let matches = options.build(reference.as_genome_subsequence(), query.as_genome_subsequence());
+    ///
+    /// let mut binary = Vec::new();
+    /// matches.write_binary(&mut binary).unwrap();
+    /// let cached: MatchTable = MatchTable::read_binary(binary.as_slice()).unwrap();
+    /// assert!(cached.validate_against(reference.as_genome_subsequence(), query.as_genome_subsequence()).is_ok());
+    /// assert!(cached.validate_options(&options).is_ok());
+    ///
+    /// let edited_query = VectorGenome::from_slice_u8(b"AAAAAAAC").unwrap();
+    /// assert!(cached.validate_against(reference.as_genome_subsequence(), edited_query.as_genome_subsequence()).is_err());
+    /// assert!(cached.validate_options(&MatchTableBuilder::new(5)).is_err());
+    /// ```
+    pub fn validate_against<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        &self,
+        reference: &GenomeSubsequence,
+        query: &GenomeSubsequence,
+    ) -> Result<(), MatchTableError> {
+        let fingerprint = self
+            .fingerprint
+            .ok_or(MatchTableError::MissingFingerprint)?;
+        let hash = |sequence: &GenomeSubsequence| {
+            sequence_hash(sequence.iter().map(|character| character.clone().into()))
+        };
+        if hash(reference) != fingerprint.reference {
+            Err(MatchTableError::FingerprintMismatch { input: "reference" })
+        } else if hash(query) != fingerprint.query {
+            Err(MatchTableError::FingerprintMismatch { input: "query" })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Check that this table was computed with options that produce the same matches as the given ones.
+    ///
+    /// Only the options that affect the matches are compared,
+    /// i.e. all options except the storage, the index backend, the strategy, the Bloom filter, the parallelism, the progress reporter and the cancellation flag.
+    ///
+    /// Returns an error under the same conditions as [`validate_against`](Self::validate_against).
+    pub fn validate_options(
+        &self,
+        options: &MatchTableBuilder<impl StorageIndex>,
+    ) -> Result<(), MatchTableError> {
+        let fingerprint = self
+            .fingerprint
+            .ok_or(MatchTableError::MissingFingerprint)?;
+        if parameters_hash(options) == fingerprint.parameters {
+            Ok(())
+        } else {
+            Err(MatchTableError::FingerprintMismatch {
+                input: "parameters",
+            })
+        }
+    }
+}
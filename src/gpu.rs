@@ -0,0 +1,596 @@
+//! Experimental construction of match tables on the GPU via `wgpu`.
+//!
+//! The kmers of all four texts are hashed by a compute shader,
+//! and a second compute shader compares the hashes of all pairs of primary and reverse-complemented secondary kmers,
+//! marking the pairs with equal hashes in a bit matrix.
+//! The bit matrix is computed in tiles that fit into a GPU buffer,
+//! and the marked pairs of each tile are verified on the CPU to rule out hash collisions.
+
+use std::sync::mpsc;
+
+use log::{debug, warn};
+use wgpu::util::DeviceExt;
+
+use crate::{
+    ContigLayout, MatchOrientation, MatchTableBuilder, MatchTableError, ProgressEvent, Quadrant,
+    StorageIndex,
+    band::Band,
+    construction::{MatchSink, QuadrantSinks, Texts, find_matches},
+    mask::{AmbiguityPolicy, KmerFlags},
+};
+
+/// The number of threads in a workgroup of both shaders.
+const WORKGROUP_SIZE: u32 = 64;
+
+/// The maximum size in bytes of the bit matrix of a tile.
+const MAX_TILE_BYTES: u64 = 1 << 26;
+
+/// Computes the FNV-1a hash of each kmer of a text packed into four characters per word.
+///
+/// If `reverse` is set, then the kmers of the reverse complement of the text are hashed,
+/// complementing each character via the `complement` table.
+const HASH_SHADER: &str = r"
+struct Parameters {
+    kmer_count: u32,
+    minimum_length: u32,
+    row_stride: u32,
+    text_length: u32,
+    reverse: u32,
+}
+
+@group(0) @binding(0) var<uniform> parameters: Parameters;
+@group(0) @binding(1) var<storage, read> text: array<u32>;
+@group(0) @binding(2) var<storage, read> complement: array<u32>;
+@group(0) @binding(3) var<storage, read_write> hashes: array<u32>;
+
+fn forward_character(position: u32) -> u32 {
+    return (text[position / 4u] >> (8u * (position % 4u))) & 0xffu;
+}
+
+fn character(position: u32) -> u32 {
+    if parameters.reverse != 0u {
+        return complement[forward_character(parameters.text_length - 1u - position)];
+    }
+    return forward_character(position);
+}
+
+@compute @workgroup_size(64)
+fn hash(@builtin(global_invocation_id) id: vec3<u32>) {
+    let kmer = id.y * parameters.row_stride + id.x;
+    if kmer >= parameters.kmer_count {
+        return;
+    }
+
+    var hash = 2166136261u;
+    for (var offset = 0u; offset < parameters.minimum_length; offset++) {
+        hash = (hash ^ character(kmer + offset)) * 16777619u;
+    }
+    hashes[kmer] = hash;
+}
+";
+
+/// Marks the pairs of primary and secondary rc kmers of a tile that have equal hashes.
+///
+/// Row `row` of the tile is the primary kmer `primary_start + row`.
+/// Column `column` is the secondary rc kmer `column_start + column`,
+/// or `primary_index + band_offset + column_start + column` in a banded tile.
+const MARK_SHADER: &str = r"
+struct Parameters {
+    primary_start: u32,
+    row_count: u32,
+    column_start: u32,
+    column_count: u32,
+    secondary_count: u32,
+    band_offset: i32,
+    banded: u32,
+    padding: u32,
+}
+
+@group(0) @binding(0) var<uniform> parameters: Parameters;
+@group(0) @binding(1) var<storage, read> primary_hashes: array<u32>;
+@group(0) @binding(2) var<storage, read> secondary_hashes: array<u32>;
+@group(0) @binding(3) var<storage, read_write> candidates: array<atomic<u32>>;
+
+@compute @workgroup_size(64)
+fn mark(@builtin(global_invocation_id) id: vec3<u32>) {
+    let column = id.x;
+    let row = id.y;
+    if column >= parameters.column_count || row >= parameters.row_count {
+        return;
+    }
+
+    let primary = parameters.primary_start + row;
+    var secondary = i32(parameters.column_start + column);
+    if parameters.banded != 0u {
+        secondary += i32(primary) + parameters.band_offset;
+    }
+    if secondary < 0 || u32(secondary) >= parameters.secondary_count {
+        return;
+    }
+
+    if primary_hashes[primary] == secondary_hashes[u32(secondary)] {
+        let bit = row * parameters.column_count + column;
+        atomicOr(&candidates[bit / 32u], 1u << (bit % 32u));
+    }
+}
+";
+
+/// Find all matches between the texts on the GPU and insert them into the sinks of their quadrants.
+///
+/// Falls back to [`find_matches`] if the options are not supported on the GPU.
+/// Returns the number of excluded reference and query kmers,
+/// or an error if no GPU is available or the texts do not fit into its buffers.
+pub(crate) fn find_matches_gpu(
+    texts: &Texts,
+    contigs: [&ContigLayout; 2],
+    options: &MatchTableBuilder<impl StorageIndex>,
+    sinks: QuadrantSinks<impl MatchSink>,
+) -> Result<[usize; 2], MatchTableError> {
+    if options.max_mismatches > 0
+        || options.orientation != MatchOrientation::ReverseComplement
+        || options.ambiguity_policy == AmbiguityPolicy::Compatible
+    {
+        warn!(
+            "The GPU supports only exact matching in reverse-complement orientation, falling back to the CPU"
+        );
+        return Ok(find_matches(texts, contigs, options, sinks));
+    }
+    let [reference_contigs, query_contigs] = contigs;
+    // The shaders compute signed secondary indices that may be as large as the sum of both lengths.
+    if i32::try_from(texts.reference.len() + texts.query.len()).is_err() {
+        return Err(gpu_error("the sequences are too long"));
+    }
+
+    let reference_flags = KmerFlags::new(
+        &texts.reference,
+        texts.frequent_reference_kmers(),
+        options.reference_mask.as_ref(),
+        &options.reference_excluded_intervals,
+        reference_contigs,
+        options,
+    );
+    let query_flags = KmerFlags::new(
+        &texts.query,
+        texts.frequent_query_kmers(),
+        options.query_mask.as_ref(),
+        &options.query_excluded_intervals,
+        query_contigs,
+        options,
+    );
+    debug!(
+        "Excluded {} reference kmers and {} query kmers",
+        reference_flags.excluded_kmer_count(),
+        query_flags.excluded_kmer_count()
+    );
+
+    let gpu = Gpu::new()?;
+    debug!("Hashing kmers on the GPU");
+    let minimum_length = options.minimum_length;
+    let [reference, query, reference_rc, query_rc] = [
+        (&texts.reference, &reference_flags, None),
+        (&texts.query, &query_flags, None),
+        (&texts.reference, &reference_flags, Some(&texts.complement)),
+        (&texts.query, &query_flags, Some(&texts.complement)),
+    ]
+    .map(|(text, flags, complement)| {
+        gpu.hash_kmers(text, complement, flags.excluded.len(), minimum_length)
+    });
+    let hashes = [reference?, query?, reference_rc?, query_rc?];
+    options.report(ProgressEvent::IndexesBuilt);
+
+    let QuadrantSinks {
+        reference_reference,
+        reference_query,
+        query_reference,
+        query_query,
+    } = sinks;
+    let secondaries = [
+        (
+            "reference",
+            [
+                (Quadrant::ReferenceReference, reference_reference),
+                (Quadrant::QueryReference, query_reference),
+            ],
+        ),
+        (
+            "query",
+            [
+                (Quadrant::ReferenceQuery, reference_query),
+                (Quadrant::QueryQuery, query_query),
+            ],
+        ),
+    ];
+    for (genome, quadrants) in secondaries {
+        for (quadrant, sink) in quadrants {
+            if !options.quadrants.contains(quadrant) {
+                continue;
+            }
+
+            let side = |is_reference: bool| {
+                if is_reference {
+                    (&texts.reference, texts.reference_rc(), &reference_flags)
+                } else {
+                    (&texts.query, texts.query_rc(), &query_flags)
+                }
+            };
+            let (primary_text, _, primary_flags) = side(quadrant.primary_is_reference());
+            let (_, secondary_rc_text, secondary_flags) = side(quadrant.secondary_is_reference());
+            let primary_hashes = &hashes[if quadrant.primary_is_reference() {
+                0
+            } else {
+                1
+            }];
+            let secondary_hashes = &hashes[if quadrant.secondary_is_reference() {
+                2
+            } else {
+                3
+            }];
+            let primary_kmer_count = primary_flags.excluded.len();
+            let secondary_kmer_count = secondary_flags.excluded.len();
+
+            debug!("Marking candidate matches of {quadrant:?} on the GPU");
+            gpu.mark_candidates(
+                primary_hashes,
+                secondary_hashes,
+                [primary_kmer_count, secondary_kmer_count],
+                options.band,
+                |primary_index, secondary_rc_index| {
+                    let secondary_index = secondary_kmer_count - 1 - secondary_rc_index;
+                    if !primary_flags.excluded[primary_index]
+                        && !secondary_flags.excluded[secondary_index]
+                        && (primary_flags.is_minimizer(primary_index)
+                            || secondary_flags.is_minimizer(secondary_index))
+                        && primary_text[primary_index..primary_index + minimum_length]
+                            .iter()
+                            .copied()
+                            .eq(secondary_rc_text.substring(secondary_rc_index, minimum_length))
+                    {
+                        sink.insert(primary_index, secondary_rc_index);
+                    }
+                },
+            )?;
+        }
+
+        let total = if genome == "reference" {
+            reference_flags.excluded.len()
+        } else {
+            query_flags.excluded.len()
+        };
+        options.report(ProgressEvent::KmersProcessed {
+            genome,
+            processed: total,
+            total,
+        });
+    }
+
+    Ok([
+        reference_flags.excluded_kmer_count(),
+        query_flags.excluded_kmer_count(),
+    ])
+}
+
+fn gpu_error(message: impl Into<String>) -> MatchTableError {
+    MatchTableError::Gpu(message.into())
+}
+
+/// The kmer hashes of a text in a GPU buffer.
+struct KmerHashes {
+    buffer: wgpu::Buffer,
+}
+
+/// A GPU device together with the compiled shaders.
+struct Gpu {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    limits: wgpu::Limits,
+    hash_pipeline: wgpu::ComputePipeline,
+    mark_pipeline: wgpu::ComputePipeline,
+}
+
+impl Gpu {
+    fn new() -> Result<Self, MatchTableError> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            ..Default::default()
+        }))
+        .ok_or_else(|| gpu_error("no GPU adapter is available"))?;
+        debug!("Using GPU adapter {:?}", adapter.get_info());
+
+        let limits = adapter.limits();
+        let (device, queue) = pollster::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("template-switch-error-free-inners"),
+                required_limits: limits.clone(),
+                ..Default::default()
+            },
+            None,
+        ))
+        .map_err(|error| gpu_error(error.to_string()))?;
+
+        let pipeline = |source: &str, entry_point: &str| {
+            let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some(entry_point),
+                source: wgpu::ShaderSource::Wgsl(source.into()),
+            });
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: None,
+                module: &module,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+        let hash_pipeline = pipeline(HASH_SHADER, "hash");
+        let mark_pipeline = pipeline(MARK_SHADER, "mark");
+
+        Ok(Self {
+            device,
+            queue,
+            limits,
+            hash_pipeline,
+            mark_pipeline,
+        })
+    }
+
+    /// Returns an error if a storage buffer of the given size cannot be bound.
+    fn check_buffer_size(&self, size: u64) -> Result<(), MatchTableError> {
+        if size > u64::from(self.limits.max_storage_buffer_binding_size)
+            || size > self.limits.max_buffer_size
+        {
+            Err(gpu_error(format!(
+                "a buffer of {size} bytes exceeds the limits of the GPU"
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn storage_buffer(&self, contents: &[u8]) -> wgpu::Buffer {
+        self.device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents,
+                usage: wgpu::BufferUsages::STORAGE,
+            })
+    }
+
+    fn uniform_buffer(&self, parameters: &[u32]) -> wgpu::Buffer {
+        let contents: Vec<_> = parameters
+            .iter()
+            .flat_map(|parameter| parameter.to_le_bytes())
+            .collect();
+        self.device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: &contents,
+                usage: wgpu::BufferUsages::UNIFORM,
+            })
+    }
+
+    fn bind_group(
+        &self,
+        pipeline: &wgpu::ComputePipeline,
+        buffers: &[&wgpu::Buffer],
+    ) -> wgpu::BindGroup {
+        let entries: Vec<_> = buffers
+            .iter()
+            .enumerate()
+            .map(|(binding, buffer)| wgpu::BindGroupEntry {
+                binding: binding as u32,
+                resource: buffer.as_entire_binding(),
+            })
+            .collect();
+        self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &entries,
+        })
+    }
+
+    /// Hash the first `kmer_count` kmers of the ASCII text,
+    /// or of its reverse complement if a complement table is given.
+    fn hash_kmers(
+        &self,
+        text: &[u8],
+        complement: Option<&[u8; 256]>,
+        kmer_count: usize,
+        minimum_length: usize,
+    ) -> Result<KmerHashes, MatchTableError> {
+        // Bindings must not be empty, so the buffers hold at least one word.
+        let mut packed_text = text.to_vec();
+        packed_text.resize(text.len().next_multiple_of(4).max(4), 0);
+        let hashes_size = (kmer_count.max(1) * 4) as u64;
+        self.check_buffer_size(packed_text.len() as u64)?;
+        self.check_buffer_size(hashes_size)?;
+
+        let text_buffer = self.storage_buffer(&packed_text);
+        let complement_table: Vec<_> = complement
+            .unwrap_or(&[0; 256])
+            .iter()
+            .flat_map(|&character| u32::from(character).to_le_bytes())
+            .collect();
+        let complement_buffer = self.storage_buffer(&complement_table);
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("kmer hashes"),
+            size: hashes_size,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        if kmer_count == 0 {
+            return Ok(KmerHashes { buffer });
+        }
+
+        let group_count = (kmer_count as u32).div_ceil(WORKGROUP_SIZE);
+        let group_count_x = group_count.min(self.limits.max_compute_workgroups_per_dimension);
+        let group_count_y = group_count.div_ceil(group_count_x);
+        let parameters = self.uniform_buffer(&[
+            kmer_count as u32,
+            minimum_length as u32,
+            group_count_x * WORKGROUP_SIZE,
+            text.len() as u32,
+            u32::from(complement.is_some()),
+            0,
+            0,
+            0,
+        ]);
+        let bind_group = self.bind_group(
+            &self.hash_pipeline,
+            &[&parameters, &text_buffer, &complement_buffer, &buffer],
+        );
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+            pass.set_pipeline(&self.hash_pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(group_count_x, group_count_y, 1);
+        }
+        self.queue.submit([encoder.finish()]);
+        Ok(KmerHashes { buffer })
+    }
+
+    /// Call `f` with each pair of primary and secondary rc kmers with equal hashes.
+    ///
+    /// If a band is given, then only the pairs within the band are compared.
+    fn mark_candidates(
+        &self,
+        primary_hashes: &KmerHashes,
+        secondary_hashes: &KmerHashes,
+        [primary_kmer_count, secondary_kmer_count]: [usize; 2],
+        band: Option<Band>,
+        mut f: impl FnMut(usize, usize),
+    ) -> Result<(), MatchTableError> {
+        if primary_kmer_count == 0 || secondary_kmer_count == 0 {
+            return Ok(());
+        }
+
+        // Clip the band to the offsets that occur in the quadrant, so that it fits into the shader's integers.
+        let band = match band {
+            Some(band) => {
+                let min_offset = band.min_offset.max(1 - primary_kmer_count as isize);
+                let max_offset = band.max_offset.min(secondary_kmer_count as isize - 1);
+                if min_offset > max_offset {
+                    return Ok(());
+                }
+                Some((
+                    min_offset as i32,
+                    Band {
+                        min_offset,
+                        max_offset,
+                    }
+                    .width(),
+                ))
+            }
+            None => None,
+        };
+        let width = band.map_or(secondary_kmer_count, |(_, width)| width);
+        let max_group_count = self.limits.max_compute_workgroups_per_dimension as usize;
+        let tile_bytes = MAX_TILE_BYTES
+            .min(u64::from(self.limits.max_storage_buffer_binding_size))
+            .min(self.limits.max_buffer_size);
+        let max_tile_bits = (tile_bytes * 8) as usize;
+        let column_count = width
+            .min(max_group_count * WORKGROUP_SIZE as usize)
+            .min(max_tile_bits);
+        let row_count = max_group_count.min(max_tile_bits / column_count);
+
+        let candidates = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("candidates"),
+            size: tile_bytes,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let staging = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("candidates staging"),
+            size: tile_bytes,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        for primary_start in (0..primary_kmer_count).step_by(row_count) {
+            let row_count = row_count.min(primary_kmer_count - primary_start);
+            for column_start in (0..width).step_by(column_count) {
+                let column_count = column_count.min(width - column_start);
+                let tile_size = ((row_count * column_count).div_ceil(32) * 4) as u64;
+
+                let parameters = self.uniform_buffer(&[
+                    primary_start as u32,
+                    row_count as u32,
+                    column_start as u32,
+                    column_count as u32,
+                    secondary_kmer_count as u32,
+                    band.map_or(0, |(min_offset, _)| min_offset) as u32,
+                    u32::from(band.is_some()),
+                    0,
+                ]);
+                let bind_group = self.bind_group(
+                    &self.mark_pipeline,
+                    &[
+                        &parameters,
+                        &primary_hashes.buffer,
+                        &secondary_hashes.buffer,
+                        &candidates,
+                    ],
+                );
+
+                let mut encoder = self
+                    .device
+                    .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+                encoder.clear_buffer(&candidates, 0, Some(tile_size));
+                {
+                    let mut pass =
+                        encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+                    pass.set_pipeline(&self.mark_pipeline);
+                    pass.set_bind_group(0, &bind_group, &[]);
+                    pass.dispatch_workgroups(
+                        (column_count as u32).div_ceil(WORKGROUP_SIZE),
+                        row_count as u32,
+                        1,
+                    );
+                }
+                encoder.copy_buffer_to_buffer(&candidates, 0, &staging, 0, tile_size);
+                self.queue.submit([encoder.finish()]);
+
+                let slice = staging.slice(..tile_size);
+                let (sender, receiver) = mpsc::channel();
+                slice.map_async(wgpu::MapMode::Read, move |result| {
+                    // The receiver waits until the mapping is finished.
+                    let _ = sender.send(result);
+                });
+                self.device.poll(wgpu::Maintain::Wait);
+                receiver
+                    .recv()
+                    .map_err(|error| gpu_error(error.to_string()))?
+                    .map_err(|error| gpu_error(error.to_string()))?;
+
+                {
+                    let words = slice.get_mapped_range();
+                    for (word_index, word) in words.chunks_exact(4).enumerate() {
+                        let mut word = u32::from_le_bytes(word.try_into().unwrap());
+                        while word != 0 {
+                            let bit = word_index * 32 + word.trailing_zeros() as usize;
+                            word &= word - 1;
+
+                            let primary_index = primary_start + bit / column_count;
+                            let column = column_start + bit % column_count;
+                            let secondary_rc_index = match band {
+                                Some((min_offset, _)) => (primary_index + column)
+                                    .wrapping_add_signed(min_offset as isize),
+                                None => column,
+                            };
+                            f(primary_index, secondary_rc_index);
+                        }
+                    }
+                }
+                staging.unmap();
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,213 @@
+//! Indexes of the primary sequences that support finding all occurrences of a kmer.
+
+use suffix::SuffixTable;
+
+use crate::{StorageIndex, band::Band};
+
+pub(crate) use fm_index::FmIndex;
+pub(crate) use hash_index::{HashKmerIndex, KmerPacker};
+
+mod fm_index;
+mod hash_index;
+
+/// The largest combined length of the reference and the query for which the hash join is chosen automatically.
+///
+/// The hash map requires more memory per kmer than the suffix table, so for large inputs the suffix table is preferred.
+const AUTOMATIC_HASH_JOIN_MAXIMUM_LENGTH: usize = 1 << 28;
+
+/// The largest band width for which the band scan is chosen automatically.
+///
+/// Comparing a kmer against a kmer usually fails after the first few characters,
+/// so scanning a band of this width is cheaper than an index lookup.
+const AUTOMATIC_BAND_SCAN_MAXIMUM_WIDTH: usize = 1024;
+
+/// The strategy used to find the matching kmers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConstructionStrategy {
+    /// Use [`BandScan`](Self::BandScan) if a narrow band is configured,
+    /// [`HashJoin`](Self::HashJoin) if it is applicable and the sequences are not too long,
+    /// and [`IndexLookup`](Self::IndexLookup) otherwise.
+    #[default]
+    Automatic,
+    /// Look up each reverse-complemented kmer in an index of the primary sequence, as selected by [`IndexBackend`].
+    IndexLookup,
+    /// Pack each kmer into a `u64` and join the reverse-complemented kmers with a hash map of the primary kmers.
+    ///
+    /// This is only applicable for error-free inners, and if the kmers fit into a `u64`,
+    /// which for DNA is the case for a minimum length of up to 32.
+    /// The positions of the kmers are stored in the [index type](crate::StorageIndex) of the table,
+    /// so with `u32` indices, it is only applicable to sequences shorter than 2^32 characters.
+    /// For minimum lengths of up to 32, the join is specialised to the minimum length at compile time,
+    /// unless kmers are matched in [forward](crate::MatchOrientation::Forward) orientation
+    /// or a sequence contains [ambiguous](crate::AmbiguityPolicy::Compatible) kmers.
+    /// If it is not applicable, then [`IndexLookup`](Self::IndexLookup) is used instead.
+    HashJoin,
+    /// Pack the kmers of the primary sequences and the reverse-complemented kmers of the secondary sequences into `u64`s,
+    /// sort each list, and merge the sorted lists to find the matches.
+    ///
+    /// Sorting and merging access memory sequentially, and sorting is done in parallel if [parallel](crate::MatchTableBuilder::parallel) construction is enabled,
+    /// so this can be faster than probing an index per kmer for large inputs, while requiring 16 bytes per kmer.
+    /// It is applicable under the same conditions as [`HashJoin`](Self::HashJoin), otherwise [`IndexLookup`](Self::IndexLookup) is used instead.
+    /// If kmers are matched [canonically](crate::MatchTableBuilder::canonical),
+    /// or a sequence contains [ambiguous](crate::AmbiguityPolicy::Compatible) kmers, then [`HashJoin`](Self::HashJoin) is used instead.
+    SortMerge,
+    /// Compare each reverse-complemented kmer directly against the primary kmers within the band,
+    /// as configured by [`MatchTableBuilder::band`](crate::MatchTableBuilder::band).
+    ///
+    /// If no band is configured, then each kmer is compared against all primary kmers, which takes quadratic time.
+    BandScan,
+}
+
+impl ConstructionStrategy {
+    /// Resolve the strategy to either [`IndexLookup`](Self::IndexLookup), [`HashJoin`](Self::HashJoin),
+    /// [`SortMerge`](Self::SortMerge) or [`BandScan`](Self::BandScan).
+    ///
+    /// The hash join and the sort-merge join are applicable only if the positions in the sequences fit into `Index`.
+    pub(crate) fn resolve<Index: StorageIndex>(
+        self,
+        reference: &[u8],
+        query: &[u8],
+        minimum_length: usize,
+        max_mismatches: usize,
+        band: Option<Band>,
+    ) -> Self {
+        let hash_join_applicable = max_mismatches == 0
+            && Index::try_from_usize(reference.len().max(query.len())).is_some()
+            && KmerPacker::bits_per_symbol([reference, query], minimum_length).is_some();
+
+        match self {
+            Self::Automatic
+                if band.is_some_and(|band| band.width() <= AUTOMATIC_BAND_SCAN_MAXIMUM_WIDTH) =>
+            {
+                Self::BandScan
+            }
+            Self::BandScan => Self::BandScan,
+            Self::Automatic
+                if hash_join_applicable
+                    && reference.len() + query.len() <= AUTOMATIC_HASH_JOIN_MAXIMUM_LENGTH =>
+            {
+                Self::HashJoin
+            }
+            Self::HashJoin if hash_join_applicable => Self::HashJoin,
+            Self::SortMerge if hash_join_applicable => Self::SortMerge,
+            _ => Self::IndexLookup,
+        }
+    }
+}
+
+/// The index used to find the occurrences of kmers in the primary sequences.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IndexBackend {
+    /// A plain suffix array stored alongside the text.
+    ///
+    /// Requires about five bytes per character and answers queries fast.
+    #[default]
+    SuffixTable,
+    /// An FM-index with a sampled suffix array.
+    ///
+    /// Requires less than two bytes per character, but locating each occurrence takes a few dozen steps.
+    FmIndex,
+}
+
+impl IndexBackend {
+    /// Returns the approximate number of bytes of the index of a DNA text of the given length.
+    pub(crate) fn estimated_bytes(self, text_length: usize) -> u64 {
+        match self {
+            // The suffix array stores a `u32` per character, and the text is copied.
+            Self::SuffixTable => text_length as u64 * 5,
+            Self::FmIndex => FmIndex::estimated_bytes(text_length, 4),
+        }
+    }
+}
+
+/// Returns the ASCII text of a genome as a string slice, for indexes that operate on strings.
+pub(crate) fn ascii_str(text: &[u8]) -> &str {
+    std::str::from_utf8(text).expect("the characters of an alphabet are ASCII")
+}
+
+/// An index of a text that can find all occurrences of a pattern.
+///
+/// Construction finds the matching kmers by looking up each reverse-complemented kmer in an index of the primary sequence.
+/// The crate builds these indexes as configured by [`IndexBackend`],
+/// but custom indexes can be given to [`MatchTableBuilder::build_with_indexes`](crate::MatchTableBuilder::build_with_indexes).
+/// This trait is implemented for the [`SuffixTable`] of the `suffix` crate,
+/// for references to indexes, and for options of indexes, where `None` finds no occurrences.
+///
+/// The text and the patterns consist of the ASCII characters of the alphabet, like those returned by
+/// [`GenomeSequence::clone_as_vec`](compact_genome::interface::sequence::GenomeSequence::clone_as_vec).
+/// The patterns are the kmers of the minimum length, or shorter pieces of them if [mismatches](crate::MatchTableBuilder::max_mismatches) are allowed.
+pub trait SequenceIndex: Sync {
+    /// Returns the start positions of all occurrences of `pattern`, in no particular order.
+    fn positions(&self, pattern: &[u8]) -> impl Iterator<Item = usize>;
+}
+
+/// An index that finds no occurrences, for strategies that compare kmers directly.
+pub(crate) struct NoIndex;
+
+impl SequenceIndex for NoIndex {
+    fn positions(&self, _pattern: &[u8]) -> impl Iterator<Item = usize> {
+        std::iter::empty()
+    }
+}
+
+impl<Index: SequenceIndex> SequenceIndex for &Index {
+    fn positions(&self, pattern: &[u8]) -> impl Iterator<Item = usize> {
+        (*self).positions(pattern)
+    }
+}
+
+/// An index that is only built if its sequence is the primary sequence of a computed quadrant.
+impl<Index: SequenceIndex> SequenceIndex for Option<Index> {
+    fn positions(&self, pattern: &[u8]) -> impl Iterator<Item = usize> {
+        self.iter().flat_map(move |index| index.positions(pattern))
+    }
+}
+
+impl SequenceIndex for SuffixTable<'_, '_> {
+    fn positions(&self, pattern: &[u8]) -> impl Iterator<Item = usize> {
+        SuffixTable::positions(self, ascii_str(pattern))
+            .iter()
+            .map(|position| usize::try_from(*position).unwrap())
+    }
+}
+
+/// An owned index of a whole sequence built with either [`IndexBackend`].
+pub(crate) enum BackendIndex {
+    SuffixTable(SuffixTable<'static, 'static>),
+    FmIndex(Box<FmIndex>),
+}
+
+impl BackendIndex {
+    pub fn new(text: &[u8], index_backend: IndexBackend) -> Self {
+        match index_backend {
+            IndexBackend::SuffixTable => {
+                Self::SuffixTable(SuffixTable::new(ascii_str(text).to_owned()))
+            }
+            IndexBackend::FmIndex => Self::FmIndex(Box::new(FmIndex::new(text))),
+        }
+    }
+
+    pub fn index_backend(&self) -> IndexBackend {
+        match self {
+            Self::SuffixTable(_) => IndexBackend::SuffixTable,
+            Self::FmIndex(_) => IndexBackend::FmIndex,
+        }
+    }
+}
+
+impl SequenceIndex for BackendIndex {
+    fn positions(&self, pattern: &[u8]) -> impl Iterator<Item = usize> {
+        let (suffix_table, fm_index) = match self {
+            Self::SuffixTable(suffix_table) => (Some(suffix_table), None),
+            Self::FmIndex(fm_index) => (None, Some(fm_index)),
+        };
+        suffix_table
+            .into_iter()
+            .flat_map(move |suffix_table| SequenceIndex::positions(suffix_table, pattern))
+            .chain(
+                fm_index
+                    .into_iter()
+                    .flat_map(move |fm_index| fm_index.as_ref().positions(pattern)),
+            )
+    }
+}
@@ -0,0 +1,192 @@
+//! An FM-index over a byte text.
+
+use bitvec::vec::BitVec;
+use suffix::SuffixTable;
+
+use super::SequenceIndex;
+
+/// The number of BWT characters between two occurrence count samples.
+const OCCURRENCE_SAMPLE_RATE: usize = 64;
+/// The distance between two text positions whose suffix array entry is sampled.
+const SUFFIX_ARRAY_SAMPLE_RATE: usize = 32;
+/// The number of bits between two rank samples of the suffix array sample marks.
+const RANK_SAMPLE_RATE: usize = 64;
+
+/// An FM-index with sampled occurrence counts and a sampled suffix array.
+///
+/// The BWT is computed with an implicit sentinel character that is smaller than all other characters.
+/// The characters are mapped to dense symbols, where the sentinel is symbol zero.
+pub(crate) struct FmIndex {
+    /// The BWT as dense symbols.
+    bwt: Vec<u8>,
+    /// Maps each byte to its symbol, or zero if the byte does not occur in the text.
+    symbols: [u8; 256],
+    /// The number of distinct symbols including the sentinel.
+    symbol_count: usize,
+    /// `c[symbol]` is the number of BWT characters with a smaller symbol.
+    c: Vec<usize>,
+    /// `occurrences[block * symbol_count + symbol]` is the number of occurrences of `symbol` in `bwt[..block * OCCURRENCE_SAMPLE_RATE]`.
+    occurrences: Vec<u32>,
+    /// Marks the BWT rows whose suffix array entry is sampled.
+    sampled_rows: BitVec,
+    /// `sampled_row_ranks[block]` is the number of marked rows in `sampled_rows[..block * RANK_SAMPLE_RATE]`.
+    sampled_row_ranks: Vec<u32>,
+    /// The suffix array entries of the marked rows, in row order.
+    suffix_array_samples: Vec<u32>,
+}
+
+impl FmIndex {
+    /// Returns the approximate number of bytes of the index of a text of the given length with the given number of distinct characters.
+    pub fn estimated_bytes(text_length: usize, character_count: usize) -> u64 {
+        let rows = text_length as u64 + 1;
+        let symbol_count = character_count as u64 + 1;
+        rows + rows.div_ceil(OCCURRENCE_SAMPLE_RATE as u64) * symbol_count * 4
+            + rows.div_ceil(8)
+            + rows.div_ceil(RANK_SAMPLE_RATE as u64) * 4
+            + rows.div_ceil(SUFFIX_ARRAY_SAMPLE_RATE as u64) * 4
+    }
+
+    pub fn new(text: &[u8]) -> Self {
+        assert!(u32::try_from(text.len() + 1).is_ok());
+
+        let mut symbols = [0; 256];
+        let mut symbol_count = 1;
+        let mut present = [false; 256];
+        for &character in text {
+            present[usize::from(character)] = true;
+        }
+        for (character, is_present) in present.into_iter().enumerate() {
+            if is_present {
+                symbols[character] = u8::try_from(symbol_count).unwrap();
+                symbol_count += 1;
+            }
+        }
+
+        // The suffix table sorts shorter suffixes before longer suffixes that they are a prefix of,
+        // which is the same order as with a sentinel.
+        let suffix_array = SuffixTable::new(super::ascii_str(text)).table().to_vec();
+        let suffix_array = || {
+            std::iter::once(text.len()).chain(
+                suffix_array
+                    .iter()
+                    .map(|position| usize::try_from(*position).unwrap()),
+            )
+        };
+
+        let bwt: Vec<u8> = suffix_array()
+            .map(|position| {
+                if position == 0 {
+                    0
+                } else {
+                    symbols[usize::from(text[position - 1])]
+                }
+            })
+            .collect();
+
+        let mut c = vec![0; symbol_count + 1];
+        for &symbol in &bwt {
+            c[usize::from(symbol) + 1] += 1;
+        }
+        for symbol in 0..symbol_count {
+            c[symbol + 1] += c[symbol];
+        }
+
+        let mut occurrences =
+            Vec::with_capacity((bwt.len() / OCCURRENCE_SAMPLE_RATE + 1) * symbol_count);
+        let mut counts = vec![0; symbol_count];
+        for (row, &symbol) in bwt.iter().enumerate() {
+            if row % OCCURRENCE_SAMPLE_RATE == 0 {
+                occurrences.extend_from_slice(&counts);
+            }
+            counts[usize::from(symbol)] += 1;
+        }
+        occurrences.extend_from_slice(&counts);
+
+        let mut sampled_rows = BitVec::repeat(false, bwt.len());
+        let mut suffix_array_samples = Vec::new();
+        for (row, position) in suffix_array().enumerate() {
+            if position % SUFFIX_ARRAY_SAMPLE_RATE == 0 {
+                sampled_rows.set(row, true);
+                suffix_array_samples.push(u32::try_from(position).unwrap());
+            }
+        }
+
+        let sampled_row_ranks = sampled_rows
+            .chunks(RANK_SAMPLE_RATE)
+            .scan(0, |rank, chunk| {
+                let result = *rank;
+                *rank += u32::try_from(chunk.count_ones()).unwrap();
+                Some(result)
+            })
+            .collect();
+
+        Self {
+            bwt,
+            symbols,
+            symbol_count,
+            c,
+            occurrences,
+            sampled_rows,
+            sampled_row_ranks,
+            suffix_array_samples,
+        }
+    }
+
+    /// The number of occurrences of `symbol` in `bwt[..row]`.
+    fn occurrence(&self, symbol: u8, row: usize) -> usize {
+        let block = row / OCCURRENCE_SAMPLE_RATE;
+        let sampled =
+            usize::try_from(self.occurrences[block * self.symbol_count + usize::from(symbol)])
+                .unwrap();
+        sampled
+            + self.bwt[block * OCCURRENCE_SAMPLE_RATE..row]
+                .iter()
+                .filter(|&&other| other == symbol)
+                .count()
+    }
+
+    /// The row of the suffix that starts one position before the suffix at `row`.
+    fn last_to_first(&self, row: usize) -> usize {
+        let symbol = self.bwt[row];
+        self.c[usize::from(symbol)] + self.occurrence(symbol, row)
+    }
+
+    /// The text position of the suffix at `row`.
+    fn locate(&self, mut row: usize) -> usize {
+        let mut steps = 0;
+        while !self.sampled_rows[row] {
+            row = self.last_to_first(row);
+            steps += 1;
+        }
+
+        let block = row / RANK_SAMPLE_RATE;
+        let rank = usize::try_from(self.sampled_row_ranks[block]).unwrap()
+            + self.sampled_rows[block * RANK_SAMPLE_RATE..row].count_ones();
+        usize::try_from(self.suffix_array_samples[rank]).unwrap() + steps
+    }
+
+    /// The range of rows whose suffixes start with `pattern`.
+    fn rows(&self, pattern: &[u8]) -> std::ops::Range<usize> {
+        let mut rows = 0..self.bwt.len();
+        for &character in pattern.iter().rev() {
+            let symbol = self.symbols[usize::from(character)];
+            if symbol == 0 {
+                return 0..0;
+            }
+
+            let offset = self.c[usize::from(symbol)];
+            rows = offset + self.occurrence(symbol, rows.start)
+                ..offset + self.occurrence(symbol, rows.end);
+            if rows.is_empty() {
+                return 0..0;
+            }
+        }
+        rows
+    }
+}
+
+impl SequenceIndex for FmIndex {
+    fn positions(&self, pattern: &[u8]) -> impl Iterator<Item = usize> {
+        self.rows(pattern).map(|row| self.locate(row))
+    }
+}
@@ -0,0 +1,180 @@
+//! Packing of kmers into a `u64`, and a hash map from packed kmers to their positions.
+
+use std::collections::HashMap;
+
+use bitvec::slice::BitSlice;
+
+use super::SequenceIndex;
+use crate::StorageIndex;
+
+/// Packs kmers of a fixed length into a `u64`.
+///
+/// The characters are mapped to dense symbols that are packed with the minimum number of bits.
+/// The kmers of a text are packed by updating the packed kmer with each character,
+/// and [`pack_all_fixed`](Self::pack_all_fixed) does so for a kmer length that is known at compile time.
+#[derive(Clone)]
+pub(crate) struct KmerPacker {
+    kmer_length: usize,
+    /// Maps each byte to its symbol plus one, or zero if the byte does not occur in the alphabet.
+    symbols: [u8; 256],
+    bits_per_symbol: u32,
+}
+
+impl KmerPacker {
+    /// Create a packer with a symbol mapping derived from the characters of `alphabet_texts`.
+    ///
+    /// The kmers of the alphabet texts must fit into a `u64`.
+    pub fn new<'text>(
+        alphabet_texts: impl IntoIterator<Item = &'text [u8]>,
+        kmer_length: usize,
+    ) -> Self {
+        let alphabet_texts: Vec<_> = alphabet_texts.into_iter().collect();
+        let bits_per_symbol =
+            Self::bits_per_symbol(alphabet_texts.iter().copied(), kmer_length).unwrap();
+        Self {
+            kmer_length,
+            symbols: Self::symbols(alphabet_texts),
+            bits_per_symbol,
+        }
+    }
+
+    /// Returns the number of bits required to pack a symbol of the characters occurring in `texts`,
+    /// or `None` if kmers of the given length do not fit into a `u64`.
+    pub fn bits_per_symbol<'text>(
+        texts: impl IntoIterator<Item = &'text [u8]>,
+        kmer_length: usize,
+    ) -> Option<u32> {
+        let symbols = Self::symbols(texts);
+        let symbol_count = symbols.iter().filter(|&&symbol| symbol != 0).count();
+        let bits_per_symbol = usize::BITS - symbol_count.saturating_sub(1).leading_zeros();
+        let bits_per_symbol = bits_per_symbol.max(1);
+        (usize::try_from(bits_per_symbol).unwrap() * kmer_length <= 64).then_some(bits_per_symbol)
+    }
+
+    fn symbols<'text>(texts: impl IntoIterator<Item = &'text [u8]>) -> [u8; 256] {
+        let mut present = [false; 256];
+        for text in texts {
+            for &character in text {
+                present[usize::from(character)] = true;
+            }
+        }
+
+        let mut symbols = [0; 256];
+        let mut symbol_count = 0;
+        for (character, is_present) in present.into_iter().enumerate() {
+            if is_present {
+                symbol_count += 1;
+                symbols[character] = symbol_count;
+            }
+        }
+        symbols
+    }
+
+    /// Packs a kmer of the kmer length, or returns `None` if it contains a character that does not occur in the alphabet.
+    pub fn pack(&self, kmer: &[u8]) -> Option<u64> {
+        debug_assert_eq!(kmer.len(), self.kmer_length);
+        kmer.iter().try_fold(0, |packed, &character| {
+            let symbol = self.symbols[usize::from(character)].checked_sub(1)?;
+            Some((packed << self.bits_per_symbol) | u64::from(symbol))
+        })
+    }
+
+    /// Returns the packed kmers of `text` in order of their start, like [`pack`](Self::pack),
+    /// but updating the packed kmer with each character instead of packing each kmer from scratch.
+    pub fn pack_all(
+        &self,
+        text: impl IntoIterator<Item = u8>,
+    ) -> impl Iterator<Item = Option<u64>> {
+        self.pack_rolling(self.kmer_length, text)
+    }
+
+    /// Returns the packed kmers of `text` like [`pack_all`](Self::pack_all),
+    /// for a kmer length of `KMER_LENGTH`, which must equal the kmer length of this packer.
+    ///
+    /// The kmer length is a constant, such that the mask and the bounds are computed at compile time.
+    pub fn pack_all_fixed<const KMER_LENGTH: usize>(
+        &self,
+        text: impl IntoIterator<Item = u8>,
+    ) -> impl Iterator<Item = Option<u64>> {
+        assert_eq!(KMER_LENGTH, self.kmer_length);
+        self.pack_rolling(KMER_LENGTH, text)
+    }
+
+    #[inline(always)]
+    fn pack_rolling(
+        &self,
+        kmer_length: usize,
+        text: impl IntoIterator<Item = u8>,
+    ) -> impl Iterator<Item = Option<u64>> {
+        let bits_per_symbol = self.bits_per_symbol;
+        let kmer_bits = bits_per_symbol * u32::try_from(kmer_length).unwrap();
+        let mask = u64::MAX >> (u64::BITS - kmer_bits);
+        let mut packed = 0;
+        // The number of characters that occur in the alphabet since the last character that does not.
+        let mut valid_length = 0;
+        text.into_iter()
+            .enumerate()
+            .filter_map(move |(position, character)| {
+                let symbol = self.symbols[usize::from(character)];
+                valid_length = if symbol == 0 { 0 } else { valid_length + 1 };
+                packed = ((packed << bits_per_symbol) | u64::from(symbol.saturating_sub(1))) & mask;
+                (position + 1 >= kmer_length)
+                    .then_some((valid_length >= kmer_length).then_some(packed))
+            })
+    }
+}
+
+/// A hash map from kmers of a fixed length packed into a `u64` by a [`KmerPacker`] to their start positions,
+/// which are stored as `Index`.
+pub(crate) struct HashKmerIndex<Index> {
+    packer: KmerPacker,
+    positions: HashMap<u64, Vec<Index>>,
+}
+
+impl<Index: StorageIndex> HashKmerIndex<Index> {
+    /// Index all kmers of `text` that are not `excluded`, packing them with the given packer.
+    ///
+    /// The alphabet of the packer must contain all characters of `text`.
+    pub fn new(text: &[u8], excluded: &BitSlice, packer: KmerPacker) -> Self {
+        let text_packer = packer.clone();
+        Self::from_packed_kmers(text_packer.pack_all(text.iter().copied()), excluded, packer)
+    }
+
+    /// Index the packed kmers of a text as returned by [`KmerPacker::pack_all`] that are not `excluded`.
+    pub fn from_packed_kmers(
+        packed_kmers: impl IntoIterator<Item = Option<u64>>,
+        excluded: &BitSlice,
+        packer: KmerPacker,
+    ) -> Self {
+        assert!(Index::try_from_usize(excluded.len()).is_some());
+        let mut positions: HashMap<_, Vec<_>> = HashMap::new();
+        for (position, packed) in packed_kmers.into_iter().enumerate() {
+            if excluded[position] {
+                continue;
+            }
+            positions
+                .entry(packed.unwrap())
+                .or_default()
+                .push(Index::from_usize(position));
+        }
+
+        Self { packer, positions }
+    }
+
+    /// Returns the start positions of the packed kmer in ascending order.
+    pub fn get(&self, packed: u64) -> &[Index] {
+        self.positions.get(&packed).map_or(&[], Vec::as_slice)
+    }
+}
+
+impl<Index: StorageIndex> SequenceIndex for HashKmerIndex<Index> {
+    fn positions(&self, pattern: &[u8]) -> impl Iterator<Item = usize> {
+        assert_eq!(pattern.len(), self.packer.kmer_length);
+        self.packer
+            .pack(pattern)
+            .and_then(|packed| self.positions.get(&packed))
+            .into_iter()
+            .flatten()
+            .map(|position| position.into_usize())
+    }
+}
@@ -0,0 +1,12 @@
+//! Input and output of sequences and match tables.
+
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod export;
+#[cfg(feature = "fasta")]
+pub mod fasta;
+#[cfg(feature = "fasta")]
+pub mod fastq;
+#[cfg(feature = "heatmap")]
+pub mod heatmap;
+pub mod matrix;
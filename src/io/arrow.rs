@@ -0,0 +1,181 @@
+//! Export of matches as Apache Arrow record batches and Parquet files, for analysis with DataFrame libraries.
+//!
+//! The columns are those of [`write_tsv`](super::export::write_tsv):
+//! the quadrant, the name, start and end of the primary kmer, the name, start and end of the secondary kmer, and the length of the kmers.
+//! The quadrant and the names are dictionary-encoded strings, and the coordinates and lengths are unsigned 64-bit integers.
+
+use std::sync::Arc;
+
+use arrow_array::{
+    ArrayRef, RecordBatch, UInt64Array,
+    builder::{StringDictionaryBuilder, UInt64Builder},
+    types::UInt32Type,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+
+use super::export::{ExportError, MatchRecord, for_each_record};
+use crate::MatchTable;
+
+/// The number of matches per record batch written by [`write_parquet`].
+#[cfg(feature = "parquet")]
+pub const PARQUET_BATCH_SIZE: usize = 65_536;
+
+/// Returns the schema of the record batches of matches.
+pub fn match_schema() -> SchemaRef {
+    let dictionary = || DataType::Dictionary(Box::new(DataType::UInt32), Box::new(DataType::Utf8));
+    Arc::new(Schema::new(vec![
+        Field::new("quadrant", dictionary(), false),
+        Field::new("primary_name", dictionary(), false),
+        Field::new("primary_start", DataType::UInt64, false),
+        Field::new("primary_end", DataType::UInt64, false),
+        Field::new("secondary_name", dictionary(), false),
+        Field::new("secondary_start", DataType::UInt64, false),
+        Field::new("secondary_end", DataType::UInt64, false),
+        Field::new("length", DataType::UInt64, false),
+    ]))
+}
+
+/// Returns all matches as record batches of at most `batch_size` matches each, in the order of [`MatchTable::matches`].
+///
+/// The names are given per contig, see [`write_tsv`](super::export::write_tsv).
+///
+/// # Panics
+///
+/// Panics if `batch_size` is zero.
+///
+/// # Example
+///
+/// ```rust
+/// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+/// use compact_genome::implementation::vec_sequence::VectorGenome;
+/// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+/// use template_switch_error_free_inners::{MatchTable, io::arrow::record_batches};
+///
+/// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+/// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+/// let matches = MatchTable::new(
+///     reference.as_genome_subsequence(),
+///     query.as_genome_subsequence(),
+///     4,
+/// );
+///
+/// let batches = record_batches(&matches, &["chr1"], &["read1"], 1024).unwrap();
+/// assert_eq!(batches.len(), 1);
+/// assert_eq!(batches[0].num_rows(), 2);
+/// assert_eq!(batches[0].num_columns(), 8);
+/// ```
+pub fn record_batches(
+    matches: &MatchTable,
+    reference_names: &[impl AsRef<str>],
+    query_names: &[impl AsRef<str>],
+    batch_size: usize,
+) -> Result<Vec<RecordBatch>, ExportError> {
+    let mut batches = Vec::new();
+    for_each_batch(matches, reference_names, query_names, batch_size, |batch| {
+        batches.push(batch);
+        Ok(())
+    })?;
+    Ok(batches)
+}
+
+/// Write all matches to a Parquet file with the schema of [`match_schema`].
+///
+/// The matches are converted in record batches of [`PARQUET_BATCH_SIZE`] matches,
+/// so the memory does not grow with the number of matches.
+/// See [`record_batches`] for details.
+#[cfg(feature = "parquet")]
+pub fn write_parquet(
+    matches: &MatchTable,
+    reference_names: &[impl AsRef<str>],
+    query_names: &[impl AsRef<str>],
+    writer: impl std::io::Write + Send,
+) -> Result<(), ExportError> {
+    let mut writer = parquet::arrow::ArrowWriter::try_new(writer, match_schema(), None)?;
+    for_each_batch(
+        matches,
+        reference_names,
+        query_names,
+        PARQUET_BATCH_SIZE,
+        |batch| Ok(writer.write(&batch)?),
+    )?;
+    writer.close()?;
+    Ok(())
+}
+
+/// Call `f` with the record batches of all matches.
+fn for_each_batch(
+    matches: &MatchTable,
+    reference_names: &[impl AsRef<str>],
+    query_names: &[impl AsRef<str>],
+    batch_size: usize,
+    mut f: impl FnMut(RecordBatch) -> Result<(), ExportError>,
+) -> Result<(), ExportError> {
+    assert!(batch_size > 0, "the batch size must be positive");
+    let mut builder = BatchBuilder::new(matches.minimum_length());
+    for_each_record(matches, reference_names, query_names, |record| {
+        builder.append(record);
+        if builder.row_count == batch_size {
+            f(builder.finish()?)?;
+        }
+        Ok(())
+    })?;
+    if builder.row_count > 0 {
+        f(builder.finish()?)?;
+    }
+    Ok(())
+}
+
+/// Collects the columns of a record batch.
+struct BatchBuilder {
+    length: u64,
+    row_count: usize,
+    quadrant: StringDictionaryBuilder<UInt32Type>,
+    primary_name: StringDictionaryBuilder<UInt32Type>,
+    primary_start: UInt64Builder,
+    secondary_name: StringDictionaryBuilder<UInt32Type>,
+    secondary_start: UInt64Builder,
+}
+
+impl BatchBuilder {
+    fn new(length: usize) -> Self {
+        Self {
+            length: length as u64,
+            row_count: 0,
+            quadrant: StringDictionaryBuilder::new(),
+            primary_name: StringDictionaryBuilder::new(),
+            primary_start: UInt64Builder::new(),
+            secondary_name: StringDictionaryBuilder::new(),
+            secondary_start: UInt64Builder::new(),
+        }
+    }
+
+    fn append(&mut self, record: MatchRecord) {
+        self.quadrant.append_value(record.quadrant.to_string());
+        self.primary_name.append_value(record.primary_name);
+        self.primary_start.append_value(record.primary_start as u64);
+        self.secondary_name.append_value(record.secondary_name);
+        self.secondary_start
+            .append_value(record.secondary_start as u64);
+        self.row_count += 1;
+    }
+
+    /// Returns the batch of the appended matches and starts a new batch.
+    fn finish(&mut self) -> Result<RecordBatch, ExportError> {
+        let primary_start = self.primary_start.finish();
+        let secondary_start = self.secondary_start.finish();
+        let end = |start: &UInt64Array| UInt64Array::from_unary(start, |start| start + self.length);
+        let (primary_end, secondary_end) = (end(&primary_start), end(&secondary_start));
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(self.quadrant.finish()),
+            Arc::new(self.primary_name.finish()),
+            Arc::new(primary_start),
+            Arc::new(primary_end),
+            Arc::new(self.secondary_name.finish()),
+            Arc::new(secondary_start),
+            Arc::new(secondary_end),
+            Arc::new(UInt64Array::from_value(self.length, self.row_count)),
+        ];
+        self.row_count = 0;
+        Ok(RecordBatch::try_new(match_schema(), columns)?)
+    }
+}
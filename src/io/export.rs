@@ -0,0 +1,595 @@
+//! Export of matches and template switch candidates in text formats used by genome analysis tools.
+//!
+//! All formats report the kmers of a match in forward-strand coordinates of their contigs, zero-based and half-open,
+//! except for GFF3, which is one-based and closed as required by the format.
+//! The primary kmer lies on the forward strand, and the secondary kmer on the reverse strand.
+
+use std::io::Write;
+
+use crate::{ContigLayout, MatchTable, Quadrant, TemplateSwitchCandidate};
+
+/// An error when exporting matches.
+#[derive(Debug, thiserror::Error)]
+pub enum ExportError {
+    /// The output could not be written.
+    #[error("Error writing matches: {0}")]
+    IO(#[from] std::io::Error),
+
+    /// The matches could not be converted to Arrow record batches.
+    #[cfg(feature = "arrow")]
+    #[error("Arrow error: {0}")]
+    Arrow(#[from] arrow_schema::ArrowError),
+
+    /// The matches could not be written to a Parquet file.
+    #[cfg(feature = "parquet")]
+    #[error("Parquet error: {0}")]
+    Parquet(#[from] parquet::errors::ParquetError),
+
+    /// The number of names given for a genome does not match its number of contigs.
+    #[error("Got {name_count} names for the {contig_count} contigs of the {genome}")]
+    NameCount {
+        /// Either `"reference"` or `"query"`.
+        genome: &'static str,
+        /// The number of given names.
+        name_count: usize,
+        /// The number of contigs of the genome.
+        contig_count: usize,
+    },
+}
+
+/// A match in forward-strand coordinates.
+pub(super) struct MatchRecord<'names> {
+    pub quadrant: Quadrant,
+    pub primary_name: &'names str,
+    pub primary_start: usize,
+    pub secondary_name: &'names str,
+    pub secondary_start: usize,
+}
+
+/// Write all matches as tab-separated values with a header line.
+///
+/// The columns are the quadrant, the name, start and end of the primary kmer,
+/// the name, start and end of the secondary kmer, and the length of the kmers.
+/// The names are given per contig, so a table built from a single sequence requires a single name per genome.
+///
+/// # Example
+///
+/// ```rust
+/// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+/// use compact_genome::implementation::vec_sequence::VectorGenome;
+/// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+/// use template_switch_error_free_inners::{MatchTable, io::export::write_tsv};
+///
+/// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+/// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+/// let matches = MatchTable::new(
+///     reference.as_genome_subsequence(),
+///     query.as_genome_subsequence(),
+///     4,
+/// );
+///
+/// let mut output = Vec::new();
+/// write_tsv(&matches, &["chr1"], &["read1"], &mut output).unwrap();
+/// assert_eq!(
+///     String::from_utf8(output).unwrap(),
+///     "quadrant\tprimary_name\tprimary_start\tprimary_end\tsecondary_name\tsecondary_start\tsecondary_end\tlength\n\
+///      reference_reference\tchr1\t1\t5\tchr1\t7\t11\t4\n\
+///      reference_reference\tchr1\t7\t11\tchr1\t1\t5\t4\n",
+/// );
+/// ```
+pub fn write_tsv(
+    matches: &MatchTable,
+    reference_names: &[impl AsRef<str>],
+    query_names: &[impl AsRef<str>],
+    mut writer: impl Write,
+) -> Result<(), ExportError> {
+    let length = matches.minimum_length();
+    writeln!(
+        writer,
+        "quadrant\tprimary_name\tprimary_start\tprimary_end\tsecondary_name\tsecondary_start\tsecondary_end\tlength"
+    )?;
+    for_each_record(matches, reference_names, query_names, |record| {
+        Ok(writeln!(
+            writer,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{length}",
+            record.quadrant,
+            record.primary_name,
+            record.primary_start,
+            record.primary_start + length,
+            record.secondary_name,
+            record.secondary_start,
+            record.secondary_start + length,
+        )?)
+    })
+}
+
+/// Write all matches as BEDPE records, the paired variant of BED understood by bedtools and IGV.
+///
+/// Each record consists of the name, start and end of the primary kmer,
+/// the name, start and end of the secondary kmer, the quadrant as name, the length of the kmers as score,
+/// and the strands `+` and `-`.
+/// See [`write_tsv`] for details on the names.
+pub fn write_bedpe(
+    matches: &MatchTable,
+    reference_names: &[impl AsRef<str>],
+    query_names: &[impl AsRef<str>],
+    mut writer: impl Write,
+) -> Result<(), ExportError> {
+    let length = matches.minimum_length();
+    for_each_record(matches, reference_names, query_names, |record| {
+        Ok(writeln!(
+            writer,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{length}\t+\t-",
+            record.primary_name,
+            record.primary_start,
+            record.primary_start + length,
+            record.secondary_name,
+            record.secondary_start,
+            record.secondary_start + length,
+            record.quadrant,
+        )?)
+    })
+}
+
+/// Write a JSON report of the table and all of its matches, for web frontends and for processing with tools like `jq`.
+///
+/// The report is an object with the following keys:
+///
+/// * `parameters`: the `minimum_length`, the `max_mismatches`, the `band` as an object with `min_offset` and `max_offset`, or `null`,
+///   the `max_kmer_occurrences`, or `null`, and whether `unique_matches_only` were recorded.
+/// * `reference` and `query`: the `kmer_count`, the `skipped_kmer_count`, the `frequent_kmer_count`,
+///   and the `contigs` as an array of objects with `name` and `length`.
+/// * `quadrants`: an array of objects with the `quadrant`, whether it was `computed`, and its `match_count`.
+/// * `matches`: an array of objects with the keys of the columns of [`write_tsv`].
+///
+/// See [`write_tsv`] for details on the names.
+///
+/// # Example
+///
+/// ```rust
+/// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+/// use compact_genome::implementation::vec_sequence::VectorGenome;
+/// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+/// use template_switch_error_free_inners::{MatchTableBuilder, Quadrants, io::export::write_json};
+///
+/// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+/// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+/// let matches = MatchTableBuilder::new(4)
+///     .quadrants(Quadrants::REFERENCE_REFERENCE)
+///     .build(reference.as_genome_subsequence(), query.as_genome_subsequence());
+///
+/// let mut output = Vec::new();
+/// write_json(&matches, &["chr1"], &["read \"1\""], &mut output).unwrap();
+/// let output = String::from_utf8(output).unwrap();
+/// assert!(output.starts_with(
+///     "{\"parameters\":{\"minimum_length\":4,\"max_mismatches\":0,\"band\":null,\"max_kmer_occurrences\":null,\"unique_matches_only\":false},\
+///      \"reference\":{\"kmer_count\":10,\"skipped_kmer_count\":0,\"frequent_kmer_count\":0,\"contigs\":[{\"name\":\"chr1\",\"length\":13}]},\
+///      \"query\":{\"kmer_count\":5,\"skipped_kmer_count\":0,\"frequent_kmer_count\":0,\"contigs\":[{\"name\":\"read \\\"1\\\"\",\"length\":8}]},\
+///      \"quadrants\":[{\"quadrant\":\"reference_reference\",\"computed\":true,\"match_count\":2},"
+/// ));
+/// assert!(output.ends_with(
+///     "\"matches\":[{\"quadrant\":\"reference_reference\",\"primary_name\":\"chr1\",\"primary_start\":1,\"primary_end\":5,\
+///      \"secondary_name\":\"chr1\",\"secondary_start\":7,\"secondary_end\":11,\"length\":4},\
+///      {\"quadrant\":\"reference_reference\",\"primary_name\":\"chr1\",\"primary_start\":7,\"primary_end\":11,\
+///      \"secondary_name\":\"chr1\",\"secondary_start\":1,\"secondary_end\":5,\"length\":4}]}\n"
+/// ));
+/// ```
+pub fn write_json(
+    matches: &MatchTable,
+    reference_names: &[impl AsRef<str>],
+    query_names: &[impl AsRef<str>],
+    mut writer: impl Write,
+) -> Result<(), ExportError> {
+    let length = matches.minimum_length();
+    write!(
+        writer,
+        "{{\"parameters\":{{\"minimum_length\":{length},\"max_mismatches\":{},\"band\":",
+        matches.max_mismatches(),
+    )?;
+    match matches.band {
+        Some(band) => write!(
+            writer,
+            "{{\"min_offset\":{},\"max_offset\":{}}}",
+            band.min_offset, band.max_offset
+        )?,
+        None => write!(writer, "null")?,
+    }
+    match matches.max_kmer_occurrences() {
+        Some(max_kmer_occurrences) => {
+            write!(writer, ",\"max_kmer_occurrences\":{max_kmer_occurrences}")?
+        }
+        None => write!(writer, ",\"max_kmer_occurrences\":null")?,
+    }
+    write!(
+        writer,
+        ",\"unique_matches_only\":{}}}",
+        matches.unique_matches_only()
+    )?;
+
+    for (genome, names, contigs, kmer_count, skipped_kmer_count, frequent_kmer_count) in [
+        (
+            "reference",
+            contig_names("reference", reference_names, matches.reference_contigs())?,
+            matches.reference_contigs(),
+            matches.reference_kmer_count(),
+            matches.skipped_reference_kmer_count(),
+            matches.frequent_reference_kmer_count(),
+        ),
+        (
+            "query",
+            contig_names("query", query_names, matches.query_contigs())?,
+            matches.query_contigs(),
+            matches.query_kmer_count(),
+            matches.skipped_query_kmer_count(),
+            matches.frequent_query_kmer_count(),
+        ),
+    ] {
+        write!(
+            writer,
+            ",\"{genome}\":{{\"kmer_count\":{kmer_count},\"skipped_kmer_count\":{skipped_kmer_count},\"frequent_kmer_count\":{frequent_kmer_count},\"contigs\":["
+        )?;
+        for (contig_id, name) in names.into_iter().enumerate() {
+            if contig_id > 0 {
+                write!(writer, ",")?;
+            }
+            write!(writer, "{{\"name\":")?;
+            write_json_string(&mut writer, name)?;
+            write!(writer, ",\"length\":{}}}", contigs.contig_length(contig_id))?;
+        }
+        write!(writer, "]}}")?;
+    }
+
+    write!(writer, ",\"quadrants\":[")?;
+    for (index, quadrant) in Quadrant::ALL.into_iter().enumerate() {
+        if index > 0 {
+            write!(writer, ",")?;
+        }
+        write!(
+            writer,
+            "{{\"quadrant\":\"{quadrant}\",\"computed\":{},\"match_count\":{}}}",
+            matches.quadrants().contains(quadrant),
+            matches.quadrant(quadrant).match_count(),
+        )?;
+    }
+
+    write!(writer, "],\"matches\":[")?;
+    let mut first = true;
+    for_each_record(matches, reference_names, query_names, |record| {
+        if !first {
+            write!(writer, ",")?;
+        }
+        first = false;
+        write!(
+            writer,
+            "{{\"quadrant\":\"{}\",\"primary_name\":",
+            record.quadrant
+        )?;
+        write_json_string(&mut writer, record.primary_name)?;
+        write!(
+            writer,
+            ",\"primary_start\":{},\"primary_end\":{},\"secondary_name\":",
+            record.primary_start,
+            record.primary_start + length,
+        )?;
+        write_json_string(&mut writer, record.secondary_name)?;
+        Ok(write!(
+            writer,
+            ",\"secondary_start\":{},\"secondary_end\":{},\"length\":{length}}}",
+            record.secondary_start,
+            record.secondary_start + length,
+        )?)
+    })?;
+    writeln!(writer, "]}}")?;
+    Ok(())
+}
+
+/// Write the given template switch candidates of the table as GFF3 features, for loading them into genome browsers.
+///
+/// Each candidate is written as a `template_switch_inner` feature on the forward strand of the primary genome spanning `point1..point4`,
+/// with the ID `candidate<n>` for the `n`-th candidate starting at one and the attributes `quadrant` and `inner_length`.
+/// Its children are a `template_switch_template` feature on the reverse strand of the secondary genome spanning `point3..point2`,
+/// and a `template_switch_point` feature of a single base for each of the four switch points, named `point1` to `point4`.
+/// Points 1 and 3 are the first bases of the inner and its template, and points 2 and 4 are their last bases.
+/// The file starts with a `sequence-region` directive for each contig of both genomes.
+///
+/// The candidates are usually enumerated with [`MatchTable::candidates`].
+/// See [`write_tsv`] for details on the names, which are escaped as required by GFF3.
+///
+/// # Panics
+///
+/// Panics if a candidate lies outside of the genomes of the table.
+///
+/// # Example
+///
+/// ```rust
+/// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+/// use compact_genome::implementation::vec_sequence::VectorGenome;
+/// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+/// use template_switch_error_free_inners::{
+///     CandidateConstraints, MatchTable, Quadrant, io::export::write_gff3,
+/// };
+///
+/// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGTCAAAAATGACCA").unwrap();
+/// let query = VectorGenome::from_slice_u8(b"AAAA").unwrap();
+/// let matches = MatchTable::new(
+///     reference.as_genome_subsequence(),
+///     query.as_genome_subsequence(),
+///     4,
+/// );
+/// let candidates = matches
+///     .candidates(
+///         Quadrant::ReferenceReference,
+///         &CandidateConstraints::new().min_inner_length(5),
+///     )
+///     .take(1);
+///
+/// let mut output = Vec::new();
+/// write_gff3(&matches, candidates, &["chr1"], &["read1"], &mut output).unwrap();
+/// assert_eq!(
+///     String::from_utf8(output).unwrap(),
+///     "##gff-version 3\n##sequence-region chr1 1 16\n##sequence-region read1 1 4\n\
+///      chr1\ttsefi\ttemplate_switch_inner\t2\t6\t.\t+\t.\tID=candidate1;quadrant=reference_reference;inner_length=5\n\
+///      chr1\ttsefi\ttemplate_switch_template\t11\t15\t.\t-\t.\tID=candidate1.template;Parent=candidate1\n\
+///      chr1\ttsefi\ttemplate_switch_point\t2\t2\t.\t+\t.\tID=candidate1.point1;Parent=candidate1;Name=point1\n\
+///      chr1\ttsefi\ttemplate_switch_point\t15\t15\t.\t-\t.\tID=candidate1.point2;Parent=candidate1;Name=point2\n\
+///      chr1\ttsefi\ttemplate_switch_point\t11\t11\t.\t-\t.\tID=candidate1.point3;Parent=candidate1;Name=point3\n\
+///      chr1\ttsefi\ttemplate_switch_point\t6\t6\t.\t+\t.\tID=candidate1.point4;Parent=candidate1;Name=point4\n",
+/// );
+/// ```
+pub fn write_gff3(
+    matches: &MatchTable,
+    candidates: impl IntoIterator<Item = TemplateSwitchCandidate>,
+    reference_names: &[impl AsRef<str>],
+    query_names: &[impl AsRef<str>],
+    mut writer: impl Write,
+) -> Result<(), ExportError> {
+    let reference_names: Vec<_> =
+        contig_names("reference", reference_names, matches.reference_contigs())?
+            .into_iter()
+            .map(escape_gff3)
+            .collect();
+    let query_names: Vec<_> = contig_names("query", query_names, matches.query_contigs())?
+        .into_iter()
+        .map(escape_gff3)
+        .collect();
+
+    writeln!(writer, "##gff-version 3")?;
+    for (names, contigs) in [
+        (&reference_names, matches.reference_contigs()),
+        (&query_names, matches.query_contigs()),
+    ] {
+        for (contig_id, name) in names.iter().enumerate() {
+            let length = contigs.contig_length(contig_id);
+            if length > 0 {
+                writeln!(writer, "##sequence-region {name} 1 {length}")?;
+            }
+        }
+    }
+
+    let genome = |is_reference| {
+        if is_reference {
+            (&reference_names, matches.reference_contigs())
+        } else {
+            (&query_names, matches.query_contigs())
+        }
+    };
+    for (index, candidate) in candidates.into_iter().enumerate() {
+        let id = format!("candidate{}", index + 1);
+        let (primary_names, primary_contigs) = genome(candidate.quadrant.primary_is_reference());
+        let (secondary_names, secondary_contigs) =
+            genome(candidate.quadrant.secondary_is_reference());
+        // One-based inclusive coordinates of the first and the last base of an interval in its contig.
+        let bases = |contigs: &ContigLayout, start: usize, end: usize| {
+            let first = contigs.position(start);
+            let last = contigs.position(end - 1);
+            debug_assert_eq!(first.contig_id, last.contig_id);
+            (first.contig_id, first.offset + 1, last.offset + 1)
+        };
+        let (primary_contig, point1, point4) =
+            bases(primary_contigs, candidate.point1, candidate.point4);
+        let (secondary_contig, point3, point2) =
+            bases(secondary_contigs, candidate.point3, candidate.point2);
+        let primary_name = &primary_names[primary_contig];
+        let secondary_name = &secondary_names[secondary_contig];
+
+        writeln!(
+            writer,
+            "{primary_name}\ttsefi\ttemplate_switch_inner\t{point1}\t{point4}\t.\t+\t.\tID={id};quadrant={};inner_length={}",
+            candidate.quadrant,
+            candidate.inner_length(),
+        )?;
+        writeln!(
+            writer,
+            "{secondary_name}\ttsefi\ttemplate_switch_template\t{point3}\t{point2}\t.\t-\t.\tID={id}.template;Parent={id}",
+        )?;
+        for (point_name, name, position, strand) in [
+            ("point1", primary_name, point1, '+'),
+            ("point2", secondary_name, point2, '-'),
+            ("point3", secondary_name, point3, '-'),
+            ("point4", primary_name, point4, '+'),
+        ] {
+            writeln!(
+                writer,
+                "{name}\ttsefi\ttemplate_switch_point\t{position}\t{position}\t.\t{strand}\t.\tID={id}.{point_name};Parent={id};Name={point_name}",
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Write the given template switch candidates of the table in template switch arrangement notation, one candidate per line,
+/// for viewing them with template switch visualizers.
+///
+/// The arrangement of a candidate lists the segments of the genomes that make up the primary after the template switch:
+/// `{primary}[..{point1}] {secondary}[{point2}..{point3}] {primary}[{point4}..]`.
+/// The primary is followed up to point 1, where it switches to the secondary at point 2,
+/// which is followed backwards on the reverse strand up to point 3, where it switches back to the primary at point 4.
+/// So a segment with a decreasing range is the reverse complement of the increasing range.
+/// The points are zero-based offsets into their contigs, and the names are those of the contigs.
+///
+/// The candidates are usually enumerated with [`MatchTable::candidates`].
+/// See [`write_tsv`] for details on the names.
+///
+/// # Panics
+///
+/// Panics if a candidate lies outside of the genomes of the table.
+///
+/// # Example
+///
+/// ```rust
+/// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+/// use compact_genome::implementation::vec_sequence::VectorGenome;
+/// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+/// use template_switch_error_free_inners::{
+///     CandidateConstraints, MatchTable, Quadrant, io::export::write_ts_arrangements,
+/// };
+///
+/// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGTCAAAAATGACCA").unwrap();
+/// let query = VectorGenome::from_slice_u8(b"AAAA").unwrap();
+/// let matches = MatchTable::new(
+///     reference.as_genome_subsequence(),
+///     query.as_genome_subsequence(),
+///     4,
+/// );
+/// let candidates = matches
+///     .candidates(
+///         Quadrant::ReferenceReference,
+///         &CandidateConstraints::new().min_inner_length(5),
+///     )
+///     .take(1);
+///
+/// // The inner `GGTCA` at 1..6 is the reverse complement of `TGACC` at 10..15.
+/// let mut output = Vec::new();
+/// write_ts_arrangements(&matches, candidates, &["chr1"], &["read1"], &mut output).unwrap();
+/// assert_eq!(
+///     String::from_utf8(output).unwrap(),
+///     "chr1[..1] chr1[15..10] chr1[6..]\n",
+/// );
+/// ```
+pub fn write_ts_arrangements(
+    matches: &MatchTable,
+    candidates: impl IntoIterator<Item = TemplateSwitchCandidate>,
+    reference_names: &[impl AsRef<str>],
+    query_names: &[impl AsRef<str>],
+    mut writer: impl Write,
+) -> Result<(), ExportError> {
+    let reference_names = contig_names("reference", reference_names, matches.reference_contigs())?;
+    let query_names = contig_names("query", query_names, matches.query_contigs())?;
+    let genome = |is_reference| {
+        if is_reference {
+            (&reference_names, matches.reference_contigs())
+        } else {
+            (&query_names, matches.query_contigs())
+        }
+    };
+
+    for candidate in candidates {
+        let (primary_names, primary_contigs) = genome(candidate.quadrant.primary_is_reference());
+        let (secondary_names, secondary_contigs) =
+            genome(candidate.quadrant.secondary_is_reference());
+        // The contig and the zero-based offsets of the start and the end of an interval in its contig.
+        let offsets = |contigs: &ContigLayout, start: usize, end: usize| {
+            let first = contigs.position(start);
+            let last = contigs.position(end - 1);
+            debug_assert_eq!(first.contig_id, last.contig_id);
+            (first.contig_id, first.offset, last.offset + 1)
+        };
+        let (primary_contig, point1, point4) =
+            offsets(primary_contigs, candidate.point1, candidate.point4);
+        let (secondary_contig, point3, point2) =
+            offsets(secondary_contigs, candidate.point3, candidate.point2);
+        let primary_name = primary_names[primary_contig];
+        let secondary_name = secondary_names[secondary_contig];
+
+        writeln!(
+            writer,
+            "{primary_name}[..{point1}] {secondary_name}[{point2}..{point3}] {primary_name}[{point4}..]",
+        )?;
+    }
+    Ok(())
+}
+
+/// Returns the given sequence name with all characters that are not allowed unescaped in GFF3 seqids percent-encoded.
+fn escape_gff3(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for byte in name.bytes() {
+        if byte.is_ascii_alphanumeric() || b".:^*$@!+_?-|".contains(&byte) {
+            escaped.push(byte as char);
+        } else {
+            escaped.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    escaped
+}
+
+/// Write the given string as a JSON string literal.
+fn write_json_string(writer: &mut impl Write, string: &str) -> std::io::Result<()> {
+    write!(writer, "\"")?;
+    for character in string.chars() {
+        match character {
+            '"' => write!(writer, "\\\"")?,
+            '\\' => write!(writer, "\\\\")?,
+            '\n' => write!(writer, "\\n")?,
+            '\r' => write!(writer, "\\r")?,
+            '\t' => write!(writer, "\\t")?,
+            character if character.is_control() => {
+                write!(writer, "\\u{:04x}", u32::from(character))?
+            }
+            character => write!(writer, "{character}")?,
+        }
+    }
+    write!(writer, "\"")
+}
+
+pub(super) fn for_each_record(
+    matches: &MatchTable,
+    reference_names: &[impl AsRef<str>],
+    query_names: &[impl AsRef<str>],
+    mut f: impl FnMut(MatchRecord) -> Result<(), ExportError>,
+) -> Result<(), ExportError> {
+    let reference_names = contig_names("reference", reference_names, matches.reference_contigs())?;
+    let query_names = contig_names("query", query_names, matches.query_contigs())?;
+
+    for quadrant in Quadrant::ALL {
+        let names = |is_reference| {
+            if is_reference {
+                (&reference_names, matches.reference_contigs())
+            } else {
+                (&query_names, matches.query_contigs())
+            }
+        };
+        let (primary_names, primary_contigs) = names(quadrant.primary_is_reference());
+        let (secondary_names, secondary_contigs) = names(quadrant.secondary_is_reference());
+        let secondary_kmer_count = matches.secondary_kmer_count(quadrant);
+
+        for (primary_index, secondary_rc_index) in matches.matches(quadrant) {
+            let primary = primary_contigs.position(primary_index);
+            let secondary =
+                secondary_contigs.position(secondary_kmer_count - 1 - secondary_rc_index);
+            f(MatchRecord {
+                quadrant,
+                primary_name: primary_names[primary.contig_id],
+                primary_start: primary.offset,
+                secondary_name: secondary_names[secondary.contig_id],
+                secondary_start: secondary.offset,
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+fn contig_names<'names>(
+    genome: &'static str,
+    names: &'names [impl AsRef<str>],
+    contigs: &ContigLayout,
+) -> Result<Vec<&'names str>, ExportError> {
+    if names.len() != contigs.contig_count() {
+        return Err(ExportError::NameCount {
+            genome,
+            name_count: names.len(),
+            contig_count: contigs.contig_count(),
+        });
+    }
+    Ok(names.iter().map(AsRef::as_ref).collect())
+}
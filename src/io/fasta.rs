@@ -0,0 +1,195 @@
+//! Loading of reference and query sequences from fasta files.
+
+use std::path::{Path, PathBuf};
+
+use compact_genome::{
+    implementation::{
+        bit_vec_sequence::BitVectorGenome, bit_vec_sequence_store::BitVectorSequenceStore,
+        vec_sequence::VectorGenome, vec_sequence_store::VectorSequenceStore,
+    },
+    interface::{alphabet::Alphabet, sequence::GenomeSequence, sequence_store::SequenceStore},
+    io::{error::IOError, fasta::read_fasta_file},
+};
+use log::debug;
+use traitsequence::interface::Sequence;
+
+use crate::MatchTable;
+
+/// An error when loading sequences from fasta files.
+#[derive(Debug, thiserror::Error)]
+pub enum FastaError {
+    /// The fasta file could not be read or contains characters outside of the alphabet.
+    #[error("Error reading fasta file {path:?}: {source}")]
+    IO {
+        /// The path of the fasta file.
+        path: PathBuf,
+        /// The underlying error.
+        source: IOError,
+    },
+
+    /// The fasta file does not contain exactly one record.
+    #[error("Fasta file {path:?} contains {record_count} records, but exactly one is expected")]
+    RecordCount {
+        /// The path of the fasta file.
+        path: PathBuf,
+        /// The number of records in the fasta file.
+        record_count: usize,
+    },
+}
+
+/// A sequence loaded from a fasta file.
+pub struct FastaSequence<AlphabetType: Alphabet> {
+    /// The id of the fasta record.
+    pub id: String,
+    /// Anything after the id of the fasta record.
+    pub comment: String,
+    /// The sequence of the fasta record.
+    pub sequence: VectorGenome<AlphabetType>,
+}
+
+/// A bit-packed sequence loaded from a fasta file, see [`read_fasta_contigs_packed`].
+pub struct PackedFastaSequence<AlphabetType: Alphabet> {
+    /// The id of the fasta record.
+    pub id: String,
+    /// Anything after the id of the fasta record.
+    pub comment: String,
+    /// The sequence of the fasta record.
+    pub sequence: BitVectorGenome<AlphabetType>,
+}
+
+/// Read the single sequence contained in the fasta file at the given path.
+///
+/// Lower-case characters are parsed as upper-case, and characters outside of the alphabet result in an error.
+/// The file may be compressed.
+pub fn read_fasta_sequence<AlphabetType: Alphabet + 'static>(
+    path: impl AsRef<Path>,
+) -> Result<FastaSequence<AlphabetType>, FastaError> {
+    let path = path.as_ref();
+    let mut records = read_fasta_contigs(path)?;
+
+    if records.len() != 1 {
+        return Err(FastaError::RecordCount {
+            path: path.to_owned(),
+            record_count: records.len(),
+        });
+    }
+    Ok(records.pop().unwrap())
+}
+
+/// Read all sequences contained in the fasta file at the given path, e.g. the contigs of an assembly.
+///
+/// The sequences can be passed to [`MatchTableBuilder::build_contigs`](crate::MatchTableBuilder::build_contigs).
+/// See [`read_fasta_sequence`] for details on the parsing.
+pub fn read_fasta_contigs<AlphabetType: Alphabet + 'static>(
+    path: impl AsRef<Path>,
+) -> Result<Vec<FastaSequence<AlphabetType>>, FastaError> {
+    let path = path.as_ref();
+    debug!("Reading fasta file {path:?}");
+
+    let mut store = VectorSequenceStore::<AlphabetType>::new();
+    let records =
+        read_fasta_file(path, &mut store, false, true, &[]).map_err(|source| FastaError::IO {
+            path: path.to_owned(),
+            source,
+        })?;
+
+    Ok(records
+        .into_iter()
+        .map(|record| FastaSequence {
+            sequence: VectorGenome::from_iter(store.get(&record.sequence_handle).iter().cloned()),
+            id: record.id,
+            comment: record.comment,
+        })
+        .collect())
+}
+
+/// Read all sequences contained in the fasta file at the given path into bit-packed genomes,
+/// which store each character in the fewest bits that fit the alphabet, e.g. two bits for DNA.
+///
+/// The sequences are parsed into a [`BitVectorSequenceStore`] and never expanded to one byte per character,
+/// so large genomes take a fraction of the memory of [`read_fasta_contigs`].
+/// They can be passed to the builder like any other genome, see [`MatchTableBuilder::try_build`](crate::MatchTableBuilder::try_build).
+/// See [`read_fasta_sequence`] for details on the parsing.
+///
+/// # Example
+///
+/// ```rust
+/// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+/// use compact_genome::interface::sequence::GenomeSequence;
+/// use template_switch_error_free_inners::{MatchTable, io::fasta::read_fasta_contigs_packed};
+///
+/// let path = std::env::temp_dir().join("tsefi-fasta-packed-doctest.fa");
+/// std::fs::write(&path, ">reference\nAGGGGAA\n>query\naacccca\n").unwrap();
+///
+/// let records = read_fasta_contigs_packed::<DnaAlphabet>(&path).unwrap();
+/// assert_eq!(records[1].id, "query");
+/// let matches = MatchTable::new(
+///     records[0].sequence.as_genome_subsequence(),
+///     records[1].sequence.as_genome_subsequence(),
+///     4,
+/// );
+/// assert!(matches.has_reference_query_match(1, 1));
+/// # std::fs::remove_file(path).unwrap();
+/// ```
+pub fn read_fasta_contigs_packed<AlphabetType: Alphabet + 'static>(
+    path: impl AsRef<Path>,
+) -> Result<Vec<PackedFastaSequence<AlphabetType>>, FastaError> {
+    let path = path.as_ref();
+    debug!("Reading fasta file {path:?} into bit-packed genomes");
+
+    let mut store = BitVectorSequenceStore::<AlphabetType>::new();
+    let records =
+        read_fasta_file(path, &mut store, false, true, &[]).map_err(|source| FastaError::IO {
+            path: path.to_owned(),
+            source,
+        })?;
+
+    Ok(records
+        .into_iter()
+        .map(|record| PackedFastaSequence {
+            sequence: BitVectorGenome::from_iter(
+                store.get(&record.sequence_handle).iter().cloned(),
+            ),
+            id: record.id,
+            comment: record.comment,
+        })
+        .collect())
+}
+
+/// Load the reference and the query from the given fasta files and compute their [`MatchTable`].
+///
+/// Each fasta file must contain exactly one record.
+/// See [`read_fasta_sequence`] for details on the parsing and [`MatchTable::new`] for details on the computation.
+///
+/// # Example
+///
+/// ```rust
+/// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+/// use template_switch_error_free_inners::io::fasta::match_table_from_fasta_files;
+///
+/// let directory = std::env::temp_dir();
+/// let reference_path = directory.join("tsefi-fasta-doctest-reference.fa");
+/// let query_path = directory.join("tsefi-fasta-doctest-query.fa");
+/// std::fs::write(&reference_path, ">reference\nAGGGGAA\n").unwrap();
+/// std::fs::write(&query_path, ">query\naacccca\n").unwrap();
+///
+/// let matches =
+///     match_table_from_fasta_files::<DnaAlphabet>(&reference_path, &query_path, 4).unwrap();
+/// assert!(matches.has_reference_query_match(1, 1));
+/// # std::fs::remove_file(reference_path).unwrap();
+/// # std::fs::remove_file(query_path).unwrap();
+/// ```
+pub fn match_table_from_fasta_files<AlphabetType: Alphabet + 'static>(
+    reference_path: impl AsRef<Path>,
+    query_path: impl AsRef<Path>,
+    minimum_length: usize,
+) -> Result<MatchTable, FastaError> {
+    let reference = read_fasta_sequence::<AlphabetType>(reference_path)?;
+    let query = read_fasta_sequence::<AlphabetType>(query_path)?;
+
+    Ok(MatchTable::new(
+        reference.sequence.as_genome_subsequence(),
+        query.sequence.as_genome_subsequence(),
+        minimum_length,
+    ))
+}
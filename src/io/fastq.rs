@@ -0,0 +1,211 @@
+//! Loading of reference and query sequences from fastq files, masking low-quality bases.
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+};
+
+use bitvec::vec::BitVec;
+use compact_genome::{
+    implementation::vec_sequence::VectorGenome,
+    interface::{
+        alphabet::{Alphabet, AlphabetError},
+        sequence::OwnedGenomeSequence,
+    },
+};
+use log::debug;
+
+/// The offset of the ASCII encoding of phred quality scores in fastq files.
+const PHRED_OFFSET: u8 = 33;
+
+/// An error when loading sequences from fastq files.
+#[derive(Debug, thiserror::Error)]
+pub enum FastqError {
+    /// The fastq file could not be read.
+    #[error("Error reading fastq file {path:?}: {source}")]
+    IO {
+        /// The path of the fastq file.
+        path: PathBuf,
+        /// The underlying error.
+        source: std::io::Error,
+    },
+
+    /// The fastq file is malformed.
+    #[error("Malformed fastq file {path:?} at line {line}: {reason}")]
+    Format {
+        /// The path of the fastq file.
+        path: PathBuf,
+        /// The line number of the malformed line, starting at one.
+        line: usize,
+        /// What is wrong with the line.
+        reason: &'static str,
+    },
+
+    /// A sequence of the fastq file contains characters outside of the alphabet.
+    #[error("Error parsing sequence {id} of fastq file {path:?}: {source}")]
+    Alphabet {
+        /// The path of the fastq file.
+        path: PathBuf,
+        /// The id of the fastq record.
+        id: String,
+        /// The underlying error.
+        source: AlphabetError,
+    },
+
+    /// The fastq file does not contain exactly one record.
+    #[error("Fastq file {path:?} contains {record_count} records, but exactly one is expected")]
+    RecordCount {
+        /// The path of the fastq file.
+        path: PathBuf,
+        /// The number of records in the fastq file.
+        record_count: usize,
+    },
+}
+
+/// A sequence loaded from a fastq file.
+pub struct FastqSequence<AlphabetType: Alphabet> {
+    /// The id of the fastq record.
+    pub id: String,
+    /// Anything after the id of the fastq record.
+    pub comment: String,
+    /// The sequence of the fastq record.
+    pub sequence: VectorGenome<AlphabetType>,
+    /// Marks the bases whose quality is below the threshold.
+    ///
+    /// Pass this to [`MatchTableBuilder::reference_mask`](crate::MatchTableBuilder::reference_mask)
+    /// or [`MatchTableBuilder::query_mask`](crate::MatchTableBuilder::query_mask)
+    /// to exclude all kmers overlapping a low-quality base from matching, like kmers containing `N` are excluded by
+    /// [`MatchTableBuilder::skip_n`](crate::MatchTableBuilder::skip_n).
+    pub low_quality: BitVec,
+}
+
+/// Read the single sequence contained in the fastq file at the given path,
+/// marking the bases whose phred quality is below `min_quality`.
+///
+/// The file must be uncompressed and contain records of four lines each,
+/// with the qualities encoded as phred scores plus 33.
+/// Lower-case characters are parsed as upper-case, and characters outside of the alphabet result in an error.
+///
+/// # Example
+///
+/// ```rust
+/// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+/// use compact_genome::implementation::vec_sequence::VectorGenome;
+/// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+/// use template_switch_error_free_inners::MatchTableBuilder;
+/// use template_switch_error_free_inners::io::fastq::read_fastq_sequence;
+///
+/// let path = std::env::temp_dir().join("tsefi-fastq-doctest-query.fq");
+/// // The first `C` has quality 2.
+/// std::fs::write(&path, "@query read\nAACCCCA\n+\nIII#III\n").unwrap();
+/// let query = read_fastq_sequence::<DnaAlphabet>(&path, 20).unwrap();
+/// assert_eq!(query.id, "query");
+/// assert_eq!(query.low_quality.iter_ones().collect::<Vec<_>>(), vec![3]);
+///
+/// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAA").unwrap();
+/// let options = MatchTableBuilder::new(4);
+/// let matches = options
+///     .build(reference.as_genome_subsequence(), query.sequence.as_genome_subsequence());
+/// assert!(matches.has_reference_query_match(1, 1));
+/// let matches = options
+///     .query_mask(query.low_quality)
+///     .build(reference.as_genome_subsequence(), query.sequence.as_genome_subsequence());
+/// assert!(!matches.has_reference_query_match(1, 1));
+/// # std::fs::remove_file(path).unwrap();
+/// ```
+pub fn read_fastq_sequence<AlphabetType: Alphabet>(
+    path: impl AsRef<Path>,
+    min_quality: u8,
+) -> Result<FastqSequence<AlphabetType>, FastqError> {
+    let path = path.as_ref();
+    let mut records = read_fastq_records(path, min_quality)?;
+
+    if records.len() != 1 {
+        return Err(FastqError::RecordCount {
+            path: path.to_owned(),
+            record_count: records.len(),
+        });
+    }
+    Ok(records.pop().unwrap())
+}
+
+/// Read all sequences contained in the fastq file at the given path,
+/// marking the bases whose phred quality is below `min_quality`.
+///
+/// See [`read_fastq_sequence`] for details on the parsing.
+pub fn read_fastq_records<AlphabetType: Alphabet>(
+    path: impl AsRef<Path>,
+    min_quality: u8,
+) -> Result<Vec<FastqSequence<AlphabetType>>, FastqError> {
+    let path = path.as_ref();
+    debug!("Reading fastq file {path:?}");
+    let io_error = |source| FastqError::IO {
+        path: path.to_owned(),
+        source,
+    };
+    let format_error = |line, reason| FastqError::Format {
+        path: path.to_owned(),
+        line,
+        reason,
+    };
+
+    let mut lines = BufReader::new(File::open(path).map_err(io_error)?)
+        .lines()
+        .enumerate()
+        .map(|(index, line)| line.map(|line| (index + 1, line)));
+    let mut records = Vec::new();
+    while let Some(header) = lines.next() {
+        let (header_line, header) = header.map_err(io_error)?;
+        if header.is_empty() {
+            continue;
+        }
+        let Some(header) = header.strip_prefix('@') else {
+            return Err(format_error(
+                header_line,
+                "expected a header starting with '@'",
+            ));
+        };
+        let (id, comment) = header
+            .split_once(char::is_whitespace)
+            .unwrap_or((header, ""));
+
+        let mut next_line = |reason| match lines.next() {
+            Some(line) => line.map_err(io_error),
+            None => Err(format_error(header_line, reason)),
+        };
+        let (_, sequence) = next_line("missing sequence line")?;
+        let (separator_line, separator) = next_line("missing separator line")?;
+        let (quality_line, quality) = next_line("missing quality line")?;
+        if !separator.starts_with('+') {
+            return Err(format_error(
+                separator_line,
+                "expected a separator starting with '+'",
+            ));
+        }
+        if quality.len() != sequence.len() {
+            return Err(format_error(
+                quality_line,
+                "the quality line differs in length from the sequence line",
+            ));
+        }
+
+        let low_quality = quality
+            .bytes()
+            .map(|quality| quality.saturating_sub(PHRED_OFFSET) < min_quality)
+            .collect();
+        let sequence = VectorGenome::from_slice_u8(&sequence.to_ascii_uppercase().into_bytes())
+            .map_err(|source| FastqError::Alphabet {
+                path: path.to_owned(),
+                id: id.to_owned(),
+                source,
+            })?;
+        records.push(FastqSequence {
+            id: id.to_owned(),
+            comment: comment.trim_start().to_owned(),
+            sequence,
+            low_quality,
+        });
+    }
+    Ok(records)
+}
@@ -0,0 +1,155 @@
+//! Downsampled heatmaps of match tables.
+//!
+//! A heatmap aggregates the matches of a quadrant into a fixed number of bins,
+//! so even genome-scale tables can be rendered as an image of a few megapixels.
+
+use std::io::Write;
+
+use flate2::{Compression, write::ZlibEncoder};
+
+use crate::{MatchTable, Quadrant};
+
+/// The number of matches of a quadrant per bin of a regular grid.
+///
+/// The rows of the grid divide the primary indices, and the columns divide the secondary rc indices.
+pub struct Heatmap {
+    width: usize,
+    height: usize,
+    counts: Vec<u64>,
+}
+
+impl Heatmap {
+    /// Count the matches of the given quadrant in a grid of `width` columns and `height` rows.
+    ///
+    /// The width and height are reduced to the number of secondary and primary kmers if they are larger,
+    /// and increased to one if they are zero.
+    /// Requires memory proportional to the number of bins, and time proportional to the number of matches.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTable, Quadrant, io::heatmap::Heatmap};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+    /// let matches = MatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    ///
+    /// let heatmap = Heatmap::new(&matches, Quadrant::ReferenceReference, 2, 2);
+    /// assert_eq!(heatmap.count(0, 0), 1);
+    /// assert_eq!(heatmap.count(1, 1), 1);
+    /// assert_eq!(heatmap.count(1, 0), 0);
+    ///
+    /// let mut png = Vec::new();
+    /// heatmap.write_png(&mut png).unwrap();
+    /// assert!(png.starts_with(b"\x89PNG"));
+    /// ```
+    pub fn new(matches: &MatchTable, quadrant: Quadrant, width: usize, height: usize) -> Self {
+        let primary_kmer_count = matches.primary_kmer_count(quadrant);
+        let secondary_kmer_count = matches.secondary_kmer_count(quadrant);
+        let width = width.clamp(1, secondary_kmer_count.max(1));
+        let height = height.clamp(1, primary_kmer_count.max(1));
+
+        let mut counts = vec![0; width * height];
+        for (primary_index, secondary_rc_index) in matches.matches(quadrant) {
+            let row = bin(primary_index, primary_kmer_count, height);
+            let column = bin(secondary_rc_index, secondary_kmer_count, width);
+            counts[row * width + column] += 1;
+        }
+
+        Self {
+            width,
+            height,
+            counts,
+        }
+    }
+
+    /// Returns the number of columns.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the number of rows.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the number of matches in the bin at the given row and column.
+    pub fn count(&self, row: usize, column: usize) -> u64 {
+        self.counts[row * self.width + column]
+    }
+
+    /// Returns the largest number of matches in a bin.
+    pub fn max_count(&self) -> u64 {
+        self.counts.iter().copied().max().unwrap_or(0)
+    }
+
+    /// Write the heatmap as a greyscale PNG image with one pixel per bin.
+    ///
+    /// Empty bins are white and the fullest bins are black.
+    /// The intensity scales logarithmically with the count,
+    /// such that sparse bins remain visible next to dense ones.
+    pub fn write_png(&self, mut writer: impl Write) -> std::io::Result<()> {
+        writer.write_all(b"\x89PNG\r\n\x1a\n")?;
+
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&png_dimension(self.width)?.to_be_bytes());
+        header.extend_from_slice(&png_dimension(self.height)?.to_be_bytes());
+        // Bit depth 8, greyscale, default compression, default filtering, no interlacing.
+        header.extend_from_slice(&[8, 0, 0, 0, 0]);
+        write_png_chunk(&mut writer, b"IHDR", &header)?;
+
+        let scale = 255.0 / (self.max_count() as f64).ln_1p().max(f64::MIN_POSITIVE);
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        let mut scanline = Vec::with_capacity(self.width + 1);
+        for row in self.counts.chunks(self.width) {
+            scanline.clear();
+            // Filter type none.
+            scanline.push(0);
+            scanline.extend(
+                row.iter()
+                    .map(|&count| 255 - ((count as f64).ln_1p() * scale).round() as u8),
+            );
+            encoder.write_all(&scanline)?;
+        }
+        write_png_chunk(&mut writer, b"IDAT", &encoder.finish()?)?;
+        write_png_chunk(&mut writer, b"IEND", &[])
+    }
+}
+
+/// Returns the bin of `index` when dividing `0..count` into `bin_count` bins of nearly equal size.
+fn bin(index: usize, count: usize, bin_count: usize) -> usize {
+    (index as u128 * bin_count as u128 / count as u128) as usize
+}
+
+fn png_dimension(dimension: usize) -> std::io::Result<u32> {
+    u32::try_from(dimension)
+        .ok()
+        .filter(|&dimension| dimension <= i32::MAX as u32)
+        .ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Heatmap dimension {dimension} is too large for PNG"),
+            )
+        })
+}
+
+fn write_png_chunk(writer: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> std::io::Result<()> {
+    let length = u32::try_from(data.len()).map_err(|_| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, "PNG chunk is too large")
+    })?;
+    let mut crc = crc32fast::Hasher::new();
+    crc.update(kind);
+    crc.update(data);
+
+    writer.write_all(&length.to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+    writer.write_all(&crc.finalize().to_be_bytes())
+}
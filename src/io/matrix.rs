@@ -0,0 +1,119 @@
+//! Export of the matches of a quadrant as a sparse matrix, e.g. for loading with SciPy or Julia.
+//!
+//! The rows of the matrix are the primary kmers, and the columns are the kmers of the reverse-complemented secondary,
+//! so an entry is the `(primary_index, secondary_rc_index)` pair of a match,
+//! and an inner is a run of entries along a diagonal.
+
+use std::io::Write;
+
+use crate::{MatchTable, Quadrant};
+
+/// Write the matches of the given quadrant as a MatrixMarket coordinate pattern matrix.
+///
+/// The matrix has one row per primary kmer and one column per secondary kmer, and the entries are one-based as required by the format.
+/// The header comments record the quadrant and the minimum length of the inners.
+/// The matrix can be loaded with `scipy.io.mmread` or with `MatrixMarket.mmread` in Julia.
+///
+/// # Example
+///
+/// ```rust
+/// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+/// use compact_genome::implementation::vec_sequence::VectorGenome;
+/// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+/// use template_switch_error_free_inners::{MatchTable, Quadrant, io::matrix::write_matrix_market};
+///
+/// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+/// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+/// let matches = MatchTable::new(
+///     reference.as_genome_subsequence(),
+///     query.as_genome_subsequence(),
+///     4,
+/// );
+///
+/// let mut output = Vec::new();
+/// write_matrix_market(&matches, Quadrant::ReferenceReference, &mut output).unwrap();
+/// assert_eq!(
+///     String::from_utf8(output).unwrap(),
+///     "%%MatrixMarket matrix coordinate pattern general\n\
+///      % quadrant: reference_reference\n\
+///      % minimum_length: 4\n\
+///      % rows: primary kmers, columns: kmers of the reverse-complemented secondary\n\
+///      10 10 2\n\
+///      2 3\n\
+///      8 9\n",
+/// );
+/// ```
+pub fn write_matrix_market(
+    matches: &MatchTable,
+    quadrant: Quadrant,
+    mut writer: impl Write,
+) -> std::io::Result<()> {
+    let primary_kmer_count = matches.primary_kmer_count(quadrant);
+    let match_count: usize = (0..primary_kmer_count)
+        .map(|primary_index| matches.match_count(quadrant, primary_index))
+        .sum();
+
+    writeln!(writer, "%%MatrixMarket matrix coordinate pattern general")?;
+    writeln!(writer, "% quadrant: {quadrant}")?;
+    writeln!(writer, "% minimum_length: {}", matches.minimum_length())?;
+    writeln!(
+        writer,
+        "% rows: primary kmers, columns: kmers of the reverse-complemented secondary"
+    )?;
+    writeln!(
+        writer,
+        "{primary_kmer_count} {} {match_count}",
+        matches.secondary_kmer_count(quadrant)
+    )?;
+    for (primary_index, secondary_rc_index) in matches.matches(quadrant) {
+        writeln!(writer, "{} {}", primary_index + 1, secondary_rc_index + 1)?;
+    }
+    Ok(())
+}
+
+/// Write the matches of the given quadrant as a zero-based coordinate list of tab-separated values.
+///
+/// The header line names the columns `row` and `column` and records the shape of the matrix as `rows=...` and `columns=...`,
+/// followed by one line per match with its primary index and its secondary rc index.
+/// The matches are ordered by row and then by column.
+/// The list can be loaded e.g. with `numpy.loadtxt(path, skiprows=1, dtype=int)` and passed to `scipy.sparse.coo_array`.
+///
+/// # Example
+///
+/// ```rust
+/// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+/// use compact_genome::implementation::vec_sequence::VectorGenome;
+/// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+/// use template_switch_error_free_inners::{MatchTable, Quadrant, io::matrix::write_coo};
+///
+/// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+/// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+/// let matches = MatchTable::new(
+///     reference.as_genome_subsequence(),
+///     query.as_genome_subsequence(),
+///     4,
+/// );
+///
+/// let mut output = Vec::new();
+/// write_coo(&matches, Quadrant::ReferenceReference, &mut output).unwrap();
+/// assert_eq!(
+///     String::from_utf8(output).unwrap(),
+///     "row\tcolumn\trows=10\tcolumns=10\n1\t2\n7\t8\n",
+/// );
+/// ```
+pub fn write_coo(
+    matches: &MatchTable,
+    quadrant: Quadrant,
+    mut writer: impl Write,
+) -> std::io::Result<()> {
+    writeln!(
+        writer,
+        "row\tcolumn\trows={}\tcolumns={}",
+        matches.primary_kmer_count(quadrant),
+        matches.secondary_kmer_count(quadrant)
+    )?;
+    for (primary_index, secondary_rc_index) in matches.matches(quadrant) {
+        writeln!(writer, "{primary_index}\t{secondary_rc_index}")?;
+    }
+    Ok(())
+}
@@ -0,0 +1,450 @@
+//! A match table that compares kmers on demand instead of precomputing all matches.
+
+use bitvec::vec::BitVec;
+use compact_genome::interface::{alphabet::Alphabet, sequence::GenomeSequence};
+use suffix::SuffixTable;
+
+use crate::{
+    AmbiguityPolicy, ContigLayout, MatchOrientation, MatchTableBuilder, Quadrant, StorageIndex,
+    band::Band,
+    construction::{RcText, Texts, kmers_match},
+    index::ascii_str,
+    mask::KmerFlags,
+    packed::PackedText,
+};
+
+/// A genome together with its suffix table, which owns the text.
+struct LazyGenome {
+    index: SuffixTable<'static, 'static>,
+    flags: KmerFlags,
+    /// The packed text and reverse complement, if the genome consists only of `ACGT`.
+    packed: Option<(PackedText, PackedText)>,
+}
+
+impl LazyGenome {
+    fn text(&self) -> &[u8] {
+        self.index.text().as_bytes()
+    }
+
+    fn kmer_count(&self) -> usize {
+        self.flags.excluded.len()
+    }
+
+    fn packed_text(&self) -> Option<&PackedText> {
+        self.packed.as_ref().map(|(text, _)| text)
+    }
+
+    fn packed_rc(&self) -> Option<&PackedText> {
+        self.packed.as_ref().map(|(_, rc)| rc)
+    }
+}
+
+/// A match table that answers queries by comparing kmers on demand.
+///
+/// Construction takes time and memory linear in the length of the genomes,
+/// since only the genomes and their suffix tables are stored.
+/// Reverse complements are computed on the fly.
+/// [`has_match`](Self::has_match) compares the two kmers directly,
+/// and [`row_matches`](Self::row_matches) looks up the reverse complement of the primary kmer in the suffix table.
+/// This avoids the quadratic precomputation of a [`MatchTable`](crate::MatchTable) if only few pairs are queried.
+///
+/// The table reports the same matches as a [`MatchTable`](crate::MatchTable) built with the same options,
+/// except that the [quadrant selection](MatchTableBuilder::quadrants) is ignored, since no quadrant is precomputed.
+pub struct LazyMatchTable {
+    reference: LazyGenome,
+    query: LazyGenome,
+    minimum_length: usize,
+    max_mismatches: usize,
+    ambiguity_policy: AmbiguityPolicy,
+    band: Option<Band>,
+    orientation: MatchOrientation,
+    /// Maps each character of the alphabet to its complement.
+    complement: [u8; 256],
+}
+
+impl LazyMatchTable {
+    /// Prepare on-demand queries of the error-free template switch inners of the given minimum length for a pair of genome strings.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`MatchTableBuilder::try_build_lazy`] returns an error.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{LazyMatchTable, Quadrant};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+    /// let matches = LazyMatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    ///
+    /// assert!(matches.has_reference_reference_match(1, 2));
+    /// assert!(!matches.has_reference_reference_match(1, 3));
+    /// assert_eq!(
+    ///     matches.row_matches(Quadrant::ReferenceReference, 7).collect::<Vec<_>>(),
+    ///     vec![8],
+    /// );
+    /// ```
+    pub fn new<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        reference: &GenomeSubsequence,
+        query: &GenomeSubsequence,
+        minimum_length: usize,
+    ) -> Self {
+        MatchTableBuilder::new(minimum_length).build_lazy(reference, query)
+    }
+
+    /// Prepare the queries of the given texts, which must be valid for the options.
+    pub(crate) fn from_texts(
+        texts: Texts,
+        [reference_contigs, query_contigs]: [ContigLayout; 2],
+        options: &MatchTableBuilder<impl StorageIndex>,
+    ) -> Self {
+        let Texts {
+            reference,
+            query,
+            complement,
+            frequent_kmers,
+        } = texts;
+        let [frequent_reference_kmers, frequent_query_kmers] =
+            frequent_kmers.map_or([None, None], |kmers| kmers.map(Some));
+        let genome = |text: Vec<u8>,
+                      frequent: Option<BitVec>,
+                      mask,
+                      excluded_intervals,
+                      contigs: &ContigLayout| LazyGenome {
+            flags: KmerFlags::new(
+                &text,
+                frequent.as_ref(),
+                mask,
+                excluded_intervals,
+                contigs,
+                options,
+            ),
+            packed: PackedText::new(&text).and_then(|packed_text| {
+                PackedText::from_characters(RcText::new(&text, &complement).iter())
+                    .map(|packed_rc| (packed_text, packed_rc))
+            }),
+            index: SuffixTable::new(
+                String::from_utf8(text).expect("the characters of an alphabet are ASCII"),
+            ),
+        };
+
+        Self {
+            reference: genome(
+                reference,
+                frequent_reference_kmers,
+                options.reference_mask.as_ref(),
+                &options.reference_excluded_intervals,
+                &reference_contigs,
+            ),
+            query: genome(
+                query,
+                frequent_query_kmers,
+                options.query_mask.as_ref(),
+                &options.query_excluded_intervals,
+                &query_contigs,
+            ),
+            minimum_length: options.minimum_length,
+            max_mismatches: options.max_mismatches,
+            ambiguity_policy: options.ambiguity_policy,
+            band: options.band,
+            orientation: options.orientation,
+            complement,
+        }
+    }
+
+    fn rc<'table>(&'table self, genome: &'table LazyGenome) -> RcText<'table> {
+        RcText::new(genome.text(), &self.complement)
+    }
+
+    fn genomes(&self, quadrant: Quadrant) -> (&LazyGenome, &LazyGenome) {
+        let genome = |is_reference| {
+            if is_reference {
+                &self.reference
+            } else {
+                &self.query
+            }
+        };
+        (
+            genome(quadrant.primary_is_reference()),
+            genome(quadrant.secondary_is_reference()),
+        )
+    }
+
+    /// Returns `true` if the primary kmer at `primary_index` matches the reverse-complemented secondary kmer at `secondary_rc_index` in the given quadrant.
+    ///
+    /// Takes time linear in the minimum length.
+    /// If both genomes consist only of `ACGT`, then the kmers are compared 32 characters at a time,
+    /// or with the `simd` feature 128 characters at a time on CPUs that support AVX2.
+    pub fn has_match(
+        &self,
+        quadrant: Quadrant,
+        primary_index: usize,
+        secondary_rc_index: usize,
+    ) -> bool {
+        let (primary, secondary) = self.genomes(quadrant);
+        debug_assert!(primary_index < primary.kmer_count());
+        debug_assert!(secondary_rc_index < secondary.kmer_count());
+
+        let k = self.minimum_length;
+        let secondary_index = secondary.kmer_count() - 1 - secondary_rc_index;
+        let primary_kmer = &primary.text()[primary_index..primary_index + k];
+        // Without ambiguous characters, all ambiguity policies compare characters literally.
+        let kmer_matches = |secondary_kmer: &mut dyn Iterator<Item = u8>,
+                            packed_secondary_text: Option<&PackedText>,
+                            secondary_start: usize| {
+            match primary.packed_text().zip(packed_secondary_text) {
+                Some((packed_primary_text, packed_secondary_text)) => {
+                    packed_primary_text.mismatch_count(
+                        primary_index,
+                        packed_secondary_text,
+                        secondary_start,
+                        k,
+                    ) <= self.max_mismatches
+                }
+                None => kmers_match(
+                    primary_kmer,
+                    secondary_kmer,
+                    self.max_mismatches,
+                    self.ambiguity_policy,
+                ),
+            }
+        };
+
+        !primary.flags.excluded[primary_index]
+            && !secondary.flags.excluded[secondary_index]
+            && (primary.flags.is_minimizer(primary_index)
+                || secondary.flags.is_minimizer(secondary_index))
+            && self
+                .band
+                .is_none_or(|band| band.contains(primary_index, secondary_rc_index))
+            && ((self.orientation.reverse_complement()
+                && kmer_matches(
+                    &mut self.rc(secondary).substring(secondary_rc_index, k),
+                    secondary.packed_rc(),
+                    secondary_rc_index,
+                ))
+                || (self.orientation.forward()
+                    && kmer_matches(
+                        &mut secondary.text()[secondary_index..secondary_index + k]
+                            .iter()
+                            .copied(),
+                        secondary.packed_text(),
+                        secondary_index,
+                    )))
+    }
+
+    /// Returns an iterator over the secondary rc indices that match the primary kmer at `primary_index` in the given quadrant.
+    ///
+    /// The indices are returned in increasing order.
+    /// Without mismatches, the reverse complement of the primary kmer is looked up in the suffix table of the secondary genome,
+    /// taking time logarithmic in its length.
+    /// With mismatches or matching in forward orientation, all secondary kmers within the band are compared.
+    pub fn row_matches(
+        &self,
+        quadrant: Quadrant,
+        primary_index: usize,
+    ) -> impl Iterator<Item = usize> + '_ {
+        let (primary, secondary) = self.genomes(quadrant);
+        let secondary_kmer_count = secondary.kmer_count();
+
+        let candidates: Vec<usize> = if self.max_mismatches > 0
+            || self.orientation != MatchOrientation::ReverseComplement
+            || primary.flags.ambiguous[primary_index]
+        {
+            match self.band {
+                Some(band) => band
+                    .secondary_range(primary_index, secondary_kmer_count)
+                    .collect(),
+                None => (0..secondary_kmer_count).collect(),
+            }
+        } else {
+            // The primary kmer matches a secondary rc kmer if its reverse complement matches the forward secondary kmer.
+            let rc_kmer: Vec<u8> = self
+                .rc(primary)
+                .substring(
+                    primary.kmer_count() - 1 - primary_index,
+                    self.minimum_length,
+                )
+                .collect();
+            let mut candidates: Vec<_> = secondary
+                .index
+                .positions(ascii_str(&rc_kmer))
+                .iter()
+                .map(|&position| secondary_kmer_count - 1 - position as usize)
+                .chain(
+                    // Ambiguous kmers may match without being equal.
+                    secondary
+                        .flags
+                        .ambiguous_kmers
+                        .iter()
+                        .map(|&forward_index| secondary_kmer_count - 1 - forward_index),
+                )
+                .collect();
+            candidates.sort_unstable();
+            candidates.dedup();
+            candidates
+        };
+
+        candidates.into_iter().filter(move |&secondary_rc_index| {
+            self.has_match(quadrant, primary_index, secondary_rc_index)
+        })
+    }
+
+    /// Returns the length of the longest inner that starts with the match `(primary_index, secondary_rc_index)` in the given quadrant,
+    /// or `None` if the kmers do not match.
+    ///
+    /// The result is the same as of [`MatchTable::longest_match_length`](crate::MatchTable::longest_match_length)
+    /// for a table built with the same options.
+    /// Without mismatches, matching in forward orientation and sparsification, if both genomes consist only of `ACGT`,
+    /// the inner is extended by comparing 32 characters at a time,
+    /// or with the `simd` feature 128 characters at a time on CPUs that support AVX2.
+    /// Otherwise, the consecutive kmers along the diagonal are compared one by one.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{LazyMatchTable, Quadrant};
+    ///
+    /// // The reverse complement of `GGGGA` is `TCCCC`.
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAATCCCCAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+    /// let matches = LazyMatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    ///
+    /// assert_eq!(matches.longest_match_length(Quadrant::ReferenceReference, 1, 2), Some(5));
+    /// assert_eq!(matches.longest_match_length(Quadrant::ReferenceReference, 2, 2), None);
+    /// ```
+    pub fn longest_match_length(
+        &self,
+        quadrant: Quadrant,
+        primary_index: usize,
+        secondary_rc_index: usize,
+    ) -> Option<usize> {
+        if !self.has_match(quadrant, primary_index, secondary_rc_index) {
+            return None;
+        }
+        let (primary, secondary) = self.genomes(quadrant);
+        let secondary_kmer_count = secondary.kmer_count();
+
+        let is_packed_extension = self.max_mismatches == 0
+            && self.orientation == MatchOrientation::ReverseComplement
+            && primary.flags.minimizers.is_none()
+            && secondary.flags.minimizers.is_none();
+        let run_length = match primary.packed_text().zip(secondary.packed_rc()) {
+            Some((packed_primary_text, packed_secondary_rc)) if is_packed_extension => {
+                // The band is constant along the diagonal, so only excluded kmers end the run early.
+                let common_prefix_length = packed_primary_text.common_prefix_length(
+                    primary_index,
+                    packed_secondary_rc,
+                    secondary_rc_index,
+                );
+                let run_length = common_prefix_length + 1 - self.minimum_length;
+                let primary_run_length = primary.flags.excluded
+                    [primary_index..primary_index + run_length]
+                    .first_one()
+                    .unwrap_or(run_length);
+                // The secondary forward indices of the run decrease from `secondary_kmer_count - 1 - secondary_rc_index`.
+                let secondary_end = secondary_kmer_count - secondary_rc_index;
+                let secondary_run_length = secondary.flags.excluded
+                    [secondary_end - run_length..secondary_end]
+                    .last_one()
+                    .map_or(run_length, |offset| run_length - 1 - offset);
+                primary_run_length.min(secondary_run_length)
+            }
+            _ => {
+                let primary_kmer_count = primary.kmer_count();
+                (1..)
+                    .take_while(|&offset| {
+                        primary_index + offset < primary_kmer_count
+                            && secondary_rc_index + offset < secondary_kmer_count
+                            && self.has_match(
+                                quadrant,
+                                primary_index + offset,
+                                secondary_rc_index + offset,
+                            )
+                    })
+                    .count()
+                    + 1
+            }
+        };
+        Some(self.minimum_length + run_length - 1)
+    }
+
+    /// Returns `true` if the reference kmer at `primary_index` matches the kmer in the reverse-complemented reference at `secondary_rc_index`.
+    pub fn has_reference_reference_match(
+        &self,
+        primary_index: usize,
+        secondary_rc_index: usize,
+    ) -> bool {
+        self.has_match(
+            Quadrant::ReferenceReference,
+            primary_index,
+            secondary_rc_index,
+        )
+    }
+
+    /// Returns `true` if the reference kmer at `primary_index` matches the kmer in the reverse-complemented query at `secondary_rc_index`.
+    pub fn has_reference_query_match(
+        &self,
+        primary_index: usize,
+        secondary_rc_index: usize,
+    ) -> bool {
+        self.has_match(Quadrant::ReferenceQuery, primary_index, secondary_rc_index)
+    }
+
+    /// Returns `true` if the query kmer at `primary_index` matches the kmer in the reverse-complemented reference at `secondary_rc_index`.
+    pub fn has_query_reference_match(
+        &self,
+        primary_index: usize,
+        secondary_rc_index: usize,
+    ) -> bool {
+        self.has_match(Quadrant::QueryReference, primary_index, secondary_rc_index)
+    }
+
+    /// Returns `true` if the query kmer at `primary_index` matches the kmer in the reverse-complemented query at `secondary_rc_index`.
+    pub fn has_query_query_match(&self, primary_index: usize, secondary_rc_index: usize) -> bool {
+        self.has_match(Quadrant::QueryQuery, primary_index, secondary_rc_index)
+    }
+
+    /// Returns the minimum length of the inners, i.e. the length of the kmers.
+    pub fn minimum_length(&self) -> usize {
+        self.minimum_length
+    }
+
+    /// Returns the number of kmers in the reference.
+    pub fn reference_kmer_count(&self) -> usize {
+        self.reference.kmer_count()
+    }
+
+    /// Returns the number of kmers in the query.
+    pub fn query_kmer_count(&self) -> usize {
+        self.query.kmer_count()
+    }
+
+    /// Returns the number of valid primary indices in the given quadrant.
+    pub fn primary_kmer_count(&self, quadrant: Quadrant) -> usize {
+        self.genomes(quadrant).0.kmer_count()
+    }
+
+    /// Returns the number of valid secondary rc indices in the given quadrant.
+    pub fn secondary_kmer_count(&self, quadrant: Quadrant) -> usize {
+        self.genomes(quadrant).1.kmer_count()
+    }
+}
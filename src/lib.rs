@@ -2,33 +2,167 @@
 
 #![warn(missing_docs)]
 
-use std::iter;
+use std::ops::Range;
 
-use bitvec::vec::BitVec;
+use band::Band;
 use compact_genome::{
-    implementation::vec_sequence::VectorGenome,
-    interface::{alphabet::Alphabet, sequence::GenomeSequence},
+    implementation::{alphabets::dna_alphabet::DnaAlphabet, vec_sequence::VectorGenome},
+    interface::{
+        alphabet::Alphabet,
+        sequence::{GenomeSequence, OwnedGenomeSequence},
+    },
 };
-use log::debug;
-use suffix::SuffixTable;
+use fingerprint::Fingerprint;
+use storage::QuadrantStorage;
 
+pub use alignment::{AlignmentProjection, ProjectedMatch};
+pub use all_vs_all::AllVsAllMatchTable;
+pub use builder::MatchTableBuilder;
+pub use candidate::{CandidateConstraints, TemplateSwitchCandidate};
+pub use chaining::{ChainingParameters, MatchChain};
+pub use chunked::MatchChunk;
+pub use complexity::LowComplexityFilter;
+pub use construction::MatchOrientation;
+pub use contig::{ContigLayout, ContigPosition};
+pub use coordinates::{forward_end_to_rc_index, rc_index_to_forward, rc_index_to_forward_end};
+pub use detect::{DetectionParameters, TemplateSwitchEvent, detect_template_switches};
+pub use diff::MatchTableDiff;
+pub use dotplot::Dotplot;
+pub use error::MatchTableError;
+pub use extension::Maximality;
+pub use index::{ConstructionStrategy, IndexBackend, SequenceIndex};
+pub use lazy::LazyMatchTable;
+pub use mask::{AmbiguityPolicy, soft_masked_characters};
+pub use memory::MemoryEstimate;
+pub use multi_k::MultiKMatchTable;
+pub use positions::KmerPositions;
+pub use progress::{ProgressEvent, ProgressReporter};
+pub use quadrant::{Quadrant, Quadrants};
+pub use quadtree::MatchQuadtree;
+pub use run_length::RunLengthMatchTable;
+pub use scanner::{ScannedWindow, ScannedWindows, WindowScanner};
+pub use scoring::{DefaultScore, Inner, Score, ScoredInner};
+pub use seeds::TsalignSeed;
+pub use select::RankSelectIndex;
+pub use shared::SharedMatchTable;
+pub use statistics::{
+    BUSIEST_LINE_COUNT, InnerLengthHistogram, MatchTableStatistics, QuadrantStatistics,
+};
+pub use storage::{StorageBackend, StorageIndex};
+pub use stream::Match;
+#[cfg(not(target_arch = "wasm32"))]
+pub use stream::{MatchStream, find_matches_streaming};
+pub use transposed::SecondaryMajorView;
+pub use x_drop::{XDropExtension, XDropParameters};
+
+/// Enter an info-level [`tracing`](https://docs.rs/tracing) span until the end of the enclosing block, if the `tracing` feature is enabled.
+macro_rules! enter_span {
+    ($name:literal $(, $($fields:tt)*)?) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!($name $(, $($fields)*)?).entered();
+    };
+}
+
+mod alignment;
+mod all_vs_all;
+mod band;
+mod binary;
+mod bloom;
+mod builder;
+mod candidate;
+mod chaining;
+mod chunked;
+mod complexity;
+mod construction;
+mod contig;
+mod coordinates;
+mod crop;
+#[cfg(feature = "ndarray")]
+mod dense_array;
+mod detect;
+mod diff;
+mod dotplot;
+mod error;
+mod extension;
+mod fingerprint;
+#[cfg(feature = "gpu")]
+mod gpu;
+mod index;
+pub mod io;
+mod lazy;
+mod mask;
+mod memory;
+mod merge;
+mod minimizer;
+mod multi_k;
+#[cfg(feature = "naive")]
+pub mod naive;
+mod packed;
+mod positions;
+mod progress;
+mod quadrant;
+mod quadtree;
+mod rank;
+mod run_length;
+mod sampling;
+mod scanner;
+mod scoring;
+mod seeds;
+mod select;
+mod shared;
+pub mod simulate;
+mod statistics;
+mod storage;
+mod stream;
 #[cfg(test)]
 mod tests;
+mod transposed;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+mod x_drop;
 
 /// A table of all error-free template switch inner entry points for a pair of genome strings.
-pub struct MatchTable {
-    reference_reference: BitVec,
-    reference_query: BitVec,
-    query_reference: BitVec,
-    query_query: BitVec,
+///
+/// The table is immutable after construction and `Send + Sync`, and all queries take `&self`,
+/// so it can be queried concurrently from several threads.
+/// To give each thread its own handle without copying the table, use [`into_shared`](Self::into_shared).
+///
+/// The kmer indices are stored as `Index`, which is `u32` by default, see [`StorageIndex`].
+pub struct MatchTable<Index: StorageIndex = u32> {
+    reference_reference: QuadrantStorage<Index>,
+    reference_query: QuadrantStorage<Index>,
+    query_reference: QuadrantStorage<Index>,
+    query_query: QuadrantStorage<Index>,
     reference_kmer_count: usize,
     query_kmer_count: usize,
+    minimum_length: usize,
+    max_mismatches: usize,
+    skipped_reference_kmer_count: usize,
+    skipped_query_kmer_count: usize,
+    max_kmer_occurrences: Option<usize>,
+    unique_matches_only: bool,
+    maximum_length: Option<usize>,
+    frequent_reference_kmer_count: usize,
+    frequent_query_kmer_count: usize,
+    reference_contigs: ContigLayout,
+    query_contigs: ContigLayout,
+    band: Option<Band>,
+    quadrants: Quadrants,
+    reference_excluded_intervals: Vec<Range<usize>>,
+    query_excluded_intervals: Vec<Range<usize>>,
+    /// The hashes of the inputs, or `None` if the table was merged, cropped or loaded from an older binary format.
+    fingerprint: Option<Fingerprint>,
 }
 
 impl MatchTable {
     /// Compute all error-free template switch inner entry points for a pair of genome strings.
     ///
     /// The inners must have the given minimum length.
+    /// The matches are stored in [`StorageBackend::Dense`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`try_new`](Self::try_new) returns an error.
     pub fn new<
         AlphabetType: Alphabet,
         GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
@@ -37,94 +171,333 @@ impl MatchTable {
         query: &GenomeSubsequence,
         minimum_length: usize,
     ) -> Self {
-        assert!(minimum_length > 0);
-
-        debug!("Converting genomes to strings");
-        let reference_rc =
-            VectorGenome::<AlphabetType>::from_iter(reference.reverse_complement_iter())
-                .as_string();
-        let query_rc =
-            VectorGenome::<AlphabetType>::from_iter(query.reverse_complement_iter()).as_string();
-        let reference = reference.as_string();
-        let query = query.as_string();
-
-        debug!("Computing indexes");
-        let reference_index = SuffixTable::new(&reference);
-        let query_index = SuffixTable::new(&query);
-
-        debug!("Initialising bitvectors");
-        let reference_kmer_count = reference.len() - minimum_length + 1;
-        let query_kmer_count = query.len() - minimum_length + 1;
-
-        let mut reference_reference =
-            BitVec::repeat(false, reference_kmer_count * reference_kmer_count);
-        let mut reference_query = BitVec::repeat(false, reference_kmer_count * query_kmer_count);
-        let mut query_reference = BitVec::repeat(false, query_kmer_count * reference_kmer_count);
-        let mut query_query = BitVec::repeat(false, query_kmer_count * query_kmer_count);
-
-        debug!("Finding matches");
-        let reference_rc_character_offsets: Vec<_> = reference_rc
-            .char_indices()
-            .map(|(index, _)| index)
-            .chain(iter::once(reference_rc.len()))
-            .collect();
-        let query_rc_character_offsets: Vec<_> = query_rc
-            .char_indices()
-            .map(|(index, _)| index)
-            .chain(iter::once(query_rc.len()))
-            .collect();
-
-        for reference_rc_kmer_index in 0..reference_kmer_count {
-            let reference_rc_kmer = &reference_rc[reference_rc_character_offsets
-                [reference_rc_kmer_index]
-                ..reference_rc_character_offsets[reference_rc_kmer_index + minimum_length]];
-
-            for reference_kmer_index in reference_index.positions(reference_rc_kmer) {
-                let reference_kmer_index = usize::try_from(*reference_kmer_index).unwrap();
-                reference_reference.set(
-                    reference_kmer_index * reference_kmer_count + reference_rc_kmer_index,
-                    true,
-                );
-            }
+        MatchTableBuilder::new(minimum_length).build(reference, query)
+    }
 
-            for query_kmer_index in query_index.positions(reference_rc_kmer) {
-                let query_kmer_index = usize::try_from(*query_kmer_index).unwrap();
-                query_reference.set(
-                    query_kmer_index * reference_kmer_count + reference_rc_kmer_index,
-                    true,
-                );
-            }
-        }
+    /// Compute all error-free template switch inner entry points for a pair of genome strings,
+    /// returning an error instead of panicking on invalid inputs.
+    ///
+    /// The inners must have the given minimum length.
+    /// The matches are stored in [`StorageBackend::Dense`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTable, MatchTableError};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AAA").unwrap();
+    /// let result = MatchTable::try_new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    ///
+    /// assert!(matches!(
+    ///     result,
+    ///     Err(MatchTableError::SequenceTooShort { sequence: "query", length: 3, .. })
+    /// ));
+    /// ```
+    pub fn try_new<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        reference: &GenomeSubsequence,
+        query: &GenomeSubsequence,
+        minimum_length: usize,
+    ) -> Result<Self, MatchTableError> {
+        MatchTableBuilder::new(minimum_length).try_build(reference, query)
+    }
 
-        for query_rc_kmer_index in 0..query_kmer_count {
-            let query_rc_kmer = &query_rc[query_rc_character_offsets[query_rc_kmer_index]
-                ..query_rc_character_offsets[query_rc_kmer_index + minimum_length]];
+    /// Compute all error-free template switch inner entry points for a pair of DNA strings given as ASCII,
+    /// e.g. as `&[u8]` or `&str`.
+    ///
+    /// The sequences are encoded into [`DnaAlphabet`],
+    /// which consists of the upper-case characters `ACGT` only.
+    /// Otherwise, this is equivalent to [`try_new`](Self::try_new).
+    ///
+    /// Returns an error with the position of the first invalid character if a sequence contains a character not in `ACGT`,
+    /// or if [`try_new`](Self::try_new) returns an error.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use template_switch_error_free_inners::{MatchTable, MatchTableError};
+    ///
+    /// let matches = MatchTable::from_ascii(b"AGGGGAACCCCAA", "AAAAAAAA", 4).unwrap();
+    /// assert!(matches.has_reference_reference_match(1, 2));
+    ///
+    /// let result = MatchTable::from_ascii("AGGGGAACCCCAA", "AAAnAAAA", 4);
+    /// assert!(matches!(
+    ///     result,
+    ///     Err(MatchTableError::InvalidCharacter { sequence: "query", position: 3, character: b'n' })
+    /// ));
+    /// ```
+    pub fn from_ascii(
+        reference: impl AsRef<[u8]>,
+        query: impl AsRef<[u8]>,
+        minimum_length: usize,
+    ) -> Result<Self, MatchTableError> {
+        let reference = dna_from_ascii("reference", reference.as_ref())?;
+        let query = dna_from_ascii("query", query.as_ref())?;
+        Self::try_new(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+            minimum_length,
+        )
+    }
 
-            for reference_kmer_index in reference_index.positions(query_rc_kmer) {
-                let reference_kmer_index = usize::try_from(*reference_kmer_index).unwrap();
-                reference_query.set(
-                    reference_kmer_index * query_kmer_count + query_rc_kmer_index,
-                    true,
-                );
-            }
+    /// Compute all error-free template switch inner entry points of a genome string against itself.
+    ///
+    /// The inners must have the given minimum length.
+    /// Only [`Quadrant::ReferenceReference`] is computed, with the sequence as the reference.
+    /// The query is empty, so the other quadrants contain no kmers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`MatchTableBuilder::try_build_self`] returns an error.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::MatchTable;
+    ///
+    /// let sequence = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    /// let matches = MatchTable::new_self(sequence.as_genome_subsequence(), 4);
+    ///
+    /// assert!(matches.has_reference_reference_match(1, 2));
+    /// assert!(matches.has_reference_reference_match(7, 8));
+    /// assert_eq!(matches.query_kmer_count(), 0);
+    /// ```
+    pub fn new_self<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        sequence: &GenomeSubsequence,
+        minimum_length: usize,
+    ) -> Self {
+        MatchTableBuilder::new(minimum_length).build_self(sequence)
+    }
 
-            for query_kmer_index in query_index.positions(query_rc_kmer) {
-                let query_kmer_index = usize::try_from(*query_kmer_index).unwrap();
-                query_query.set(
-                    query_kmer_index * query_kmer_count + query_rc_kmer_index,
-                    true,
-                );
-            }
-        }
+    /// Compute all error-free template switch inner entry points for a pair of genome strings,
+    /// storing the matches in the given storage backend.
+    ///
+    /// The inners must have the given minimum length.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTable, StorageBackend};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+    /// let matches = MatchTable::new_with_storage(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    ///     StorageBackend::Sparse,
+    /// );
+    ///
+    /// assert!(matches.has_reference_reference_match(1, 2));
+    /// assert!(!matches.has_reference_reference_match(1, 3));
+    /// ```
+    pub fn new_with_storage<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        reference: &GenomeSubsequence,
+        query: &GenomeSubsequence,
+        minimum_length: usize,
+        storage: StorageBackend,
+    ) -> Self {
+        MatchTableBuilder::new(minimum_length)
+            .storage(storage)
+            .build(reference, query)
+    }
 
-        Self {
-            reference_reference,
-            reference_query,
-            query_reference,
-            query_query,
-            reference_kmer_count,
-            query_kmer_count,
-        }
+    /// Compute all template switch inner entry points with at most `max_mismatches` mismatches for a pair of genome strings.
+    ///
+    /// The inners must have the given minimum length, which must be greater than `max_mismatches`.
+    /// A primary kmer matches a reverse-complemented secondary kmer if they have a Hamming distance of at most `max_mismatches`.
+    /// The matches are stored in [`StorageBackend::Dense`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::MatchTable;
+    ///
+    /// // The reverse complement of the query is `TTACGTATT`.
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"TTACGGATT").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AATACGTAA").unwrap();
+    /// let matches = MatchTable::new_with_mismatches(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     5,
+    ///     1,
+    /// );
+    ///
+    /// assert!(matches.has_reference_query_match(1, 1));
+    /// assert!(!matches.has_reference_query_match(0, 4));
+    /// ```
+    pub fn new_with_mismatches<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        reference: &GenomeSubsequence,
+        query: &GenomeSubsequence,
+        minimum_length: usize,
+        max_mismatches: usize,
+    ) -> Self {
+        MatchTableBuilder::new(minimum_length)
+            .max_mismatches(max_mismatches)
+            .build(reference, query)
+    }
+
+    /// Compute all error-free template switch inner entry points for a pair of genome strings,
+    /// using the given index to find the occurrences of kmers.
+    ///
+    /// The inners must have the given minimum length.
+    /// The construction uses [`ConstructionStrategy::IndexLookup`].
+    /// The matches are stored in [`StorageBackend::Dense`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{IndexBackend, MatchTable};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+    /// let matches = MatchTable::new_with_index_backend(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    ///     IndexBackend::FmIndex,
+    /// );
+    ///
+    /// assert!(matches.has_reference_reference_match(1, 2));
+    /// assert!(matches.has_reference_reference_match(7, 8));
+    /// ```
+    pub fn new_with_index_backend<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        reference: &GenomeSubsequence,
+        query: &GenomeSubsequence,
+        minimum_length: usize,
+        index_backend: IndexBackend,
+    ) -> Self {
+        MatchTableBuilder::new(minimum_length)
+            .index_backend(index_backend)
+            .strategy(ConstructionStrategy::IndexLookup)
+            .build(reference, query)
+    }
+
+    /// Compute all error-free template switch inner entry points for a pair of genome strings,
+    /// using the given strategy to find the matching kmers.
+    ///
+    /// The inners must have the given minimum length.
+    /// The matches are stored in [`StorageBackend::Dense`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{ConstructionStrategy, MatchTable};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+    /// let matches = MatchTable::new_with_strategy(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    ///     ConstructionStrategy::HashJoin,
+    /// );
+    ///
+    /// assert!(matches.has_reference_reference_match(1, 2));
+    /// assert!(matches.has_reference_reference_match(7, 8));
+    /// ```
+    pub fn new_with_strategy<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        reference: &GenomeSubsequence,
+        query: &GenomeSubsequence,
+        minimum_length: usize,
+        strategy: ConstructionStrategy,
+    ) -> Self {
+        MatchTableBuilder::new(minimum_length)
+            .strategy(strategy)
+            .build(reference, query)
+    }
+
+    /// Compute all error-free template switch inner entry points for a pair of genome strings,
+    /// storing the matches in a memory-mapped file at the given path.
+    ///
+    /// The inners must have the given minimum length.
+    /// The file is created or truncated, and holds one bit for each pair of kmers like [`StorageBackend::Dense`].
+    /// Queries are served through the mapping, such that the operating system can page out parts of the table
+    /// that are not in use.
+    /// The file is not deleted when the table is dropped.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::MatchTable;
+    ///
+    /// let path = std::env::temp_dir().join("tsefi-new-mmap-doctest.bin");
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+    /// let matches = MatchTable::new_mmap(
+    ///     &path,
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// )
+    /// .unwrap();
+    ///
+    /// assert!(matches.has_reference_reference_match(1, 2));
+    /// assert!(matches.has_reference_reference_match(7, 8));
+    /// # drop(matches);
+    /// # std::fs::remove_file(path).unwrap();
+    /// ```
+    #[cfg(feature = "mmap")]
+    pub fn new_mmap<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        path: impl AsRef<std::path::Path>,
+        reference: &GenomeSubsequence,
+        query: &GenomeSubsequence,
+        minimum_length: usize,
+    ) -> Result<Self, MatchTableError> {
+        MatchTableBuilder::new(minimum_length).build_mmap(path, reference, query)
+    }
+}
+
+impl<Index: StorageIndex> MatchTable<Index> {
+    /// Wrap the table into a [`SharedMatchTable`], which can be cloned cheaply to query the table from several threads.
+    pub fn into_shared(self) -> SharedMatchTable<Index> {
+        SharedMatchTable::new(self)
     }
 
     /// Returns `true` if the reference kmer at `primary_index` matches the kmer in the reverse-complemented reference at `secondary_rc_index`.
@@ -155,7 +528,8 @@ impl MatchTable {
     ) -> bool {
         debug_assert!(primary_index < self.reference_kmer_count);
         debug_assert!(secondary_rc_index < self.reference_kmer_count);
-        self.reference_reference[primary_index * self.reference_kmer_count + secondary_rc_index]
+        self.reference_reference
+            .has_match(primary_index, secondary_rc_index)
     }
 
     /// Returns `true` if the reference kmer at `primary_index` matches the kmer in the reverse-complemented query at `secondary_rc_index`.
@@ -185,7 +559,8 @@ impl MatchTable {
     ) -> bool {
         debug_assert!(primary_index < self.reference_kmer_count);
         debug_assert!(secondary_rc_index < self.query_kmer_count);
-        self.reference_query[primary_index * self.query_kmer_count + secondary_rc_index]
+        self.reference_query
+            .has_match(primary_index, secondary_rc_index)
     }
 
     /// Returns `true` if the query kmer at `primary_index` matches the kmer in the reverse-complemented reference at `secondary_rc_index`.
@@ -215,7 +590,8 @@ impl MatchTable {
     ) -> bool {
         debug_assert!(primary_index < self.query_kmer_count);
         debug_assert!(secondary_rc_index < self.reference_kmer_count);
-        self.query_reference[primary_index * self.reference_kmer_count + secondary_rc_index]
+        self.query_reference
+            .has_match(primary_index, secondary_rc_index)
     }
 
     /// Returns `true` if the query kmer at `primary_index` matches the kmer in the reverse-complemented query at `secondary_rc_index`.
@@ -242,6 +618,723 @@ impl MatchTable {
     pub fn has_query_query_match(&self, primary_index: usize, secondary_rc_index: usize) -> bool {
         debug_assert!(primary_index < self.query_kmer_count);
         debug_assert!(secondary_rc_index < self.query_kmer_count);
-        self.query_query[primary_index * self.query_kmer_count + secondary_rc_index]
+        self.query_query
+            .has_match(primary_index, secondary_rc_index)
+    }
+
+    /// Returns an iterator over all matches in the given quadrant as `(primary_index, secondary_rc_index)` pairs.
+    ///
+    /// The matches are ordered by primary index first and secondary rc index second.
+    /// The iteration takes time linear in the size of the underlying storage, not in the number of index pairs.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTable, Quadrant};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+    /// let matches = MatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    ///
+    /// assert_eq!(
+    ///     matches.matches(Quadrant::ReferenceReference).collect::<Vec<_>>(),
+    ///     vec![(1, 2), (7, 8)],
+    /// );
+    /// assert_eq!(matches.matches(Quadrant::QueryQuery).count(), 0);
+    /// ```
+    pub fn matches(&self, quadrant: Quadrant) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.quadrant(quadrant).iter()
+    }
+
+    /// Returns an iterator over the matches in the given quadrant whose primary index lies in the given range,
+    /// as `(primary_index, secondary_rc_index)` pairs.
+    ///
+    /// The matches are ordered like those of [`matches`](Self::matches).
+    /// Only the storage of the rows in the range is visited,
+    /// so the iteration takes time linear in the size of the storage of these rows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start of the range is greater than its end, or its end is greater than the number of primary kmers of the quadrant.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTable, Quadrant};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+    /// let matches = MatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    ///
+    /// assert_eq!(
+    ///     matches
+    ///         .matches_in_primary_range(Quadrant::ReferenceReference, 5..10)
+    ///         .collect::<Vec<_>>(),
+    ///     vec![(7, 8)],
+    /// );
+    /// ```
+    pub fn matches_in_primary_range(
+        &self,
+        quadrant: Quadrant,
+        primary_indices: Range<usize>,
+    ) -> impl Iterator<Item = (usize, usize)> + '_ {
+        assert!(
+            primary_indices.start <= primary_indices.end
+                && primary_indices.end <= self.primary_kmer_count(quadrant),
+            "The primary range {}..{} does not lie within the {} primary kmers of quadrant {quadrant}",
+            primary_indices.start,
+            primary_indices.end,
+            self.primary_kmer_count(quadrant),
+        );
+        self.quadrant(quadrant).rows_iter(primary_indices)
+    }
+
+    /// Returns an iterator over all matches in the given quadrant as pairs of contig positions.
+    ///
+    /// The offset of the primary position is relative to the forward primary contig,
+    /// and the offset of the secondary position is relative to the reverse complement of the secondary contig.
+    /// See [`matches`](Self::matches) for details and [`MatchTableBuilder::build_contigs`] for an example.
+    pub fn contig_matches(
+        &self,
+        quadrant: Quadrant,
+    ) -> impl Iterator<Item = (ContigPosition, ContigPosition)> + '_ {
+        let contigs = |is_reference| {
+            if is_reference {
+                &self.reference_contigs
+            } else {
+                &self.query_contigs
+            }
+        };
+        let primary_contigs = contigs(quadrant.primary_is_reference());
+        let secondary_contigs = contigs(quadrant.secondary_is_reference());
+
+        self.matches(quadrant)
+            .map(move |(primary_index, secondary_rc_index)| {
+                (
+                    primary_contigs.position(primary_index),
+                    secondary_contigs.rc_position(secondary_rc_index, self.minimum_length),
+                )
+            })
+    }
+
+    /// Returns an iterator over all matches between reference kmers and kmers of the reverse-complemented reference.
+    ///
+    /// See [`matches`](Self::matches) for details.
+    pub fn reference_reference_matches(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.matches(Quadrant::ReferenceReference)
+    }
+
+    /// Returns an iterator over all matches between reference kmers and kmers of the reverse-complemented query.
+    ///
+    /// See [`matches`](Self::matches) for details.
+    pub fn reference_query_matches(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.matches(Quadrant::ReferenceQuery)
+    }
+
+    /// Returns an iterator over all matches between query kmers and kmers of the reverse-complemented reference.
+    ///
+    /// See [`matches`](Self::matches) for details.
+    pub fn query_reference_matches(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.matches(Quadrant::QueryReference)
+    }
+
+    /// Returns an iterator over all matches between query kmers and kmers of the reverse-complemented query.
+    ///
+    /// See [`matches`](Self::matches) for details.
+    pub fn query_query_matches(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.matches(Quadrant::QueryQuery)
+    }
+
+    /// Returns `true` if the primary kmer at `primary_index` matches the reverse-complemented secondary kmer at `secondary_rc_index` in the given quadrant.
+    pub fn has_match(
+        &self,
+        quadrant: Quadrant,
+        primary_index: usize,
+        secondary_rc_index: usize,
+    ) -> bool {
+        debug_assert!(primary_index < self.primary_kmer_count(quadrant));
+        debug_assert!(secondary_rc_index < self.secondary_kmer_count(quadrant));
+        self.quadrant(quadrant)
+            .has_match(primary_index, secondary_rc_index)
+    }
+
+    /// Returns an iterator over the secondary rc indices that match the primary kmer at `primary_index` in the given quadrant.
+    ///
+    /// The indices are returned in increasing order.
+    /// The iteration takes time linear in the size of the row in the underlying storage,
+    /// so it is much faster than calling [`has_match`](Self::has_match) for each secondary rc index.
+    pub fn row_matches(
+        &self,
+        quadrant: Quadrant,
+        primary_index: usize,
+    ) -> impl Iterator<Item = usize> + '_ {
+        debug_assert!(primary_index < self.primary_kmer_count(quadrant));
+        self.quadrant(quadrant).row_iter(primary_index)
+    }
+
+    /// Returns an iterator over the secondary rc indices that match the primary kmer at `primary_index` in the given quadrant
+    /// and lie on a diagonal near the expected one.
+    ///
+    /// These are the secondary rc indices `j` with `min_offset <= j - primary_index <= max_offset`,
+    /// with offsets as in [`MatchTableBuilder::band`].
+    /// The indices are returned in increasing order, and the iterator is empty if `min_offset > max_offset`.
+    /// Only the storage of the band of the row is visited, so this is much faster than filtering the [`row_matches`](Self::row_matches) of long rows.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTable, Quadrant};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AAAAAAAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"TTTTTTTT").unwrap();
+    /// let matches = MatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    ///
+    /// assert_eq!(matches.row_matches(Quadrant::ReferenceQuery, 2).count(), 5);
+    /// assert_eq!(
+    ///     matches
+    ///         .matches_near_diagonal(Quadrant::ReferenceQuery, 2, -1, 1)
+    ///         .collect::<Vec<_>>(),
+    ///     vec![1, 2, 3],
+    /// );
+    /// ```
+    pub fn matches_near_diagonal(
+        &self,
+        quadrant: Quadrant,
+        primary_index: usize,
+        min_offset: isize,
+        max_offset: isize,
+    ) -> impl DoubleEndedIterator<Item = usize> + '_ {
+        debug_assert!(primary_index < self.primary_kmer_count(quadrant));
+        let secondary_kmer_count = self.secondary_kmer_count(quadrant) as isize;
+        let clamp = |offset: isize| {
+            (primary_index as isize)
+                .saturating_add(offset)
+                .clamp(0, secondary_kmer_count) as usize
+        };
+        let start = clamp(min_offset);
+        let end = clamp(max_offset.saturating_add(1)).max(start);
+        self.quadrant(quadrant)
+            .row_range_iter(primary_index, start..end)
+    }
+
+    /// Returns an iterator over all matches of the reference kmer at `primary_index`
+    /// as `(quadrant, secondary_rc_index)` pairs.
+    ///
+    /// The matches of [`Quadrant::ReferenceReference`] are returned before those of [`Quadrant::ReferenceQuery`],
+    /// each in increasing order of the secondary rc index.
+    /// See [`row_matches`](Self::row_matches) for details.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTable, Quadrant};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"TTGGGGTT").unwrap();
+    /// let matches = MatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    ///
+    /// assert_eq!(
+    ///     matches.matches_for_reference_kmer(7).collect::<Vec<_>>(),
+    ///     vec![(Quadrant::ReferenceReference, 8), (Quadrant::ReferenceQuery, 2)],
+    /// );
+    /// ```
+    pub fn matches_for_reference_kmer(
+        &self,
+        primary_index: usize,
+    ) -> impl Iterator<Item = (Quadrant, usize)> + '_ {
+        self.matches_for_primary_kmer(
+            [Quadrant::ReferenceReference, Quadrant::ReferenceQuery],
+            primary_index,
+        )
+    }
+
+    /// Returns an iterator over all matches of the query kmer at `primary_index`
+    /// as `(quadrant, secondary_rc_index)` pairs.
+    ///
+    /// The matches of [`Quadrant::QueryReference`] are returned before those of [`Quadrant::QueryQuery`].
+    /// See [`matches_for_reference_kmer`](Self::matches_for_reference_kmer) for details.
+    pub fn matches_for_query_kmer(
+        &self,
+        primary_index: usize,
+    ) -> impl Iterator<Item = (Quadrant, usize)> + '_ {
+        self.matches_for_primary_kmer(
+            [Quadrant::QueryReference, Quadrant::QueryQuery],
+            primary_index,
+        )
+    }
+
+    fn matches_for_primary_kmer(
+        &self,
+        quadrants: [Quadrant; 2],
+        primary_index: usize,
+    ) -> impl Iterator<Item = (Quadrant, usize)> + '_ {
+        quadrants.into_iter().flat_map(move |quadrant| {
+            self.row_matches(quadrant, primary_index)
+                .map(move |secondary_rc_index| (quadrant, secondary_rc_index))
+        })
+    }
+
+    /// Returns an iterator over the primary indices that match the reverse-complemented secondary kmer at `secondary_rc_index` in the given quadrant.
+    ///
+    /// The indices are returned in increasing order.
+    /// Without a band, a column of a quadrant is answered by the mirrored row of the [transposed](Quadrant::transposed) quadrant,
+    /// so the iteration is as fast as [`row_matches`](Self::row_matches).
+    /// With a band, only the primary indices within the band are checked.
+    /// If the transposed quadrant was not [computed](Self::quadrants), then all primary indices within the band are checked.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTable, Quadrant};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"TTGGGGTT").unwrap();
+    /// let matches = MatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    ///
+    /// assert_eq!(
+    ///     matches.column_matches(Quadrant::ReferenceQuery, 2).collect::<Vec<_>>(),
+    ///     vec![7],
+    /// );
+    /// ```
+    pub fn column_matches(
+        &self,
+        quadrant: Quadrant,
+        secondary_rc_index: usize,
+    ) -> impl Iterator<Item = usize> + '_ {
+        let primary_kmer_count = self.primary_kmer_count(quadrant);
+        let secondary_kmer_count = self.secondary_kmer_count(quadrant);
+        debug_assert!(secondary_rc_index < secondary_kmer_count);
+
+        // The band is not symmetric under transposition if the genomes differ in length,
+        // so the column is scanned within the band instead.
+        // A quadrant that was not computed is empty, even if its transposed quadrant was computed.
+        let is_computed = self.quadrants.contains(quadrant);
+        let is_transposable =
+            is_computed && self.band.is_none() && self.quadrants.contains(quadrant.transposed());
+        let band_scan = (!is_transposable).then(|| {
+            let primary_range = match self.band {
+                _ if !is_computed => 0..0,
+                Some(band) => band.primary_range(secondary_rc_index, primary_kmer_count),
+                None => 0..primary_kmer_count,
+            };
+            primary_range.filter(move |&primary_index| {
+                self.has_match(quadrant, primary_index, secondary_rc_index)
+            })
+        });
+        let transposed_row = is_transposable.then(|| {
+            self.quadrant(quadrant.transposed())
+                .row_iter(secondary_kmer_count - 1 - secondary_rc_index)
+                .rev()
+                .map(move |transposed_index| primary_kmer_count - 1 - transposed_index)
+        });
+        band_scan
+            .into_iter()
+            .flatten()
+            .chain(transposed_row.into_iter().flatten())
+    }
+
+    /// Returns an iterator over all matches of the reverse-complemented reference kmer at `secondary_rc_index`
+    /// as `(quadrant, primary_index)` pairs.
+    ///
+    /// The matches of [`Quadrant::ReferenceReference`] are returned before those of [`Quadrant::QueryReference`],
+    /// each in increasing order of the primary index.
+    /// See [`column_matches`](Self::column_matches) for details.
+    pub fn matches_for_reference_rc_kmer(
+        &self,
+        secondary_rc_index: usize,
+    ) -> impl Iterator<Item = (Quadrant, usize)> + '_ {
+        self.matches_for_secondary_kmer(
+            [Quadrant::ReferenceReference, Quadrant::QueryReference],
+            secondary_rc_index,
+        )
+    }
+
+    /// Returns an iterator over all matches of the reverse-complemented query kmer at `secondary_rc_index`
+    /// as `(quadrant, primary_index)` pairs.
+    ///
+    /// The matches of [`Quadrant::ReferenceQuery`] are returned before those of [`Quadrant::QueryQuery`],
+    /// each in increasing order of the primary index.
+    /// See [`column_matches`](Self::column_matches) for details.
+    pub fn matches_for_query_rc_kmer(
+        &self,
+        secondary_rc_index: usize,
+    ) -> impl Iterator<Item = (Quadrant, usize)> + '_ {
+        self.matches_for_secondary_kmer(
+            [Quadrant::ReferenceQuery, Quadrant::QueryQuery],
+            secondary_rc_index,
+        )
+    }
+
+    fn matches_for_secondary_kmer(
+        &self,
+        quadrants: [Quadrant; 2],
+        secondary_rc_index: usize,
+    ) -> impl Iterator<Item = (Quadrant, usize)> + '_ {
+        quadrants.into_iter().flat_map(move |quadrant| {
+            self.column_matches(quadrant, secondary_rc_index)
+                .map(move |primary_index| (quadrant, primary_index))
+        })
+    }
+
+    /// Returns the number of secondary rc indices that match the primary kmer at `primary_index` in the given quadrant.
+    ///
+    /// The count takes constant time, using a rank index over the bits of dense storage,
+    /// or the row offsets of sparse storage.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTable, Quadrant};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+    /// let matches = MatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    ///
+    /// assert_eq!(matches.match_count(Quadrant::ReferenceReference, 1), 1);
+    /// assert_eq!(matches.reference_reference_match_count(2), 0);
+    /// ```
+    pub fn match_count(&self, quadrant: Quadrant, primary_index: usize) -> usize {
+        debug_assert!(primary_index < self.primary_kmer_count(quadrant));
+        self.quadrant(quadrant).row_match_count(primary_index)
+    }
+
+    /// Returns the number of kmers of the reverse-complemented reference that match the reference kmer at `primary_index`.
+    ///
+    /// See [`match_count`](Self::match_count) for details.
+    pub fn reference_reference_match_count(&self, primary_index: usize) -> usize {
+        self.match_count(Quadrant::ReferenceReference, primary_index)
+    }
+
+    /// Returns the number of kmers of the reverse-complemented query that match the reference kmer at `primary_index`.
+    ///
+    /// See [`match_count`](Self::match_count) for details.
+    pub fn reference_query_match_count(&self, primary_index: usize) -> usize {
+        self.match_count(Quadrant::ReferenceQuery, primary_index)
+    }
+
+    /// Returns the number of kmers of the reverse-complemented reference that match the query kmer at `primary_index`.
+    ///
+    /// See [`match_count`](Self::match_count) for details.
+    pub fn query_reference_match_count(&self, primary_index: usize) -> usize {
+        self.match_count(Quadrant::QueryReference, primary_index)
     }
+
+    /// Returns the number of kmers of the reverse-complemented query that match the query kmer at `primary_index`.
+    ///
+    /// See [`match_count`](Self::match_count) for details.
+    pub fn query_query_match_count(&self, primary_index: usize) -> usize {
+        self.match_count(Quadrant::QueryQuery, primary_index)
+    }
+
+    /// Returns an iterator over all maximal error-free inners in the given quadrant as `(primary_start, secondary_rc_start, length)` triples.
+    ///
+    /// Each match is extended maximally in both directions, and the full length of the resulting error-free inner is reported.
+    /// Since a match extends by one character exactly if the next kmer pair on the same diagonal matches as well,
+    /// the maximal inners are the maximal runs of matches along the diagonals of the quadrant.
+    /// If the table was constructed with mismatches, then the reported inners are maximal runs of matching kmers,
+    /// which may contain more than [`max_mismatches`](Self::max_mismatches) mismatches in total.
+    /// The inners are ordered by their start like in [`matches`](Self::matches).
+    /// If the table has a [maximum length](Self::maximum_length), then a longer run is reported only with its first `maximum_length` characters,
+    /// and its remaining matches are not reported as the start of another inner.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTable, Quadrant};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"TTACGGATTT").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"GGTCCGTGG").unwrap();
+    /// let matches = MatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    ///
+    /// assert_eq!(
+    ///     matches.maximal_matches(Quadrant::ReferenceQuery).collect::<Vec<_>>(),
+    ///     vec![(2, 2, 5)],
+    /// );
+    /// ```
+    pub fn maximal_matches(
+        &self,
+        quadrant: Quadrant,
+    ) -> impl Iterator<Item = (usize, usize, usize)> + '_ {
+        self.diagonal_runs(quadrant, self.max_run_length())
+    }
+
+    /// Returns an iterator over the maximal runs of matches along the diagonals of the given quadrant
+    /// as `(primary_start, secondary_rc_start, run_length)` triples, where `run_length` is the number of matches of the run.
+    ///
+    /// A diagonal in primary and secondary rc indices is an anti-diagonal in forward-strand coordinates of the secondary,
+    /// and the run starting at `(primary_start, secondary_rc_start)` consists of the matches `(primary_start + i, secondary_rc_start + i)` for `i < run_length`.
+    /// So each match belongs to exactly one run, and each run is the inner of length `minimum_length + run_length - 1` reported by [`maximal_matches`](Self::maximal_matches).
+    /// Unlike those inners, the runs are not cut at the [maximum length](Self::maximum_length).
+    /// The runs are ordered by their start like in [`matches`](Self::matches).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTable, Quadrant};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"TTACGGATTT").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"GGTCCGTGG").unwrap();
+    /// let matches = MatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    ///
+    /// // The inner `ACGGA` consists of the matches `(2, 2)` and `(3, 3)`.
+    /// assert_eq!(matches.runs(Quadrant::ReferenceQuery).collect::<Vec<_>>(), vec![(2, 2, 2)]);
+    /// ```
+    pub fn runs(&self, quadrant: Quadrant) -> impl Iterator<Item = (usize, usize, usize)> + '_ {
+        self.diagonal_runs(quadrant, usize::MAX).map(
+            |(primary_start, secondary_rc_start, length)| {
+                (
+                    primary_start,
+                    secondary_rc_start,
+                    length - self.minimum_length + 1,
+                )
+            },
+        )
+    }
+
+    /// Returns an iterator over the runs of matches along the diagonals of the given quadrant as `(primary_start, secondary_rc_start, length)` triples,
+    /// where each run is cut after `max_run_length` matches.
+    pub(crate) fn diagonal_runs(
+        &self,
+        quadrant: Quadrant,
+        max_run_length: usize,
+    ) -> impl Iterator<Item = (usize, usize, usize)> + '_ {
+        let storage = self.quadrant(quadrant);
+        let primary_kmer_count = self.primary_kmer_count(quadrant);
+        let secondary_kmer_count = self.secondary_kmer_count(quadrant);
+
+        storage
+            .iter()
+            .filter(|&(primary_index, secondary_rc_index)| {
+                primary_index == 0
+                    || secondary_rc_index == 0
+                    || !storage.has_match(primary_index - 1, secondary_rc_index - 1)
+            })
+            .map(move |(primary_start, secondary_rc_start)| {
+                let run_length = (1..max_run_length)
+                    .take_while(|&offset| {
+                        primary_start + offset < primary_kmer_count
+                            && secondary_rc_start + offset < secondary_kmer_count
+                            && storage
+                                .has_match(primary_start + offset, secondary_rc_start + offset)
+                    })
+                    .count()
+                    + 1;
+                (
+                    primary_start,
+                    secondary_rc_start,
+                    self.minimum_length + run_length - 1,
+                )
+            })
+    }
+
+    /// Returns the minimum length of the inners stored in this table, i.e. the length of the kmers.
+    pub fn minimum_length(&self) -> usize {
+        self.minimum_length
+    }
+
+    /// Returns the maximum number of mismatches between two matching kmers.
+    pub fn max_mismatches(&self) -> usize {
+        self.max_mismatches
+    }
+
+    /// Returns the quadrants that were computed (see [`MatchTableBuilder::quadrants`]).
+    ///
+    /// The other quadrants contain no matches.
+    pub fn quadrants(&self) -> Quadrants {
+        self.quadrants
+    }
+
+    /// Returns the number of kmers in the reference.
+    pub fn reference_kmer_count(&self) -> usize {
+        self.reference_kmer_count
+    }
+
+    /// Returns the number of kmers in the query.
+    pub fn query_kmer_count(&self) -> usize {
+        self.query_kmer_count
+    }
+
+    /// Returns the number of reference kmers that were excluded from matching,
+    /// e.g. because they overlap an `N` (see [`MatchTableBuilder::skip_n`]).
+    pub fn skipped_reference_kmer_count(&self) -> usize {
+        self.skipped_reference_kmer_count
+    }
+
+    /// Returns the number of query kmers that were excluded from matching,
+    /// e.g. because they overlap an `N` (see [`MatchTableBuilder::skip_n`]).
+    pub fn skipped_query_kmer_count(&self) -> usize {
+        self.skipped_query_kmer_count
+    }
+
+    /// Returns the maximum number of occurrences of a kmer that was not excluded from matching,
+    /// see [`MatchTableBuilder::max_kmer_occurrences`].
+    ///
+    /// Returns `None` if kmers were not excluded by their number of occurrences,
+    /// or if the table was [merged](Self::merge).
+    pub fn max_kmer_occurrences(&self) -> Option<usize> {
+        self.max_kmer_occurrences
+    }
+
+    /// Returns `true` if only the matches between kmers that occur once in their sequences were recorded,
+    /// see [`MatchTableBuilder::unique_matches_only`].
+    ///
+    /// Returns `false` if the table was [merged](Self::merge).
+    pub fn unique_matches_only(&self) -> bool {
+        self.unique_matches_only
+    }
+
+    /// Returns the length at which the extension of matches into inners stops,
+    /// see [`MatchTableBuilder::maximum_length`].
+    ///
+    /// Returns `None` if the inners are extended without limit.
+    pub fn maximum_length(&self) -> Option<usize> {
+        self.maximum_length
+    }
+
+    /// Returns the maximum number of consecutive matches along a diagonal that are extended into a single inner.
+    fn max_run_length(&self) -> usize {
+        self.maximum_length.map_or(usize::MAX, |maximum_length| {
+            maximum_length - self.minimum_length + 1
+        })
+    }
+
+    /// Returns the number of reference kmers that were excluded from matching because they occur too often,
+    /// see [`MatchTableBuilder::max_kmer_occurrences`] and [`MatchTableBuilder::unique_matches_only`].
+    ///
+    /// These kmers are included in [`skipped_reference_kmer_count`](Self::skipped_reference_kmer_count),
+    /// and some of them may have been excluded for other reasons as well.
+    pub fn frequent_reference_kmer_count(&self) -> usize {
+        self.frequent_reference_kmer_count
+    }
+
+    /// Returns the number of query kmers that were excluded from matching because they occur too often,
+    /// see [`MatchTableBuilder::max_kmer_occurrences`] and [`MatchTableBuilder::unique_matches_only`].
+    ///
+    /// See [`frequent_reference_kmer_count`](Self::frequent_reference_kmer_count) for details.
+    pub fn frequent_query_kmer_count(&self) -> usize {
+        self.frequent_query_kmer_count
+    }
+
+    /// Returns the intervals of the reference whose kmers were excluded from matching,
+    /// see [`MatchTableBuilder::reference_excluded_intervals`].
+    ///
+    /// The intervals are sorted, and overlapping and adjacent intervals are merged.
+    pub fn reference_excluded_intervals(&self) -> &[Range<usize>] {
+        &self.reference_excluded_intervals
+    }
+
+    /// Returns the intervals of the query whose kmers were excluded from matching,
+    /// see [`MatchTableBuilder::query_excluded_intervals`].
+    ///
+    /// The intervals are sorted, and overlapping and adjacent intervals are merged.
+    pub fn query_excluded_intervals(&self) -> &[Range<usize>] {
+        &self.query_excluded_intervals
+    }
+
+    /// Returns the layout of the contigs of the reference.
+    pub fn reference_contigs(&self) -> &ContigLayout {
+        &self.reference_contigs
+    }
+
+    /// Returns the layout of the contigs of the query.
+    pub fn query_contigs(&self) -> &ContigLayout {
+        &self.query_contigs
+    }
+
+    /// Returns the number of valid primary indices in the given quadrant.
+    pub fn primary_kmer_count(&self, quadrant: Quadrant) -> usize {
+        quadrant
+            .dimensions(self.reference_kmer_count, self.query_kmer_count)
+            .0
+    }
+
+    /// Returns the number of valid secondary rc indices in the given quadrant.
+    pub fn secondary_kmer_count(&self, quadrant: Quadrant) -> usize {
+        quadrant
+            .dimensions(self.reference_kmer_count, self.query_kmer_count)
+            .1
+    }
+
+    pub(crate) fn quadrant(&self, quadrant: Quadrant) -> &QuadrantStorage<Index> {
+        match quadrant {
+            Quadrant::ReferenceReference => &self.reference_reference,
+            Quadrant::ReferenceQuery => &self.reference_query,
+            Quadrant::QueryReference => &self.query_reference,
+            Quadrant::QueryQuery => &self.query_query,
+        }
+    }
+}
+
+/// Encodes the given ASCII sequence into the DNA alphabet, or returns an error with the first invalid character.
+fn dna_from_ascii(
+    sequence: &'static str,
+    ascii: &[u8],
+) -> Result<VectorGenome<DnaAlphabet>, MatchTableError> {
+    VectorGenome::from_slice_u8(ascii).map_err(|_| {
+        let position = ascii
+            .iter()
+            .position(|&character| DnaAlphabet::ascii_to_character(character).is_err())
+            .expect("the sequence was rejected, so it contains an invalid character");
+        MatchTableError::InvalidCharacter {
+            sequence,
+            position,
+            character: ascii[position],
+        }
+    })
 }
@@ -0,0 +1,290 @@
+//! Flags of kmers that are excluded from matching or need special treatment.
+
+use std::{collections::HashMap, ops::Range};
+
+use bitvec::vec::BitVec;
+
+use crate::{ContigLayout, MatchTableBuilder, StorageIndex, minimizer::window_minimizers};
+
+/// How IUPAC ambiguity codes are handled when matching kmers.
+///
+/// A character is ambiguous if it is an IUPAC nucleotide code that represents more than one base,
+/// e.g. `N`, `R` or `Y`.
+/// All other characters, including characters that are not IUPAC nucleotide codes, are unambiguous.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AmbiguityPolicy {
+    /// Treat ambiguity codes like any other character, i.e. they match only themselves.
+    #[default]
+    Literal,
+    /// Kmers containing an ambiguous character never match.
+    NeverMatch,
+    /// Characters match if the sets of bases they represent intersect, e.g. `R` matches `A`, `G`, `N` and `S`.
+    ///
+    /// Kmers containing ambiguous characters are compared against all kmers of the other sequence,
+    /// so this is only efficient if ambiguous characters are rare.
+    Compatible,
+}
+
+/// Returns the set of bases represented by the given IUPAC nucleotide code as a bitmask of `A`, `C`, `G` and `T`,
+/// or zero if the character is not an IUPAC nucleotide code.
+fn iupac_bases(character: u8) -> u8 {
+    const A: u8 = 1;
+    const C: u8 = 2;
+    const G: u8 = 4;
+    const T: u8 = 8;
+
+    match character {
+        b'A' => A,
+        b'C' => C,
+        b'G' => G,
+        b'T' | b'U' => T,
+        b'R' => A | G,
+        b'Y' => C | T,
+        b'S' => C | G,
+        b'W' => A | T,
+        b'K' => G | T,
+        b'M' => A | C,
+        b'B' => C | G | T,
+        b'D' => A | G | T,
+        b'H' => A | C | T,
+        b'V' => A | C | G,
+        b'N' => A | C | G | T,
+        _ => 0,
+    }
+}
+
+/// Returns `true` if the character is an IUPAC nucleotide code that represents more than one base.
+pub(crate) fn is_ambiguous(character: u8) -> bool {
+    iupac_bases(character).count_ones() > 1
+}
+
+/// Returns `true` if the characters match under [`AmbiguityPolicy::Compatible`].
+pub(crate) fn is_compatible(a: u8, b: u8) -> bool {
+    a == b || iupac_bases(a) & iupac_bases(b) != 0
+}
+
+/// Returns a bitvector that marks each soft-masked (lowercase) character of the given ASCII sequence.
+///
+/// The result can be passed to [`MatchTableBuilder::reference_mask`] or [`MatchTableBuilder::query_mask`]
+/// to exclude soft-masked regions from matching.
+/// Since genome sequences do not preserve the case of their characters, the mask needs to be computed from the raw input.
+///
+/// # Example
+///
+/// ```rust
+/// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+/// use compact_genome::implementation::vec_sequence::VectorGenome;
+/// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+/// use template_switch_error_free_inners::{MatchTableBuilder, soft_masked_characters};
+///
+/// let reference_ascii = b"AGGGGAAcccca";
+/// let reference =
+///     VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii.to_ascii_uppercase()).unwrap();
+/// let query = VectorGenome::from_slice_u8(b"AACCCCA").unwrap();
+///
+/// let matches = MatchTableBuilder::new(4)
+///     .reference_mask(soft_masked_characters(reference_ascii))
+///     .build(reference.as_genome_subsequence(), query.as_genome_subsequence());
+/// assert!(matches.has_reference_query_match(1, 1));
+/// assert!(!matches.has_reference_reference_match(1, 1));
+/// assert_eq!(matches.skipped_reference_kmer_count(), 5);
+/// ```
+pub fn soft_masked_characters(ascii: &[u8]) -> BitVec {
+    ascii
+        .iter()
+        .map(|character| character.is_ascii_lowercase())
+        .collect()
+}
+
+/// Returns the given intervals sorted and with overlapping and adjacent intervals merged, omitting empty intervals.
+pub(crate) fn merge_intervals(intervals: &[Range<usize>]) -> Vec<Range<usize>> {
+    let mut intervals: Vec<_> = intervals
+        .iter()
+        .filter(|interval| !interval.is_empty())
+        .cloned()
+        .collect();
+    intervals.sort_unstable_by_key(|interval| interval.start);
+
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(intervals.len());
+    for interval in intervals {
+        match merged.last_mut() {
+            Some(last) if interval.start <= last.end => last.end = last.end.max(interval.end),
+            _ => merged.push(interval),
+        }
+    }
+    merged
+}
+
+/// Returns a bitvector that marks each kmer that overlaps a marked character.
+pub(crate) fn kmers_overlapping(marked_characters: &BitVec, kmer_length: usize) -> BitVec {
+    let kmer_count = (marked_characters.len() + 1).saturating_sub(kmer_length);
+    let mut result = BitVec::repeat(false, kmer_count);
+    for marked_character in marked_characters.iter_ones() {
+        let first_kmer = marked_character.saturating_sub(kmer_length - 1);
+        let last_kmer = marked_character.min(kmer_count - 1);
+        result[first_kmer..=last_kmer].fill(true);
+    }
+    result
+}
+
+/// Returns the kmers of the reference and the query that are excluded for occurring too often,
+/// or `None` if neither [`MatchTableBuilder::max_kmer_occurrences`] nor [`MatchTableBuilder::unique_matches_only`] is set.
+///
+/// A kmer occurs too often if it occurs more than the maximum number of times on both strands of the reference or on both strands of the query,
+/// or, if only unique matches are requested, if it occurs more than once on the forward strand of its own sequence.
+/// The texts are given together with their reverse complements.
+pub(crate) fn frequent_kmers(
+    texts: [&[u8]; 2],
+    rc_texts: [&[u8]; 2],
+    options: &MatchTableBuilder<impl StorageIndex>,
+) -> Option<[BitVec; 2]> {
+    if options.max_kmer_occurrences.is_none() && !options.unique_matches_only {
+        return None;
+    }
+    let kmer_length = options.minimum_length;
+
+    #[derive(Default)]
+    struct Occurrences {
+        forward: [usize; 2],
+        both_strands: [usize; 2],
+    }
+    let mut occurrences = HashMap::<&[u8], Occurrences>::new();
+    for (genome, (text, rc_text)) in texts.into_iter().zip(rc_texts).enumerate() {
+        for kmer in text.windows(kmer_length) {
+            let occurrences = occurrences.entry(kmer).or_default();
+            occurrences.forward[genome] += 1;
+            occurrences.both_strands[genome] += 1;
+        }
+        if options.max_kmer_occurrences.is_some() {
+            for kmer in rc_text.windows(kmer_length) {
+                occurrences.entry(kmer).or_default().both_strands[genome] += 1;
+            }
+        }
+    }
+    let max_occurrences = options.max_kmer_occurrences.unwrap_or(usize::MAX);
+    Some([0, 1].map(|genome| {
+        texts[genome]
+            .windows(kmer_length)
+            .map(|kmer| {
+                let occurrences = &occurrences[kmer];
+                occurrences
+                    .both_strands
+                    .iter()
+                    .any(|&count| count > max_occurrences)
+                    || (options.unique_matches_only && occurrences.forward[genome] > 1)
+            })
+            .collect()
+    }))
+}
+
+/// Per-kmer flags of a sequence, indexed by the forward kmer index.
+pub(crate) struct KmerFlags {
+    /// Kmers that are excluded from matching.
+    pub excluded: BitVec,
+    /// Kmers that contain an ambiguous character under [`AmbiguityPolicy::Compatible`].
+    pub ambiguous: BitVec,
+    /// The indices of the ambiguous kmers in increasing order.
+    pub ambiguous_kmers: Vec<usize>,
+    /// Kmers that are window minimizers, if the table is sparsified by [`MatchTableBuilder::minimizer_window`].
+    pub minimizers: Option<BitVec>,
+}
+
+impl KmerFlags {
+    /// Returns the number of kmers that are excluded from matching.
+    pub fn excluded_kmer_count(&self) -> usize {
+        self.excluded.count_ones()
+    }
+
+    /// Returns the flags of the kmers in the given range, indexed relative to its start.
+    pub fn window(&self, kmers: Range<usize>) -> Self {
+        Self {
+            excluded: self.excluded[kmers.clone()].to_bitvec(),
+            ambiguous: self.ambiguous[kmers.clone()].to_bitvec(),
+            ambiguous_kmers: self
+                .ambiguous_kmers
+                .iter()
+                .filter(|kmer_index| kmers.contains(kmer_index))
+                .map(|kmer_index| kmer_index - kmers.start)
+                .collect(),
+            minimizers: self
+                .minimizers
+                .as_ref()
+                .map(|minimizers| minimizers[kmers.clone()].to_bitvec()),
+        }
+    }
+
+    /// Returns `true` if the kmer is a window minimizer or the table is not sparsified.
+    pub fn is_minimizer(&self, kmer_index: usize) -> bool {
+        self.minimizers
+            .as_ref()
+            .is_none_or(|minimizers| minimizers[kmer_index])
+    }
+}
+
+impl KmerFlags {
+    /// Compute the flags of `text`, additionally excluding the `frequent` kmers, all kmers overlapping a character marked in `mask`
+    /// or one of the `excluded_intervals`, and all kmers spanning the boundary between two contigs.
+    pub fn new(
+        text: &[u8],
+        frequent: Option<&BitVec>,
+        mask: Option<&BitVec>,
+        excluded_intervals: &[Range<usize>],
+        contigs: &ContigLayout,
+        options: &MatchTableBuilder<impl StorageIndex>,
+    ) -> Self {
+        let ambiguous_characters: BitVec = match options.ambiguity_policy {
+            AmbiguityPolicy::Literal => BitVec::repeat(false, text.len()),
+            AmbiguityPolicy::NeverMatch | AmbiguityPolicy::Compatible => text
+                .iter()
+                .map(|&character| is_ambiguous(character))
+                .collect(),
+        };
+        let ambiguous_kmers = kmers_overlapping(&ambiguous_characters, options.minimum_length);
+
+        let none = BitVec::repeat(false, ambiguous_kmers.len());
+        let (mut excluded, mut ambiguous) = match options.ambiguity_policy {
+            AmbiguityPolicy::Literal => (none.clone(), none),
+            AmbiguityPolicy::NeverMatch => (ambiguous_kmers, none),
+            AmbiguityPolicy::Compatible => (none, ambiguous_kmers),
+        };
+
+        excluded |= contigs.spanning_kmers(options.minimum_length);
+        if let Some(mask) = mask {
+            excluded |= kmers_overlapping(mask, options.minimum_length);
+        }
+        for interval in excluded_intervals
+            .iter()
+            .filter(|interval| !interval.is_empty())
+        {
+            let first_kmer = interval.start.saturating_sub(options.minimum_length - 1);
+            let end_kmer = interval.end.min(excluded.len());
+            if first_kmer < end_kmer {
+                excluded[first_kmer..end_kmer].fill(true);
+            }
+        }
+        if options.skip_n {
+            let n_characters: BitVec = text
+                .iter()
+                .map(|&character| character.eq_ignore_ascii_case(&b'N'))
+                .collect();
+            excluded |= kmers_overlapping(&n_characters, options.minimum_length);
+        }
+        if let Some(filter) = options.low_complexity_filter {
+            excluded |= filter.low_complexity_kmers(text, options.minimum_length);
+        }
+        if let Some(frequent) = frequent {
+            excluded |= frequent;
+        }
+        ambiguous &= !excluded.clone();
+        let minimizers = options.minimizer_window.map(|window_size| {
+            window_minimizers(text, &excluded, options.minimum_length, window_size)
+        });
+
+        Self {
+            ambiguous_kmers: ambiguous.iter_ones().collect(),
+            excluded,
+            ambiguous,
+            minimizers,
+        }
+    }
+}
@@ -0,0 +1,166 @@
+//! Estimation of the memory required to construct a match table.
+
+use crate::{
+    MatchTable, MatchTableBuilder, Quadrant, StorageBackend, StorageIndex, bloom::BloomFilter,
+};
+
+/// The number of bits per block of the rank index, see [`RankIndex`](crate::rank::RankIndex).
+const RANK_BLOCK_SIZE: u64 = 1024;
+
+/// An estimate of the memory in bytes required to construct a [`MatchTable`] with given options and sequence lengths.
+///
+/// The sizes of the quadrant storage are given for each storage mode, so a pipeline can choose between them before construction.
+/// Sparse storage depends on the number of matches, so it is given as a fixed part and a part per match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryEstimate {
+    /// The bitvectors and rank indexes of all computed quadrants in [`StorageBackend::Dense`],
+    /// or in [`StorageBackend::Symmetric`] if configured, storing only the band if a band is configured.
+    pub dense_storage_bytes: u64,
+    /// The row offsets of all computed quadrants in [`StorageBackend::Sparse`].
+    pub sparse_storage_bytes: u64,
+    /// The additional bytes per match in [`StorageBackend::Sparse`], i.e. the size of the [index type](StorageIndex).
+    ///
+    /// During construction, each match temporarily takes three times as much.
+    pub sparse_bytes_per_match: u64,
+    /// The size of the file of a memory-mapped table as created by `MatchTableBuilder::build_mmap`.
+    pub mapped_file_bytes: u64,
+    /// The rank indexes of a memory-mapped table, which are kept in memory.
+    pub mapped_rank_bytes: u64,
+    /// The indexes of the primary sequences with the configured [`IndexBackend`](crate::IndexBackend),
+    /// and their Bloom filters if configured.
+    ///
+    /// They exist only during construction.
+    /// If the kmers are joined by hashing or scanned within a band, then the actual size differs.
+    pub index_bytes: u64,
+    /// The sequences and the flags of their kmers, which exist only during construction.
+    pub text_bytes: u64,
+}
+
+impl MemoryEstimate {
+    /// Returns the peak memory of constructing a table with dense storage.
+    pub fn total_dense_bytes(&self) -> u64 {
+        self.dense_storage_bytes + self.index_bytes + self.text_bytes
+    }
+
+    /// Returns the peak memory of constructing a table with sparse storage containing the given number of matches.
+    pub fn total_sparse_bytes(&self, match_count: u64) -> u64 {
+        self.sparse_storage_bytes
+            + 3 * self.sparse_bytes_per_match * match_count
+            + self.index_bytes
+            + self.text_bytes
+    }
+
+    /// Returns the peak memory of constructing a memory-mapped table, not counting the file.
+    pub fn total_mapped_bytes(&self) -> u64 {
+        self.mapped_rank_bytes + self.index_bytes + self.text_bytes
+    }
+}
+
+impl MatchTable {
+    /// Estimate the memory required to construct a table with the default options for sequences of the given lengths.
+    ///
+    /// See [`MatchTableBuilder::estimate_memory`] for details.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use template_switch_error_free_inners::MatchTable;
+    ///
+    /// let estimate = MatchTable::estimate_memory(1_000_000, 1_000, 10);
+    ///
+    /// // Four quadrants of roughly 10^12, 10^9, 10^9 and 10^6 bits.
+    /// assert!(estimate.dense_storage_bytes > 125_000_000_000);
+    /// assert!(estimate.total_sparse_bytes(0) < 100_000_000);
+    /// ```
+    pub fn estimate_memory(
+        reference_length: usize,
+        query_length: usize,
+        minimum_length: usize,
+    ) -> MemoryEstimate {
+        MatchTableBuilder::new(minimum_length).estimate_memory(reference_length, query_length)
+    }
+}
+
+impl<Index: StorageIndex> MatchTableBuilder<Index> {
+    /// Estimate the memory required to construct a table with these options for sequences of the given lengths.
+    ///
+    /// Takes into account the computed quadrants, the band, [`StorageBackend::Symmetric`] and the index backend.
+    /// The configured storage backend only matters for choosing between dense and symmetric storage,
+    /// since the estimate contains the sizes of all storage modes.
+    /// The estimate assumes DNA sequences and neglects constant overheads.
+    pub fn estimate_memory(&self, reference_length: usize, query_length: usize) -> MemoryEstimate {
+        let kmer_count = |length: usize| (length + 1).saturating_sub(self.minimum_length.max(1));
+        let (reference_kmer_count, query_kmer_count) =
+            (kmer_count(reference_length), kmer_count(query_length));
+        let bit_bytes = |bit_count: u64| bit_count.div_ceil(u64::from(usize::BITS)) * 8;
+        let rank_bytes = |bit_count: u64| (bit_count.div_ceil(RANK_BLOCK_SIZE) + 1) * 8;
+
+        let mut estimate = MemoryEstimate {
+            dense_storage_bytes: 0,
+            sparse_storage_bytes: 0,
+            sparse_bytes_per_match: u64::from(Index::BITS / 8),
+            mapped_file_bytes: 0,
+            mapped_rank_bytes: 0,
+            index_bytes: 0,
+            text_bytes: 0,
+        };
+        for quadrant in Quadrant::ALL {
+            let (primary_kmer_count, secondary_kmer_count) =
+                quadrant.dimensions(reference_kmer_count, query_kmer_count);
+            if !self.quadrants.contains(quadrant) {
+                continue;
+            }
+
+            let (primary_kmer_count, secondary_kmer_count) =
+                (primary_kmer_count as u64, secondary_kmer_count as u64);
+            let full_bits = primary_kmer_count.saturating_mul(secondary_kmer_count);
+            let dense_bits = match self.band {
+                Some(band) if (band.width() as u64) < secondary_kmer_count => {
+                    primary_kmer_count.saturating_mul(band.width() as u64)
+                }
+                _ if self.storage == StorageBackend::Symmetric && quadrant.is_self_comparison() => {
+                    primary_kmer_count.saturating_mul(primary_kmer_count + 1) / 2
+                }
+                _ => full_bits,
+            };
+            estimate.dense_storage_bytes = estimate
+                .dense_storage_bytes
+                .saturating_add(bit_bytes(dense_bits) + rank_bytes(dense_bits));
+            estimate.sparse_storage_bytes += (primary_kmer_count + 1) * 8;
+            estimate.mapped_file_bytes = estimate
+                .mapped_file_bytes
+                .saturating_add(full_bits.div_ceil(8));
+            estimate.mapped_rank_bytes += rank_bytes(full_bits);
+        }
+
+        // Only the primary sequences of computed quadrants are indexed.
+        for (length, quadrants) in [
+            (
+                reference_length,
+                [Quadrant::ReferenceReference, Quadrant::ReferenceQuery],
+            ),
+            (
+                query_length,
+                [Quadrant::QueryReference, Quadrant::QueryQuery],
+            ),
+        ] {
+            if quadrants
+                .into_iter()
+                .any(|quadrant| self.quadrants.contains(quadrant))
+            {
+                estimate.index_bytes += self.index_backend.estimated_bytes(length);
+                if let Some(false_positive_rate) = self.bloom_false_positive_rate {
+                    if self.max_mismatches == 0 {
+                        estimate.index_bytes +=
+                            BloomFilter::estimated_bytes(kmer_count(length), false_positive_rate);
+                    }
+                }
+            }
+        }
+
+        let total_length = reference_length as u64 + query_length as u64;
+        // The texts and two flags per kmer.
+        estimate.text_bytes = total_length + total_length.div_ceil(4);
+        estimate
+    }
+}
@@ -0,0 +1,257 @@
+//! Merging of tables computed on adjacent ranges of the same sequences.
+
+use std::ops::Range;
+
+use crate::{
+    ContigLayout, MatchTable, MatchTableBuilder, MatchTableError, Quadrant, Quadrants,
+    StorageBackend, StorageIndex, mask::merge_intervals,
+};
+
+impl<Index: StorageIndex> MatchTable<Index> {
+    /// Merge tables computed on ranges of the same reference and query into a table of the whole sequences.
+    ///
+    /// Each shard is given as `(reference_start, query_start, table)`,
+    /// where the table was computed on the ranges of the reference and the query starting at the given positions.
+    /// The ranges of each sequence must tile it, i.e. the first range must start at zero,
+    /// and each further range must start `minimum_length - 1` characters before the end of the previous range,
+    /// such that each kmer lies in exactly one range.
+    /// Several shards may share the same range, e.g. if only the reference is split and each shard contains the whole query.
+    ///
+    /// The matches of each shard are translated into the coordinates of the whole sequences, and the matches of all shards are combined.
+    /// A pair of kmers is only compared if some shard contains both of them,
+    /// so if the reference is split, the reference–reference quadrant contains only the matches within each range of the reference.
+    /// Only the quadrants computed in all shards are computed in the merged table,
+    /// and the merged table has no band, since bands are relative to the ranges of the shards.
+    /// For the same reason, the merged table records no [maximum number of kmer occurrences](Self::max_kmer_occurrences)
+    /// and does not record [unique matches only](Self::unique_matches_only).
+    ///
+    /// Returns an error if no tables are given, if they differ in their minimum length, maximum number of mismatches or maximum length,
+    /// if a table has more than one contig, or if the ranges do not tile the sequences.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTable, Quadrant, StorageBackend};
+    ///
+    /// let reference = b"TTACGGATTTGGATCCGTAA";
+    /// let query = VectorGenome::<DnaAlphabet>::from_slice_u8(b"GGTCCGTGG").unwrap();
+    /// let k = 4;
+    ///
+    /// // Split the reference into two ranges that overlap by k - 1 characters.
+    /// let shards: Vec<_> = [0..12, 9..20]
+    ///     .into_iter()
+    ///     .map(|range| {
+    ///         let start = range.start;
+    ///         let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference[range]).unwrap();
+    ///         let table = MatchTable::new(
+    ///             reference.as_genome_subsequence(),
+    ///             query.as_genome_subsequence(),
+    ///             k,
+    ///         );
+    ///         (start, 0, table)
+    ///     })
+    ///     .collect();
+    /// let merged = MatchTable::merge(
+    ///     shards.iter().map(|(start, query_start, table)| (*start, *query_start, table)),
+    ///     StorageBackend::Sparse,
+    /// )
+    /// .unwrap();
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(reference).unwrap();
+    /// let full = MatchTable::new(reference.as_genome_subsequence(), query.as_genome_subsequence(), k);
+    /// assert_eq!(merged.reference_kmer_count(), full.reference_kmer_count());
+    /// for quadrant in [Quadrant::ReferenceQuery, Quadrant::QueryReference, Quadrant::QueryQuery] {
+    ///     assert!(merged.matches(quadrant).eq(full.matches(quadrant)));
+    /// }
+    /// ```
+    pub fn merge<'table>(
+        shards: impl IntoIterator<Item = (usize, usize, &'table MatchTable<Index>)>,
+        storage: StorageBackend,
+    ) -> Result<Self, MatchTableError> {
+        let shards: Vec<_> = shards.into_iter().collect();
+        let Some(&(_, _, first)) = shards.first() else {
+            return Err(MatchTableError::InvalidMerge("no tables given"));
+        };
+        let minimum_length = first.minimum_length;
+        let max_mismatches = first.max_mismatches;
+        let maximum_length = first.maximum_length;
+        if shards
+            .iter()
+            .any(|(_, _, table)| table.minimum_length != minimum_length)
+        {
+            return Err(MatchTableError::InvalidMerge(
+                "the tables have different minimum lengths",
+            ));
+        }
+        if shards
+            .iter()
+            .any(|(_, _, table)| table.max_mismatches != max_mismatches)
+        {
+            return Err(MatchTableError::InvalidMerge(
+                "the tables have different maximum numbers of mismatches",
+            ));
+        }
+        if shards
+            .iter()
+            .any(|(_, _, table)| table.maximum_length != maximum_length)
+        {
+            return Err(MatchTableError::InvalidMerge(
+                "the tables have different maximum lengths",
+            ));
+        }
+        if shards.iter().any(|(_, _, table)| {
+            table.reference_contigs.contig_count() > 1 || table.query_contigs.contig_count() > 1
+        }) {
+            return Err(MatchTableError::InvalidMerge(
+                "a table has multiple contigs",
+            ));
+        }
+
+        let reference = TiledRanges::new(shards.iter().map(|&(reference_start, _, table)| {
+            (
+                reference_start,
+                table.reference_kmer_count,
+                table.skipped_reference_kmer_count,
+            )
+        }))
+        .ok_or(MatchTableError::InvalidMerge(
+            "the reference ranges do not tile the reference",
+        ))?;
+        let query = TiledRanges::new(shards.iter().map(|&(_, query_start, table)| {
+            (
+                query_start,
+                table.query_kmer_count,
+                table.skipped_query_kmer_count,
+            )
+        }))
+        .ok_or(MatchTableError::InvalidMerge(
+            "the query ranges do not tile the query",
+        ))?;
+        let quadrants = shards
+            .iter()
+            .fold(Quadrants::ALL, |quadrants, (_, _, table)| {
+                quadrants & table.quadrants
+            });
+
+        let mut builders = MatchTableBuilder::new(minimum_length)
+            .index_type::<Index>()
+            .storage(storage)
+            .quadrants(quadrants)
+            .quadrant_storage_builders((reference.kmer_count, query.kmer_count))?;
+        for &(reference_start, query_start, table) in &shards {
+            // The primary index offset and the secondary rc index offset of each sequence.
+            let offsets = |is_reference: bool| {
+                if is_reference {
+                    let end = reference_start + table.reference_kmer_count;
+                    (reference_start, reference.kmer_count - end)
+                } else {
+                    let end = query_start + table.query_kmer_count;
+                    (query_start, query.kmer_count - end)
+                }
+            };
+            for (quadrant, builder) in Quadrant::ALL.into_iter().zip(&mut builders) {
+                if !quadrants.contains(quadrant) {
+                    continue;
+                }
+                let (primary_offset, _) = offsets(quadrant.primary_is_reference());
+                let (_, secondary_rc_offset) = offsets(quadrant.secondary_is_reference());
+                for (primary_index, secondary_rc_index) in table.matches(quadrant) {
+                    builder.insert(
+                        primary_index + primary_offset,
+                        secondary_rc_index + secondary_rc_offset,
+                    );
+                }
+            }
+        }
+
+        let shifted_intervals = |intervals: fn(&MatchTable<Index>) -> &[Range<usize>],
+                                 is_reference| {
+            let intervals: Vec<_> = shards
+                .iter()
+                .flat_map(|&(reference_start, query_start, table)| {
+                    let start = if is_reference {
+                        reference_start
+                    } else {
+                        query_start
+                    };
+                    intervals(table)
+                        .iter()
+                        .map(move |interval| interval.start + start..interval.end + start)
+                })
+                .collect();
+            merge_intervals(&intervals)
+        };
+
+        let [
+            reference_reference,
+            reference_query,
+            query_reference,
+            query_query,
+        ] = builders;
+        Ok(MatchTable {
+            reference_reference: reference_reference.build(),
+            reference_query: reference_query.build(),
+            query_reference: query_reference.build(),
+            query_query: query_query.build(),
+            reference_kmer_count: reference.kmer_count,
+            query_kmer_count: query.kmer_count,
+            minimum_length,
+            max_mismatches,
+            skipped_reference_kmer_count: reference.skipped_kmer_count,
+            skipped_query_kmer_count: query.skipped_kmer_count,
+            // The shards counted the occurrences of kmers only within their ranges,
+            // so the frequent kmers of the shards are not the frequent kmers of the whole sequences.
+            max_kmer_occurrences: None,
+            unique_matches_only: false,
+            maximum_length,
+            frequent_reference_kmer_count: 0,
+            frequent_query_kmer_count: 0,
+            reference_contigs: ContigLayout::new([reference.kmer_count + minimum_length - 1]),
+            query_contigs: ContigLayout::new([query.kmer_count + minimum_length - 1]),
+            band: None,
+            quadrants,
+            reference_excluded_intervals: shifted_intervals(
+                MatchTable::reference_excluded_intervals,
+                true,
+            ),
+            query_excluded_intervals: shifted_intervals(
+                MatchTable::query_excluded_intervals,
+                false,
+            ),
+            // The shards may have been computed from different options, so the merged table has no fingerprint.
+            fingerprint: None,
+        })
+    }
+}
+
+/// The kmers of a sequence covered by the ranges of the shards.
+struct TiledRanges {
+    kmer_count: usize,
+    skipped_kmer_count: usize,
+}
+
+impl TiledRanges {
+    /// Combine ranges given as `(start, kmer_count, skipped_kmer_count)`,
+    /// or return `None` if the distinct ranges do not tile the kmers of the sequence.
+    fn new(ranges: impl IntoIterator<Item = (usize, usize, usize)>) -> Option<Self> {
+        let mut ranges: Vec<_> = ranges.into_iter().collect();
+        ranges.sort_unstable();
+        ranges.dedup();
+
+        let mut tiled = Self {
+            kmer_count: 0,
+            skipped_kmer_count: 0,
+        };
+        for (start, kmer_count, skipped_kmer_count) in ranges {
+            if start != tiled.kmer_count {
+                return None;
+            }
+            tiled.kmer_count += kmer_count;
+            tiled.skipped_kmer_count += skipped_kmer_count;
+        }
+        Some(tiled)
+    }
+}
@@ -0,0 +1,70 @@
+//! Selection of window minimizers to sparsify the match table.
+
+use std::collections::VecDeque;
+
+use bitvec::vec::BitVec;
+
+/// Returns a bitvector that marks each kmer of `text` that is the minimizer of a window of `window_size` consecutive kmers.
+///
+/// Kmers are ordered by a hash of their characters, and ties are broken by taking the leftmost kmer.
+/// Excluded kmers are ordered after all other kmers, so they are only selected if their whole window is excluded.
+/// If there are fewer than `window_size` kmers, then the minimizer of all kmers is selected.
+pub(crate) fn window_minimizers(
+    text: &[u8],
+    excluded: &BitVec,
+    kmer_length: usize,
+    window_size: usize,
+) -> BitVec {
+    debug_assert!(window_size > 0);
+    let kmer_count = excluded.len();
+    let window_size = window_size.min(kmer_count);
+    let order = |kmer_index: usize| {
+        if excluded[kmer_index] {
+            u64::MAX
+        } else {
+            kmer_hash(&text[kmer_index..kmer_index + kmer_length])
+        }
+    };
+
+    let mut result = BitVec::repeat(false, kmer_count);
+    // The candidate minimizers of the current window as `(order, kmer_index)`, with strictly increasing order.
+    let mut candidates = VecDeque::<(u64, usize)>::new();
+    for kmer_index in 0..kmer_count {
+        let kmer_order = order(kmer_index);
+        while candidates
+            .back()
+            .is_some_and(|&(candidate_order, _)| candidate_order > kmer_order)
+        {
+            candidates.pop_back();
+        }
+        candidates.push_back((kmer_order, kmer_index));
+
+        if let Some(window_start) = (kmer_index + 1).checked_sub(window_size) {
+            while candidates
+                .front()
+                .is_some_and(|&(_, candidate_index)| candidate_index < window_start)
+            {
+                candidates.pop_front();
+            }
+            result.set(candidates[0].1, true);
+        }
+    }
+    result
+}
+
+/// A hash of a kmer that does not depend on the platform or the version of the standard library.
+///
+/// Uses FNV-1a followed by the finaliser of MurmurHash3,
+/// such that the order of kmers is not biased towards lexicographically small kmers.
+pub(crate) fn kmer_hash(kmer: &[u8]) -> u64 {
+    let mut hash = kmer
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325, |hash: u64, &character| {
+            (hash ^ u64::from(character)).wrapping_mul(0x0000_0100_0000_01b3)
+        });
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    hash ^ (hash >> 33)
+}
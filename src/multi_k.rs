@@ -0,0 +1,320 @@
+//! Match tables for several minimum lengths computed in one pass.
+
+use compact_genome::interface::{alphabet::Alphabet, sequence::GenomeSequence};
+use log::debug;
+
+use crate::{
+    ContigLayout, MatchOrientation, MatchTable, MatchTableBuilder, MatchTableError, ProgressEvent,
+    Quadrant, StorageBackend, StorageIndex,
+    construction::{QuadrantSinks, Texts, find_matches},
+    fingerprint::Fingerprint,
+    mask::{KmerFlags, merge_intervals},
+    storage::{QuadrantStorage, QuadrantStorageBuilder},
+};
+
+/// Match tables of the same pair of genome strings for several minimum lengths.
+///
+/// Without mismatches, a kmer matches if and only if all its shorter sub-kmers at the same offsets match.
+/// So the index is searched only once for the shortest minimum length,
+/// and the tables of the longer minimum lengths are derived from the runs of consecutive matches along the diagonals.
+///
+/// The kmer indices of the tables are stored as `Index`, see [`StorageIndex`].
+pub struct MultiKMatchTable<Index: StorageIndex = u32> {
+    /// The tables in increasing order of their minimum length.
+    tables: Vec<MatchTable<Index>>,
+}
+
+impl MultiKMatchTable {
+    /// Compute the error-free template switch inner entry points of each of the given minimum lengths for a pair of genome strings.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`MatchTableBuilder::try_build_multi_k`] returns an error.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::MultiKMatchTable;
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+    /// let matches = MultiKMatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     &[5, 3, 4],
+    /// );
+    ///
+    /// assert_eq!(matches.minimum_lengths().collect::<Vec<_>>(), vec![3, 4, 5]);
+    /// assert!(matches.table(4).unwrap().has_reference_reference_match(1, 2));
+    /// assert_eq!(matches.table(5).unwrap().reference_reference_matches().count(), 0);
+    /// assert!(matches.table(6).is_none());
+    /// ```
+    pub fn new<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        reference: &GenomeSubsequence,
+        query: &GenomeSubsequence,
+        minimum_lengths: &[usize],
+    ) -> Self {
+        MatchTableBuilder::new(1).build_multi_k(reference, query, minimum_lengths)
+    }
+}
+
+impl<Index: StorageIndex> MultiKMatchTable<Index> {
+    pub(crate) fn from_tables(tables: Vec<MatchTable<Index>>) -> Self {
+        debug_assert!(
+            tables
+                .windows(2)
+                .all(|tables| tables[0].minimum_length() < tables[1].minimum_length())
+        );
+        Self { tables }
+    }
+
+    /// Compute the tables of the given options, which must be valid and in strictly increasing order of their minimum length,
+    /// together with the kmer counts of the reference and the query for each minimum length.
+    ///
+    /// All options must be equal apart from their minimum length, and must not allow mismatches or matching in forward orientation.
+    pub(crate) fn construct<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        reference: &GenomeSubsequence,
+        query: &GenomeSubsequence,
+        options: &[(MatchTableBuilder<Index>, (usize, usize))],
+    ) -> Result<Self, MatchTableError> {
+        let Some((shortest_options, shortest_kmer_counts)) = options.first() else {
+            return Ok(Self::from_tables(Vec::new()));
+        };
+        debug_assert!(
+            shortest_options.max_mismatches == 0
+                && shortest_options.orientation == MatchOrientation::ReverseComplement
+        );
+
+        // Minimizers of longer kmers may be sub-kmers that are no minimizers,
+        // and longer kmers may occur less often than their sub-kmers,
+        // so the shortest kmers are matched without sparsification and without excluding frequent kmers.
+        let shortest_options = MatchTableBuilder {
+            minimizer_window: None,
+            max_kmer_occurrences: None,
+            unique_matches_only: false,
+            ..shortest_options.clone()
+        };
+        let texts = Texts::new(reference, query, &shortest_options);
+        let contigs = [
+            ContigLayout::new([reference.len()]),
+            ContigLayout::new([query.len()]),
+        ];
+        let shortest_builder = |quadrant: Quadrant| {
+            let (primary_kmer_count, secondary_kmer_count) =
+                quadrant.dimensions(shortest_kmer_counts.0, shortest_kmer_counts.1);
+            QuadrantStorageBuilder::new(
+                StorageBackend::Sparse,
+                primary_kmer_count,
+                secondary_kmer_count,
+                None,
+                false,
+            )
+        };
+        let [
+            mut reference_reference,
+            mut reference_query,
+            mut query_reference,
+            mut query_query,
+        ] = [
+            shortest_builder(Quadrant::ReferenceReference)?,
+            shortest_builder(Quadrant::ReferenceQuery)?,
+            shortest_builder(Quadrant::QueryReference)?,
+            shortest_builder(Quadrant::QueryQuery)?,
+        ];
+        find_matches(
+            &texts,
+            [&contigs[0], &contigs[1]],
+            &shortest_options,
+            QuadrantSinks {
+                reference_reference: &mut reference_reference,
+                reference_query: &mut reference_query,
+                query_reference: &mut query_reference,
+                query_query: &mut query_query,
+            },
+        );
+        shortest_options.check_cancelled()?;
+        let shortest_matches = [
+            reference_reference.build(),
+            reference_query.build(),
+            query_reference.build(),
+            query_query.build(),
+        ];
+
+        let tables = options
+            .iter()
+            .map(|(options, kmer_counts)| {
+                debug!(
+                    "Deriving the match table for minimum length {}",
+                    options.minimum_length
+                );
+                derive_table(
+                    &texts,
+                    &contigs,
+                    &shortest_matches,
+                    *shortest_kmer_counts,
+                    shortest_options.minimum_length,
+                    options,
+                    *kmer_counts,
+                )
+            })
+            .collect::<Result<_, _>>()?;
+        shortest_options.report(ProgressEvent::Finished);
+        Ok(Self::from_tables(tables))
+    }
+
+    /// Returns the table of the given minimum length, if it was computed.
+    pub fn table(&self, minimum_length: usize) -> Option<&MatchTable<Index>> {
+        self.tables
+            .binary_search_by_key(&minimum_length, MatchTable::minimum_length)
+            .ok()
+            .map(|index| &self.tables[index])
+    }
+
+    /// Returns an iterator over the minimum lengths of the tables in increasing order.
+    pub fn minimum_lengths(&self) -> impl Iterator<Item = usize> + '_ {
+        self.tables.iter().map(MatchTable::minimum_length)
+    }
+
+    /// Returns the tables in increasing order of their minimum length.
+    pub fn tables(&self) -> &[MatchTable<Index>] {
+        &self.tables
+    }
+
+    /// Returns the tables in increasing order of their minimum length.
+    pub fn into_tables(self) -> Vec<MatchTable<Index>> {
+        self.tables
+    }
+}
+
+/// Derive the table of the given options from the matches of the shortest kmers.
+///
+/// A kmer that is `extension` characters longer than the shortest kmers matches
+/// if and only if the `extension + 1` shortest kmers starting at the same offsets match,
+/// i.e. if a run of `extension + 1` consecutive matches starts at its pair of indices along the diagonal.
+/// The band is the same for all minimum lengths, and is constant along the diagonals.
+fn derive_table<Index: StorageIndex>(
+    texts: &Texts,
+    [reference_contigs, query_contigs]: &[ContigLayout; 2],
+    shortest_matches: &[QuadrantStorage<Index>; 4],
+    shortest_kmer_counts: (usize, usize),
+    shortest_minimum_length: usize,
+    options: &MatchTableBuilder<Index>,
+    (reference_kmer_count, query_kmer_count): (usize, usize),
+) -> Result<MatchTable<Index>, MatchTableError> {
+    let frequent_kmers = texts.find_frequent_kmers(options);
+    let [frequent_reference_kmers, frequent_query_kmers] = match &frequent_kmers {
+        Some([reference, query]) => [Some(reference), Some(query)],
+        None => [None, None],
+    };
+    let reference_flags = KmerFlags::new(
+        &texts.reference,
+        frequent_reference_kmers,
+        options.reference_mask.as_ref(),
+        &options.reference_excluded_intervals,
+        reference_contigs,
+        options,
+    );
+    let query_flags = KmerFlags::new(
+        &texts.query,
+        frequent_query_kmers,
+        options.query_mask.as_ref(),
+        &options.query_excluded_intervals,
+        query_contigs,
+        options,
+    );
+    let extension = options.minimum_length - shortest_minimum_length;
+
+    let mut builders =
+        options.quadrant_storage_builders((reference_kmer_count, query_kmer_count))?;
+    for ((quadrant, shortest_matches), builder) in Quadrant::ALL
+        .into_iter()
+        .zip(shortest_matches)
+        .zip(&mut builders)
+    {
+        let flags = |is_reference| {
+            if is_reference {
+                &reference_flags
+            } else {
+                &query_flags
+            }
+        };
+        let primary_flags = flags(quadrant.primary_is_reference());
+        let secondary_flags = flags(quadrant.secondary_is_reference());
+        let secondary_kmer_count = secondary_flags.excluded.len();
+        let (shortest_primary_kmer_count, shortest_secondary_kmer_count) =
+            quadrant.dimensions(shortest_kmer_counts.0, shortest_kmer_counts.1);
+
+        for (primary_index, secondary_rc_index) in shortest_matches.iter() {
+            let is_run_start = primary_index == 0
+                || secondary_rc_index == 0
+                || !shortest_matches.has_match(primary_index - 1, secondary_rc_index - 1);
+            if !is_run_start {
+                continue;
+            }
+
+            let run_length = (1..)
+                .take_while(|&offset| {
+                    primary_index + offset < shortest_primary_kmer_count
+                        && secondary_rc_index + offset < shortest_secondary_kmer_count
+                        && shortest_matches
+                            .has_match(primary_index + offset, secondary_rc_index + offset)
+                })
+                .count()
+                + 1;
+
+            for offset in 0..run_length.saturating_sub(extension) {
+                let primary_index = primary_index + offset;
+                let secondary_rc_index = secondary_rc_index + offset;
+                let secondary_index = secondary_kmer_count - 1 - secondary_rc_index;
+                if !primary_flags.excluded[primary_index]
+                    && !secondary_flags.excluded[secondary_index]
+                    && (primary_flags.is_minimizer(primary_index)
+                        || secondary_flags.is_minimizer(secondary_index))
+                {
+                    builder.insert(primary_index, secondary_rc_index);
+                }
+            }
+        }
+    }
+
+    let [
+        reference_reference,
+        reference_query,
+        query_reference,
+        query_query,
+    ] = builders;
+    Ok(MatchTable {
+        reference_reference: reference_reference.build(),
+        reference_query: reference_query.build(),
+        query_reference: query_reference.build(),
+        query_query: query_query.build(),
+        reference_kmer_count,
+        query_kmer_count,
+        minimum_length: options.minimum_length,
+        max_mismatches: options.max_mismatches,
+        skipped_reference_kmer_count: reference_flags.excluded_kmer_count(),
+        skipped_query_kmer_count: query_flags.excluded_kmer_count(),
+        max_kmer_occurrences: options.max_kmer_occurrences,
+        unique_matches_only: options.unique_matches_only,
+        maximum_length: options.maximum_length,
+        frequent_reference_kmer_count: frequent_reference_kmers
+            .map_or(0, |kmers| kmers.count_ones()),
+        frequent_query_kmer_count: frequent_query_kmers.map_or(0, |kmers| kmers.count_ones()),
+        reference_contigs: reference_contigs.clone(),
+        query_contigs: query_contigs.clone(),
+        band: options.band,
+        quadrants: options.quadrants,
+        reference_excluded_intervals: merge_intervals(&options.reference_excluded_intervals),
+        query_excluded_intervals: merge_intervals(&options.query_excluded_intervals),
+        fingerprint: Some(Fingerprint::new(&texts.reference, &texts.query, options)),
+    })
+}
@@ -0,0 +1,175 @@
+//! A brute-force reference implementation for validating construction backends and modes.
+//!
+//! [`naive_matches`] compares every primary kmer with every reverse-complemented secondary kmer character by character,
+//! taking `O(n * m * k)` time for sequences of lengths `n` and `m` and minimum length `k`.
+//! [`assert_equivalent`] compares a [`MatchTable`] against its result, so new backends or modes can be validated against ground truth in tests.
+//!
+//! Which kmers are excluded from matching, e.g. by masks, [`skip_n`](MatchTableBuilder::skip_n),
+//! contig boundaries or [minimizer sparsification](MatchTableBuilder::minimizer_window),
+//! is decided exactly as in construction, while the kmers themselves are compared naively.
+
+use compact_genome::interface::{alphabet::Alphabet, sequence::GenomeSequence};
+
+use crate::{
+    ContigLayout, MatchTable, MatchTableBuilder, MatchTableError, Quadrant, StorageIndex,
+    construction::kmers_match,
+    mask::{KmerFlags, frequent_kmers},
+};
+
+/// Compute the matches of all quadrants by comparing all pairs of kmers directly.
+///
+/// Honours all options of the builder that affect the matches, i.e. all options except the storage, index and construction strategy.
+/// Quadrants that are not [selected](MatchTableBuilder::quadrants) are empty.
+/// The matches of each quadrant are returned in the order of [`Quadrant::ALL`],
+/// as `(primary_index, secondary_rc_index)` pairs in the row-major order of [`MatchTable::matches`].
+///
+/// Returns an error if the options are invalid for the sequences, like [`MatchTableBuilder::try_build`].
+///
+/// # Example
+///
+/// ```rust
+/// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+/// use compact_genome::implementation::vec_sequence::VectorGenome;
+/// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+/// use template_switch_error_free_inners::MatchTableBuilder;
+/// use template_switch_error_free_inners::naive::{assert_equivalent, naive_matches};
+///
+/// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+/// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+/// let options = MatchTableBuilder::new(4);
+///
+/// let expected = naive_matches(
+///     reference.as_genome_subsequence(),
+///     query.as_genome_subsequence(),
+///     &options,
+/// )
+/// .unwrap();
+/// assert_eq!(expected[0], vec![(1, 2), (7, 8)]);
+///
+/// let matches = options.build(reference.as_genome_subsequence(), query.as_genome_subsequence());
+/// assert_equivalent(&matches, &expected);
+/// ```
+pub fn naive_matches<
+    AlphabetType: Alphabet,
+    GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+>(
+    reference: &GenomeSubsequence,
+    query: &GenomeSubsequence,
+    options: &MatchTableBuilder<impl StorageIndex>,
+) -> Result<[Vec<(usize, usize)>; 4], MatchTableError> {
+    options.kmer_counts(reference.len(), query.len())?;
+    let k = options.minimum_length;
+    let forward = |sequence: &GenomeSubsequence| -> Vec<u8> {
+        sequence
+            .iter()
+            .map(|character| character.clone().into())
+            .collect()
+    };
+    let reverse_complement = |sequence: &GenomeSubsequence| -> Vec<u8> {
+        sequence.reverse_complement_iter().map(Into::into).collect()
+    };
+    let (reference, reference_rc, query, query_rc) = (
+        forward(reference),
+        reverse_complement(reference),
+        forward(query),
+        reverse_complement(query),
+    );
+
+    let frequent_kmers = frequent_kmers([&reference, &query], [&reference_rc, &query_rc], options);
+    let [frequent_reference_kmers, frequent_query_kmers] = match &frequent_kmers {
+        Some([reference, query]) => [Some(reference), Some(query)],
+        None => [None, None],
+    };
+    let reference_flags = KmerFlags::new(
+        &reference,
+        frequent_reference_kmers,
+        options.reference_mask.as_ref(),
+        &options.reference_excluded_intervals,
+        &ContigLayout::new([reference.len()]),
+        options,
+    );
+    let query_flags = KmerFlags::new(
+        &query,
+        frequent_query_kmers,
+        options.query_mask.as_ref(),
+        &options.query_excluded_intervals,
+        &ContigLayout::new([query.len()]),
+        options,
+    );
+    let genome = |is_reference: bool| {
+        if is_reference {
+            (&reference, &reference_rc, &reference_flags)
+        } else {
+            (&query, &query_rc, &query_flags)
+        }
+    };
+    let is_match = |primary_kmer: &[u8], secondary_kmer: &[u8]| {
+        kmers_match(
+            primary_kmer,
+            secondary_kmer.iter().copied(),
+            options.max_mismatches,
+            options.ambiguity_policy,
+        )
+    };
+
+    Ok(Quadrant::ALL.map(|quadrant| {
+        let mut matches = Vec::new();
+        if !options.quadrants.contains(quadrant) {
+            return matches;
+        }
+
+        let (primary, _, primary_flags) = genome(quadrant.primary_is_reference());
+        let (secondary, secondary_rc, secondary_flags) = genome(quadrant.secondary_is_reference());
+        let secondary_kmer_count = secondary_flags.excluded.len();
+        for (primary_index, primary_kmer) in primary.windows(k).enumerate() {
+            for (secondary_rc_index, secondary_rc_kmer) in secondary_rc.windows(k).enumerate() {
+                let secondary_index = secondary_kmer_count - 1 - secondary_rc_index;
+                let secondary_kmer = &secondary[secondary_index..secondary_index + k];
+                if !primary_flags.excluded[primary_index]
+                    && !secondary_flags.excluded[secondary_index]
+                    && (primary_flags.is_minimizer(primary_index)
+                        || secondary_flags.is_minimizer(secondary_index))
+                    && options
+                        .band
+                        .is_none_or(|band| band.contains(primary_index, secondary_rc_index))
+                    && ((options.orientation.reverse_complement()
+                        && is_match(primary_kmer, secondary_rc_kmer))
+                        || (options.orientation.forward()
+                            && is_match(primary_kmer, secondary_kmer)))
+                {
+                    matches.push((primary_index, secondary_rc_index));
+                }
+            }
+        }
+        matches
+    }))
+}
+
+/// Assert that the table contains exactly the `expected` matches of each quadrant, as returned by [`naive_matches`].
+///
+/// # Panics
+///
+/// Panics if the matches of a quadrant differ,
+/// with a message listing the quadrant and the first missing and unexpected matches.
+pub fn assert_equivalent(
+    table: &MatchTable<impl StorageIndex>,
+    expected: &[Vec<(usize, usize)>; 4],
+) {
+    for (quadrant, expected) in Quadrant::ALL.into_iter().zip(expected) {
+        let actual: Vec<_> = table.matches(quadrant).collect();
+        if actual != *expected {
+            let missing = expected
+                .iter()
+                .find(|pair| actual.binary_search(pair).is_err());
+            let unexpected = actual
+                .iter()
+                .find(|pair| expected.binary_search(pair).is_err());
+            panic!(
+                "The matches of quadrant {quadrant} differ from the naive matches: \
+                 {} matches instead of {}, first missing match {missing:?}, first unexpected match {unexpected:?}",
+                actual.len(),
+                expected.len(),
+            );
+        }
+    }
+}
@@ -0,0 +1,330 @@
+//! Texts packed into two bits per character for fast comparison of long substrings.
+
+/// The number of characters in a word.
+const CHARACTERS_PER_WORD: usize = 32;
+
+/// Selects the lower bit of each character of a word.
+const LOWER_BITS: u64 = 0x5555_5555_5555_5555;
+
+/// A text over the alphabet `ACGT` packed into two bits per character.
+///
+/// Substrings are compared a word of 32 characters at a time,
+/// and with the `simd` feature four words at a time using AVX2 if the CPU supports it.
+pub(crate) struct PackedText {
+    /// Character `i` is stored in bits `2 * (i % 32)..2 * (i % 32) + 2` of word `i / 32`.
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl PackedText {
+    /// Pack the text, or return `None` if it contains characters other than `ACGT`.
+    pub fn new(text: &[u8]) -> Option<Self> {
+        Self::from_characters(text.iter().copied())
+    }
+
+    /// Pack the characters, or return `None` if they contain characters other than `ACGT`.
+    pub fn from_characters(characters: impl ExactSizeIterator<Item = u8>) -> Option<Self> {
+        let len = characters.len();
+        let mut words = vec![0; len.div_ceil(CHARACTERS_PER_WORD)];
+        for (position, character) in characters.enumerate() {
+            let code: u64 = match character {
+                b'A' => 0,
+                b'C' => 1,
+                b'G' => 2,
+                b'T' => 3,
+                _ => return None,
+            };
+            words[position / CHARACTERS_PER_WORD] |= code << (2 * (position % CHARACTERS_PER_WORD));
+        }
+        Some(Self { words, len })
+    }
+
+    /// Returns the 32 characters starting at `position`, padded with zero bits beyond the end of the text.
+    fn window(&self, position: usize) -> u64 {
+        let word_index = position / CHARACTERS_PER_WORD;
+        let shift = 2 * (position % CHARACTERS_PER_WORD);
+        let word = |index: usize| self.words.get(index).copied().unwrap_or(0);
+        if shift == 0 {
+            word(word_index)
+        } else {
+            (word(word_index) >> shift) | (word(word_index + 1) << (64 - shift))
+        }
+    }
+
+    /// Returns the number of mismatching characters between `self[start..start + length]` and `other[other_start..other_start + length]`.
+    pub fn mismatch_count(
+        &self,
+        start: usize,
+        other: &Self,
+        other_start: usize,
+        length: usize,
+    ) -> usize {
+        debug_assert!(start + length <= self.len && other_start + length <= other.len);
+        #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+        if std::arch::is_x86_feature_detected!("avx2") {
+            // Safety: AVX2 is supported by the CPU.
+            let (mismatch_count, offset) =
+                unsafe { avx2::mismatch_count(self, start, other, other_start, length) };
+            return mismatch_count
+                + self.scalar_mismatch_count(start, other, other_start, offset, length);
+        }
+        self.scalar_mismatch_count(start, other, other_start, 0, length)
+    }
+
+    /// Like [`mismatch_count`](Self::mismatch_count), but compares a word at a time starting at `offset`.
+    pub fn scalar_mismatch_count(
+        &self,
+        start: usize,
+        other: &Self,
+        other_start: usize,
+        mut offset: usize,
+        length: usize,
+    ) -> usize {
+        let mut mismatch_count = 0;
+        while offset < length {
+            let chunk_length = (length - offset).min(CHARACTERS_PER_WORD);
+            let difference = (self.window(start + offset) ^ other.window(other_start + offset))
+                & low_characters_mask(chunk_length);
+            mismatch_count += mismatching_characters(difference);
+            offset += chunk_length;
+        }
+        mismatch_count
+    }
+
+    /// Returns the length of the longest common prefix of `self[start..]` and `other[other_start..]`.
+    pub fn common_prefix_length(&self, start: usize, other: &Self, other_start: usize) -> usize {
+        #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+        if std::arch::is_x86_feature_detected!("avx2") {
+            let length = (self.len - start).min(other.len - other_start);
+            // Safety: AVX2 is supported by the CPU.
+            return match unsafe {
+                avx2::common_prefix_length(self, start, other, other_start, length)
+            } {
+                Ok(common_prefix_length) => common_prefix_length,
+                Err(offset) => self.scalar_common_prefix_length(start, other, other_start, offset),
+            };
+        }
+        self.scalar_common_prefix_length(start, other, other_start, 0)
+    }
+
+    /// Like [`common_prefix_length`](Self::common_prefix_length), but compares a word at a time,
+    /// given that the first `offset` characters are equal.
+    pub fn scalar_common_prefix_length(
+        &self,
+        start: usize,
+        other: &Self,
+        other_start: usize,
+        mut offset: usize,
+    ) -> usize {
+        let length = (self.len - start).min(other.len - other_start);
+        while offset < length {
+            let chunk_length = (length - offset).min(CHARACTERS_PER_WORD);
+            let difference = (self.window(start + offset) ^ other.window(other_start + offset))
+                & low_characters_mask(chunk_length);
+            if difference != 0 {
+                return offset + difference.trailing_zeros() as usize / 2;
+            }
+            offset += chunk_length;
+        }
+        length
+    }
+}
+
+/// Returns a mask of the lowest `character_count` characters of a word.
+fn low_characters_mask(character_count: usize) -> u64 {
+    if character_count >= CHARACTERS_PER_WORD {
+        u64::MAX
+    } else {
+        (1 << (2 * character_count)) - 1
+    }
+}
+
+/// Returns the number of characters with a non-zero code in the word.
+fn mismatching_characters(difference: u64) -> usize {
+    ((difference | (difference >> 1)) & LOWER_BITS).count_ones() as usize
+}
+
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+mod avx2 {
+    use std::arch::x86_64::{
+        __m256i, _mm_cvtsi64_si128, _mm256_add_epi8, _mm256_and_si256, _mm256_castsi256_pd,
+        _mm256_cmpeq_epi64, _mm256_extract_epi64, _mm256_loadu_si256, _mm256_movemask_pd,
+        _mm256_or_si256, _mm256_sad_epu8, _mm256_set1_epi8, _mm256_set1_epi64x, _mm256_setr_epi8,
+        _mm256_setzero_si256, _mm256_shuffle_epi8, _mm256_sll_epi64, _mm256_srl_epi64,
+        _mm256_srli_epi16, _mm256_srli_epi64, _mm256_storeu_si256, _mm256_testz_si256,
+        _mm256_xor_si256,
+    };
+
+    use super::{CHARACTERS_PER_WORD, LOWER_BITS, PackedText};
+
+    /// The number of characters compared by one vector instruction.
+    const CHARACTERS_PER_VECTOR: usize = 4 * CHARACTERS_PER_WORD;
+
+    /// The number of vectors after which the byte counts of [`popcount`] are summed up, before they overflow.
+    const VECTORS_PER_SUM: usize = 63;
+
+    /// Returns the 128 characters starting at `position`,
+    /// or `None` if the text does not have the five words that contain them.
+    ///
+    /// # Safety
+    ///
+    /// The CPU must support AVX2.
+    #[target_feature(enable = "avx2")]
+    unsafe fn window(text: &PackedText, position: usize) -> Option<__m256i> {
+        let word_index = position / CHARACTERS_PER_WORD;
+        if word_index + 5 > text.words.len() {
+            return None;
+        }
+        let shift = (2 * (position % CHARACTERS_PER_WORD)) as i64;
+        // Safety: the words `word_index..word_index + 5` are in bounds, and AVX2 is supported by the CPU.
+        // Shifting by 64 bits yields zero, so an aligned window is just the lower words.
+        unsafe {
+            let lower = _mm256_loadu_si256(text.words.as_ptr().add(word_index).cast());
+            let upper = _mm256_loadu_si256(text.words.as_ptr().add(word_index + 1).cast());
+            Some(_mm256_or_si256(
+                _mm256_srl_epi64(lower, _mm_cvtsi64_si128(shift)),
+                _mm256_sll_epi64(upper, _mm_cvtsi64_si128(64 - shift)),
+            ))
+        }
+    }
+
+    /// Returns the XOR of the windows of 128 characters starting at `start` in `a` and at `other_start` in `b`.
+    ///
+    /// # Safety
+    ///
+    /// The CPU must support AVX2.
+    #[target_feature(enable = "avx2")]
+    unsafe fn difference(
+        a: &PackedText,
+        start: usize,
+        b: &PackedText,
+        other_start: usize,
+    ) -> Option<__m256i> {
+        unsafe { Some(_mm256_xor_si256(window(a, start)?, window(b, other_start)?)) }
+    }
+
+    /// Returns the number of set bits of each byte of the vector.
+    ///
+    /// # Safety
+    ///
+    /// The CPU must support AVX2.
+    #[target_feature(enable = "avx2")]
+    unsafe fn popcount(vector: __m256i) -> __m256i {
+        // Safety: AVX2 is supported by the CPU.
+        unsafe {
+            let nibble_counts = _mm256_setr_epi8(
+                0, 1, 1, 2, 1, 2, 2, 3, 1, 2, 2, 3, 2, 3, 3, 4, 0, 1, 1, 2, 1, 2, 2, 3, 1, 2, 2, 3,
+                2, 3, 3, 4,
+            );
+            let low_nibbles = _mm256_set1_epi8(0x0f);
+            _mm256_add_epi8(
+                _mm256_shuffle_epi8(nibble_counts, _mm256_and_si256(vector, low_nibbles)),
+                _mm256_shuffle_epi8(
+                    nibble_counts,
+                    _mm256_and_si256(_mm256_srli_epi16(vector, 4), low_nibbles),
+                ),
+            )
+        }
+    }
+
+    /// Returns the sum of the bytes of the vector.
+    ///
+    /// # Safety
+    ///
+    /// The CPU must support AVX2.
+    #[target_feature(enable = "avx2")]
+    unsafe fn byte_sum(vector: __m256i) -> usize {
+        // Safety: AVX2 is supported by the CPU.
+        unsafe {
+            let sums = _mm256_sad_epu8(vector, _mm256_setzero_si256());
+            (_mm256_extract_epi64::<0>(sums)
+                + _mm256_extract_epi64::<1>(sums)
+                + _mm256_extract_epi64::<2>(sums)
+                + _mm256_extract_epi64::<3>(sums)) as usize
+        }
+    }
+
+    /// Returns the number of mismatching characters in the longest prefix of whole vectors of the first `length` characters
+    /// that lies within both texts, and the length of that prefix.
+    ///
+    /// # Safety
+    ///
+    /// The CPU must support AVX2.
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn mismatch_count(
+        a: &PackedText,
+        start: usize,
+        b: &PackedText,
+        other_start: usize,
+        length: usize,
+    ) -> (usize, usize) {
+        // Safety: AVX2 is supported by the CPU.
+        unsafe {
+            let lower_bits = _mm256_set1_epi64x(LOWER_BITS as i64);
+            let mut mismatch_count = 0;
+            let mut byte_counts = _mm256_setzero_si256();
+            let mut offset = 0;
+            let mut vector_count = 0;
+            while offset + CHARACTERS_PER_VECTOR <= length {
+                let Some(difference) = difference(a, start + offset, b, other_start + offset)
+                else {
+                    break;
+                };
+                let mismatches = _mm256_and_si256(
+                    _mm256_or_si256(difference, _mm256_srli_epi64(difference, 1)),
+                    lower_bits,
+                );
+                // Each byte counts at most four mismatches per vector.
+                byte_counts = _mm256_add_epi8(byte_counts, popcount(mismatches));
+                vector_count += 1;
+                if vector_count % VECTORS_PER_SUM == 0 {
+                    mismatch_count += byte_sum(byte_counts);
+                    byte_counts = _mm256_setzero_si256();
+                }
+                offset += CHARACTERS_PER_VECTOR;
+            }
+            (mismatch_count + byte_sum(byte_counts), offset)
+        }
+    }
+
+    /// Returns the length of the longest common prefix of the first `length` characters,
+    /// or `Err` with the length of the equal prefix of whole vectors that lies within both texts if no mismatch was found there.
+    ///
+    /// # Safety
+    ///
+    /// The CPU must support AVX2.
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn common_prefix_length(
+        a: &PackedText,
+        start: usize,
+        b: &PackedText,
+        other_start: usize,
+        length: usize,
+    ) -> Result<usize, usize> {
+        // Safety: AVX2 is supported by the CPU.
+        unsafe {
+            let mut offset = 0;
+            while offset + CHARACTERS_PER_VECTOR <= length {
+                let Some(difference) = difference(a, start + offset, b, other_start + offset)
+                else {
+                    break;
+                };
+                if _mm256_testz_si256(difference, difference) == 0 {
+                    // The first word with a difference, then its first differing character.
+                    let equal_words = _mm256_movemask_pd(_mm256_castsi256_pd(_mm256_cmpeq_epi64(
+                        difference,
+                        _mm256_setzero_si256(),
+                    )));
+                    let word = equal_words.trailing_ones() as usize;
+                    let mut words = [0u64; 4];
+                    _mm256_storeu_si256(words.as_mut_ptr().cast(), difference);
+                    return Ok(offset
+                        + word * CHARACTERS_PER_WORD
+                        + words[word].trailing_zeros() as usize / 2);
+                }
+                offset += CHARACTERS_PER_VECTOR;
+            }
+            Err(offset)
+        }
+    }
+}
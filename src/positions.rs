@@ -0,0 +1,249 @@
+//! The kmer indexes built during construction, exposed for reuse by downstream analyses,
+//! and the construction from indexes given by the user.
+
+use compact_genome::interface::{alphabet::Alphabet, sequence::GenomeSequence};
+use log::debug;
+
+use crate::{
+    ConstructionStrategy, ContigLayout, IndexBackend, MatchTable, MatchTableBuilder,
+    MatchTableError, StorageIndex,
+    construction::find_indexed_matches,
+    index::{BackendIndex, SequenceIndex},
+};
+
+/// The indexes of the reference and the query that list the positions of each kmer,
+/// see [`MatchTableBuilder::build_with_kmer_positions`].
+///
+/// Kmers of any length can be looked up, not only kmers of the minimum length of the table.
+/// The indexes cover the whole sequences, regardless of masks, excluded intervals and other options that exclude kmers from matching.
+pub struct KmerPositions {
+    reference: BackendIndex,
+    query: BackendIndex,
+}
+
+impl KmerPositions {
+    /// Returns the start positions of all occurrences of `kmer` in the reference in increasing order.
+    ///
+    /// The kmer is given as ASCII characters of the alphabet, like those returned by [`GenomeSequence::clone_as_vec`].
+    /// Returns no positions if the kmer is empty.
+    pub fn positions_of_reference_kmer(&self, kmer: &[u8]) -> Vec<usize> {
+        Self::positions(&self.reference, kmer)
+    }
+
+    /// Returns the start positions of all occurrences of `kmer` in the query in increasing order.
+    ///
+    /// See [`positions_of_reference_kmer`](Self::positions_of_reference_kmer) for details.
+    pub fn positions_of_query_kmer(&self, kmer: &[u8]) -> Vec<usize> {
+        Self::positions(&self.query, kmer)
+    }
+
+    /// Returns the backend of the indexes, which is the one configured with [`MatchTableBuilder::index_backend`].
+    pub fn index_backend(&self) -> IndexBackend {
+        self.reference.index_backend()
+    }
+
+    fn positions(index: &BackendIndex, kmer: &[u8]) -> Vec<usize> {
+        if kmer.is_empty() {
+            return Vec::new();
+        }
+        let mut positions: Vec<_> = index.positions(kmer).collect();
+        positions.sort_unstable();
+        positions
+    }
+}
+
+impl<Index: StorageIndex> MatchTableBuilder<Index> {
+    /// Compute the match table of the given reference and query, and keep the indexes of the kmer positions of both sequences.
+    ///
+    /// The indexes are built with the configured [`IndexBackend`] and are used to construct the table
+    /// if the [strategy](Self::strategy) resolves to [`ConstructionStrategy::IndexLookup`](crate::ConstructionStrategy::IndexLookup).
+    /// Otherwise, they are built in addition to the structures of the strategy.
+    /// Unlike during [`build`](Self::build), the index of a sequence is built even if it is not the primary sequence of a computed quadrant.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`try_build_with_kmer_positions`](Self::try_build_with_kmer_positions) returns an error.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::MatchTableBuilder;
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+    ///
+    /// let (matches, positions) = MatchTableBuilder::new(4)
+    ///     .build_with_kmer_positions(reference.as_genome_subsequence(), query.as_genome_subsequence());
+    /// assert!(matches.has_reference_reference_match(1, 2));
+    /// assert_eq!(positions.positions_of_reference_kmer(b"GGGG"), vec![1]);
+    /// assert_eq!(positions.positions_of_reference_kmer(b"AA"), vec![5, 11]);
+    /// assert_eq!(positions.positions_of_query_kmer(b"AAAAAAA"), vec![0, 1]);
+    /// ```
+    pub fn build_with_kmer_positions<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        &self,
+        reference: &GenomeSubsequence,
+        query: &GenomeSubsequence,
+    ) -> (MatchTable<Index>, KmerPositions) {
+        self.try_build_with_kmer_positions(reference, query)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Compute the match table of the given reference and query, and keep the indexes of the kmer positions of both sequences.
+    ///
+    /// Returns an error under the same conditions as [`try_build`](Self::try_build).
+    /// See [`build_with_kmer_positions`](Self::build_with_kmer_positions) for details.
+    pub fn try_build_with_kmer_positions<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        &self,
+        reference: &GenomeSubsequence,
+        query: &GenomeSubsequence,
+    ) -> Result<(MatchTable<Index>, KmerPositions), MatchTableError> {
+        let kmer_counts = self.kmer_counts(reference.len(), query.len())?;
+        let builders = self.quadrant_storage_builders(kmer_counts)?;
+        let contigs = [
+            ContigLayout::new([reference.len()]),
+            ContigLayout::new([query.len()]),
+        ];
+
+        let mut positions = None;
+        let Ok(table) = MatchTable::construct_with(
+            reference,
+            query,
+            contigs,
+            self,
+            builders,
+            |texts, contigs, options, sinks| {
+                debug!("Computing kmer position indexes");
+                let indexes = {
+                    enter_span!("build_indexes", backend = ?options.index_backend);
+                    KmerPositions {
+                        reference: BackendIndex::new(&texts.reference, options.index_backend),
+                        query: BackendIndex::new(&texts.query, options.index_backend),
+                    }
+                };
+                let skipped_kmer_counts = find_indexed_matches(
+                    texts,
+                    contigs,
+                    options,
+                    Some([&indexes.reference, &indexes.query]),
+                    sinks,
+                );
+                positions = Some(indexes);
+                Ok::<_, std::convert::Infallible>(skipped_kmer_counts)
+            },
+        );
+        self.check_cancelled()?;
+        let positions = positions.unwrap_or_else(|| unreachable!("the indexes are always built"));
+        Ok((table, positions))
+    }
+
+    /// Compute the match table of the given reference and query, looking up the kmers in the given indexes
+    /// instead of building indexes with the configured [`IndexBackend`].
+    ///
+    /// The reference index must index the reference, and the query index the query, see [`SequenceIndex`].
+    /// An index is only used if its sequence is the primary sequence of a [computed quadrant](Self::quadrants).
+    /// The kmers are always looked up in the indexes, so the configured [strategy](Self::strategy) is ignored.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`try_build_with_indexes`](Self::try_build_with_indexes) returns an error.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTable, MatchTableBuilder, SequenceIndex};
+    ///
+    /// /// An index that finds the occurrences of a pattern by comparing it with each position of the text.
+    /// struct ScanIndex(Vec<u8>);
+    ///
+    /// impl SequenceIndex for ScanIndex {
+    ///     fn positions(&self, pattern: &[u8]) -> impl Iterator<Item = usize> {
+    ///         self.0
+    ///             .windows(pattern.len())
+    ///             .enumerate()
+    ///             .filter(move |(_, window)| *window == pattern)
+    ///             .map(|(position, _)| position)
+    ///     }
+    /// }
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+    ///
+    /// let matches = MatchTableBuilder::new(4).build_with_indexes(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     &ScanIndex(reference.clone_as_vec()),
+    ///     &ScanIndex(query.clone_as_vec()),
+    /// );
+    /// assert!(matches.has_reference_reference_match(1, 2));
+    /// assert!(matches.has_reference_reference_match(7, 8));
+    /// ```
+    pub fn build_with_indexes<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+        SequenceIndexType: SequenceIndex,
+    >(
+        &self,
+        reference: &GenomeSubsequence,
+        query: &GenomeSubsequence,
+        reference_index: &SequenceIndexType,
+        query_index: &SequenceIndexType,
+    ) -> MatchTable<Index> {
+        self.try_build_with_indexes(reference, query, reference_index, query_index)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Compute the match table of the given reference and query, looking up the kmers in the given indexes.
+    ///
+    /// Returns an error under the same conditions as [`try_build`](Self::try_build).
+    /// See [`build_with_indexes`](Self::build_with_indexes) for details.
+    pub fn try_build_with_indexes<
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+        SequenceIndexType: SequenceIndex,
+    >(
+        &self,
+        reference: &GenomeSubsequence,
+        query: &GenomeSubsequence,
+        reference_index: &SequenceIndexType,
+        query_index: &SequenceIndexType,
+    ) -> Result<MatchTable<Index>, MatchTableError> {
+        let options = self.clone().strategy(ConstructionStrategy::IndexLookup);
+        let kmer_counts = options.kmer_counts(reference.len(), query.len())?;
+        let builders = options.quadrant_storage_builders(kmer_counts)?;
+        let contigs = [
+            ContigLayout::new([reference.len()]),
+            ContigLayout::new([query.len()]),
+        ];
+
+        let Ok(table) = MatchTable::construct_with(
+            reference,
+            query,
+            contigs,
+            &options,
+            builders,
+            |texts, contigs, options, sinks| {
+                Ok::<_, std::convert::Infallible>(find_indexed_matches(
+                    texts,
+                    contigs,
+                    options,
+                    Some([reference_index, query_index]),
+                    sinks,
+                ))
+            },
+        );
+        options.check_cancelled()?;
+        Ok(table)
+    }
+}
@@ -0,0 +1,60 @@
+//! Progress reporting during the construction of match tables.
+
+use std::{fmt::Debug, sync::Arc};
+
+/// An event during the construction of a [`MatchTable`](crate::MatchTable).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressEvent {
+    /// The kmer indexes of the reference and the query are built, and the search for matches starts.
+    IndexesBuilt,
+    /// The matches of `processed` of the `total` reverse-complemented kmers of a genome have been found.
+    ///
+    /// The reverse-complemented kmers of the reference are processed before those of the query.
+    /// When constructing in parallel, the events of a genome may arrive out of order,
+    /// but an event with `processed == total` is always reported.
+    KmersProcessed {
+        /// The genome whose reverse-complemented kmers are processed, either `"reference"` or `"query"`.
+        genome: &'static str,
+        /// The number of processed kmers.
+        processed: usize,
+        /// The number of reverse-complemented kmers of the genome.
+        total: usize,
+    },
+    /// All matches have been found, and the storage of all quadrants is built unless the matches are streamed.
+    Finished,
+}
+
+/// Receives [`ProgressEvent`]s during the construction of a [`MatchTable`](crate::MatchTable).
+///
+/// This is implemented for closures, so a reporter can forward the events to any progress bar or logger.
+/// Reporters are called from the constructing threads, so they should return quickly.
+pub trait ProgressReporter: Send + Sync {
+    /// Handle the given event.
+    fn report(&self, event: ProgressEvent);
+}
+
+impl<Function: Fn(ProgressEvent) + Send + Sync> ProgressReporter for Function {
+    fn report(&self, event: ProgressEvent) {
+        self(event)
+    }
+}
+
+/// A shared reporter stored in a [`MatchTableBuilder`](crate::MatchTableBuilder).
+#[derive(Clone)]
+pub(crate) struct ProgressHandle(Arc<dyn ProgressReporter>);
+
+impl ProgressHandle {
+    pub fn new(reporter: impl ProgressReporter + 'static) -> Self {
+        Self(Arc::new(reporter))
+    }
+
+    pub fn report(&self, event: ProgressEvent) {
+        self.0.report(event);
+    }
+}
+
+impl Debug for ProgressHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ProgressHandle").finish_non_exhaustive()
+    }
+}
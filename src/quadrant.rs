@@ -0,0 +1,197 @@
+//! The quadrants of a match table.
+
+use std::{
+    fmt::Display,
+    ops::{BitAnd, BitOr, BitOrAssign},
+};
+
+/// One of the four quadrants of a [`MatchTable`](crate::MatchTable).
+///
+/// The first genome is the one the primary index refers to,
+/// and the second genome is the one whose reverse complement the secondary rc index refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "wasm", wasm_bindgen::prelude::wasm_bindgen)]
+pub enum Quadrant {
+    /// Reference kmers against kmers of the reverse-complemented reference.
+    ReferenceReference,
+    /// Reference kmers against kmers of the reverse-complemented query.
+    ReferenceQuery,
+    /// Query kmers against kmers of the reverse-complemented reference.
+    QueryReference,
+    /// Query kmers against kmers of the reverse-complemented query.
+    QueryQuery,
+}
+
+impl Quadrant {
+    /// All four quadrants in storage order.
+    pub const ALL: [Self; 4] = [
+        Self::ReferenceReference,
+        Self::ReferenceQuery,
+        Self::QueryReference,
+        Self::QueryQuery,
+    ];
+
+    /// Returns `true` if the primary index of this quadrant refers to the reference.
+    pub fn primary_is_reference(&self) -> bool {
+        matches!(self, Self::ReferenceReference | Self::ReferenceQuery)
+    }
+
+    /// Returns `true` if the secondary rc index of this quadrant refers to the reference.
+    pub fn secondary_is_reference(&self) -> bool {
+        matches!(self, Self::ReferenceReference | Self::QueryReference)
+    }
+
+    /// Returns `true` if the primary index and the secondary rc index of this quadrant refer to the same genome.
+    pub fn is_self_comparison(&self) -> bool {
+        self.primary_is_reference() == self.secondary_is_reference()
+    }
+
+    /// Returns the quadrant with the roles of the two genomes swapped.
+    ///
+    /// A match `(primary_index, secondary_rc_index)` in this quadrant corresponds to the match
+    /// `(secondary_kmer_count - 1 - secondary_rc_index, primary_kmer_count - 1 - primary_index)` in the transposed quadrant,
+    /// since a kmer matches a reverse-complemented kmer exactly if their reverse complements match.
+    pub fn transposed(&self) -> Self {
+        match self {
+            Self::ReferenceReference => Self::ReferenceReference,
+            Self::ReferenceQuery => Self::QueryReference,
+            Self::QueryReference => Self::ReferenceQuery,
+            Self::QueryQuery => Self::QueryQuery,
+        }
+    }
+
+    /// Returns the number of primary and secondary kmers of this quadrant, given the number of kmers in the reference and the query.
+    pub(crate) fn dimensions(
+        &self,
+        reference_kmer_count: usize,
+        query_kmer_count: usize,
+    ) -> (usize, usize) {
+        let primary_kmer_count = if self.primary_is_reference() {
+            reference_kmer_count
+        } else {
+            query_kmer_count
+        };
+        let secondary_kmer_count = if self.secondary_is_reference() {
+            reference_kmer_count
+        } else {
+            query_kmer_count
+        };
+        (primary_kmer_count, secondary_kmer_count)
+    }
+
+    /// Returns the bit of this quadrant in [`Quadrants`].
+    fn bit(&self) -> u8 {
+        1 << *self as u8
+    }
+}
+
+impl Display for Quadrant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ReferenceReference => write!(f, "reference_reference"),
+            Self::ReferenceQuery => write!(f, "reference_query"),
+            Self::QueryReference => write!(f, "query_reference"),
+            Self::QueryQuery => write!(f, "query_query"),
+        }
+    }
+}
+
+/// A set of quadrants, combined with `|`.
+///
+/// # Example
+///
+/// ```rust
+/// use template_switch_error_free_inners::{Quadrant, Quadrants};
+///
+/// let cross = Quadrants::REFERENCE_QUERY | Quadrants::QUERY_REFERENCE;
+/// assert!(cross.contains(Quadrant::ReferenceQuery));
+/// assert!(!cross.contains(Quadrant::ReferenceReference));
+/// assert_eq!(cross.iter().collect::<Vec<_>>(), vec![Quadrant::ReferenceQuery, Quadrant::QueryReference]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Quadrants(u8);
+
+impl Quadrants {
+    /// No quadrants.
+    pub const NONE: Self = Self(0);
+    /// Only [`Quadrant::ReferenceReference`].
+    pub const REFERENCE_REFERENCE: Self = Self(1 << Quadrant::ReferenceReference as u8);
+    /// Only [`Quadrant::ReferenceQuery`].
+    pub const REFERENCE_QUERY: Self = Self(1 << Quadrant::ReferenceQuery as u8);
+    /// Only [`Quadrant::QueryReference`].
+    pub const QUERY_REFERENCE: Self = Self(1 << Quadrant::QueryReference as u8);
+    /// Only [`Quadrant::QueryQuery`].
+    pub const QUERY_QUERY: Self = Self(1 << Quadrant::QueryQuery as u8);
+    /// All four quadrants.
+    pub const ALL: Self = Self(0b1111);
+
+    /// Returns `true` if the set contains the given quadrant.
+    pub fn contains(&self, quadrant: Quadrant) -> bool {
+        self.0 & quadrant.bit() != 0
+    }
+
+    /// Returns `true` if the set contains no quadrant.
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns an iterator over the quadrants of the set in storage order.
+    pub fn iter(&self) -> impl Iterator<Item = Quadrant> + use<> {
+        let quadrants = *self;
+        Quadrant::ALL
+            .into_iter()
+            .filter(move |&quadrant| quadrants.contains(quadrant))
+    }
+
+    /// Returns the set as bits, where bit `i` is set if the set contains the `i`-th quadrant of [`Quadrant::ALL`].
+    pub fn bits(&self) -> u8 {
+        self.0
+    }
+
+    /// Returns the set with the given bits, or `None` if bits other than the lowest four are set.
+    pub fn from_bits(bits: u8) -> Option<Self> {
+        (bits & !Self::ALL.0 == 0).then_some(Self(bits))
+    }
+}
+
+impl Default for Quadrants {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl From<Quadrant> for Quadrants {
+    fn from(quadrant: Quadrant) -> Self {
+        Self(quadrant.bit())
+    }
+}
+
+impl FromIterator<Quadrant> for Quadrants {
+    fn from_iter<T: IntoIterator<Item = Quadrant>>(iter: T) -> Self {
+        iter.into_iter().fold(Self::NONE, |quadrants, quadrant| {
+            quadrants | quadrant.into()
+        })
+    }
+}
+
+impl BitOr for Quadrants {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for Quadrants {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitAnd for Quadrants {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        Self(self.0 & rhs.0)
+    }
+}
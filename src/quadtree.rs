@@ -0,0 +1,268 @@
+//! A hierarchical summary of the matches of a quadrant for counting queries over rectangles at multiple zoom levels.
+
+use std::ops::Range;
+
+use crate::{MatchTable, Quadrant, StorageIndex};
+
+/// A quadtree over the matches of a quadrant that counts the matches in rectangles of kmer pairs, see [`MatchTable::quadtree`].
+///
+/// The quadrant is padded to a square whose side is a power of two, and split recursively into four equal cells.
+/// A cell at level `l` covers `2^l` primary kmers and `2^l` secondary rc kmers, so level zero consists of the single pairs of kmers,
+/// and the single cell at the top level [`height`](Self::height) covers the whole quadrant.
+/// The tree stores the number of matches of each non-empty cell above level zero, ordered by its position,
+/// and looks up the cells of level zero in the match table.
+/// So it requires memory linear in the number of non-empty cells, which is at most the number of matches per level.
+///
+/// A viewer renders a zoom level from its [`cells`](Self::cells),
+/// and asks whether a rectangle contains a match with [`has_match_in`](Self::has_match_in) and how many with [`match_count_in`](Self::match_count_in).
+/// These queries descend from the top level and stop at cells that are empty or that lie completely in the rectangle,
+/// so a rectangle aligned to the cells of a level is answered by visiting `O(log n)` cells,
+/// and an arbitrary rectangle by visiting `O(log n)` cells per cell along its border.
+pub struct MatchQuadtree<'table, Index: StorageIndex = u32> {
+    table: &'table MatchTable<Index>,
+    quadrant: Quadrant,
+    height: usize,
+    /// The non-empty cells of each level from level one as `((primary_cell, secondary_rc_cell), match_count)` pairs,
+    /// ordered by position.
+    levels: Vec<Vec<((usize, usize), usize)>>,
+}
+
+impl<'table, Index: StorageIndex> MatchQuadtree<'table, Index> {
+    fn new(table: &'table MatchTable<Index>, quadrant: Quadrant) -> Self {
+        let side = table
+            .primary_kmer_count(quadrant)
+            .max(table.secondary_kmer_count(quadrant));
+        let height = side.next_power_of_two().trailing_zeros() as usize;
+
+        let mut levels: Vec<Vec<((usize, usize), usize)>> = Vec::with_capacity(height);
+        for level in 1..=height {
+            let mut cells: Vec<_> = if level == 1 {
+                table
+                    .matches(quadrant)
+                    .map(|(primary_index, secondary_rc_index)| {
+                        ((primary_index >> 1, secondary_rc_index >> 1), 1)
+                    })
+                    .collect()
+            } else {
+                levels[level - 2]
+                    .iter()
+                    .map(|&((primary_cell, secondary_rc_cell), count)| {
+                        ((primary_cell >> 1, secondary_rc_cell >> 1), count)
+                    })
+                    .collect()
+            };
+            cells.sort_unstable_by_key(|&(position, _)| position);
+            cells.dedup_by(|(position, count), (kept_position, kept_count)| {
+                if position == kept_position {
+                    *kept_count += *count;
+                    true
+                } else {
+                    false
+                }
+            });
+            cells.shrink_to_fit();
+            levels.push(cells);
+        }
+
+        Self {
+            table,
+            quadrant,
+            height,
+            levels,
+        }
+    }
+
+    /// Returns the quadrant of this quadtree.
+    pub fn quadrant(&self) -> Quadrant {
+        self.quadrant
+    }
+
+    /// Returns the top level of this quadtree, whose single cell covers the whole quadrant.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the number of matches of the quadrant.
+    pub fn match_count(&self) -> usize {
+        self.cell_match_count(self.height, 0, 0)
+    }
+
+    /// Returns an iterator over the non-empty cells of the given level as `(primary_cell, secondary_rc_cell, match_count)` triples,
+    /// ordered by primary cell and then by secondary rc cell.
+    ///
+    /// The cell `(i, j)` covers the primary kmers `i * 2^level..(i + 1) * 2^level` and the secondary rc kmers `j * 2^level..(j + 1) * 2^level`.
+    /// The cells of level zero are the [matches](MatchTable::matches) of the quadrant.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the level is greater than the [`height`](Self::height).
+    pub fn cells(&self, level: usize) -> impl Iterator<Item = (usize, usize, usize)> + '_ {
+        assert!(
+            level <= self.height,
+            "the level {level} exceeds the height {} of the quadtree",
+            self.height
+        );
+        let (matches, cells) = if level == 0 {
+            (Some(self.table.matches(self.quadrant)), None)
+        } else {
+            (None, Some(&self.levels[level - 1]))
+        };
+        matches
+            .into_iter()
+            .flatten()
+            .map(|(primary_index, secondary_rc_index)| (primary_index, secondary_rc_index, 1))
+            .chain(cells.into_iter().flatten().map(
+                |&((primary_cell, secondary_rc_cell), count)| {
+                    (primary_cell, secondary_rc_cell, count)
+                },
+            ))
+    }
+
+    /// Returns the number of matches whose primary index lies in `primary_indices` and whose secondary rc index lies in `secondary_rc_indices`.
+    pub fn match_count_in(
+        &self,
+        primary_indices: Range<usize>,
+        secondary_rc_indices: Range<usize>,
+    ) -> usize {
+        self.count_in(
+            self.height,
+            0,
+            0,
+            &primary_indices,
+            &secondary_rc_indices,
+            usize::MAX,
+        )
+    }
+
+    /// Returns `true` if a match has its primary index in `primary_indices` and its secondary rc index in `secondary_rc_indices`.
+    pub fn has_match_in(
+        &self,
+        primary_indices: Range<usize>,
+        secondary_rc_indices: Range<usize>,
+    ) -> bool {
+        self.count_in(
+            self.height,
+            0,
+            0,
+            &primary_indices,
+            &secondary_rc_indices,
+            1,
+        ) > 0
+    }
+
+    /// Returns the size of the quadtree in bytes, excluding the storage of the quadrant.
+    pub fn heap_bytes(&self) -> usize {
+        self.levels
+            .iter()
+            .map(|cells| cells.capacity() * size_of::<((usize, usize), usize)>())
+            .sum::<usize>()
+            + self.levels.capacity() * size_of::<Vec<((usize, usize), usize)>>()
+    }
+
+    /// Returns the number of matches of the given cell.
+    fn cell_match_count(
+        &self,
+        level: usize,
+        primary_cell: usize,
+        secondary_rc_cell: usize,
+    ) -> usize {
+        if level == 0 {
+            return usize::from(
+                primary_cell < self.table.primary_kmer_count(self.quadrant)
+                    && secondary_rc_cell < self.table.secondary_kmer_count(self.quadrant)
+                    && self
+                        .table
+                        .has_match(self.quadrant, primary_cell, secondary_rc_cell),
+            );
+        }
+        let cells = &self.levels[level - 1];
+        cells
+            .binary_search_by_key(&(primary_cell, secondary_rc_cell), |&(position, _)| {
+                position
+            })
+            .map_or(0, |index| cells[index].1)
+    }
+
+    /// Returns the number of matches of the given cell within the rectangle,
+    /// stopping once at least `limit` matches are found.
+    fn count_in(
+        &self,
+        level: usize,
+        primary_cell: usize,
+        secondary_rc_cell: usize,
+        primary_indices: &Range<usize>,
+        secondary_rc_indices: &Range<usize>,
+        limit: usize,
+    ) -> usize {
+        let primary_cell_indices = primary_cell << level..(primary_cell + 1) << level;
+        let secondary_rc_cell_indices =
+            secondary_rc_cell << level..(secondary_rc_cell + 1) << level;
+        if primary_cell_indices.start >= primary_indices.end
+            || primary_cell_indices.end <= primary_indices.start
+            || secondary_rc_cell_indices.start >= secondary_rc_indices.end
+            || secondary_rc_cell_indices.end <= secondary_rc_indices.start
+        {
+            return 0;
+        }
+
+        let count = self.cell_match_count(level, primary_cell, secondary_rc_cell);
+        if count == 0
+            || (primary_indices.start <= primary_cell_indices.start
+                && primary_cell_indices.end <= primary_indices.end
+                && secondary_rc_indices.start <= secondary_rc_cell_indices.start
+                && secondary_rc_cell_indices.end <= secondary_rc_indices.end)
+        {
+            return count;
+        }
+
+        let mut count = 0;
+        for (primary_child, secondary_rc_child) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+            count += self.count_in(
+                level - 1,
+                primary_cell * 2 + primary_child,
+                secondary_rc_cell * 2 + secondary_rc_child,
+                primary_indices,
+                secondary_rc_indices,
+                limit - count,
+            );
+            if count >= limit {
+                break;
+            }
+        }
+        count
+    }
+}
+
+impl<Index: StorageIndex> MatchTable<Index> {
+    /// Build a [`MatchQuadtree`] over the matches of the given quadrant.
+    ///
+    /// Building takes time `O(m log m)` per level for `m` matches.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTable, Quadrant};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"TTACGGATTT").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"GGTCCGTGG").unwrap();
+    /// let matches = MatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    ///
+    /// // The matches are (2, 2) and (3, 3).
+    /// let quadtree = matches.quadtree(Quadrant::ReferenceQuery);
+    /// assert_eq!(quadtree.height(), 3);
+    /// assert_eq!(quadtree.match_count(), 2);
+    /// assert_eq!(quadtree.cells(1).collect::<Vec<_>>(), vec![(1, 1, 2)]);
+    /// assert_eq!(quadtree.match_count_in(0..3, 0..6), 1);
+    /// assert!(!quadtree.has_match_in(4..7, 0..6));
+    /// ```
+    pub fn quadtree(&self, quadrant: Quadrant) -> MatchQuadtree<'_, Index> {
+        MatchQuadtree::new(self, quadrant)
+    }
+}
@@ -0,0 +1,79 @@
+//! Rank queries over bitvectors.
+
+use bitvec::{order::Lsb0, slice::BitSlice, store::BitStore};
+
+/// The number of bits per block of the rank index.
+///
+/// A rank query counts the ones of at most one block, i.e. `BLOCK_SIZE / 64` words.
+const BLOCK_SIZE: usize = 1024;
+
+/// The number of ones before each block of a bitvector, answering rank queries in constant time.
+///
+/// Requires one `u64` per [`BLOCK_SIZE`] bits.
+pub(crate) struct RankIndex {
+    block_ranks: Vec<u64>,
+}
+
+impl RankIndex {
+    pub fn new<Store: BitStore>(bits: &BitSlice<Store, Lsb0>) -> Self {
+        let mut block_ranks = Vec::with_capacity(bits.len().div_ceil(BLOCK_SIZE) + 1);
+        let mut rank = 0;
+        block_ranks.push(rank);
+        for block in bits.chunks(BLOCK_SIZE) {
+            rank += block.count_ones() as u64;
+            block_ranks.push(rank);
+        }
+        Self { block_ranks }
+    }
+
+    /// Returns the number of ones in `bits[..index]`.
+    ///
+    /// `bits` must be the bitvector this index was built from.
+    pub fn rank<Store: BitStore>(&self, bits: &BitSlice<Store, Lsb0>, index: usize) -> usize {
+        let block = index / BLOCK_SIZE;
+        self.block_ranks[block] as usize + bits[block * BLOCK_SIZE..index].count_ones()
+    }
+
+    /// Returns the index of the one with the given rank, i.e. the index `i` with `bits[i]` and `rank(bits, i) == rank`,
+    /// or `None` if there are at most `rank` ones.
+    ///
+    /// The block of the one is found by binary search, and at most one block is scanned.
+    /// `bits` must be the bitvector this index was built from.
+    pub fn select<Store: BitStore>(
+        &self,
+        bits: &BitSlice<Store, Lsb0>,
+        rank: usize,
+    ) -> Option<usize> {
+        if rank >= self.total_ones() {
+            return None;
+        }
+        let block = self
+            .block_ranks
+            .partition_point(|&block_rank| block_rank as usize <= rank)
+            - 1;
+        let block_start = block * BLOCK_SIZE;
+        let offset = bits[block_start..]
+            .iter_ones()
+            .nth(rank - self.block_ranks[block] as usize)?;
+        Some(block_start + offset)
+    }
+
+    /// Returns the number of ones in the bitvector this index was built from.
+    pub fn total_ones(&self) -> usize {
+        self.block_ranks.last().copied().unwrap_or(0) as usize
+    }
+
+    /// Returns the size of the index in bytes.
+    pub fn heap_bytes(&self) -> usize {
+        self.block_ranks.capacity() * size_of::<u64>()
+    }
+
+    /// Returns the number of ones in `bits[range]`.
+    pub fn count_ones<Store: BitStore>(
+        &self,
+        bits: &BitSlice<Store, Lsb0>,
+        range: std::ops::Range<usize>,
+    ) -> usize {
+        self.rank(bits, range.end) - self.rank(bits, range.start)
+    }
+}
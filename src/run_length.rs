@@ -0,0 +1,175 @@
+//! A run-length encoded representation of match tables, storing the maximal runs of matches along the diagonals of the quadrants.
+
+use crate::{Inner, MatchTable, Quadrant, StorageIndex};
+
+/// A match table that stores each maximal error-free inner as a single run instead of one bit or index per match,
+/// see [`MatchTable::to_run_length`].
+///
+/// An inner of length `l` consists of `l - k + 1` consecutive matches along a diagonal of its quadrant,
+/// i.e. along an anti-diagonal on the forward strand of the secondary.
+/// This representation stores its start and length instead,
+/// so memory scales with the number of maximal inners rather than with the number of matches or pairs of kmers.
+/// The runs of each quadrant are ordered by their diagonal and then by their start,
+/// so the run through a pair of kmers is found by a binary search.
+pub struct RunLengthMatchTable {
+    minimum_length: usize,
+    /// The maximal inners of each quadrant as `(primary_start, secondary_rc_start, length)` triples,
+    /// ordered by the diagonal `secondary_rc_start - primary_start` and then by `primary_start`.
+    runs: [Vec<(usize, usize, usize)>; 4],
+    /// The number of matches of each quadrant.
+    match_counts: [usize; 4],
+}
+
+impl<Index: StorageIndex> MatchTable<Index> {
+    /// Convert this table into a [`RunLengthMatchTable`] that stores the [maximal matches](Self::maximal_matches) of each quadrant.
+    ///
+    /// The runs are not cut at the [maximum length](Self::maximum_length), so the run-length table contains all matches of this table.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTable, Quadrant};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"TTACGGATTT").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"GGTCCGTGG").unwrap();
+    /// let matches = MatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    ///
+    /// let runs = matches.to_run_length();
+    /// assert_eq!(runs.run_count(Quadrant::ReferenceQuery), 1);
+    /// assert_eq!(runs.match_count(Quadrant::ReferenceQuery), 2);
+    /// assert!(runs.has_match(Quadrant::ReferenceQuery, 3, 3));
+    ///
+    /// // The match at `(3, 3)` is the second kmer of the inner `ACGGA` at 2..7 of the reference.
+    /// let inner = runs.longest_inner_through(Quadrant::ReferenceQuery, 3, 3).unwrap();
+    /// assert_eq!(inner.primary, 2..7);
+    /// assert_eq!(inner.secondary_rc, 2..7);
+    /// ```
+    pub fn to_run_length(&self) -> RunLengthMatchTable {
+        let runs = Quadrant::ALL.map(|quadrant| {
+            let mut runs: Vec<_> = self.diagonal_runs(quadrant, usize::MAX).collect();
+            runs.sort_unstable_by_key(|&(primary_start, secondary_rc_start, _)| {
+                (
+                    secondary_rc_start as isize - primary_start as isize,
+                    primary_start,
+                )
+            });
+            runs.shrink_to_fit();
+            runs
+        });
+        let match_counts = runs.each_ref().map(|runs| {
+            runs.iter()
+                .map(|&(_, _, length)| length - self.minimum_length + 1)
+                .sum()
+        });
+        RunLengthMatchTable {
+            minimum_length: self.minimum_length,
+            runs,
+            match_counts,
+        }
+    }
+}
+
+impl RunLengthMatchTable {
+    /// Returns the minimum length of the inners, i.e. the length of the kmers.
+    pub fn minimum_length(&self) -> usize {
+        self.minimum_length
+    }
+
+    /// Returns the number of maximal inners of the given quadrant.
+    pub fn run_count(&self, quadrant: Quadrant) -> usize {
+        self.runs[quadrant as usize].len()
+    }
+
+    /// Returns the number of matches of the given quadrant, i.e. the number of pairs of matching kmers covered by the runs.
+    pub fn match_count(&self, quadrant: Quadrant) -> usize {
+        self.match_counts[quadrant as usize]
+    }
+
+    /// Returns an iterator over the maximal inners of the given quadrant as `(primary_start, secondary_rc_start, length)` triples,
+    /// like [`MatchTable::maximal_matches`].
+    ///
+    /// The inners are ordered by their diagonal `secondary_rc_start - primary_start` and then by their start.
+    pub fn runs(&self, quadrant: Quadrant) -> impl Iterator<Item = (usize, usize, usize)> + '_ {
+        self.runs[quadrant as usize].iter().copied()
+    }
+
+    /// Returns an iterator over the matches of the given quadrant as `(primary_index, secondary_rc_index)` pairs.
+    ///
+    /// The matches are ordered like their [`runs`](Self::runs), so unlike those of [`MatchTable::matches`], they are ordered by diagonal.
+    pub fn matches(&self, quadrant: Quadrant) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let minimum_length = self.minimum_length;
+        self.runs(quadrant)
+            .flat_map(move |(primary_start, secondary_rc_start, length)| {
+                (0..=length - minimum_length)
+                    .map(move |offset| (primary_start + offset, secondary_rc_start + offset))
+            })
+    }
+
+    /// Returns `true` if the primary kmer at `primary_index` matches the kmer of the reverse-complemented secondary at `secondary_rc_index`.
+    pub fn has_match(
+        &self,
+        quadrant: Quadrant,
+        primary_index: usize,
+        secondary_rc_index: usize,
+    ) -> bool {
+        self.run_through(quadrant, primary_index, secondary_rc_index)
+            .is_some()
+    }
+
+    /// Returns the longest error-free inner that contains the match of the given pair of kmers, or `None` if the kmers do not match.
+    ///
+    /// Since the maximal inners along a diagonal do not overlap, this is the maximal inner containing the match,
+    /// which is found by a binary search over the runs of the quadrant.
+    pub fn longest_inner_through(
+        &self,
+        quadrant: Quadrant,
+        primary_index: usize,
+        secondary_rc_index: usize,
+    ) -> Option<Inner> {
+        self.run_through(quadrant, primary_index, secondary_rc_index)
+            .map(|(primary_start, secondary_rc_start, length)| Inner {
+                quadrant,
+                primary: primary_start..primary_start + length,
+                secondary_rc: secondary_rc_start..secondary_rc_start + length,
+            })
+    }
+
+    /// Returns the size of the runs in bytes.
+    pub fn heap_bytes(&self) -> usize {
+        self.runs
+            .iter()
+            .map(|runs| runs.capacity() * size_of::<(usize, usize, usize)>())
+            .sum()
+    }
+
+    /// Returns the run containing the match of the given pair of kmers.
+    fn run_through(
+        &self,
+        quadrant: Quadrant,
+        primary_index: usize,
+        secondary_rc_index: usize,
+    ) -> Option<(usize, usize, usize)> {
+        let runs = &self.runs[quadrant as usize];
+        let key = (
+            secondary_rc_index as isize - primary_index as isize,
+            primary_index,
+        );
+        let next = runs.partition_point(|&(primary_start, secondary_rc_start, _)| {
+            (
+                secondary_rc_start as isize - primary_start as isize,
+                primary_start,
+            ) <= key
+        });
+        let run @ (primary_start, secondary_rc_start, length) = runs[..next].last().copied()?;
+        (secondary_rc_start as isize - primary_start as isize == key.0
+            && primary_index <= primary_start + length - self.minimum_length)
+            .then_some(run)
+    }
+}
@@ -0,0 +1,80 @@
+//! Deterministic subsampling of matches, e.g. for dotplot overviews of repeat-rich genomes.
+
+use crate::{MatchTable, Quadrant, StorageIndex};
+
+impl<Index: StorageIndex> MatchTable<Index> {
+    /// Returns an iterator over a reproducible random subset of the matches of all quadrants,
+    /// as `(quadrant, primary_index, secondary_rc_index)` triples.
+    ///
+    /// Each match is kept with probability `fraction`, decided by a hash of the match and the `seed`,
+    /// so the subset depends only on the matches and the seed, not on the storage or the construction of the table.
+    /// The matches are filtered while iterating, so they are never collected,
+    /// and the subset for a smaller fraction is contained in the subset for a larger fraction with the same seed.
+    /// The matches are ordered by quadrant, and within each quadrant like those of [`matches`](Self::matches).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `fraction` is not within `[0, 1]`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::MatchTable;
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&b"ACGTTGCA".repeat(8)).unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+    /// let matches = MatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    ///
+    /// let sample: Vec<_> = matches.sample_matches(0.1, 42).collect();
+    /// assert!(sample.len() < matches.sample_matches(1.0, 42).count());
+    /// assert_eq!(matches.sample_matches(0.1, 42).collect::<Vec<_>>(), sample);
+    /// assert_eq!(matches.sample_matches(0.0, 42).count(), 0);
+    /// ```
+    pub fn sample_matches(
+        &self,
+        fraction: f64,
+        seed: u64,
+    ) -> impl Iterator<Item = (Quadrant, usize, usize)> + '_ {
+        assert!(
+            (0.0..=1.0).contains(&fraction),
+            "the fraction {fraction} is not within [0, 1]"
+        );
+        Quadrant::ALL.into_iter().flat_map(move |quadrant| {
+            self.matches(quadrant)
+                .filter(move |&(primary_index, secondary_rc_index)| {
+                    sample_probability(seed, quadrant, primary_index, secondary_rc_index) < fraction
+                })
+                .map(move |(primary_index, secondary_rc_index)| {
+                    (quadrant, primary_index, secondary_rc_index)
+                })
+        })
+    }
+}
+
+/// Returns a number in `[0, 1)` that is uniformly distributed over the matches, derived from the match and the seed.
+fn sample_probability(
+    seed: u64,
+    quadrant: Quadrant,
+    primary_index: usize,
+    secondary_rc_index: usize,
+) -> f64 {
+    let mut hash = mix(seed ^ quadrant as u64);
+    hash = mix(hash ^ primary_index as u64);
+    hash = mix(hash ^ secondary_rc_index as u64);
+    (hash >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// The finalizer of the SplitMix64 pseudorandom number generator.
+fn mix(value: u64) -> u64 {
+    let mut value = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    value ^ (value >> 31)
+}
@@ -0,0 +1,254 @@
+//! Screening of a long reference for windows that are dense in matches against a query.
+
+use std::{marker::PhantomData, ops::Range};
+
+use compact_genome::interface::{alphabet::Alphabet, sequence::GenomeSequence};
+use log::debug;
+
+use crate::{MatchTable, MatchTableBuilder, MatchTableError, Quadrant};
+
+/// Slides a window along a long reference and computes a match table of each window against a fixed query,
+/// keeping only the windows whose match density exceeds a threshold.
+///
+/// This is meant as a screening step that narrows a long reference down to the regions worth a full analysis.
+/// The windows have the configured size and start every `stride` characters.
+/// If the last such window does not reach the end of the reference, a final window is aligned to its end,
+/// so all windows have the same size, unless the reference is shorter than a window, in which case the whole reference is the only window.
+///
+/// Each window is built with the options of the given [`MatchTableBuilder`] as if it were the whole reference.
+/// The reference mask and the excluded reference intervals are restricted to the window,
+/// and a [band](MatchTableBuilder::band) applies to the primary indices within the window.
+/// To screen only for matches between the reference and the query,
+/// restrict the [quadrants](MatchTableBuilder::quadrants) to [`Quadrants::REFERENCE_QUERY`](crate::Quadrants::REFERENCE_QUERY)
+/// and [`Quadrants::QUERY_REFERENCE`](crate::Quadrants::QUERY_REFERENCE).
+///
+/// # Example
+///
+/// ```rust
+/// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+/// use compact_genome::implementation::vec_sequence::VectorGenome;
+/// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+/// use template_switch_error_free_inners::{MatchTableBuilder, Quadrants, WindowScanner};
+///
+/// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AAAAAAAAAAGGTCCGTGGAAAAAAAAAA").unwrap();
+/// let query = VectorGenome::from_slice_u8(b"TTACGGATTT").unwrap();
+/// let options = MatchTableBuilder::new(4).quadrants(Quadrants::REFERENCE_QUERY);
+///
+/// let windows: Vec<_> = WindowScanner::new(options, 10)
+///     .stride(5)
+///     .scan(reference.as_genome_subsequence(), query.as_genome_subsequence())
+///     .map(Result::unwrap)
+///     .collect();
+/// assert_eq!(
+///     windows.iter().map(|window| window.reference_range.clone()).collect::<Vec<_>>(),
+///     vec![10..20],
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct WindowScanner {
+    options: MatchTableBuilder,
+    window_size: usize,
+    stride: usize,
+    min_density: f64,
+}
+
+/// A window of the reference whose match density exceeds the threshold of a [`WindowScanner`].
+pub struct ScannedWindow {
+    /// The range of the reference covered by the window.
+    pub reference_range: Range<usize>,
+    /// The number of matches divided by the number of kmer pairs, summed over the computed quadrants.
+    pub density: f64,
+    /// The match table of the window against the query.
+    ///
+    /// Its reference indices are relative to the start of the window.
+    pub table: MatchTable,
+}
+
+impl WindowScanner {
+    /// Create a scanner with the given construction options and window size.
+    ///
+    /// By default, the windows do not overlap, i.e. the stride equals the window size,
+    /// and all windows with at least one match are yielded.
+    pub fn new(options: MatchTableBuilder, window_size: usize) -> Self {
+        Self {
+            options,
+            window_size,
+            stride: window_size,
+            min_density: 0.0,
+        }
+    }
+
+    /// Set the distance between the starts of consecutive windows.
+    ///
+    /// A stride smaller than the window size makes the windows overlap, so that no dense region is split unfavourably.
+    pub fn stride(mut self, stride: usize) -> Self {
+        self.stride = stride;
+        self
+    }
+
+    /// Yield only windows whose match density is strictly greater than `min_density`.
+    pub fn min_density(mut self, min_density: f64) -> Self {
+        self.min_density = min_density;
+        self
+    }
+
+    /// Scan the reference for windows that are dense in matches against the query.
+    ///
+    /// The windows are computed lazily and yielded in order of their start.
+    /// An error of the construction of a window is yielded in its place.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`try_scan`](Self::try_scan) returns an error.
+    pub fn scan<
+        'sequence,
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        &self,
+        reference: &'sequence GenomeSubsequence,
+        query: &'sequence GenomeSubsequence,
+    ) -> ScannedWindows<'sequence, AlphabetType, GenomeSubsequence> {
+        self.try_scan(reference, query)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Scan the reference for windows that are dense in matches against the query.
+    ///
+    /// Returns an error if the window size is smaller than the minimum length, if the stride is zero,
+    /// or under the same conditions as [`MatchTableBuilder::try_build`] for the whole reference.
+    /// See [`scan`](Self::scan) for details.
+    pub fn try_scan<
+        'sequence,
+        AlphabetType: Alphabet,
+        GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+    >(
+        &self,
+        reference: &'sequence GenomeSubsequence,
+        query: &'sequence GenomeSubsequence,
+    ) -> Result<ScannedWindows<'sequence, AlphabetType, GenomeSubsequence>, MatchTableError> {
+        if self.window_size < self.options.minimum_length || self.stride == 0 {
+            return Err(MatchTableError::InvalidWindow {
+                window_size: self.window_size,
+                stride: self.stride,
+                minimum_length: self.options.minimum_length,
+            });
+        }
+        self.options.kmer_counts(reference.len(), query.len())?;
+
+        Ok(ScannedWindows {
+            scanner: self.clone(),
+            reference,
+            query,
+            next_start: Some(0),
+            phantom_data: PhantomData,
+        })
+    }
+
+    /// Returns the options for building the table of the given window of the reference.
+    fn window_options(&self, window: &Range<usize>) -> MatchTableBuilder {
+        let mut options = self.options.clone();
+        options.reference_mask = options
+            .reference_mask
+            .map(|mask| mask[window.clone()].to_bitvec());
+        options.reference_excluded_intervals = options
+            .reference_excluded_intervals
+            .iter()
+            .map(|interval| interval.start.max(window.start)..interval.end.min(window.end))
+            .filter(|interval| !interval.is_empty())
+            .map(|interval| interval.start - window.start..interval.end - window.start)
+            .collect();
+        options
+    }
+}
+
+/// The iterator over the dense windows of a reference, see [`WindowScanner::scan`].
+pub struct ScannedWindows<'sequence, AlphabetType, GenomeSubsequence: ?Sized> {
+    scanner: WindowScanner,
+    reference: &'sequence GenomeSubsequence,
+    query: &'sequence GenomeSubsequence,
+    /// The start of the next window, or `None` if the last window was scanned.
+    next_start: Option<usize>,
+    phantom_data: PhantomData<AlphabetType>,
+}
+
+impl<
+    AlphabetType: Alphabet,
+    GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+> ScannedWindows<'_, AlphabetType, GenomeSubsequence>
+{
+    /// Returns the next window to scan and advances past it.
+    fn next_window(&mut self) -> Option<Range<usize>> {
+        let start = self.next_start?;
+        let WindowScanner {
+            window_size,
+            stride,
+            ..
+        } = self.scanner;
+        let length = self.reference.len();
+
+        let window = if start + window_size >= length {
+            length.saturating_sub(window_size)..length
+        } else {
+            start..start + window_size
+        };
+        self.next_start = (window.end < length).then(|| start + stride);
+        Some(window)
+    }
+}
+
+impl<
+    AlphabetType: Alphabet,
+    GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+> Iterator for ScannedWindows<'_, AlphabetType, GenomeSubsequence>
+{
+    type Item = Result<ScannedWindow, MatchTableError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(window) = self.next_window() {
+            let table = match self
+                .scanner
+                .window_options(&window)
+                .try_build(&self.reference[window.clone()], self.query)
+            {
+                Ok(table) => table,
+                Err(error) => return Some(Err(error)),
+            };
+
+            let density = density(&table);
+            debug!(
+                "Window {}..{} has match density {density}",
+                window.start, window.end
+            );
+            if density > self.scanner.min_density {
+                return Some(Ok(ScannedWindow {
+                    reference_range: window,
+                    density,
+                    table,
+                }));
+            }
+        }
+        None
+    }
+}
+
+/// Returns the number of matches divided by the number of kmer pairs of the computed quadrants of the table,
+/// or zero if there are no such pairs.
+fn density(table: &MatchTable) -> f64 {
+    let (match_count, pair_count) = Quadrant::ALL
+        .into_iter()
+        .filter(|&quadrant| table.quadrants.contains(quadrant))
+        .fold((0.0, 0.0), |(match_count, pair_count), quadrant| {
+            (
+                match_count + table.quadrant(quadrant).match_count() as f64,
+                pair_count
+                    + table.primary_kmer_count(quadrant) as f64
+                        * table.secondary_kmer_count(quadrant) as f64,
+            )
+        });
+    if pair_count > 0.0 {
+        match_count / pair_count
+    } else {
+        0.0
+    }
+}
@@ -0,0 +1,230 @@
+//! Scoring and ranking of inners.
+
+use std::ops::Range;
+
+use crate::{MatchChain, MatchTable, Quadrant, StorageIndex};
+
+/// An inner to be scored, i.e. a region of the primary that is similar to the reverse complement of a region of the secondary.
+///
+/// Inners are obtained from [`MatchTable::maximal_inners`] or from [chains](MatchTable::chains).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Inner {
+    /// The quadrant of the inner.
+    pub quadrant: Quadrant,
+    /// The interval of the inner on the forward strand of the primary.
+    pub primary: Range<usize>,
+    /// The interval of the template of the inner on the reverse complement of the secondary.
+    pub secondary_rc: Range<usize>,
+}
+
+impl From<&MatchChain> for Inner {
+    fn from(chain: &MatchChain) -> Self {
+        Self {
+            quadrant: chain.quadrant,
+            primary: chain.primary.clone(),
+            secondary_rc: chain.secondary_rc.clone(),
+        }
+    }
+}
+
+/// An inner together with its score, see [`MatchTable::rank_inners`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoredInner {
+    /// The inner.
+    pub inner: Inner,
+    /// The score of the inner.
+    pub score: f64,
+}
+
+/// A score of inners, where higher scores are better.
+pub trait Score<Index: StorageIndex = u32> {
+    /// Returns the score of the given inner of the table.
+    ///
+    /// The `characters` are the ASCII characters of the inner in the primary, i.e. those at `inner.primary`.
+    fn score(&self, table: &MatchTable<Index>, inner: &Inner, characters: &[u8]) -> f64;
+}
+
+/// The default [`Score`], a weighted sum of the length, the kmer uniqueness and the GC balance of an inner.
+///
+/// The kmer uniqueness is the average of `1 / n` over the kmers of the inner in the primary that match `n > 0` kmers of the secondary,
+/// and zero if no kmer matches.
+/// It is one if each kmer of the inner matches only its template, and drops for inners in repeats, which have many possible templates.
+/// The GC balance is `1 - 2 * |gc - 0.5|`, where `gc` is the fraction of `C` and `G` among the characters of the inner.
+/// It is one for a balanced inner and zero for an inner of only `A` and `T` or only `C` and `G`,
+/// whose matches are more likely to be spurious.
+///
+/// # Example
+///
+/// ```rust
+/// use template_switch_error_free_inners::DefaultScore;
+///
+/// let score = DefaultScore::new()
+///     .length_weight(2.0)
+///     .uniqueness_weight(50.0)
+///     .gc_balance_weight(0.0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct DefaultScore {
+    length_weight: f64,
+    uniqueness_weight: f64,
+    gc_balance_weight: f64,
+}
+
+impl Default for DefaultScore {
+    fn default() -> Self {
+        Self {
+            length_weight: 1.0,
+            uniqueness_weight: 20.0,
+            gc_balance_weight: 10.0,
+        }
+    }
+}
+
+impl DefaultScore {
+    /// Create the default score, with a weight of 1 for the length, 20 for the kmer uniqueness and 10 for the GC balance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the weight of the length of the inner.
+    pub fn length_weight(mut self, length_weight: f64) -> Self {
+        self.length_weight = length_weight;
+        self
+    }
+
+    /// Set the weight of the kmer uniqueness of the inner.
+    pub fn uniqueness_weight(mut self, uniqueness_weight: f64) -> Self {
+        self.uniqueness_weight = uniqueness_weight;
+        self
+    }
+
+    /// Set the weight of the GC balance of the inner.
+    pub fn gc_balance_weight(mut self, gc_balance_weight: f64) -> Self {
+        self.gc_balance_weight = gc_balance_weight;
+        self
+    }
+}
+
+impl<Index: StorageIndex> Score<Index> for DefaultScore {
+    fn score(&self, table: &MatchTable<Index>, inner: &Inner, characters: &[u8]) -> f64 {
+        let (uniqueness_sum, matching_kmer_count) = inner
+            .primary
+            .clone()
+            .take(inner.primary.len().saturating_sub(table.minimum_length - 1))
+            .map(|primary_index| table.match_count(inner.quadrant, primary_index))
+            .filter(|&match_count| match_count > 0)
+            .fold((0.0, 0), |(sum, count), match_count| {
+                (sum + 1.0 / match_count as f64, count + 1)
+            });
+        let uniqueness = if matching_kmer_count == 0 {
+            0.0
+        } else {
+            uniqueness_sum / matching_kmer_count as f64
+        };
+
+        let gc_count = characters
+            .iter()
+            .filter(|character| matches!(character.to_ascii_uppercase(), b'C' | b'G'))
+            .count();
+        let gc_balance = if characters.is_empty() {
+            0.0
+        } else {
+            1.0 - 2.0 * (gc_count as f64 / characters.len() as f64 - 0.5).abs()
+        };
+
+        self.length_weight * inner.primary.len() as f64
+            + self.uniqueness_weight * uniqueness
+            + self.gc_balance_weight * gc_balance
+    }
+}
+
+impl<Index: StorageIndex> MatchTable<Index> {
+    /// Returns an iterator over the maximal error-free inners of the given quadrant,
+    /// which are those of [`maximal_matches`](Self::maximal_matches).
+    pub fn maximal_inners(&self, quadrant: Quadrant) -> impl Iterator<Item = Inner> + '_ {
+        self.maximal_matches(quadrant)
+            .map(move |(primary_start, secondary_rc_start, length)| Inner {
+                quadrant,
+                primary: primary_start..primary_start + length,
+                secondary_rc: secondary_rc_start..secondary_rc_start + length,
+            })
+    }
+
+    /// Score the given inners of this table, and return those with at least `min_score` ordered by decreasing score.
+    ///
+    /// The reference and the query are given as ASCII characters, like those returned by [`GenomeSequence::clone_as_vec`](compact_genome::interface::sequence::GenomeSequence::clone_as_vec).
+    /// Inners with equal scores are ordered by their quadrant and then by their start in the primary and the secondary.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an inner exceeds its primary sequence.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{DefaultScore, MatchTable, Quadrant};
+    ///
+    /// // The reverse complement of `ACGGA` is `TCCGT`, and the reverse complement of `TTTT` is `AAAA`.
+    /// let reference_ascii = b"TTACGGATTTT";
+    /// let query_ascii = b"GGTCCGTGGAAAA";
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(reference_ascii).unwrap();
+    /// let query = VectorGenome::from_slice_u8(query_ascii).unwrap();
+    /// let matches = MatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    ///
+    /// let ranked = matches.rank_inners(
+    ///     matches.maximal_inners(Quadrant::ReferenceQuery),
+    ///     reference_ascii,
+    ///     query_ascii,
+    ///     &DefaultScore::new(),
+    ///     0.0,
+    /// );
+    /// assert_eq!(ranked.len(), 2);
+    /// assert_eq!(ranked[0].inner.primary, 2..7);
+    /// assert_eq!(ranked[1].inner.primary, 7..11);
+    /// assert!(ranked[0].score > ranked[1].score);
+    /// ```
+    pub fn rank_inners(
+        &self,
+        inners: impl IntoIterator<Item = Inner>,
+        reference: &[u8],
+        query: &[u8],
+        score: &impl Score<Index>,
+        min_score: f64,
+    ) -> Vec<ScoredInner> {
+        let mut scored: Vec<_> = inners
+            .into_iter()
+            .map(|inner| {
+                let primary = if inner.quadrant.primary_is_reference() {
+                    reference
+                } else {
+                    query
+                };
+                let score = score.score(self, &inner, &primary[inner.primary.clone()]);
+                ScoredInner { inner, score }
+            })
+            .filter(|scored| scored.score >= min_score)
+            .collect();
+        scored.sort_by(|a, b| {
+            b.score.total_cmp(&a.score).then_with(|| {
+                (
+                    a.inner.quadrant,
+                    a.inner.primary.start,
+                    a.inner.secondary_rc.start,
+                )
+                    .cmp(&(
+                        b.inner.quadrant,
+                        b.inner.primary.start,
+                        b.inner.secondary_rc.start,
+                    ))
+            })
+        });
+        scored
+    }
+}
@@ -0,0 +1,119 @@
+//! Conversion of matches into the seeds of the template switch aligner tsalign.
+
+use std::ops::Range;
+
+use crate::{MatchTable, Quadrant, StorageIndex};
+
+/// A seed of a template switch for the template switch aligner tsalign.
+///
+/// tsalign aligns the inner of a template switch to the reverse complement of the secondary,
+/// so like the matches of a [`MatchTable`], seeds index the secondary on its reverse complement.
+/// The primary interval lies on the forward strand of the primary,
+/// and the secondary interval lies on the reverse complement of the secondary,
+/// such that the reverse complement of the secondary interval equals the primary interval character by character.
+/// Both intervals are zero-based, half-open and have the same length.
+///
+/// For tables of [contigs](crate::MatchTableBuilder::build_contigs),
+/// the coordinates refer to the concatenations of the contigs.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TsalignSeed {
+    /// The quadrant of the seed, whose first genome is the template switch primary of tsalign and whose second genome is the template switch secondary.
+    pub quadrant: Quadrant,
+    /// The interval of the seed on the forward strand of the primary.
+    pub primary: Range<usize>,
+    /// The interval of the seed on the reverse complement of the secondary.
+    pub secondary_rc: Range<usize>,
+}
+
+impl TsalignSeed {
+    fn new(
+        quadrant: Quadrant,
+        primary_start: usize,
+        secondary_rc_start: usize,
+        length: usize,
+    ) -> Self {
+        Self {
+            quadrant,
+            primary: primary_start..primary_start + length,
+            secondary_rc: secondary_rc_start..secondary_rc_start + length,
+        }
+    }
+
+    /// Returns the length of the seed.
+    pub fn len(&self) -> usize {
+        self.primary.len()
+    }
+
+    /// Returns `true` if the seed has length zero, which never happens for seeds returned by a [`MatchTable`].
+    pub fn is_empty(&self) -> bool {
+        self.primary.is_empty()
+    }
+
+    /// Returns `"Reference"` or `"Query"`, the name of the template switch primary of the seed in tsalign.
+    pub fn primary_name(&self) -> &'static str {
+        if self.quadrant.primary_is_reference() {
+            "Reference"
+        } else {
+            "Query"
+        }
+    }
+
+    /// Returns `"Reference"` or `"Query"`, the name of the template switch secondary of the seed in tsalign.
+    pub fn secondary_name(&self) -> &'static str {
+        if self.quadrant.secondary_is_reference() {
+            "Reference"
+        } else {
+            "Query"
+        }
+    }
+}
+
+impl<Index: StorageIndex> MatchTable<Index> {
+    /// Returns an iterator over all matches of the given quadrant as tsalign seeds of the minimum length.
+    ///
+    /// The seeds are ordered like the matches returned by [`matches`](Self::matches).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTable, Quadrant, TsalignSeed};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"TTACGGATTT").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"GGTCCGTGG").unwrap();
+    /// let matches = MatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    ///
+    /// // `ACGG` at 2..6 in the reference is the reverse complement of `CCGT` at 3..7 in the query,
+    /// // which lies at 2..6 of the reverse-complemented query `CCACGGACC`.
+    /// let seeds: Vec<_> = matches.tsalign_seeds(Quadrant::ReferenceQuery).collect();
+    /// assert_eq!(seeds[0], TsalignSeed { quadrant: Quadrant::ReferenceQuery, primary: 2..6, secondary_rc: 2..6 });
+    /// assert_eq!((seeds[0].primary_name(), seeds[0].secondary_name()), ("Reference", "Query"));
+    /// ```
+    pub fn tsalign_seeds(&self, quadrant: Quadrant) -> impl Iterator<Item = TsalignSeed> + '_ {
+        let minimum_length = self.minimum_length;
+        self.matches(quadrant)
+            .map(move |(primary_index, secondary_rc_index)| {
+                TsalignSeed::new(quadrant, primary_index, secondary_rc_index, minimum_length)
+            })
+    }
+
+    /// Returns an iterator over all maximal error-free inners of the given quadrant as tsalign seeds.
+    ///
+    /// The seeds are those of [`maximal_matches`](Self::maximal_matches), so they do not overlap along their diagonals
+    /// and are usually far fewer than the seeds of [`tsalign_seeds`](Self::tsalign_seeds).
+    pub fn maximal_tsalign_seeds(
+        &self,
+        quadrant: Quadrant,
+    ) -> impl Iterator<Item = TsalignSeed> + '_ {
+        self.maximal_matches(quadrant)
+            .map(move |(primary_start, secondary_rc_start, length)| {
+                TsalignSeed::new(quadrant, primary_start, secondary_rc_start, length)
+            })
+    }
+}
@@ -0,0 +1,136 @@
+//! Rank and select queries over the matches of a quadrant.
+
+use std::ops::Range;
+
+use crate::{MatchTable, Quadrant, StorageIndex, storage::QuadrantStorage};
+
+/// An index over the matches of a quadrant that answers counting queries in constant time
+/// and finds the `n`-th match and the next match after a position in logarithmic time, see [`MatchTable::rank_select_index`].
+///
+/// The matches are ordered like those of [`MatchTable::matches`], i.e. by primary index and then by secondary rc index.
+/// The index stores the number of matches before each row, one `usize` per primary kmer,
+/// and uses the rank index of the bit-based storage backends or the row offsets of [`StorageBackend::Sparse`](crate::StorageBackend::Sparse) within each row.
+/// Only the rows of [`StorageBackend::Symmetric`](crate::StorageBackend::Symmetric) self-comparison quadrants
+/// are scanned bit by bit within their mirrored half.
+pub struct RankSelectIndex<'table, Index: StorageIndex = u32> {
+    quadrant: Quadrant,
+    storage: &'table QuadrantStorage<Index>,
+    /// The number of matches before each row, with a final entry of the total number of matches.
+    row_offsets: Vec<usize>,
+}
+
+impl<'table, Index: StorageIndex> RankSelectIndex<'table, Index> {
+    fn new(table: &'table MatchTable<Index>, quadrant: Quadrant) -> Self {
+        let storage = table.quadrant(quadrant);
+        let mut row_offsets = Vec::with_capacity(table.primary_kmer_count(quadrant) + 1);
+        let mut offset = 0;
+        row_offsets.push(offset);
+        for primary_index in 0..table.primary_kmer_count(quadrant) {
+            offset += storage.row_match_count(primary_index);
+            row_offsets.push(offset);
+        }
+        Self {
+            quadrant,
+            storage,
+            row_offsets,
+        }
+    }
+
+    /// Returns the quadrant of this index.
+    pub fn quadrant(&self) -> Quadrant {
+        self.quadrant
+    }
+
+    /// Returns the number of matches of the quadrant.
+    pub fn match_count(&self) -> usize {
+        self.row_offsets[self.row_offsets.len() - 1]
+    }
+
+    /// Returns the number of matches whose primary index lies in the given range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start of the range is greater than its end, or its end is greater than the number of primary kmers of the quadrant.
+    pub fn match_count_in_primary_range(&self, primary_indices: Range<usize>) -> usize {
+        assert!(primary_indices.start <= primary_indices.end);
+        self.row_offsets[primary_indices.end] - self.row_offsets[primary_indices.start]
+    }
+
+    /// Returns the number of matches before the given position, i.e. the matches with a smaller primary index,
+    /// or with the same primary index and a smaller secondary rc index.
+    ///
+    /// The position may lie outside of the quadrant, e.g. a primary index equal to the number of primary kmers counts all matches.
+    pub fn rank(&self, primary_index: usize, secondary_rc_index: usize) -> usize {
+        if primary_index >= self.row_offsets.len() - 1 {
+            return self.match_count();
+        }
+        self.row_offsets[primary_index] + self.storage.row_rank(primary_index, secondary_rc_index)
+    }
+
+    /// Returns the `n`-th match (counting from zero) as a `(primary_index, secondary_rc_index)` pair,
+    /// or `None` if there are at most `n` matches.
+    pub fn nth_match(&self, n: usize) -> Option<(usize, usize)> {
+        if n >= self.match_count() {
+            return None;
+        }
+        // The last row starting at or before the match, skipping empty rows.
+        let primary_index = self.row_offsets.partition_point(|&offset| offset <= n) - 1;
+        let secondary_rc_index = self
+            .storage
+            .row_select(primary_index, n - self.row_offsets[primary_index])
+            .unwrap_or_else(|| unreachable!("the row contains the match by its offset"));
+        Some((primary_index, secondary_rc_index))
+    }
+
+    /// Returns the first match at or after the given position as a `(primary_index, secondary_rc_index)` pair,
+    /// or `None` if there is no such match.
+    ///
+    /// To obtain the next match strictly after a match `(i, j)`, pass `(i, j + 1)`.
+    pub fn next_match(
+        &self,
+        primary_index: usize,
+        secondary_rc_index: usize,
+    ) -> Option<(usize, usize)> {
+        self.nth_match(self.rank(primary_index, secondary_rc_index))
+    }
+
+    /// Returns the size of the index in bytes, excluding the storage of the quadrant.
+    pub fn heap_bytes(&self) -> usize {
+        self.row_offsets.capacity() * size_of::<usize>()
+    }
+}
+
+impl<Index: StorageIndex> MatchTable<Index> {
+    /// Build a [`RankSelectIndex`] over the matches of the given quadrant.
+    ///
+    /// Building takes time linear in the number of primary kmers of the quadrant,
+    /// since the number of matches of each row is counted in constant time.
+    /// The index is optional and is built only on demand, since it requires one `usize` per primary kmer.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTable, Quadrant};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+    /// let matches = MatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    ///
+    /// let index = matches.rank_select_index(Quadrant::ReferenceReference);
+    /// assert_eq!(index.match_count(), 2);
+    /// assert_eq!(index.nth_match(1), Some((7, 8)));
+    /// assert_eq!(index.nth_match(2), None);
+    /// assert_eq!(index.next_match(1, 3), Some((7, 8)));
+    /// assert_eq!(index.match_count_in_primary_range(0..5), 1);
+    /// ```
+    pub fn rank_select_index(&self, quadrant: Quadrant) -> RankSelectIndex<'_, Index> {
+        RankSelectIndex::new(self, quadrant)
+    }
+}
@@ -0,0 +1,94 @@
+//! A cheaply cloneable handle for querying a match table from several threads.
+
+use std::{ops::Deref, sync::Arc};
+
+use crate::{MatchTable, StorageIndex};
+
+/// A read-only handle to a [`MatchTable`] that is shared between threads.
+///
+/// Cloning the handle only increments a reference count, so each alignment thread can own a handle
+/// without copying the storage of the table.
+/// The handle dereferences to the table, so all queries of [`MatchTable`] are available on it.
+///
+/// # Example
+///
+/// ```rust
+/// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+/// use compact_genome::implementation::vec_sequence::VectorGenome;
+/// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+/// use template_switch_error_free_inners::MatchTable;
+///
+/// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+/// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+/// let matches = MatchTable::new(
+///     reference.as_genome_subsequence(),
+///     query.as_genome_subsequence(),
+///     4,
+/// )
+/// .into_shared();
+///
+/// let threads: Vec<_> = (0..4)
+///     .map(|_| {
+///         let matches = matches.clone();
+///         std::thread::spawn(move || matches.has_reference_reference_match(1, 2))
+///     })
+///     .collect();
+/// for thread in threads {
+///     assert!(thread.join().unwrap());
+/// }
+/// ```
+pub struct SharedMatchTable<Index: StorageIndex = u32> {
+    table: Arc<MatchTable<Index>>,
+}
+
+impl<Index: StorageIndex> Clone for SharedMatchTable<Index> {
+    fn clone(&self) -> Self {
+        Self {
+            table: Arc::clone(&self.table),
+        }
+    }
+}
+
+impl<Index: StorageIndex> SharedMatchTable<Index> {
+    /// Share the given table.
+    pub fn new(table: MatchTable<Index>) -> Self {
+        Self {
+            table: Arc::new(table),
+        }
+    }
+
+    /// Returns the shared table.
+    pub fn table(&self) -> &MatchTable<Index> {
+        &self.table
+    }
+
+    /// Returns the number of handles sharing the table.
+    pub fn handle_count(&self) -> usize {
+        Arc::strong_count(&self.table)
+    }
+
+    /// Returns the table if this is the only handle sharing it, or the handle otherwise.
+    pub fn try_into_inner(self) -> Result<MatchTable<Index>, Self> {
+        Arc::try_unwrap(self.table).map_err(|table| Self { table })
+    }
+}
+
+impl<Index: StorageIndex> Deref for SharedMatchTable<Index> {
+    type Target = MatchTable<Index>;
+
+    fn deref(&self) -> &MatchTable<Index> {
+        &self.table
+    }
+}
+
+impl<Index: StorageIndex> From<MatchTable<Index>> for SharedMatchTable<Index> {
+    fn from(table: MatchTable<Index>) -> Self {
+        Self::new(table)
+    }
+}
+
+impl<Index: StorageIndex> From<Arc<MatchTable<Index>>> for SharedMatchTable<Index> {
+    fn from(table: Arc<MatchTable<Index>>) -> Self {
+        Self { table }
+    }
+}
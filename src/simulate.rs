@@ -0,0 +1,494 @@
+//! Generation of synthetic genome pairs with planted template switches, for benchmarking and testing detectors.
+//!
+//! The query is a copy of a random reference, into which the inners of template switches are planted
+//! as reverse complements of regions of the reference.
+//! Inners replace the same number of characters, so the outer alignment of the genomes has offset zero.
+//! The positions of the planted template switches are returned as ground truth,
+//! against which detected template switches are [evaluated](evaluate) by precision and recall.
+
+use std::ops::RangeInclusive;
+
+use crate::{MatchTable, Quadrant, StorageIndex, TemplateSwitchCandidate};
+
+/// The characters of the generated genomes.
+const ALPHABET: [u8; 4] = *b"ACGT";
+
+/// An error when the options of a [`SimulationBuilder`] are invalid.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum SimulationError {
+    /// The range of inner lengths is empty or contains zero.
+    #[error("The inner lengths {min_length}..={max_length} are empty or contain zero")]
+    InvalidInnerLength {
+        /// The configured minimum inner length.
+        min_length: usize,
+        /// The configured maximum inner length.
+        max_length: usize,
+    },
+
+    /// The range of template offsets is empty.
+    #[error("The template offsets {min_offset}..={max_offset} are empty")]
+    InvalidTemplateOffset {
+        /// The configured minimum template offset.
+        min_offset: isize,
+        /// The configured maximum template offset.
+        max_offset: isize,
+    },
+
+    /// The mutation rate is not a probability.
+    #[error("The mutation rate {mutation_rate} is not within [0, 1]")]
+    InvalidMutationRate {
+        /// The configured mutation rate.
+        mutation_rate: f64,
+    },
+
+    /// The genome is too short to hold the inners of all template switches.
+    #[error(
+        "Cannot plant {event_count} template switches with inners of up to {max_inner_length} characters into genomes of length {length}"
+    )]
+    TooManyEvents {
+        /// The configured number of template switches.
+        event_count: usize,
+        /// The configured maximum inner length.
+        max_inner_length: usize,
+        /// The configured genome length.
+        length: usize,
+    },
+
+    /// No template of an inner within a slot of the query lies within the reference at an offset within the configured range.
+    #[error(
+        "No template of an inner in {slot_start}..{slot_end} lies within the reference at offsets {min_offset}..={max_offset}"
+    )]
+    TemplateOutOfBounds {
+        /// The first position of the slot in the query.
+        slot_start: usize,
+        /// The exclusive end of the slot in the query.
+        slot_end: usize,
+        /// The configured minimum template offset.
+        min_offset: isize,
+        /// The configured maximum template offset.
+        max_offset: isize,
+    },
+}
+
+/// A pair of synthetic genomes with planted template switches, see [`SimulationBuilder::generate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulatedGenomes {
+    /// The reference as ASCII characters `ACGT`.
+    pub reference: Vec<u8>,
+    /// The query as ASCII characters `ACGT`, of the same length as the reference.
+    pub query: Vec<u8>,
+    /// The planted template switches in [`Quadrant::QueryReference`] in increasing order of their position in the query.
+    ///
+    /// The inners do not overlap and contain no mutations,
+    /// but may be extended by chance by matching characters around them.
+    pub events: Vec<TemplateSwitchCandidate>,
+}
+
+/// Options for generating a pair of synthetic genomes with planted template switches.
+///
+/// The generation is deterministic for a given seed.
+///
+/// # Example
+///
+/// ```rust
+/// use template_switch_error_free_inners::simulate::SimulationBuilder;
+///
+/// let genomes = SimulationBuilder::new(1_000)
+///     .event_count(3)
+///     .inner_length(20, 40)
+///     .template_offset(-50, 50)
+///     .mutation_rate(0.01)
+///     .seed(42)
+///     .generate()
+///     .unwrap();
+///
+/// assert_eq!(genomes.query.len(), 1_000);
+/// assert_eq!(genomes.events.len(), 3);
+/// for event in &genomes.events {
+///     assert!((20..=40).contains(&event.inner_length()));
+///     assert!((-50..=50).contains(&event.template_offset(0)));
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SimulationBuilder {
+    length: usize,
+    event_count: usize,
+    inner_length: RangeInclusive<usize>,
+    template_offset: RangeInclusive<isize>,
+    mutation_rate: f64,
+    seed: u64,
+}
+
+impl SimulationBuilder {
+    /// Create options for generating genomes of the given length with a single template switch.
+    ///
+    /// By default, inners have 20 to 50 characters, templates are offset by up to 100 characters,
+    /// and there are no mutations.
+    pub fn new(length: usize) -> Self {
+        Self {
+            length,
+            event_count: 1,
+            inner_length: 20..=50,
+            template_offset: -100..=100,
+            mutation_rate: 0.0,
+            seed: 0,
+        }
+    }
+
+    /// Set the number of planted template switches.
+    pub fn event_count(mut self, event_count: usize) -> Self {
+        self.event_count = event_count;
+        self
+    }
+
+    /// Set the range of inner lengths, from which the length of each inner is drawn uniformly.
+    pub fn inner_length(mut self, min_length: usize, max_length: usize) -> Self {
+        self.inner_length = min_length..=max_length;
+        self
+    }
+
+    /// Set the range of template offsets, see [`TemplateSwitchCandidate::template_offset`].
+    ///
+    /// The offset of each template is drawn uniformly from the offsets that keep the template within the reference.
+    pub fn template_offset(mut self, min_offset: isize, max_offset: isize) -> Self {
+        self.template_offset = min_offset..=max_offset;
+        self
+    }
+
+    /// Set the probability with which each character of the query outside of the inners is substituted by a different character.
+    pub fn mutation_rate(mut self, mutation_rate: f64) -> Self {
+        self.mutation_rate = mutation_rate;
+        self
+    }
+
+    /// Set the seed of the random number generator.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Generate the genomes.
+    ///
+    /// The query is divided into one slot per template switch, and each inner is placed randomly within its slot.
+    ///
+    /// Returns an error if the options are invalid, or if the genomes are too short to place the inners and their templates.
+    pub fn generate(&self) -> Result<SimulatedGenomes, SimulationError> {
+        self.validate()?;
+        let mut random = SplitMix64::new(self.seed);
+        let reference: Vec<u8> = (0..self.length)
+            .map(|_| ALPHABET[random.below(ALPHABET.len())])
+            .collect();
+        let mut query = reference.clone();
+        let mut is_inner = vec![false; self.length];
+        let mut events = Vec::with_capacity(self.event_count);
+
+        let slot_length = self.length.checked_div(self.event_count).unwrap_or(0);
+        for slot_start in (0..self.event_count).map(|slot| slot * slot_length) {
+            let inner_length = *self.inner_length.start()
+                + random.below(self.inner_length.end() - self.inner_length.start() + 1);
+            let event = self.plant(&mut random, slot_start, slot_length, inner_length)?;
+
+            for (position, character) in
+                (event.point1..event.point4).zip(reference[event.point3..event.point2].iter().rev())
+            {
+                query[position] = complement(*character);
+                is_inner[position] = true;
+            }
+            events.push(event);
+        }
+
+        for (character, is_inner) in query.iter_mut().zip(is_inner) {
+            if !is_inner && random.probability() < self.mutation_rate {
+                let substitute = (character_rank(*character) + 1 + random.below(3)) % 4;
+                *character = ALPHABET[substitute];
+            }
+        }
+
+        Ok(SimulatedGenomes {
+            reference,
+            query,
+            events,
+        })
+    }
+
+    fn validate(&self) -> Result<(), SimulationError> {
+        let (&min_length, &max_length) = (self.inner_length.start(), self.inner_length.end());
+        if min_length == 0 || min_length > max_length {
+            return Err(SimulationError::InvalidInnerLength {
+                min_length,
+                max_length,
+            });
+        }
+        if self.template_offset.is_empty() {
+            return Err(SimulationError::InvalidTemplateOffset {
+                min_offset: *self.template_offset.start(),
+                max_offset: *self.template_offset.end(),
+            });
+        }
+        if !(0.0..=1.0).contains(&self.mutation_rate) {
+            return Err(SimulationError::InvalidMutationRate {
+                mutation_rate: self.mutation_rate,
+            });
+        }
+        if self.event_count > 0 && self.length / self.event_count < max_length {
+            return Err(SimulationError::TooManyEvents {
+                event_count: self.event_count,
+                max_inner_length: max_length,
+                length: self.length,
+            });
+        }
+        Ok(())
+    }
+
+    /// Place an inner of the given length within the slot and its template within the genome.
+    fn plant(
+        &self,
+        random: &mut SplitMix64,
+        slot_start: usize,
+        slot_length: usize,
+        inner_length: usize,
+    ) -> Result<TemplateSwitchCandidate, SimulationError> {
+        // The inner starts within `slot_start..=last_start` and its template within `0..=last_template`.
+        let last_start = (slot_start + slot_length - inner_length) as isize;
+        let last_template = (self.length - inner_length) as isize;
+        let min_offset = (*self.template_offset.start()).max(-last_start);
+        let max_offset = (*self.template_offset.end()).min(last_template - slot_start as isize);
+        if min_offset > max_offset {
+            return Err(SimulationError::TemplateOutOfBounds {
+                slot_start,
+                slot_end: slot_start + slot_length,
+                min_offset: *self.template_offset.start(),
+                max_offset: *self.template_offset.end(),
+            });
+        }
+
+        let offset = random.inclusive(min_offset, max_offset);
+        let point1 = random.inclusive(
+            (slot_start as isize).max(-offset),
+            last_start.min(last_template - offset),
+        );
+        let point3 = (point1 + offset) as usize;
+        let point1 = point1 as usize;
+        Ok(TemplateSwitchCandidate {
+            quadrant: Quadrant::QueryReference,
+            point1,
+            point2: point3 + inner_length,
+            point3,
+            point4: point1 + inner_length,
+        })
+    }
+}
+
+impl SimulatedGenomes {
+    /// Evaluate the maximal error-free inners of [`Quadrant::QueryReference`] of a table of these genomes against the planted template switches.
+    ///
+    /// Each maximal inner is a detected template switch, see [`evaluate`] for the meaning of `tolerance`.
+    /// The table must have been constructed with these genomes as reference and query.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::MatchTable;
+    /// use template_switch_error_free_inners::simulate::SimulationBuilder;
+    ///
+    /// let genomes = SimulationBuilder::new(1_000).event_count(2).generate().unwrap();
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&genomes.reference).unwrap();
+    /// let query = VectorGenome::from_slice_u8(&genomes.query).unwrap();
+    /// let matches = MatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     16,
+    /// );
+    ///
+    /// let evaluation = genomes.evaluate(&matches, 2);
+    /// assert_eq!(evaluation.recall(), 1.0);
+    /// ```
+    pub fn evaluate(&self, table: &MatchTable<impl StorageIndex>, tolerance: usize) -> Evaluation {
+        let quadrant = Quadrant::QueryReference;
+        let candidates =
+            table
+                .maximal_matches(quadrant)
+                .map(|(primary_start, secondary_rc_start, length)| {
+                    let point2 = table.secondary_forward_end(quadrant, secondary_rc_start);
+                    TemplateSwitchCandidate {
+                        quadrant,
+                        point1: primary_start,
+                        point2,
+                        point3: point2 - length,
+                        point4: primary_start + length,
+                    }
+                });
+        evaluate(candidates, &self.events, tolerance)
+    }
+}
+
+/// The agreement of detected template switches with planted ones, see [`evaluate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Evaluation {
+    /// The number of planted template switches.
+    pub event_count: usize,
+    /// The number of planted template switches that agree with at least one detected template switch.
+    pub recovered_event_count: usize,
+    /// The number of detected template switches.
+    pub candidate_count: usize,
+    /// The number of detected template switches that agree with at least one planted template switch.
+    pub true_candidate_count: usize,
+}
+
+impl Evaluation {
+    /// Returns the fraction of detected template switches that agree with a planted one, or one if none were detected.
+    pub fn precision(&self) -> f64 {
+        ratio(self.true_candidate_count, self.candidate_count)
+    }
+
+    /// Returns the fraction of planted template switches that were detected, or one if none were planted.
+    pub fn recall(&self) -> f64 {
+        ratio(self.recovered_event_count, self.event_count)
+    }
+
+    /// Returns the harmonic mean of [`precision`](Self::precision) and [`recall`](Self::recall), or zero if both are zero.
+    pub fn f1_score(&self) -> f64 {
+        let (precision, recall) = (self.precision(), self.recall());
+        if precision + recall == 0.0 {
+            0.0
+        } else {
+            2.0 * precision * recall / (precision + recall)
+        }
+    }
+}
+
+fn ratio(numerator: usize, denominator: usize) -> f64 {
+    if denominator == 0 {
+        1.0
+    } else {
+        numerator as f64 / denominator as f64
+    }
+}
+
+/// Evaluate detected template switches against planted ones.
+///
+/// A detected template switch agrees with a planted one if both are in the same quadrant
+/// and each of their four points differs by at most `tolerance`.
+/// A tolerance of a few characters accounts for inners that are extended by chance by matching characters around them.
+///
+/// # Example
+///
+/// ```rust
+/// use template_switch_error_free_inners::{Quadrant, TemplateSwitchCandidate};
+/// use template_switch_error_free_inners::simulate::evaluate;
+///
+/// let event = TemplateSwitchCandidate {
+///     quadrant: Quadrant::QueryReference,
+///     point1: 100,
+///     point2: 150,
+///     point3: 120,
+///     point4: 130,
+/// };
+/// let extended = TemplateSwitchCandidate {
+///     point1: 99,
+///     point2: 151,
+///     ..event
+/// };
+/// let spurious = TemplateSwitchCandidate {
+///     point1: 500,
+///     point4: 530,
+///     ..event
+/// };
+///
+/// let evaluation = evaluate([extended, spurious], &[event], 1);
+/// assert_eq!(evaluation.recall(), 1.0);
+/// assert_eq!(evaluation.precision(), 0.5);
+/// assert_eq!(evaluate([extended], &[event], 0).recall(), 0.0);
+/// ```
+pub fn evaluate(
+    candidates: impl IntoIterator<Item = TemplateSwitchCandidate>,
+    events: &[TemplateSwitchCandidate],
+    tolerance: usize,
+) -> Evaluation {
+    let mut events: Vec<_> = events.to_vec();
+    events.sort_unstable_by_key(|event| event.point1);
+    let mut is_recovered = vec![false; events.len()];
+    let mut evaluation = Evaluation {
+        event_count: events.len(),
+        recovered_event_count: 0,
+        candidate_count: 0,
+        true_candidate_count: 0,
+    };
+
+    for candidate in candidates {
+        evaluation.candidate_count += 1;
+        let first_event =
+            events.partition_point(|event| event.point1 + tolerance < candidate.point1);
+        let mut is_true = false;
+        for (event, is_recovered) in events[first_event..]
+            .iter()
+            .zip(&mut is_recovered[first_event..])
+            .take_while(|(event, _)| event.point1 <= candidate.point1 + tolerance)
+        {
+            if event.quadrant == candidate.quadrant
+                && [
+                    (event.point2, candidate.point2),
+                    (event.point3, candidate.point3),
+                    (event.point4, candidate.point4),
+                ]
+                .into_iter()
+                .all(|(expected, actual)| expected.abs_diff(actual) <= tolerance)
+            {
+                is_true = true;
+                *is_recovered = true;
+            }
+        }
+        evaluation.true_candidate_count += usize::from(is_true);
+    }
+
+    evaluation.recovered_event_count = is_recovered.into_iter().filter(|&x| x).count();
+    evaluation
+}
+
+fn character_rank(character: u8) -> usize {
+    ALPHABET
+        .iter()
+        .position(|&other| other == character)
+        .unwrap_or_else(|| unreachable!("generated genomes contain only ACGT"))
+}
+
+fn complement(character: u8) -> u8 {
+    ALPHABET[3 - character_rank(character)]
+}
+
+/// The SplitMix64 pseudorandom number generator, which is small, fast and good enough for generating test data.
+struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut value = self.state;
+        value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        value ^ (value >> 31)
+    }
+
+    /// Returns a uniformly random number in `0..bound`, neglecting the bias of the modulo for small bounds.
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+
+    /// Returns a uniformly random number in `start..=end`, where `start <= end`.
+    fn inclusive(&mut self, start: isize, end: isize) -> isize {
+        start + self.below(end.abs_diff(start) + 1) as isize
+    }
+
+    /// Returns a uniformly random number in `[0, 1)`.
+    fn probability(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
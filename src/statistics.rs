@@ -0,0 +1,286 @@
+//! Summary statistics of the matches of a match table.
+
+use std::fmt::Display;
+
+use crate::{MatchTable, Quadrant, Quadrants, StorageIndex};
+
+/// The number of busiest rows and columns reported per quadrant.
+pub const BUSIEST_LINE_COUNT: usize = 10;
+
+/// Summary statistics of the matches of a single quadrant, see [`MatchTable::statistics`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuadrantStatistics {
+    /// The quadrant these statistics belong to.
+    pub quadrant: Quadrant,
+    /// If the quadrant was computed, see [`MatchTable::quadrants`].
+    pub computed: bool,
+    /// The number of matches.
+    pub match_count: usize,
+    /// The fraction of kmer pairs that match, or zero if the quadrant has no kmer pairs.
+    pub density: f64,
+    /// Up to [`BUSIEST_LINE_COUNT`] primary indices with the most matches as `(primary_index, match_count)` pairs.
+    ///
+    /// Ordered by decreasing match count and then by increasing index. Rows without matches are omitted.
+    pub busiest_rows: Vec<(usize, usize)>,
+    /// Up to [`BUSIEST_LINE_COUNT`] secondary rc indices with the most matches as `(secondary_rc_index, match_count)` pairs.
+    ///
+    /// Ordered like [`busiest_rows`](Self::busiest_rows).
+    pub busiest_columns: Vec<(usize, usize)>,
+    /// The number of bytes occupied by the storage of the quadrant, including memory-mapped bytes.
+    pub storage_bytes: usize,
+}
+
+/// Summary statistics of the matches of all quadrants, see [`MatchTable::statistics`].
+///
+/// Displays as a TSV table with one line per quadrant, listing the busiest row and column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchTableStatistics {
+    /// The statistics of the quadrants in the order of [`Quadrant::ALL`].
+    pub quadrants: [QuadrantStatistics; 4],
+}
+
+impl MatchTableStatistics {
+    /// Returns the statistics of the given quadrant.
+    pub fn quadrant(&self, quadrant: Quadrant) -> &QuadrantStatistics {
+        self.quadrants
+            .iter()
+            .find(|statistics| statistics.quadrant == quadrant)
+            .unwrap_or_else(|| unreachable!("all quadrants have statistics"))
+    }
+
+    /// Returns the number of matches in all quadrants.
+    pub fn match_count(&self) -> usize {
+        self.quadrants
+            .iter()
+            .map(|statistics| statistics.match_count)
+            .sum()
+    }
+
+    /// Returns the number of bytes occupied by the storage of all quadrants.
+    pub fn storage_bytes(&self) -> usize {
+        self.quadrants
+            .iter()
+            .map(|statistics| statistics.storage_bytes)
+            .sum()
+    }
+}
+
+impl Display for MatchTableStatistics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "quadrant\tcomputed\tmatch_count\tdensity\tbusiest_row\tbusiest_row_match_count\tbusiest_column\tbusiest_column_match_count\tstorage_bytes"
+        )?;
+        let busiest = |lines: &[(usize, usize)]| match lines.first() {
+            Some((index, match_count)) => format!("{index}\t{match_count}"),
+            None => "-\t0".to_string(),
+        };
+        for statistics in &self.quadrants {
+            writeln!(
+                f,
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                statistics.quadrant,
+                statistics.computed,
+                statistics.match_count,
+                statistics.density,
+                busiest(&statistics.busiest_rows),
+                busiest(&statistics.busiest_columns),
+                statistics.storage_bytes,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<Index: StorageIndex> MatchTable<Index> {
+    /// Compute summary statistics of the matches of each quadrant.
+    ///
+    /// The match counts of the rows are obtained from the rank indexes of the quadrants,
+    /// so only the column counts require iterating over the matches.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTable, Quadrant};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+    /// let matches = MatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    ///
+    /// let statistics = matches.statistics();
+    /// let reference_reference = statistics.quadrant(Quadrant::ReferenceReference);
+    /// assert_eq!(reference_reference.match_count, 2);
+    /// assert_eq!(reference_reference.density, 2.0 / 100.0);
+    /// assert_eq!(reference_reference.busiest_rows, vec![(1, 1), (7, 1)]);
+    /// assert_eq!(statistics.quadrant(Quadrant::QueryQuery).match_count, 0);
+    /// ```
+    pub fn statistics(&self) -> MatchTableStatistics {
+        MatchTableStatistics {
+            quadrants: Quadrant::ALL.map(|quadrant| self.quadrant_statistics(quadrant)),
+        }
+    }
+
+    fn quadrant_statistics(&self, quadrant: Quadrant) -> QuadrantStatistics {
+        let storage = self.quadrant(quadrant);
+        let primary_kmer_count = self.primary_kmer_count(quadrant);
+        let secondary_kmer_count = self.secondary_kmer_count(quadrant);
+        let match_count = storage.match_count();
+        let pair_count = primary_kmer_count as f64 * secondary_kmer_count as f64;
+
+        let row_match_counts =
+            (0..primary_kmer_count).map(|primary_index| storage.row_match_count(primary_index));
+        let mut column_match_counts = vec![0; secondary_kmer_count];
+        if match_count > 0 {
+            for (_, secondary_rc_index) in storage.iter() {
+                column_match_counts[secondary_rc_index] += 1;
+            }
+        }
+
+        QuadrantStatistics {
+            quadrant,
+            computed: self.quadrants.contains(quadrant),
+            match_count,
+            density: if pair_count > 0.0 {
+                match_count as f64 / pair_count
+            } else {
+                0.0
+            },
+            busiest_rows: busiest_lines(row_match_counts),
+            busiest_columns: busiest_lines(column_match_counts),
+            storage_bytes: storage.storage_bytes(),
+        }
+    }
+}
+
+/// A histogram of the lengths of the maximal error-free inners, see [`MatchTable::inner_length_histogram`].
+///
+/// Displays as a TSV table with one line per length that occurs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InnerLengthHistogram {
+    minimum_length: usize,
+    /// The number of inners of length `minimum_length + i` at index `i`.
+    counts: Vec<usize>,
+}
+
+impl InnerLengthHistogram {
+    fn new(minimum_length: usize, lengths: impl IntoIterator<Item = usize>) -> Self {
+        let mut counts = Vec::new();
+        for length in lengths {
+            let bin = length - minimum_length;
+            if bin >= counts.len() {
+                counts.resize(bin + 1, 0);
+            }
+            counts[bin] += 1;
+        }
+        Self {
+            minimum_length,
+            counts,
+        }
+    }
+
+    /// Returns the number of inners of the given length.
+    pub fn count(&self, length: usize) -> usize {
+        length
+            .checked_sub(self.minimum_length)
+            .and_then(|bin| self.counts.get(bin))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Returns the number of inners of at least the given length,
+    /// i.e. the number of inners that a table with this minimum length would contain.
+    pub fn count_at_least(&self, length: usize) -> usize {
+        let first_bin = length.saturating_sub(self.minimum_length);
+        self.counts.iter().skip(first_bin).sum()
+    }
+
+    /// Returns the total number of inners.
+    pub fn total_count(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    /// Returns the length of the longest inner, or `None` if there are no inners.
+    pub fn max_length(&self) -> Option<usize> {
+        self.counts
+            .iter()
+            .rposition(|&count| count > 0)
+            .map(|bin| self.minimum_length + bin)
+    }
+
+    /// Returns an iterator over the lengths that occur and their counts as `(length, count)` pairs in increasing order of length.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.counts
+            .iter()
+            .enumerate()
+            .filter(|&(_, &count)| count > 0)
+            .map(|(bin, &count)| (self.minimum_length + bin, count))
+    }
+}
+
+impl Display for InnerLengthHistogram {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "length\tcount")?;
+        for (length, count) in self.iter() {
+            writeln!(f, "{length}\t{count}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<Index: StorageIndex> MatchTable<Index> {
+    /// Compute the histogram of the lengths of the maximal error-free inners of the given quadrants.
+    ///
+    /// The inners are those of [`maximal_matches`](Self::maximal_matches),
+    /// so the histogram shows how many inners would remain for each larger minimum length.
+    /// In the self-comparison quadrants, each inner is reported together with its mirror image, so it is counted twice.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTable, Quadrants};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"TTACGGATTT").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"GGTCCGTGG").unwrap();
+    /// let matches = MatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    ///
+    /// let histogram = matches.inner_length_histogram(Quadrants::REFERENCE_QUERY);
+    /// assert_eq!(histogram.iter().collect::<Vec<_>>(), vec![(5, 1)]);
+    /// assert_eq!(histogram.count_at_least(5), 1);
+    /// assert_eq!(histogram.count_at_least(6), 0);
+    /// ```
+    pub fn inner_length_histogram(&self, quadrants: Quadrants) -> InnerLengthHistogram {
+        InnerLengthHistogram::new(
+            self.minimum_length,
+            quadrants
+                .iter()
+                .flat_map(|quadrant| self.maximal_matches(quadrant).map(|(_, _, length)| length)),
+        )
+    }
+}
+
+/// Returns up to [`BUSIEST_LINE_COUNT`] indices with the highest nonzero counts together with their counts,
+/// ordered by decreasing count and then by increasing index.
+fn busiest_lines(counts: impl IntoIterator<Item = usize>) -> Vec<(usize, usize)> {
+    let mut lines: Vec<_> = counts
+        .into_iter()
+        .enumerate()
+        .filter(|&(_, count)| count > 0)
+        .collect();
+    lines.sort_unstable_by_key(|&(index, count)| (std::cmp::Reverse(count), index));
+    lines.truncate(BUSIEST_LINE_COUNT);
+    lines
+}
@@ -0,0 +1,1436 @@
+//! Storage backends for the quadrants of a match table.
+
+use std::{fmt::Debug, ops::Range};
+
+use bitvec::{
+    order::Lsb0,
+    slice::{BitSlice, IterOnes},
+    store::BitStore,
+    vec::BitVec,
+};
+
+#[cfg(feature = "roaring")]
+use roaring::RoaringTreemap;
+
+use crate::{MatchTableError, band::Band, rank::RankIndex};
+
+/// The storage backend used for the quadrants of a [`MatchTable`](crate::MatchTable).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StorageBackend {
+    /// Store one bit for each pair of kmers.
+    ///
+    /// Memory scales with the product of the kmer counts, but queries are a single bit lookup.
+    /// On 64-bit platforms, quadrants may have more than 2^32 bits as long as they fit into memory,
+    /// while on 32-bit platforms, constructing a quadrant of more than 2^29 bits returns [`MatchTableError::QuadrantTooLarge`].
+    #[default]
+    Dense,
+    /// Store the matches as adjacency lists per primary index (compressed sparse rows).
+    ///
+    /// Memory scales with the number of matches, and queries are a binary search within a row.
+    /// Each match stores its secondary rc index as the [index type](StorageIndex) of the table.
+    Sparse,
+    /// Like [`Dense`](Self::Dense), but the self-comparison quadrants store only one triangle.
+    ///
+    /// A reference–reference match at `(i, j)` implies a match at `(n - 1 - j, n - 1 - i)`,
+    /// where `n` is the number of reference kmers, since the reverse complement of a match is a match as well.
+    /// The same holds for the query–query quadrant.
+    /// Hence only the pairs with `i + j < n` are stored, roughly halving the memory of self-comparisons.
+    /// Iterating the matches scans the mirrored half bit by bit, which makes it slower than for [`Dense`](Self::Dense).
+    Symmetric,
+    /// Store the bits of [`Dense`](Self::Dense) in a compressed Roaring bitmap.
+    ///
+    /// Roaring bitmaps split the bits into chunks of 2^16 bits and store each chunk as a sorted array of its ones,
+    /// as a plain bitvector, or as runs of ones, whichever is smallest.
+    /// So memory scales with the number of matches for sparse quadrants, and is bounded by that of [`Dense`](Self::Dense) for dense quadrants,
+    /// while queries are a binary search for the chunk followed by a lookup within the chunk.
+    /// Tables in this backend are written in the [binary format](crate::MatchTable::write_binary) like [`Sparse`](Self::Sparse),
+    /// so they can be read without the `roaring` feature.
+    #[cfg(feature = "roaring")]
+    Roaring,
+}
+
+/// The matches of a single quadrant of a match table.
+///
+/// The bit-based variants carry a [`RankIndex`] to count the matches of a row in constant time.
+pub(crate) enum QuadrantStorage<Index> {
+    Dense {
+        bits: BitVec,
+        secondary_kmer_count: usize,
+        rank: RankIndex,
+    },
+    Sparse(SparseRows<Index>),
+    /// Like [`Dense`](Self::Dense), but storing only the pairs within the band, `band.width()` bits per primary index.
+    Banded {
+        bits: BitVec,
+        band: Band,
+        rank: RankIndex,
+    },
+    /// A self-comparison quadrant storing only the pairs `(i, j)` with `i + j < kmer_count`, row by row.
+    Triangular {
+        bits: BitVec,
+        kmer_count: usize,
+        rank: RankIndex,
+        /// The number of matches of each row that are stored mirrored in other rows.
+        mirrored_row_counts: Vec<usize>,
+    },
+    #[cfg(feature = "mmap")]
+    Mapped {
+        map: memmap2::MmapMut,
+        secondary_kmer_count: usize,
+        rank: RankIndex,
+    },
+    /// The pair `(i, j)` is stored as `i * secondary_kmer_count + j`, like in [`Dense`](Self::Dense).
+    #[cfg(feature = "roaring")]
+    Roaring {
+        bitmap: RoaringTreemap,
+        primary_kmer_count: usize,
+        secondary_kmer_count: usize,
+    },
+}
+
+/// Collects the matches of a quadrant during construction.
+pub(crate) enum QuadrantStorageBuilder<Index> {
+    Dense {
+        bits: BitVec,
+        secondary_kmer_count: usize,
+    },
+    Sparse(SparseRowsBuilder<Index>),
+    Banded {
+        bits: BitVec,
+        band: Band,
+    },
+    Triangular {
+        bits: BitVec,
+        kmer_count: usize,
+    },
+    #[cfg(feature = "mmap")]
+    Mapped {
+        map: memmap2::MmapMut,
+        secondary_kmer_count: usize,
+    },
+    #[cfg(feature = "roaring")]
+    Roaring {
+        bitmap: RoaringTreemap,
+        primary_kmer_count: usize,
+        secondary_kmer_count: usize,
+    },
+}
+
+/// Returns the number of bits of a quadrant of the given dimensions, as computed by a checked multiplication,
+/// or an error if it overflowed or exceeds the maximum length of a bitvector.
+pub(crate) fn checked_bit_count(
+    bit_count: Option<usize>,
+    primary_kmer_count: usize,
+    secondary_kmer_count: usize,
+) -> Result<usize, MatchTableError> {
+    bit_count
+        .filter(|&bit_count| bit_count <= BitSlice::<usize, Lsb0>::MAX_BITS)
+        .ok_or(MatchTableError::QuadrantTooLarge {
+            primary_kmer_count,
+            secondary_kmer_count,
+            max_bit_count: BitSlice::<usize, Lsb0>::MAX_BITS,
+        })
+}
+
+/// Returns the position of the pair in triangular storage,
+/// after mirroring it into the stored triangle if necessary.
+fn triangular_index(kmer_count: usize, primary_index: usize, secondary_rc_index: usize) -> usize {
+    let (row, column) = if primary_index + secondary_rc_index < kmer_count {
+        (primary_index, secondary_rc_index)
+    } else {
+        (
+            kmer_count - 1 - secondary_rc_index,
+            kmer_count - 1 - primary_index,
+        )
+    };
+    // Row `r` stores `kmer_count - r` pairs.
+    row * kmer_count - row * row.saturating_sub(1) / 2 + column
+}
+
+impl<Index: StorageIndex> QuadrantStorageBuilder<Index> {
+    /// Create a builder for the given backend.
+    ///
+    /// If a band is given that is narrower than the quadrant, then [`StorageBackend::Dense`] stores only the band.
+    /// The quadrant is a self-comparison if `self_comparison` is set.
+    pub fn new(
+        backend: StorageBackend,
+        primary_kmer_count: usize,
+        secondary_kmer_count: usize,
+        band: Option<Band>,
+        self_comparison: bool,
+    ) -> Result<Self, MatchTableError> {
+        let allocation_too_large = || MatchTableError::AllocationTooLarge {
+            primary_kmer_count,
+            secondary_kmer_count,
+        };
+        let zeroed_bits = |bit_count: Option<usize>| -> Result<BitVec, MatchTableError> {
+            let bit_count = checked_bit_count(bit_count, primary_kmer_count, secondary_kmer_count)?;
+            let word_count = bit_count.div_ceil(usize::BITS as usize);
+            let mut words = Vec::new();
+            words
+                .try_reserve_exact(word_count)
+                .map_err(|_| allocation_too_large())?;
+            words.resize(word_count, 0);
+            let mut bits = BitVec::from_vec(words);
+            bits.truncate(bit_count);
+            Ok(bits)
+        };
+
+        Ok(match backend {
+            StorageBackend::Dense | StorageBackend::Symmetric => match band {
+                Some(band) if band.width() < secondary_kmer_count => Self::Banded {
+                    bits: zeroed_bits(primary_kmer_count.checked_mul(band.width()))?,
+                    band,
+                },
+                _ if backend == StorageBackend::Symmetric && self_comparison => {
+                    debug_assert_eq!(primary_kmer_count, secondary_kmer_count);
+                    Self::Triangular {
+                        bits: zeroed_bits(
+                            primary_kmer_count
+                                .checked_add(1)
+                                .and_then(|n| n.checked_mul(primary_kmer_count))
+                                .map(|n| n / 2),
+                        )?,
+                        kmer_count: primary_kmer_count,
+                    }
+                }
+                _ => Self::Dense {
+                    bits: zeroed_bits(primary_kmer_count.checked_mul(secondary_kmer_count))?,
+                    secondary_kmer_count,
+                },
+            },
+            StorageBackend::Sparse => {
+                if Index::try_from_usize(primary_kmer_count.max(secondary_kmer_count)).is_none() {
+                    return Err(MatchTableError::IndexTypeTooNarrow {
+                        primary_kmer_count,
+                        secondary_kmer_count,
+                        index_bits: Index::BITS,
+                    });
+                }
+                Self::Sparse(SparseRowsBuilder::new(primary_kmer_count))
+            }
+            #[cfg(feature = "roaring")]
+            StorageBackend::Roaring => {
+                checked_bit_count(
+                    primary_kmer_count.checked_mul(secondary_kmer_count),
+                    primary_kmer_count,
+                    secondary_kmer_count,
+                )?;
+                Self::Roaring {
+                    bitmap: RoaringTreemap::new(),
+                    primary_kmer_count,
+                    secondary_kmer_count,
+                }
+            }
+        })
+    }
+
+    /// Create a builder for a quadrant that is not computed and stays empty.
+    ///
+    /// The quadrant is stored in [`StorageBackend::Sparse`], so it takes memory linear in the primary kmer count.
+    /// It stores no indices, so the kmer counts do not need to fit into the index type.
+    pub fn empty(primary_kmer_count: usize) -> Self {
+        Self::Sparse(SparseRowsBuilder::new(primary_kmer_count))
+    }
+
+    /// Returns the number of bytes of the file region required by [`new_mapped`](Self::new_mapped),
+    /// or an error if the quadrant has more bits than can be addressed.
+    #[cfg(feature = "mmap")]
+    pub fn mapped_region_length(
+        primary_kmer_count: usize,
+        secondary_kmer_count: usize,
+    ) -> Result<u64, MatchTableError> {
+        let bit_count = checked_bit_count(
+            primary_kmer_count.checked_mul(secondary_kmer_count),
+            primary_kmer_count,
+            secondary_kmer_count,
+        )?;
+        Ok(bit_count.div_ceil(8) as u64)
+    }
+
+    /// Map the region of `file` starting at `offset` as dense storage.
+    ///
+    /// The file must be large enough to hold the region, and the region must be zeroed.
+    #[cfg(feature = "mmap")]
+    pub fn new_mapped(
+        file: &std::fs::File,
+        offset: u64,
+        primary_kmer_count: usize,
+        secondary_kmer_count: usize,
+    ) -> Result<Self, MatchTableError> {
+        let length = Self::mapped_region_length(primary_kmer_count, secondary_kmer_count)?;
+        // SAFETY: the file was created by us and is not expected to be modified by other processes while mapped.
+        let map = unsafe {
+            memmap2::MmapOptions::new()
+                .offset(offset)
+                // The region holds at most `BitSlice::MAX_BITS` bits, so its length fits into `usize`.
+                .len(length as usize)
+                .map_mut(file)?
+        };
+        Ok(Self::Mapped {
+            map,
+            secondary_kmer_count,
+        })
+    }
+
+    pub fn insert(&mut self, primary_index: usize, secondary_rc_index: usize) {
+        match self {
+            Self::Dense {
+                bits,
+                secondary_kmer_count,
+            } => bits.set(
+                primary_index * *secondary_kmer_count + secondary_rc_index,
+                true,
+            ),
+            Self::Sparse(builder) => builder.insert(primary_index, secondary_rc_index),
+            Self::Banded { bits, band } => bits.set(
+                primary_index * band.width()
+                    + band.offset_in_band(primary_index, secondary_rc_index),
+                true,
+            ),
+            Self::Triangular { bits, kmer_count } => bits.set(
+                triangular_index(*kmer_count, primary_index, secondary_rc_index),
+                true,
+            ),
+            #[cfg(feature = "mmap")]
+            Self::Mapped {
+                map,
+                secondary_kmer_count,
+            } => BitSlice::<u8, Lsb0>::from_slice_mut(map).set(
+                primary_index * *secondary_kmer_count + secondary_rc_index,
+                true,
+            ),
+            #[cfg(feature = "roaring")]
+            Self::Roaring {
+                bitmap,
+                secondary_kmer_count,
+                ..
+            } => {
+                bitmap.insert((primary_index * *secondary_kmer_count + secondary_rc_index) as u64);
+            }
+        }
+    }
+
+    pub fn build(self) -> QuadrantStorage<Index> {
+        match self {
+            Self::Dense {
+                bits,
+                secondary_kmer_count,
+            } => QuadrantStorage::Dense {
+                rank: RankIndex::new(&bits),
+                bits,
+                secondary_kmer_count,
+            },
+            Self::Sparse(builder) => QuadrantStorage::Sparse(builder.build()),
+            Self::Banded { bits, band } => QuadrantStorage::Banded {
+                rank: RankIndex::new(&bits),
+                bits,
+                band,
+            },
+            Self::Triangular { bits, kmer_count } => {
+                let mut mirrored_row_counts = vec![0; kmer_count];
+                for row in 0..kmer_count {
+                    let row_start = triangular_index(kmer_count, row, 0);
+                    for column in bits[row_start..row_start + kmer_count - row].iter_ones() {
+                        // Pairs on the anti-diagonal are their own mirror image.
+                        if row + column < kmer_count - 1 {
+                            mirrored_row_counts[kmer_count - 1 - column] += 1;
+                        }
+                    }
+                }
+
+                QuadrantStorage::Triangular {
+                    rank: RankIndex::new(&bits),
+                    bits,
+                    kmer_count,
+                    mirrored_row_counts,
+                }
+            }
+            #[cfg(feature = "mmap")]
+            Self::Mapped {
+                map,
+                secondary_kmer_count,
+            } => QuadrantStorage::Mapped {
+                rank: RankIndex::new(BitSlice::<u8, Lsb0>::from_slice(&map)),
+                map,
+                secondary_kmer_count,
+            },
+            #[cfg(feature = "roaring")]
+            Self::Roaring {
+                bitmap,
+                primary_kmer_count,
+                secondary_kmer_count,
+            } => QuadrantStorage::Roaring {
+                bitmap,
+                primary_kmer_count,
+                secondary_kmer_count,
+            },
+        }
+    }
+}
+
+impl<Index: StorageIndex> QuadrantStorage<Index> {
+    pub fn has_match(&self, primary_index: usize, secondary_rc_index: usize) -> bool {
+        match self {
+            Self::Dense {
+                bits,
+                secondary_kmer_count,
+                ..
+            } => bits[primary_index * secondary_kmer_count + secondary_rc_index],
+            Self::Sparse(rows) => rows.has_match(primary_index, secondary_rc_index),
+            Self::Banded { bits, band, .. } => {
+                band.contains(primary_index, secondary_rc_index)
+                    && bits[primary_index * band.width()
+                        + band.offset_in_band(primary_index, secondary_rc_index)]
+            }
+            Self::Triangular {
+                bits, kmer_count, ..
+            } => bits[triangular_index(*kmer_count, primary_index, secondary_rc_index)],
+            #[cfg(feature = "mmap")]
+            Self::Mapped {
+                map,
+                secondary_kmer_count,
+                ..
+            } => BitSlice::<u8, Lsb0>::from_slice(map)
+                [primary_index * secondary_kmer_count + secondary_rc_index],
+            #[cfg(feature = "roaring")]
+            Self::Roaring {
+                bitmap,
+                secondary_kmer_count,
+                ..
+            } => {
+                bitmap.contains((primary_index * secondary_kmer_count + secondary_rc_index) as u64)
+            }
+        }
+    }
+
+    /// Returns `true` if this storage holds a quadrant of the given number of primary and secondary kmers.
+    pub fn has_dimensions(&self, primary_kmer_count: usize, secondary_kmer_count: usize) -> bool {
+        let bit_count = |row_length: usize| primary_kmer_count.checked_mul(row_length);
+        match self {
+            Self::Dense {
+                bits,
+                secondary_kmer_count: stored_secondary_kmer_count,
+                ..
+            } => {
+                *stored_secondary_kmer_count == secondary_kmer_count
+                    && bit_count(secondary_kmer_count) == Some(bits.len())
+            }
+            Self::Sparse(rows) => {
+                rows.row_offsets.len() == primary_kmer_count + 1
+                    && rows
+                        .secondary_indices
+                        .iter()
+                        .all(|index| index.into_usize() < secondary_kmer_count)
+            }
+            Self::Banded { bits, band, .. } => bit_count(band.width()) == Some(bits.len()),
+            Self::Triangular { kmer_count, .. } => {
+                *kmer_count == primary_kmer_count && *kmer_count == secondary_kmer_count
+            }
+            #[cfg(feature = "mmap")]
+            Self::Mapped {
+                secondary_kmer_count: stored_secondary_kmer_count,
+                ..
+            } => *stored_secondary_kmer_count == secondary_kmer_count,
+            #[cfg(feature = "roaring")]
+            Self::Roaring {
+                primary_kmer_count: stored_primary_kmer_count,
+                secondary_kmer_count: stored_secondary_kmer_count,
+                ..
+            } => {
+                *stored_primary_kmer_count == primary_kmer_count
+                    && *stored_secondary_kmer_count == secondary_kmer_count
+            }
+        }
+    }
+
+    /// Returns the number of matches of the given primary index.
+    pub fn row_match_count(&self, primary_index: usize) -> usize {
+        match self {
+            Self::Dense {
+                bits,
+                secondary_kmer_count,
+                rank,
+            } => rank.count_ones(
+                bits,
+                primary_index * secondary_kmer_count..(primary_index + 1) * secondary_kmer_count,
+            ),
+            Self::Sparse(rows) => rows.row_match_count(primary_index),
+            Self::Banded { bits, band, rank } => rank.count_ones(
+                bits,
+                primary_index * band.width()..(primary_index + 1) * band.width(),
+            ),
+            Self::Triangular {
+                bits,
+                kmer_count,
+                rank,
+                mirrored_row_counts,
+            } => {
+                let row_start = triangular_index(*kmer_count, primary_index, 0);
+                rank.count_ones(bits, row_start..row_start + kmer_count - primary_index)
+                    + mirrored_row_counts[primary_index]
+            }
+            #[cfg(feature = "mmap")]
+            Self::Mapped {
+                map,
+                secondary_kmer_count,
+                rank,
+            } => rank.count_ones(
+                BitSlice::<u8, Lsb0>::from_slice(map),
+                primary_index * secondary_kmer_count..(primary_index + 1) * secondary_kmer_count,
+            ),
+            #[cfg(feature = "roaring")]
+            Self::Roaring {
+                bitmap,
+                secondary_kmer_count,
+                ..
+            } => {
+                roaring_rank(bitmap, (primary_index + 1) * secondary_kmer_count)
+                    - roaring_rank(bitmap, primary_index * secondary_kmer_count)
+            }
+        }
+    }
+
+    /// Returns the number of matches of the given primary index whose secondary rc index is below `secondary_rc_index`.
+    ///
+    /// Takes constant time for the bit-based variants, except for the mirrored part of [`Triangular`](Self::Triangular) rows,
+    /// which is scanned bit by bit, and logarithmic time in the length of the row for the sparse variants.
+    pub fn row_rank(&self, primary_index: usize, secondary_rc_index: usize) -> usize {
+        match self {
+            Self::Dense {
+                bits,
+                secondary_kmer_count,
+                rank,
+            } => {
+                let row_start = primary_index * secondary_kmer_count;
+                rank.count_ones(
+                    bits,
+                    row_start..row_start + secondary_rc_index.min(*secondary_kmer_count),
+                )
+            }
+            Self::Sparse(rows) => rows
+                .row(primary_index)
+                .partition_point(|index| index.into_usize() < secondary_rc_index),
+            Self::Banded { bits, band, rank } => {
+                let first = primary_index as isize + band.min_offset;
+                let end_bit =
+                    (secondary_rc_index as isize - first).clamp(0, band.width() as isize) as usize;
+                let row_start = primary_index * band.width();
+                rank.count_ones(bits, row_start..row_start + end_bit)
+            }
+            Self::Triangular {
+                bits,
+                kmer_count,
+                rank,
+                ..
+            } => {
+                let row_start = triangular_index(*kmer_count, primary_index, 0);
+                let row_length = *kmer_count - primary_index;
+                rank.count_ones(
+                    bits,
+                    row_start..row_start + secondary_rc_index.min(row_length),
+                ) + self
+                    .row_range_iter(primary_index, row_length..secondary_rc_index)
+                    .count()
+            }
+            #[cfg(feature = "mmap")]
+            Self::Mapped {
+                map,
+                secondary_kmer_count,
+                rank,
+            } => {
+                let row_start = primary_index * secondary_kmer_count;
+                rank.count_ones(
+                    BitSlice::<u8, Lsb0>::from_slice(map),
+                    row_start..row_start + secondary_rc_index.min(*secondary_kmer_count),
+                )
+            }
+            #[cfg(feature = "roaring")]
+            Self::Roaring {
+                bitmap,
+                secondary_kmer_count,
+                ..
+            } => {
+                let row_start = primary_index * secondary_kmer_count;
+                roaring_rank(
+                    bitmap,
+                    row_start + secondary_rc_index.min(*secondary_kmer_count),
+                ) - roaring_rank(bitmap, row_start)
+            }
+        }
+    }
+
+    /// Returns the secondary rc index of the match of the given primary index with the given rank within the row,
+    /// i.e. the `row_rank + 1`-th smallest, or `None` if the row has at most `row_rank` matches.
+    ///
+    /// Takes logarithmic time in the size of the quadrant for the bit-based variants,
+    /// except for the mirrored part of [`Triangular`](Self::Triangular) rows, which is scanned bit by bit,
+    /// and constant time for the sparse variants.
+    pub fn row_select(&self, primary_index: usize, row_rank: usize) -> Option<usize> {
+        fn select<Store: BitStore>(
+            bits: &BitSlice<Store, Lsb0>,
+            rank: &RankIndex,
+            row: Range<usize>,
+            row_rank: usize,
+        ) -> Option<usize> {
+            rank.select(bits, rank.rank(bits, row.start) + row_rank)
+                .filter(|&index| index < row.end)
+                .map(|index| index - row.start)
+        }
+
+        match self {
+            Self::Dense {
+                bits,
+                secondary_kmer_count,
+                rank,
+            } => select(
+                bits,
+                rank,
+                primary_index * secondary_kmer_count..(primary_index + 1) * secondary_kmer_count,
+                row_rank,
+            ),
+            Self::Sparse(rows) => rows
+                .row(primary_index)
+                .get(row_rank)
+                .map(|index| index.into_usize()),
+            Self::Banded { bits, band, rank } => select(
+                bits,
+                rank,
+                primary_index * band.width()..(primary_index + 1) * band.width(),
+                row_rank,
+            )
+            .map(|offset| (primary_index as isize + band.min_offset + offset as isize) as usize),
+            Self::Triangular {
+                bits,
+                kmer_count,
+                rank,
+                ..
+            } => {
+                let row_start = triangular_index(*kmer_count, primary_index, 0);
+                let row_length = *kmer_count - primary_index;
+                let stored_count = rank.count_ones(bits, row_start..row_start + row_length);
+                if row_rank < stored_count {
+                    select(bits, rank, row_start..row_start + row_length, row_rank)
+                } else {
+                    self.row_range_iter(primary_index, row_length..*kmer_count)
+                        .nth(row_rank - stored_count)
+                }
+            }
+            #[cfg(feature = "mmap")]
+            Self::Mapped {
+                map,
+                secondary_kmer_count,
+                rank,
+            } => select(
+                BitSlice::<u8, Lsb0>::from_slice(map),
+                rank,
+                primary_index * secondary_kmer_count..(primary_index + 1) * secondary_kmer_count,
+                row_rank,
+            ),
+            #[cfg(feature = "roaring")]
+            Self::Roaring {
+                bitmap,
+                secondary_kmer_count,
+                ..
+            } => {
+                let row_start = primary_index * secondary_kmer_count;
+                bitmap
+                    .select((roaring_rank(bitmap, row_start) + row_rank) as u64)
+                    .map(|index| index as usize - row_start)
+                    .filter(|&offset| offset < *secondary_kmer_count)
+            }
+        }
+    }
+
+    /// Iterate over the secondary rc indices that match the given primary index in increasing order.
+    ///
+    /// The iterator is double-ended, so the indices can also be obtained in decreasing order.
+    pub fn row_iter(&self, primary_index: usize) -> QuadrantRowIter<'_, Index> {
+        match self {
+            Self::Dense {
+                bits,
+                secondary_kmer_count,
+                ..
+            } => QuadrantRowIter::Bits {
+                ones: bits[primary_index * secondary_kmer_count
+                    ..(primary_index + 1) * secondary_kmer_count]
+                    .iter_ones(),
+                first_secondary_rc_index: 0,
+            },
+            Self::Sparse(rows) => QuadrantRowIter::Sparse(rows.row(primary_index).iter()),
+            Self::Banded { bits, band, .. } => QuadrantRowIter::Bits {
+                ones: bits[primary_index * band.width()..(primary_index + 1) * band.width()]
+                    .iter_ones(),
+                first_secondary_rc_index: primary_index as isize + band.min_offset,
+            },
+            Self::Triangular {
+                bits, kmer_count, ..
+            } => {
+                let row_start = triangular_index(*kmer_count, primary_index, 0);
+                let row_length = *kmer_count - primary_index;
+                QuadrantRowIter::Triangular {
+                    bits,
+                    kmer_count: *kmer_count,
+                    primary_index,
+                    stored_ones: bits[row_start..row_start + row_length].iter_ones(),
+                    first_stored_secondary_rc_index: 0,
+                    mirrored_secondary_rc_indices: row_length..*kmer_count,
+                }
+            }
+            #[cfg(feature = "mmap")]
+            Self::Mapped {
+                map,
+                secondary_kmer_count,
+                ..
+            } => QuadrantRowIter::Mapped {
+                ones: BitSlice::<u8, Lsb0>::from_slice(map)[primary_index * secondary_kmer_count
+                    ..(primary_index + 1) * secondary_kmer_count]
+                    .iter_ones(),
+                first_secondary_rc_index: 0,
+            },
+            #[cfg(feature = "roaring")]
+            Self::Roaring {
+                bitmap,
+                secondary_kmer_count,
+                ..
+            } => {
+                let row_start = primary_index * secondary_kmer_count;
+                QuadrantRowIter::Roaring {
+                    ones: RoaringRangeIter::new(
+                        bitmap,
+                        row_start..row_start + secondary_kmer_count,
+                    ),
+                    first_bit: row_start,
+                }
+            }
+        }
+    }
+
+    /// Iterate over the secondary rc indices within the given range that match the given primary index in increasing order.
+    ///
+    /// Only the storage of the range is visited.
+    pub fn row_range_iter(
+        &self,
+        primary_index: usize,
+        secondary_rc_indices: Range<usize>,
+    ) -> QuadrantRowIter<'_, Index> {
+        let Range { start, end } = secondary_rc_indices;
+        let end = end.max(start);
+        match self {
+            Self::Dense {
+                bits,
+                secondary_kmer_count,
+                ..
+            } => {
+                let row_start = primary_index * secondary_kmer_count;
+                QuadrantRowIter::Bits {
+                    ones: bits[row_start + start..row_start + end].iter_ones(),
+                    first_secondary_rc_index: start as isize,
+                }
+            }
+            Self::Sparse(rows) => {
+                let row = rows.row(primary_index);
+                let range = row.partition_point(|index| index.into_usize() < start)
+                    ..row.partition_point(|index| index.into_usize() < end);
+                QuadrantRowIter::Sparse(row[range].iter())
+            }
+            Self::Banded { bits, band, .. } => {
+                // The row stores the secondary rc indices `first..first + band.width()`, which may exceed the quadrant.
+                let first = primary_index as isize + band.min_offset;
+                let bit_range = |index: usize| {
+                    (index as isize - first).clamp(0, band.width() as isize) as usize
+                };
+                let (start_bit, end_bit) = (bit_range(start), bit_range(end));
+                let row_start = primary_index * band.width();
+                QuadrantRowIter::Bits {
+                    ones: bits[row_start + start_bit..row_start + end_bit].iter_ones(),
+                    first_secondary_rc_index: first + start_bit as isize,
+                }
+            }
+            Self::Triangular {
+                bits, kmer_count, ..
+            } => {
+                let row_start = triangular_index(*kmer_count, primary_index, 0);
+                let row_length = *kmer_count - primary_index;
+                let stored = start.min(row_length)..end.min(row_length);
+                QuadrantRowIter::Triangular {
+                    bits,
+                    kmer_count: *kmer_count,
+                    primary_index,
+                    stored_ones: bits[row_start + stored.start..row_start + stored.end].iter_ones(),
+                    first_stored_secondary_rc_index: stored.start,
+                    mirrored_secondary_rc_indices: start.max(row_length)..end,
+                }
+            }
+            #[cfg(feature = "mmap")]
+            Self::Mapped {
+                map,
+                secondary_kmer_count,
+                ..
+            } => {
+                let row_start = primary_index * secondary_kmer_count;
+                QuadrantRowIter::Mapped {
+                    ones: BitSlice::<u8, Lsb0>::from_slice(map)[row_start + start..row_start + end]
+                        .iter_ones(),
+                    first_secondary_rc_index: start,
+                }
+            }
+            #[cfg(feature = "roaring")]
+            Self::Roaring {
+                bitmap,
+                secondary_kmer_count,
+                ..
+            } => {
+                let row_start = primary_index * secondary_kmer_count;
+                QuadrantRowIter::Roaring {
+                    ones: RoaringRangeIter::new(bitmap, row_start + start..row_start + end),
+                    first_bit: row_start,
+                }
+            }
+        }
+    }
+
+    /// Returns the total number of matches.
+    pub fn match_count(&self) -> usize {
+        match self {
+            Self::Dense { rank, .. } | Self::Banded { rank, .. } => rank.total_ones(),
+            Self::Sparse(rows) => rows.secondary_indices.len(),
+            Self::Triangular {
+                rank,
+                mirrored_row_counts,
+                ..
+            } => rank.total_ones() + mirrored_row_counts.iter().sum::<usize>(),
+            #[cfg(feature = "mmap")]
+            Self::Mapped { rank, .. } => rank.total_ones(),
+            #[cfg(feature = "roaring")]
+            Self::Roaring { bitmap, .. } => bitmap.len() as usize,
+        }
+    }
+
+    /// Returns the number of bytes occupied by the storage, including memory-mapped bytes.
+    pub fn storage_bytes(&self) -> usize {
+        let bit_bytes = |bits: &BitVec| bits.capacity().div_ceil(8);
+        match self {
+            Self::Dense { bits, rank, .. } | Self::Banded { bits, rank, .. } => {
+                bit_bytes(bits) + rank.heap_bytes()
+            }
+            Self::Sparse(rows) => rows.heap_bytes(),
+            Self::Triangular {
+                bits,
+                rank,
+                mirrored_row_counts,
+                ..
+            } => {
+                bit_bytes(bits)
+                    + rank.heap_bytes()
+                    + mirrored_row_counts.capacity() * size_of::<usize>()
+            }
+            #[cfg(feature = "mmap")]
+            Self::Mapped { map, rank, .. } => map.len() + rank.heap_bytes(),
+            #[cfg(feature = "roaring")]
+            Self::Roaring { bitmap, .. } => bitmap.serialized_size(),
+        }
+    }
+
+    /// Write the storage to disk if it is memory-mapped.
+    #[cfg(feature = "mmap")]
+    pub fn flush(&self) -> std::io::Result<()> {
+        match self {
+            Self::Mapped { map, .. } => map.flush(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Iterate over all matches as `(primary_index, secondary_rc_index)` pairs in row-major order.
+    pub fn iter(&self) -> QuadrantStorageIter<'_, Index> {
+        self.rows_iter(0..self.stored_row_count())
+    }
+
+    /// Returns the number of rows covered by the storage.
+    ///
+    /// This is the number of primary kmers, except that it is zero for bit-based storage without secondary kmers,
+    /// and may be larger for memory-mapped storage, which is padded to whole bytes.
+    fn stored_row_count(&self) -> usize {
+        match self {
+            Self::Dense {
+                bits,
+                secondary_kmer_count,
+                ..
+            } => bits.len().checked_div(*secondary_kmer_count).unwrap_or(0),
+            Self::Sparse(rows) => rows.row_offsets.len() - 1,
+            Self::Banded { bits, band, .. } => bits.len() / band.width(),
+            Self::Triangular { kmer_count, .. } => *kmer_count,
+            #[cfg(feature = "mmap")]
+            Self::Mapped {
+                map,
+                secondary_kmer_count,
+                ..
+            } => (map.len() * 8)
+                .checked_div(*secondary_kmer_count)
+                .unwrap_or(0),
+            #[cfg(feature = "roaring")]
+            Self::Roaring {
+                primary_kmer_count,
+                secondary_kmer_count,
+                ..
+            } => {
+                if *secondary_kmer_count == 0 {
+                    0
+                } else {
+                    *primary_kmer_count
+                }
+            }
+        }
+    }
+
+    /// Iterate over the matches of the given rows as `(primary_index, secondary_rc_index)` pairs in row-major order.
+    ///
+    /// Only the storage of the given rows is visited.
+    pub fn rows_iter(&self, primary_indices: Range<usize>) -> QuadrantStorageIter<'_, Index> {
+        let Range { start, end } = primary_indices;
+        match self {
+            Self::Dense {
+                bits,
+                secondary_kmer_count,
+                ..
+            } => QuadrantStorageIter::Dense {
+                ones: bits[start * secondary_kmer_count..end * secondary_kmer_count].iter_ones(),
+                first_bit: start * secondary_kmer_count,
+                secondary_kmer_count: *secondary_kmer_count,
+            },
+            Self::Sparse(rows) => QuadrantStorageIter::Sparse(rows.rows_iter(start..end)),
+            Self::Banded { bits, band, .. } => QuadrantStorageIter::Banded {
+                ones: bits[start * band.width()..end * band.width()].iter_ones(),
+                first_bit: start * band.width(),
+                band: *band,
+            },
+            Self::Triangular {
+                bits, kmer_count, ..
+            } => triangular_rows_iter(bits, *kmer_count, start..end),
+            #[cfg(feature = "mmap")]
+            Self::Mapped {
+                map,
+                secondary_kmer_count,
+                ..
+            } => QuadrantStorageIter::Mapped {
+                ones: BitSlice::<u8, Lsb0>::from_slice(map)
+                    [start * secondary_kmer_count..end * secondary_kmer_count]
+                    .iter_ones(),
+                first_bit: start * secondary_kmer_count,
+                secondary_kmer_count: *secondary_kmer_count,
+            },
+            #[cfg(feature = "roaring")]
+            Self::Roaring {
+                bitmap,
+                secondary_kmer_count,
+                ..
+            } => QuadrantStorageIter::Roaring {
+                ones: RoaringRangeIter::new(
+                    bitmap,
+                    start * secondary_kmer_count..end * secondary_kmer_count,
+                ),
+                secondary_kmer_count: *secondary_kmer_count,
+            },
+        }
+    }
+}
+
+/// Iterate over the given rows of triangular storage.
+fn triangular_rows_iter<Index>(
+    bits: &BitVec,
+    kmer_count: usize,
+    primary_indices: Range<usize>,
+) -> QuadrantStorageIter<'_, Index> {
+    let primary_index = primary_indices.start;
+    // The row is empty if the range contains no rows.
+    let row_length = if primary_indices.is_empty() {
+        0
+    } else {
+        kmer_count - primary_index
+    };
+    let row_start = if row_length > 0 {
+        triangular_index(kmer_count, primary_index, 0)
+    } else {
+        0
+    };
+    QuadrantStorageIter::Triangular {
+        bits,
+        kmer_count,
+        primary_index,
+        end_primary_index: primary_indices.end,
+        stored_ones: bits[row_start..row_start + row_length].iter_ones(),
+        mirrored_secondary_rc_index: row_length,
+    }
+}
+
+pub(crate) enum QuadrantRowIter<'storage, Index> {
+    Bits {
+        ones: IterOnes<'storage, usize, Lsb0>,
+        /// The secondary rc index of the first bit.
+        first_secondary_rc_index: isize,
+    },
+    Sparse(std::slice::Iter<'storage, Index>),
+    Triangular {
+        bits: &'storage BitVec,
+        kmer_count: usize,
+        primary_index: usize,
+        /// The stored pairs of the row.
+        stored_ones: IterOnes<'storage, usize, Lsb0>,
+        /// The secondary rc index of the first bit iterated over by `stored_ones`.
+        first_stored_secondary_rc_index: usize,
+        /// The candidates of the mirrored pairs of the row, which follow the stored pairs.
+        mirrored_secondary_rc_indices: Range<usize>,
+    },
+    #[cfg(feature = "mmap")]
+    Mapped {
+        ones: IterOnes<'storage, u8, Lsb0>,
+        /// The secondary rc index of the first bit iterated over by `ones`.
+        first_secondary_rc_index: usize,
+    },
+    #[cfg(feature = "roaring")]
+    Roaring {
+        ones: RoaringRangeIter<'storage>,
+        /// The index of the first bit of the row.
+        first_bit: usize,
+    },
+}
+
+impl<Index: StorageIndex> Iterator for QuadrantRowIter<'_, Index> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Bits {
+                ones,
+                first_secondary_rc_index,
+            } => ones
+                .next()
+                .map(|index| (*first_secondary_rc_index + index as isize) as usize),
+            Self::Sparse(iter) => iter.next().map(|index| index.into_usize()),
+            Self::Triangular {
+                bits,
+                kmer_count,
+                primary_index,
+                stored_ones,
+                first_stored_secondary_rc_index,
+                mirrored_secondary_rc_indices,
+            } => stored_ones
+                .next()
+                .map(|index| *first_stored_secondary_rc_index + index)
+                .or_else(|| {
+                    mirrored_secondary_rc_indices.find(|&secondary_rc_index| {
+                        bits[triangular_index(*kmer_count, *primary_index, secondary_rc_index)]
+                    })
+                }),
+            #[cfg(feature = "mmap")]
+            Self::Mapped {
+                ones,
+                first_secondary_rc_index,
+            } => ones.next().map(|index| *first_secondary_rc_index + index),
+            #[cfg(feature = "roaring")]
+            Self::Roaring { ones, first_bit } => ones.next().map(|index| index - *first_bit),
+        }
+    }
+}
+
+impl<Index: StorageIndex> DoubleEndedIterator for QuadrantRowIter<'_, Index> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Bits {
+                ones,
+                first_secondary_rc_index,
+            } => ones
+                .next_back()
+                .map(|index| (*first_secondary_rc_index + index as isize) as usize),
+            Self::Sparse(iter) => iter.next_back().map(|index| index.into_usize()),
+            Self::Triangular {
+                bits,
+                kmer_count,
+                primary_index,
+                stored_ones,
+                first_stored_secondary_rc_index,
+                mirrored_secondary_rc_indices,
+            } => mirrored_secondary_rc_indices
+                .rfind(|&secondary_rc_index| {
+                    bits[triangular_index(*kmer_count, *primary_index, secondary_rc_index)]
+                })
+                .or_else(|| {
+                    stored_ones
+                        .next_back()
+                        .map(|index| *first_stored_secondary_rc_index + index)
+                }),
+            #[cfg(feature = "mmap")]
+            Self::Mapped {
+                ones,
+                first_secondary_rc_index,
+            } => ones
+                .next_back()
+                .map(|index| *first_secondary_rc_index + index),
+            #[cfg(feature = "roaring")]
+            Self::Roaring { ones, first_bit } => ones.next_back().map(|index| index - *first_bit),
+        }
+    }
+}
+
+pub(crate) enum QuadrantStorageIter<'storage, Index> {
+    Dense {
+        ones: IterOnes<'storage, usize, Lsb0>,
+        /// The index of the first bit iterated over by `ones`.
+        first_bit: usize,
+        secondary_kmer_count: usize,
+    },
+    Sparse(SparseRowsIter<'storage, Index>),
+    Banded {
+        ones: IterOnes<'storage, usize, Lsb0>,
+        /// The index of the first bit iterated over by `ones`.
+        first_bit: usize,
+        band: Band,
+    },
+    Triangular {
+        bits: &'storage BitVec,
+        kmer_count: usize,
+        primary_index: usize,
+        /// The end of the range of rows to iterate over.
+        end_primary_index: usize,
+        /// The stored pairs of the current row.
+        stored_ones: IterOnes<'storage, usize, Lsb0>,
+        /// The next candidate of the mirrored pairs of the current row, which follow the stored pairs.
+        mirrored_secondary_rc_index: usize,
+    },
+    #[cfg(feature = "mmap")]
+    Mapped {
+        ones: IterOnes<'storage, u8, Lsb0>,
+        /// The index of the first bit iterated over by `ones`.
+        first_bit: usize,
+        secondary_kmer_count: usize,
+    },
+    #[cfg(feature = "roaring")]
+    Roaring {
+        ones: RoaringRangeIter<'storage>,
+        secondary_kmer_count: usize,
+    },
+}
+
+impl<Index: StorageIndex> Iterator for QuadrantStorageIter<'_, Index> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Dense {
+                ones,
+                first_bit,
+                secondary_kmer_count,
+            } => ones.next().map(|index| {
+                let index = *first_bit + index;
+                (index / *secondary_kmer_count, index % *secondary_kmer_count)
+            }),
+            Self::Sparse(iter) => iter.next(),
+            Self::Banded {
+                ones,
+                first_bit,
+                band,
+            } => ones.next().map(|index| {
+                let index = *first_bit + index;
+                let primary_index = index / band.width();
+                let offset = band.min_offset + (index % band.width()) as isize;
+                (primary_index, (primary_index as isize + offset) as usize)
+            }),
+            Self::Triangular {
+                bits,
+                kmer_count,
+                primary_index,
+                end_primary_index,
+                stored_ones,
+                mirrored_secondary_rc_index,
+            } => loop {
+                if *primary_index >= *end_primary_index {
+                    return None;
+                }
+                if let Some(secondary_rc_index) = stored_ones.next() {
+                    return Some((*primary_index, secondary_rc_index));
+                }
+                if *mirrored_secondary_rc_index < *kmer_count {
+                    let secondary_rc_index = *mirrored_secondary_rc_index;
+                    *mirrored_secondary_rc_index += 1;
+                    if bits[triangular_index(*kmer_count, *primary_index, secondary_rc_index)] {
+                        return Some((*primary_index, secondary_rc_index));
+                    }
+                    continue;
+                }
+
+                *primary_index += 1;
+                if *primary_index < *end_primary_index {
+                    let row_start = triangular_index(*kmer_count, *primary_index, 0);
+                    let row_length = *kmer_count - *primary_index;
+                    *stored_ones = bits[row_start..row_start + row_length].iter_ones();
+                    *mirrored_secondary_rc_index = row_length;
+                }
+            },
+            #[cfg(feature = "mmap")]
+            Self::Mapped {
+                ones,
+                first_bit,
+                secondary_kmer_count,
+            } => ones.next().map(|index| {
+                let index = *first_bit + index;
+                (index / *secondary_kmer_count, index % *secondary_kmer_count)
+            }),
+            #[cfg(feature = "roaring")]
+            Self::Roaring {
+                ones,
+                secondary_kmer_count,
+            } => ones
+                .next()
+                .map(|index| (index / *secondary_kmer_count, index % *secondary_kmer_count)),
+        }
+    }
+}
+
+/// Returns the number of ones of the bitmap below `index`.
+#[cfg(feature = "roaring")]
+fn roaring_rank(bitmap: &RoaringTreemap, index: usize) -> usize {
+    index
+        .checked_sub(1)
+        .map_or(0, |last| bitmap.rank(last as u64) as usize)
+}
+
+/// Iterates over the ones of a Roaring bitmap within a range in both directions.
+#[cfg(feature = "roaring")]
+pub(crate) struct RoaringRangeIter<'storage> {
+    ones: roaring::treemap::Iter<'storage>,
+    /// The number of ones of the range that were not yet returned.
+    remaining: usize,
+}
+
+#[cfg(feature = "roaring")]
+impl<'storage> RoaringRangeIter<'storage> {
+    fn new(bitmap: &'storage RoaringTreemap, range: Range<usize>) -> Self {
+        let mut ones = bitmap.iter();
+        ones.advance_to(range.start as u64);
+        if let Some(last) = range.end.checked_sub(1) {
+            ones.advance_back_to(last as u64);
+        }
+        Self {
+            ones,
+            remaining: roaring_rank(bitmap, range.end)
+                .saturating_sub(roaring_rank(bitmap, range.start)),
+        }
+    }
+}
+
+#[cfg(feature = "roaring")]
+impl Iterator for RoaringRangeIter<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.remaining = self.remaining.checked_sub(1)?;
+        self.ones.next().map(|index| index as usize)
+    }
+}
+
+#[cfg(feature = "roaring")]
+impl DoubleEndedIterator for RoaringRangeIter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.remaining = self.remaining.checked_sub(1)?;
+        self.ones.next_back().map(|index| index as usize)
+    }
+}
+
+/// The unsigned integer type that stores the kmer indices of a [`MatchTable`](crate::MatchTable), either `u32` or `u64`.
+///
+/// [Sparse storage](StorageBackend::Sparse) stores one index per match, and the hash indexes of
+/// [`ConstructionStrategy::HashJoin`](crate::ConstructionStrategy::HashJoin) and [`ConstructionStrategy::SortMerge`](crate::ConstructionStrategy::SortMerge)
+/// store one position per kmer, so `u32` indices take half the memory of `u64` indices.
+/// However, with `u32` indices, the sequences must have fewer than 2^32 kmers,
+/// and the hash join and the sort-merge join are only applicable to sequences shorter than 2^32 characters.
+///
+/// Tables use `u32` indices by default, and `u64` indices if selected with [`MatchTableBuilder::index_type`](crate::MatchTableBuilder::index_type).
+/// The API takes and returns indices as `usize` regardless of the index type.
+///
+/// This trait is sealed and cannot be implemented outside of this crate.
+pub trait StorageIndex: sealed::Sealed + Copy + Ord + Debug + Send + Sync + 'static {
+    /// The number of bits of an index.
+    const BITS: u32;
+
+    /// Convert from `usize`, or return `None` if the value does not fit.
+    fn try_from_usize(value: usize) -> Option<Self>;
+
+    /// Convert from `usize`, panicking if the value does not fit.
+    fn from_usize(value: usize) -> Self {
+        Self::try_from_usize(value).unwrap()
+    }
+
+    /// Convert into `usize`, panicking if the value does not fit.
+    fn into_usize(self) -> usize;
+}
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for u32 {}
+    impl Sealed for u64 {}
+}
+
+impl StorageIndex for u32 {
+    const BITS: u32 = u32::BITS;
+
+    fn try_from_usize(value: usize) -> Option<Self> {
+        value.try_into().ok()
+    }
+
+    fn into_usize(self) -> usize {
+        self as usize
+    }
+}
+
+impl StorageIndex for u64 {
+    const BITS: u32 = u64::BITS;
+
+    fn try_from_usize(value: usize) -> Option<Self> {
+        value.try_into().ok()
+    }
+
+    fn into_usize(self) -> usize {
+        self.try_into().unwrap()
+    }
+}
+
+/// The matches of a quadrant stored as adjacency lists per primary index (compressed sparse rows).
+pub(crate) struct SparseRows<Index> {
+    /// The matches of primary index `i` are stored in `secondary_indices[row_offsets[i]..row_offsets[i + 1]]`.
+    row_offsets: Vec<usize>,
+    secondary_indices: Vec<Index>,
+}
+
+pub(crate) struct SparseRowsBuilder<Index> {
+    primary_kmer_count: usize,
+    matches: Vec<(Index, Index)>,
+}
+
+impl<Index: StorageIndex> SparseRowsBuilder<Index> {
+    pub fn new(primary_kmer_count: usize) -> Self {
+        Self {
+            primary_kmer_count,
+            matches: Vec::new(),
+        }
+    }
+
+    pub fn insert(&mut self, primary_index: usize, secondary_rc_index: usize) {
+        self.matches.push((
+            Index::from_usize(primary_index),
+            Index::from_usize(secondary_rc_index),
+        ));
+    }
+
+    pub fn build(mut self) -> SparseRows<Index> {
+        self.matches.sort_unstable();
+        self.matches.dedup();
+
+        let mut row_offsets = Vec::with_capacity(self.primary_kmer_count + 1);
+        row_offsets.push(0);
+        let mut matches_iter = self.matches.iter().peekable();
+        for primary_index in 0..self.primary_kmer_count {
+            let mut offset = *row_offsets.last().unwrap();
+            while matches_iter
+                .next_if(|(match_primary_index, _)| {
+                    match_primary_index.into_usize() == primary_index
+                })
+                .is_some()
+            {
+                offset += 1;
+            }
+            row_offsets.push(offset);
+        }
+        debug_assert!(matches_iter.next().is_none());
+
+        let secondary_indices = self
+            .matches
+            .into_iter()
+            .map(|(_, secondary_rc_index)| secondary_rc_index)
+            .collect();
+
+        SparseRows {
+            row_offsets,
+            secondary_indices,
+        }
+    }
+}
+
+impl<Index: StorageIndex> SparseRows<Index> {
+    pub fn has_match(&self, primary_index: usize, secondary_rc_index: usize) -> bool {
+        self.row(primary_index)
+            .binary_search(&Index::from_usize(secondary_rc_index))
+            .is_ok()
+    }
+
+    /// Create rows from their raw parts, returning `None` if the parts are inconsistent.
+    pub fn from_parts(row_offsets: Vec<usize>, secondary_indices: Vec<Index>) -> Option<Self> {
+        let consistent = row_offsets.first() == Some(&0)
+            && row_offsets.last() == Some(&secondary_indices.len())
+            && row_offsets.is_sorted()
+            && row_offsets.windows(2).all(|row| {
+                secondary_indices[row[0]..row[1]]
+                    .windows(2)
+                    .all(|pair| pair[0] < pair[1])
+            });
+        consistent.then_some(Self {
+            row_offsets,
+            secondary_indices,
+        })
+    }
+
+    pub fn row_offsets(&self) -> &[usize] {
+        &self.row_offsets
+    }
+
+    pub fn secondary_indices(&self) -> &[Index] {
+        &self.secondary_indices
+    }
+
+    pub fn row_match_count(&self, primary_index: usize) -> usize {
+        self.row_offsets[primary_index + 1] - self.row_offsets[primary_index]
+    }
+
+    pub fn heap_bytes(&self) -> usize {
+        self.row_offsets.capacity() * size_of::<usize>()
+            + self.secondary_indices.capacity() * size_of::<Index>()
+    }
+
+    pub fn row(&self, primary_index: usize) -> &[Index] {
+        &self.secondary_indices
+            [self.row_offsets[primary_index]..self.row_offsets[primary_index + 1]]
+    }
+
+    pub fn rows_iter(&self, primary_indices: Range<usize>) -> SparseRowsIter<'_, Index> {
+        SparseRowsIter {
+            rows: self,
+            primary_index: primary_indices.start,
+            offset: self.row_offsets[primary_indices.start],
+            end_offset: self.row_offsets[primary_indices.end],
+        }
+    }
+}
+
+pub(crate) struct SparseRowsIter<'rows, Index> {
+    rows: &'rows SparseRows<Index>,
+    primary_index: usize,
+    offset: usize,
+    /// The offset of the end of the last row to iterate over.
+    end_offset: usize,
+}
+
+impl<Index: StorageIndex> Iterator for SparseRowsIter<'_, Index> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.end_offset {
+            return None;
+        }
+        let secondary_rc_index = self.rows.secondary_indices[self.offset].into_usize();
+        while self.rows.row_offsets[self.primary_index + 1] <= self.offset {
+            self.primary_index += 1;
+        }
+        self.offset += 1;
+        Some((self.primary_index, secondary_rc_index))
+    }
+}
@@ -0,0 +1,223 @@
+//! Enumeration of matches without materializing a match table.
+//!
+//! The matches are searched by a background thread, so streaming is not available on `wasm32`,
+//! where threads cannot be spawned.
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    sync::mpsc::{Receiver, SyncSender, sync_channel},
+    thread::JoinHandle,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use compact_genome::interface::{alphabet::Alphabet, sequence::GenomeSequence};
+
+use crate::Quadrant;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{
+    ContigLayout, MatchTableBuilder, ProgressEvent, StorageIndex,
+    construction::{MatchSink, QuadrantSinks, Texts, find_matches},
+};
+
+/// The number of matches sent from the search thread to the iterator at once.
+#[cfg(not(target_arch = "wasm32"))]
+const BATCH_SIZE: usize = 1024;
+
+/// The number of batches that the search thread may compute ahead of the iterator.
+#[cfg(not(target_arch = "wasm32"))]
+const CHANNEL_CAPACITY: usize = 16;
+
+/// A match between a primary kmer and a reverse-complemented secondary kmer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Match {
+    /// The quadrant of the match.
+    pub quadrant: Quadrant,
+    /// The index of the primary kmer.
+    pub primary_index: usize,
+    /// The index of the secondary kmer in the reverse complement of the secondary genome.
+    pub secondary_rc_index: usize,
+}
+
+/// Enumerate all error-free template switch inners of the given minimum length between a pair of genome strings,
+/// without storing them in a [`MatchTable`](crate::MatchTable).
+///
+/// See [`MatchTableBuilder::stream`] for details.
+///
+/// # Panics
+///
+/// Panics if [`MatchTableBuilder::try_stream`] returns an error.
+///
+/// # Example
+///
+/// ```rust
+/// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+/// use compact_genome::implementation::vec_sequence::VectorGenome;
+/// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+/// use template_switch_error_free_inners::{Match, Quadrant, find_matches_streaming};
+///
+/// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+/// let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+/// let mut matches: Vec<_> = find_matches_streaming(
+///     reference.as_genome_subsequence(),
+///     query.as_genome_subsequence(),
+///     4,
+/// )
+/// .collect();
+/// matches.sort_unstable();
+///
+/// assert_eq!(
+///     matches,
+///     vec![
+///         Match {
+///             quadrant: Quadrant::ReferenceReference,
+///             primary_index: 1,
+///             secondary_rc_index: 2,
+///         },
+///         Match {
+///             quadrant: Quadrant::ReferenceReference,
+///             primary_index: 7,
+///             secondary_rc_index: 8,
+///         },
+///     ],
+/// );
+/// ```
+#[cfg(not(target_arch = "wasm32"))]
+pub fn find_matches_streaming<
+    AlphabetType: Alphabet,
+    GenomeSubsequence: GenomeSequence<AlphabetType, GenomeSubsequence> + ?Sized,
+>(
+    reference: &GenomeSubsequence,
+    query: &GenomeSubsequence,
+    minimum_length: usize,
+) -> MatchStream {
+    MatchTableBuilder::new(minimum_length).stream(reference, query)
+}
+
+/// An iterator over the matches found by a background thread.
+///
+/// Created by [`MatchTableBuilder::stream`] and [`find_matches_streaming`].
+/// Dropping the iterator stops the search early.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct MatchStream {
+    receiver: Receiver<Vec<Match>>,
+    batch: std::vec::IntoIter<Match>,
+    search: Option<JoinHandle<()>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl MatchStream {
+    /// Start searching the matches of the given texts in a background thread.
+    pub(crate) fn spawn(
+        texts: Texts,
+        contigs: [ContigLayout; 2],
+        options: MatchTableBuilder<impl StorageIndex>,
+    ) -> Self {
+        let (sender, receiver) = sync_channel(CHANNEL_CAPACITY);
+        let search = std::thread::spawn(move || {
+            let [
+                mut reference_reference,
+                mut reference_query,
+                mut query_reference,
+                mut query_query,
+            ] = Quadrant::ALL.map(|quadrant| ChannelSink::new(quadrant, sender.clone()));
+            find_matches(
+                &texts,
+                [&contigs[0], &contigs[1]],
+                &options,
+                QuadrantSinks {
+                    reference_reference: &mut reference_reference,
+                    reference_query: &mut reference_query,
+                    query_reference: &mut query_reference,
+                    query_query: &mut query_query,
+                },
+            );
+            options.report(ProgressEvent::Finished);
+        });
+
+        Self {
+            receiver,
+            batch: Vec::new().into_iter(),
+            search: Some(search),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Iterator for MatchStream {
+    type Item = Match;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(result) = self.batch.next() {
+                return Some(result);
+            }
+
+            match self.receiver.recv() {
+                Ok(batch) => self.batch = batch.into_iter(),
+                Err(_) => {
+                    // All senders are dropped, so the search has ended.
+                    if let Some(search) = self.search.take() {
+                        if let Err(panic) = search.join() {
+                            std::panic::resume_unwind(panic);
+                        }
+                    }
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+/// Sends the matches of a quadrant to a [`MatchStream`] in batches.
+#[cfg(not(target_arch = "wasm32"))]
+struct ChannelSink {
+    quadrant: Quadrant,
+    batch: Vec<Match>,
+    sender: SyncSender<Vec<Match>>,
+    closed: bool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ChannelSink {
+    fn new(quadrant: Quadrant, sender: SyncSender<Vec<Match>>) -> Self {
+        Self {
+            quadrant,
+            batch: Vec::with_capacity(BATCH_SIZE),
+            sender,
+            closed: false,
+        }
+    }
+
+    fn flush(&mut self) {
+        if !self.batch.is_empty() && !self.closed {
+            let batch = std::mem::replace(&mut self.batch, Vec::with_capacity(BATCH_SIZE));
+            // The receiver is dropped if the stream is dropped.
+            self.closed = self.sender.send(batch).is_err();
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl MatchSink for ChannelSink {
+    fn insert(&mut self, primary_index: usize, secondary_rc_index: usize) {
+        self.batch.push(Match {
+            quadrant: self.quadrant,
+            primary_index,
+            secondary_rc_index,
+        });
+        if self.batch.len() >= BATCH_SIZE {
+            self.flush();
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Drop for ChannelSink {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
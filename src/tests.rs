@@ -1,10 +1,26 @@
 use compact_genome::{
-    implementation::{alphabets::dna_alphabet::DnaAlphabet, vec_sequence::VectorGenome},
+    implementation::{
+        alphabets::{
+            dna_alphabet::DnaAlphabet, dna_iupac_nucleic_acid_alphabet::DnaIupacNucleicAcidAlphabet,
+        },
+        vec_sequence::VectorGenome,
+    },
     interface::sequence::{GenomeSequence, OwnedGenomeSequence},
 };
+use suffix::SuffixTable;
 use traitsequence::interface::Sequence;
 
-use crate::MatchTable;
+use crate::{
+    AmbiguityPolicy, CandidateConstraints, ChainingParameters, ConstructionStrategy,
+    ContigPosition, DetectionParameters, IndexBackend, Match, MatchOrientation, MatchTable,
+    MatchTableBuilder, MatchTableError, ProgressEvent, Quadrant, Quadrants, SharedMatchTable,
+    StorageBackend, TemplateSwitchCandidate, WindowScanner, XDropParameters,
+    detect_template_switches, find_matches_streaming,
+    index::{FmIndex, KmerPacker, SequenceIndex},
+    packed::PackedText,
+    rc_index_to_forward, rc_index_to_forward_end,
+    storage::{QuadrantStorageBuilder, SparseRowsBuilder, StorageIndex},
+};
 
 #[test]
 fn reference_reference() {
@@ -113,3 +129,6430 @@ fn query_query() {
         }
     }
 }
+
+#[test]
+fn sparse_storage_equals_dense_storage() {
+    let reference =
+        VectorGenome::<DnaAlphabet>::from_slice_u8(b"ACGTTGCAAGGCTTAGCCGATAAGCTTGCAGCTA").unwrap();
+    let query = VectorGenome::from_slice_u8(b"TTGCAAGCTTATCGGCTAAGCCTTGCAACGTA").unwrap();
+    let dense = MatchTable::new_with_storage(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        3,
+        StorageBackend::Dense,
+    );
+    let sparse = MatchTable::new_with_storage(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        3,
+        StorageBackend::Sparse,
+    );
+
+    for reference_index in 0..reference.len() - 2 {
+        for reference_rc_index in 0..reference.len() - 2 {
+            assert_eq!(
+                dense.has_reference_reference_match(reference_index, reference_rc_index),
+                sparse.has_reference_reference_match(reference_index, reference_rc_index),
+            );
+        }
+        for query_rc_index in 0..query.len() - 2 {
+            assert_eq!(
+                dense.has_reference_query_match(reference_index, query_rc_index),
+                sparse.has_reference_query_match(reference_index, query_rc_index),
+            );
+        }
+    }
+
+    for query_index in 0..query.len() - 2 {
+        for reference_rc_index in 0..reference.len() - 2 {
+            assert_eq!(
+                dense.has_query_reference_match(query_index, reference_rc_index),
+                sparse.has_query_reference_match(query_index, reference_rc_index),
+            );
+        }
+        for query_rc_index in 0..query.len() - 2 {
+            assert_eq!(
+                dense.has_query_query_match(query_index, query_rc_index),
+                sparse.has_query_query_match(query_index, query_rc_index),
+            );
+        }
+    }
+}
+
+#[test]
+fn match_iterators_equal_bit_scan() {
+    let reference =
+        VectorGenome::<DnaAlphabet>::from_slice_u8(b"ACGTTGCAAGGCTTAGCCGATAAGCTTGCAGCTA").unwrap();
+    let query = VectorGenome::from_slice_u8(b"TTGCAAGCTTATCGGCTAAGCCTTGCAACGTA").unwrap();
+
+    for storage in [
+        StorageBackend::Dense,
+        StorageBackend::Sparse,
+        StorageBackend::Symmetric,
+    ] {
+        let matches = MatchTable::new_with_storage(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+            3,
+            storage,
+        );
+
+        for quadrant in Quadrant::ALL {
+            let mut expected = Vec::new();
+            for primary_index in 0..matches.primary_kmer_count(quadrant) {
+                for secondary_rc_index in 0..matches.secondary_kmer_count(quadrant) {
+                    if matches.has_match(quadrant, primary_index, secondary_rc_index) {
+                        expected.push((primary_index, secondary_rc_index));
+                    }
+                }
+            }
+
+            assert!(!expected.is_empty(), "{quadrant:?}");
+            assert_eq!(
+                matches.matches(quadrant).collect::<Vec<_>>(),
+                expected,
+                "{storage:?} {quadrant:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn maximal_matches() {
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGGTAACCCCCA").unwrap();
+    let query = VectorGenome::from_slice_u8(b"ACCCCCCA").unwrap();
+
+    for storage in [
+        StorageBackend::Dense,
+        StorageBackend::Sparse,
+        StorageBackend::Symmetric,
+    ] {
+        let matches = MatchTable::new_with_storage(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+            4,
+            storage,
+        );
+
+        // The reverse-complemented reference is `TGGGGGTTACCCCCT`.
+        assert_eq!(
+            matches
+                .maximal_matches(Quadrant::ReferenceReference)
+                .collect::<Vec<_>>(),
+            vec![
+                (1, 1, 6),
+                (1, 2, 4),
+                (2, 1, 4),
+                (8, 8, 6),
+                (9, 10, 4),
+                (10, 9, 4)
+            ],
+        );
+        // The reverse-complemented query is `TGGGGGGT`.
+        assert_eq!(
+            matches
+                .maximal_matches(Quadrant::ReferenceQuery)
+                .collect::<Vec<_>>(),
+            vec![(1, 1, 5), (1, 2, 6), (1, 3, 4), (2, 1, 4)],
+        );
+    }
+}
+
+#[cfg(feature = "roaring")]
+#[test]
+fn roaring_storage_equals_dense_storage() {
+    let reference =
+        VectorGenome::<DnaAlphabet>::from_slice_u8(&pseudo_random_dna(600, 43)).unwrap();
+    let query = VectorGenome::from_slice_u8(&pseudo_random_dna(500, 44)).unwrap();
+
+    let dense = MatchTable::new(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        6,
+    );
+    let roaring = MatchTableBuilder::new(6)
+        .storage(StorageBackend::Roaring)
+        .build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+
+    for quadrant in Quadrant::ALL {
+        let matches: Vec<_> = dense.matches(quadrant).collect();
+        assert!(!matches.is_empty());
+        assert_eq!(roaring.matches(quadrant).collect::<Vec<_>>(), matches);
+        for primary_index in 0..dense.primary_kmer_count(quadrant) {
+            assert_eq!(
+                roaring.match_count(quadrant, primary_index),
+                dense.match_count(quadrant, primary_index)
+            );
+            assert!(
+                roaring
+                    .row_matches(quadrant, primary_index)
+                    .eq(dense.row_matches(quadrant, primary_index))
+            );
+        }
+        // Columns are iterated as reversed rows of the transposed quadrant.
+        for secondary_rc_index in 0..dense.secondary_kmer_count(quadrant) {
+            assert!(
+                roaring
+                    .column_matches(quadrant, secondary_rc_index)
+                    .eq(dense.column_matches(quadrant, secondary_rc_index))
+            );
+        }
+        for &(primary_index, secondary_rc_index) in &matches {
+            assert!(roaring.has_match(quadrant, primary_index, secondary_rc_index));
+            assert!(!roaring.has_match(quadrant, primary_index, (secondary_rc_index + 1) % 3));
+        }
+        assert!(
+            roaring
+                .matches_in_primary_range(quadrant, 100..200)
+                .eq(dense.matches_in_primary_range(quadrant, 100..200))
+        );
+        let index = roaring.rank_select_index(quadrant);
+        for (n, &entry) in matches.iter().enumerate() {
+            assert_eq!(index.nth_match(n), Some(entry));
+            assert_eq!(index.rank(entry.0, entry.1), n);
+        }
+
+        let statistics = roaring.statistics();
+        let dense_statistics = dense.statistics();
+        let quadrant_statistics = &statistics.quadrants[quadrant as usize];
+        assert_eq!(quadrant_statistics.match_count, matches.len());
+        assert!(
+            quadrant_statistics.storage_bytes
+                < dense_statistics.quadrants[quadrant as usize].storage_bytes
+        );
+    }
+
+    // Roaring storage is written like sparse storage.
+    let mut binary = Vec::new();
+    roaring.write_binary(&mut binary).unwrap();
+    let loaded = MatchTable::<u32>::read_binary(binary.as_slice()).unwrap();
+    let mut sparse_binary = Vec::new();
+    MatchTableBuilder::new(6)
+        .storage(StorageBackend::Sparse)
+        .build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        )
+        .write_binary(&mut sparse_binary)
+        .unwrap();
+    assert_eq!(binary, sparse_binary);
+    for quadrant in Quadrant::ALL {
+        assert!(loaded.matches(quadrant).eq(dense.matches(quadrant)));
+    }
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn mapped_storage_equals_dense_storage() {
+    let reference =
+        VectorGenome::<DnaAlphabet>::from_slice_u8(b"ACGTTGCAAGGCTTAGCCGATAAGCTTGCAGCTA").unwrap();
+    let query = VectorGenome::from_slice_u8(b"TTGCAAGCTTATCGGCTAAGCCTTGCAACGTA").unwrap();
+    let path = std::env::temp_dir().join(format!(
+        "tsefi-mapped-storage-test-{}.bin",
+        std::process::id()
+    ));
+
+    let dense = MatchTable::new(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        3,
+    );
+    let mapped = MatchTable::new_mmap(
+        &path,
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        3,
+    )
+    .unwrap();
+
+    for quadrant in Quadrant::ALL {
+        assert_eq!(
+            dense.matches(quadrant).collect::<Vec<_>>(),
+            mapped.matches(quadrant).collect::<Vec<_>>(),
+            "{quadrant:?}"
+        );
+    }
+
+    let mut binary = Vec::new();
+    mapped.write_binary(&mut binary).unwrap();
+    let loaded = MatchTable::<u32>::read_binary(binary.as_slice()).unwrap();
+    for quadrant in Quadrant::ALL {
+        assert_eq!(
+            dense.matches(quadrant).collect::<Vec<_>>(),
+            loaded.matches(quadrant).collect::<Vec<_>>(),
+            "{quadrant:?}"
+        );
+    }
+
+    drop(mapped);
+    std::fs::remove_file(path).unwrap();
+}
+
+/// Generate a deterministic pseudo-random DNA string.
+fn pseudo_random_dna(length: usize, seed: u64) -> Vec<u8> {
+    let mut state = seed;
+    (0..length)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            b"ACGT"[(state >> 62) as usize]
+        })
+        .collect()
+}
+
+/// Compute the matches of a quadrant by hashing the kmers of the primary sequence.
+fn hashed_matches(primary: &[u8], secondary: &[u8], k: usize) -> Vec<(usize, usize)> {
+    let secondary_rc: Vec<u8> = secondary
+        .iter()
+        .rev()
+        .map(|character| match character {
+            b'A' => b'T',
+            b'C' => b'G',
+            b'G' => b'C',
+            b'T' => b'A',
+            _ => unreachable!(),
+        })
+        .collect();
+    let mut primary_kmers = std::collections::HashMap::<_, Vec<_>>::new();
+    for (primary_index, kmer) in primary.windows(k).enumerate() {
+        primary_kmers.entry(kmer).or_default().push(primary_index);
+    }
+
+    let mut matches = Vec::new();
+    for (secondary_rc_index, kmer) in secondary_rc.windows(k).enumerate() {
+        for primary_index in primary_kmers.get(kmer).into_iter().flatten() {
+            matches.push((*primary_index, secondary_rc_index));
+        }
+    }
+    matches.sort_unstable();
+    matches
+}
+
+#[test]
+fn long_sequences_equal_hashed_matches() {
+    let reference_ascii = pseudo_random_dna(10_000, 1);
+    let query_ascii = pseudo_random_dna(9_000, 2);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+    let matches = MatchTable::new_with_storage(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        7,
+        StorageBackend::Sparse,
+    );
+
+    for quadrant in Quadrant::ALL {
+        let primary = if quadrant.primary_is_reference() {
+            &reference_ascii
+        } else {
+            &query_ascii
+        };
+        let secondary = if quadrant.secondary_is_reference() {
+            &reference_ascii
+        } else {
+            &query_ascii
+        };
+        assert_eq!(
+            matches.matches(quadrant).collect::<Vec<_>>(),
+            hashed_matches(primary, secondary, 7),
+            "{quadrant:?}"
+        );
+    }
+}
+
+#[cfg(feature = "fasta")]
+#[test]
+fn fasta_with_multiple_records_is_rejected() {
+    use crate::io::fasta::{FastaError, read_fasta_sequence};
+
+    let path = std::env::temp_dir().join(format!(
+        "tsefi-fasta-multiple-records-test-{}.fa",
+        std::process::id()
+    ));
+    std::fs::write(&path, ">a\nACGT\n>b\nTTGCA\n").unwrap();
+
+    let result = read_fasta_sequence::<DnaAlphabet>(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(
+        result,
+        Err(FastaError::RecordCount {
+            record_count: 2,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn mismatches_equal_brute_force() {
+    let reference_ascii = pseudo_random_dna(300, 3);
+    let query_ascii = pseudo_random_dna(250, 4);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+    let query_rc: Vec<u8> = query.reverse_complement_iter().map(u8::from).collect();
+
+    for max_mismatches in 0..3 {
+        let matches = MatchTable::new_with_mismatches(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+            7,
+            max_mismatches,
+        );
+
+        let mut expected = Vec::new();
+        for (reference_index, reference_kmer) in reference_ascii.windows(7).enumerate() {
+            for (query_rc_index, query_rc_kmer) in query_rc.windows(7).enumerate() {
+                let mismatches = reference_kmer
+                    .iter()
+                    .zip(query_rc_kmer)
+                    .filter(|(a, b)| a != b)
+                    .count();
+                if mismatches <= max_mismatches {
+                    expected.push((reference_index, query_rc_index));
+                }
+            }
+        }
+
+        assert_eq!(
+            matches.reference_query_matches().collect::<Vec<_>>(),
+            expected,
+            "{max_mismatches}"
+        );
+    }
+}
+
+#[test]
+fn fm_index_positions_equal_suffix_table_positions() {
+    let text = pseudo_random_dna(2_000, 5);
+    let suffix_table = SuffixTable::new(std::str::from_utf8(&text).unwrap());
+    let fm_index = FmIndex::new(&text);
+
+    for pattern in text.windows(5).step_by(7).chain([
+        b"A".as_slice(),
+        b"ACGTACGTACGT".as_slice(),
+        b"NNN".as_slice(),
+    ]) {
+        let mut expected: Vec<_> = SequenceIndex::positions(&suffix_table, pattern).collect();
+        let mut actual: Vec<_> = fm_index.positions(pattern).collect();
+        expected.sort_unstable();
+        actual.sort_unstable();
+        assert_eq!(actual, expected, "{pattern:?}");
+    }
+}
+
+#[test]
+fn fm_index_backend_equals_suffix_table_backend() {
+    let reference_ascii = pseudo_random_dna(3_000, 6);
+    let query_ascii = pseudo_random_dna(2_500, 7);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+
+    let suffix_table = MatchTable::new_with_index_backend(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        6,
+        IndexBackend::SuffixTable,
+    );
+    let fm_index = MatchTable::new_with_index_backend(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        6,
+        IndexBackend::FmIndex,
+    );
+
+    for quadrant in Quadrant::ALL {
+        assert_eq!(
+            suffix_table.matches(quadrant).collect::<Vec<_>>(),
+            fm_index.matches(quadrant).collect::<Vec<_>>(),
+            "{quadrant:?}"
+        );
+    }
+}
+
+#[test]
+fn hash_join_equals_index_lookup() {
+    let reference_ascii = pseudo_random_dna(3_000, 8);
+    let query_ascii = pseudo_random_dna(2_500, 9);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+
+    for minimum_length in [3, 6, 32, 33] {
+        let index_lookup = MatchTable::new_with_strategy(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+            minimum_length,
+            ConstructionStrategy::IndexLookup,
+        );
+        let hash_join = MatchTable::new_with_strategy(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+            minimum_length,
+            ConstructionStrategy::HashJoin,
+        );
+
+        for quadrant in Quadrant::ALL {
+            assert_eq!(
+                index_lookup.matches(quadrant).collect::<Vec<_>>(),
+                hash_join.matches(quadrant).collect::<Vec<_>>(),
+                "{minimum_length} {quadrant:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn hash_join_packs_fixed_and_long_kmers() {
+    // Over the alphabet `AT`, each character is packed into a single bit,
+    // so kmers longer than 32 characters are packed as well.
+    let text: Vec<_> = pseudo_random_dna(1_000, 12)
+        .into_iter()
+        .map(|character| {
+            if b"AC".contains(&character) {
+                b'A'
+            } else {
+                b'T'
+            }
+        })
+        .collect();
+    let mut reference_ascii = text.clone();
+    reference_ascii.extend(
+        text[100..300]
+            .iter()
+            .rev()
+            .map(|&character| if character == b'A' { b'T' } else { b'A' }),
+    );
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&text[500..]).unwrap();
+
+    for minimum_length in [1, 2, 7, 16, 31, 32, 33, 40, 64] {
+        assert_eq!(
+            ConstructionStrategy::HashJoin.resolve::<u32>(
+                &reference_ascii,
+                &text[500..],
+                minimum_length,
+                0,
+                None
+            ),
+            ConstructionStrategy::HashJoin,
+        );
+        let index_lookup = MatchTable::new_with_strategy(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+            minimum_length,
+            ConstructionStrategy::IndexLookup,
+        );
+        let hash_join = MatchTable::new_with_strategy(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+            minimum_length,
+            ConstructionStrategy::HashJoin,
+        );
+        assert!(
+            index_lookup
+                .matches(Quadrant::ReferenceReference)
+                .next()
+                .is_some()
+        );
+        assert!(
+            index_lookup.diff(&hash_join).unwrap().is_empty(),
+            "{minimum_length}"
+        );
+    }
+}
+
+#[test]
+fn fixed_length_packing_equals_packing_each_kmer() {
+    let mut text = pseudo_random_dna(300, 15);
+    text[100] = b'N';
+    macro_rules! assert_packs_fixed_length {
+        ($($length:literal)*) => {$(
+            let packer = KmerPacker::new([&b"ACGT"[..]], $length);
+            let expected: Vec<_> = text.windows($length).map(|kmer| packer.pack(kmer)).collect();
+            assert_eq!(
+                packer.pack_all_fixed::<$length>(text.iter().copied()).collect::<Vec<_>>(),
+                expected,
+                "{}",
+                $length
+            );
+            assert_eq!(packer.pack_all(text.iter().copied()).collect::<Vec<_>>(), expected);
+        )*};
+    }
+    assert_packs_fixed_length!(1 2 3 5 8 13 21 31 32);
+}
+
+#[test]
+fn joined_kmers_equal_looked_up_kmers() {
+    let reference_ascii = pseudo_random_dna(400, 16);
+    let mut query_ascii = pseudo_random_dna(300, 17);
+    query_ascii.extend(reference_ascii[100..200].iter().rev().map(|&character| {
+        b"TGCA"[b"ACGT"
+            .iter()
+            .position(|&other| other == character)
+            .unwrap()]
+    }));
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+
+    // Each minimum length of up to 32 is joined by its own specialisation.
+    for minimum_length in 1..=33 {
+        for builder in [
+            MatchTableBuilder::new(minimum_length),
+            MatchTableBuilder::new(minimum_length).parallel(true),
+            MatchTableBuilder::new(minimum_length).quadrants(Quadrants::REFERENCE_QUERY),
+            MatchTableBuilder::new(minimum_length).minimizer_window(3),
+            MatchTableBuilder::new(minimum_length).band(-200, 50),
+        ] {
+            let build = |strategy| {
+                builder.clone().strategy(strategy).build(
+                    reference.as_genome_subsequence(),
+                    query.as_genome_subsequence(),
+                )
+            };
+            let index_lookup = build(ConstructionStrategy::IndexLookup);
+            let hash_join = build(ConstructionStrategy::HashJoin);
+            assert!(
+                index_lookup.diff(&hash_join).unwrap().is_empty(),
+                "{builder:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn sort_merge_equals_index_lookup() {
+    let reference_ascii = pseudo_random_dna(3_000, 13);
+    // The query contains the reverse complement of a part of the reference.
+    let mut query_ascii = pseudo_random_dna(2_500, 14);
+    query_ascii.extend(
+        reference_ascii[1_000..1_400]
+            .iter()
+            .rev()
+            .map(|&character| {
+                b"TGCA"[b"ACGT"
+                    .iter()
+                    .position(|&other| other == character)
+                    .unwrap()]
+            }),
+    );
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+
+    let builders = [
+        MatchTableBuilder::new(5),
+        MatchTableBuilder::new(5).parallel(true),
+        MatchTableBuilder::new(5).quadrants(Quadrants::REFERENCE_QUERY),
+        MatchTableBuilder::new(6).max_kmer_occurrences(3),
+        MatchTableBuilder::new(6).minimizer_window(4),
+        MatchTableBuilder::new(7).band(-500, 300),
+        MatchTableBuilder::new(32),
+        // Canonical matching falls back to the hash join.
+        MatchTableBuilder::new(5).canonical(true),
+    ];
+    for builder in builders {
+        let index_lookup = builder
+            .clone()
+            .strategy(ConstructionStrategy::IndexLookup)
+            .build(
+                reference.as_genome_subsequence(),
+                query.as_genome_subsequence(),
+            );
+        let sort_merge = builder
+            .clone()
+            .strategy(ConstructionStrategy::SortMerge)
+            .build(
+                reference.as_genome_subsequence(),
+                query.as_genome_subsequence(),
+            );
+        assert!(
+            index_lookup.diff(&sort_merge).unwrap().is_empty(),
+            "{builder:?}"
+        );
+    }
+
+    let matches = MatchTableBuilder::new(32)
+        .strategy(ConstructionStrategy::SortMerge)
+        .build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+    assert!(matches.has_reference_query_match(1_000, 0));
+
+    assert_eq!(
+        ConstructionStrategy::SortMerge.resolve::<u32>(b"ACGT", b"ACGT", 32, 0, None),
+        ConstructionStrategy::SortMerge
+    );
+    assert_eq!(
+        ConstructionStrategy::SortMerge.resolve::<u32>(b"ACGT", b"ACGT", 33, 0, None),
+        ConstructionStrategy::IndexLookup
+    );
+    assert_eq!(
+        ConstructionStrategy::SortMerge.resolve::<u32>(b"ACGT", b"ACGT", 8, 1, None),
+        ConstructionStrategy::IndexLookup
+    );
+}
+
+#[test]
+fn automatic_strategy_resolution() {
+    let short = b"ACGTACGT".as_slice();
+    assert_eq!(
+        ConstructionStrategy::Automatic.resolve::<u32>(short, short, 32, 0, None),
+        ConstructionStrategy::HashJoin
+    );
+    assert_eq!(
+        ConstructionStrategy::Automatic.resolve::<u32>(short, short, 33, 0, None),
+        ConstructionStrategy::IndexLookup
+    );
+    assert_eq!(
+        ConstructionStrategy::Automatic.resolve::<u32>(short, short, 8, 1, None),
+        ConstructionStrategy::IndexLookup
+    );
+    assert_eq!(
+        ConstructionStrategy::HashJoin.resolve::<u32>(short, b"ACGTN", 32, 0, None),
+        ConstructionStrategy::IndexLookup
+    );
+}
+
+#[test]
+fn builder_options_equal_default_table() {
+    let reference_ascii = pseudo_random_dna(2_000, 10);
+    let query_ascii = pseudo_random_dna(1_500, 11);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+    let expected = MatchTable::new(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        5,
+    );
+
+    for parallel in [false, true] {
+        for storage in [StorageBackend::Dense, StorageBackend::Sparse] {
+            for index_backend in [IndexBackend::SuffixTable, IndexBackend::FmIndex] {
+                for strategy in [
+                    ConstructionStrategy::IndexLookup,
+                    ConstructionStrategy::HashJoin,
+                ] {
+                    let actual = MatchTableBuilder::new(5)
+                        .parallel(parallel)
+                        .storage(storage)
+                        .index_backend(index_backend)
+                        .strategy(strategy)
+                        .build(
+                            reference.as_genome_subsequence(),
+                            query.as_genome_subsequence(),
+                        );
+
+                    for quadrant in Quadrant::ALL {
+                        assert!(
+                            expected.matches(quadrant).eq(actual.matches(quadrant)),
+                            "{parallel} {storage:?} {index_backend:?} {strategy:?} {quadrant:?}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn try_build_reports_errors() {
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"ACGTACGT").unwrap();
+    let query = VectorGenome::from_slice_u8(b"ACG").unwrap();
+
+    assert!(matches!(
+        MatchTable::try_new(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+            0,
+        ),
+        Err(MatchTableError::InvalidMinimumLength {
+            minimum_length: 0,
+            max_mismatches: 0,
+        })
+    ));
+    assert!(matches!(
+        MatchTableBuilder::new(2).max_mismatches(2).try_build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        ),
+        Err(MatchTableError::InvalidMinimumLength {
+            minimum_length: 2,
+            max_mismatches: 2,
+        })
+    ));
+    assert!(matches!(
+        MatchTable::try_new(
+            query.as_genome_subsequence(),
+            reference.as_genome_subsequence(),
+            4,
+        ),
+        Err(MatchTableError::SequenceTooShort {
+            sequence: "reference",
+            length: 3,
+            minimum_length: 4,
+        })
+    ));
+    assert!(
+        MatchTable::try_new(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+            3,
+        )
+        .is_ok()
+    );
+    assert!(matches!(
+        MatchTableBuilder::new(3)
+            .query_mask(bitvec::bitvec![0; 4])
+            .try_build(
+                reference.as_genome_subsequence(),
+                query.as_genome_subsequence(),
+            ),
+        Err(MatchTableError::MaskLengthMismatch {
+            sequence: "query",
+            mask_length: 4,
+            sequence_length: 3,
+        })
+    ));
+    assert!(matches!(
+        MatchTableBuilder::new(3).band(1, 0).try_build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        ),
+        Err(MatchTableError::InvalidBand {
+            min_offset: 1,
+            max_offset: 0,
+        })
+    ));
+    assert!(matches!(
+        QuadrantStorageBuilder::<u32>::new(StorageBackend::Dense, usize::MAX / 2, 3, None, false),
+        Err(MatchTableError::QuadrantTooLarge { .. })
+    ));
+}
+
+#[test]
+fn ambiguity_policies_equal_brute_force() {
+    fn bases(character: u8) -> &'static [u8] {
+        match character {
+            b'A' => b"A",
+            b'C' => b"C",
+            b'G' => b"G",
+            b'T' => b"T",
+            b'R' => b"AG",
+            b'Y' => b"CT",
+            b'N' => b"ACGT",
+            _ => unreachable!(),
+        }
+    }
+
+    let mut reference_ascii = pseudo_random_dna(200, 6);
+    let mut query_ascii = pseudo_random_dna(150, 7);
+    for (index, character) in [(17, b'N'), (60, b'R'), (61, b'Y'), (140, b'N')] {
+        reference_ascii[index] = character;
+    }
+    for (index, character) in [(5, b'Y'), (80, b'N'), (81, b'N')] {
+        query_ascii[index] = character;
+    }
+    let reference =
+        VectorGenome::<DnaIupacNucleicAcidAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+    let query_rc: Vec<u8> = query.reverse_complement_iter().map(u8::from).collect();
+
+    for max_mismatches in 0..2 {
+        for policy in [
+            AmbiguityPolicy::Literal,
+            AmbiguityPolicy::NeverMatch,
+            AmbiguityPolicy::Compatible,
+        ] {
+            let matches = MatchTableBuilder::new(5)
+                .max_mismatches(max_mismatches)
+                .ambiguity_policy(policy)
+                .build(
+                    reference.as_genome_subsequence(),
+                    query.as_genome_subsequence(),
+                );
+
+            let mut expected = Vec::new();
+            for (reference_index, reference_kmer) in reference_ascii.windows(5).enumerate() {
+                for (query_rc_index, query_rc_kmer) in query_rc.windows(5).enumerate() {
+                    let is_ambiguous = |kmer: &[u8]| kmer.iter().any(|c| bases(*c).len() > 1);
+                    if policy == AmbiguityPolicy::NeverMatch
+                        && (is_ambiguous(reference_kmer) || is_ambiguous(query_rc_kmer))
+                    {
+                        continue;
+                    }
+
+                    let mismatches = reference_kmer
+                        .iter()
+                        .zip(query_rc_kmer)
+                        .filter(|(a, b)| match policy {
+                            AmbiguityPolicy::Compatible => {
+                                !bases(**a).iter().any(|base| bases(**b).contains(base))
+                            }
+                            _ => a != b,
+                        })
+                        .count();
+                    if mismatches <= max_mismatches {
+                        expected.push((reference_index, query_rc_index));
+                    }
+                }
+            }
+
+            assert_eq!(
+                matches.reference_query_matches().collect::<Vec<_>>(),
+                expected,
+                "{max_mismatches} {policy:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn skip_n_excludes_kmers_overlapping_n() {
+    let mut reference_ascii = pseudo_random_dna(200, 8);
+    let mut query_ascii = pseudo_random_dna(150, 9);
+    reference_ascii[30..40].fill(b'N');
+    reference_ascii[199] = b'N';
+    query_ascii[0] = b'N';
+    query_ascii[70..72].fill(b'N');
+    let reference =
+        VectorGenome::<DnaIupacNucleicAcidAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+    let query_rc: Vec<u8> = query.reverse_complement_iter().map(u8::from).collect();
+
+    let mut expected = Vec::new();
+    for (reference_index, reference_kmer) in reference_ascii.windows(4).enumerate() {
+        for (query_rc_index, query_rc_kmer) in query_rc.windows(4).enumerate() {
+            if reference_kmer == query_rc_kmer && !reference_kmer.contains(&b'N') {
+                expected.push((reference_index, query_rc_index));
+            }
+        }
+    }
+
+    for strategy in [
+        ConstructionStrategy::IndexLookup,
+        ConstructionStrategy::HashJoin,
+    ] {
+        let matches = MatchTableBuilder::new(4)
+            .strategy(strategy)
+            .skip_n(true)
+            .build(
+                reference.as_genome_subsequence(),
+                query.as_genome_subsequence(),
+            );
+
+        assert_eq!(matches.skipped_reference_kmer_count(), 13 + 1);
+        assert_eq!(matches.skipped_query_kmer_count(), 1 + 5);
+        assert_eq!(
+            matches.reference_query_matches().collect::<Vec<_>>(),
+            expected,
+            "{strategy:?}"
+        );
+    }
+}
+
+#[test]
+fn contig_matches_equal_brute_force() {
+    let reference_contigs: Vec<_> = [(60, 10), (3, 11), (80, 12)]
+        .map(|(length, seed)| pseudo_random_dna(length, seed))
+        .into_iter()
+        .collect();
+    let query_contigs: Vec<_> = [(50, 13), (70, 14)]
+        .map(|(length, seed)| pseudo_random_dna(length, seed))
+        .into_iter()
+        .collect();
+    let to_genomes = |contigs: &[Vec<u8>]| -> Vec<VectorGenome<DnaAlphabet>> {
+        contigs
+            .iter()
+            .map(|contig| VectorGenome::from_slice_u8(contig).unwrap())
+            .collect()
+    };
+    let reference = to_genomes(&reference_contigs);
+    let query = to_genomes(&query_contigs);
+
+    let matches = MatchTableBuilder::new(4).build_contigs(
+        &reference
+            .iter()
+            .map(|contig| contig.as_genome_subsequence())
+            .collect::<Vec<_>>(),
+        &query
+            .iter()
+            .map(|contig| contig.as_genome_subsequence())
+            .collect::<Vec<_>>(),
+    );
+    assert_eq!(matches.reference_contigs().contig_count(), 3);
+    assert_eq!(matches.query_contigs().len(), 120);
+
+    let mut actual: Vec<_> = matches.contig_matches(Quadrant::ReferenceQuery).collect();
+    actual.sort_unstable();
+
+    let mut expected = Vec::new();
+    for (reference_id, reference_contig) in reference.iter().enumerate() {
+        let reference_ascii: Vec<u8> = reference_contig.iter().copied().map(u8::from).collect();
+        for (query_id, query_contig) in query.iter().enumerate() {
+            let query_rc: Vec<u8> = query_contig
+                .reverse_complement_iter()
+                .map(u8::from)
+                .collect();
+            for (reference_offset, reference_kmer) in reference_ascii.windows(4).enumerate() {
+                for (query_rc_offset, query_rc_kmer) in query_rc.windows(4).enumerate() {
+                    if reference_kmer == query_rc_kmer {
+                        expected.push((
+                            ContigPosition {
+                                contig_id: reference_id,
+                                offset: reference_offset,
+                            },
+                            ContigPosition {
+                                contig_id: query_id,
+                                offset: query_rc_offset,
+                            },
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    expected.sort_unstable();
+
+    assert!(!expected.is_empty());
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn band_equals_filtered_full_table() {
+    let reference_ascii = pseudo_random_dna(300, 15);
+    let query_ascii = pseudo_random_dna(250, 16);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+
+    for max_mismatches in 0..2 {
+        let full = MatchTable::new_with_mismatches(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+            5,
+            max_mismatches,
+        );
+
+        for (min_offset, max_offset) in [(-7, 20), (0, 0), (-400, 400)] {
+            for strategy in [
+                ConstructionStrategy::Automatic,
+                ConstructionStrategy::IndexLookup,
+                ConstructionStrategy::BandScan,
+            ] {
+                for storage in [StorageBackend::Dense, StorageBackend::Sparse] {
+                    let banded = MatchTableBuilder::new(5)
+                        .max_mismatches(max_mismatches)
+                        .band(min_offset, max_offset)
+                        .strategy(strategy)
+                        .storage(storage)
+                        .build(
+                            reference.as_genome_subsequence(),
+                            query.as_genome_subsequence(),
+                        );
+
+                    for quadrant in Quadrant::ALL {
+                        let expected: Vec<_> = full
+                            .matches(quadrant)
+                            .filter(|&(primary_index, secondary_rc_index)| {
+                                (min_offset..=max_offset).contains(
+                                    &(secondary_rc_index as isize - primary_index as isize),
+                                )
+                            })
+                            .collect();
+                        assert_eq!(
+                            banded.matches(quadrant).collect::<Vec<_>>(),
+                            expected,
+                            "{max_mismatches} {min_offset} {max_offset} {strategy:?} {storage:?} {quadrant:?}"
+                        );
+                        for (primary_index, secondary_rc_index) in full.matches(quadrant) {
+                            assert_eq!(
+                                banded.has_match(quadrant, primary_index, secondary_rc_index),
+                                expected.contains(&(primary_index, secondary_rc_index)),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn compact_sparse_rows_equal_wide_sparse_rows() {
+    fn build<Index: StorageIndex>(matches: &[(usize, usize)]) -> Vec<(usize, usize)> {
+        let mut builder = SparseRowsBuilder::<Index>::new(100);
+        for &(primary_index, secondary_rc_index) in matches {
+            builder.insert(primary_index, secondary_rc_index);
+        }
+        let rows = builder.build();
+        for primary_index in 0..100 {
+            for secondary_rc_index in 0..100 {
+                assert_eq!(
+                    rows.has_match(primary_index, secondary_rc_index),
+                    matches.contains(&(primary_index, secondary_rc_index))
+                );
+            }
+        }
+        rows.rows_iter(0..100).collect()
+    }
+
+    let matches: Vec<_> = pseudo_random_dna(400, 17)
+        .chunks(2)
+        .enumerate()
+        .map(|(index, pair)| {
+            (
+                (index * 7 + usize::from(pair[0])) % 100,
+                usize::from(pair[1]) % 100,
+            )
+        })
+        .collect();
+    let mut expected = matches.clone();
+    expected.sort_unstable();
+    expected.dedup();
+
+    assert_eq!(build::<u32>(&matches), expected);
+    assert_eq!(build::<u64>(&matches), expected);
+}
+
+#[test]
+fn wide_index_type_equals_narrow_index_type() {
+    let reference_ascii = pseudo_random_dna(300, 57);
+    let query_ascii = pseudo_random_dna(200, 58);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+
+    for strategy in [
+        ConstructionStrategy::IndexLookup,
+        ConstructionStrategy::HashJoin,
+        ConstructionStrategy::SortMerge,
+    ] {
+        for storage in [StorageBackend::Dense, StorageBackend::Sparse] {
+            let options = MatchTableBuilder::new(6)
+                .strategy(strategy)
+                .storage(storage);
+            let narrow = options.clone().build(
+                reference.as_genome_subsequence(),
+                query.as_genome_subsequence(),
+            );
+            let wide = options.index_type::<u64>().build(
+                reference.as_genome_subsequence(),
+                query.as_genome_subsequence(),
+            );
+
+            assert!(narrow.diff(&wide).unwrap().is_empty());
+            assert!(narrow.statistics().match_count() > 0);
+            if storage == StorageBackend::Sparse {
+                assert!(narrow.statistics().storage_bytes() < wide.statistics().storage_bytes());
+            }
+
+            // Tables can be loaded with either index type.
+            let mut binary = Vec::new();
+            wide.write_binary(&mut binary).unwrap();
+            let loaded = MatchTable::<u32>::read_binary(binary.as_slice()).unwrap();
+            assert!(loaded.diff(&wide).unwrap().is_empty());
+            let mut binary = Vec::new();
+            narrow.write_binary(&mut binary).unwrap();
+            let loaded = MatchTable::<u64>::read_binary(binary.as_slice()).unwrap();
+            assert!(loaded.diff(&narrow).unwrap().is_empty());
+        }
+    }
+}
+
+#[test]
+fn wide_index_type_memory_estimate() {
+    let narrow = MatchTableBuilder::new(10).estimate_memory(1000, 100);
+    let wide = MatchTableBuilder::new(10)
+        .index_type::<u64>()
+        .estimate_memory(1000, 100);
+    assert_eq!(narrow.sparse_bytes_per_match, 4);
+    assert_eq!(wide.sparse_bytes_per_match, 8);
+    assert_eq!(narrow.dense_storage_bytes, wide.dense_storage_bytes);
+}
+
+#[test]
+fn wide_index_type_all_vs_all_equals_narrow_index_type() {
+    let sequences = [(120, 59), (90, 60), (150, 61)].map(|(length, seed)| {
+        VectorGenome::<DnaAlphabet>::from_slice_u8(&pseudo_random_dna(length, seed)).unwrap()
+    });
+    let subsequences = sequences
+        .each_ref()
+        .map(|sequence| sequence.as_genome_subsequence());
+
+    for storage in [StorageBackend::Dense, StorageBackend::Sparse] {
+        let options = MatchTableBuilder::new(4).storage(storage);
+        let narrow = options.clone().build_all_vs_all(&subsequences);
+        let wide = options.index_type::<u64>().build_all_vs_all(&subsequences);
+
+        assert!(narrow.table().diff(wide.table()).unwrap().is_empty());
+        for sequence_a in 0..sequences.len() {
+            for sequence_b in 0..sequences.len() {
+                assert_eq!(
+                    wide.matches(sequence_a, sequence_b).collect::<Vec<_>>(),
+                    narrow.matches(sequence_a, sequence_b).collect::<Vec<_>>(),
+                    "{storage:?} {sequence_a} {sequence_b}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn wide_index_type_multi_k_equals_narrow_index_type() {
+    let reference =
+        VectorGenome::<DnaAlphabet>::from_slice_u8(&pseudo_random_dna(200, 62)).unwrap();
+    let query = VectorGenome::from_slice_u8(&pseudo_random_dna(150, 63)).unwrap();
+    let minimum_lengths = [3, 4, 6];
+
+    for storage in [StorageBackend::Dense, StorageBackend::Sparse] {
+        let options = MatchTableBuilder::new(3).storage(storage);
+        let narrow = options.clone().build_multi_k(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+            &minimum_lengths,
+        );
+        let wide = options.index_type::<u64>().build_multi_k(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+            &minimum_lengths,
+        );
+
+        assert_eq!(narrow.tables().len(), wide.tables().len());
+        for (narrow, wide) in narrow.tables().iter().zip(wide.tables()) {
+            assert_eq!(narrow.minimum_length(), wide.minimum_length());
+            assert!(narrow.diff(wide).unwrap().is_empty(), "{storage:?}");
+        }
+    }
+}
+
+#[test]
+fn wide_index_type_contigs_equal_narrow_index_type() {
+    let contigs = [(80, 64), (60, 65)].map(|(length, seed)| {
+        VectorGenome::<DnaAlphabet>::from_slice_u8(&pseudo_random_dna(length, seed)).unwrap()
+    });
+    let reference: Vec<_> = contigs
+        .iter()
+        .map(|contig| contig.as_genome_subsequence())
+        .collect();
+    let query = VectorGenome::from_slice_u8(&pseudo_random_dna(70, 66)).unwrap();
+    let query_contigs = [query.as_genome_subsequence()];
+
+    let options = MatchTableBuilder::new(4).storage(StorageBackend::Sparse);
+    let narrow = options.clone().build_contigs(&reference, &query_contigs);
+    let wide = options
+        .index_type::<u64>()
+        .build_contigs(&reference, &query_contigs);
+    assert!(narrow.statistics().match_count() > 0);
+    assert!(narrow.diff(&wide).unwrap().is_empty());
+}
+
+#[test]
+fn wide_index_type_stream_equals_narrow_index_type() {
+    let reference =
+        VectorGenome::<DnaAlphabet>::from_slice_u8(&pseudo_random_dna(300, 67)).unwrap();
+    let query = VectorGenome::from_slice_u8(&pseudo_random_dna(200, 68)).unwrap();
+
+    let options = MatchTableBuilder::new(5).storage(StorageBackend::Sparse);
+    let mut narrow: Vec<_> = options
+        .clone()
+        .stream(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        )
+        .collect();
+    let mut wide: Vec<_> = options
+        .index_type::<u64>()
+        .stream(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        )
+        .collect();
+    narrow.sort_unstable();
+    wide.sort_unstable();
+    assert!(!narrow.is_empty());
+    assert_eq!(wide, narrow);
+}
+
+#[test]
+fn wide_index_type_lazy_equals_narrow_index_type() {
+    let reference =
+        VectorGenome::<DnaAlphabet>::from_slice_u8(&pseudo_random_dna(150, 69)).unwrap();
+    let query = VectorGenome::from_slice_u8(&pseudo_random_dna(100, 70)).unwrap();
+
+    let options = MatchTableBuilder::new(4).max_mismatches(1);
+    let narrow = options.clone().build(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+    );
+    let wide = options.index_type::<u64>().build_lazy(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+    );
+    for quadrant in Quadrant::ALL {
+        for primary_index in 0..narrow.primary_kmer_count(quadrant) {
+            assert_eq!(
+                wide.row_matches(quadrant, primary_index)
+                    .collect::<Vec<_>>(),
+                narrow
+                    .row_matches(quadrant, primary_index)
+                    .collect::<Vec<_>>(),
+                "{quadrant:?} {primary_index}"
+            );
+        }
+    }
+}
+
+#[test]
+fn wide_index_type_chunks_equal_narrow_index_type() {
+    let reference =
+        VectorGenome::<DnaAlphabet>::from_slice_u8(&pseudo_random_dna(600, 71)).unwrap();
+    let query = VectorGenome::from_slice_u8(&pseudo_random_dna(400, 72)).unwrap();
+
+    let options = MatchTableBuilder::new(5).storage(StorageBackend::Sparse);
+    let narrow = options.clone().build(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+    );
+    let mut chunks = Vec::new();
+    options
+        .index_type::<u64>()
+        .try_for_each_chunk(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+            2_000,
+            |chunk| {
+                chunks.push(chunk);
+                Ok(())
+            },
+        )
+        .unwrap();
+    assert!(chunks.len() > 1);
+
+    let mut chunked: Vec<_> = chunks.into_iter().flat_map(|chunk| chunk.matches).collect();
+    chunked.sort_unstable();
+    let mut expected: Vec<_> = Quadrant::ALL
+        .into_iter()
+        .flat_map(|quadrant| {
+            narrow
+                .matches(quadrant)
+                .map(move |(primary_index, secondary_rc_index)| Match {
+                    quadrant,
+                    primary_index,
+                    secondary_rc_index,
+                })
+        })
+        .collect();
+    expected.sort_unstable();
+    assert_eq!(chunked, expected);
+}
+
+#[test]
+fn wide_index_type_positions_equal_narrow_index_type() {
+    let reference_ascii = pseudo_random_dna(500, 73);
+    let query_ascii = pseudo_random_dna(400, 74);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+
+    let options = MatchTableBuilder::new(5).storage(StorageBackend::Sparse);
+    let narrow = options.clone().build(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+    );
+    let wide_options = options.index_type::<u64>();
+
+    let (wide, _) = wide_options.build_with_kmer_positions(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+    );
+    assert!(narrow.diff(&wide).unwrap().is_empty());
+
+    let reference_index = SuffixTable::new(std::str::from_utf8(&reference_ascii).unwrap());
+    let query_index = SuffixTable::new(std::str::from_utf8(&query_ascii).unwrap());
+    let wide = wide_options.build_with_indexes(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        &reference_index,
+        &query_index,
+    );
+    assert!(narrow.diff(&wide).unwrap().is_empty());
+}
+
+#[test]
+fn symmetric_storage_equals_dense_storage() {
+    let reference_ascii = pseudo_random_dna(200, 18);
+    let query_ascii = pseudo_random_dna(170, 19);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+
+    for max_mismatches in 0..2 {
+        let builder = MatchTableBuilder::new(4).max_mismatches(max_mismatches);
+        let dense = builder.build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+        let symmetric = builder.storage(StorageBackend::Symmetric).build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+
+        for quadrant in Quadrant::ALL {
+            let dense_matches: Vec<_> = dense.matches(quadrant).collect();
+            assert!(!dense_matches.is_empty());
+            assert_eq!(
+                symmetric.matches(quadrant).collect::<Vec<_>>(),
+                dense_matches,
+                "{max_mismatches} {quadrant:?}"
+            );
+
+            let kmer_count = dense.primary_kmer_count(quadrant);
+            if quadrant.is_self_comparison() {
+                for &(primary_index, secondary_rc_index) in &dense_matches {
+                    assert!(dense.has_match(
+                        quadrant,
+                        kmer_count - 1 - secondary_rc_index,
+                        kmer_count - 1 - primary_index
+                    ));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn match_counts_equal_row_scans() {
+    let reference_ascii = pseudo_random_dna(300, 20);
+    let query_ascii = pseudo_random_dna(200, 21);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+
+    for storage in [
+        StorageBackend::Dense,
+        StorageBackend::Sparse,
+        StorageBackend::Symmetric,
+    ] {
+        for band in [None, Some((-20, 30))] {
+            let mut builder = MatchTableBuilder::new(3).storage(storage);
+            if let Some((min_offset, max_offset)) = band {
+                builder = builder.band(min_offset, max_offset);
+            }
+            let matches = builder.build(
+                reference.as_genome_subsequence(),
+                query.as_genome_subsequence(),
+            );
+
+            for quadrant in Quadrant::ALL {
+                let mut expected = vec![0; matches.primary_kmer_count(quadrant)];
+                for (primary_index, _) in matches.matches(quadrant) {
+                    expected[primary_index] += 1;
+                }
+                let actual: Vec<_> = (0..matches.primary_kmer_count(quadrant))
+                    .map(|primary_index| matches.match_count(quadrant, primary_index))
+                    .collect();
+                assert_eq!(actual, expected, "{storage:?} {band:?} {quadrant:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn row_matches_equal_filtered_matches() {
+    let reference_ascii = pseudo_random_dna(250, 22);
+    let query_ascii = pseudo_random_dna(200, 23);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+
+    for storage in [
+        StorageBackend::Dense,
+        StorageBackend::Sparse,
+        StorageBackend::Symmetric,
+    ] {
+        for band in [None, Some((-20, 30))] {
+            let mut builder = MatchTableBuilder::new(3).storage(storage);
+            if let Some((min_offset, max_offset)) = band {
+                builder = builder.band(min_offset, max_offset);
+            }
+            let matches = builder.build(
+                reference.as_genome_subsequence(),
+                query.as_genome_subsequence(),
+            );
+
+            for quadrant in Quadrant::ALL {
+                let mut expected = vec![Vec::new(); matches.primary_kmer_count(quadrant)];
+                for (primary_index, secondary_rc_index) in matches.matches(quadrant) {
+                    expected[primary_index].push(secondary_rc_index);
+                }
+                for (primary_index, expected) in expected.into_iter().enumerate() {
+                    assert_eq!(
+                        matches
+                            .row_matches(quadrant, primary_index)
+                            .collect::<Vec<_>>(),
+                        expected,
+                        "{storage:?} {band:?} {quadrant:?} {primary_index}"
+                    );
+                }
+            }
+
+            for primary_index in 0..matches.reference_kmer_count() {
+                let expected: Vec<_> = [Quadrant::ReferenceReference, Quadrant::ReferenceQuery]
+                    .into_iter()
+                    .flat_map(|quadrant| {
+                        matches
+                            .row_matches(quadrant, primary_index)
+                            .map(move |secondary_rc_index| (quadrant, secondary_rc_index))
+                    })
+                    .collect();
+                assert_eq!(
+                    matches
+                        .matches_for_reference_kmer(primary_index)
+                        .collect::<Vec<_>>(),
+                    expected
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn column_matches_equal_filtered_matches() {
+    let reference_ascii = pseudo_random_dna(250, 24);
+    let query_ascii = pseudo_random_dna(200, 25);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+    let mut reference_mask = bitvec::vec::BitVec::repeat(false, reference_ascii.len());
+    reference_mask[40..90].fill(true);
+
+    for storage in [
+        StorageBackend::Dense,
+        StorageBackend::Sparse,
+        StorageBackend::Symmetric,
+    ] {
+        for builder in [
+            MatchTableBuilder::new(3),
+            MatchTableBuilder::new(5).max_mismatches(1),
+            MatchTableBuilder::new(3).reference_mask(reference_mask.clone()),
+            MatchTableBuilder::new(3).band(-20, 30),
+        ] {
+            let matches = builder.storage(storage).build(
+                reference.as_genome_subsequence(),
+                query.as_genome_subsequence(),
+            );
+
+            for quadrant in Quadrant::ALL {
+                let mut expected = vec![Vec::new(); matches.secondary_kmer_count(quadrant)];
+                for (primary_index, secondary_rc_index) in matches.matches(quadrant) {
+                    expected[secondary_rc_index].push(primary_index);
+                }
+                for expected in &mut expected {
+                    expected.sort_unstable();
+                }
+                for (secondary_rc_index, expected) in expected.into_iter().enumerate() {
+                    assert_eq!(
+                        matches
+                            .column_matches(quadrant, secondary_rc_index)
+                            .collect::<Vec<_>>(),
+                        expected,
+                        "{storage:?} {quadrant:?} {secondary_rc_index}"
+                    );
+                }
+            }
+
+            for secondary_rc_index in 0..matches.query_kmer_count() {
+                let expected: Vec<_> = [Quadrant::ReferenceQuery, Quadrant::QueryQuery]
+                    .into_iter()
+                    .flat_map(|quadrant| {
+                        matches
+                            .column_matches(quadrant, secondary_rc_index)
+                            .map(move |primary_index| (quadrant, primary_index))
+                    })
+                    .collect();
+                assert_eq!(
+                    matches
+                        .matches_for_query_rc_kmer(secondary_rc_index)
+                        .collect::<Vec<_>>(),
+                    expected
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn exported_coordinates_are_reverse_complements() {
+    let contigs: Vec<_> = [(60, 26), (3, 27), (80, 28)]
+        .map(|(length, seed)| pseudo_random_dna(length, seed))
+        .into_iter()
+        .collect();
+    let names = ["chr1", "chr2", "chr3"];
+    let reference: Vec<_> = contigs
+        .iter()
+        .map(|contig| VectorGenome::<DnaAlphabet>::from_slice_u8(contig).unwrap())
+        .collect();
+    let reference: Vec<_> = reference
+        .iter()
+        .map(|contig| contig.as_genome_subsequence())
+        .collect();
+    let query = VectorGenome::from_slice_u8(&pseudo_random_dna(70, 29)).unwrap();
+    let query_contigs = [query.as_genome_subsequence()];
+    let matches = MatchTableBuilder::new(4).build_contigs(&reference, &query_contigs);
+
+    let query_ascii: Vec<u8> = query.iter().copied().map(u8::from).collect();
+    let sequence = |name: &str| -> &[u8] {
+        match names.iter().position(|&contig_name| contig_name == name) {
+            Some(contig_id) => &contigs[contig_id],
+            None => {
+                assert_eq!(name, "read");
+                &query_ascii
+            }
+        }
+    };
+    let reverse_complement = |kmer: &[u8]| -> Vec<u8> {
+        kmer.iter()
+            .rev()
+            .map(|character| match character {
+                b'A' => b'T',
+                b'C' => b'G',
+                b'G' => b'C',
+                b'T' => b'A',
+                _ => unreachable!(),
+            })
+            .collect()
+    };
+
+    let mut output = Vec::new();
+    crate::io::export::write_tsv(&matches, &names, &["read"], &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    let mut lines = output.lines();
+    assert!(lines.next().unwrap().starts_with("quadrant\t"));
+
+    let mut record_count = 0;
+    for line in lines {
+        let columns: Vec<_> = line.split('\t').collect();
+        let [
+            _,
+            primary_name,
+            primary_start,
+            primary_end,
+            secondary_name,
+            secondary_start,
+            secondary_end,
+            length,
+        ] = columns[..]
+        else {
+            panic!("{line}");
+        };
+        let kmer = |name, start: &str, end: &str| {
+            &sequence(name)[start.parse::<usize>().unwrap()..end.parse::<usize>().unwrap()]
+        };
+        let primary_kmer = kmer(primary_name, primary_start, primary_end);
+        let secondary_kmer = kmer(secondary_name, secondary_start, secondary_end);
+        assert_eq!(length, "4");
+        assert_eq!(primary_kmer, reverse_complement(secondary_kmer), "{line}");
+        record_count += 1;
+    }
+    assert_eq!(
+        record_count,
+        Quadrant::ALL
+            .into_iter()
+            .map(|quadrant| matches.matches(quadrant).count())
+            .sum::<usize>()
+    );
+
+    let mut output = Vec::new();
+    crate::io::export::write_bedpe(&matches, &names, &["read"], &mut output).unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap().lines().count(),
+        record_count
+    );
+
+    assert!(matches!(
+        crate::io::export::write_tsv(&matches, &names[..2], &["read"], Vec::new()),
+        Err(crate::io::export::ExportError::NameCount {
+            genome: "reference",
+            name_count: 2,
+            contig_count: 3,
+        })
+    ));
+}
+
+#[cfg(feature = "heatmap")]
+#[test]
+fn heatmap_counts_equal_binned_matches() {
+    use crate::io::heatmap::Heatmap;
+
+    let reference =
+        VectorGenome::<DnaAlphabet>::from_slice_u8(&pseudo_random_dna(300, 30)).unwrap();
+    let query = VectorGenome::from_slice_u8(&pseudo_random_dna(170, 31)).unwrap();
+    let matches = MatchTable::new(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        3,
+    );
+
+    for quadrant in Quadrant::ALL {
+        for (width, height) in [(1, 1), (7, 13), (10_000, 10_000)] {
+            let heatmap = Heatmap::new(&matches, quadrant, width, height);
+            assert!(heatmap.width() <= matches.secondary_kmer_count(quadrant));
+            assert!(heatmap.height() <= matches.primary_kmer_count(quadrant));
+
+            let mut expected = vec![0; heatmap.width() * heatmap.height()];
+            for (primary_index, secondary_rc_index) in matches.matches(quadrant) {
+                let row = primary_index * heatmap.height() / matches.primary_kmer_count(quadrant);
+                let column =
+                    secondary_rc_index * heatmap.width() / matches.secondary_kmer_count(quadrant);
+                expected[row * heatmap.width() + column] += 1;
+            }
+            for row in 0..heatmap.height() {
+                for column in 0..heatmap.width() {
+                    assert_eq!(
+                        heatmap.count(row, column),
+                        expected[row * heatmap.width() + column]
+                    );
+                }
+            }
+
+            let mut png = Vec::new();
+            heatmap.write_png(&mut png).unwrap();
+            assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+            assert!(png.ends_with(b"IEND\xae\x42\x60\x82"));
+        }
+    }
+}
+
+#[test]
+fn binary_round_trip_preserves_tables() {
+    let reference_ascii = pseudo_random_dna(150, 32);
+    let query_ascii = pseudo_random_dna(110, 33);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+
+    for storage in [
+        StorageBackend::Dense,
+        StorageBackend::Sparse,
+        StorageBackend::Symmetric,
+    ] {
+        for builder in [
+            MatchTableBuilder::new(3),
+            MatchTableBuilder::new(4).max_mismatches(1).skip_n(true),
+            MatchTableBuilder::new(3).band(-10, 25),
+            MatchTableBuilder::new(3).maximum_length(5),
+        ] {
+            let matches = builder.storage(storage).build(
+                reference.as_genome_subsequence(),
+                query.as_genome_subsequence(),
+            );
+            let mut binary = Vec::new();
+            matches.write_binary(&mut binary).unwrap();
+            let loaded = MatchTable::<u32>::read_binary(binary.as_slice()).unwrap();
+
+            assert_eq!(loaded.minimum_length(), matches.minimum_length());
+            assert_eq!(loaded.max_mismatches(), matches.max_mismatches());
+            assert_eq!(loaded.maximum_length(), matches.maximum_length());
+            assert_eq!(
+                loaded.reference_kmer_count(),
+                matches.reference_kmer_count()
+            );
+            assert_eq!(loaded.query_kmer_count(), matches.query_kmer_count());
+            assert_eq!(loaded.reference_contigs(), matches.reference_contigs());
+            assert_eq!(loaded.query_contigs(), matches.query_contigs());
+            for quadrant in Quadrant::ALL {
+                assert_eq!(
+                    loaded.matches(quadrant).collect::<Vec<_>>(),
+                    matches.matches(quadrant).collect::<Vec<_>>(),
+                    "{storage:?} {quadrant:?}"
+                );
+                if let Some(secondary_rc_index) =
+                    matches.secondary_kmer_count(quadrant).checked_sub(5)
+                {
+                    assert_eq!(
+                        loaded
+                            .column_matches(quadrant, secondary_rc_index)
+                            .collect::<Vec<_>>(),
+                        matches
+                            .column_matches(quadrant, secondary_rc_index)
+                            .collect::<Vec<_>>(),
+                    );
+                }
+            }
+
+            // Truncated input.
+            assert!(matches!(
+                MatchTable::<u32>::read_binary(&binary[..binary.len() - 1]),
+                Err(MatchTableError::IO(_))
+            ));
+        }
+    }
+}
+
+#[test]
+fn read_binary_rejects_invalid_input() {
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+    let matches = MatchTable::new(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        4,
+    );
+    let mut binary = Vec::new();
+    matches.write_binary(&mut binary).unwrap();
+
+    let mut wrong_version = binary.clone();
+    wrong_version[8..12].copy_from_slice(&8u32.to_le_bytes());
+    assert!(matches!(
+        MatchTable::<u32>::read_binary(wrong_version.as_slice()),
+        Err(MatchTableError::UnsupportedBinaryVersion {
+            version: 8,
+            supported_version: 7,
+        })
+    ));
+
+    let mut wrong_magic = binary.clone();
+    wrong_magic[0] = b'X';
+    assert!(matches!(
+        MatchTable::<u32>::read_binary(wrong_magic.as_slice()),
+        Err(MatchTableError::InvalidBinaryFormat(_))
+    ));
+
+    // Increase the number of reference kmers.
+    let mut wrong_kmer_count = binary;
+    wrong_kmer_count[28..36].copy_from_slice(&11u64.to_le_bytes());
+    assert!(matches!(
+        MatchTable::<u32>::read_binary(wrong_kmer_count.as_slice()),
+        Err(MatchTableError::InvalidBinaryFormat(_))
+    ));
+}
+
+#[test]
+fn progress_events_cover_all_kmers() {
+    let reference =
+        VectorGenome::<DnaAlphabet>::from_slice_u8(&pseudo_random_dna(10_000, 34)).unwrap();
+    let query = VectorGenome::from_slice_u8(&pseudo_random_dna(300, 35)).unwrap();
+
+    for parallel in [false, true] {
+        let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let reporter_events = events.clone();
+        let matches = MatchTableBuilder::new(8)
+            .parallel(parallel)
+            .progress(move |event| reporter_events.lock().unwrap().push(event))
+            .build(
+                reference.as_genome_subsequence(),
+                query.as_genome_subsequence(),
+            );
+        let events = events.lock().unwrap();
+
+        assert_eq!(events.first(), Some(&ProgressEvent::IndexesBuilt));
+        assert_eq!(events.last(), Some(&ProgressEvent::Finished));
+        for (genome, total) in [
+            ("reference", matches.reference_kmer_count()),
+            ("query", matches.query_kmer_count()),
+        ] {
+            let mut processed: Vec<_> = events
+                .iter()
+                .filter_map(|event| match *event {
+                    ProgressEvent::KmersProcessed {
+                        genome: event_genome,
+                        processed,
+                        total: event_total,
+                    } if event_genome == genome => {
+                        assert_eq!(event_total, total);
+                        Some(processed)
+                    }
+                    _ => None,
+                })
+                .collect();
+            if !parallel || cfg!(not(feature = "parallel")) {
+                assert!(processed.is_sorted(), "{processed:?}");
+            }
+            processed.sort_unstable();
+            assert_eq!(processed.last(), Some(&total));
+            if genome == "reference" {
+                assert!(processed.len() > 1);
+            }
+        }
+    }
+}
+
+#[test]
+fn streamed_matches_equal_table_matches() {
+    let reference_ascii = pseudo_random_dna(400, 36);
+    let query_ascii = pseudo_random_dna(300, 37);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+
+    for builder in [
+        MatchTableBuilder::new(3),
+        MatchTableBuilder::new(6).max_mismatches(1),
+        MatchTableBuilder::new(4).band(-30, 10),
+        MatchTableBuilder::new(4).strategy(ConstructionStrategy::HashJoin),
+        MatchTableBuilder::new(4).strategy(ConstructionStrategy::SortMerge),
+    ] {
+        let matches = builder.build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+        let mut expected: Vec<_> = Quadrant::ALL
+            .into_iter()
+            .flat_map(|quadrant| {
+                matches
+                    .matches(quadrant)
+                    .map(move |(primary_index, secondary_rc_index)| Match {
+                        quadrant,
+                        primary_index,
+                        secondary_rc_index,
+                    })
+            })
+            .collect();
+        expected.sort_unstable();
+
+        let mut streamed: Vec<_> = builder
+            .stream(
+                reference.as_genome_subsequence(),
+                query.as_genome_subsequence(),
+            )
+            .collect();
+        streamed.sort_unstable();
+        assert_eq!(streamed, expected);
+    }
+
+    // Dropping the stream early stops the search.
+    let first_matches: Vec<_> = find_matches_streaming(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        2,
+    )
+    .take(5)
+    .collect();
+    assert_eq!(first_matches.len(), 5);
+
+    assert!(matches!(
+        MatchTableBuilder::new(0).try_stream(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        ),
+        Err(MatchTableError::InvalidMinimumLength { .. })
+    ));
+}
+
+#[test]
+fn lazy_matches_equal_table_matches() {
+    let mut reference_ascii = pseudo_random_dna(120, 38);
+    let mut query_ascii = pseudo_random_dna(90, 39);
+    for (index, character) in [(17, b'N'), (60, b'R'), (61, b'Y')] {
+        reference_ascii[index] = character;
+    }
+    query_ascii[40] = b'N';
+    let reference =
+        VectorGenome::<DnaIupacNucleicAcidAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+    let mut query_mask = bitvec::vec::BitVec::repeat(false, query_ascii.len());
+    query_mask[70..75].fill(true);
+
+    for builder in [
+        MatchTableBuilder::new(3),
+        MatchTableBuilder::new(5).max_mismatches(1),
+        MatchTableBuilder::new(3).band(-15, 40),
+        MatchTableBuilder::new(4).ambiguity_policy(AmbiguityPolicy::Compatible),
+        MatchTableBuilder::new(4)
+            .ambiguity_policy(AmbiguityPolicy::NeverMatch)
+            .query_mask(query_mask.clone()),
+        MatchTableBuilder::new(3).skip_n(true),
+    ] {
+        let matches = builder.build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+        let lazy = builder.build_lazy(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+        assert_eq!(lazy.reference_kmer_count(), matches.reference_kmer_count());
+        assert_eq!(lazy.query_kmer_count(), matches.query_kmer_count());
+
+        for quadrant in Quadrant::ALL {
+            for primary_index in 0..matches.primary_kmer_count(quadrant) {
+                for secondary_rc_index in 0..matches.secondary_kmer_count(quadrant) {
+                    assert_eq!(
+                        lazy.has_match(quadrant, primary_index, secondary_rc_index),
+                        matches.has_match(quadrant, primary_index, secondary_rc_index),
+                        "{builder:?} {quadrant:?} {primary_index} {secondary_rc_index}"
+                    );
+                }
+                assert_eq!(
+                    lazy.row_matches(quadrant, primary_index)
+                        .collect::<Vec<_>>(),
+                    matches
+                        .row_matches(quadrant, primary_index)
+                        .collect::<Vec<_>>(),
+                    "{builder:?} {quadrant:?} {primary_index}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn minimizer_window_keeps_long_inners() {
+    let reverse_complement = |sequence: &[u8]| -> Vec<u8> {
+        sequence
+            .iter()
+            .rev()
+            .map(|&character| match character {
+                b'A' => b'T',
+                b'C' => b'G',
+                b'G' => b'C',
+                _ => b'A',
+            })
+            .collect()
+    };
+    let mut reference_ascii = pseudo_random_dna(300, 40);
+    let mut query_ascii = pseudo_random_dna(200, 41);
+    query_ascii[50..90].copy_from_slice(&reverse_complement(&reference_ascii[100..140]));
+    let planted = reverse_complement(&reference_ascii[20..50]);
+    reference_ascii[200..230].copy_from_slice(&planted);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+    let (minimum_length, window_size) = (6, 5);
+
+    let full = MatchTable::new(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        minimum_length,
+    );
+    for storage in [
+        StorageBackend::Dense,
+        StorageBackend::Sparse,
+        StorageBackend::Symmetric,
+    ] {
+        let builder = MatchTableBuilder::new(minimum_length)
+            .storage(storage)
+            .minimizer_window(window_size);
+        let sparse = builder.build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+        let lazy = builder.build_lazy(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+
+        for quadrant in Quadrant::ALL {
+            let primary_kmer_count = full.primary_kmer_count(quadrant);
+            let secondary_kmer_count = full.secondary_kmer_count(quadrant);
+            let full_matches: Vec<_> = full.matches(quadrant).collect();
+            let sparse_matches: Vec<_> = sparse.matches(quadrant).collect();
+            assert!(
+                sparse_matches.len() < full_matches.len(),
+                "{storage:?} {quadrant:?}"
+            );
+
+            for &(primary_index, secondary_rc_index) in &sparse_matches {
+                assert!(full.has_match(quadrant, primary_index, secondary_rc_index));
+                assert!(sparse.has_match(
+                    quadrant.transposed(),
+                    secondary_kmer_count - 1 - secondary_rc_index,
+                    primary_kmer_count - 1 - primary_index,
+                ));
+            }
+            for primary_index in 0..primary_kmer_count {
+                for secondary_rc_index in 0..secondary_kmer_count {
+                    assert_eq!(
+                        lazy.has_match(quadrant, primary_index, secondary_rc_index),
+                        sparse.has_match(quadrant, primary_index, secondary_rc_index),
+                    );
+                }
+            }
+
+            // Each run of `window_size` consecutive matches along a diagonal contains a kept match.
+            for &(primary_index, secondary_rc_index) in &full_matches {
+                let run_length = (0..)
+                    .take_while(|&offset| {
+                        primary_index + offset < primary_kmer_count
+                            && secondary_rc_index + offset < secondary_kmer_count
+                            && full.has_match(
+                                quadrant,
+                                primary_index + offset,
+                                secondary_rc_index + offset,
+                            )
+                    })
+                    .count();
+                if run_length >= window_size {
+                    assert!(
+                        (0..window_size).any(|offset| sparse.has_match(
+                            quadrant,
+                            primary_index + offset,
+                            secondary_rc_index + offset
+                        )),
+                        "{storage:?} {quadrant:?} {primary_index} {secondary_rc_index}"
+                    );
+                }
+            }
+        }
+    }
+
+    assert!(matches!(
+        MatchTableBuilder::new(minimum_length)
+            .minimizer_window(0)
+            .try_build(
+                reference.as_genome_subsequence(),
+                query.as_genome_subsequence(),
+            ),
+        Err(MatchTableError::InvalidMinimizerWindow)
+    ));
+}
+
+#[test]
+fn canonical_matches_equal_brute_force() {
+    let mut reference_ascii = pseudo_random_dna(150, 42);
+    let query_ascii = pseudo_random_dna(100, 43);
+    reference_ascii[60..80].copy_from_slice(&query_ascii[10..30]);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+    let texts = [&reference_ascii, &query_ascii];
+    let reverse_complement = |text: &[u8]| -> Vec<u8> {
+        text.iter()
+            .rev()
+            .map(|&character| match character {
+                b'A' => b'T',
+                b'C' => b'G',
+                b'G' => b'C',
+                _ => b'A',
+            })
+            .collect()
+    };
+
+    for builder in [
+        MatchTableBuilder::new(4).strategy(ConstructionStrategy::IndexLookup),
+        MatchTableBuilder::new(4)
+            .strategy(ConstructionStrategy::IndexLookup)
+            .index_backend(IndexBackend::FmIndex),
+        MatchTableBuilder::new(4).strategy(ConstructionStrategy::HashJoin),
+        MatchTableBuilder::new(4).strategy(ConstructionStrategy::BandScan),
+        MatchTableBuilder::new(4).band(-30, 10),
+        MatchTableBuilder::new(6).max_mismatches(1),
+        MatchTableBuilder::new(4).storage(StorageBackend::Symmetric),
+    ] {
+        let builder = builder.canonical(true);
+        let k = builder.minimum_length;
+        let matches = builder.build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+        let lazy = builder.build_lazy(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+
+        for quadrant in Quadrant::ALL {
+            let primary = texts[usize::from(!quadrant.primary_is_reference())];
+            let secondary = texts[usize::from(!quadrant.secondary_is_reference())];
+            let secondary_rc = reverse_complement(secondary);
+            let primary_kmer_count = matches.primary_kmer_count(quadrant);
+            let secondary_kmer_count = matches.secondary_kmer_count(quadrant);
+            let mismatches = |a: &[u8], b: &[u8]| a.iter().zip(b).filter(|(a, b)| a != b).count();
+
+            for primary_index in 0..primary_kmer_count {
+                for secondary_rc_index in 0..secondary_kmer_count {
+                    let secondary_index = secondary_kmer_count - 1 - secondary_rc_index;
+                    let primary_kmer = &primary[primary_index..primary_index + k];
+                    let expected = builder
+                        .band
+                        .is_none_or(|band| band.contains(primary_index, secondary_rc_index))
+                        && (mismatches(
+                            primary_kmer,
+                            &secondary_rc[secondary_rc_index..secondary_rc_index + k],
+                        ) <= builder.max_mismatches
+                            || mismatches(
+                                primary_kmer,
+                                &secondary[secondary_index..secondary_index + k],
+                            ) <= builder.max_mismatches);
+                    let message =
+                        format!("{builder:?} {quadrant:?} {primary_index} {secondary_rc_index}");
+
+                    assert_eq!(
+                        matches.has_match(quadrant, primary_index, secondary_rc_index),
+                        expected,
+                        "{message}"
+                    );
+                    assert_eq!(
+                        lazy.has_match(quadrant, primary_index, secondary_rc_index),
+                        expected,
+                        "{message}"
+                    );
+                    if builder.band.is_none() {
+                        assert_eq!(
+                            matches.has_match(
+                                quadrant.transposed(),
+                                secondary_index,
+                                primary_kmer_count - 1 - primary_index
+                            ),
+                            expected,
+                            "{message}"
+                        );
+                    }
+                }
+            }
+        }
+
+        // The inserted copy of the query matches in forward orientation.
+        assert_eq!(
+            matches.has_reference_query_match(60, query_ascii.len() - k - 10),
+            builder.band.is_none()
+        );
+    }
+}
+
+#[test]
+fn forward_orientation_matches_direct_repeats() {
+    let mut reference_ascii = pseudo_random_dna(150, 44);
+    let query_ascii = pseudo_random_dna(100, 45);
+    reference_ascii[60..80].copy_from_slice(&query_ascii[10..30]);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+    let texts = [&reference_ascii, &query_ascii];
+    let k = 5;
+
+    for builder in [
+        MatchTableBuilder::new(k).strategy(ConstructionStrategy::IndexLookup),
+        MatchTableBuilder::new(k).strategy(ConstructionStrategy::HashJoin),
+        MatchTableBuilder::new(k).strategy(ConstructionStrategy::BandScan),
+        MatchTableBuilder::new(k).storage(StorageBackend::Sparse),
+    ] {
+        let build = |orientation| {
+            builder.clone().orientation(orientation).build(
+                reference.as_genome_subsequence(),
+                query.as_genome_subsequence(),
+            )
+        };
+        let forward = build(MatchOrientation::Forward);
+        let reverse_complement = build(MatchOrientation::ReverseComplement);
+        let both = build(MatchOrientation::Both);
+        let lazy = builder
+            .clone()
+            .orientation(MatchOrientation::Forward)
+            .build_lazy(
+                reference.as_genome_subsequence(),
+                query.as_genome_subsequence(),
+            );
+
+        for quadrant in Quadrant::ALL {
+            let primary = texts[usize::from(!quadrant.primary_is_reference())];
+            let secondary = texts[usize::from(!quadrant.secondary_is_reference())];
+            let secondary_kmer_count = forward.secondary_kmer_count(quadrant);
+            for primary_index in 0..forward.primary_kmer_count(quadrant) {
+                let expected: Vec<_> = (0..secondary_kmer_count)
+                    .filter(|&secondary_rc_index| {
+                        let secondary_index = secondary_kmer_count - 1 - secondary_rc_index;
+                        primary[primary_index..primary_index + k]
+                            == secondary[secondary_index..secondary_index + k]
+                    })
+                    .collect();
+                assert_eq!(
+                    forward
+                        .row_matches(quadrant, primary_index)
+                        .collect::<Vec<_>>(),
+                    expected,
+                    "{builder:?} {quadrant:?} {primary_index}"
+                );
+                assert_eq!(
+                    lazy.row_matches(quadrant, primary_index)
+                        .collect::<Vec<_>>(),
+                    expected
+                );
+                for secondary_rc_index in 0..secondary_kmer_count {
+                    assert_eq!(
+                        both.has_match(quadrant, primary_index, secondary_rc_index),
+                        forward.has_match(quadrant, primary_index, secondary_rc_index)
+                            || reverse_complement.has_match(
+                                quadrant,
+                                primary_index,
+                                secondary_rc_index
+                            )
+                    );
+                }
+            }
+        }
+
+        // The inserted copy of the query is a direct repeat.
+        assert!(forward.has_reference_query_match(60, query_ascii.len() - k - 10));
+    }
+}
+
+#[test]
+fn forward_accessors_equal_rc_accessors() {
+    let reference_ascii = pseudo_random_dna(80, 44);
+    let query_ascii = pseudo_random_dna(60, 45);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+    let k = 3;
+    let matches = MatchTable::new(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        k,
+    );
+
+    for quadrant in Quadrant::ALL {
+        let secondary = if quadrant.secondary_is_reference() {
+            &reference_ascii
+        } else {
+            &query_ascii
+        };
+        for primary_index in 0..matches.primary_kmer_count(quadrant) {
+            for secondary_rc_index in 0..matches.secondary_kmer_count(quadrant) {
+                let secondary_forward_end =
+                    matches.secondary_forward_end(quadrant, secondary_rc_index);
+                assert_eq!(
+                    secondary_forward_end,
+                    rc_index_to_forward(secondary.len(), k, secondary_rc_index) + k
+                );
+                assert_eq!(
+                    matches.secondary_rc_index(quadrant, secondary_forward_end),
+                    secondary_rc_index
+                );
+                assert_eq!(
+                    matches.has_match_forward(quadrant, primary_index, secondary_forward_end),
+                    matches.has_match(quadrant, primary_index, secondary_rc_index)
+                );
+            }
+        }
+    }
+
+    for (primary_index, secondary_rc_index) in matches.reference_query_matches() {
+        let secondary_forward_end =
+            rc_index_to_forward_end(query_ascii.len(), k, secondary_rc_index);
+        assert!(matches.has_reference_query_match_forward(primary_index, secondary_forward_end));
+        // The primary kmer is the reverse complement of the forward secondary kmer.
+        let primary_kmer = &reference_ascii[primary_index..primary_index + k];
+        let secondary_kmer = &query_ascii[secondary_forward_end - k..secondary_forward_end];
+        assert!(
+            primary_kmer
+                .iter()
+                .zip(secondary_kmer.iter().rev())
+                .all(|(&a, &b)| matches!(
+                    (a, b),
+                    (b'A', b'T') | (b'C', b'G') | (b'G', b'C') | (b'T', b'A')
+                ))
+        );
+    }
+}
+
+#[test]
+fn candidates_equal_brute_force() {
+    let reference_ascii = pseudo_random_dna(70, 46);
+    let mut query_ascii = pseudo_random_dna(50, 47);
+    // Plant a long inner, so that runs of several consecutive matches exist.
+    let inner: Vec<u8> = reference_ascii[20..32]
+        .iter()
+        .rev()
+        .map(|&character| match character {
+            b'A' => b'T',
+            b'C' => b'G',
+            b'G' => b'C',
+            _ => b'A',
+        })
+        .collect();
+    query_ascii[30..42].copy_from_slice(&inner);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+    let k = 3;
+    let matches = MatchTable::new(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        k,
+    );
+
+    for (min_inner_length, max_inner_length) in [
+        (0, None),
+        (5, None),
+        (3, Some(6)),
+        (10, Some(12)),
+        (0, Some(2)),
+    ] {
+        let mut constraints = CandidateConstraints::new().min_inner_length(min_inner_length);
+        if let Some(max_inner_length) = max_inner_length {
+            constraints = constraints.max_inner_length(max_inner_length);
+        }
+
+        for quadrant in Quadrant::ALL {
+            let primary = if quadrant.primary_is_reference() {
+                &reference_ascii
+            } else {
+                &query_ascii
+            };
+            let secondary = if quadrant.secondary_is_reference() {
+                &reference_ascii
+            } else {
+                &query_ascii
+            };
+            let is_reverse_complement = |primary_range: std::ops::Range<usize>, secondary_end| {
+                primary[primary_range.clone()]
+                    .iter()
+                    .zip(secondary[..secondary_end].iter().rev())
+                    .all(|(&a, &b)| {
+                        matches!(
+                            (a, b),
+                            (b'A', b'T') | (b'C', b'G') | (b'G', b'C') | (b'T', b'A')
+                        )
+                    })
+                    && primary_range.len() <= secondary_end
+            };
+
+            let mut expected = Vec::new();
+            for point1 in 0..primary.len() {
+                for point2 in 0..=secondary.len() {
+                    for inner_length in min_inner_length.max(k)
+                        ..=max_inner_length
+                            .unwrap_or(usize::MAX)
+                            .min(primary.len() - point1)
+                    {
+                        if is_reverse_complement(point1..point1 + inner_length, point2) {
+                            expected.push(TemplateSwitchCandidate {
+                                quadrant,
+                                point1,
+                                point2,
+                                point3: point2 - inner_length,
+                                point4: point1 + inner_length,
+                            });
+                        }
+                    }
+                }
+            }
+
+            let mut actual: Vec<_> = matches.candidates(quadrant, &constraints).collect();
+            for candidate in &actual {
+                assert_eq!(
+                    candidate.inner_length(),
+                    candidate.point2 - candidate.point3
+                );
+            }
+            actual.sort_unstable();
+            expected.sort_unstable();
+            assert_eq!(
+                actual, expected,
+                "{quadrant:?} {min_inner_length} {max_inner_length:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn constrained_candidates_equal_filtered_candidates() {
+    let reference_ascii = pseudo_random_dna(120, 48);
+    let query_ascii = pseudo_random_dna(90, 49);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+    let matches = MatchTable::new(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        3,
+    );
+
+    for (outer_offset, switch_distance, template_offset) in [
+        (0, Some((0, 20)), None),
+        (0, Some((10, 60)), Some((-30, 5))),
+        (-15, None, Some((0, 40))),
+        (7, Some((3, 3)), Some((-100, 100))),
+    ] {
+        let mut constraints = CandidateConstraints::new()
+            .min_inner_length(3)
+            .max_inner_length(5)
+            .outer_offset(outer_offset);
+        if let Some((min_distance, max_distance)) = switch_distance {
+            constraints = constraints.switch_distance(min_distance, max_distance);
+        }
+        if let Some((min_offset, max_offset)) = template_offset {
+            constraints = constraints.template_offset(min_offset, max_offset);
+        }
+        let unconstrained = CandidateConstraints::new()
+            .min_inner_length(3)
+            .max_inner_length(5);
+
+        for quadrant in Quadrant::ALL {
+            let expected: Vec<_> = matches
+                .candidates(quadrant, &unconstrained)
+                .filter(|candidate| {
+                    let point1 = candidate.point1 as isize + outer_offset;
+                    let point4 = candidate.point4 as isize + outer_offset;
+                    let point2 = candidate.point2 as isize;
+                    let point3 = candidate.point3 as isize;
+                    switch_distance.is_none_or(|(min_distance, max_distance)| {
+                        (min_distance as isize..=max_distance as isize)
+                            .contains(&(point2 - point1).abs())
+                            && (min_distance as isize..=max_distance as isize)
+                                .contains(&(point4 - point3).abs())
+                    }) && template_offset.is_none_or(|(min_offset, max_offset)| {
+                        (min_offset..=max_offset).contains(&(point3 - point1))
+                    })
+                })
+                .collect();
+            let actual: Vec<_> = matches.candidates(quadrant, &constraints).collect();
+            assert_eq!(actual, expected, "{quadrant:?} {constraints:?}");
+        }
+    }
+}
+
+#[test]
+fn self_tables_equal_reference_quadrant() {
+    let sequence_ascii = pseudo_random_dna(150, 50);
+    let sequence = VectorGenome::<DnaAlphabet>::from_slice_u8(&sequence_ascii).unwrap();
+    let dummy_query = VectorGenome::<DnaAlphabet>::from_slice_u8(b"ACGTACGT").unwrap();
+
+    for builder in [
+        MatchTableBuilder::new(3),
+        MatchTableBuilder::new(3).strategy(ConstructionStrategy::HashJoin),
+        MatchTableBuilder::new(3)
+            .strategy(ConstructionStrategy::IndexLookup)
+            .index_backend(IndexBackend::FmIndex),
+        MatchTableBuilder::new(3).strategy(ConstructionStrategy::BandScan),
+        MatchTableBuilder::new(5).max_mismatches(1),
+        MatchTableBuilder::new(3).storage(StorageBackend::Sparse),
+        MatchTableBuilder::new(3).storage(StorageBackend::Symmetric),
+        MatchTableBuilder::new(3).band(-20, 20),
+        MatchTableBuilder::new(3)
+            .query_mask(bitvec::vec::BitVec::repeat(false, dummy_query.len()))
+            .parallel(false),
+    ] {
+        let expected = builder.build(
+            sequence.as_genome_subsequence(),
+            dummy_query.as_genome_subsequence(),
+        );
+        let actual = builder.build_self(sequence.as_genome_subsequence());
+
+        assert_eq!(
+            actual.reference_kmer_count(),
+            expected.reference_kmer_count()
+        );
+        assert_eq!(actual.query_kmer_count(), 0);
+        assert_eq!(
+            actual.reference_reference_matches().collect::<Vec<_>>(),
+            expected.reference_reference_matches().collect::<Vec<_>>(),
+            "{builder:?}"
+        );
+        for quadrant in [
+            Quadrant::ReferenceQuery,
+            Quadrant::QueryReference,
+            Quadrant::QueryQuery,
+        ] {
+            assert_eq!(actual.matches(quadrant).count(), 0);
+        }
+
+        let mut binary = Vec::new();
+        actual.write_binary(&mut binary).unwrap();
+        let read = MatchTable::<u32>::read_binary(binary.as_slice()).unwrap();
+        assert_eq!(
+            read.reference_reference_matches().collect::<Vec<_>>(),
+            expected.reference_reference_matches().collect::<Vec<_>>(),
+        );
+    }
+
+    assert!(matches!(
+        MatchTableBuilder::new(3).try_build_self(sequence[0..2].as_genome_subsequence()),
+        Err(MatchTableError::SequenceTooShort {
+            sequence: "reference",
+            ..
+        })
+    ));
+}
+
+#[test]
+fn all_vs_all_matches_equal_pairwise_tables() {
+    let sequences_ascii = [
+        pseudo_random_dna(60, 51),
+        pseudo_random_dna(2, 52),
+        pseudo_random_dna(45, 53),
+        pseudo_random_dna(70, 54),
+    ];
+    let sequences = sequences_ascii
+        .each_ref()
+        .map(|ascii| VectorGenome::<DnaAlphabet>::from_slice_u8(ascii).unwrap());
+    let subsequences = sequences
+        .each_ref()
+        .map(|sequence| sequence.as_genome_subsequence());
+    let k = 3;
+
+    for storage in [StorageBackend::Dense, StorageBackend::Symmetric] {
+        let all_vs_all = MatchTableBuilder::new(k)
+            .storage(storage)
+            .build_all_vs_all(&subsequences);
+        assert_eq!(all_vs_all.sequence_count(), sequences.len());
+        assert_eq!(all_vs_all.kmer_count(1), 0);
+
+        for sequence_a in 0..sequences.len() {
+            for sequence_b in 0..sequences.len() {
+                let expected: Vec<_> = if sequence_a == 1 || sequence_b == 1 {
+                    Vec::new()
+                } else if sequence_a == sequence_b {
+                    MatchTable::new_self(subsequences[sequence_a], k)
+                        .reference_reference_matches()
+                        .collect()
+                } else {
+                    MatchTable::new(subsequences[sequence_a], subsequences[sequence_b], k)
+                        .reference_query_matches()
+                        .collect()
+                };
+
+                assert_eq!(
+                    all_vs_all
+                        .matches(sequence_a, sequence_b)
+                        .collect::<Vec<_>>(),
+                    expected,
+                    "{storage:?} {sequence_a} {sequence_b}"
+                );
+                for primary_index in 0..all_vs_all.kmer_count(sequence_a) {
+                    for secondary_rc_index in 0..all_vs_all.kmer_count(sequence_b) {
+                        assert_eq!(
+                            all_vs_all.has_match(
+                                sequence_a,
+                                primary_index,
+                                sequence_b,
+                                secondary_rc_index
+                            ),
+                            expected.contains(&(primary_index, secondary_rc_index))
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn selected_quadrants_equal_full_table_quadrants() {
+    let reference_ascii = pseudo_random_dna(80, 55);
+    let query_ascii = pseudo_random_dna(65, 56);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::<DnaAlphabet>::from_slice_u8(&query_ascii).unwrap();
+
+    for builder in [
+        MatchTableBuilder::new(3),
+        MatchTableBuilder::new(3).strategy(ConstructionStrategy::HashJoin),
+        MatchTableBuilder::new(3)
+            .strategy(ConstructionStrategy::IndexLookup)
+            .index_backend(IndexBackend::FmIndex),
+        MatchTableBuilder::new(3).strategy(ConstructionStrategy::BandScan),
+        MatchTableBuilder::new(4).max_mismatches(1).parallel(false),
+        MatchTableBuilder::new(3).storage(StorageBackend::Symmetric),
+        MatchTableBuilder::new(3).band(-10, 10),
+    ] {
+        let full = builder.build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+        for quadrants in [
+            Quadrants::REFERENCE_QUERY | Quadrants::QUERY_REFERENCE,
+            Quadrants::REFERENCE_QUERY,
+            Quadrants::QUERY_QUERY,
+            Quadrants::NONE,
+        ] {
+            let table = builder.clone().quadrants(quadrants).build(
+                reference.as_genome_subsequence(),
+                query.as_genome_subsequence(),
+            );
+            assert_eq!(table.quadrants(), quadrants);
+
+            for quadrant in Quadrant::ALL {
+                let expected: Vec<_> = if quadrants.contains(quadrant) {
+                    full.matches(quadrant).collect()
+                } else {
+                    Vec::new()
+                };
+                assert_eq!(
+                    table.matches(quadrant).collect::<Vec<_>>(),
+                    expected,
+                    "{builder:?} {quadrants:?} {quadrant}"
+                );
+                for secondary_rc_index in 0..table.secondary_kmer_count(quadrant) {
+                    assert_eq!(
+                        table
+                            .column_matches(quadrant, secondary_rc_index)
+                            .collect::<Vec<_>>(),
+                        expected
+                            .iter()
+                            .filter(|&&(_, j)| j == secondary_rc_index)
+                            .map(|&(i, _)| i)
+                            .collect::<Vec<_>>(),
+                    );
+                }
+            }
+
+            let mut binary = Vec::new();
+            table.write_binary(&mut binary).unwrap();
+            let read = MatchTable::<u32>::read_binary(binary.as_slice()).unwrap();
+            assert_eq!(read.quadrants(), quadrants);
+        }
+    }
+}
+
+#[test]
+fn multi_k_tables_equal_separate_tables() {
+    let reference_ascii = pseudo_random_dna(90, 57);
+    let query_ascii = pseudo_random_dna(70, 58);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::<DnaAlphabet>::from_slice_u8(&query_ascii).unwrap();
+    let reference_mask: bitvec::vec::BitVec = (0..reference_ascii.len())
+        .map(|position| (30..35).contains(&position))
+        .collect();
+    let minimum_lengths = [6, 2, 3, 4, 3];
+
+    for builder in [
+        MatchTableBuilder::new(1),
+        MatchTableBuilder::new(1).strategy(ConstructionStrategy::HashJoin),
+        MatchTableBuilder::new(1).storage(StorageBackend::Sparse),
+        MatchTableBuilder::new(1).storage(StorageBackend::Symmetric),
+        MatchTableBuilder::new(1).band(-15, 10),
+        MatchTableBuilder::new(1).minimizer_window(3),
+        MatchTableBuilder::new(1).reference_mask(reference_mask.clone()),
+        MatchTableBuilder::new(1).quadrants(Quadrants::REFERENCE_QUERY),
+        MatchTableBuilder::new(1).max_mismatches(1),
+        MatchTableBuilder::new(1).canonical(true),
+    ] {
+        let builder = builder.parallel(false);
+        let multi_k = builder.build_multi_k(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+            if builder.max_mismatches > 0 {
+                &minimum_lengths[..2]
+            } else {
+                &minimum_lengths
+            },
+        );
+
+        for table in multi_k.tables() {
+            let expected = builder
+                .clone()
+                .minimum_length(table.minimum_length())
+                .build(
+                    reference.as_genome_subsequence(),
+                    query.as_genome_subsequence(),
+                );
+            assert_eq!(
+                table.skipped_reference_kmer_count(),
+                expected.skipped_reference_kmer_count()
+            );
+            assert_eq!(table.quadrants(), expected.quadrants());
+            for quadrant in Quadrant::ALL {
+                assert_eq!(
+                    table.matches(quadrant).collect::<Vec<_>>(),
+                    expected.matches(quadrant).collect::<Vec<_>>(),
+                    "{builder:?} {} {quadrant}",
+                    table.minimum_length()
+                );
+            }
+        }
+    }
+
+    let multi_k = MatchTableBuilder::new(1).build_multi_k(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        &minimum_lengths,
+    );
+    assert_eq!(
+        multi_k.minimum_lengths().collect::<Vec<_>>(),
+        vec![2, 3, 4, 6]
+    );
+    assert!(
+        MatchTableBuilder::new(1)
+            .try_build_multi_k(
+                reference.as_genome_subsequence(),
+                query.as_genome_subsequence(),
+                &[3, 71],
+            )
+            .is_err()
+    );
+}
+
+#[test]
+fn maximum_length_caps_extension() {
+    let reference_ascii = [pseudo_random_dna(60, 61), b"ACGTTGCA".repeat(3)].concat();
+    let query_ascii = [b"TGCAACGT".repeat(3), pseudo_random_dna(50, 62)].concat();
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::<DnaAlphabet>::from_slice_u8(&query_ascii).unwrap();
+    let unlimited = MatchTable::new(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        3,
+    );
+
+    for maximum_length in [3, 4, 7] {
+        let limited = MatchTableBuilder::new(3)
+            .maximum_length(maximum_length)
+            .build(
+                reference.as_genome_subsequence(),
+                query.as_genome_subsequence(),
+            );
+        assert_eq!(limited.maximum_length(), Some(maximum_length));
+        for quadrant in Quadrant::ALL {
+            assert!(limited.matches(quadrant).eq(unlimited.matches(quadrant)));
+            assert_eq!(
+                limited.maximal_matches(quadrant).collect::<Vec<_>>(),
+                unlimited
+                    .maximal_matches(quadrant)
+                    .map(|(primary_start, secondary_rc_start, length)| {
+                        (
+                            primary_start,
+                            secondary_rc_start,
+                            length.min(maximum_length),
+                        )
+                    })
+                    .collect::<Vec<_>>(),
+                "{quadrant} {maximum_length}"
+            );
+            for ((primary_index, secondary_rc_index, length), (_, _, unlimited_length)) in limited
+                .matches_with_length(quadrant)
+                .zip(unlimited.matches_with_length(quadrant))
+            {
+                assert_eq!(length, unlimited_length.min(maximum_length));
+                assert_eq!(
+                    limited.longest_match_length(quadrant, primary_index, secondary_rc_index),
+                    Some(length)
+                );
+            }
+            assert_eq!(
+                limited.to_run_length().match_count(quadrant),
+                unlimited.to_run_length().match_count(quadrant)
+            );
+        }
+    }
+
+    assert!(matches!(
+        MatchTableBuilder::new(4).maximum_length(3).try_build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence()
+        ),
+        Err(MatchTableError::InvalidMaximumLength {
+            maximum_length: 3,
+            minimum_length: 4,
+        })
+    ));
+}
+
+#[test]
+fn runs_partition_matches_into_maximal_diagonal_runs() {
+    let reference_ascii = [pseudo_random_dna(80, 65), b"ACGTTGCA".repeat(3)].concat();
+    let query_ascii = [b"TGCAACGT".repeat(3), pseudo_random_dna(60, 66)].concat();
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::<DnaAlphabet>::from_slice_u8(&query_ascii).unwrap();
+    let matches = MatchTableBuilder::new(3).maximum_length(4).build(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+    );
+
+    for quadrant in Quadrant::ALL {
+        let mut covered = Vec::new();
+        for (primary_start, secondary_rc_start, run_length) in matches.runs(quadrant) {
+            assert!(run_length > 0);
+            assert!(
+                primary_start == 0
+                    || secondary_rc_start == 0
+                    || !matches.has_match(quadrant, primary_start - 1, secondary_rc_start - 1)
+            );
+            let (primary_end, secondary_rc_end) =
+                (primary_start + run_length, secondary_rc_start + run_length);
+            assert!(
+                primary_end == matches.primary_kmer_count(quadrant)
+                    || secondary_rc_end == matches.secondary_kmer_count(quadrant)
+                    || !matches.has_match(quadrant, primary_end, secondary_rc_end)
+            );
+            covered.extend(
+                (0..run_length).map(|offset| (primary_start + offset, secondary_rc_start + offset)),
+            );
+        }
+        covered.sort_unstable();
+        assert_eq!(
+            covered,
+            matches.matches(quadrant).collect::<Vec<_>>(),
+            "{quadrant}"
+        );
+
+        // The left- and right-maximal matches are the first and last matches of the runs.
+        let mut first_and_last: Vec<_> = matches
+            .runs(quadrant)
+            .flat_map(|(primary_start, secondary_rc_start, run_length)| {
+                [
+                    (primary_start, secondary_rc_start, true),
+                    (
+                        primary_start + run_length - 1,
+                        secondary_rc_start + run_length - 1,
+                        false,
+                    ),
+                ]
+            })
+            .collect();
+        first_and_last.sort_unstable();
+        let mut maximal: Vec<_> = matches
+            .matches_with_maximality(quadrant)
+            .flat_map(|(primary_index, secondary_rc_index, maximality)| {
+                [
+                    maximality
+                        .left
+                        .then_some((primary_index, secondary_rc_index, true)),
+                    maximality
+                        .right
+                        .then_some((primary_index, secondary_rc_index, false)),
+                ]
+            })
+            .flatten()
+            .collect();
+        maximal.sort_unstable();
+        assert_eq!(maximal, first_and_last, "{quadrant}");
+    }
+}
+
+#[test]
+fn x_drop_extension_bridges_planted_errors() {
+    let complement = |character: &u8| match character {
+        b'A' => b'T',
+        b'C' => b'G',
+        b'G' => b'C',
+        _ => b'A',
+    };
+    let reference_ascii = pseudo_random_dna(300, 67);
+    let mut inner_rc: Vec<u8> = reference_ascii[100..160]
+        .iter()
+        .rev()
+        .map(complement)
+        .collect();
+    for offset in [20, 40] {
+        inner_rc[offset] = complement(&inner_rc[offset]);
+    }
+    let query_ascii = [
+        pseudo_random_dna(40, 68),
+        inner_rc,
+        pseudo_random_dna(40, 69),
+    ]
+    .concat();
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::<DnaAlphabet>::from_slice_u8(&query_ascii).unwrap();
+    let matches = MatchTable::new(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        8,
+    );
+    let query_rc: Vec<u8> = query_ascii.iter().rev().map(complement).collect();
+
+    for (parameters, max_mismatches) in [
+        (XDropParameters::new(), usize::MAX),
+        (XDropParameters::new().max_mismatches(1), 1),
+    ] {
+        let extensions = matches.x_drop_extensions(
+            Quadrant::ReferenceQuery,
+            &reference_ascii,
+            &query_ascii,
+            &parameters,
+        );
+        assert!(!extensions.is_empty());
+        assert!(extensions.is_sorted_by_key(|extension| std::cmp::Reverse(extension.score)));
+        for extension in &extensions {
+            assert_eq!(extension.primary.len(), extension.secondary_rc.len());
+            let mismatch_count = reference_ascii[extension.primary.clone()]
+                .iter()
+                .zip(&query_rc[extension.secondary_rc.clone()])
+                .filter(|(a, b)| a != b)
+                .count();
+            assert_eq!(extension.mismatch_count, mismatch_count);
+            assert!(mismatch_count <= max_mismatches);
+            assert_eq!(
+                extension.score,
+                (extension.primary.len() - mismatch_count) as isize - 2 * mismatch_count as isize
+            );
+        }
+
+        let best = &extensions[0];
+        if max_mismatches == usize::MAX {
+            assert!(best.primary.start <= 100 && best.primary.end >= 160);
+            assert!(best.mismatch_count >= 2);
+        } else {
+            assert!(best.primary.len() >= 40);
+        }
+    }
+
+    // Delete a character of the template, which splits the inner into two runs.
+    let mut inner_rc: Vec<u8> = reference_ascii[100..160]
+        .iter()
+        .rev()
+        .map(complement)
+        .collect();
+    inner_rc.remove(30);
+    let query_ascii = [
+        pseudo_random_dna(40, 68),
+        inner_rc,
+        pseudo_random_dna(40, 69),
+    ]
+    .concat();
+    let query = VectorGenome::<DnaAlphabet>::from_slice_u8(&query_ascii).unwrap();
+    let matches = MatchTable::new(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        8,
+    );
+    let ungapped = matches.x_drop_extensions(
+        Quadrant::ReferenceQuery,
+        &reference_ascii,
+        &query_ascii,
+        &XDropParameters::new(),
+    );
+    assert!(ungapped[0].primary.len() < 40);
+    let gapped = matches.x_drop_extensions(
+        Quadrant::ReferenceQuery,
+        &reference_ascii,
+        &query_ascii,
+        &XDropParameters::new().max_indels(2),
+    );
+    let best = &gapped[0];
+    assert!(best.primary.start <= 100 && best.primary.end >= 160);
+    assert_eq!(best.primary.len(), best.secondary_rc.len() + 1);
+    assert_eq!(best.indel_count, 1);
+    assert_eq!(best.mismatch_count, 0);
+    assert!(gapped.iter().all(|extension| extension.indel_count <= 2));
+}
+
+#[test]
+fn matching_statistics_equal_brute_force() {
+    let reference_ascii = [pseudo_random_dna(70, 63), b"ACGTTGCA".repeat(3)].concat();
+    let query_ascii = [b"TGCAACGT".repeat(3), pseudo_random_dna(40, 64)].concat();
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::<DnaAlphabet>::from_slice_u8(&query_ascii).unwrap();
+    let k = 3;
+    let matches = MatchTable::new(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        k,
+    );
+
+    for quadrant in Quadrant::ALL {
+        let (primary, secondary) = match quadrant {
+            Quadrant::ReferenceReference => (&reference_ascii, &reference_ascii),
+            Quadrant::ReferenceQuery => (&reference_ascii, &query_ascii),
+            Quadrant::QueryReference => (&query_ascii, &reference_ascii),
+            Quadrant::QueryQuery => (&query_ascii, &query_ascii),
+        };
+        let secondary_rc: Vec<u8> = secondary
+            .iter()
+            .rev()
+            .map(|&character| match character {
+                b'A' => b'T',
+                b'C' => b'G',
+                b'G' => b'C',
+                _ => b'A',
+            })
+            .collect();
+
+        let expected: Vec<_> = (0..primary.len() - k + 1)
+            .map(|primary_index| {
+                let length = (0..secondary_rc.len())
+                    .map(|secondary_rc_index| {
+                        primary[primary_index..]
+                            .iter()
+                            .zip(&secondary_rc[secondary_rc_index..])
+                            .take_while(|(a, b)| a == b)
+                            .count()
+                    })
+                    .max()
+                    .unwrap_or(0);
+                if length >= k { length } else { 0 }
+            })
+            .collect();
+        assert_eq!(
+            matches.matching_statistics(quadrant),
+            expected,
+            "{quadrant}"
+        );
+
+        for (primary_index, inner) in matches.longest_inners(quadrant).into_iter().enumerate() {
+            let Some(inner) = inner else {
+                assert_eq!(expected[primary_index], 0);
+                continue;
+            };
+            assert_eq!(inner.primary.start, primary_index);
+            assert_eq!(
+                primary[inner.primary.clone()],
+                secondary_rc[inner.secondary_rc.clone()]
+            );
+        }
+    }
+}
+
+#[test]
+fn longest_match_lengths_equal_brute_force() {
+    let reference_ascii = [pseudo_random_dna(60, 59), b"ACGTTGCA".repeat(3)].concat();
+    let query_ascii = [b"TGCAACGT".repeat(3), pseudo_random_dna(50, 60)].concat();
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::<DnaAlphabet>::from_slice_u8(&query_ascii).unwrap();
+    let k = 3;
+    let matches = MatchTable::new(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        k,
+    );
+
+    for quadrant in Quadrant::ALL {
+        let (primary, secondary) = match quadrant {
+            Quadrant::ReferenceReference => (&reference_ascii, &reference_ascii),
+            Quadrant::ReferenceQuery => (&reference_ascii, &query_ascii),
+            Quadrant::QueryReference => (&query_ascii, &reference_ascii),
+            Quadrant::QueryQuery => (&query_ascii, &query_ascii),
+        };
+        let secondary_rc: Vec<u8> = secondary
+            .iter()
+            .rev()
+            .map(|&character| match character {
+                b'A' => b'T',
+                b'C' => b'G',
+                b'G' => b'C',
+                _ => b'A',
+            })
+            .collect();
+
+        let with_length: Vec<_> = matches.matches_with_length(quadrant).collect();
+        assert_eq!(
+            with_length
+                .iter()
+                .map(|&(primary_index, secondary_rc_index, _)| (primary_index, secondary_rc_index))
+                .collect::<Vec<_>>(),
+            matches.matches(quadrant).collect::<Vec<_>>()
+        );
+        for (primary_index, secondary_rc_index, length) in with_length {
+            let expected = primary[primary_index..]
+                .iter()
+                .zip(&secondary_rc[secondary_rc_index..])
+                .take_while(|(a, b)| a == b)
+                .count();
+            assert_eq!(
+                length, expected,
+                "{quadrant} {primary_index} {secondary_rc_index}"
+            );
+            assert_eq!(
+                matches.longest_match_length(quadrant, primary_index, secondary_rc_index),
+                Some(expected)
+            );
+        }
+    }
+}
+
+#[cfg(feature = "wasm")]
+#[test]
+fn wasm_matches_equal_table_matches() {
+    let reference_ascii = pseudo_random_dna(50, 61);
+    let query_ascii = pseudo_random_dna(40, 62);
+    let wasm_table = crate::wasm::WasmMatchTable::new(
+        std::str::from_utf8(&reference_ascii).unwrap(),
+        std::str::from_utf8(&query_ascii).unwrap(),
+        3,
+        None,
+    )
+    .unwrap_or_else(|_| panic!("the sequences are valid"));
+    let table = wasm_table.table();
+
+    for quadrant in Quadrant::ALL {
+        let matches: Vec<_> = table
+            .matches(quadrant)
+            .flat_map(|(primary_index, secondary_rc_index)| [primary_index, secondary_rc_index])
+            .collect();
+        assert_eq!(wasm_table.matches(quadrant), matches);
+        assert_eq!(
+            wasm_table.matches_in_range(quadrant, 0, usize::MAX),
+            matches
+        );
+        let primary_kmer_count = wasm_table.primary_kmer_count(quadrant);
+        assert_eq!(
+            wasm_table.row_matches(quadrant, primary_kmer_count),
+            Vec::<usize>::new()
+        );
+        assert!(!wasm_table.has_match(quadrant, primary_kmer_count, 0));
+    }
+}
+
+#[test]
+fn packed_comparison_equals_scalar_comparison() {
+    let a = [pseudo_random_dna(200, 63), b"ACGT".repeat(100)].concat();
+    let b = [b"ACGT".repeat(100), pseudo_random_dna(150, 64)].concat();
+    let packed_a = PackedText::new(&a).unwrap();
+    let packed_b = PackedText::new(&b).unwrap();
+    assert!(PackedText::new(b"ACGN").is_none());
+
+    for (start, other_start) in [(0, 0), (5, 3), (200, 0), (201, 4), (203, 31), (390, 399)] {
+        for length in [0, 1, 31, 32, 33, 127, 128, 129, 200] {
+            if start + length > a.len() || other_start + length > b.len() {
+                continue;
+            }
+            let expected = a[start..start + length]
+                .iter()
+                .zip(&b[other_start..other_start + length])
+                .filter(|(x, y)| x != y)
+                .count();
+            assert_eq!(
+                packed_a.mismatch_count(start, &packed_b, other_start, length),
+                expected,
+                "{start} {other_start} {length}"
+            );
+        }
+        let expected = a[start..]
+            .iter()
+            .zip(&b[other_start..])
+            .take_while(|(x, y)| x == y)
+            .count();
+        assert_eq!(
+            packed_a.common_prefix_length(start, &packed_b, other_start),
+            expected,
+            "{start} {other_start}"
+        );
+    }
+}
+
+#[test]
+fn simd_comparison_equals_scalar_comparison() {
+    let a = pseudo_random_dna(1000, 83);
+    // Long common substrings of `a` at word boundaries and unaligned offsets, with some mutations.
+    let mut b = [&a[7..], &a[..300]].concat();
+    for position in [40, 200, 201, 529, 700, 1100] {
+        b[position] = if b[position] == b'A' { b'C' } else { b'A' };
+    }
+    let packed_a = PackedText::new(&a).unwrap();
+    let packed_b = PackedText::new(&b).unwrap();
+
+    let starts = [
+        0usize, 1, 7, 31, 32, 33, 63, 64, 100, 128, 129, 256, 300, 511, 700,
+    ];
+    for start in starts {
+        for other_start in starts.into_iter().chain([start.saturating_sub(7)]) {
+            for length in [0, 127, 128, 129, 255, 256, 257, 300, 500, 640] {
+                if start + length > a.len() || other_start + length > b.len() {
+                    continue;
+                }
+                assert_eq!(
+                    packed_a.mismatch_count(start, &packed_b, other_start, length),
+                    packed_a.scalar_mismatch_count(start, &packed_b, other_start, 0, length),
+                    "{start} {other_start} {length}"
+                );
+            }
+            assert_eq!(
+                packed_a.common_prefix_length(start, &packed_b, other_start),
+                packed_a.scalar_common_prefix_length(start, &packed_b, other_start, 0),
+                "{start} {other_start}"
+            );
+        }
+    }
+}
+
+#[test]
+fn lazy_long_kmers_equal_table_matches() {
+    let complement = |character: &u8| match character {
+        b'A' => b'T',
+        b'C' => b'G',
+        b'G' => b'C',
+        b'T' => b'A',
+        _ => unreachable!(),
+    };
+    let reference_ascii = pseudo_random_dna(700, 84);
+    // The reverse complement of an unaligned substring of the reference, with two mismatches.
+    let mut inserted: Vec<_> = reference_ascii[33..533]
+        .iter()
+        .rev()
+        .map(complement)
+        .collect();
+    inserted[150] = complement(&inserted[150]);
+    inserted[351] = complement(&inserted[351]);
+    let query_ascii = [
+        pseudo_random_dna(90, 85),
+        inserted,
+        pseudo_random_dna(90, 86),
+    ]
+    .concat();
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::<DnaAlphabet>::from_slice_u8(&query_ascii).unwrap();
+
+    // With the `simd` feature, kmers of at least 128 characters are compared with AVX2,
+    // while the table compares them character by character.
+    for builder in [
+        MatchTableBuilder::new(130),
+        MatchTableBuilder::new(140).max_mismatches(2),
+    ] {
+        let table = builder.build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+        let lazy = builder.build_lazy(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+        for quadrant in [Quadrant::ReferenceQuery, Quadrant::QueryReference] {
+            assert!(table.matches(quadrant).count() > 0);
+            for primary_index in (0..table.primary_kmer_count(quadrant)).step_by(5) {
+                for secondary_rc_index in 0..table.secondary_kmer_count(quadrant) {
+                    assert_eq!(
+                        lazy.has_match(quadrant, primary_index, secondary_rc_index),
+                        table.has_match(quadrant, primary_index, secondary_rc_index),
+                        "{builder:?} {quadrant} {primary_index} {secondary_rc_index}"
+                    );
+                }
+            }
+            for (primary_index, secondary_rc_index, length) in table.matches_with_length(quadrant) {
+                assert_eq!(
+                    lazy.longest_match_length(quadrant, primary_index, secondary_rc_index),
+                    Some(length),
+                    "{builder:?} {quadrant} {primary_index} {secondary_rc_index}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn lazy_longest_match_lengths_equal_table_lengths() {
+    let reference_ascii = [pseudo_random_dna(70, 65), b"ACGTTGCA".repeat(20)].concat();
+    let query_ascii = [b"TGCAACGT".repeat(20), pseudo_random_dna(60, 66)].concat();
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::<DnaAlphabet>::from_slice_u8(&query_ascii).unwrap();
+    let reference_mask: bitvec::vec::BitVec = (0..reference_ascii.len())
+        .map(|position| position == 150)
+        .collect();
+
+    for builder in [
+        MatchTableBuilder::new(4),
+        MatchTableBuilder::new(4).reference_mask(reference_mask),
+        MatchTableBuilder::new(4).band(-100, 100),
+        MatchTableBuilder::new(4).minimizer_window(3),
+        MatchTableBuilder::new(5).max_mismatches(1),
+    ] {
+        let table = builder.build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+        let lazy = builder.build_lazy(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+        for quadrant in Quadrant::ALL {
+            for (primary_index, secondary_rc_index, length) in table.matches_with_length(quadrant) {
+                assert_eq!(
+                    lazy.longest_match_length(quadrant, primary_index, secondary_rc_index),
+                    Some(length),
+                    "{builder:?} {quadrant} {primary_index} {secondary_rc_index}"
+                );
+            }
+        }
+    }
+}
+
+#[cfg(feature = "gpu")]
+#[test]
+fn gpu_tables_equal_cpu_tables() {
+    let reference_ascii = [pseudo_random_dna(300, 67), b"ACGTTGCA".repeat(10)].concat();
+    let query_ascii = [b"TGCAACGT".repeat(10), pseudo_random_dna(250, 68)].concat();
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::<DnaAlphabet>::from_slice_u8(&query_ascii).unwrap();
+    let reference_mask: bitvec::vec::BitVec = (0..reference_ascii.len())
+        .map(|position| position % 50 == 7)
+        .collect();
+
+    for builder in [
+        MatchTableBuilder::new(4),
+        MatchTableBuilder::new(5).reference_mask(reference_mask),
+        MatchTableBuilder::new(4).band(-100, 50),
+        MatchTableBuilder::new(4).minimizer_window(3),
+        MatchTableBuilder::new(4).quadrants(Quadrants::REFERENCE_QUERY),
+        MatchTableBuilder::new(5).max_mismatches(1),
+    ] {
+        let table = builder.build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+        let gpu_table = match builder.build_gpu(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        ) {
+            Ok(gpu_table) => gpu_table,
+            // Machines without a GPU cannot run the test.
+            Err(MatchTableError::Gpu(_)) => return,
+            Err(error) => panic!("{error}"),
+        };
+        for quadrant in Quadrant::ALL {
+            assert!(
+                table.matches(quadrant).eq(gpu_table.matches(quadrant)),
+                "{builder:?} {quadrant}"
+            );
+        }
+        assert_eq!(
+            table.skipped_reference_kmer_count(),
+            gpu_table.skipped_reference_kmer_count()
+        );
+    }
+}
+
+#[test]
+fn shared_table_is_queried_concurrently() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<MatchTable>();
+    assert_send_sync::<SharedMatchTable>();
+
+    let reference_ascii = pseudo_random_dna(200, 69);
+    let query_ascii = pseudo_random_dna(150, 70);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::<DnaAlphabet>::from_slice_u8(&query_ascii).unwrap();
+
+    for storage in [StorageBackend::Dense, StorageBackend::Sparse] {
+        let table = MatchTableBuilder::new(3).storage(storage).build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+        let expected: Vec<Vec<_>> = Quadrant::ALL
+            .into_iter()
+            .map(|quadrant| table.matches(quadrant).collect())
+            .collect();
+        let shared = table.into_shared();
+
+        let threads: Vec<_> = Quadrant::ALL
+            .into_iter()
+            .map(|quadrant| {
+                let shared = shared.clone();
+                std::thread::spawn(move || shared.matches(quadrant).collect::<Vec<_>>())
+            })
+            .collect();
+        for (thread, expected) in threads.into_iter().zip(&expected) {
+            assert_eq!(&thread.join().unwrap(), expected);
+        }
+
+        assert_eq!(shared.handle_count(), 1);
+        let table = shared.try_into_inner().unwrap_or_else(|_| unreachable!());
+        assert_eq!(
+            table.matches(Quadrant::ReferenceQuery).collect::<Vec<_>>(),
+            expected[1]
+        );
+    }
+}
+
+#[test]
+fn memory_estimate_follows_storage_layout() {
+    // 1000 reference kmers and 100 query kmers.
+    let estimate = MatchTable::estimate_memory(1009, 109, 10);
+    let quadrant_bits: [u64; 4] = [1000 * 1000, 1000 * 100, 100 * 1000, 100 * 100];
+    let dense_bytes: u64 = quadrant_bits
+        .iter()
+        .map(|bits| bits.div_ceil(64) * 8 + (bits.div_ceil(1024) + 1) * 8)
+        .sum();
+    assert_eq!(estimate.dense_storage_bytes, dense_bytes);
+    assert_eq!(
+        estimate.mapped_file_bytes,
+        quadrant_bits.iter().map(|bits| bits / 8).sum::<u64>()
+    );
+    assert_eq!(estimate.sparse_storage_bytes, (1001 + 1001 + 101 + 101) * 8);
+    assert_eq!(estimate.sparse_bytes_per_match, 4);
+    assert_eq!(estimate.index_bytes, 5 * (1009 + 109));
+    assert_eq!(
+        estimate.total_sparse_bytes(10),
+        estimate.total_sparse_bytes(0) + 10 * 3 * 4
+    );
+
+    let banded = MatchTableBuilder::new(10)
+        .band(-5, 5)
+        .estimate_memory(1009, 109);
+    assert!(banded.dense_storage_bytes < estimate.dense_storage_bytes / 8);
+    assert_eq!(banded.mapped_file_bytes, estimate.mapped_file_bytes);
+
+    let symmetric = MatchTableBuilder::new(10)
+        .storage(StorageBackend::Symmetric)
+        .estimate_memory(1009, 109);
+    assert!(symmetric.dense_storage_bytes < estimate.dense_storage_bytes);
+
+    let selected = MatchTableBuilder::new(10)
+        .quadrants(Quadrants::QUERY_QUERY)
+        .index_backend(IndexBackend::FmIndex)
+        .estimate_memory(1009, 109);
+    assert_eq!(
+        selected.dense_storage_bytes,
+        100 * 100 / 64 * 8 + 8 + (100 * 100 / 1024 + 2) * 8
+    );
+    assert!(selected.index_bytes < 2 * 109);
+    assert_eq!(selected.text_bytes, estimate.text_bytes);
+}
+
+#[test]
+fn oversized_quadrants_return_errors() {
+    let max_bit_count = bitvec::slice::BitSlice::<usize, bitvec::order::Lsb0>::MAX_BITS;
+    for (backend, band, self_comparison) in [
+        (StorageBackend::Dense, None, false),
+        (StorageBackend::Symmetric, None, true),
+        (
+            StorageBackend::Dense,
+            Some(crate::band::Band {
+                min_offset: -2,
+                max_offset: 2,
+            }),
+            false,
+        ),
+    ] {
+        let kmer_count = usize::MAX / 3;
+        assert!(matches!(
+            QuadrantStorageBuilder::<u32>::new(backend, kmer_count, kmer_count, band, self_comparison),
+            Err(MatchTableError::QuadrantTooLarge {
+                primary_kmer_count,
+                max_bit_count: error_max_bit_count,
+                ..
+            }) if primary_kmer_count == kmer_count && error_max_bit_count == max_bit_count
+        ));
+    }
+
+    // Addressable, but far beyond any memory.
+    if usize::BITS == 64 {
+        assert!(matches!(
+            QuadrantStorageBuilder::<u32>::new(
+                StorageBackend::Dense,
+                1 << 40,
+                1 << 20,
+                None,
+                false
+            ),
+            Err(MatchTableError::AllocationTooLarge { .. })
+        ));
+    }
+
+    #[cfg(feature = "mmap")]
+    {
+        assert!(matches!(
+            QuadrantStorageBuilder::<u32>::mapped_region_length(usize::MAX / 2, 3),
+            Err(MatchTableError::QuadrantTooLarge { .. })
+        ));
+        if usize::BITS == 64 {
+            assert_eq!(
+                QuadrantStorageBuilder::<u32>::mapped_region_length(1 << 20, 1 << 20).unwrap(),
+                1 << 37
+            );
+        }
+    }
+}
+
+#[test]
+fn bit_packed_genomes_are_converted_without_strings() {
+    use compact_genome::implementation::bit_vec_sequence::BitVectorGenome;
+
+    let reference_ascii = pseudo_random_dna(300, 71);
+    let query_ascii = b"ACGTRYNNACGTKM".repeat(10);
+    let reference =
+        BitVectorGenome::<DnaIupacNucleicAcidAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query =
+        BitVectorGenome::<DnaIupacNucleicAcidAlphabet>::from_slice_u8(&query_ascii).unwrap();
+
+    let texts = crate::construction::Texts::new(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        &MatchTableBuilder::new(4),
+    );
+    assert_eq!(texts.reference, reference_ascii);
+    assert_eq!(texts.query, query_ascii);
+    assert!(
+        texts
+            .query_rc()
+            .iter()
+            .eq(query.reverse_complement_iter().map(u8::from))
+    );
+
+    let vector_reference =
+        VectorGenome::<DnaIupacNucleicAcidAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let vector_query =
+        VectorGenome::<DnaIupacNucleicAcidAlphabet>::from_slice_u8(&query_ascii).unwrap();
+    let builder = MatchTableBuilder::new(4).ambiguity_policy(AmbiguityPolicy::Compatible);
+    let expected = builder.build(
+        vector_reference.as_genome_subsequence(),
+        vector_query.as_genome_subsequence(),
+    );
+    let actual = builder.build(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+    );
+    for quadrant in Quadrant::ALL {
+        assert!(
+            expected.matches(quadrant).eq(actual.matches(quadrant)),
+            "{quadrant}"
+        );
+    }
+}
+
+#[test]
+fn reverse_complement_kmers_are_computed_on_the_fly() {
+    let reference_ascii = b"ACGTRYSWKMBDHVN".repeat(3);
+    let query_ascii = pseudo_random_dna(40, 72);
+    let reference =
+        VectorGenome::<DnaIupacNucleicAcidAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::<DnaIupacNucleicAcidAlphabet>::from_slice_u8(&query_ascii).unwrap();
+    let texts = crate::construction::Texts::new(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        &MatchTableBuilder::new(4),
+    );
+
+    for (rc_text, genome) in [
+        (texts.reference_rc(), &reference),
+        (texts.query_rc(), &query),
+    ] {
+        let rc: Vec<u8> = genome.reverse_complement_iter().map(u8::from).collect();
+        for start in 0..rc.len() {
+            for length in 0..=rc.len() - start {
+                assert!(
+                    rc_text
+                        .substring(start, length)
+                        .eq(rc[start..start + length].iter().copied()),
+                    "{start} {length}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn bloom_filter_does_not_change_tables() {
+    let reference_ascii = pseudo_random_dna(400, 73);
+    let mut query_ascii = pseudo_random_dna(300, 74);
+    // Share a few kmers between the otherwise unrelated sequences.
+    query_ascii[100..130].copy_from_slice(&reference_ascii[200..230]);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+
+    for builder in [
+        MatchTableBuilder::new(8),
+        MatchTableBuilder::new(8).index_backend(IndexBackend::FmIndex),
+        MatchTableBuilder::new(8).strategy(ConstructionStrategy::HashJoin),
+        MatchTableBuilder::new(8).canonical(true),
+        MatchTableBuilder::new(8).max_mismatches(1),
+        MatchTableBuilder::new(8).quadrants(Quadrants::REFERENCE_QUERY),
+    ] {
+        let expected = builder.build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+        for false_positive_rate in [0.5, 0.01, 1e-6] {
+            let actual = builder.clone().bloom_filter(false_positive_rate).build(
+                reference.as_genome_subsequence(),
+                query.as_genome_subsequence(),
+            );
+            for quadrant in Quadrant::ALL {
+                assert!(
+                    expected.matches(quadrant).eq(actual.matches(quadrant)),
+                    "{builder:?} {false_positive_rate} {quadrant}"
+                );
+            }
+        }
+    }
+
+    for false_positive_rate in [0.0, 1.0, f64::NAN] {
+        assert!(matches!(
+            MatchTableBuilder::new(8)
+                .bloom_filter(false_positive_rate)
+                .try_build(
+                    reference.as_genome_subsequence(),
+                    query.as_genome_subsequence()
+                ),
+            Err(MatchTableError::InvalidFalsePositiveRate { .. })
+        ));
+    }
+}
+
+#[test]
+fn statistics_equal_full_scans() {
+    let reference_ascii = pseudo_random_dna(200, 75);
+    let mut query_ascii = pseudo_random_dna(150, 76);
+    query_ascii[20..60].copy_from_slice(&reference_ascii[100..140]);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+
+    for builder in [
+        MatchTableBuilder::new(3),
+        MatchTableBuilder::new(3).storage(StorageBackend::Sparse),
+        MatchTableBuilder::new(3).storage(StorageBackend::Symmetric),
+        MatchTableBuilder::new(3).band(-20, 20),
+        MatchTableBuilder::new(3).quadrants(Quadrants::REFERENCE_QUERY),
+    ] {
+        let matches = builder.build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+        let statistics = matches.statistics();
+        for quadrant in Quadrant::ALL {
+            let quadrant_statistics = statistics.quadrant(quadrant);
+            let mut rows = vec![0; matches.primary_kmer_count(quadrant)];
+            let mut columns = vec![0; matches.secondary_kmer_count(quadrant)];
+            for (primary_index, secondary_rc_index) in matches.matches(quadrant) {
+                rows[primary_index] += 1;
+                columns[secondary_rc_index] += 1;
+            }
+            let busiest = |counts: Vec<usize>| {
+                let mut lines: Vec<_> = counts
+                    .into_iter()
+                    .enumerate()
+                    .filter(|&(_, count)| count > 0)
+                    .collect();
+                lines.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+                lines.truncate(crate::BUSIEST_LINE_COUNT);
+                lines
+            };
+
+            let match_count = matches.matches(quadrant).count();
+            assert_eq!(quadrant_statistics.match_count, match_count, "{builder:?}");
+            assert_eq!(
+                quadrant_statistics.density,
+                match_count as f64 / (rows.len() * columns.len()) as f64
+            );
+            assert_eq!(
+                quadrant_statistics.computed,
+                builder.quadrants.contains(quadrant)
+            );
+            assert_eq!(
+                quadrant_statistics.busiest_rows,
+                busiest(rows),
+                "{builder:?}"
+            );
+            assert_eq!(
+                quadrant_statistics.busiest_columns,
+                busiest(columns),
+                "{builder:?}"
+            );
+            assert!(quadrant_statistics.storage_bytes > 0 || !quadrant_statistics.computed);
+        }
+        assert_eq!(statistics.to_string().lines().count(), 5, "{statistics}");
+    }
+}
+
+#[test]
+fn inner_length_histogram_counts_maximal_matches() {
+    let reference_ascii = pseudo_random_dna(300, 77);
+    let mut query_ascii = pseudo_random_dna(200, 78);
+    // Plant inners of lengths 25 and 12 into the reverse complement of the query.
+    let reverse_complement = |sequence: &[u8]| -> Vec<u8> {
+        sequence
+            .iter()
+            .rev()
+            .map(|&character| match character {
+                b'A' => b'T',
+                b'C' => b'G',
+                b'G' => b'C',
+                _ => b'A',
+            })
+            .collect()
+    };
+    query_ascii[10..35].copy_from_slice(&reverse_complement(&reference_ascii[50..75]));
+    query_ascii[100..112].copy_from_slice(&reverse_complement(&reference_ascii[150..162]));
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+    let matches = MatchTable::new(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        6,
+    );
+
+    for quadrants in [Quadrants::REFERENCE_QUERY, Quadrants::ALL] {
+        let histogram = matches.inner_length_histogram(quadrants);
+        let lengths: Vec<_> = quadrants
+            .iter()
+            .flat_map(|quadrant| matches.maximal_matches(quadrant))
+            .map(|(_, _, length)| length)
+            .collect();
+        assert_eq!(histogram.total_count(), lengths.len());
+        assert_eq!(histogram.max_length(), lengths.iter().copied().max());
+        for length in 0..40 {
+            assert_eq!(
+                histogram.count(length),
+                lengths.iter().filter(|&&other| other == length).count()
+            );
+            assert_eq!(
+                histogram.count_at_least(length),
+                lengths.iter().filter(|&&other| other >= length).count()
+            );
+        }
+        assert_eq!(
+            histogram.iter().map(|(_, count)| count).sum::<usize>(),
+            lengths.len()
+        );
+    }
+
+    let histogram = matches.inner_length_histogram(Quadrants::REFERENCE_QUERY);
+    assert!(histogram.count_at_least(25) >= 1);
+    assert!(histogram.count_at_least(12) >= 2);
+    assert_eq!(
+        histogram.to_string().lines().count(),
+        histogram.iter().count() + 1
+    );
+}
+
+#[test]
+fn excluded_intervals_equal_character_masks() {
+    let reference_ascii = pseudo_random_dna(200, 79);
+    let query_ascii = pseudo_random_dna(150, 80);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+    let reference_intervals = [30..60, 0..3, 55..70, 100..100, 197..200];
+    let query_intervals = [10..20, 20..25];
+    let mask = |length: usize, intervals: &[std::ops::Range<usize>]| {
+        let mut mask = bitvec::vec::BitVec::repeat(false, length);
+        for interval in intervals {
+            mask[interval.clone()].fill(true);
+        }
+        mask
+    };
+
+    let builder = MatchTableBuilder::new(4);
+    let expected = builder
+        .clone()
+        .reference_mask(mask(reference_ascii.len(), &reference_intervals))
+        .query_mask(mask(query_ascii.len(), &query_intervals))
+        .build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+    let actual = builder
+        .clone()
+        .reference_excluded_intervals(reference_intervals.clone())
+        .query_excluded_intervals(query_intervals.clone())
+        .build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+    for quadrant in Quadrant::ALL {
+        assert!(expected.matches(quadrant).eq(actual.matches(quadrant)));
+    }
+    assert_eq!(
+        actual.skipped_reference_kmer_count(),
+        expected.skipped_reference_kmer_count()
+    );
+    assert_eq!(
+        actual.skipped_query_kmer_count(),
+        expected.skipped_query_kmer_count()
+    );
+    assert_eq!(
+        actual.reference_excluded_intervals(),
+        &[0..3, 30..70, 197..200]
+    );
+    assert_eq!(
+        actual.query_excluded_intervals(),
+        &[std::ops::Range { start: 10, end: 25 }]
+    );
+
+    let mut binary = Vec::new();
+    actual.write_binary(&mut binary).unwrap();
+    let loaded = MatchTable::<u32>::read_binary(binary.as_slice()).unwrap();
+    assert_eq!(
+        loaded.reference_excluded_intervals(),
+        actual.reference_excluded_intervals()
+    );
+    assert_eq!(
+        loaded.query_excluded_intervals(),
+        actual.query_excluded_intervals()
+    );
+
+    let lazy = builder
+        .clone()
+        .reference_excluded_intervals(reference_intervals.clone())
+        .build_lazy(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+    assert!(!lazy.has_reference_query_match(40, 0));
+
+    let beyond_end = std::ops::Range {
+        start: 190,
+        end: 201,
+    };
+    let reversed = std::ops::Range { start: 5, end: 4 };
+    for (reference_intervals, query_intervals) in
+        [(vec![beyond_end], vec![]), (vec![], vec![reversed])]
+    {
+        assert!(matches!(
+            builder
+                .clone()
+                .reference_excluded_intervals(reference_intervals)
+                .query_excluded_intervals(query_intervals)
+                .try_build(
+                    reference.as_genome_subsequence(),
+                    query.as_genome_subsequence()
+                ),
+            Err(MatchTableError::InvalidInterval { .. })
+        ));
+    }
+}
+
+#[test]
+fn low_complexity_kmers_are_excluded() {
+    use crate::LowComplexityFilter;
+
+    let mut reference_ascii = pseudo_random_dna(300, 81);
+    reference_ascii[50..80].fill(b'A');
+    reference_ascii[100..130].fill(b'T');
+    for (offset, character) in reference_ascii[150..180].iter_mut().enumerate() {
+        *character = if offset % 2 == 0 { b'C' } else { b'A' };
+    }
+    let query_ascii = pseudo_random_dna(200, 82);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+    let k = 12;
+
+    let brute_force_dust = |kmer: &[u8]| {
+        let triplets: Vec<_> = kmer.windows(3).collect();
+        let pair_count = (0..triplets.len())
+            .flat_map(|i| (i + 1..triplets.len()).map(move |j| (i, j)))
+            .filter(|&(i, j)| triplets[i] == triplets[j])
+            .count();
+        pair_count as f64 / (triplets.len() - 1) as f64
+    };
+    let brute_force_entropy = |kmer: &[u8]| {
+        b"ACGT"
+            .iter()
+            .map(|character| kmer.iter().filter(|&c| c == character).count())
+            .filter(|&count| count > 0)
+            .map(|count| {
+                let p = count as f64 / kmer.len() as f64;
+                -p * p.log2()
+            })
+            .sum::<f64>()
+    };
+
+    for filter in [
+        LowComplexityFilter::Dust { threshold: 1.5 },
+        LowComplexityFilter::Entropy { min_entropy: 1.2 },
+    ] {
+        let is_low_complexity = |kmer: &[u8]| match filter {
+            LowComplexityFilter::Dust { threshold } => brute_force_dust(kmer) > threshold,
+            LowComplexityFilter::Entropy { min_entropy } => {
+                brute_force_entropy(kmer) < min_entropy - 1e-9
+            }
+        };
+        let expected = reference_ascii
+            .windows(k)
+            .map(is_low_complexity)
+            .collect::<Vec<_>>();
+        assert!(expected[50..80 - k + 1].iter().all(|&low| low));
+        assert!(expected[150..180 - k + 1].iter().all(|&low| low));
+        assert_eq!(
+            filter
+                .low_complexity_kmers(&reference_ascii, k)
+                .iter()
+                .by_vals()
+                .collect::<Vec<_>>(),
+            expected,
+            "{filter:?}"
+        );
+
+        let unfiltered = MatchTableBuilder::new(k).build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+        let filtered = MatchTableBuilder::new(k)
+            .low_complexity_filter(filter)
+            .build(
+                reference.as_genome_subsequence(),
+                query.as_genome_subsequence(),
+            );
+        assert_eq!(
+            filtered.skipped_reference_kmer_count(),
+            expected.iter().filter(|&&low| low).count()
+        );
+        for quadrant in Quadrant::ALL {
+            assert!(
+                filtered
+                    .matches(quadrant)
+                    .all(|(primary_index, secondary_rc_index)| {
+                        unfiltered.has_match(quadrant, primary_index, secondary_rc_index)
+                    })
+            );
+        }
+        assert!(
+            filtered.matches(Quadrant::ReferenceReference).count()
+                < unfiltered.matches(Quadrant::ReferenceReference).count()
+        );
+    }
+
+    assert!(matches!(
+        MatchTableBuilder::new(k)
+            .low_complexity_filter(LowComplexityFilter::Dust { threshold: -1.0 })
+            .try_build(
+                reference.as_genome_subsequence(),
+                query.as_genome_subsequence()
+            ),
+        Err(MatchTableError::InvalidLowComplexityThreshold { .. })
+    ));
+}
+
+#[cfg(feature = "naive")]
+#[test]
+fn naive_matches_equal_constructed_matches() {
+    use crate::naive::{assert_equivalent, naive_matches};
+
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(
+        b"TTACGGATTTGGGGAACCCCAACGTACGGTCCGTAAAACGTTTTGCA",
+    )
+    .unwrap();
+    let query = VectorGenome::from_slice_u8(b"GGTCCGTGGAAACGTTTCCGTAATCCGAAAAGGGG").unwrap();
+
+    for options in [
+        MatchTableBuilder::new(4),
+        MatchTableBuilder::new(5).max_mismatches(1),
+        MatchTableBuilder::new(4).canonical(true),
+        MatchTableBuilder::new(4).band(-10, 5),
+        MatchTableBuilder::new(4).minimizer_window(3),
+        MatchTableBuilder::new(4)
+            .storage(StorageBackend::Sparse)
+            .quadrants(Quadrants::REFERENCE_QUERY),
+        MatchTableBuilder::new(4)
+            .reference_excluded_intervals([std::ops::Range { start: 10, end: 25 }]),
+        MatchTableBuilder::new(4).strategy(ConstructionStrategy::BandScan),
+        MatchTableBuilder::new(4).orientation(MatchOrientation::Forward),
+        MatchTableBuilder::new(4).max_kmer_occurrences(2),
+        MatchTableBuilder::new(4).unique_matches_only(true),
+        #[cfg(feature = "roaring")]
+        MatchTableBuilder::new(4).storage(StorageBackend::Roaring),
+    ] {
+        let expected = naive_matches(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+            &options,
+        )
+        .unwrap();
+        assert!(expected.iter().any(|matches| !matches.is_empty()));
+        let matches = options.build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+        assert_equivalent(&matches, &expected);
+    }
+
+    assert!(matches!(
+        naive_matches(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+            &MatchTableBuilder::new(0),
+        ),
+        Err(MatchTableError::InvalidMinimumLength { .. })
+    ));
+}
+
+#[test]
+fn simulated_template_switches_are_found() {
+    use crate::simulate::{SimulationBuilder, SimulationError};
+
+    let genomes = SimulationBuilder::new(2_000)
+        .event_count(5)
+        .inner_length(15, 30)
+        .template_offset(-200, 200)
+        .mutation_rate(0.05)
+        .seed(7)
+        .generate()
+        .unwrap();
+    assert_eq!(genomes.reference.len(), 2_000);
+    assert_eq!(genomes.query.len(), 2_000);
+    assert_eq!(genomes.events.len(), 5);
+    let mismatch_count = genomes
+        .reference
+        .iter()
+        .zip(&genomes.query)
+        .filter(|(reference, query)| reference != query)
+        .count();
+    assert!((50..=250).contains(&mismatch_count));
+
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&genomes.reference).unwrap();
+    let query = VectorGenome::from_slice_u8(&genomes.query).unwrap();
+    let matches = MatchTable::new(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        15,
+    );
+    for event in &genomes.events {
+        assert!((15..=30).contains(&event.inner_length()));
+        assert!((-200..=200).contains(&event.template_offset(0)));
+        let constraints = CandidateConstraints::new()
+            .min_inner_length(event.inner_length())
+            .max_inner_length(event.inner_length());
+        assert!(
+            matches
+                .candidates(Quadrant::QueryReference, &constraints)
+                .any(|candidate| candidate == *event),
+            "{event:?}"
+        );
+    }
+
+    assert_eq!(
+        SimulationBuilder::new(2_000).seed(3).generate(),
+        SimulationBuilder::new(2_000).seed(3).generate()
+    );
+    assert!(matches!(
+        SimulationBuilder::new(100).event_count(5).generate(),
+        Err(SimulationError::TooManyEvents { .. })
+    ));
+    assert!(matches!(
+        SimulationBuilder::new(100).inner_length(0, 10).generate(),
+        Err(SimulationError::InvalidInnerLength { .. })
+    ));
+    assert!(matches!(
+        SimulationBuilder::new(100).mutation_rate(1.5).generate(),
+        Err(SimulationError::InvalidMutationRate { .. })
+    ));
+    assert!(matches!(
+        SimulationBuilder::new(100)
+            .template_offset(500, 600)
+            .generate(),
+        Err(SimulationError::TemplateOutOfBounds { .. })
+    ));
+}
+
+#[test]
+fn evaluation_against_simulated_template_switches() {
+    use crate::simulate::{SimulationBuilder, evaluate};
+
+    let genomes = SimulationBuilder::new(3_000)
+        .event_count(4)
+        .inner_length(20, 30)
+        .mutation_rate(0.02)
+        .seed(11)
+        .generate()
+        .unwrap();
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&genomes.reference).unwrap();
+    let query = VectorGenome::from_slice_u8(&genomes.query).unwrap();
+    let build = |k| {
+        MatchTable::new(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+            k,
+        )
+    };
+
+    let (short, long) = (build(8), build(20));
+    let (short_evaluation, long_evaluation) =
+        (genomes.evaluate(&short, 3), genomes.evaluate(&long, 3));
+    assert_eq!(short_evaluation.event_count, 4);
+    assert_eq!(short_evaluation.recall(), 1.0);
+    assert_eq!(long_evaluation.recall(), 1.0);
+    assert!(short_evaluation.precision() < long_evaluation.precision());
+    assert_eq!(
+        long_evaluation.candidate_count,
+        long.maximal_matches(Quadrant::QueryReference).count()
+    );
+    assert!(short_evaluation.f1_score() < long_evaluation.f1_score());
+
+    let exact = evaluate(
+        long.candidates(Quadrant::QueryReference, &CandidateConstraints::new()),
+        &genomes.events,
+        0,
+    );
+    assert_eq!(exact.recall(), 1.0);
+    assert!(exact.true_candidate_count >= exact.recovered_event_count);
+    assert_eq!(evaluate([], &genomes.events, 0).recall(), 0.0);
+}
+
+#[test]
+fn merged_shards_equal_whole_table() {
+    let reference_ascii = pseudo_random_dna(150, 46);
+    let mut query_ascii = pseudo_random_dna(80, 47);
+    query_ascii[20..40].copy_from_slice(&reference_ascii[100..120]);
+    let k = 5;
+    let reference_ranges = [0..60, 56..110, 106..150];
+    let query_ranges = [0..45, 41..80];
+    let genome = |text: &[u8]| VectorGenome::<DnaAlphabet>::from_slice_u8(text).unwrap();
+    let (reference, query) = (genome(&reference_ascii), genome(&query_ascii));
+
+    let mut shards = Vec::new();
+    for reference_range in &reference_ranges {
+        for query_range in &query_ranges {
+            let mut builder = MatchTableBuilder::new(k);
+            if reference_range.start == 0 {
+                builder =
+                    builder.reference_excluded_intervals([std::ops::Range { start: 30, end: 40 }]);
+            }
+            let table = builder.build(
+                genome(&reference_ascii[reference_range.clone()]).as_genome_subsequence(),
+                genome(&query_ascii[query_range.clone()]).as_genome_subsequence(),
+            );
+            shards.push((reference_range.start, query_range.start, table));
+        }
+    }
+    let whole = MatchTableBuilder::new(k)
+        .reference_excluded_intervals([std::ops::Range { start: 30, end: 40 }])
+        .build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+
+    for storage in [StorageBackend::Dense, StorageBackend::Sparse] {
+        let merged = MatchTable::merge(
+            shards.iter().map(|(reference_start, query_start, table)| {
+                (*reference_start, *query_start, table)
+            }),
+            storage,
+        )
+        .unwrap();
+        assert_eq!(merged.reference_kmer_count(), whole.reference_kmer_count());
+        assert_eq!(merged.query_kmer_count(), whole.query_kmer_count());
+        assert_eq!(
+            merged.skipped_reference_kmer_count(),
+            whole.skipped_reference_kmer_count()
+        );
+        assert_eq!(
+            merged.reference_excluded_intervals(),
+            whole.reference_excluded_intervals()
+        );
+        assert!(merged.matches(Quadrant::ReferenceQuery).count() > 0);
+        for quadrant in [Quadrant::ReferenceQuery, Quadrant::QueryReference] {
+            assert!(merged.matches(quadrant).eq(whole.matches(quadrant)));
+        }
+
+        // Self-comparisons contain only the pairs of kmers within the same range.
+        for (quadrant, ranges) in [
+            (Quadrant::ReferenceReference, &reference_ranges[..]),
+            (Quadrant::QueryQuery, &query_ranges[..]),
+        ] {
+            let kmer_count = whole.primary_kmer_count(quadrant);
+            let range_of = |kmer_index: usize| {
+                ranges
+                    .iter()
+                    .position(|range| kmer_index + k <= range.end)
+                    .unwrap()
+            };
+            let expected: Vec<_> = whole
+                .matches(quadrant)
+                .filter(|&(primary_index, secondary_rc_index)| {
+                    range_of(primary_index) == range_of(kmer_count - 1 - secondary_rc_index)
+                })
+                .collect();
+            assert_eq!(merged.matches(quadrant).collect::<Vec<_>>(), expected);
+        }
+    }
+
+    let invalid = |shards: &[(usize, usize, &MatchTable)]| {
+        MatchTable::merge(shards.iter().copied(), StorageBackend::Dense)
+    };
+    assert!(matches!(
+        invalid(&[]),
+        Err(MatchTableError::InvalidMerge(_))
+    ));
+    assert!(matches!(
+        invalid(&[(0, 0, &shards[0].2), (50, 0, &shards[2].2)]),
+        Err(MatchTableError::InvalidMerge(_))
+    ));
+    assert!(matches!(
+        invalid(&[(0, 0, &shards[0].2), (0, 0, &whole)]),
+        Err(MatchTableError::InvalidMerge(_))
+    ));
+}
+
+#[test]
+fn chunked_matches_equal_table() {
+    let mut reference_ascii = pseudo_random_dna(400, 48);
+    let query_ascii = pseudo_random_dna(150, 49);
+    reference_ascii[200..230].copy_from_slice(&query_ascii[50..80]);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+
+    for builder in [
+        MatchTableBuilder::new(5),
+        MatchTableBuilder::new(5).strategy(ConstructionStrategy::HashJoin),
+        MatchTableBuilder::new(5).index_backend(IndexBackend::FmIndex),
+        MatchTableBuilder::new(5).band(-100, 50),
+        MatchTableBuilder::new(5)
+            .band(-20, 20)
+            .strategy(ConstructionStrategy::BandScan),
+        MatchTableBuilder::new(6).max_mismatches(1),
+        MatchTableBuilder::new(5).minimizer_window(4),
+        MatchTableBuilder::new(5).bloom_filter(0.01),
+        MatchTableBuilder::new(5).orientation(MatchOrientation::Both),
+        MatchTableBuilder::new(5).quadrants(Quadrants::QUERY_REFERENCE),
+    ] {
+        let table = builder.build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+        let mut chunks = Vec::new();
+        builder
+            .try_for_each_chunk(
+                reference.as_genome_subsequence(),
+                query.as_genome_subsequence(),
+                900,
+                |chunk| {
+                    chunks.push(chunk);
+                    Ok(())
+                },
+            )
+            .unwrap();
+        assert!(chunks.len() > 3, "{builder:?}");
+
+        let mut expected_start = [0, 0];
+        for chunk in &chunks {
+            let sequence = usize::from(!chunk.primary_is_reference);
+            assert_eq!(chunk.primary_kmers.start, expected_start[sequence]);
+            expected_start[sequence] = chunk.primary_kmers.end;
+            assert!(chunk.matches.is_sorted());
+            assert!(chunk.matches.iter().all(|m| {
+                m.quadrant.primary_is_reference() == chunk.primary_is_reference
+                    && chunk.primary_kmers.contains(&m.primary_index)
+            }));
+        }
+        let mut chunked_matches: Vec<_> =
+            chunks.into_iter().flat_map(|chunk| chunk.matches).collect();
+        chunked_matches.sort_unstable();
+        let expected: Vec<_> = Quadrant::ALL
+            .into_iter()
+            .flat_map(|quadrant| {
+                table
+                    .matches(quadrant)
+                    .map(move |(primary_index, secondary_rc_index)| Match {
+                        quadrant,
+                        primary_index,
+                        secondary_rc_index,
+                    })
+            })
+            .collect();
+        assert_eq!(chunked_matches, expected, "{builder:?}");
+    }
+
+    assert!(matches!(
+        MatchTableBuilder::new(5).try_for_each_chunk(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+            100,
+            |_| Ok(())
+        ),
+        Err(MatchTableError::MemoryBudgetTooSmall { .. })
+    ));
+    assert!(matches!(
+        MatchTableBuilder::new(5).try_for_each_chunk(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+            2_000,
+            |_| Err(std::io::Error::other("disk full").into())
+        ),
+        Err(MatchTableError::IO(_))
+    ));
+}
+
+#[test]
+fn window_scanner_finds_dense_windows() {
+    let mut reference_ascii = pseudo_random_dna(1010, 50);
+    let query_ascii = pseudo_random_dna(200, 51);
+    let query_rc_ascii: Vec<_> = VectorGenome::<DnaAlphabet>::from_slice_u8(&query_ascii)
+        .unwrap()
+        .reverse_complement_iter()
+        .map(u8::from)
+        .collect();
+    reference_ascii[320..360].copy_from_slice(&query_rc_ascii[20..60]);
+    reference_ascii[760..800].copy_from_slice(&query_rc_ascii[120..160]);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+    let options = MatchTableBuilder::new(12).quadrants(Quadrants::REFERENCE_QUERY);
+    let whole = options.build(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+    );
+
+    let all_windows: Vec<_> = WindowScanner::new(options.clone(), 100)
+        .stride(50)
+        .min_density(-1.0)
+        .scan(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        )
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(all_windows.len(), 20);
+    assert_eq!(all_windows[18].reference_range, 900..1000);
+    assert_eq!(all_windows[19].reference_range, 910..1010);
+    for window in &all_windows {
+        let start = window.reference_range.start;
+        let window_kmers = start..window.reference_range.end - 11;
+        let expected: Vec<_> = whole
+            .matches(Quadrant::ReferenceQuery)
+            .filter(|(primary_index, _)| window_kmers.contains(primary_index))
+            .map(|(primary_index, secondary_rc_index)| (primary_index - start, secondary_rc_index))
+            .collect();
+        assert_eq!(
+            window
+                .table
+                .matches(Quadrant::ReferenceQuery)
+                .collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    let dense_windows: Vec<_> = WindowScanner::new(options.clone(), 100)
+        .stride(50)
+        .min_density(1e-3)
+        .scan(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        )
+        .map(|window| window.unwrap().reference_range)
+        .collect();
+    assert_eq!(dense_windows, vec![250..350, 300..400, 700..800, 750..850]);
+
+    assert!(matches!(
+        WindowScanner::new(options.clone(), 11).try_scan(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        ),
+        Err(MatchTableError::InvalidWindow { .. })
+    ));
+    assert!(matches!(
+        WindowScanner::new(options, 100).stride(0).try_scan(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        ),
+        Err(MatchTableError::InvalidWindow { .. })
+    ));
+}
+
+#[test]
+fn matches_in_primary_range_equal_filtered_matches() {
+    let reference_ascii = pseudo_random_dna(120, 52);
+    let mut query_ascii = pseudo_random_dna(90, 53);
+    query_ascii[10..40].copy_from_slice(&reference_ascii[60..90]);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+
+    let tables: Vec<_> = [
+        MatchTableBuilder::new(3),
+        MatchTableBuilder::new(3).storage(StorageBackend::Sparse),
+        MatchTableBuilder::new(3).storage(StorageBackend::Symmetric),
+        MatchTableBuilder::new(3).band(-40, 10),
+    ]
+    .iter()
+    .map(|builder| {
+        builder.build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        )
+    })
+    .collect();
+    #[cfg(feature = "mmap")]
+    let path = std::env::temp_dir().join(format!(
+        "tsefi-primary-range-test-{}.bin",
+        std::process::id()
+    ));
+    #[cfg(feature = "mmap")]
+    let tables = {
+        let mut tables = tables;
+        tables.push(
+            MatchTable::new_mmap(
+                &path,
+                reference.as_genome_subsequence(),
+                query.as_genome_subsequence(),
+                3,
+            )
+            .unwrap(),
+        );
+        tables
+    };
+
+    for table in &tables {
+        for quadrant in Quadrant::ALL {
+            let kmer_count = table.primary_kmer_count(quadrant);
+            for primary_indices in [
+                0..kmer_count,
+                0..0,
+                kmer_count..kmer_count,
+                5..17,
+                40..41,
+                kmer_count / 2..kmer_count,
+            ] {
+                let expected: Vec<_> = table
+                    .matches(quadrant)
+                    .filter(|(primary_index, _)| primary_indices.contains(primary_index))
+                    .collect();
+                assert_eq!(
+                    table
+                        .matches_in_primary_range(quadrant, primary_indices.clone())
+                        .collect::<Vec<_>>(),
+                    expected,
+                    "{quadrant} {primary_indices:?}"
+                );
+            }
+        }
+    }
+    #[cfg(feature = "mmap")]
+    {
+        drop(tables);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
+
+#[test]
+fn kmer_positions_equal_scanned_positions() {
+    let reference_ascii = pseudo_random_dna(300, 54);
+    let query_ascii = pseudo_random_dna(200, 55);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+    let scanned_positions = |text: &[u8], kmer: &[u8]| -> Vec<usize> {
+        text.windows(kmer.len())
+            .enumerate()
+            .filter(|(_, window)| *window == kmer)
+            .map(|(position, _)| position)
+            .collect()
+    };
+
+    for builder in [
+        MatchTableBuilder::new(5).strategy(ConstructionStrategy::IndexLookup),
+        MatchTableBuilder::new(5)
+            .strategy(ConstructionStrategy::IndexLookup)
+            .index_backend(IndexBackend::FmIndex),
+        MatchTableBuilder::new(5).strategy(ConstructionStrategy::HashJoin),
+        MatchTableBuilder::new(5)
+            .strategy(ConstructionStrategy::IndexLookup)
+            .quadrants(Quadrants::REFERENCE_QUERY),
+    ] {
+        let expected = builder.build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+        let (table, positions) = builder.build_with_kmer_positions(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+        for quadrant in Quadrant::ALL {
+            assert_eq!(
+                table.matches(quadrant).collect::<Vec<_>>(),
+                expected.matches(quadrant).collect::<Vec<_>>(),
+                "{builder:?}"
+            );
+        }
+
+        assert_eq!(positions.index_backend(), builder.index_backend);
+        for kmer_length in [1, 3, 5, 8] {
+            for kmer in reference_ascii
+                .windows(kmer_length)
+                .chain(query_ascii.windows(kmer_length))
+                .step_by(7)
+            {
+                assert_eq!(
+                    positions.positions_of_reference_kmer(kmer),
+                    scanned_positions(&reference_ascii, kmer)
+                );
+                assert_eq!(
+                    positions.positions_of_query_kmer(kmer),
+                    scanned_positions(&query_ascii, kmer)
+                );
+            }
+        }
+        assert!(positions.positions_of_reference_kmer(b"").is_empty());
+    }
+}
+
+#[test]
+fn matches_near_diagonal_equal_filtered_row_matches() {
+    let reference_ascii = b"ACGTACGTTTACGTAAACGTACGTGGGGCCCCACGT".repeat(3);
+    let query_ascii = b"TTTTACGTACGTCCAAAACGT".repeat(4);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+
+    for builder in [
+        MatchTableBuilder::new(4),
+        MatchTableBuilder::new(4).storage(StorageBackend::Sparse),
+        MatchTableBuilder::new(4).storage(StorageBackend::Symmetric),
+        MatchTableBuilder::new(4).band(-30, 20),
+    ] {
+        let table = builder.build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+        for quadrant in Quadrant::ALL {
+            for primary_index in (0..table.primary_kmer_count(quadrant)).step_by(3) {
+                for (min_offset, max_offset) in [
+                    (0, 0),
+                    (-5, 5),
+                    (-40, -10),
+                    (10, 200),
+                    (isize::MIN, isize::MAX),
+                    (3, -3),
+                ] {
+                    let expected: Vec<_> = table
+                        .row_matches(quadrant, primary_index)
+                        .filter(|&secondary_rc_index| {
+                            let offset = secondary_rc_index as isize - primary_index as isize;
+                            (min_offset..=max_offset).contains(&offset)
+                        })
+                        .collect();
+                    let near_diagonal = table.matches_near_diagonal(
+                        quadrant,
+                        primary_index,
+                        min_offset,
+                        max_offset,
+                    );
+                    assert_eq!(
+                        near_diagonal.collect::<Vec<_>>(),
+                        expected,
+                        "{builder:?} {quadrant} {primary_index} {min_offset} {max_offset}"
+                    );
+                    let mut reversed: Vec<_> = table
+                        .matches_near_diagonal(quadrant, primary_index, min_offset, max_offset)
+                        .rev()
+                        .collect();
+                    reversed.reverse();
+                    assert_eq!(reversed, expected);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn fingerprints_detect_stale_inputs() {
+    let reference_ascii = pseudo_random_dna(120, 56);
+    let query_ascii = pseudo_random_dna(80, 57);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+    let options = MatchTableBuilder::new(5).band(-50, 50);
+    let table = options.build(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+    );
+    let mut binary = Vec::new();
+    table.write_binary(&mut binary).unwrap();
+    let cached = MatchTable::<u32>::read_binary(binary.as_slice()).unwrap();
+
+    cached
+        .validate_against(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        )
+        .unwrap();
+    cached.validate_options(&options).unwrap();
+    cached
+        .validate_options(
+            &options
+                .clone()
+                .storage(StorageBackend::Sparse)
+                .parallel(false),
+        )
+        .unwrap();
+
+    let mut edited_reference_ascii = reference_ascii.clone();
+    edited_reference_ascii[60] = if edited_reference_ascii[60] == b'A' {
+        b'C'
+    } else {
+        b'A'
+    };
+    let edited_reference =
+        VectorGenome::<DnaAlphabet>::from_slice_u8(&edited_reference_ascii).unwrap();
+    assert!(matches!(
+        cached.validate_against(
+            edited_reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        ),
+        Err(MatchTableError::FingerprintMismatch { input: "reference" })
+    ));
+    let truncated_query = VectorGenome::from_slice_u8(&query_ascii[..79]).unwrap();
+    assert!(matches!(
+        cached.validate_against(
+            reference.as_genome_subsequence(),
+            truncated_query.as_genome_subsequence(),
+        ),
+        Err(MatchTableError::FingerprintMismatch { input: "query" })
+    ));
+    for stale_options in [
+        MatchTableBuilder::new(5),
+        options.clone().band(-50, 51),
+        options.clone().skip_n(true),
+        options
+            .clone()
+            .reference_excluded_intervals([std::ops::Range { start: 3, end: 7 }]),
+        options.clone().orientation(MatchOrientation::Both),
+    ] {
+        assert!(
+            matches!(
+                cached.validate_options(&stale_options),
+                Err(MatchTableError::FingerprintMismatch {
+                    input: "parameters"
+                })
+            ),
+            "{stale_options:?}"
+        );
+    }
+
+    let self_table = MatchTableBuilder::new(5).build_self(reference.as_genome_subsequence());
+    self_table
+        .validate_against(
+            reference.as_genome_subsequence(),
+            &reference.as_genome_subsequence()[0..0],
+        )
+        .unwrap();
+
+    let merged = MatchTable::merge([(0, 0, &table)], StorageBackend::Dense).unwrap();
+    assert!(matches!(
+        merged.validate_against(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        ),
+        Err(MatchTableError::MissingFingerprint)
+    ));
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn tracing_spans_cover_construction_phases() {
+    use std::sync::{Arc, Mutex};
+
+    use tracing::{
+        Event, Metadata, Subscriber,
+        span::{Attributes, Id, Record},
+    };
+
+    /// Records the names of the created spans.
+    struct SpanNames(Arc<Mutex<Vec<&'static str>>>);
+
+    impl Subscriber for SpanNames {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut names = self.0.lock().unwrap();
+            names.push(span.metadata().name());
+            Id::from_u64(names.len() as u64)
+        }
+
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, _: &Event<'_>) {}
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+    let names = Arc::new(Mutex::new(Vec::new()));
+    let table = tracing::subscriber::with_default(SpanNames(names.clone()), || {
+        MatchTableBuilder::new(4).build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        )
+    });
+    assert!(table.has_reference_reference_match(1, 2));
+    assert_eq!(
+        *names.lock().unwrap(),
+        [
+            "allocate_storage",
+            "construct_match_table",
+            "build_indexes",
+            "find_matches",
+            "find_matches",
+        ]
+    );
+}
+
+#[test]
+fn cancellation_aborts_construction() {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    };
+
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    let query = VectorGenome::from_slice_u8(b"AAAAAAAA").unwrap();
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let reporter_cancelled = cancelled.clone();
+    let options = MatchTableBuilder::new(4)
+        .cancellation(cancelled.clone())
+        .progress(move |event| {
+            if event == ProgressEvent::IndexesBuilt {
+                reporter_cancelled.store(true, Ordering::Relaxed);
+            }
+        });
+
+    assert!(matches!(
+        options.try_build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence()
+        ),
+        Err(MatchTableError::Cancelled)
+    ));
+    assert!(matches!(
+        options.try_build_self(reference.as_genome_subsequence()),
+        Err(MatchTableError::Cancelled)
+    ));
+    assert!(matches!(
+        options.try_build_multi_k(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+            &[4, 5],
+        ),
+        Err(MatchTableError::Cancelled)
+    ));
+    let mut chunk_count = 0;
+    assert!(matches!(
+        options.try_for_each_chunk(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+            u64::MAX,
+            |_| {
+                chunk_count += 1;
+                Ok(())
+            },
+        ),
+        Err(MatchTableError::Cancelled)
+    ));
+    assert_eq!(chunk_count, 0);
+
+    cancelled.store(false, Ordering::Relaxed);
+    let uncancelled = MatchTableBuilder::new(4).cancellation(cancelled);
+    assert!(
+        uncancelled
+            .build(
+                reference.as_genome_subsequence(),
+                query.as_genome_subsequence()
+            )
+            .has_reference_reference_match(1, 2)
+    );
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn parallel_output_is_independent_of_thread_count() {
+    // Long enough for several parallel chunks per genome.
+    let reference_ascii = pseudo_random_dna(9_000, 12);
+    let query_ascii = pseudo_random_dna(5_000, 13);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+
+    // Banded dense storage keeps the bit vectors small.
+    for options in [
+        MatchTableBuilder::new(7).storage(StorageBackend::Sparse),
+        MatchTableBuilder::new(7).band(-64, 64),
+    ] {
+        let build = |parallel: bool| {
+            let table = options.clone().parallel(parallel).build(
+                reference.as_genome_subsequence(),
+                query.as_genome_subsequence(),
+            );
+            let matches: Vec<Vec<_>> = Quadrant::ALL
+                .into_iter()
+                .map(|quadrant| table.matches(quadrant).collect())
+                .collect();
+            let mut binary = Vec::new();
+            table.write_binary(&mut binary).unwrap();
+            (matches, binary)
+        };
+
+        let expected = build(false);
+        assert!(expected.0.iter().all(|matches| !matches.is_empty()));
+        for thread_count in [1, 2, 3, 8] {
+            let actual = rayon::ThreadPoolBuilder::new()
+                .num_threads(thread_count)
+                .build()
+                .unwrap()
+                .install(|| build(true));
+            assert!(expected == actual, "{options:?} {thread_count}");
+        }
+    }
+}
+
+#[test]
+fn alignment_projection_follows_cigar() {
+    use crate::{AlignmentProjection, ProjectedMatch};
+
+    let alignment = AlignmentProjection::from_cigar("2=1X3I10M4N2D5M").unwrap();
+    assert_eq!(alignment.column_count(), 27);
+    assert_eq!(alignment.reference_length(), 24);
+    assert_eq!(alignment.query_length(), 21);
+    for position in 0..alignment.reference_length() {
+        let column = alignment.reference_to_column(position).unwrap();
+        assert_eq!(alignment.column_to_reference(column), Some(position));
+    }
+    for position in 0..alignment.query_length() {
+        let column = alignment.query_to_column(position).unwrap();
+        assert_eq!(alignment.column_to_query(column), Some(position));
+    }
+    assert_eq!(alignment.reference_to_column(24), None);
+    assert_eq!(alignment.query_to_column(3), Some(3));
+    assert_eq!(alignment.reference_to_column(3), Some(6));
+    assert_eq!(alignment.column_to_query(18), None);
+    assert_eq!(alignment.query_range_to_columns(15..18), Some(15..24));
+    assert_eq!(alignment.query_range_to_columns(15..15), None);
+    assert_eq!(alignment.reference_range_to_columns(20..25), None);
+
+    for (cigar, position) in [
+        ("3M2", 3),
+        ("M", 0),
+        ("3M4S", 3),
+        ("99999999999999999999M", 19),
+    ] {
+        assert!(
+            matches!(
+                AlignmentProjection::from_cigar(cigar),
+                Err(MatchTableError::InvalidCigar { position: actual }) if actual == position
+            ),
+            "{cigar}"
+        );
+    }
+
+    // The query lacks the `AA` at 5..7 of the reference.
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    let query = VectorGenome::from_slice_u8(b"AGGGGCCCCAA").unwrap();
+    let table = MatchTable::new(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        4,
+    );
+    let alignment = AlignmentProjection::from_cigar("5M2D6M").unwrap();
+    assert_eq!(
+        table.project_match(&alignment, Quadrant::ReferenceQuery, 1, 2),
+        Some(ProjectedMatch {
+            primary_columns: 1..5,
+            secondary_columns: 7..11,
+        })
+    );
+    assert_eq!(
+        table.project_match(&alignment, Quadrant::ReferenceReference, 4, 2),
+        Some(ProjectedMatch {
+            primary_columns: 4..8,
+            secondary_columns: 7..11,
+        })
+    );
+    let short_alignment = AlignmentProjection::from_cigar("5M2D2M").unwrap();
+    assert_eq!(
+        table.project_match(&short_alignment, Quadrant::ReferenceQuery, 1, 2),
+        None
+    );
+}
+
+#[cfg(feature = "fasta")]
+#[test]
+fn fastq_masks_low_quality_bases() {
+    use crate::io::fastq::{FastqError, read_fastq_records, read_fastq_sequence};
+
+    let path = std::env::temp_dir().join(format!(
+        "tsefi-fastq-records-test-{}.fq",
+        std::process::id()
+    ));
+    std::fs::write(
+        &path,
+        "@a first read\nacgt\n+a\n!5?I\n\n@b\nGGTTA\n+\n55555\n",
+    )
+    .unwrap();
+    let records = read_fastq_records::<DnaAlphabet>(&path, 20);
+    let single = read_fastq_sequence::<DnaAlphabet>(&path, 20);
+    std::fs::write(&path, "@a\nACGT\n+\n!!!\n").unwrap();
+    let truncated_quality = read_fastq_records::<DnaAlphabet>(&path, 20);
+    std::fs::write(&path, "@a\nACGT\n").unwrap();
+    let missing_lines = read_fastq_records::<DnaAlphabet>(&path, 20);
+    std::fs::remove_file(&path).unwrap();
+
+    let records = records.unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].id, "a");
+    assert_eq!(records[0].comment, "first read");
+    assert_eq!(records[0].sequence.clone_as_vec(), b"ACGT");
+    // Qualities 0, 20, 30 and 40.
+    assert_eq!(records[0].low_quality.iter_ones().collect::<Vec<_>>(), [0]);
+    assert_eq!(records[1].id, "b");
+    // Quality 20 is not below the threshold.
+    assert_eq!(records[1].low_quality.count_ones(), 0);
+    assert!(matches!(
+        single,
+        Err(FastqError::RecordCount {
+            record_count: 2,
+            ..
+        })
+    ));
+    assert!(matches!(
+        truncated_quality,
+        Err(FastqError::Format { line: 4, .. })
+    ));
+    assert!(matches!(
+        missing_lines,
+        Err(FastqError::Format { line: 1, .. })
+    ));
+}
+
+#[cfg(feature = "arrow")]
+#[test]
+fn arrow_batches_equal_tsv_export() {
+    use arrow_array::{Array, DictionaryArray, UInt64Array, cast::AsArray, types::UInt32Type};
+
+    use crate::io::{arrow::record_batches, export::write_tsv};
+
+    let reference_ascii = pseudo_random_dna(200, 14);
+    let query_ascii = pseudo_random_dna(150, 15);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+    let table = MatchTableBuilder::new(4).build_contigs(
+        &[
+            &reference.as_genome_subsequence()[..120],
+            &reference.as_genome_subsequence()[120..],
+        ],
+        &[query.as_genome_subsequence()],
+    );
+    let reference_names = ["chr1", "chr2"];
+    let query_names = ["read1"];
+
+    let mut tsv = Vec::new();
+    write_tsv(&table, &reference_names, &query_names, &mut tsv).unwrap();
+    let expected: Vec<_> = String::from_utf8(tsv)
+        .unwrap()
+        .lines()
+        .skip(1)
+        .map(str::to_owned)
+        .collect();
+    assert!(expected.len() > 100);
+
+    let batches = record_batches(&table, &reference_names, &query_names, 64).unwrap();
+    assert!(
+        batches[..batches.len() - 1]
+            .iter()
+            .all(|batch| batch.num_rows() == 64)
+    );
+    let mut actual = Vec::new();
+    for batch in &batches {
+        let string = |column: usize, row: usize| {
+            let column: &DictionaryArray<UInt32Type> = batch.column(column).as_dictionary();
+            let values = column.values().as_string::<i32>();
+            values.value(column.keys().value(row) as usize).to_owned()
+        };
+        let integer = |column: usize, row: usize| {
+            batch
+                .column(column)
+                .as_any()
+                .downcast_ref::<UInt64Array>()
+                .unwrap()
+                .value(row)
+                .to_string()
+        };
+        for row in 0..batch.num_rows() {
+            actual.push(
+                [
+                    string(0, row),
+                    string(1, row),
+                    integer(2, row),
+                    integer(3, row),
+                    string(4, row),
+                    integer(5, row),
+                    integer(6, row),
+                    integer(7, row),
+                ]
+                .join("\t"),
+            );
+        }
+        assert!(
+            batch
+                .columns()
+                .iter()
+                .all(|column| column.null_count() == 0)
+        );
+    }
+    assert_eq!(actual, expected);
+
+    #[cfg(feature = "parquet")]
+    {
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        use crate::io::arrow::{PARQUET_BATCH_SIZE, write_parquet};
+
+        let path = std::env::temp_dir().join(format!(
+            "tsefi-parquet-export-test-{}.parquet",
+            std::process::id()
+        ));
+        write_parquet(
+            &table,
+            &reference_names,
+            &query_names,
+            std::fs::File::create(&path).unwrap(),
+        )
+        .unwrap();
+        let read: Vec<_> =
+            ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(&path).unwrap())
+                .unwrap()
+                .build()
+                .unwrap()
+                .collect::<Result<_, _>>()
+                .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            read,
+            record_batches(&table, &reference_names, &query_names, PARQUET_BATCH_SIZE).unwrap()
+        );
+    }
+}
+
+#[test]
+fn json_report_lists_metadata_and_matches() {
+    use crate::io::export::write_json;
+
+    let reference_ascii = pseudo_random_dna(200, 16);
+    let query_ascii = pseudo_random_dna(150, 17);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+    let table = MatchTableBuilder::new(4)
+        .band(-40, 40)
+        .quadrants(Quadrants::REFERENCE_QUERY | Quadrants::QUERY_QUERY)
+        .build_contigs(
+            &[
+                &reference.as_genome_subsequence()[..120],
+                &reference.as_genome_subsequence()[120..],
+            ],
+            &[query.as_genome_subsequence()],
+        );
+
+    let mut output = Vec::new();
+    write_json(&table, &["chr1", "chr\t2"], &["read/1"], &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.starts_with(
+        "{\"parameters\":{\"minimum_length\":4,\"max_mismatches\":0,\"band\":{\"min_offset\":-40,\"max_offset\":40},\"max_kmer_occurrences\":null,\"unique_matches_only\":false},"
+    ));
+    assert!(output.contains(
+        "\"contigs\":[{\"name\":\"chr1\",\"length\":120},{\"name\":\"chr\\t2\",\"length\":80}]"
+    ));
+    assert!(
+        output.contains(
+            "{\"quadrant\":\"reference_reference\",\"computed\":false,\"match_count\":0}"
+        )
+    );
+    assert!(output.contains(&format!(
+        "{{\"quadrant\":\"query_query\",\"computed\":true,\"match_count\":{}}}",
+        table.matches(Quadrant::QueryQuery).count()
+    )));
+    let match_count: usize = Quadrant::ALL
+        .into_iter()
+        .map(|quadrant| table.matches(quadrant).count())
+        .sum();
+    assert!(match_count > 0);
+    assert_eq!(output.matches("\"primary_start\":").count(), match_count);
+    assert_eq!(output.matches('{').count(), output.matches('}').count());
+    assert!(output.ends_with("}]}\n"));
+}
+
+#[test]
+fn gff3_features_locate_candidate_points_in_contigs() {
+    use crate::io::export::write_gff3;
+
+    let contigs = [
+        pseudo_random_dna(60, 18),
+        [pseudo_random_dna(40, 19), pseudo_random_dna(30, 20)].concat(),
+    ];
+    let query_ascii = pseudo_random_dna(50, 21);
+    let reference: Vec<_> = contigs
+        .iter()
+        .map(|contig| VectorGenome::<DnaAlphabet>::from_slice_u8(contig).unwrap())
+        .collect();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+    let table = MatchTableBuilder::new(4).build_contigs(
+        &[
+            reference[0].as_genome_subsequence(),
+            reference[1].as_genome_subsequence(),
+        ],
+        &[query.as_genome_subsequence()],
+    );
+    let constraints = CandidateConstraints::new();
+    let candidates: Vec<_> = Quadrant::ALL
+        .into_iter()
+        .flat_map(|quadrant| table.candidates(quadrant, &constraints))
+        .collect();
+    assert!(candidates.len() > 10);
+
+    let mut output = Vec::new();
+    write_gff3(
+        &table,
+        candidates.iter().copied(),
+        &["chr 1", "chr2"],
+        &["read;1"],
+        &mut output,
+    )
+    .unwrap();
+    let output = String::from_utf8(output).unwrap();
+    let mut lines = output.lines();
+    assert_eq!(lines.next(), Some("##gff-version 3"));
+    assert_eq!(lines.next(), Some("##sequence-region chr%201 1 60"));
+    assert_eq!(lines.next(), Some("##sequence-region chr2 1 70"));
+    assert_eq!(lines.next(), Some("##sequence-region read%3B1 1 50"));
+
+    let sequence = |name: &str| -> &[u8] {
+        match name {
+            "chr%201" => &contigs[0],
+            "chr2" => &contigs[1],
+            "read%3B1" => &query_ascii,
+            _ => panic!("{name}"),
+        }
+    };
+    let features: Vec<Vec<_>> = lines.map(|line| line.split('\t').collect()).collect();
+    assert_eq!(features.len(), 6 * candidates.len());
+    for (index, (candidate, features)) in candidates.iter().zip(features.chunks(6)).enumerate() {
+        let id = format!("candidate{}", index + 1);
+        let interval = |feature: &[&str]| {
+            let start: usize = feature[3].parse().unwrap();
+            let end: usize = feature[4].parse().unwrap();
+            &sequence(feature[0])[start - 1..end]
+        };
+        let inner = interval(&features[0]);
+        let template = interval(&features[1]);
+        assert_eq!(inner.len(), candidate.inner_length());
+        assert!(
+            inner
+                .iter()
+                .zip(template.iter().rev())
+                .all(|(&a, &b)| matches!(
+                    (a, b),
+                    (b'A', b'T') | (b'T', b'A') | (b'C', b'G') | (b'G', b'C')
+                )),
+            "{features:?}"
+        );
+        assert_eq!(
+            features[0][8],
+            format!(
+                "ID={id};quadrant={};inner_length={}",
+                candidate.quadrant,
+                candidate.inner_length()
+            )
+        );
+        assert_eq!(features[1][8], format!("ID={id}.template;Parent={id}"));
+        for point in 1..=4 {
+            let feature = &features[point + 1];
+            assert_eq!(feature[2], "template_switch_point");
+            assert_eq!(feature[3], feature[4]);
+            assert!(feature[8].ends_with(&format!(";Parent={id};Name=point{point}")));
+        }
+        assert_eq!(features[2][3], features[0][3]);
+        assert_eq!(features[3][3], features[1][4]);
+        assert_eq!(features[4][3], features[1][3]);
+        assert_eq!(features[5][3], features[0][4]);
+    }
+}
+
+#[test]
+fn ts_arrangements_locate_inner_and_template_in_contigs() {
+    use crate::io::export::write_ts_arrangements;
+
+    let contigs = [
+        pseudo_random_dna(60, 24),
+        [pseudo_random_dna(40, 25), pseudo_random_dna(30, 26)].concat(),
+    ];
+    let query_ascii = pseudo_random_dna(50, 27);
+    let reference: Vec<_> = contigs
+        .iter()
+        .map(|contig| VectorGenome::<DnaAlphabet>::from_slice_u8(contig).unwrap())
+        .collect();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+    let table = MatchTableBuilder::new(4).build_contigs(
+        &[
+            reference[0].as_genome_subsequence(),
+            reference[1].as_genome_subsequence(),
+        ],
+        &[query.as_genome_subsequence()],
+    );
+    let constraints = CandidateConstraints::new();
+    let candidates: Vec<_> = Quadrant::ALL
+        .into_iter()
+        .flat_map(|quadrant| table.candidates(quadrant, &constraints))
+        .collect();
+    assert!(candidates.len() > 10);
+
+    let mut output = Vec::new();
+    write_ts_arrangements(
+        &table,
+        candidates.iter().copied(),
+        &["chr1", "chr2"],
+        &["read1"],
+        &mut output,
+    )
+    .unwrap();
+    let output = String::from_utf8(output).unwrap();
+
+    let sequence = |name: &str| -> &[u8] {
+        match name {
+            "chr1" => &contigs[0],
+            "chr2" => &contigs[1],
+            "read1" => &query_ascii,
+            _ => panic!("{name}"),
+        }
+    };
+    let lines: Vec<_> = output.lines().collect();
+    assert_eq!(lines.len(), candidates.len());
+    for (candidate, line) in candidates.iter().zip(lines) {
+        let segments: Vec<_> = line
+            .split(' ')
+            .map(|segment| {
+                let (name, range) = segment.trim_end_matches(']').split_once('[').unwrap();
+                let (start, end) = range.split_once("..").unwrap();
+                (name, start.parse::<usize>().ok(), end.parse::<usize>().ok())
+            })
+            .collect();
+        let [
+            (primary_name, None, Some(point1)),
+            (secondary_name, Some(point2), Some(point3)),
+            (end_name, Some(point4), None),
+        ] = segments[..]
+        else {
+            panic!("{line}");
+        };
+        assert_eq!(primary_name, end_name);
+        assert_eq!(point4 - point1, candidate.inner_length());
+        assert_eq!(point2 - point3, candidate.inner_length());
+        let inner = &sequence(primary_name)[point1..point4];
+        let template = &sequence(secondary_name)[point3..point2];
+        assert!(
+            inner
+                .iter()
+                .zip(template.iter().rev())
+                .all(|(&a, &b)| matches!(
+                    (a, b),
+                    (b'A', b'T') | (b'T', b'A') | (b'C', b'G') | (b'G', b'C')
+                )),
+            "{line}"
+        );
+    }
+}
+
+#[test]
+fn bit_packed_genomes_equal_vector_genomes() {
+    use compact_genome::{
+        implementation::{
+            bit_vec_sequence::BitVectorGenome, bit_vec_sequence_store::BitVectorSequenceStore,
+        },
+        interface::sequence_store::SequenceStore,
+    };
+
+    let reference_ascii = pseudo_random_dna(200, 28);
+    let query_ascii = [b"TGCAACGT".repeat(3), pseudo_random_dna(120, 29)].concat();
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+    let builder = MatchTableBuilder::new(5).max_kmer_occurrences(4);
+    let expected = builder.build(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+    );
+
+    let packed_reference = BitVectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let packed_query = BitVectorGenome::from_slice_u8(&query_ascii).unwrap();
+    let packed = builder.build(
+        packed_reference.as_genome_subsequence(),
+        packed_query.as_genome_subsequence(),
+    );
+    assert!(packed.diff(&expected).unwrap().is_empty());
+    packed
+        .validate_against(
+            packed_reference.as_genome_subsequence(),
+            packed_query.as_genome_subsequence(),
+        )
+        .unwrap();
+
+    let mut store = BitVectorSequenceStore::<DnaAlphabet>::new();
+    let reference_handle = store.add_from_slice_u8(&reference_ascii).unwrap();
+    let query_handle = store.add_from_slice_u8(&query_ascii).unwrap();
+    let stored = builder.build(store.get(&reference_handle), store.get(&query_handle));
+    assert!(stored.diff(&expected).unwrap().is_empty());
+
+    let lazy = builder.build_lazy(
+        packed_reference.as_genome_subsequence(),
+        packed_query.as_genome_subsequence(),
+    );
+    for quadrant in Quadrant::ALL {
+        assert!(
+            (0..expected.primary_kmer_count(quadrant)).all(|primary_index| lazy
+                .row_matches(quadrant, primary_index)
+                .eq(expected.row_matches(quadrant, primary_index)))
+        );
+    }
+}
+
+#[test]
+fn tsalign_seeds_pair_primary_with_reverse_complemented_secondary() {
+    let reference_ascii = pseudo_random_dna(300, 22);
+    let query_ascii = pseudo_random_dna(200, 23);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+    let matches = MatchTable::new(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        5,
+    );
+    let reverse_complement = |sequence: &[u8]| -> Vec<u8> {
+        sequence
+            .iter()
+            .rev()
+            .map(|character| match character {
+                b'A' => b'T',
+                b'C' => b'G',
+                b'G' => b'C',
+                b'T' => b'A',
+                _ => unreachable!(),
+            })
+            .collect()
+    };
+
+    for quadrant in Quadrant::ALL {
+        let sequence = |is_reference| {
+            if is_reference {
+                reference_ascii.clone()
+            } else {
+                query_ascii.clone()
+            }
+        };
+        let primary = sequence(quadrant.primary_is_reference());
+        let secondary_rc = reverse_complement(&sequence(quadrant.secondary_is_reference()));
+
+        let seeds: Vec<_> = matches.tsalign_seeds(quadrant).collect();
+        assert_eq!(seeds.len(), matches.matches(quadrant).count());
+        let maximal_seeds: Vec<_> = matches.maximal_tsalign_seeds(quadrant).collect();
+        assert!(!maximal_seeds.is_empty());
+        for seed in seeds.iter().chain(&maximal_seeds) {
+            assert_eq!(seed.quadrant, quadrant);
+            assert!(seed.len() >= 5);
+            assert_eq!(
+                primary[seed.primary.clone()],
+                secondary_rc[seed.secondary_rc.clone()]
+            );
+        }
+        for seed in &maximal_seeds {
+            let (primary_range, secondary_rc_range) = (&seed.primary, &seed.secondary_rc);
+            assert!(
+                primary_range.start == 0
+                    || secondary_rc_range.start == 0
+                    || primary[primary_range.start - 1]
+                        != secondary_rc[secondary_rc_range.start - 1]
+            );
+            assert!(
+                primary_range.end == primary.len()
+                    || secondary_rc_range.end == secondary_rc.len()
+                    || primary[primary_range.end] != secondary_rc[secondary_rc_range.end]
+            );
+        }
+    }
+}
+
+#[test]
+fn chains_bridge_mismatches_and_indels_of_inners() {
+    let reference_ascii = pseudo_random_dna(400, 24);
+    let mut inner = reference_ascii[100..160].to_vec();
+    inner[20] = if inner[20] == b'A' { b'C' } else { b'A' };
+    inner.splice(40..40, *b"GT");
+    let complement = |character: &u8| match character {
+        b'A' => b'T',
+        b'C' => b'G',
+        b'G' => b'C',
+        b'T' => b'A',
+        _ => unreachable!(),
+    };
+    let query_ascii = [
+        pseudo_random_dna(100, 25),
+        inner.iter().rev().map(complement).collect(),
+        pseudo_random_dna(100, 26),
+    ]
+    .concat();
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+    let matches = MatchTable::new(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        8,
+    );
+
+    let chains = matches.chains(Quadrant::ReferenceQuery, &ChainingParameters::new());
+    let best = &chains[0];
+    assert_eq!(best.primary, 100..160);
+    assert!(best.score > 40, "{best:?}");
+    assert!(chains[1..].iter().all(|chain| chain.score < 20));
+    assert!(chains.windows(2).all(|pair| pair[0].score >= pair[1].score));
+    for chain in &chains {
+        assert!(
+            chain
+                .anchors
+                .windows(2)
+                .all(|pair| pair[0].0 < pair[1].0 && pair[0].1 < pair[1].1)
+        );
+    }
+    let mut anchors: Vec<_> = chains
+        .iter()
+        .flat_map(|chain| chain.anchors.iter().copied())
+        .collect();
+    anchors.sort_unstable();
+    assert_eq!(
+        anchors,
+        matches
+            .matches(Quadrant::ReferenceQuery)
+            .collect::<Vec<_>>()
+    );
+
+    let same_diagonal = matches.chains(
+        Quadrant::ReferenceQuery,
+        &ChainingParameters::new()
+            .max_diagonal_shift(0)
+            .min_score(20),
+    );
+    assert_eq!(same_diagonal.len(), 2);
+    assert_eq!(same_diagonal[0].primary.start, 100);
+    assert_eq!(same_diagonal[1].primary.end, 160);
+}
+
+#[test]
+fn rank_inners_prefers_unique_inners_and_accepts_custom_scores() {
+    use crate::{DefaultScore, Inner, Score};
+
+    // The query contains the reverse complement of a unique region of the reference and of a region that occurs three times.
+    let unique = pseudo_random_dna(20, 27);
+    let repeat = pseudo_random_dna(20, 28);
+    let reference_ascii = [
+        pseudo_random_dna(30, 29),
+        unique.clone(),
+        repeat.clone(),
+        pseudo_random_dna(30, 30),
+        repeat.clone(),
+        pseudo_random_dna(30, 31),
+        repeat.clone(),
+    ]
+    .concat();
+    let complement = |character: &u8| match character {
+        b'A' => b'T',
+        b'C' => b'G',
+        b'G' => b'C',
+        b'T' => b'A',
+        _ => unreachable!(),
+    };
+    let query_ascii = [
+        unique.iter().rev().map(complement).collect(),
+        pseudo_random_dna(30, 32),
+        repeat.iter().rev().map(complement).collect(),
+    ]
+    .concat();
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+    let matches = MatchTable::new(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        10,
+    );
+
+    let ranked = matches.rank_inners(
+        matches.maximal_inners(Quadrant::QueryReference),
+        &reference_ascii,
+        &query_ascii,
+        &DefaultScore::new().gc_balance_weight(0.0),
+        25.0,
+    );
+    assert_eq!(ranked.len(), 4, "{ranked:?}");
+    // The unique inner happens to extend into the random flank.
+    assert_eq!(ranked[0].inner.primary, 0..22);
+    assert_eq!(ranked[0].score, 42.0);
+    // Each kmer of the repeated inner matches three templates.
+    for (scored, template_start) in ranked[1..].iter().zip([0, 50, 100]) {
+        assert_eq!(scored.inner.primary, 50..70);
+        assert_eq!(scored.inner.secondary_rc.start, template_start);
+        assert!((scored.score - (20.0 + 20.0 / 3.0)).abs() < 1e-9);
+    }
+
+    struct TemplateStart;
+    impl Score for TemplateStart {
+        fn score(&self, _: &MatchTable, inner: &Inner, characters: &[u8]) -> f64 {
+            assert_eq!(characters.len(), inner.primary.len());
+            inner.secondary_rc.start as f64
+        }
+    }
+    let chains = matches.chains(
+        Quadrant::ReferenceQuery,
+        &ChainingParameters::new().min_score(20),
+    );
+    let ranked = matches.rank_inners(
+        chains.iter().map(Inner::from),
+        &reference_ascii,
+        &query_ascii,
+        &TemplateStart,
+        f64::NEG_INFINITY,
+    );
+    assert_eq!(ranked.len(), chains.len());
+    assert!(
+        ranked
+            .windows(2)
+            .all(|pair| pair[0].inner.secondary_rc.start >= pair[1].inner.secondary_rc.start)
+    );
+}
+
+#[test]
+fn max_kmer_occurrences_excludes_repeated_kmers() {
+    let repeat = pseudo_random_dna(12, 33);
+    let mut reference_ascii = pseudo_random_dna(300, 34);
+    for position in [20, 80, 140, 200, 260] {
+        reference_ascii[position..position + 12].copy_from_slice(&repeat);
+    }
+    let mut query_ascii = pseudo_random_dna(200, 35);
+    query_ascii[100..112].copy_from_slice(&repeat);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+
+    let k = 6;
+    let max_occurrences = 3;
+    let reverse_complement = |kmer: &[u8]| -> Vec<u8> {
+        kmer.iter()
+            .rev()
+            .map(|character| match character {
+                b'A' => b'T',
+                b'C' => b'G',
+                b'G' => b'C',
+                b'T' => b'A',
+                _ => unreachable!(),
+            })
+            .collect()
+    };
+    let occurrences = |text: &[u8], kmer: &[u8]| {
+        let rc_kmer = reverse_complement(kmer);
+        text.windows(k)
+            .map(|window| usize::from(window == kmer) + usize::from(window == rc_kmer))
+            .sum::<usize>()
+    };
+    let frequent = |text: &[u8]| -> Vec<bool> {
+        text.windows(k)
+            .map(|kmer| {
+                occurrences(&reference_ascii, kmer) > max_occurrences
+                    || occurrences(&query_ascii, kmer) > max_occurrences
+            })
+            .collect()
+    };
+    let (frequent_reference, frequent_query) = (frequent(&reference_ascii), frequent(&query_ascii));
+    let frequent_reference_count = frequent_reference
+        .iter()
+        .filter(|&&frequent| frequent)
+        .count();
+    let frequent_query_count = frequent_query.iter().filter(|&&frequent| frequent).count();
+    assert!(frequent_reference_count >= 35);
+    assert!(frequent_query_count >= 7);
+
+    let options = MatchTableBuilder::new(k)
+        .storage(StorageBackend::Sparse)
+        .max_kmer_occurrences(max_occurrences);
+    let table = options.build(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+    );
+    assert_eq!(table.max_kmer_occurrences(), Some(max_occurrences));
+    assert_eq!(
+        table.frequent_reference_kmer_count(),
+        frequent_reference_count
+    );
+    assert_eq!(table.frequent_query_kmer_count(), frequent_query_count);
+    assert_eq!(
+        table.skipped_reference_kmer_count(),
+        frequent_reference_count
+    );
+    assert_eq!(table.skipped_query_kmer_count(), frequent_query_count);
+
+    let unfiltered = MatchTableBuilder::new(k)
+        .storage(StorageBackend::Sparse)
+        .build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+    assert_eq!(unfiltered.max_kmer_occurrences(), None);
+    assert_eq!(unfiltered.frequent_reference_kmer_count(), 0);
+    let mut removed_match_count = 0;
+    for quadrant in Quadrant::ALL {
+        let is_frequent = |is_reference: bool, index: usize| {
+            if is_reference {
+                frequent_reference[index]
+            } else {
+                frequent_query[index]
+            }
+        };
+        let secondary_kmer_count = unfiltered.secondary_kmer_count(quadrant);
+        let expected: Vec<_> = unfiltered
+            .matches(quadrant)
+            .filter(|&(primary_index, secondary_rc_index)| {
+                !is_frequent(quadrant.primary_is_reference(), primary_index)
+                    && !is_frequent(
+                        quadrant.secondary_is_reference(),
+                        secondary_kmer_count - 1 - secondary_rc_index,
+                    )
+            })
+            .collect();
+        removed_match_count += unfiltered.matches(quadrant).count() - expected.len();
+        assert_eq!(table.matches(quadrant).collect::<Vec<_>>(), expected);
+    }
+    assert!(removed_match_count > 0);
+
+    let mut binary = Vec::new();
+    table.write_binary(&mut binary).unwrap();
+    let loaded = MatchTable::<u32>::read_binary(binary.as_slice()).unwrap();
+    assert_eq!(loaded.max_kmer_occurrences(), Some(max_occurrences));
+    assert_eq!(
+        loaded.frequent_reference_kmer_count(),
+        frequent_reference_count
+    );
+    assert_eq!(loaded.frequent_query_kmer_count(), frequent_query_count);
+    assert!(loaded.validate_options(&options).is_ok());
+    assert!(loaded.validate_options(&MatchTableBuilder::new(k)).is_err());
+
+    // The longer kmers are filtered by their own occurrences, not by those of the shortest kmers.
+    let multi_k = options.build_multi_k(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        &[4, k],
+    );
+    let derived = multi_k.table(k).unwrap();
+    assert_eq!(
+        derived.frequent_reference_kmer_count(),
+        frequent_reference_count
+    );
+    for quadrant in Quadrant::ALL {
+        assert!(derived.matches(quadrant).eq(table.matches(quadrant)));
+    }
+
+    assert!(matches!(
+        MatchTableBuilder::new(k).max_kmer_occurrences(0).try_build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        ),
+        Err(MatchTableError::InvalidMaxKmerOccurrences)
+    ));
+}
+#[test]
+fn unique_matches_only_keeps_matches_of_unique_kmers() {
+    let reverse_complement = |text: &[u8]| -> Vec<u8> {
+        text.iter()
+            .rev()
+            .map(|character| match character {
+                b'A' => b'T',
+                b'C' => b'G',
+                b'G' => b'C',
+                b'T' => b'A',
+                _ => unreachable!(),
+            })
+            .collect()
+    };
+    let repeat = pseudo_random_dna(10, 36);
+    let mut reference_ascii = pseudo_random_dna(200, 37);
+    reference_ascii[30..40].copy_from_slice(&repeat);
+    reference_ascii[120..130].copy_from_slice(&repeat);
+    let mut query_ascii = pseudo_random_dna(150, 38);
+    query_ascii[50..60].copy_from_slice(&reverse_complement(&repeat));
+    // The reverse complement of a unique part of the reference.
+    query_ascii[100..120].copy_from_slice(&reverse_complement(&reference_ascii[150..170]));
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+
+    let k = 8;
+    let unique = |text: &[u8]| -> Vec<bool> {
+        text.windows(k)
+            .map(|kmer| text.windows(k).filter(|window| *window == kmer).count() == 1)
+            .collect()
+    };
+    let (unique_reference, unique_query) = (unique(&reference_ascii), unique(&query_ascii));
+
+    let options = MatchTableBuilder::new(k).unique_matches_only(true);
+    let table = options.build(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+    );
+    assert!(table.unique_matches_only());
+    assert_eq!(table.max_kmer_occurrences(), None);
+    assert_eq!(
+        table.frequent_reference_kmer_count(),
+        unique_reference.iter().filter(|&&unique| !unique).count()
+    );
+    assert_eq!(
+        table.frequent_query_kmer_count(),
+        unique_query.iter().filter(|&&unique| !unique).count()
+    );
+
+    let unfiltered = MatchTableBuilder::new(k).build(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+    );
+    assert!(!unfiltered.unique_matches_only());
+    let mut removed_match_count = 0;
+    for quadrant in Quadrant::ALL {
+        let is_unique = |is_reference: bool, index: usize| {
+            if is_reference {
+                unique_reference[index]
+            } else {
+                unique_query[index]
+            }
+        };
+        let secondary_kmer_count = unfiltered.secondary_kmer_count(quadrant);
+        let expected: Vec<_> = unfiltered
+            .matches(quadrant)
+            .filter(|&(primary_index, secondary_rc_index)| {
+                is_unique(quadrant.primary_is_reference(), primary_index)
+                    && is_unique(
+                        quadrant.secondary_is_reference(),
+                        secondary_kmer_count - 1 - secondary_rc_index,
+                    )
+            })
+            .collect();
+        removed_match_count += unfiltered.matches(quadrant).count() - expected.len();
+        assert_eq!(table.matches(quadrant).collect::<Vec<_>>(), expected);
+    }
+    assert!(removed_match_count > 0);
+    // The unique inner is kept.
+    assert!(
+        table
+            .maximal_matches(Quadrant::ReferenceQuery)
+            .any(|(_, _, length)| length >= 20)
+    );
+
+    let mut binary = Vec::new();
+    table.write_binary(&mut binary).unwrap();
+    let loaded = MatchTable::<u32>::read_binary(binary.as_slice()).unwrap();
+    assert!(loaded.unique_matches_only());
+    assert!(loaded.validate_options(&options).is_ok());
+    assert!(loaded.validate_options(&MatchTableBuilder::new(k)).is_err());
+
+    // Both filters combine.
+    let combined = options.clone().max_kmer_occurrences(1).build(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+    );
+    assert!(combined.frequent_reference_kmer_count() >= table.frequent_reference_kmer_count());
+}
+
+#[test]
+fn sample_matches_are_reproducible_nested_subsets() {
+    let repeat = pseudo_random_dna(40, 39);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&repeat.repeat(10)).unwrap();
+    let query = VectorGenome::from_slice_u8(&pseudo_random_dna(200, 40)).unwrap();
+    let dense = MatchTable::new(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        4,
+    );
+    let sparse = MatchTableBuilder::new(4)
+        .storage(StorageBackend::Sparse)
+        .build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+    let all: Vec<_> = dense.sample_matches(1.0, 7).collect();
+    let match_count: usize = Quadrant::ALL
+        .into_iter()
+        .map(|quadrant| dense.matches(quadrant).count())
+        .sum();
+    assert_eq!(all.len(), match_count);
+    assert!(match_count > 1000);
+
+    let sample: Vec<_> = dense.sample_matches(0.2, 7).collect();
+    assert_eq!(sparse.sample_matches(0.2, 7).collect::<Vec<_>>(), sample);
+    let expected = match_count as f64 * 0.2;
+    assert!((sample.len() as f64 - expected).abs() < expected * 0.2);
+    assert!(sample.is_sorted());
+
+    let larger: Vec<_> = dense.sample_matches(0.5, 7).collect();
+    assert!(sample.iter().all(|entry| larger.contains(entry)));
+    assert_ne!(dense.sample_matches(0.2, 8).collect::<Vec<_>>(), sample);
+    assert_eq!(dense.sample_matches(0.0, 7).count(), 0);
+}
+
+#[test]
+fn rank_select_index_equals_enumeration() {
+    let reference =
+        VectorGenome::<DnaAlphabet>::from_slice_u8(&pseudo_random_dna(400, 41)).unwrap();
+    let query = VectorGenome::from_slice_u8(&pseudo_random_dna(300, 42)).unwrap();
+
+    for builder in [
+        MatchTableBuilder::new(3),
+        MatchTableBuilder::new(3).storage(StorageBackend::Sparse),
+        MatchTableBuilder::new(3).storage(StorageBackend::Symmetric),
+        MatchTableBuilder::new(3).band(-60, 60),
+    ] {
+        let table = builder.build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+        for quadrant in Quadrant::ALL {
+            let index = table.rank_select_index(quadrant);
+            let matches: Vec<_> = table.matches(quadrant).collect();
+            assert_eq!(index.quadrant(), quadrant);
+            assert_eq!(index.match_count(), matches.len());
+            assert!(matches.len() > 100, "{builder:?} {quadrant}");
+
+            for (n, &(primary_index, secondary_rc_index)) in matches.iter().enumerate() {
+                assert_eq!(
+                    index.nth_match(n),
+                    Some((primary_index, secondary_rc_index))
+                );
+                assert_eq!(index.rank(primary_index, secondary_rc_index), n);
+                assert_eq!(
+                    index.next_match(primary_index, secondary_rc_index + 1),
+                    matches.get(n + 1).copied()
+                );
+            }
+            assert_eq!(index.nth_match(matches.len()), None);
+
+            let primary_kmer_count = table.primary_kmer_count(quadrant);
+            let secondary_kmer_count = table.secondary_kmer_count(quadrant);
+            for primary_index in (0..primary_kmer_count).step_by(7) {
+                for secondary_rc_index in (0..=secondary_kmer_count).step_by(13) {
+                    let rank = matches.partition_point(|&position| {
+                        position < (primary_index, secondary_rc_index)
+                    });
+                    assert_eq!(index.rank(primary_index, secondary_rc_index), rank);
+                    assert_eq!(
+                        index.next_match(primary_index, secondary_rc_index),
+                        matches.get(rank).copied()
+                    );
+                }
+                assert_eq!(
+                    index.match_count_in_primary_range(primary_index..primary_kmer_count),
+                    table
+                        .matches_in_primary_range(quadrant, primary_index..primary_kmer_count)
+                        .count()
+                );
+            }
+            assert_eq!(index.rank(primary_kmer_count, 0), matches.len());
+        }
+    }
+}
+
+#[test]
+fn run_length_table_equals_match_table() {
+    let inner = pseudo_random_dna(60, 45);
+    let mut reference_ascii = pseudo_random_dna(300, 46);
+    reference_ascii[100..160].copy_from_slice(&inner);
+    let mut query_ascii = pseudo_random_dna(250, 47);
+    let inner_rc: Vec<u8> = inner
+        .iter()
+        .rev()
+        .map(|character| match character {
+            b'A' => b'T',
+            b'C' => b'G',
+            b'G' => b'C',
+            b'T' => b'A',
+            _ => unreachable!(),
+        })
+        .collect();
+    query_ascii[20..80].copy_from_slice(&inner_rc);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+    let table = MatchTable::new(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        4,
+    );
+    let runs = table.to_run_length();
+    assert_eq!(runs.minimum_length(), 4);
+
+    for quadrant in Quadrant::ALL {
+        let maximal_matches: Vec<_> = table.maximal_matches(quadrant).collect();
+        assert_eq!(runs.run_count(quadrant), maximal_matches.len());
+        assert_eq!(runs.match_count(quadrant), table.matches(quadrant).count());
+        let mut run_matches: Vec<_> = runs.matches(quadrant).collect();
+        run_matches.sort_unstable();
+        assert_eq!(run_matches, table.matches(quadrant).collect::<Vec<_>>());
+
+        for primary_index in 0..table.primary_kmer_count(quadrant) {
+            for secondary_rc_index in 0..table.secondary_kmer_count(quadrant) {
+                let has_match = table.has_match(quadrant, primary_index, secondary_rc_index);
+                assert_eq!(
+                    runs.has_match(quadrant, primary_index, secondary_rc_index),
+                    has_match
+                );
+                let expected =
+                    maximal_matches
+                        .iter()
+                        .find(|&&(primary_start, secondary_rc_start, length)| {
+                            has_match
+                                && secondary_rc_start as isize - primary_start as isize
+                                    == secondary_rc_index as isize - primary_index as isize
+                                && (primary_start..=primary_start + length - 4)
+                                    .contains(&primary_index)
+                        });
+                assert_eq!(
+                    runs.longest_inner_through(quadrant, primary_index, secondary_rc_index)
+                        .map(|inner| (
+                            inner.primary.start,
+                            inner.secondary_rc.start,
+                            inner.primary.len()
+                        )),
+                    expected.copied()
+                );
+            }
+        }
+    }
+
+    // The planted inner is a single run.
+    let inner = runs
+        .longest_inner_through(Quadrant::ReferenceQuery, 120, 190)
+        .unwrap();
+    assert!(inner.primary.start <= 100 && inner.primary.end >= 160);
+    assert_eq!(
+        inner.secondary_rc.start as isize - inner.primary.start as isize,
+        70
+    );
+    assert!(runs.heap_bytes() > 0);
+}
+
+#[test]
+fn quadtree_counts_equal_naive_counts() {
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&pseudo_random_dna(90, 48)).unwrap();
+    let query = VectorGenome::from_slice_u8(&pseudo_random_dna(70, 49)).unwrap();
+    let table = MatchTable::new(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        3,
+    );
+
+    for quadrant in Quadrant::ALL {
+        let quadtree = table.quadtree(quadrant);
+        let matches: Vec<_> = table.matches(quadrant).collect();
+        assert_eq!(quadtree.match_count(), matches.len());
+        assert_eq!(quadtree.cells(0).count(), matches.len());
+        for level in 0..=quadtree.height() {
+            let total: usize = quadtree.cells(level).map(|(_, _, count)| count).sum();
+            assert_eq!(total, matches.len());
+        }
+        assert_eq!(quadtree.cells(quadtree.height()).count(), 1);
+
+        let bounds = [0, 1, 5, 13, 32, 40, 67, 88, 100];
+        for primary_start in bounds {
+            for primary_end in bounds.into_iter().filter(|&end| end >= primary_start) {
+                for secondary_rc_start in bounds {
+                    for secondary_rc_end in
+                        bounds.into_iter().filter(|&end| end >= secondary_rc_start)
+                    {
+                        let expected = matches
+                            .iter()
+                            .filter(|(primary_index, secondary_rc_index)| {
+                                (primary_start..primary_end).contains(primary_index)
+                                    && (secondary_rc_start..secondary_rc_end)
+                                        .contains(secondary_rc_index)
+                            })
+                            .count();
+                        assert_eq!(
+                            quadtree.match_count_in(
+                                primary_start..primary_end,
+                                secondary_rc_start..secondary_rc_end
+                            ),
+                            expected
+                        );
+                        assert_eq!(
+                            quadtree.has_match_in(
+                                primary_start..primary_end,
+                                secondary_rc_start..secondary_rc_end
+                            ),
+                            expected > 0
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn matrix_exports_contain_all_matches() {
+    use crate::io::matrix::{write_coo, write_matrix_market};
+
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&pseudo_random_dna(80, 50)).unwrap();
+    let query = VectorGenome::from_slice_u8(&pseudo_random_dna(60, 51)).unwrap();
+    let table = MatchTable::new(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        3,
+    );
+
+    for quadrant in Quadrant::ALL {
+        let matches: Vec<_> = table.matches(quadrant).collect();
+
+        let mut output = Vec::new();
+        write_matrix_market(&table, quadrant, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let mut lines = output.lines().filter(|line| !line.starts_with('%'));
+        assert_eq!(
+            lines.next().unwrap(),
+            format!(
+                "{} {} {}",
+                table.primary_kmer_count(quadrant),
+                table.secondary_kmer_count(quadrant),
+                matches.len()
+            )
+        );
+        let entries: Vec<_> = lines
+            .map(|line| {
+                let (row, column) = line.split_once(' ').unwrap();
+                (
+                    row.parse::<usize>().unwrap() - 1,
+                    column.parse::<usize>().unwrap() - 1,
+                )
+            })
+            .collect();
+        assert_eq!(entries, matches);
+
+        let mut output = Vec::new();
+        write_coo(&table, quadrant, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let entries: Vec<_> = output
+            .lines()
+            .skip(1)
+            .map(|line| {
+                let (row, column) = line.split_once('\t').unwrap();
+                (row.parse().unwrap(), column.parse().unwrap())
+            })
+            .collect();
+        assert_eq!(entries, matches);
+    }
+}
+
+#[cfg(feature = "ndarray")]
+#[test]
+fn dense_arrays_equal_matches() {
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&pseudo_random_dna(70, 52)).unwrap();
+    let query = VectorGenome::from_slice_u8(&pseudo_random_dna(50, 53)).unwrap();
+    let table = MatchTable::new(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        3,
+    );
+
+    for quadrant in Quadrant::ALL {
+        let array = table.to_dense_array(quadrant).unwrap();
+        assert_eq!(
+            array.dim(),
+            (
+                table.primary_kmer_count(quadrant),
+                table.secondary_kmer_count(quadrant)
+            )
+        );
+        let ones: Vec<_> = array
+            .indexed_iter()
+            .filter(|&(_, &value)| value == 1)
+            .map(|(position, _)| position)
+            .collect();
+        assert_eq!(ones, table.matches(quadrant).collect::<Vec<_>>());
+
+        let window = table.to_dense_window(quadrant, 10..30, 5..25).unwrap();
+        assert_eq!(window, array.slice(ndarray::s![10..30, 5..25]));
+        assert_eq!(
+            table.to_dense_window(quadrant, 7..7, 0..40).unwrap().dim(),
+            (0, 40)
+        );
+    }
+
+    assert!(matches!(
+        table.to_dense_window(Quadrant::ReferenceQuery, 0..0, 0..0),
+        Ok(array) if array.is_empty()
+    ));
+}
+
+#[test]
+fn dotplot_marks_matches_and_bounds_width() {
+    let reference =
+        VectorGenome::<DnaAlphabet>::from_slice_u8(&pseudo_random_dna(150, 54)).unwrap();
+    let query = VectorGenome::from_slice_u8(&pseudo_random_dna(40, 55)).unwrap();
+    let table = MatchTable::new(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        3,
+    );
+
+    for quadrant in Quadrant::ALL {
+        let full = table.fmt_dotplot(quadrant, 1000).to_string();
+        let rows: Vec<_> = full.lines().skip(1).collect();
+        assert_eq!(rows.len(), table.primary_kmer_count(quadrant));
+        let marked: Vec<_> = rows
+            .iter()
+            .enumerate()
+            .flat_map(|(primary_index, row)| {
+                row.bytes()
+                    .enumerate()
+                    .filter(|&(_, character)| character == b'*')
+                    .map(move |(secondary_rc_index, _)| (primary_index, secondary_rc_index))
+            })
+            .collect();
+        assert_eq!(marked, table.matches(quadrant).collect::<Vec<_>>());
+
+        let bounded = table.fmt_dotplot(quadrant, 20).to_string();
+        let rows: Vec<_> = bounded.lines().skip(1).collect();
+        assert!(rows.len() <= 20);
+        assert!(rows.iter().all(|row| row.len() <= 20));
+        assert!(bounded.contains('*'));
+    }
+
+    let debug = format!("{table:?}");
+    assert!(debug.starts_with("MatchTable { minimum_length: 3,"));
+    assert_eq!(debug.matches("secondary rc kmers").count(), 4);
+}
+
+#[test]
+fn diff_reports_matches_of_one_table_only() {
+    let reference =
+        VectorGenome::<DnaAlphabet>::from_slice_u8(&pseudo_random_dna(200, 56)).unwrap();
+    let query = VectorGenome::from_slice_u8(&pseudo_random_dna(150, 57)).unwrap();
+    let dense = MatchTable::new(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        4,
+    );
+    let sparse = MatchTableBuilder::new(4)
+        .storage(StorageBackend::Sparse)
+        .build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+    let diff = dense.diff(&sparse).unwrap();
+    assert!(diff.is_empty());
+    assert_eq!(diff.difference_count(), 0);
+
+    let minimizers = MatchTableBuilder::new(4).minimizer_window(3).build(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+    );
+    let diff = dense.diff(&minimizers).unwrap();
+    let reverse = minimizers.diff(&dense).unwrap();
+    assert!(!diff.is_empty());
+    for quadrant in Quadrant::ALL {
+        let kept: std::collections::HashSet<_> = minimizers.matches(quadrant).collect();
+        let removed: Vec<_> = dense
+            .matches(quadrant)
+            .filter(|position| !kept.contains(position))
+            .collect();
+        assert_eq!(diff.only_in_self(quadrant), removed);
+        assert!(diff.only_in_other(quadrant).is_empty());
+        assert_eq!(reverse.only_in_other(quadrant), removed);
+        assert!(reverse.only_in_self(quadrant).is_empty());
+    }
+
+    let shorter = VectorGenome::<DnaAlphabet>::from_slice_u8(&pseudo_random_dna(199, 56)).unwrap();
+    let other = MatchTable::new(
+        shorter.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        4,
+    );
+    assert!(matches!(
+        dense.diff(&other),
+        Err(MatchTableError::InvalidDiff(_))
+    ));
+    let other = MatchTable::new(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        5,
+    );
+    assert!(matches!(
+        dense.diff(&other),
+        Err(MatchTableError::InvalidDiff(_))
+    ));
+}
+
+#[test]
+fn cropped_table_equals_table_of_ranges() {
+    let reference_ascii = pseudo_random_dna(200, 58);
+    let query_ascii = pseudo_random_dna(150, 59);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+    let table = MatchTableBuilder::new(3)
+        .reference_excluded_intervals(vec![40..60, 130..135])
+        .build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+
+    let reference_range = 50..140;
+    let query_range = 20..95;
+    let cropped = table
+        .crop(reference_range.clone(), query_range.clone())
+        .unwrap();
+
+    let reference_part =
+        VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii[reference_range]).unwrap();
+    let query_part = VectorGenome::from_slice_u8(&query_ascii[query_range]).unwrap();
+    let expected = MatchTableBuilder::new(3)
+        .reference_excluded_intervals(vec![0..10, 80..85])
+        .build(
+            reference_part.as_genome_subsequence(),
+            query_part.as_genome_subsequence(),
+        );
+    assert_eq!(
+        cropped.reference_kmer_count(),
+        expected.reference_kmer_count()
+    );
+    assert_eq!(cropped.query_kmer_count(), expected.query_kmer_count());
+    assert_eq!(
+        cropped.reference_excluded_intervals(),
+        expected.reference_excluded_intervals()
+    );
+    assert!(cropped.diff(&expected).unwrap().is_empty());
+
+    assert!(matches!(
+        table.crop(10..12, 0..150),
+        Err(MatchTableError::SequenceTooShort {
+            sequence: "reference",
+            length: 2,
+            minimum_length: 3,
+        })
+    ));
+}
+
+#[test]
+fn secondary_major_view_equals_transposed_matches() {
+    let reference =
+        VectorGenome::<DnaAlphabet>::from_slice_u8(&pseudo_random_dna(120, 60)).unwrap();
+    let query = VectorGenome::from_slice_u8(&pseudo_random_dna(90, 61)).unwrap();
+    for storage in [
+        StorageBackend::Dense,
+        StorageBackend::Sparse,
+        StorageBackend::Symmetric,
+    ] {
+        let table = MatchTableBuilder::new(3).storage(storage).build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+        for quadrant in Quadrant::ALL {
+            let view = table.as_secondary_major(quadrant).unwrap();
+            assert_eq!(view.quadrant(), quadrant);
+            let mut expected: Vec<_> = table
+                .matches(quadrant)
+                .map(|(primary_index, secondary_rc_index)| (secondary_rc_index, primary_index))
+                .collect();
+            expected.sort_unstable();
+            assert_eq!(view.matches().collect::<Vec<_>>(), expected);
+
+            for secondary_rc_index in 0..view.secondary_kmer_count() {
+                let column: Vec<_> = view.column_matches(secondary_rc_index).collect();
+                assert_eq!(
+                    column,
+                    table
+                        .column_matches(quadrant, secondary_rc_index)
+                        .collect::<Vec<_>>()
+                );
+                assert_eq!(view.column_match_count(secondary_rc_index), column.len());
+                for primary_index in 0..view.primary_kmer_count() {
+                    assert_eq!(
+                        view.has_match(secondary_rc_index, primary_index),
+                        table.has_match(quadrant, primary_index, secondary_rc_index)
+                    );
+                }
+            }
+            assert_eq!(
+                view.matches_in_secondary_range(10..20).collect::<Vec<_>>(),
+                expected
+                    .iter()
+                    .copied()
+                    .filter(|(secondary_rc_index, _)| (10..20).contains(secondary_rc_index))
+                    .collect::<Vec<_>>()
+            );
+        }
+    }
+
+    let banded = MatchTableBuilder::new(3).band(-10, 10).build(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+    );
+    assert!(
+        banded
+            .as_secondary_major(Quadrant::ReferenceReference)
+            .is_none()
+    );
+}
+
+#[test]
+fn detect_template_switches_finds_planted_switch() {
+    let reference_ascii = pseudo_random_dna(400, 62);
+    let mut query_ascii = reference_ascii.clone();
+    let template_rc: Vec<u8> = reference_ascii[210..240]
+        .iter()
+        .rev()
+        .map(|character| match character {
+            b'A' => b'T',
+            b'C' => b'G',
+            b'G' => b'C',
+            b'T' => b'A',
+            _ => unreachable!(),
+        })
+        .collect();
+    query_ascii[200..230].copy_from_slice(&template_rc);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+
+    let parameters =
+        DetectionParameters::new(MatchTableBuilder::new(8).quadrants(Quadrants::QUERY_REFERENCE))
+            .constraints(
+                CandidateConstraints::new()
+                    .min_inner_length(20)
+                    .switch_distance(0, 100),
+            );
+    let events = detect_template_switches(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        &parameters,
+    )
+    .unwrap();
+    assert_eq!(events.len(), 1);
+    let candidate = events[0].candidate;
+    assert_eq!(candidate.quadrant, Quadrant::QueryReference);
+    assert!(candidate.point1 <= 200 && candidate.point4 >= 230);
+    assert_eq!(candidate.point1 + candidate.point2, 440);
+    assert_eq!(candidate.point3 + candidate.point4, 440);
+    assert_eq!(events[0].inner.primary, candidate.point1..candidate.point4);
+
+    // A mismatch splits the inner into two error-free inners that are too short, but chaining bridges it.
+    query_ascii[215] = if query_ascii[215] == b'A' { b'C' } else { b'A' };
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+    let events = detect_template_switches(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        &parameters,
+    )
+    .unwrap();
+    assert!(events.is_empty());
+    let events = detect_template_switches(
+        reference.as_genome_subsequence(),
+        query.as_genome_subsequence(),
+        &parameters.chaining(
+            ChainingParameters::new()
+                .max_diagonal_shift(0)
+                .min_score(20),
+        ),
+    )
+    .unwrap();
+    assert_eq!(events.len(), 1);
+    assert!(events[0].candidate.point1 <= 200 && events[0].candidate.point4 >= 230);
+}
+
+#[test]
+fn from_ascii_equals_encoded_construction() {
+    let reference = b"ACGTTGCAAGGCTTAGCCGATAAGCTTGCAGCTA";
+    let query = "TTGCAAGCTTATCGGCTAAGCCTTGCAACGTA";
+    let encoded = MatchTable::new(
+        VectorGenome::<DnaAlphabet>::from_slice_u8(reference)
+            .unwrap()
+            .as_genome_subsequence(),
+        VectorGenome::from_slice_u8(query.as_bytes())
+            .unwrap()
+            .as_genome_subsequence(),
+        4,
+    );
+    let from_ascii = MatchTable::from_ascii(reference, query, 4).unwrap();
+    assert!(encoded.diff(&from_ascii).unwrap().is_empty());
+
+    let error = MatchTable::from_ascii("ACGTN", query, 4).unwrap_err();
+    assert!(matches!(
+        error,
+        MatchTableError::InvalidCharacter {
+            sequence: "reference",
+            position: 4,
+            character: b'N',
+        }
+    ));
+    assert_eq!(
+        error.to_string(),
+        "The reference contains the invalid character 'N' at position 4, expected one of 'ACGT'"
+    );
+    assert!(matches!(
+        MatchTable::from_ascii("ACG", query, 4),
+        Err(MatchTableError::SequenceTooShort { .. })
+    ));
+}
+
+#[test]
+fn custom_indexes_equal_built_indexes() {
+    let reference_ascii = pseudo_random_dna(2_000, 15);
+    let query_ascii = pseudo_random_dna(1_500, 16);
+    let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(&reference_ascii).unwrap();
+    let query = VectorGenome::from_slice_u8(&query_ascii).unwrap();
+    let reference_index = SuffixTable::new(std::str::from_utf8(&reference_ascii).unwrap());
+    let query_index = SuffixTable::new(std::str::from_utf8(&query_ascii).unwrap());
+
+    for builder in [
+        MatchTableBuilder::new(5),
+        MatchTableBuilder::new(8).max_mismatches(1),
+        MatchTableBuilder::new(6).band(-100, 100),
+        MatchTableBuilder::new(5).quadrants(Quadrants::REFERENCE_QUERY),
+        MatchTableBuilder::new(5).strategy(ConstructionStrategy::HashJoin),
+    ] {
+        let expected = builder.build(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+        );
+        let actual = builder.build_with_indexes(
+            reference.as_genome_subsequence(),
+            query.as_genome_subsequence(),
+            &reference_index,
+            &query_index,
+        );
+        assert!(expected.diff(&actual).unwrap().is_empty(), "{builder:?}");
+    }
+}
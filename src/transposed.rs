@@ -0,0 +1,158 @@
+//! Column-major views of quadrants, backed by the mirrored rows of their transposed quadrants.
+
+use std::ops::Range;
+
+use crate::{MatchTable, Quadrant, StorageIndex, storage::QuadrantStorage};
+
+/// A view of a quadrant in which the secondary rc index is the major index, see [`MatchTable::as_secondary_major`].
+///
+/// A match `(primary_index, secondary_rc_index)` of the quadrant corresponds to the match
+/// `(secondary_kmer_count - 1 - secondary_rc_index, primary_kmer_count - 1 - primary_index)` of its [transposed](Quadrant::transposed) quadrant.
+/// So a column of the quadrant is a row of the transposed quadrant in reverse,
+/// and the view answers column queries from the row-major storage of the transposed quadrant without copying it.
+pub struct SecondaryMajorView<'table, Index: StorageIndex = u32> {
+    quadrant: Quadrant,
+    transposed: &'table QuadrantStorage<Index>,
+    primary_kmer_count: usize,
+    secondary_kmer_count: usize,
+}
+
+impl<'table, Index: StorageIndex> SecondaryMajorView<'table, Index> {
+    /// Returns the quadrant of this view.
+    pub fn quadrant(&self) -> Quadrant {
+        self.quadrant
+    }
+
+    /// Returns the number of primary kmers of the quadrant, i.e. the length of each column.
+    pub fn primary_kmer_count(&self) -> usize {
+        self.primary_kmer_count
+    }
+
+    /// Returns the number of secondary kmers of the quadrant, i.e. the number of columns.
+    pub fn secondary_kmer_count(&self) -> usize {
+        self.secondary_kmer_count
+    }
+
+    /// Returns `true` if the primary kmer at `primary_index` matches the reverse-complemented secondary kmer at `secondary_rc_index`.
+    pub fn has_match(&self, secondary_rc_index: usize, primary_index: usize) -> bool {
+        debug_assert!(primary_index < self.primary_kmer_count);
+        debug_assert!(secondary_rc_index < self.secondary_kmer_count);
+        self.transposed.has_match(
+            self.transposed_row(secondary_rc_index),
+            self.primary_kmer_count - 1 - primary_index,
+        )
+    }
+
+    /// Returns an iterator over the primary indices that match the reverse-complemented secondary kmer at `secondary_rc_index`.
+    ///
+    /// The indices are returned in increasing order, like those of [`MatchTable::column_matches`],
+    /// and the iteration takes time linear in the size of the row of the transposed quadrant in the underlying storage.
+    pub fn column_matches(
+        &self,
+        secondary_rc_index: usize,
+    ) -> impl DoubleEndedIterator<Item = usize> + use<'table, Index> {
+        debug_assert!(secondary_rc_index < self.secondary_kmer_count);
+        let primary_kmer_count = self.primary_kmer_count;
+        self.transposed
+            .row_iter(self.transposed_row(secondary_rc_index))
+            .rev()
+            .map(move |transposed_index| primary_kmer_count - 1 - transposed_index)
+    }
+
+    /// Returns the number of primary indices that match the reverse-complemented secondary kmer at `secondary_rc_index`,
+    /// in constant time like [`MatchTable::match_count`].
+    pub fn column_match_count(&self, secondary_rc_index: usize) -> usize {
+        debug_assert!(secondary_rc_index < self.secondary_kmer_count);
+        self.transposed
+            .row_match_count(self.transposed_row(secondary_rc_index))
+    }
+
+    /// Returns an iterator over the matches whose secondary rc index lies in the given range,
+    /// as `(secondary_rc_index, primary_index)` pairs ordered by secondary rc index and then by primary index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start of the range is greater than its end, or its end is greater than the number of secondary kmers of the quadrant.
+    pub fn matches_in_secondary_range(
+        &self,
+        secondary_rc_indices: Range<usize>,
+    ) -> impl Iterator<Item = (usize, usize)> + use<'_, 'table, Index> {
+        assert!(
+            secondary_rc_indices.start <= secondary_rc_indices.end
+                && secondary_rc_indices.end <= self.secondary_kmer_count,
+            "The secondary rc range {}..{} does not lie within the {} secondary kmers of quadrant {}",
+            secondary_rc_indices.start,
+            secondary_rc_indices.end,
+            self.secondary_kmer_count,
+            self.quadrant,
+        );
+        secondary_rc_indices.flat_map(move |secondary_rc_index| {
+            self.column_matches(secondary_rc_index)
+                .map(move |primary_index| (secondary_rc_index, primary_index))
+        })
+    }
+
+    /// Returns an iterator over all matches of the quadrant,
+    /// as `(secondary_rc_index, primary_index)` pairs ordered by secondary rc index and then by primary index.
+    pub fn matches(&self) -> impl Iterator<Item = (usize, usize)> + use<'_, 'table, Index> {
+        self.matches_in_secondary_range(0..self.secondary_kmer_count)
+    }
+
+    /// Returns the row of the transposed quadrant that mirrors the given column.
+    fn transposed_row(&self, secondary_rc_index: usize) -> usize {
+        self.secondary_kmer_count - 1 - secondary_rc_index
+    }
+}
+
+impl<Index: StorageIndex> MatchTable<Index> {
+    /// Returns a view of the given quadrant in which the secondary rc index is the major index,
+    /// for algorithms that iterate over the matches by secondary kmer.
+    ///
+    /// The view reads the [transposed](Quadrant::transposed) quadrant, whose rows are the columns of the quadrant in reverse,
+    /// so it requires no memory, and iterating a column is as fast as iterating a row.
+    /// Returns `None` if the quadrant or its transposed quadrant was not [computed](Self::quadrants),
+    /// or if the table has a band, which is not symmetric under transposition if the genomes differ in length.
+    /// Then, [`column_matches`](Self::column_matches) scans the columns instead.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTable, MatchTableBuilder, Quadrant, Quadrants};
+    ///
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(b"AGGGGAACCCCAA").unwrap();
+    /// let query = VectorGenome::from_slice_u8(b"TTGGGGTT").unwrap();
+    /// let matches = MatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    ///
+    /// let view = matches.as_secondary_major(Quadrant::ReferenceQuery).unwrap();
+    /// assert_eq!(view.column_matches(2).collect::<Vec<_>>(), vec![7]);
+    /// assert_eq!(view.column_match_count(2), 1);
+    /// assert!(view.has_match(2, 7));
+    /// assert_eq!(
+    ///     view.matches().collect::<Vec<_>>(),
+    ///     matches.matches(Quadrant::ReferenceQuery).map(|(i, j)| (j, i)).collect::<Vec<_>>(),
+    /// );
+    ///
+    /// let one_quadrant = MatchTableBuilder::new(4)
+    ///     .quadrants(Quadrants::REFERENCE_QUERY)
+    ///     .build(reference.as_genome_subsequence(), query.as_genome_subsequence());
+    /// assert!(one_quadrant.as_secondary_major(Quadrant::ReferenceQuery).is_none());
+    /// ```
+    pub fn as_secondary_major(&self, quadrant: Quadrant) -> Option<SecondaryMajorView<'_, Index>> {
+        (self.quadrants.contains(quadrant)
+            && self.quadrants.contains(quadrant.transposed())
+            && self.band.is_none())
+        .then(|| SecondaryMajorView {
+            quadrant,
+            transposed: self.quadrant(quadrant.transposed()),
+            primary_kmer_count: self.primary_kmer_count(quadrant),
+            secondary_kmer_count: self.secondary_kmer_count(quadrant),
+        })
+    }
+}
@@ -0,0 +1,128 @@
+//! Thin `wasm-bindgen` wrappers for computing match tables in the browser.
+//!
+//! The wrappers are exported to JavaScript as the class `MatchTable` and the enum `Quadrant`.
+//! Sequences are passed as strings over the DNA alphabet `ACGT`.
+//! Matches are returned as flat arrays to avoid allocating one JavaScript object per match.
+//! To use them, build a `cdylib` crate that depends on this crate with the `wasm` feature, e.g. with `wasm-pack`.
+
+use compact_genome::{
+    implementation::{alphabets::dna_alphabet::DnaAlphabet, vec_sequence::VectorGenome},
+    interface::sequence::{GenomeSequence, OwnedGenomeSequence},
+};
+use wasm_bindgen::prelude::*;
+
+use crate::{MatchTable, MatchTableBuilder, Quadrant};
+
+/// A [`MatchTable`] exported to JavaScript.
+#[wasm_bindgen(js_name = MatchTable)]
+pub struct WasmMatchTable {
+    table: MatchTable,
+}
+
+#[wasm_bindgen(js_class = MatchTable)]
+impl WasmMatchTable {
+    /// Compute the match table of the given reference and query with the given minimum length,
+    /// allowing up to `max_mismatches` mismatches, or none if undefined.
+    ///
+    /// Throws an error if a sequence contains characters other than `ACGT` or the table cannot be built.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        reference: &str,
+        query: &str,
+        minimum_length: usize,
+        max_mismatches: Option<usize>,
+    ) -> Result<WasmMatchTable, JsError> {
+        let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(reference.as_bytes())?;
+        let query = VectorGenome::<DnaAlphabet>::from_slice_u8(query.as_bytes())?;
+        let table = MatchTableBuilder::new(minimum_length)
+            .max_mismatches(max_mismatches.unwrap_or(0))
+            .try_build(
+                reference.as_genome_subsequence(),
+                query.as_genome_subsequence(),
+            )?;
+        Ok(Self { table })
+    }
+
+    /// Returns the minimum length of the inners, i.e. the length of the kmers.
+    #[wasm_bindgen(getter, js_name = minimumLength)]
+    pub fn minimum_length(&self) -> usize {
+        self.table.minimum_length()
+    }
+
+    /// Returns the number of valid primary indices in the given quadrant.
+    #[wasm_bindgen(js_name = primaryKmerCount)]
+    pub fn primary_kmer_count(&self, quadrant: Quadrant) -> usize {
+        self.table.primary_kmer_count(quadrant)
+    }
+
+    /// Returns the number of valid secondary rc indices in the given quadrant.
+    #[wasm_bindgen(js_name = secondaryKmerCount)]
+    pub fn secondary_kmer_count(&self, quadrant: Quadrant) -> usize {
+        self.table.secondary_kmer_count(quadrant)
+    }
+
+    /// Returns `true` if the primary kmer at `primary_index` matches the reverse-complemented secondary kmer at `secondary_rc_index` in the given quadrant.
+    ///
+    /// See [`MatchTable::has_match`].
+    #[wasm_bindgen(js_name = hasMatch)]
+    pub fn has_match(
+        &self,
+        quadrant: Quadrant,
+        primary_index: usize,
+        secondary_rc_index: usize,
+    ) -> bool {
+        primary_index < self.table.primary_kmer_count(quadrant)
+            && secondary_rc_index < self.table.secondary_kmer_count(quadrant)
+            && self
+                .table
+                .has_match(quadrant, primary_index, secondary_rc_index)
+    }
+
+    /// Returns the secondary rc indices that match the primary kmer at `primary_index` in the given quadrant in increasing order.
+    ///
+    /// See [`MatchTable::row_matches`].
+    #[wasm_bindgen(js_name = rowMatches)]
+    pub fn row_matches(&self, quadrant: Quadrant, primary_index: usize) -> Vec<usize> {
+        if primary_index < self.table.primary_kmer_count(quadrant) {
+            self.table.row_matches(quadrant, primary_index).collect()
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Returns the matches of the given quadrant as a flat array `[primary_index, secondary_rc_index, ...]`.
+    ///
+    /// See [`MatchTable::matches`].
+    pub fn matches(&self, quadrant: Quadrant) -> Vec<usize> {
+        self.table
+            .matches(quadrant)
+            .flat_map(|(primary_index, secondary_rc_index)| [primary_index, secondary_rc_index])
+            .collect()
+    }
+
+    /// Returns the matches of the given quadrant within the primary range `primary_start..primary_end`
+    /// as a flat array `[primary_index, secondary_rc_index, ...]`, for rendering a region of a dotplot.
+    #[wasm_bindgen(js_name = matchesInRange)]
+    pub fn matches_in_range(
+        &self,
+        quadrant: Quadrant,
+        primary_start: usize,
+        primary_end: usize,
+    ) -> Vec<usize> {
+        let primary_end = primary_end.min(self.table.primary_kmer_count(quadrant));
+        (primary_start..primary_end)
+            .flat_map(|primary_index| {
+                self.table
+                    .row_matches(quadrant, primary_index)
+                    .flat_map(move |secondary_rc_index| [primary_index, secondary_rc_index])
+            })
+            .collect()
+    }
+}
+
+impl WasmMatchTable {
+    /// Returns the wrapped table.
+    pub fn table(&self) -> &MatchTable {
+        &self.table
+    }
+}
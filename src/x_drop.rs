@@ -0,0 +1,482 @@
+//! X-drop extension of matches into inners that may contain mismatches and short indels.
+
+use std::{cmp::Reverse, ops::Range};
+
+use crate::{Inner, MatchTable, Quadrant, StorageIndex};
+
+/// Parameters of the x-drop extension by [`MatchTable::extend_x_drop`].
+///
+/// # Example
+///
+/// ```rust
+/// use template_switch_error_free_inners::XDropParameters;
+///
+/// let parameters = XDropParameters::new()
+///     .match_score(1)
+///     .mismatch_penalty(3)
+///     .x_drop(15)
+///     .max_mismatches(4)
+///     .max_indels(2)
+///     .gap_penalty(4);
+/// ```
+#[derive(Debug, Clone)]
+pub struct XDropParameters {
+    pub(crate) match_score: usize,
+    pub(crate) mismatch_penalty: usize,
+    pub(crate) x_drop: usize,
+    pub(crate) max_mismatches: Option<usize>,
+    pub(crate) max_indels: usize,
+    pub(crate) gap_penalty: usize,
+}
+
+impl Default for XDropParameters {
+    fn default() -> Self {
+        Self {
+            match_score: 1,
+            mismatch_penalty: 2,
+            x_drop: 10,
+            max_mismatches: None,
+            max_indels: 0,
+            gap_penalty: 3,
+        }
+    }
+}
+
+impl XDropParameters {
+    /// Create the default parameters, which score 1 per matching and -2 per mismatching character,
+    /// stop the extension when the score drops 10 below its maximum, allow any number of mismatches, and allow no indels.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the score of a pair of complementary characters.
+    pub fn match_score(mut self, match_score: usize) -> Self {
+        self.match_score = match_score;
+        self
+    }
+
+    /// Set the penalty of a pair of characters that are not complementary.
+    pub fn mismatch_penalty(mut self, mismatch_penalty: usize) -> Self {
+        self.mismatch_penalty = mismatch_penalty;
+        self
+    }
+
+    /// Set by how much the score may drop below the best score of the extension before the extension stops.
+    pub fn x_drop(mut self, x_drop: usize) -> Self {
+        self.x_drop = x_drop;
+        self
+    }
+
+    /// Set the maximum number of mismatches of an extended inner, including those within the seed kmers.
+    ///
+    /// The extension in each direction stops before the mismatch that would exceed it.
+    pub fn max_mismatches(mut self, max_mismatches: usize) -> Self {
+        self.max_mismatches = Some(max_mismatches);
+        self
+    }
+
+    /// Set the maximum number of inserted or deleted characters of an extended inner.
+    ///
+    /// With indels, the extension is a dynamic program in a band of this many diagonals around the diagonal of the seed,
+    /// so an inner interrupted by a short indel is extended as a whole rather than reported as two fragments.
+    /// With zero, the extension is ungapped.
+    pub fn max_indels(mut self, max_indels: usize) -> Self {
+        self.max_indels = max_indels;
+        self
+    }
+
+    /// Set the penalty of each inserted or deleted character.
+    pub fn gap_penalty(mut self, gap_penalty: usize) -> Self {
+        self.gap_penalty = gap_penalty;
+        self
+    }
+}
+
+/// An inner obtained by extending a match, see [`MatchTable::extend_x_drop`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct XDropExtension {
+    /// The quadrant of the extended match.
+    pub quadrant: Quadrant,
+    /// The interval of the inner on the forward strand of the primary.
+    pub primary: Range<usize>,
+    /// The interval of the template of the inner on the reverse complement of the secondary.
+    ///
+    /// The forward-strand positions can be obtained with [`MatchTable::secondary_forward_end`].
+    pub secondary_rc: Range<usize>,
+    /// The number of characters of the inner that are not complementary to their counterparts in the secondary.
+    pub mismatch_count: usize,
+    /// The number of characters of the inner or its template that are inserted or deleted,
+    /// which is zero unless [indels](XDropParameters::max_indels) are allowed.
+    pub indel_count: usize,
+    /// The score of the inner under the [parameters](XDropParameters) of the extension.
+    pub score: isize,
+}
+
+impl From<&XDropExtension> for Inner {
+    fn from(extension: &XDropExtension) -> Self {
+        Self {
+            quadrant: extension.quadrant,
+            primary: extension.primary.clone(),
+            secondary_rc: extension.secondary_rc.clone(),
+        }
+    }
+}
+
+impl<Index: StorageIndex> MatchTable<Index> {
+    /// Extend the match `(primary_index, secondary_rc_index)` of the given quadrant in both directions,
+    /// allowing mismatches and, if [enabled](XDropParameters::max_indels), indels under the x-drop criterion.
+    ///
+    /// The reference and the query are given as ASCII characters, like those returned by [`GenomeSequence::clone_as_vec`](compact_genome::interface::sequence::GenomeSequence::clone_as_vec).
+    /// Starting from the score of the seed kmers, each direction is extended one character at a time,
+    /// adding the [match score](XDropParameters::match_score) for complementary characters and subtracting the [mismatch penalty](XDropParameters::mismatch_penalty) otherwise,
+    /// until the score drops more than [`x_drop`](XDropParameters::x_drop) below the best score of that direction,
+    /// a sequence ends, or the [maximum number of mismatches](XDropParameters::max_mismatches) would be exceeded.
+    /// Each direction is then trimmed back to its best score.
+    /// With indels, each direction is a banded dynamic program that also subtracts the [gap penalty](XDropParameters::gap_penalty) per indel,
+    /// in which alignments whose score dropped too far or that exceed the maximum number of mismatches or indels are discarded,
+    /// so the primary and the secondary rc interval of the extension may differ in length.
+    /// Characters other than `ACGT` never match, and the extension does not stop at the borders of contigs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the reference or the query has a different length than the sequence the table was computed from,
+    /// or if the indices lie outside of the quadrant.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use compact_genome::interface::sequence::{GenomeSequence, OwnedGenomeSequence};
+    /// use compact_genome::implementation::vec_sequence::VectorGenome;
+    /// use compact_genome::implementation::alphabets::dna_alphabet::DnaAlphabet;
+    /// use template_switch_error_free_inners::{MatchTable, Quadrant, XDropParameters};
+    ///
+    /// // The query contains the reverse complement of the reference with a mismatch in the middle.
+    /// let reference_ascii = b"ACGTTGCAAGTCCTGA";
+    /// let query_ascii = b"TCAGGACTAGCAACGT";
+    /// let reference = VectorGenome::<DnaAlphabet>::from_slice_u8(reference_ascii).unwrap();
+    /// let query = VectorGenome::from_slice_u8(query_ascii).unwrap();
+    /// let matches = MatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    /// assert!(matches.has_reference_query_match(0, 0));
+    ///
+    /// let extension = matches.extend_x_drop(
+    ///     Quadrant::ReferenceQuery,
+    ///     0,
+    ///     0,
+    ///     reference_ascii,
+    ///     query_ascii,
+    ///     &XDropParameters::new(),
+    /// );
+    /// assert_eq!(extension.primary, 0..16);
+    /// assert_eq!(extension.secondary_rc, 0..16);
+    /// assert_eq!(extension.mismatch_count, 1);
+    /// assert_eq!(extension.score, 13);
+    ///
+    /// // Without mismatches, the extension stops before the mismatch.
+    /// let extension = matches.extend_x_drop(
+    ///     Quadrant::ReferenceQuery,
+    ///     0,
+    ///     0,
+    ///     reference_ascii,
+    ///     query_ascii,
+    ///     &XDropParameters::new().max_mismatches(0),
+    /// );
+    /// assert_eq!(extension.primary, 0..7);
+    /// assert_eq!(extension.mismatch_count, 0);
+    ///
+    /// // The second query lacks the `A` at position 8 of the reference.
+    /// let query_ascii = b"TCAGGACTGCAACGT";
+    /// let query = VectorGenome::from_slice_u8(query_ascii).unwrap();
+    /// let matches = MatchTable::new(
+    ///     reference.as_genome_subsequence(),
+    ///     query.as_genome_subsequence(),
+    ///     4,
+    /// );
+    /// let extension = matches.extend_x_drop(
+    ///     Quadrant::ReferenceQuery,
+    ///     0,
+    ///     0,
+    ///     reference_ascii,
+    ///     query_ascii,
+    ///     &XDropParameters::new().max_indels(1),
+    /// );
+    /// assert_eq!(extension.primary, 0..16);
+    /// assert_eq!(extension.secondary_rc, 0..15);
+    /// assert_eq!(extension.indel_count, 1);
+    /// assert_eq!(extension.score, 12);
+    /// ```
+    pub fn extend_x_drop(
+        &self,
+        quadrant: Quadrant,
+        primary_index: usize,
+        secondary_rc_index: usize,
+        reference: &[u8],
+        query: &[u8],
+        parameters: &XDropParameters,
+    ) -> XDropExtension {
+        let (primary, secondary) = self.x_drop_sequences(quadrant, reference, query);
+        assert!(
+            primary_index < self.primary_kmer_count(quadrant)
+                && secondary_rc_index < self.secondary_kmer_count(quadrant),
+            "The match ({primary_index}, {secondary_rc_index}) does not lie within quadrant {quadrant}"
+        );
+        let minimum_length = self.minimum_length;
+        // Whether the primary character at the given position is complementary to the secondary character at the given rc position.
+        let matches = |primary_position: usize, secondary_rc_position: usize| {
+            is_complementary(
+                primary[primary_position],
+                secondary[secondary.len() - 1 - secondary_rc_position],
+            )
+        };
+        let character_score = |is_match| {
+            if is_match {
+                parameters.match_score as isize
+            } else {
+                -(parameters.mismatch_penalty as isize)
+            }
+        };
+
+        let seed_mismatch_count = (0..minimum_length)
+            .filter(|&offset| !matches(primary_index + offset, secondary_rc_index + offset))
+            .count();
+        let seed_score = (0..minimum_length)
+            .map(|offset| {
+                character_score(matches(primary_index + offset, secondary_rc_index + offset))
+            })
+            .sum::<isize>();
+        let max_mismatches = parameters
+            .max_mismatches
+            .map_or(usize::MAX, |max_mismatches| {
+                max_mismatches.saturating_sub(seed_mismatch_count)
+            });
+
+        let right = extend_direction(
+            primary.len() - primary_index - minimum_length,
+            secondary.len() - secondary_rc_index - minimum_length,
+            |primary_offset, secondary_offset| {
+                matches(
+                    primary_index + minimum_length + primary_offset,
+                    secondary_rc_index + minimum_length + secondary_offset,
+                )
+            },
+            max_mismatches,
+            parameters.max_indels,
+            parameters,
+        );
+        let left = extend_direction(
+            primary_index,
+            secondary_rc_index,
+            |primary_offset, secondary_offset| {
+                matches(
+                    primary_index - 1 - primary_offset,
+                    secondary_rc_index - 1 - secondary_offset,
+                )
+            },
+            max_mismatches - right.mismatch_count,
+            parameters.max_indels - right.indel_count,
+            parameters,
+        );
+
+        XDropExtension {
+            quadrant,
+            primary: primary_index - left.primary_length
+                ..primary_index + minimum_length + right.primary_length,
+            secondary_rc: secondary_rc_index - left.secondary_length
+                ..secondary_rc_index + minimum_length + right.secondary_length,
+            mismatch_count: seed_mismatch_count + left.mismatch_count + right.mismatch_count,
+            indel_count: left.indel_count + right.indel_count,
+            score: seed_score + left.score + right.score,
+        }
+    }
+
+    /// Extend the first match of each [run](Self::runs) of the given quadrant with [`extend_x_drop`](Self::extend_x_drop),
+    /// and return the distinct extensions ordered by decreasing score.
+    ///
+    /// Runs that are separated by few mismatches or, if enabled, indels usually extend into the same inner, which is reported only once.
+    /// Extensions with equal scores are ordered by their start in the primary and the secondary.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the reference or the query has a different length than the sequence the table was computed from.
+    pub fn x_drop_extensions(
+        &self,
+        quadrant: Quadrant,
+        reference: &[u8],
+        query: &[u8],
+        parameters: &XDropParameters,
+    ) -> Vec<XDropExtension> {
+        let mut extensions: Vec<_> = self
+            .runs(quadrant)
+            .map(|(primary_start, secondary_rc_start, _)| {
+                self.extend_x_drop(
+                    quadrant,
+                    primary_start,
+                    secondary_rc_start,
+                    reference,
+                    query,
+                    parameters,
+                )
+            })
+            .collect();
+        extensions.sort_by_key(|extension| {
+            (
+                Reverse(extension.score),
+                extension.primary.start,
+                extension.secondary_rc.start,
+                extension.primary.end,
+            )
+        });
+        extensions.dedup();
+        extensions
+    }
+
+    /// Returns the primary and the secondary of the given quadrant, after checking their lengths against the table.
+    fn x_drop_sequences<'sequence>(
+        &self,
+        quadrant: Quadrant,
+        reference: &'sequence [u8],
+        query: &'sequence [u8],
+    ) -> (&'sequence [u8], &'sequence [u8]) {
+        assert_eq!(
+            reference.len(),
+            self.reference_contigs.len(),
+            "The reference has a different length than the reference of the table"
+        );
+        assert_eq!(
+            query.len(),
+            self.query_contigs.len(),
+            "The query has a different length than the query of the table"
+        );
+        let sequence = |is_reference| if is_reference { reference } else { query };
+        (
+            sequence(quadrant.primary_is_reference()),
+            sequence(quadrant.secondary_is_reference()),
+        )
+    }
+}
+
+/// The trimmed extension in one direction, or an alignment of a cell of the dynamic program.
+#[derive(Clone, Copy)]
+struct DirectionalExtension {
+    primary_length: usize,
+    secondary_length: usize,
+    mismatch_count: usize,
+    indel_count: usize,
+    score: isize,
+}
+
+impl DirectionalExtension {
+    /// Returns the alignment extended by a pair of characters, or by a character of only one sequence if `is_match` is `None`.
+    fn extend(
+        self,
+        primary_step: usize,
+        secondary_step: usize,
+        is_match: Option<bool>,
+        parameters: &XDropParameters,
+    ) -> Self {
+        let (mismatch_count, indel_count, score) = match is_match {
+            Some(true) => (0, 0, parameters.match_score as isize),
+            Some(false) => (1, 0, -(parameters.mismatch_penalty as isize)),
+            None => (0, 1, -(parameters.gap_penalty as isize)),
+        };
+        Self {
+            primary_length: self.primary_length + primary_step,
+            secondary_length: self.secondary_length + secondary_step,
+            mismatch_count: self.mismatch_count + mismatch_count,
+            indel_count: self.indel_count + indel_count,
+            score: self.score + score,
+        }
+    }
+}
+
+/// Extend in one direction over at most `primary_length` characters of the primary and `secondary_length` characters of the secondary,
+/// where `matches(primary_offset, secondary_offset)` tells if the characters at the offsets are complementary.
+///
+/// The alignments are computed row by row of the primary, within the band of `max_indels` diagonals around the main diagonal.
+/// Each cell keeps its best-scoring alignment, preferring mismatches over indels on ties,
+/// and discards it if it exceeds the maximum numbers of mismatches or indels, or drops more than the x-drop below the best score.
+/// The extension stops when a row has no alignment left.
+fn extend_direction(
+    primary_length: usize,
+    secondary_length: usize,
+    matches: impl Fn(usize, usize) -> bool,
+    max_mismatches: usize,
+    max_indels: usize,
+    parameters: &XDropParameters,
+) -> DirectionalExtension {
+    let empty = DirectionalExtension {
+        primary_length: 0,
+        secondary_length: 0,
+        mismatch_count: 0,
+        indel_count: 0,
+        score: 0,
+    };
+    let mut best = empty;
+    let is_valid = |alignment: &DirectionalExtension, best: &DirectionalExtension| {
+        alignment.mismatch_count <= max_mismatches
+            && alignment.indel_count <= max_indels
+            && best.score - alignment.score <= parameters.x_drop as isize
+    };
+
+    // The cells of a row are indexed by `secondary_offset + max_indels - primary_offset`.
+    let width = 2 * max_indels + 1;
+    let mut previous_row: Vec<Option<DirectionalExtension>> = vec![None; width];
+    for primary_offset in 0..=primary_length {
+        let mut row = vec![None; width];
+        for column in 0..width {
+            let Some(secondary_offset) = (primary_offset + column).checked_sub(max_indels) else {
+                continue;
+            };
+            if secondary_offset > secondary_length {
+                break;
+            }
+            if primary_offset == 0 && secondary_offset == 0 {
+                row[column] = Some(empty);
+                continue;
+            }
+
+            let diagonal = (primary_offset > 0 && secondary_offset > 0)
+                .then(|| previous_row[column])
+                .flatten()
+                .map(|alignment| {
+                    let is_match = matches(primary_offset - 1, secondary_offset - 1);
+                    alignment.extend(1, 1, Some(is_match), parameters)
+                });
+            let deletion = (primary_offset > 0 && column + 1 < width)
+                .then(|| previous_row[column + 1])
+                .flatten()
+                .map(|alignment| alignment.extend(1, 0, None, parameters));
+            let insertion = (column > 0)
+                .then(|| row[column - 1])
+                .flatten()
+                .map(|alignment| alignment.extend(0, 1, None, parameters));
+            row[column] = [diagonal, deletion, insertion]
+                .into_iter()
+                .flatten()
+                .filter(|alignment| is_valid(alignment, &best))
+                .reduce(|a, b| if b.score > a.score { b } else { a });
+
+            if let Some(alignment) = row[column] {
+                if alignment.score > best.score {
+                    best = alignment;
+                }
+            }
+        }
+        if row.iter().all(Option::is_none) {
+            break;
+        }
+        previous_row = row;
+    }
+    best
+}
+
+/// Returns `true` if the given ASCII characters are complementary nucleotides.
+fn is_complementary(a: u8, b: u8) -> bool {
+    matches!(
+        (a.to_ascii_uppercase(), b.to_ascii_uppercase()),
+        (b'A', b'T') | (b'C', b'G') | (b'G', b'C') | (b'T', b'A')
+    )
+}